@@ -1,22 +1,136 @@
+use anyhow::Context;
 use clap::Parser;
 use flume;
-use gui::core::gui::RacePlot;
-use racesim::post::race_result::RaceResult;
-use racesim::pre::read_sim_pars::{read_sim_pars_flexible, read_sim_constants, read_tire_config};
-use racesim::pre::sim_opts::SimOpts;
-use std::path::PathBuf;
+use gui::core::gui::{RacePlot, RacePlotOptions};
+use gui::core::render::{load_render_track, render_race_gif};
+use gui::core::strategy_editor::StrategyEditor;
+use racesim::core::practice::{handle_practice, run_practice};
+use racesim::core::race::{EventObserver, SimConstants};
+use racesim::core::season::{read_season_pars, run_season};
+use racesim::core::track::{get_trackfile_path, Track};
+use racesim::core::whatif::{parse_whatif_change, read_checkpoint, run_whatif, Checkpoint};
+use racesim::interfaces::gui_interface::{check_gui_update_frequency, GuiSender};
+use racesim::post::aggregate::{format_h2h_table, head_to_head, write_h2h_to_csv};
+use racesim::post::event_stream::{events_log_observer, jsonl_observer};
+use racesim::post::output_fmt::OutputFormat;
+use racesim::post::provenance::Provenance;
+use racesim::post::history_config::HistoryConfig;
+use racesim::post::race_recording::{read_race_recording, RaceRecording};
+use racesim::post::race_result::{
+    format_lap_flags, get_lap_axis, is_retirement_event_kind, EventSnapshot, IncidentRates,
+    RaceResult, SanityWarning,
+};
+use racesim::post::report::{write_html_report, ReportInput, ReportPlot};
+use racesim::post::velocity_trace::get_velocity_trace;
+use racesim::pre::read_sim_pars::{
+    discover_scenarios, read_run_config, read_sim_pars_flexible, read_sim_pars_merged,
+    read_sim_constants, read_tire_config, write_run_config, write_sim_pars, RunConfig, SimPars,
+};
+use racesim::pre::scenario_randomizer::{
+    randomize_scenario, read_randomizer_spec, write_scenario_variants,
+};
+use racesim::pre::sim_opts::{PlotKind, SimCommand, SimOpts};
+use std::io::{IsTerminal, Write as IoWrite};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Once;
 use std::thread;
 use std::time::Instant;
+use plotters::coord::types::{RangedCoordf64, RangedCoordu32};
+use plotters::coord::Shift;
 use plotters::prelude::*;
+use plotters::style::text_anchor::{HPos, Pos, VPos};
+
+/// PLOT_FONT is the font family of all plot texts.
+const PLOT_FONT: &str = "sans-serif";
+
+static PLOT_FONT_WARNING: Once = Once::new();
+
+/// PlotTarget is the folder the plots are written to together with the availability of the plot
+/// font: if `font_warning` is set, the plots are drawn without texts.
+struct PlotTarget {
+    out_dir: PathBuf,
+    font_warning: Option<String>,
+}
+
+impl PlotTarget {
+    /// new returns the target writing to the given folder, the plot font is checked once.
+    fn new(out_dir: &Path) -> PlotTarget {
+        PlotTarget {
+            out_dir: out_dir.to_owned(),
+            font_warning: get_plot_font_warning(),
+        }
+    }
+}
+
+/// PlotFile is an exported plot. If the plot font could not be loaded, the plot is drawn without
+/// texts (caption, axis labels, legend) and `warning` names the missing font.
+struct PlotFile {
+    path: String,
+    warning: Option<String>,
+}
+
+impl std::fmt::Display for PlotFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self.warning {
+            Some(warning) => write!(f, "{} (WARNING: {})", self.path, warning),
+            None => write!(f, "{}", self.path),
+        }
+    }
+}
+
+/// get_plot_font_warning checks if the plot font can be loaded and returns a warning otherwise.
+/// The console warning is printed only once per run.
+fn get_plot_font_warning() -> Option<String> {
+    if (PLOT_FONT, 16).into_font().box_size("0").is_ok() {
+        return None;
+    }
+
+    PLOT_FONT_WARNING.call_once(|| {
+        eprintln!(
+            "WARNING: Czcionka \"{}\" jest niedostępna (brak fontów systemowych, np. fonts-dejavu), \
+            wykresy są rysowane bez opisów!",
+            PLOT_FONT
+        )
+    });
+
+    Some(format!("font \"{}\" not available, plot drawn without texts", PLOT_FONT))
+}
+
+/// draw_provenance_footer draws the provenance of the run as small footer line in the lower right
+/// corner of the plot.
+fn draw_provenance_footer(
+    root: &DrawingArea<BitMapBackend<'_>, Shift>,
+    provenance: Option<&Provenance>,
+) -> anyhow::Result<()> {
+    if let Some(provenance) = provenance {
+        let (width, height) = root.dim_in_pixel();
+        root.draw(&Text::new(
+            provenance.get_footer(),
+            (width as i32 - 10, height as i32 - 4),
+            (PLOT_FONT, 11)
+                .into_font()
+                .color(&RGBColor(120, 120, 120))
+                .pos(Pos::new(HPos::Right, VPos::Bottom)),
+        ))?;
+    }
+
+    Ok(())
+}
 
 fn export_results_plot(
     result: &racesim::post::race_result::RaceResult,
     track_length_m: f64,
     show_speed: bool,
     averaged_n: Option<u32>,
-) -> anyhow::Result<String> {
-    let out_dir = std::path::Path::new("output");
-    std::fs::create_dir_all(out_dir)?;
+    marker_lap: Option<u32>,
+    out_fmt: &OutputFormat,
+    provenance: Option<&Provenance>,
+    target: &PlotTarget,
+) -> anyhow::Result<PlotFile> {
+    std::fs::create_dir_all(&target.out_dir)?;
+    let font_warning = target.font_warning.clone();
+    let with_texts = font_warning.is_none();
     let ts = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap()
@@ -26,14 +140,14 @@ fn export_results_plot(
     } else {
         format!("race_plot_{}.png", ts)
     };
-    let out_path = out_dir.join(filename);
+    let out_path = target.out_dir.join(filename);
 
     let mut y_min = f64::INFINITY;
     let mut y_max = f64::NEG_INFINITY;
     let tot_laps = result.tot_no_laps as usize;
     for (i, _) in result.car_driver_pairs.iter().enumerate() {
         for lap in 1..=tot_laps {
-            let lt = result.laptimes[i][lap];
+            let lt = result.get_laptime(i, lap);
             if lt.is_finite() && lt > 0.0 {
                 let y = if show_speed { (track_length_m / lt) * 3.6 } else { lt };
                 if y < y_min { y_min = y; }
@@ -47,19 +161,24 @@ fn export_results_plot(
 
     let root = BitMapBackend::new(out_path.to_str().unwrap(), (1280, 720)).into_drawing_area();
     root.fill(&WHITE)?;
-    let title_base = if show_speed { "Średnia prędkość na okrążeniach" } else { "Czas okrążenia" };
+    let strings = out_fmt.strings();
+    let title_base = if show_speed { strings.caption_speed } else { strings.caption_laptime };
     let title = if let Some(n) = averaged_n {
-        format!("{} (uśrednione z {} prób)", title_base, n)
+        out_fmt.caption_averaged(title_base, n)
     } else {
         title_base.to_string()
     };
 
-    let mut chart = ChartBuilder::on(&root)
-        .caption(title, ("sans-serif", 24).into_font())
-        .margin(20)
-        .x_label_area_size(40)
-        .y_label_area_size(60)
-        .build_cartesian_2d(1u32..result.tot_no_laps, y_min..y_max)?;
+    let mut chart_builder = ChartBuilder::on(&root);
+    chart_builder.margin(20);
+    if with_texts {
+        chart_builder
+            .caption(title, (PLOT_FONT, 24).into_font())
+            .x_label_area_size(40)
+            .y_label_area_size(60);
+    }
+    let lap_axis = get_lap_axis(result.get_no_laps_driven());
+    let mut chart = chart_builder.build_cartesian_2d(lap_axis.range.clone(), y_min..y_max)?;
 
     // Light-grey background bands for rainy laps
     if !result.weather_history.is_empty() {
@@ -75,34 +194,66 @@ fn export_results_plot(
         }
     }
 
-    chart.configure_mesh()
-        .x_desc("Okrążenie")
-        .y_desc(if show_speed { "km/h" } else { "s" })
-        .label_style(("sans-serif", 16))
-        .axis_desc_style(("sans-serif", 16))
-        .draw()?;
+    if with_texts {
+        chart.configure_mesh()
+            .x_labels(lap_axis.no_labels)
+            .x_desc(strings.axis_lap)
+            .y_desc(if show_speed { "km/h" } else { "s" })
+            .label_style((PLOT_FONT, 16))
+            .axis_desc_style((PLOT_FONT, 16))
+            .draw()?;
+    }
 
-    let palette = Palette99::pick;
     for (i, pair) in result.car_driver_pairs.iter().enumerate() {
+        let [r, g, b] = result.get_car_color(i);
+        let color = RGBColor(r, g, b);
         let mut series: Vec<(u32, f64)> = Vec::new();
         for lap in 1..=tot_laps {
-            let lt = result.laptimes[i][lap];
+            let lt = result.get_laptime(i, lap);
             if lt.is_finite() && lt > 0.0 {
                 let y = if show_speed { (track_length_m / lt) * 3.6 } else { lt };
                 series.push((lap as u32, y));
             }
         }
-        chart.draw_series(LineSeries::new(series.into_iter(), palette(i)))?
+        // codes of the lap context flags above the flagged laps
+        let flag_labels: Vec<(u32, f64, String)> = series
+            .iter()
+            .map(|&(lap, y)| (lap, y, format_lap_flags(result.get_lap_flags(i, lap as usize))))
+            .filter(|(_, _, codes)| !codes.is_empty())
+            .collect();
+
+        // a single lap cannot be drawn as a line
+        if series.len() == 1 {
+            chart.draw_series(series.iter().map(|&point| Circle::new(point, 4, color.filled())))?;
+        }
+
+        chart.draw_series(LineSeries::new(series.into_iter(), color))?
             .label(format!("{} ({})", pair.car_no, pair.driver_initials))
-            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], palette(i)));
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+
+        if with_texts {
+            chart.draw_series(flag_labels.into_iter().map(|(lap, y, codes)| {
+                Text::new(codes, (lap, y), (PLOT_FONT, 11).into_font().color(&color))
+            }))?;
+        }
     }
 
     for ev in &result.events {
         let x = ev.lap as u32;
         let (color, width) = match ev.kind.as_str() {
             "WeatherRainStart" | "WeatherDryStart" => (RGBColor(150, 150, 150), 1),
-            "SC_DEPLOYED" | "SC_IN" => (RGBColor(255, 165, 0), 1),
-            "Crash" | "EngineFailure" => (RED, 2),
+            "SC_DEPLOYED" | "SC_IN" | "VSC_DEPLOYED" | "VSC_ENDING" | "RESTART"
+            | "START_BEHIND_SC" => {
+                (RGBColor(255, 165, 0), 1)
+            }
+            "RED_FLAG" => (RED, 3),
+            "TIME_LIMIT_REACHED" => (BLACK, 3),
+            kind if is_retirement_event_kind(kind) => (RED, 2),
+            "DEBRIS_START" | "YELLOW_START" => (RGBColor(230, 200, 0), 1),
+            "DAMAGE" | "DAMAGE_REPAIRED" | "PUNCTURE" => (RGBColor(160, 60, 160), 1),
+            "DrsTrain" | "DrsTrainEnd" | "Contact" | "DEBRIS_END" | "YELLOW_END" | "StartReaction"
+            | "SC_DECISION" | "PIT_CLOSED" | "PIT_UNDER_SC"
+            | "FUEL_WARNING" => continue,
             _ => (BLACK, 1),
         };
         chart.draw_series(std::iter::once(PathElement::new(
@@ -110,15 +261,556 @@ fn export_results_plot(
         )))?;
     }
 
-    chart.configure_series_labels()
-        .border_style(&BLACK)
-        .background_style(&WHITE.mix(0.8))
-        .label_font(("sans-serif", 16))
-        .position(plotters::chart::SeriesLabelPosition::UpperRight)
-        .draw()?;
+    if let Some(lap) = marker_lap {
+        draw_lap_marker(&mut chart, lap, y_min, y_max, with_texts)?;
+    }
+
+    if with_texts {
+        chart.configure_series_labels()
+            .border_style(&BLACK)
+            .background_style(&WHITE.mix(0.8))
+            .label_font((PLOT_FONT, 16))
+            .position(plotters::chart::SeriesLabelPosition::UpperRight)
+            .draw()?;
+
+        if result.lap_flags.iter().flatten().any(|&flags| flags != 0) {
+            root.draw(&Text::new(
+                strings.legend_lap_flags,
+                (20, 700),
+                (PLOT_FONT, 14).into_font(),
+            ))?;
+        }
+
+        draw_provenance_footer(&root, provenance)?;
+    }
+
+    root.present()?;
+    Ok(PlotFile {
+        path: out_path.to_string_lossy().into_owned(),
+        warning: font_warning,
+    })
+}
+
+/// draw_lap_marker draws a vertical marker at the given lap, e.g. the lap of the intermediate
+/// classification, labeled with the lap number if texts can be drawn.
+fn draw_lap_marker(
+    chart: &mut ChartContext<'_, BitMapBackend<'_>, Cartesian2d<RangedCoordu32, RangedCoordf64>>,
+    lap: u32,
+    y_min: f64,
+    y_max: f64,
+    with_texts: bool,
+) -> anyhow::Result<()> {
+    let color = RGBColor(0, 90, 200);
+    chart.draw_series(std::iter::once(PathElement::new(
+        vec![(lap, y_min), (lap, y_max)], color.stroke_width(2),
+    )))?;
+
+    if with_texts {
+        chart.draw_series(std::iter::once(Text::new(
+            format!("L{}", lap),
+            (lap, y_max),
+            (PLOT_FONT, 14).into_font().color(&color),
+        )))?;
+    }
+
+    Ok(())
+}
+
+/// export_race_trace_plot plots the gap to the leader (race trace) or the position of every car at
+/// the end of every lap, based on the running order snapshots of the result. Returns the written
+/// file.
+fn export_race_trace_plot(
+    result: &RaceResult,
+    show_positions: bool,
+    marker_lap: Option<u32>,
+    out_fmt: &OutputFormat,
+    provenance: Option<&Provenance>,
+    target: &PlotTarget,
+) -> anyhow::Result<PlotFile> {
+    std::fs::create_dir_all(&target.out_dir)?;
+    let font_warning = target.font_warning.clone();
+    let with_texts = font_warning.is_none();
+    let out_path = target.out_dir.join(if show_positions {
+        "positions_plot.png"
+    } else {
+        "race_trace_plot.png"
+    });
+
+    let snapshots: Vec<EventSnapshot> = (1..=result.tot_no_laps)
+        .filter_map(|lap| result.get_snapshot(lap))
+        .collect();
+
+    let (y_min, y_max) = if show_positions {
+        (0.5, result.car_driver_pairs.len() as f64 + 0.5)
+    } else {
+        let gap_max = snapshots
+            .iter()
+            .flat_map(|snapshot| snapshot.gaps.iter())
+            .fold(1.0, |gap_max: f64, &gap| gap_max.max(gap));
+        (0.0, gap_max * 1.05)
+    };
+
+    let root = BitMapBackend::new(out_path.to_str().unwrap(), (1280, 720)).into_drawing_area();
+    root.fill(&WHITE)?;
+    let strings = out_fmt.strings();
+
+    let mut chart_builder = ChartBuilder::on(&root);
+    chart_builder.margin(20);
+    if with_texts {
+        chart_builder
+            .caption(
+                if show_positions { strings.caption_positions } else { strings.caption_race_trace },
+                (PLOT_FONT, 24).into_font(),
+            )
+            .x_label_area_size(40)
+            .y_label_area_size(60);
+    }
+    let lap_axis = get_lap_axis(result.get_no_laps_driven());
+    let mut chart = chart_builder.build_cartesian_2d(lap_axis.range.clone(), y_min..y_max)?;
+
+    if with_texts {
+        chart.configure_mesh()
+            .x_labels(lap_axis.no_labels)
+            .x_desc(strings.axis_lap)
+            .y_desc(if show_positions { strings.axis_position } else { strings.axis_gap })
+            .label_style((PLOT_FONT, 16))
+            .axis_desc_style((PLOT_FONT, 16))
+            .draw()?;
+    }
+
+    for (i, pair) in result.car_driver_pairs.iter().enumerate() {
+        let [r, g, b] = result.get_car_color(i);
+        let color = RGBColor(r, g, b);
+        let series: Vec<(u32, f64)> = snapshots
+            .iter()
+            .filter_map(|snapshot| {
+                let pos = snapshot.car_nos.iter().position(|&car_no| car_no == pair.car_no)?;
+                let y = if show_positions { pos as f64 + 1.0 } else { snapshot.gaps[pos] };
+                Some((snapshot.lap, y))
+            })
+            .collect();
+
+        // a single lap cannot be drawn as a line
+        if series.len() == 1 {
+            chart.draw_series(series.iter().map(|&point| Circle::new(point, 4, color.filled())))?;
+        }
+
+        chart.draw_series(LineSeries::new(series.into_iter(), color))?
+            .label(format!("{} ({})", pair.car_no, pair.driver_initials))
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+    }
+
+    if let Some(lap) = marker_lap {
+        draw_lap_marker(&mut chart, lap, y_min, y_max, with_texts)?;
+    }
+
+    if with_texts {
+        chart.configure_series_labels()
+            .border_style(&BLACK)
+            .background_style(&WHITE.mix(0.8))
+            .label_font((PLOT_FONT, 16))
+            .position(plotters::chart::SeriesLabelPosition::UpperRight)
+            .draw()?;
+
+        draw_provenance_footer(&root, provenance)?;
+    }
+
+    root.present()?;
+    Ok(PlotFile {
+        path: out_path.to_string_lossy().into_owned(),
+        warning: font_warning,
+    })
+}
+
+/// export_velocity_trace_plot plots the modeled speed of a car over the track distance in a single
+/// lap. Corners are shaded gray and the part driven in the pit lane orange. Returns the written
+/// file.
+fn export_velocity_trace_plot(
+    result: &RaceResult,
+    track: &Track,
+    car_no: u32,
+    lap: u32,
+    out_fmt: &OutputFormat,
+    provenance: Option<&Provenance>,
+    target: &PlotTarget,
+) -> anyhow::Result<PlotFile> {
+    let trace = get_velocity_trace(result, track, car_no, lap)?;
+    std::fs::create_dir_all(&target.out_dir)?;
+    let font_warning = target.font_warning.clone();
+    let with_texts = font_warning.is_none();
+    let out_path = target.out_dir.join(format!("velocity_trace_{}_lap{}.png", car_no, lap));
+
+    let series: Vec<(f64, f64)> = trace
+        .dists
+        .iter()
+        .zip(trace.velocities.iter())
+        .map(|(&s, &v)| (s, v * 3.6))
+        .collect();
+    let y_max = series.iter().fold(1.0, |y_max: f64, &(_, v)| y_max.max(v)) * 1.1;
+
+    let root = BitMapBackend::new(out_path.to_str().unwrap(), (1280, 720)).into_drawing_area();
+    root.fill(&WHITE)?;
+    let strings = out_fmt.strings();
+
+    let mut chart_builder = ChartBuilder::on(&root);
+    chart_builder.margin(20);
+    if with_texts {
+        chart_builder
+            .caption(
+                format!(
+                    "{} {} - {} {} ({}s)",
+                    strings.caption_velocity_trace,
+                    lap,
+                    car_no,
+                    result
+                        .car_driver_pairs
+                        .iter()
+                        .find(|pair| pair.car_no == car_no)
+                        .map_or("", |pair| pair.driver_initials.as_str()),
+                    out_fmt.fmt_num(trace.laptime, 0, 3)
+                ),
+                (PLOT_FONT, 24).into_font(),
+            )
+            .x_label_area_size(40)
+            .y_label_area_size(60);
+    }
+    let mut chart = chart_builder.build_cartesian_2d(0.0..track.length, 0.0..y_max)?;
+
+    if with_texts {
+        chart.configure_mesh()
+            .x_desc(strings.axis_distance)
+            .y_desc(strings.axis_velocity)
+            .label_style((PLOT_FONT, 16))
+            .axis_desc_style((PLOT_FONT, 16))
+            .draw()?;
+    }
+
+    // shade corners (zones across the finish line are split)
+    let corner_color = RGBColor(200, 200, 200).mix(0.5);
+    for corner in track.corners.iter() {
+        let spans = if corner[0] <= corner[1] {
+            vec![(corner[0], corner[1])]
+        } else {
+            vec![(corner[0], track.length), (0.0, corner[1])]
+        };
+
+        chart.draw_series(spans.into_iter().map(|(start, end)| {
+            Rectangle::new([(start, 0.0), (end, y_max)], corner_color.filled())
+        }))?;
+    }
+
+    // shade the part driven in the pit lane
+    let ds = track.length / trace.dists.len().max(1) as f64;
+    let pit_color = RGBColor(255, 128, 0).mix(0.3);
+    chart.draw_series(
+        trace
+            .dists
+            .iter()
+            .zip(trace.in_pitlane.iter())
+            .filter(|(_, &pit)| pit)
+            .map(|(&s, _)| {
+                Rectangle::new([(s - ds / 2.0, 0.0), (s + ds / 2.0, y_max)], pit_color.filled())
+            }),
+    )?;
+
+    let [r, g, b] = result
+        .car_driver_pairs
+        .iter()
+        .position(|pair| pair.car_no == car_no)
+        .map_or([0, 0, 0], |idx| result.get_car_color(idx));
+    chart.draw_series(LineSeries::new(series.into_iter(), RGBColor(r, g, b).stroke_width(2)))?;
+
+    if with_texts {
+        draw_provenance_footer(&root, provenance)?;
+    }
 
     root.present()?;
-    Ok(out_path.to_string_lossy().into_owned())
+    Ok(PlotFile {
+        path: out_path.to_string_lossy().into_owned(),
+        warning: font_warning,
+    })
+}
+
+/// get_compound_color returns the color of a tire compound in the strategy plot (gray for unknown
+/// compounds).
+fn get_compound_color(compound: &str) -> RGBColor {
+    match compound.to_uppercase().as_str() {
+        "SOFT" => RGBColor(218, 41, 28),
+        "MEDIUM" => RGBColor(255, 210, 0),
+        "HARD" => RGBColor(235, 235, 235),
+        "INTERMEDIATE" => RGBColor(67, 176, 42),
+        "WET" => RGBColor(0, 103, 173),
+        _ => RGBColor(150, 150, 150),
+    }
+}
+
+/// export_strategy_plot plots the tire stints of every car as horizontal bars over the laps, one
+/// row per car in the order of the final classification. Returns the written file.
+fn export_strategy_plot(
+    result: &RaceResult,
+    out_fmt: &OutputFormat,
+    provenance: Option<&Provenance>,
+    target: &PlotTarget,
+) -> anyhow::Result<PlotFile> {
+    std::fs::create_dir_all(&target.out_dir)?;
+    let font_warning = target.font_warning.clone();
+    let with_texts = font_warning.is_none();
+    let out_path = target.out_dir.join("strategy_plot.png");
+
+    let positions = result.get_final_positions();
+    let mut idxs_sorted: Vec<usize> = (0..positions.len()).collect();
+    idxs_sorted.sort_by_key(|&idx| positions[idx]);
+    let no_cars = idxs_sorted.len() as f64;
+
+    let root = BitMapBackend::new(out_path.to_str().unwrap(), (1280, 720)).into_drawing_area();
+    root.fill(&WHITE)?;
+    let strings = out_fmt.strings();
+
+    let mut chart_builder = ChartBuilder::on(&root);
+    chart_builder.margin(20);
+    if with_texts {
+        chart_builder
+            .caption(strings.caption_strategy, (PLOT_FONT, 24).into_font())
+            .x_label_area_size(40)
+            .y_label_area_size(60);
+    }
+    let lap_axis = get_lap_axis(result.get_no_laps_driven());
+    let x_max = lap_axis.range.end + 1;
+    let mut chart = chart_builder.build_cartesian_2d(1..x_max, 0.0..no_cars)?;
+
+    if with_texts {
+        chart.configure_mesh()
+            .disable_y_mesh()
+            .x_labels(lap_axis.no_labels)
+            .x_desc(strings.axis_lap)
+            .y_labels(0)
+            .label_style((PLOT_FONT, 16))
+            .axis_desc_style((PLOT_FONT, 16))
+            .draw()?;
+    }
+
+    for (row, &idx) in idxs_sorted.iter().enumerate() {
+        // first classified car in the top row
+        let y_top = no_cars - row as f64 - 0.1;
+        let y_bottom = no_cars - row as f64 - 0.9;
+
+        for (lap_first, lap_last, compound) in result.get_stints(idx) {
+            let color = get_compound_color(&compound);
+            chart.draw_series(std::iter::once(Rectangle::new(
+                [(lap_first, y_bottom), (lap_last + 1, y_top)],
+                color.filled(),
+            )))?;
+            chart.draw_series(std::iter::once(Rectangle::new(
+                [(lap_first, y_bottom), (lap_last + 1, y_top)],
+                BLACK.stroke_width(1),
+            )))?;
+        }
+
+        if with_texts {
+            let pair = &result.car_driver_pairs[idx];
+            chart.draw_series(std::iter::once(Text::new(
+                format!("{} ({})", pair.car_no, pair.driver_initials),
+                (1, y_top),
+                (PLOT_FONT, 14).into_font(),
+            )))?;
+        }
+    }
+
+    if with_texts {
+        draw_provenance_footer(&root, provenance)?;
+    }
+
+    root.present()?;
+    Ok(PlotFile {
+        path: out_path.to_string_lossy().into_owned(),
+        warning: font_warning,
+    })
+}
+
+/// write_report exports the plots of the run and writes them together with the results and the
+/// configuration into a single self-contained HTML report. Returns the path to the written file.
+fn write_report(
+    result: &RaceResult,
+    sim_pars: &SimPars,
+    sim_consts: &SimConstants,
+    out_fmt: &OutputFormat,
+    provenance: Option<&Provenance>,
+) -> anyhow::Result<String> {
+    let strings = out_fmt.strings();
+    let track_length = sim_pars.track_pars.length;
+    let target = PlotTarget::new(Path::new("output"));
+    let mut plots = vec![];
+
+    // the plot files are read directly after the export since the lap time and speed plots share
+    // their (time stamp based) file name
+    let mut add_plot = |title: &str, plot_file: PlotFile| -> anyhow::Result<()> {
+        let path = plot_file.path;
+        plots.push(ReportPlot {
+            title: title.to_string(),
+            png: std::fs::read(&path).with_context(|| format!("Failed to read plot {}!", path))?,
+        });
+        Ok(())
+    };
+
+    add_plot(
+        strings.caption_laptime,
+        export_results_plot(
+            result,
+            track_length,
+            false,
+            None,
+            None,
+            out_fmt,
+            provenance,
+            &target,
+        )?,
+    )?;
+    add_plot(
+        strings.caption_speed,
+        export_results_plot(
+            result,
+            track_length,
+            true,
+            None,
+            None,
+            out_fmt,
+            provenance,
+            &target,
+        )?,
+    )?;
+    add_plot(
+        strings.caption_race_trace,
+        export_race_trace_plot(result, false, None, out_fmt, provenance, &target)?,
+    )?;
+    add_plot(
+        strings.caption_positions,
+        export_race_trace_plot(result, true, None, out_fmt, provenance, &target)?,
+    )?;
+    add_plot(
+        strings.caption_strategy,
+        export_strategy_plot(result, out_fmt, provenance, &target)?,
+    )?;
+
+    let sanity_warnings = get_sanity_warnings(result, sim_pars);
+    let report_input = ReportInput {
+        result,
+        sim_pars,
+        sim_consts,
+        sanity_warnings: &sanity_warnings,
+        plots: &plots,
+        provenance,
+    };
+
+    write_html_report(None, &report_input, out_fmt)
+}
+
+fn render_recording(
+    recording: &RaceRecording,
+    out_path: &Path,
+    fps: f64,
+    speedup: f64,
+) -> anyhow::Result<()> {
+    let track = load_render_track(recording, &get_trackfile_path(&recording.track_pars.name))?;
+    let no_frames = render_race_gif(recording, &track, out_path, fps, speedup)?;
+    println!("INFO: Animation saved: {} ({} frames)", out_path.display(), no_frames);
+    Ok(())
+}
+
+/// anonymize_if_requested replaces the identities in the result by aliases if indicated and writes
+/// the private mapping to the real identities to output/mapping.json.
+fn anonymize_if_requested(result: RaceResult, sim_opts: &SimOpts) -> anyhow::Result<RaceResult> {
+    if !sim_opts.anonymize {
+        return Ok(result);
+    }
+
+    let (anonymized, identity_map) = result.anonymized();
+    let path = identity_map.write_to_file(None)?;
+    println!("INFO: Identity mapping saved (keep private): {}", path);
+
+    Ok(anonymized)
+}
+
+/// get_event_observers creates the live event streams (output/last_run_events_live.txt and
+/// output/last_run_events.jsonl) if indicated.
+fn get_event_observers(
+    sim_opts: &SimOpts,
+    out_fmt: &OutputFormat,
+) -> anyhow::Result<Vec<EventObserver>> {
+    if !sim_opts.stream_events {
+        return Ok(Vec::new());
+    }
+
+    let out_dir = Path::new("output");
+    let observers = vec![
+        events_log_observer(&out_dir.join("last_run_events_live.txt"), *out_fmt)?,
+        jsonl_observer(&out_dir.join("last_run_events.jsonl"))?,
+    ];
+    println!("INFO: Streaming race events to {}", out_dir.display());
+
+    Ok(observers)
+}
+
+/// get_sanity_warnings runs the sanity check on the results.
+fn get_sanity_warnings(result: &RaceResult, sim_pars: &SimPars) -> Vec<SanityWarning> {
+    // upper bound of the pit stop time loss: driving through the pit lane plus the standstill
+    let t_pit_tirechange_max = sim_pars
+        .car_pars_all
+        .values()
+        .map(|car_pars| car_pars.t_pit_tirechange)
+        .fold(0.0, f64::max);
+    let t_pit_loss = sim_pars.track_pars.real_length_pit_zone
+        / sim_pars.track_pars.pit_speedlimit.m_s
+        + 2.0 * t_pit_tirechange_max;
+
+    result.sanity_check(sim_pars.track_pars.t_q, t_pit_loss)
+}
+
+/// check_results runs the sanity check on the results, prints the findings, and writes the JSON
+/// export if indicated. In strict mode an error is returned if any warning was found.
+fn check_results(
+    result: &RaceResult,
+    sim_pars: &SimPars,
+    sim_opts: &SimOpts,
+    json_path: Option<&Path>,
+    provenance: Option<&Provenance>,
+) -> anyhow::Result<()> {
+    let sanity_warnings = get_sanity_warnings(result, sim_pars);
+
+    if !sanity_warnings.is_empty() {
+        println!("WARNINGS: Sanity check found {} non-physical values", sanity_warnings.len());
+        for warning in sanity_warnings.iter() {
+            println!(
+                "WARNING: {:?} (car {}, lap {}): {}",
+                warning.rule, warning.car_no, warning.lap, warning.msg
+            );
+        }
+    }
+
+    if sim_opts.json {
+        match result.write_to_json(json_path, &sanity_warnings, provenance) {
+            Ok(path) => println!("INFO: JSON results saved: {}", path),
+            Err(e) => eprintln!("WARNING: Could not save JSON results: {}", e),
+        }
+
+        // the resolved configuration allows to post-process the run later (plot subcommand)
+        let trackfile_path = get_trackfile_path(&sim_pars.track_pars.name);
+        let run_config = RunConfig {
+            sim_pars: sim_pars.to_owned(),
+            trackfile_path: std::fs::canonicalize(&trackfile_path).unwrap_or(trackfile_path),
+        };
+        let run_config_path = Path::new("output").join("run_config.json");
+
+        match write_run_config(&run_config, &run_config_path) {
+            Ok(()) => println!("INFO: Run configuration saved: {}", run_config_path.display()),
+            Err(e) => eprintln!("WARNING: Could not save run configuration: {:#}", e),
+        }
+    }
+
+    if sim_opts.strict && !sanity_warnings.is_empty() {
+        anyhow::bail!("Sanity check failed with {} warnings!", sanity_warnings.len());
+    }
+
+    Ok(())
 }
 
 fn average_results(results: &[RaceResult]) -> RaceResult {
@@ -161,33 +853,394 @@ fn average_results(results: &[RaceResult]) -> RaceResult {
     }
 
     RaceResult {
-        tot_no_laps: base.tot_no_laps,
-        car_driver_pairs: base.car_driver_pairs.clone(),
-        laptimes: avg_laptimes,
-        racetimes: avg_racetimes,
-        sc_active: false,
-        sc_position: 0.0,
-        weather_history: Vec::new(),
-        events: Vec::new(),
+        car_colors: base.car_colors.clone(),
+        track_length: base.track_length,
+        ..RaceResult::new(
+            base.tot_no_laps,
+            base.car_driver_pairs.clone(),
+            avg_laptimes,
+            avg_racetimes,
+        )
     }
 }
 
+/// prompt_scenario lists the scenarios found in input/parameters and in the current directory and
+/// lets the user pick one by its number. Returns the path to the selected scenario.
+fn prompt_scenario() -> anyhow::Result<PathBuf> {
+    let par_dir: PathBuf = ["input", "parameters"].iter().collect();
+    let scenario_paths = discover_scenarios(&[par_dir.as_path(), Path::new(".")]);
+
+    if scenario_paths.is_empty() {
+        anyhow::bail!(
+            "No parameter file provided and no scenarios found in input/parameters or the current \
+            directory! Use -p <path_to_json> to run the simulation."
+        );
+    }
+
+    println!("Dostępne scenariusze:");
+    for (i, path) in scenario_paths.iter().enumerate() {
+        println!("  [{}] {}", i + 1, path.display());
+    }
+
+    let stdin = std::io::stdin();
+
+    loop {
+        print!("Wybierz scenariusz (1-{}): ", scenario_paths.len());
+        std::io::stdout().flush()?;
+
+        let mut input = String::new();
+        if stdin.read_line(&mut input)? == 0 {
+            anyhow::bail!("No scenario selected!");
+        }
+
+        match input.trim().parse::<usize>() {
+            Ok(no) if (1..=scenario_paths.len()).contains(&no) => {
+                return Ok(scenario_paths[no - 1].to_owned())
+            }
+            _ => println!("Nieprawidłowy wybór \"{}\"!", input.trim()),
+        }
+    }
+}
+
+/// check_sim_pars validates the simulation parameters, prints the warnings and returns an error
+/// containing all errors found.
+fn check_sim_pars(sim_pars: &SimPars) -> anyhow::Result<()> {
+    let report = sim_pars.validate();
+
+    for msg in report.warnings.iter() {
+        eprintln!("WARNING: {}", msg);
+    }
+
+    if !report.errors.is_empty() {
+        anyhow::bail!("Simulation parameters are invalid:\n{}", report.errors.join("\n"));
+    }
+
+    Ok(())
+}
+
+/// check_merged_sim_pars validates the simulation parameters merged from several files and writes
+/// them to output/merged_sim_pars.json such that the combined grid can be inspected and reproduced.
+fn check_merged_sim_pars(sim_pars: &SimPars) -> anyhow::Result<()> {
+    check_sim_pars(sim_pars).context("Failed to merge simulation parameters!")?;
+
+    let out_dir = Path::new("output");
+    std::fs::create_dir_all(out_dir)?;
+    let out_path = out_dir.join("merged_sim_pars.json");
+    write_sim_pars(sim_pars, &out_path)?;
+    println!("INFO: Merged simulation parameters saved: {}", out_path.display());
+
+    Ok(())
+}
+
+/// select_participants applies the participant options of the command line (--only-cars,
+/// --exclude-cars, --withdraw) to the simulation parameters. It returns true if the participants
+/// were changed.
+fn select_participants(sim_pars: &mut SimPars, sim_opts: &SimOpts) -> anyhow::Result<bool> {
+    if sim_opts.only_cars.is_empty()
+        && sim_opts.exclude_cars.is_empty()
+        && sim_opts.withdraw.is_empty()
+    {
+        return Ok(false);
+    }
+
+    sim_pars
+        .select_participants(&sim_opts.only_cars, &sim_opts.exclude_cars)
+        .context("Failed to select the participants!")?;
+
+    for withdrawal in sim_opts.withdraw.iter() {
+        sim_pars
+            .withdraw_car(withdrawal.car_no, withdrawal.lap)
+            .context("Failed to withdraw car!")?;
+    }
+
+    println!("INFO: Participants: {:?}", sim_pars.race_pars.participants);
+    Ok(true)
+}
+
 fn main() -> anyhow::Result<()> {
     // PRE-PROCESSING ------------------------------------------------------------------------------
     // get simulation options from the command line arguments
     let sim_opts: SimOpts = SimOpts::parse();
+    let out_fmt = sim_opts.get_output_format();
+
+    // the GUI sender is created in the simulation thread, i.e. its errors would not be shown
+    if sim_opts.gui {
+        check_gui_update_frequency(sim_opts.gui_fps)?;
+    }
+
+    // run tools instead of a race simulation if indicated
+    if let Some(SimCommand::RandomizeScenario {
+        base,
+        spec,
+        out_dir,
+        no_variants,
+        seed,
+    }) = &sim_opts.command
+    {
+        let base_pars = read_sim_pars_flexible(base)?;
+        let spec = read_randomizer_spec(spec)?;
+        let variants = randomize_scenario(&base_pars, &spec, *no_variants, *seed);
+        let paths = write_scenario_variants(&variants, out_dir)?;
+        println!("INFO: Wrote {} scenario variants to {}", paths.len(), out_dir.display());
+        return Ok(());
+    }
+
+    if let Some(SimCommand::Checkpoint {
+        parfile_path,
+        lap,
+        seed,
+        out,
+    }) = &sim_opts.command
+    {
+        let mut sim_pars = read_sim_pars_flexible(parfile_path)?;
+        let sim_consts_path: PathBuf = ["input", "parameters", "sim_constants.json"].iter().collect();
+        let sim_consts = read_sim_constants(&sim_consts_path)?;
+        let tire_cfg_path: PathBuf = ["input", "parameters", "tires.json"].iter().collect();
+        let tire_cfg = read_tire_config(&tire_cfg_path)?;
+
+        sim_pars.fix_seed(*seed);
+
+        println!("INFO: Simulating race until lap {}...", lap);
+        let checkpoint =
+            Checkpoint::create(&sim_pars, &sim_consts, &tire_cfg, sim_opts.timestep_size, *lap)?;
+        checkpoint.write_to_file(out)?;
+        println!(
+            "INFO: Wrote checkpoint after lap {} (seed {}) to {}",
+            lap,
+            checkpoint.seed,
+            out.display()
+        );
+        return Ok(());
+    }
+
+    if let Some(SimCommand::Whatif {
+        resume_from,
+        set,
+        runs,
+    }) = &sim_opts.command
+    {
+        let checkpoint = read_checkpoint(resume_from)?;
+        let change = parse_whatif_change(set)?;
+        println!(
+            "INFO: Resuming race after lap {} with {} runs per variant...",
+            checkpoint.lap, runs
+        );
+        let comparison = run_whatif(&checkpoint, &change, (*runs).max(1))?;
+        print!("{}", comparison.format()?);
+        return Ok(());
+    }
+
+    if let Some(SimCommand::Practice {
+        parfile_path,
+        car,
+        laps,
+    }) = &sim_opts.command
+    {
+        let sim_pars = read_sim_pars_flexible(parfile_path)?;
+        let sim_consts_path: PathBuf = ["input", "parameters", "sim_constants.json"].iter().collect();
+        let sim_consts = read_sim_constants(&sim_consts_path)?;
+        let tire_cfg_path: PathBuf = ["input", "parameters", "tires.json"].iter().collect();
+        let tire_cfg = read_tire_config(&tire_cfg_path)?;
+        let out_fmt = sim_opts.get_output_format();
+
+        println!("INFO: Practice of car {} over {} laps...", car, laps);
+
+        if sim_opts.gui {
+            // the practice is simulated in real-time in a separate thread, the GUI shows the lap
+            // time breakdown of every completed lap next to the track
+            let (tx, rx) = flume::unbounded();
+            let (tx_control, rx_control) = flume::unbounded();
+
+            let sim_pars_thread = sim_pars.clone();
+            let sim_opts_thread = sim_opts.clone();
+            let car_thread = *car;
+            let laps_thread = *laps;
+
+            let _ = thread::spawn(move || -> anyhow::Result<()> {
+                let mut gui_sender = GuiSender::new(tx, sim_opts_thread.gui_fps)?
+                    .with_control(rx_control);
+                let practice_result = handle_practice(
+                    &sim_pars_thread,
+                    &sim_consts,
+                    &tire_cfg,
+                    car_thread,
+                    laps_thread,
+                    sim_opts_thread.timestep_size,
+                    &mut gui_sender,
+                    sim_opts_thread.realtime_factor,
+                )?;
+
+                match practice_result
+                    .write_breakdown_to_csv(None, &sim_opts_thread.get_output_format())
+                {
+                    Ok(path) => println!("INFO: Rozbicie czasów okrążeń zapisane: {}", path),
+                    Err(e) => {
+                        eprintln!("WARNING: Nie udało się zapisać rozbicia czasów okrążeń: {}", e)
+                    }
+                }
+                Ok(())
+            });
+
+            // the GUI shows the number of laps of the practice
+            let mut race_pars = sim_pars.race_pars.clone();
+            race_pars.tot_no_laps = *laps;
+            let trackfile_path = get_trackfile_path(&sim_pars.track_pars.name);
+
+            let gui = RacePlot::new(
+                rx,
+                &race_pars,
+                &sim_pars.track_pars,
+                trackfile_path.as_path(),
+                RacePlotOptions {
+                    tx_control: Some(tx_control),
+                    realtime_factor: sim_opts.realtime_factor,
+                    out_fmt,
+                    strategy_editor: None,
+                },
+            )?;
+            let native_options = eframe::NativeOptions {
+                initial_window_size: Some(eframe::egui::Vec2::new(1280.0, 720.0)),
+                ..eframe::NativeOptions::default()
+            };
+            eframe::run_native(Box::new(gui), native_options);
+        } else {
+            let practice_result = run_practice(
+                &sim_pars,
+                &sim_consts,
+                &tire_cfg,
+                *car,
+                *laps,
+                sim_opts.timestep_size,
+            )?;
+            print!("{}", practice_result.format(&out_fmt)?);
+
+            match practice_result.write_breakdown_to_csv(None, &out_fmt) {
+                Ok(path) => println!("INFO: Rozbicie czasów okrążeń zapisane: {}", path),
+                Err(e) => eprintln!("WARNING: Nie udało się zapisać rozbicia czasów okrążeń: {}", e),
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some(SimCommand::Season { season_path }) = &sim_opts.command {
+        let (season_pars, rounds) = read_season_pars(season_path)?;
+        let sim_consts_path: PathBuf = ["input", "parameters", "sim_constants.json"].iter().collect();
+        let sim_consts = read_sim_constants(&sim_consts_path)?;
+        let tire_cfg_path: PathBuf = ["input", "parameters", "tires.json"].iter().collect();
+        let tire_cfg = read_tire_config(&tire_cfg_path)?;
+
+        let out_fmt = sim_opts.get_output_format();
+
+        println!("INFO: Simulating a season of {} rounds...", rounds.len());
+        let season_result = run_season(
+            &season_pars,
+            &rounds,
+            &sim_consts,
+            &tire_cfg,
+            sim_opts.timestep_size,
+        )?;
+        print!("{}", season_result.format_summary(&season_pars, &out_fmt)?);
+
+        match season_result.state.write_to_json(None) {
+            Ok(path) => println!("INFO: Stan sezonu zapisany: {}", path),
+            Err(e) => eprintln!("WARNING: Nie udało się zapisać stanu sezonu: {}", e),
+        }
+        return Ok(());
+    }
+
+    if let Some(SimCommand::Plot {
+        kind,
+        car,
+        lap,
+        run_dir,
+    }) = &sim_opts.command
+    {
+        let run_config = read_run_config(&run_dir.join("run_config.json"))?;
+        let (result, provenance) =
+            RaceResult::read_from_json_with_provenance(&run_dir.join("last_run.json"))?;
+
+        match kind {
+            PlotKind::Velocity => {
+                let track = Track::with_trackfile(
+                    &run_config.sim_pars.track_pars,
+                    &run_config.trackfile_path,
+                );
+                let plot_file = export_velocity_trace_plot(
+                    &result,
+                    &track,
+                    *car,
+                    *lap,
+                    &out_fmt,
+                    provenance.as_ref(),
+                    &PlotTarget::new(run_dir),
+                )?;
+                println!("INFO: Wykres zapisany: {}", plot_file);
+            }
+        }
+        return Ok(());
+    }
+
+    // render a previously recorded race without simulating
+    if let Some(SimCommand::Render {
+        input,
+        out,
+        fps,
+        speedup,
+    }) = &sim_opts.command
+    {
+        let out_path = out.to_owned().unwrap_or_else(|| input.with_extension("gif"));
+        let recording = read_race_recording(input)?;
+        return render_recording(&recording, &out_path, *fps, *speedup);
+    }
 
     // get simulation parameters (scenario + data)
-    let sim_pars = if let Some(parfile_path) = &sim_opts.parfile_path {
+    let (mut sim_pars, scenario) = if sim_opts.parfile_path.len() > 1 {
+        println!("INFO: Merging simulation parameters from {:?}", sim_opts.parfile_path);
+        let scenario: Vec<String> = sim_opts
+            .parfile_path
+            .iter()
+            .map(|parfile_path| parfile_path.display().to_string())
+            .collect();
+        (read_sim_pars_merged(&sim_opts.parfile_path)?, scenario.join(" + "))
+    } else if let Some(parfile_path) = sim_opts.parfile_path.first() {
+        println!("INFO: Reading simulation parameters from {:?}", parfile_path);
+        (read_sim_pars_flexible(parfile_path)?, parfile_path.display().to_string())
+    } else if !sim_opts.non_interactive && std::io::stdin().is_terminal() {
+        let parfile_path = prompt_scenario()?;
         println!("INFO: Reading simulation parameters from {:?}", parfile_path);
-        read_sim_pars_flexible(parfile_path)?
+        (read_sim_pars_flexible(&parfile_path)?, parfile_path.display().to_string())
     } else {
         anyhow::bail!("No parameter file provided! Use -p <path_to_json> to run the simulation.");
     };
 
+    // the validation runs on the selected participants
+    let participants_changed = select_participants(&mut sim_pars, &sim_opts)?;
+
+    if sim_opts.parfile_path.len() > 1 {
+        check_merged_sim_pars(&sim_pars)?;
+    } else if participants_changed {
+        check_sim_pars(&sim_pars)?;
+    }
+
+    // switched off model components are part of the resolved parameters (and the provenance)
+    if let Some(model_toggles) = &sim_opts.disable {
+        sim_pars.race_pars.model_toggles.disable_all(model_toggles);
+        println!(
+            "INFO: Disabled model components: {}",
+            sim_pars.race_pars.model_toggles.get_disabled_names().join(", ")
+        );
+    }
+
+    // the seed is fixed before the run such that it is recorded in the provenance, which is
+    // embedded into every output file
+    let seed = sim_pars.fix_seed(sim_opts.seed);
+    println!("INFO: Random seed of the run: {}", seed);
+    let provenance = Provenance::new(&scenario, Some(seed), sim_opts.timestep_size, &sim_pars)?;
+
     // get simulation constants (physics engine), from default path
     let sim_consts_path: PathBuf = ["input", "parameters", "sim_constants.json"].iter().collect();
     let sim_consts = read_sim_constants(&sim_consts_path)?;
+    sim_pars.check_sim_consts(&sim_consts)?;
 
     // get tire configuration from default path
     let tire_cfg_path: PathBuf = ["input", "parameters", "tires.json"].iter().collect();
@@ -207,66 +1260,222 @@ fn main() -> anyhow::Result<()> {
             println!("INFO: Running single simulation without GUI...");
             let t_start = Instant::now();
 
-            let race_result = racesim::core::handle_race::handle_race(
-                &sim_pars,
-                &sim_consts,
-                &tire_cfg,
-                sim_opts.timestep_size,
-                sim_opts.debug,
-                None,
-                1.0,
-                true,
-            )?;
+            let race_result = if sim_opts.record.is_some() || sim_opts.render.is_some() {
+                let history_config = HistoryConfig {
+                    max_memory_bytes: sim_opts.history_budget_mb * 1024 * 1024,
+                    recording_spill_path: sim_opts.record_spill.to_owned(),
+                    ..HistoryConfig::default()
+                };
+                let (race_result, mut recording) = racesim::core::handle_race::record_race(
+                    &sim_pars,
+                    &sim_consts,
+                    &tire_cfg,
+                    sim_opts.timestep_size,
+                    &history_config,
+                    true,
+                )?;
+
+                if let Some(record_path) = &sim_opts.record {
+                    let no_dropped = recording.write_to_file(record_path)?;
+                    println!("INFO: Recording saved: {}", record_path.display());
+                    if no_dropped > 0 {
+                        eprintln!(
+                            "WARNING: {} spilled frames could not be read back and are missing in \
+                            the recording!",
+                            no_dropped
+                        );
+                    }
+                }
+
+                if let Some(render_path) = &sim_opts.render {
+                    // rendering needs all frames in memory
+                    recording.load_spilled_frames()?;
+
+                    if let Err(e) = render_recording(
+                        &recording,
+                        render_path,
+                        sim_opts.render_fps,
+                        sim_opts.render_speedup,
+                    ) {
+                        eprintln!("WARNING: Could not render animation: {:#}", e);
+                    }
+                }
+
+                race_result
+            } else {
+                racesim::core::handle_race::handle_race(
+                    &sim_pars,
+                    &sim_consts,
+                    &tire_cfg,
+                    sim_opts.timestep_size,
+                    sim_opts.debug,
+                    None,
+                    1.0,
+                    true,
+                    get_event_observers(&sim_opts, &out_fmt)?,
+                )?
+            };
 
             println!("INFO: Execution time: {}ms", t_start.elapsed().as_millis());
 
-            match race_result.write_lap_and_race_times_to_file(None) {
+            let race_result = anonymize_if_requested(race_result, &sim_opts)?;
+
+            if let Some(pit_stop) = race_result.get_fastest_pit_stop() {
+                println!(
+                    "INFO: Fastest pit stop: car {} on lap {} ({}s)",
+                    pit_stop.car_no,
+                    pit_stop.lap,
+                    out_fmt.fmt_num(pit_stop.t_standstill, 0, 2)
+                );
+            }
+
+            println!("RESULT: Classification");
+            print!("{}", race_result.format_classification(&out_fmt)?);
+
+            if let Some(time_losses) = race_result.format_time_losses(&out_fmt)? {
+                println!("RESULT: Time lost versus a clean run (s)");
+                print!("{}", time_losses);
+            }
+
+            if let Some(lap) = sim_opts.classification_at_lap {
+                match race_result.format_classification_at_lap(lap, &out_fmt)? {
+                    Some(classification) => {
+                        println!("RESULT: Classification at the end of lap {}", lap);
+                        print!("{}", classification);
+                    }
+                    None => eprintln!("WARNING: No car completed lap {}!", lap),
+                }
+            }
+
+            match race_result.write_lap_and_race_times_to_file(None, &out_fmt, Some(&provenance)) {
                 Ok(path) => println!("INFO: Wyniki zapisane: {}", path),
                 Err(e) => eprintln!("WARNING: Nie udało się zapisać wyników: {}", e),
             }
 
-            match export_results_plot(&race_result, sim_pars.track_pars.length, false, None) {
+            if sim_opts.csv {
+                match race_result.write_lap_times_to_csv(None, &out_fmt, Some(&provenance)) {
+                    Ok(path) => println!("INFO: Wyniki CSV zapisane: {}", path),
+                    Err(e) => eprintln!("WARNING: Nie udało się zapisać wyników CSV: {}", e),
+                }
+            }
+
+            match race_result.write_events_to_file(None, &out_fmt, Some(&provenance)) {
+                Ok(path) => println!("INFO: Dziennik zdarzeń zapisany: {}", path),
+                Err(e) => eprintln!("WARNING: Nie udało się zapisać dziennika zdarzeń: {}", e),
+            }
+
+            match export_results_plot(
+                &race_result,
+                sim_pars.track_pars.length,
+                false,
+                None,
+                sim_opts.classification_at_lap,
+                &out_fmt,
+                Some(&provenance),
+                &PlotTarget::new(Path::new("output")),
+            ) {
                 Ok(path) => println!("INFO: Wykres zapisany: {}", path),
                 Err(e) => eprintln!("WARNING: Nie udało się zapisać wykresu: {}", e),
             }
+
+            if sim_opts.report {
+                match write_report(
+                    &race_result,
+                    &sim_pars,
+                    &sim_consts,
+                    &out_fmt,
+                    Some(&provenance),
+                ) {
+                    Ok(path) => println!("INFO: Raport HTML zapisany: {}", path),
+                    Err(e) => eprintln!("WARNING: Nie udało się zapisać raportu HTML: {:#}", e),
+                }
+            }
+
+            check_results(&race_result, &sim_pars, &sim_opts, None, Some(&provenance))?;
         } else {
             println!("INFO: Running {} simulations for averaging...", runs);
             let t_start_total = Instant::now();
             let mut results: Vec<RaceResult> = Vec::with_capacity(runs as usize);
 
+            // the track geometry is read and processed once for all runs
+            let track = Rc::new(Track::new(&sim_pars.track_pars));
+            println!("INFO: Track set up in {}ms", t_start_total.elapsed().as_millis());
+
             for i in 0..runs {
                 println!("INFO: Simulating run {}/{}", i + 1, runs);
-                let res = racesim::core::handle_race::handle_race(
-                    &sim_pars,
+
+                // run i is seeded with seed + i such that every run can be reproduced on its own
+                let mut sim_pars_run = sim_pars.clone();
+                sim_pars_run.race_pars.seed = Some(seed.wrapping_add(i as u64));
+
+                let res = racesim::core::handle_race::run_race_on_track(
+                    &sim_pars_run,
                     &sim_consts,
                     &tire_cfg,
+                    &track,
                     sim_opts.timestep_size,
-                    false, // suppress per-run debug for speed; use --debug with single run
-                    None,
-                    1.0,
-                    false, // suppress event prints in multi-run
                 )?;
                 results.push(res);
             }
 
-            let averaged = average_results(&results);
+            let averaged = anonymize_if_requested(average_results(&results), &sim_opts)?;
             println!("INFO: All runs done in {}ms", t_start_total.elapsed().as_millis());
 
+            println!("RESULT: Incident rates by weather ({} runs)", runs);
+            print!("{}", IncidentRates::from_results(&results).format(&out_fmt));
+
+            if !sim_opts.h2h.is_empty() {
+                let h2h_reports = head_to_head(&sim_opts.h2h, &results);
+                println!("RESULT: Head-to-head ({} runs)", runs);
+                print!("{}", format_h2h_table(&h2h_reports, &out_fmt)?);
+
+                match write_h2h_to_csv(&h2h_reports, None, &out_fmt, Some(&provenance)) {
+                    Ok(path) => println!("INFO: Head-to-head CSV saved: {}", path),
+                    Err(e) => eprintln!("WARNING: Could not save head-to-head CSV: {}", e),
+                }
+            }
+
             // Save averaged results to a dedicated file
             let mut out_path = PathBuf::new();
             out_path.push("output");
             std::fs::create_dir_all(&out_path)?;
             out_path.push("last_run_averaged.txt");
 
-            match averaged.write_lap_and_race_times_to_file(Some(&out_path)) {
+            match averaged.write_lap_and_race_times_to_file(
+                Some(&out_path),
+                &out_fmt,
+                Some(&provenance),
+            ) {
                 Ok(path) => println!("INFO: Averaged results saved: {}", path),
                 Err(e) => eprintln!("WARNING: Could not save averaged results: {}", e),
             }
 
-            match export_results_plot(&averaged, sim_pars.track_pars.length, false, Some(runs)) {
+            if sim_opts.csv {
+                out_path.set_extension("csv");
+                let csv_written =
+                    averaged.write_lap_times_to_csv(Some(&out_path), &out_fmt, Some(&provenance));
+                match csv_written {
+                    Ok(path) => println!("INFO: Averaged CSV results saved: {}", path),
+                    Err(e) => eprintln!("WARNING: Could not save averaged CSV results: {}", e),
+                }
+            }
+
+            match export_results_plot(
+                &averaged,
+                sim_pars.track_pars.length,
+                false,
+                Some(runs),
+                None,
+                &out_fmt,
+                Some(&provenance),
+                &PlotTarget::new(Path::new("output")),
+            ) {
                 Ok(path) => println!("INFO: Averaged plot saved: {}", path),
                 Err(e) => eprintln!("WARNING: Could not save averaged plot: {}", e),
             }
+
+            out_path.set_extension("json");
+            check_results(&averaged, &sim_pars, &sim_opts, Some(&out_path), Some(&provenance))?;
         }
     } else {
         // GUI CASE - symulacja w czasie rzeczywistym z wizualizacją
@@ -274,32 +1483,38 @@ fn main() -> anyhow::Result<()> {
         
         // Utwórz kanał komunikacji między GUI a symulatorem
         let (tx, rx) = flume::unbounded();
+        // Kanał sterowania symulacją z GUI (pauza, prędkość)
+        let (tx_control, rx_control) = flume::unbounded();
+        // Kanał z parametrami po edycji strategii na ekranie przed startem
+        let (tx_pars, rx_pars) = flume::bounded::<SimPars>(1);
 
-        // Uruchom symulator w osobnym wątku
+        // Uruchom symulator w osobnym wątku (czeka na start wyścigu w GUI, każde ponowne
+        // uruchomienie z GUI przesyła parametry jeszcze raz)
         let sim_opts_thread = sim_opts.clone();
-        let sim_pars_thread = sim_pars.clone();
         let sim_consts_thread = sim_consts.clone();
         let tire_cfg_thread = tire_cfg.clone();
 
-        let _ = thread::spawn(move || {
-            racesim::core::handle_race::handle_race(
-                &sim_pars_thread,
-                &sim_consts_thread,
-                &tire_cfg_thread,
-                sim_opts_thread.timestep_size,
-                false, // debug wyłączony w GUI
-                Some(&tx),
-                sim_opts_thread.realtime_factor,
-                false, // suppress event prints in GUI
-            )
+        let _ = thread::spawn(move || -> anyhow::Result<()> {
+            while let Ok(sim_pars_thread) = rx_pars.recv() {
+                let mut gui_sender = GuiSender::new(tx.clone(), sim_opts_thread.gui_fps)?
+                    .with_control(rx_control.clone());
+                racesim::core::handle_race::handle_race(
+                    &sim_pars_thread,
+                    &sim_consts_thread,
+                    &tire_cfg_thread,
+                    sim_opts_thread.timestep_size,
+                    false, // debug wyłączony w GUI
+                    Some(&mut gui_sender),
+                    sim_opts_thread.realtime_factor,
+                    false, // suppress event prints in GUI
+                    Vec::new(),
+                )?;
+            }
+            Ok(())
         });
 
         // Ustaw ścieżkę do pliku toru (zawsze z input/tracks)
-        let mut trackfile_path = std::path::PathBuf::new();
-        trackfile_path.push("input");
-        trackfile_path.push("tracks");
-        trackfile_path.push(&sim_pars.track_pars.name);
-        trackfile_path.set_extension("csv");
+        let trackfile_path = get_trackfile_path(&sim_pars.track_pars.name);
 
         println!("INFO: Loading track from: {:?}", trackfile_path);
 
@@ -309,6 +1524,12 @@ fn main() -> anyhow::Result<()> {
             &sim_pars.race_pars,
             &sim_pars.track_pars,
             trackfile_path.as_path(),
+            RacePlotOptions {
+                tx_control: Some(tx_control),
+                realtime_factor: sim_opts.realtime_factor,
+                out_fmt,
+                strategy_editor: Some(StrategyEditor::new(sim_pars.clone(), tx_pars)),
+            },
         )?;
         let native_options = eframe::NativeOptions {
             initial_window_size: Some(eframe::egui::Vec2::new(1280.0, 720.0)),
@@ -318,4 +1539,108 @@ fn main() -> anyhow::Result<()> {
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod plot_tests {
+    use super::*;
+    use racesim::core::handle_race::run_race;
+    use racesim::pre::read_sim_pars::read_sim_pars;
+
+    /// run_test_race simulates the test race without random failures and collisions over the
+    /// given number of laps.
+    fn run_test_race(tot_no_laps: u32) -> RaceResult {
+        let par_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../input/parameters");
+        let mut sim_pars = read_sim_pars(&par_dir.join("test_race.json")).unwrap();
+        let mut sim_consts = read_sim_constants(&par_dir.join("sim_constants.json")).unwrap();
+        let tire_config = read_tire_config(&par_dir.join("tires.json")).unwrap();
+        sim_pars.race_pars.tot_no_laps = tot_no_laps;
+        sim_consts.failure_rate_per_hour = 0.0;
+        sim_consts.collision_factor = 0.0;
+        run_race(&sim_pars, &sim_consts, &tire_config, 0.5).unwrap()
+    }
+
+    /// get_test_target returns a plot target in a new folder in the temporary directory that is
+    /// unique to the test and the test process.
+    fn get_test_target(test_name: &str, font_warning: Option<String>) -> PlotTarget {
+        let out_dir = std::env::temp_dir()
+            .join(format!("racesim_{}_{}", test_name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&out_dir);
+        PlotTarget {
+            out_dir,
+            font_warning,
+        }
+    }
+
+    #[test]
+    fn test_plots_of_short_and_long_races() {
+        let out_fmt = OutputFormat::default();
+        let provenance = Provenance {
+            scenario: String::from("test_race.json"),
+            seed: None,
+            timestep_size: 0.5,
+            version: String::from(env!("CARGO_PKG_VERSION")),
+            timestamp: String::from("1970-01-01T00:00:00Z"),
+            config_hash: String::from("0123456789abcdef"),
+            disabled_components: Vec::new(),
+        };
+        let target = get_test_target("plots_short_long", None);
+
+        for &tot_no_laps in [1, 500].iter() {
+            let result = run_test_race(tot_no_laps);
+
+            let plot_files = vec![
+                export_results_plot(
+                    &result,
+                    5000.0,
+                    false,
+                    None,
+                    Some(1),
+                    &out_fmt,
+                    Some(&provenance),
+                    &target,
+                )
+                .unwrap(),
+                export_race_trace_plot(
+                    &result,
+                    false,
+                    Some(1),
+                    &out_fmt,
+                    Some(&provenance),
+                    &target,
+                )
+                .unwrap(),
+                export_race_trace_plot(&result, true, None, &out_fmt, None, &target).unwrap(),
+            ];
+
+            for plot_file in plot_files.iter() {
+                assert!(Path::new(&plot_file.path).starts_with(&target.out_dir));
+                let png = std::fs::read(&plot_file.path).unwrap();
+                assert_eq!(&png[..4], b"\x89PNG");
+            }
+        }
+
+        std::fs::remove_dir_all(&target.out_dir).unwrap();
+    }
+
+    #[test]
+    fn test_plot_without_font() {
+        let result = run_test_race(3);
+
+        // a container without fonts
+        let target = get_test_target(
+            "plot_without_font",
+            Some(String::from("font not available, plot drawn without texts")),
+        );
+        let plot_file =
+            export_race_trace_plot(&result, false, None, &OutputFormat::default(), None, &target)
+                .unwrap();
+
+        assert!(plot_file.warning.is_some());
+        assert!(plot_file.to_string().contains("WARNING"));
+        let png = std::fs::read(&plot_file.path).unwrap();
+        assert_eq!(&png[..4], b"\x89PNG");
+
+        std::fs::remove_dir_all(&target.out_dir).unwrap();
+    }
+}
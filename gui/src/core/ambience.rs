@@ -0,0 +1,96 @@
+use eframe::egui;
+
+/// Ambience contains the colors of the race screen at a time of day.
+/// * `canvas` - Background of the track view (dry track)
+/// * `canvas_rain` - Background of the track view in the rain
+/// * `track` - Color of the track centerline (without elevation shading)
+/// * `label_lift` - Share (0.0 - 1.0) by which car dots and labels are brightened towards white
+/// * `text` - Color of the general information text
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ambience {
+    pub canvas: egui::Color32,
+    pub canvas_rain: egui::Color32,
+    pub track: egui::Color32,
+    pub label_lift: f32,
+    pub text: egui::Color32,
+}
+
+/// DAY is the ambience in daylight (also used if the time of day is not modeled).
+pub const DAY: Ambience = Ambience {
+    canvas: egui::Color32::from_rgb(20, 80, 20),
+    canvas_rain: egui::Color32::from_rgb(60, 60, 60),
+    track: egui::Color32::WHITE,
+    label_lift: 0.0,
+    text: egui::Color32::WHITE,
+};
+
+/// DUSK is the ambience around sunset.
+const DUSK: Ambience = Ambience {
+    canvas: egui::Color32::from_rgb(45, 40, 55),
+    canvas_rain: egui::Color32::from_rgb(45, 45, 50),
+    track: egui::Color32::from_rgb(240, 210, 170),
+    label_lift: 0.2,
+    text: egui::Color32::from_rgb(250, 235, 210),
+};
+
+/// NIGHT is the ambience after dusk (floodlit track on a dark canvas).
+const NIGHT: Ambience = Ambience {
+    canvas: egui::Color32::from_rgb(8, 10, 25),
+    canvas_rain: egui::Color32::from_rgb(20, 22, 30),
+    track: egui::Color32::from_rgb(255, 245, 200),
+    label_lift: 0.4,
+    text: egui::Color32::from_rgb(255, 250, 225),
+};
+
+/// Keyframes of the ambience as (hours relative to sunset, ambience).
+const KEYFRAMES: [(f64, Ambience); 4] = [(-1.0, DAY), (0.0, DUSK), (0.75, NIGHT), (9.0, NIGHT)];
+
+/// lerp_color interpolates linearly between two colors.
+fn lerp_color(a: egui::Color32, b: egui::Color32, frac: f32) -> egui::Color32 {
+    let lerp = |x: u8, y: u8| (x as f32 + (y as f32 - x as f32) * frac).round() as u8;
+    egui::Color32::from_rgb(lerp(a.r(), b.r()), lerp(a.g(), b.g()), lerp(a.b(), b.b()))
+}
+
+/// lerp_ambience interpolates linearly between two ambiences.
+fn lerp_ambience(a: &Ambience, b: &Ambience, frac: f32) -> Ambience {
+    Ambience {
+        canvas: lerp_color(a.canvas, b.canvas, frac),
+        canvas_rain: lerp_color(a.canvas_rain, b.canvas_rain, frac),
+        track: lerp_color(a.track, b.track, frac),
+        label_lift: a.label_lift + (b.label_lift - a.label_lift) * frac,
+        text: lerp_color(a.text, b.text, frac),
+    }
+}
+
+/// get_ambience returns the ambience at the given time of day (h) such that the colors shift
+/// gradually from day over dusk to night around the sunset (the time of day counts from midnight
+/// of the session day, see `get_time_of_day`). Returns the day ambience if the time of day is not
+/// modeled.
+pub fn get_ambience(time_of_day_h: Option<f64>, sunset_h: f64) -> Ambience {
+    let time_of_day_h = match time_of_day_h {
+        Some(x) => x,
+        None => return DAY,
+    };
+    let t_rel = time_of_day_h - sunset_h;
+
+    if t_rel <= KEYFRAMES[0].0 {
+        return KEYFRAMES[0].1;
+    }
+
+    for keyframes in KEYFRAMES.windows(2) {
+        let (t_a, ambience_a) = &keyframes[0];
+        let (t_b, ambience_b) = &keyframes[1];
+
+        if t_rel <= *t_b {
+            return lerp_ambience(ambience_a, ambience_b, ((t_rel - t_a) / (t_b - t_a)) as f32);
+        }
+    }
+
+    NIGHT
+}
+
+/// lift_color brightens a car color towards white by the given share, such that the car dots and
+/// labels stay visible on the dark canvas at night.
+pub fn lift_color(color: egui::Color32, label_lift: f32) -> egui::Color32 {
+    lerp_color(color, egui::Color32::WHITE, label_lift)
+}
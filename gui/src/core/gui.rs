@@ -1,17 +1,39 @@
-use crate::core::track::{Track, ZoneType};
+use crate::core::ambience::{get_ambience, lift_color, Ambience};
+use crate::core::shortcuts::{read_actions, show_help_overlay, GuiAction};
+use crate::core::strategy_editor::StrategyEditor;
+use crate::core::track::{Track, Zone, ZoneType};
 use crate::interfaces::racesim_interface::RacesimInterface;
+use eframe::egui::plot::{Legend, Line, Plot, Value, Values};
 use eframe::{egui, epi};
 use plotters::prelude::*;
-use flume::Receiver;
+use flume::{Receiver, Sender};
 use helpers::buffer::RingBuffer;
 use helpers::general::max;
-use racesim::core::race::RacePars;
+use racesim::core::practice::{PracticeResult, BREAKDOWN_HEADER};
+use racesim::core::race::{FlagState, RacePars};
+use racesim::core::time_of_day::{format_time_of_day, get_time_of_day};
 use racesim::core::track::TrackPars;
-use racesim::interfaces::gui_interface::RaceState;
+use racesim::interfaces::gui_interface::{RaceState, RetiredCar, SimControl};
+use racesim::post::output_fmt::OutputFormat;
+use racesim::post::race_result::{get_lap_axis, is_retirement_event_kind, RaceResult};
+use racesim::post::result_diff::ResultDiff;
+use std::collections::HashMap;
 use std::fmt::Write;
 use std::path::Path;
 use std::time::Instant;
 
+/// Height of the position strip below the live track view (px). The rows of the cars shrink such
+/// that larger fields fit into the strip, their labels are hidden below a minimum row height.
+const POSITION_STRIP_HEIGHT: f32 = 120.0;
+const POSITION_STRIP_MAX_ROW_HEIGHT: f32 = 12.0;
+const POSITION_STRIP_MIN_LABEL_HEIGHT: f32 = 8.0;
+const POSITION_STRIP_LABEL_WIDTH: f32 = 28.0;
+
+/// Race time (s) over which the dot of a retired car fades out at its stopping position before it
+/// is drawn as a small grey cross (half size in px).
+const RETIRED_FADE_DURATION_S: f64 = 3.0;
+const RETIRED_CROSS_HALF_SIZE: f32 = 5.0;
+
 #[derive(Debug)]
 pub struct CarStateGui {
     pub color: egui::Color32,
@@ -20,9 +42,66 @@ pub struct CarStateGui {
     pub text: String,
 }
 
+/// TrackLayer caches the shapes of the static track layer (centerline and zones) that were
+/// transformed to the screen, such that they must only be recreated if the view changes.
+/// * `from` - Source rectangle (track coordinates) of the view transform
+/// * `to` - Destination rectangle (pixels) of the view transform
+/// * `show_zones` - Flag that indicates if the zones are part of the shapes
+/// * `track_color` - Color of the centerline (depends on the time of day)
+/// * `shapes` - Transformed shapes of the track layer
+#[derive(Debug)]
+pub struct TrackLayer {
+    pub from: egui::Rect,
+    pub to: egui::Rect,
+    pub show_zones: bool,
+    pub track_color: egui::Color32,
+    pub shapes: Vec<egui::Shape>,
+}
+
+impl TrackLayer {
+    /// is_valid checks if the cached shapes were created for the given view transform and
+    /// settings.
+    pub fn is_valid(
+        &self,
+        to_screen: &egui::emath::RectTransform,
+        show_zones: bool,
+        track_color: egui::Color32,
+    ) -> bool {
+        self.from == *to_screen.from()
+            && self.to == *to_screen.to()
+            && self.show_zones == show_zones
+            && self.track_color == track_color
+    }
+}
+
+/// DiffAction contains the actions of the result screen to run the scenario again and to manage
+/// the baseline result of the comparison.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiffAction {
+    RunAgain,
+    TogglePin,
+    ClearBaseline,
+}
+
 #[derive(Debug)]
 pub struct RaceInfo {
     pub tot_no_laps: u32,
+    pub session_start_h: Option<f64>,
+    pub session_duration_h: f64,
+    pub sunset_h: f64,
+}
+
+/// RacePlotOptions contains the options of the GUI that are not part of the race itself.
+/// * `tx_control` - Sender of the control commands to the simulation (e.g. pause, speed)
+/// * `realtime_factor` - Initial realtime factor of the simulation
+/// * `out_fmt` - Output format of the exported race result
+/// * `strategy_editor` - Strategy editor to run the scenario again with changed parameters
+#[derive(Debug)]
+pub struct RacePlotOptions {
+    pub tx_control: Option<Sender<SimControl>>,
+    pub realtime_factor: f64,
+    pub out_fmt: OutputFormat,
+    pub strategy_editor: Option<StrategyEditor>,
 }
 
 #[derive(Debug)]
@@ -31,11 +110,28 @@ pub struct RacePlot {
     pub race_info: RaceInfo,
     pub track: Track,
     pub centerline_cl: Vec<egui::Pos2>,
+    pub centerline_colors: Vec<egui::Color32>,
     pub prev_update: Instant,
     pub prev_update_durations: RingBuffer<u32>,
+    pub prev_prep_durations: RingBuffer<u32>,
+    pub track_layer: Option<TrackLayer>,
+    pub car_labels: HashMap<u32, (String, String)>,
     pub show_speed: bool,
     pub export_done: bool,
     pub export_path: Option<String>,
+    pub out_fmt: OutputFormat,
+    pub strategy_editor: Option<StrategyEditor>,
+    pub started_editor: Option<StrategyEditor>,
+    pub baseline_result: Option<RaceResult>,
+    pub baseline_pinned: bool,
+    pub diff_car: Option<u32>,
+    pub paused: bool,
+    pub realtime_factor: f64,
+    pub selected_car: Option<u32>,
+    pub follow_car: bool,
+    pub show_labels: bool,
+    pub show_zones: bool,
+    pub show_help: bool,
 }
 
 impl RacePlot {
@@ -44,16 +140,28 @@ impl RacePlot {
         race_pars: &RacePars,
         track_pars: &TrackPars,
         trackfile_path: &Path,
+        opts: RacePlotOptions,
     ) -> anyhow::Result<RacePlot> {
+        let RacePlotOptions {
+            tx_control,
+            realtime_factor,
+            out_fmt,
+            strategy_editor,
+        } = opts;
+
         // set up interface
         let racesim_interface = RacesimInterface {
             rx,
+            tx_control,
             race_state: Default::default(),
         };
 
         // get relevant race information
         let race_info = RaceInfo {
             tot_no_laps: race_pars.tot_no_laps,
+            session_start_h: race_pars.session_start_h,
+            session_duration_h: race_pars.session_duration_h,
+            sunset_h: race_pars.sunset_h,
         };
 
         // load track
@@ -79,21 +187,137 @@ impl RacePlot {
             })
         }
 
+        // shade the centerline by elevation (darker = lower) if the track file contains it
+        let mut centerline_colors = vec![];
+
+        if let Some((z_min, z_max)) = track.get_elevation_range() {
+            let z_range = (z_max - z_min).max(1e-6);
+
+            for track_el in track.track_cl.iter() {
+                let z_frac = (track_el.z.unwrap_or(z_min) - z_min) / z_range;
+                centerline_colors.push(egui::Color32::from_gray((80.0 + 175.0 * z_frac) as u8));
+            }
+        }
+
         // create race plot
         Ok(RacePlot {
             racesim_interface,
             race_info,
             track,
             centerline_cl,
+            centerline_colors,
             prev_update: Instant::now(),
             prev_update_durations: RingBuffer::new(10),
+            prev_prep_durations: RingBuffer::new(10),
+            track_layer: None,
+            car_labels: HashMap::new(),
             show_speed: false,
             export_done: false,
             export_path: None,
+            out_fmt,
+            strategy_editor,
+            started_editor: None,
+            baseline_result: None,
+            baseline_pinned: false,
+            diff_car: None,
+            paused: false,
+            realtime_factor,
+            selected_car: None,
+            follow_car: false,
+            show_labels: true,
+            show_zones: true,
+            show_help: false,
         })
     }
 
-    fn export_results_plot(&self, result: &racesim::post::race_result::RaceResult) -> anyhow::Result<String> {
+    /// handle_action executes an action triggered by a keyboard shortcut.
+    pub fn handle_action(&mut self, action: GuiAction) {
+        match action {
+            GuiAction::TogglePause => {
+                self.paused = !self.paused;
+                self.racesim_interface
+                    .send_control(SimControl::Pause(self.paused));
+            }
+            GuiAction::SpeedUp | GuiAction::SlowDown => {
+                self.realtime_factor = if action == GuiAction::SpeedUp {
+                    (self.realtime_factor * 2.0).min(64.0)
+                } else {
+                    (self.realtime_factor / 2.0).max(0.0625)
+                };
+                self.racesim_interface
+                    .send_control(SimControl::SetRealtimeFactor(self.realtime_factor));
+            }
+            GuiAction::SelectPosition(position) => {
+                let mut car_states: Vec<_> =
+                    self.racesim_interface.race_state.car_states.iter().collect();
+                car_states.sort_by(|a, b| {
+                    b.race_prog
+                        .partial_cmp(&a.race_prog)
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                });
+                if let Some(car_state) = car_states.get(position - 1) {
+                    self.selected_car = Some(car_state.car_no);
+                }
+            }
+            GuiAction::ToggleFollow => self.follow_car = !self.follow_car,
+            GuiAction::ToggleLabels => self.show_labels = !self.show_labels,
+            GuiAction::ToggleZones => self.show_zones = !self.show_zones,
+            GuiAction::ResetView => {
+                self.follow_car = false;
+                self.selected_car = None;
+            }
+            GuiAction::ToggleHelp => self.show_help = !self.show_help,
+        }
+    }
+
+    /// handle_diff_action executes an action of the result screen. When running the scenario again,
+    /// the current result becomes the baseline of the comparison unless the baseline is pinned.
+    fn handle_diff_action(&mut self, diff_action: DiffAction) {
+        match diff_action {
+            DiffAction::RunAgain => {
+                let started = match &self.started_editor {
+                    Some(editor) => editor.restart(),
+                    None => false,
+                };
+                if !started {
+                    self.export_path = Some(String::from("Symulator nie jest dostępny!"));
+                    return;
+                }
+
+                let result = self.racesim_interface.race_state.final_result.take();
+                if !self.baseline_pinned || self.baseline_result.is_none() {
+                    self.baseline_result = result;
+                }
+
+                self.racesim_interface.race_state = Default::default();
+                self.export_done = false;
+                self.export_path = None;
+                self.paused = false;
+                self.racesim_interface
+                    .send_control(SimControl::SetRealtimeFactor(self.realtime_factor));
+            }
+            DiffAction::TogglePin => self.baseline_pinned = !self.baseline_pinned,
+            DiffAction::ClearBaseline => {
+                self.baseline_result = None;
+                self.baseline_pinned = false;
+            }
+        }
+    }
+
+    /// get_selected_car_coords returns the track coordinates of the selected car.
+    fn get_selected_car_coords(&self) -> Option<(f64, f64)> {
+        let car_state = self
+            .racesim_interface
+            .race_state
+            .car_states
+            .iter()
+            .find(|car_state| Some(car_state.car_no) == self.selected_car)?;
+        let dists = self.track.get_dists_for_race_progs(&[car_state.race_prog]);
+        let coords = self.track.get_coords_for_dists(&dists);
+        coords.first().map(|coords| (coords.x, coords.y))
+    }
+
+    fn export_results_plot(&self, result: &RaceResult) -> anyhow::Result<String> {
         // Prepare output path
         let out_dir = std::path::Path::new("output");
         std::fs::create_dir_all(out_dir)?;
@@ -113,7 +337,7 @@ impl RacePlot {
         let track_len = self.track.track_cl.last().map(|el| el.s).unwrap_or(1.0);
         for (i, _) in result.car_driver_pairs.iter().enumerate() {
             for lap in 1..=tot_laps {
-                let lt = result.laptimes[i][lap];
+                let lt = result.get_laptime(i, lap);
                 if lt.is_finite() && lt > 0.0 {
                     let y = if use_speed { (track_len / lt) * 3.6 } else { lt };
                     if y < y_min { y_min = y; }
@@ -128,17 +352,19 @@ impl RacePlot {
         y_min -= margin;
         y_max += margin;
 
+        let strings = self.out_fmt.strings();
         let root = BitMapBackend::new(out_path.to_str().unwrap(), (1280, 720)).into_drawing_area();
         root.fill(&WHITE)?;
+        let lap_axis = get_lap_axis(result.get_no_laps_driven());
         let mut chart = ChartBuilder::on(&root)
             .caption(
-                if use_speed { "Średnia prędkość na okrążeniach" } else { "Czas okrążenia" },
+                if use_speed { strings.caption_speed } else { strings.caption_laptime },
                 ("sans-serif", 24).into_font(),
             )
             .margin(20)
             .x_label_area_size(40)
             .y_label_area_size(60)
-            .build_cartesian_2d(1u32..result.tot_no_laps, y_min..y_max)?;
+            .build_cartesian_2d(lap_axis.range.clone(), y_min..y_max)?;
 
         // Light-grey background bands for rainy laps
         if !result.weather_history.is_empty() {
@@ -155,38 +381,55 @@ impl RacePlot {
         }
 
         chart.configure_mesh()
-            .x_desc("Okrążenie")
+            .x_labels(lap_axis.no_labels)
+            .x_desc(strings.axis_lap)
             .y_desc(if use_speed { "km/h" } else { "s" })
             .label_style(("sans-serif", 16))
             .axis_desc_style(("sans-serif", 16))
             .draw()?;
 
-        // Color palette
-        let palette = Palette99::pick;
-
         // Draw series
         for (i, pair) in result.car_driver_pairs.iter().enumerate() {
+            // car colors are the same as in the race view and in the report
+            let [r, g, b] = result.get_car_color(i);
+            let color = RGBColor(r, g, b);
             let mut series: Vec<(u32, f64)> = Vec::new();
             for lap in 1..=tot_laps {
-                let lt = result.laptimes[i][lap];
+                let lt = result.get_laptime(i, lap);
                 if lt.is_finite() && lt > 0.0 {
                     let y = if use_speed { (track_len / lt) * 3.6 } else { lt };
                     series.push((lap as u32, y));
                 }
             }
-            chart.draw_series(LineSeries::new(series.into_iter(), palette(i)))?
+            // a single lap cannot be drawn as a line
+            if series.len() == 1 {
+                chart.draw_series(
+                    series.iter().map(|&point| Circle::new(point, 4, color.filled())),
+                )?;
+            }
+            chart.draw_series(LineSeries::new(series.into_iter(), color))?
                 .label(format!("{} ({})", pair.car_no, pair.driver_initials))
-                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], palette(i)));
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
         }
 
         // Event markers
-        // Weather: gray, SC: orange, Crash: red
+        // Weather: gray, SC: orange, Crash: red, Debris: yellow
         for ev in &result.events {
             let x = ev.lap as u32;
             let (color, width) = match ev.kind.as_str() {
                 "WeatherRainStart" | "WeatherDryStart" => (RGBColor(150, 150, 150), 1),
-                "SC_DEPLOYED" | "SC_IN" => (RGBColor(255, 165, 0), 1),
-                "Crash" | "EngineFailure" => (RED, 2),
+                "SC_DEPLOYED" | "SC_IN" | "VSC_DEPLOYED" | "VSC_ENDING" | "RESTART"
+                | "START_BEHIND_SC" => {
+                    (RGBColor(255, 165, 0), 1)
+                }
+                "RED_FLAG" => (RED, 3),
+                "TIME_LIMIT_REACHED" => (BLACK, 3),
+                kind if is_retirement_event_kind(kind) => (RED, 2),
+                "DEBRIS_START" | "YELLOW_START" => (RGBColor(230, 200, 0), 1),
+                "DAMAGE" | "DAMAGE_REPAIRED" | "PUNCTURE" => (RGBColor(160, 60, 160), 1),
+                "DrsTrain" | "DrsTrainEnd" | "Contact" | "DEBRIS_END" | "YELLOW_END" | "StartReaction"
+                | "SC_DECISION" | "PIT_CLOSED" | "PIT_UNDER_SC"
+                | "FUEL_WARNING" => continue,
                 _ => (BLACK, 1),
             };
             chart.draw_series(std::iter::once(PathElement::new(
@@ -205,14 +448,229 @@ impl RacePlot {
         Ok(out_path.to_string_lossy().into_owned())
     }
 
+    /// get_time_of_day returns the local time of day (h) of the current race state (None if the
+    /// session start is not set).
+    fn get_time_of_day(&self) -> Option<f64> {
+        self.race_info.session_start_h.map(|session_start_h| {
+            get_time_of_day(session_start_h, self.racesim_interface.race_state.cur_racetime_s)
+        })
+    }
+
+    /// get_ambience returns the colors of the race screen at the current time of day.
+    fn get_ambience(&self) -> Ambience {
+        get_ambience(self.get_time_of_day(), self.race_info.sunset_h)
+    }
+
+    /// get_track_shapes creates the shapes of the static track layer, i.e. the centerline (shaded
+    /// by elevation if available) and the pit and corner zones (if shown).
+    fn get_track_shapes(
+        &self,
+        to_screen: &egui::emath::RectTransform,
+        track_color: egui::Color32,
+    ) -> Vec<egui::Shape> {
+        let mut shapes = vec![];
+
+        // add track centerline
+        let centerline_cl_tmp: Vec<egui::Pos2> =
+            self.centerline_cl.iter().map(|p| *to_screen * *p).collect();
+
+        if self.centerline_colors.is_empty() {
+            shapes.push(egui::Shape::line(
+                centerline_cl_tmp,
+                egui::Stroke::new(3.0, track_color),
+            ));
+        } else {
+            for i in 0..centerline_cl_tmp.len().saturating_sub(1) {
+                shapes.push(egui::Shape::line_segment(
+                    [centerline_cl_tmp[i], centerline_cl_tmp[i + 1]],
+                    egui::Stroke::new(3.0, self.centerline_colors[i]),
+                ));
+            }
+        }
+
+        // add zones
+        let zones = if self.show_zones { self.track.get_zones() } else { vec![] };
+
+        for zone in zones.iter() {
+            let tmp_centerline: Vec<egui::Pos2> = zone
+                .centerline
+                .iter()
+                .map(|coords| egui::Pos2 {
+                    x: coords.x as f32,
+                    y: coords.y as f32,
+                })
+                .collect();
+            let tmp_color = if matches!(zone.zone_type, ZoneType::PitZone) {
+                // pit zone -> orange
+                egui::Color32::from_rgb(255, 128, 0)
+            } else {
+                // overtaking zone -> blue
+                //egui::Color32::from_rgb(0, 128, 255)
+                continue; // temporarily disable overtaking zones drawing
+            };
+
+            shapes.push(egui::Shape::line(
+                tmp_centerline.iter().map(|&x| *to_screen * x).collect(),
+                egui::Stroke::new(7.0, tmp_color),
+            ));
+        }
+
+        // add corner zones
+        let corner_zones = if self.show_zones { self.track.get_corner_zones() } else { vec![] };
+        for zone in corner_zones.iter() {
+            let tmp_centerline: Vec<egui::Pos2> = zone
+                .centerline
+                .iter()
+                .map(|coords| egui::Pos2 {
+                    x: coords.x as f32,
+                    y: coords.y as f32,
+                })
+                .collect();
+            
+            // corners -> blue
+            let tmp_color = egui::Color32::from_rgb(0, 128, 255);
+
+            shapes.push(egui::Shape::line(
+                tmp_centerline.iter().map(|&x| *to_screen * x).collect(),
+                egui::Stroke::new(7.0, tmp_color),
+            ));
+        }
+
+        shapes
+    }
+
+    /// show_position_strip draws the race progress of every car as a thin bar in the car color
+    /// (ordered by position) with ticks at the completed pit stops and a cursor at the progress
+    /// of the leader.
+    fn show_position_strip(&self, ui: &mut egui::Ui) {
+        let car_states = &self.racesim_interface.race_state.car_states;
+        if car_states.is_empty() {
+            return;
+        }
+
+        let no_cars = car_states.len() as f32;
+        let row_height = POSITION_STRIP_MAX_ROW_HEIGHT.min(POSITION_STRIP_HEIGHT / no_cars);
+        let show_labels = row_height >= POSITION_STRIP_MIN_LABEL_HEIGHT;
+
+        let (response, painter) = ui.allocate_painter(
+            egui::Vec2::new(ui.available_width(), row_height * no_cars),
+            egui::Sense::hover(),
+        );
+        let rect = response.rect;
+
+        // x axis from 0 to the total number of laps (right of the labels)
+        let x_start = rect.min.x + if show_labels { POSITION_STRIP_LABEL_WIDTH } else { 0.0 };
+        let width = (rect.max.x - x_start).max(1.0);
+        let tot_no_laps = self.race_info.tot_no_laps.max(1) as f64;
+        let to_x =
+            |race_prog: f64| x_start + width * (race_prog / tot_no_laps).clamp(0.0, 1.0) as f32;
+
+        // order cars by position, retired cars below the running cars
+        let mut car_order: Vec<&_> = car_states.iter().collect();
+        car_order.sort_by(|a, b| {
+            a.is_dnf.cmp(&b.is_dnf).then(
+                b.race_prog
+                    .partial_cmp(&a.race_prog)
+                    .unwrap_or(std::cmp::Ordering::Equal),
+            )
+        });
+
+        let ambience = self.get_ambience();
+        let mut shapes = vec![];
+
+        for (row, car_state) in car_order.iter().enumerate() {
+            let y_top = rect.min.y + row as f32 * row_height;
+            let color = if car_state.is_dnf {
+                egui::Color32::GRAY
+            } else {
+                let color = &car_state.color;
+                lift_color(egui::Color32::from_rgb(color.r, color.g, color.b), ambience.label_lift)
+            };
+
+            // progress bar (leave a small gap between the rows)
+            shapes.push(egui::Shape::rect_filled(
+                egui::Rect::from_min_max(
+                    egui::Pos2::new(x_start, y_top + 1.0),
+                    egui::Pos2::new(to_x(car_state.race_prog), y_top + row_height - 1.0),
+                ),
+                0.0,
+                color,
+            ));
+
+            // pit stop ticks at the end of the inlaps
+            for &lap in car_state.pit_stop_laps.iter() {
+                let x = to_x(lap as f64);
+                shapes.push(egui::Shape::line_segment(
+                    [egui::Pos2::new(x, y_top), egui::Pos2::new(x, y_top + row_height)],
+                    egui::Stroke::new(2.0, ambience.text),
+                ));
+            }
+
+            if show_labels {
+                shapes.push(egui::Shape::text(
+                    ui.fonts(),
+                    egui::Pos2::new(rect.min.x, y_top + 0.5 * row_height),
+                    egui::Align2::LEFT_CENTER,
+                    car_state.car_no,
+                    egui::TextStyle::Small,
+                    ambience.text,
+                ));
+
+                // live interval to the car ahead
+                if let Some(interval_ahead_s) = car_state.interval_ahead_s {
+                    shapes.push(egui::Shape::text(
+                        ui.fonts(),
+                        egui::Pos2::new(rect.max.x, y_top + 0.5 * row_height),
+                        egui::Align2::RIGHT_CENTER,
+                        format!("+{:.1}s", interval_ahead_s),
+                        egui::TextStyle::Small,
+                        ambience.text,
+                    ));
+                }
+            }
+
+            // strike through the row of a retired car
+            if car_state.is_dnf {
+                let y_mid = y_top + 0.5 * row_height;
+                shapes.push(egui::Shape::line_segment(
+                    [egui::Pos2::new(rect.min.x, y_mid), egui::Pos2::new(rect.max.x, y_mid)],
+                    egui::Stroke::new(1.0, ambience.text),
+                ));
+            }
+        }
+
+        // cursor at the progress of the leader
+        let x_leader = to_x(car_order[0].race_prog);
+        shapes.push(egui::Shape::line_segment(
+            [egui::Pos2::new(x_leader, rect.min.y), egui::Pos2::new(x_leader, rect.max.y)],
+            egui::Stroke::new(1.0, ambience.text),
+        ));
+
+        painter.extend(shapes);
+    }
+
     pub fn set_ui_content(&mut self, ui: &mut egui::Ui) -> egui::Response {
         // PREPARATIONS ----------------------------------------------------------------------------
+        let t_prep_start = Instant::now();
+
         // get UI handles
         let (response, painter) =
             ui.allocate_painter(ui.available_size_before_wrap_finite(), egui::Sense::drag());
 
         // get transformation from x/y to pixels in the window (y axis must be inverted)
-        let [x_min, x_max, y_min, y_max] = self.track.get_axes_expansion(50.0);
+        let [mut x_min, mut x_max, mut y_min, mut y_max] = self.track.get_axes_expansion(50.0);
+
+        // follow camera: zoomed view centered on the selected car
+        if self.follow_car {
+            if let Some((x_car, y_car)) = self.get_selected_car_coords() {
+                let half_width = (x_max - x_min) / 8.0;
+                let half_height = (y_max - y_min) / 8.0;
+                x_min = x_car - half_width;
+                x_max = x_car + half_width;
+                y_min = y_car - half_height;
+                y_max = y_car + half_height;
+            }
+        }
 
         // Calculate aspect ratios to preserve geometry
         let track_width = (x_max - x_min).abs() as f32;
@@ -259,63 +717,70 @@ impl RacePlot {
 
         // create vector for drawn shapes
         let mut shapes = vec![];
+        let ambience = self.get_ambience();
 
         // TRACK DRAWING ---------------------------------------------------------------------------
-        // add track centerline
-        let centerline_cl_tmp: Vec<egui::Pos2> =
-            self.centerline_cl.iter().map(|p| to_screen * *p).collect();
-
-        shapes.push(egui::Shape::line(
-            centerline_cl_tmp,
-            egui::Stroke::new(3.0, egui::Color32::WHITE),
-        ));
+        // the static track layer (centerline and zones) is only rebuilt if the view transform, the
+        // zone toggle or the track color (time of day) changed
+        let track_layer_valid = matches!(
+            &self.track_layer,
+            Some(layer) if layer.is_valid(&to_screen, self.show_zones, ambience.track)
+        );
+        if !track_layer_valid {
+            self.track_layer = Some(TrackLayer {
+                from: *to_screen.from(),
+                to: *to_screen.to(),
+                show_zones: self.show_zones,
+                track_color: ambience.track,
+                shapes: self.get_track_shapes(&to_screen, ambience.track),
+            });
+        }
+        if let Some(layer) = &self.track_layer {
+            shapes.extend(layer.shapes.iter().cloned());
+        }
 
-        // add zones
-        let zones = self.track.get_zones();
+        // add local yellow flags (debris -> yellow, stopped car -> amber), independent of the zone
+        // toggle
+        let race_state = &self.racesim_interface.race_state;
 
-        for zone in zones.iter() {
+        // color the sectors by their flag (yellow flag in a single sector, neutralizations on the
+        // whole track), the local yellow flags are drawn on top
+        for (zone, flag_state) in self
+            .track
+            .get_sector_zones()
+            .iter()
+            .zip(race_state.sector_flag_states.iter())
+        {
+            let color = match flag_state {
+                FlagState::Y => egui::Color32::YELLOW,
+                FlagState::Vsc | FlagState::Sc => egui::Color32::from_rgb(255, 200, 0),
+                FlagState::Red => egui::Color32::RED,
+                FlagState::G | FlagState::C => continue,
+            };
             let tmp_centerline: Vec<egui::Pos2> = zone
                 .centerline
                 .iter()
-                .map(|coords| egui::Pos2 {
-                    x: coords.x as f32,
-                    y: coords.y as f32,
-                })
+                .map(|coords| to_screen * egui::Pos2::new(coords.x as f32, coords.y as f32))
                 .collect();
-            let tmp_color = if matches!(zone.zone_type, ZoneType::PitZone) {
-                // pit zone -> orange
-                egui::Color32::from_rgb(255, 128, 0)
-            } else {
-                // overtaking zone -> blue
-                //egui::Color32::from_rgb(0, 128, 255)
-                continue; // temporarily disable overtaking zones drawing
-            };
 
-            shapes.push(egui::Shape::line(
-                tmp_centerline.iter().map(|&x| to_screen * x).collect(),
-                egui::Stroke::new(7.0, tmp_color),
-            ));
+            shapes.push(egui::Shape::line(tmp_centerline, egui::Stroke::new(5.0, color)));
         }
 
-        // add corner zones
-        let corner_zones = self.track.get_corner_zones();
-        for zone in corner_zones.iter() {
+        let mut yellow_zones: Vec<(Zone, egui::Color32)> = Vec::new();
+        for zone in self.track.get_debris_zones(&race_state.debris_zones) {
+            yellow_zones.push((zone, egui::Color32::YELLOW));
+        }
+        for zone in self.track.get_yellow_zones(&race_state.yellow_zones) {
+            yellow_zones.push((zone, egui::Color32::from_rgb(255, 170, 0)));
+        }
+        for (zone, color) in yellow_zones.iter() {
             let tmp_centerline: Vec<egui::Pos2> = zone
                 .centerline
                 .iter()
-                .map(|coords| egui::Pos2 {
-                    x: coords.x as f32,
-                    y: coords.y as f32,
-                })
+                .map(|coords| to_screen * egui::Pos2::new(coords.x as f32, coords.y as f32))
                 .collect();
-            
-            // corners -> blue
-            let tmp_color = egui::Color32::from_rgb(0, 128, 255);
 
-            shapes.push(egui::Shape::line(
-                tmp_centerline.iter().map(|&x| to_screen * x).collect(),
-                egui::Stroke::new(7.0, tmp_color),
-            ));
+            shapes.push(egui::Shape::line(tmp_centerline, egui::Stroke::new(7.0, *color)));
         }
 
         /*
@@ -403,12 +868,17 @@ impl RacePlot {
                     egui::Color32::RED,
                 ));
 
-                // 5. Dodaj podpis "SC"
+                // 5. Dodaj podpis "SC" ("SC IN", gdy SC jedzie już do wjazdu do alei)
+                let sc_label = if self.racesim_interface.race_state.sc_in_this_lap {
+                    "SC IN"
+                } else {
+                    "SC"
+                };
                 shapes.push(egui::Shape::text(
                     ui.fonts(),
                     sc_pos_screen + egui::Vec2::new(15.0, -20.0), // Przesunięcie tekstu
                     egui::Align2::LEFT_BOTTOM,
-                    "SC",
+                    sc_label,
                     egui::TextStyle::Heading, // Większa czcionka
                     egui::Color32::RED,
                 ));
@@ -480,13 +950,27 @@ impl RacePlot {
                 .as_vector2d()
                 .add(&(tmp_normvecs[i].mult(text_offset).mult(tmp_sign)))
                 .as_point2d();
-            let tmp_text = format!("{} ({})", car_state.car_no, car_state.driver_initials);
+
+            // labels are only formatted again if the driver of the car changed
+            let label = self
+                .car_labels
+                .entry(car_state.car_no)
+                .or_insert_with(|| (String::new(), String::new()));
+
+            if label.0 != car_state.driver_initials || label.1.is_empty() {
+                label.0 = car_state.driver_initials.to_owned();
+                label.1 = format!("{} ({})", car_state.car_no, car_state.driver_initials);
+            }
+            let tmp_text = label.1.clone();
 
             let car_state_gui = CarStateGui {
-                color: egui::Color32::from_rgb(
-                    car_state.color.r,
-                    car_state.color.g,
-                    car_state.color.b,
+                color: lift_color(
+                    egui::Color32::from_rgb(
+                        car_state.color.r,
+                        car_state.color.g,
+                        car_state.color.b,
+                    ),
+                    ambience.label_lift,
                 ),
                 pos: egui::Pos2 {
                     x: tmp_coords[i].x as f32,
@@ -502,14 +986,97 @@ impl RacePlot {
             car_states_gui.push(car_state_gui);
         }
 
+        // link the DRS indicators of the cars in a DRS train
+        let drs_color = egui::Color32::from_rgb(0, 200, 80);
+
+        for drs_train in self.racesim_interface.race_state.drs_trains.iter() {
+            let train_pos: Vec<egui::Pos2> = drs_train
+                .iter()
+                .filter_map(|car_no| {
+                    self.racesim_interface
+                        .race_state
+                        .car_states
+                        .iter()
+                        .position(|car_state| car_state.car_no == *car_no)
+                })
+                .map(|i| to_screen * car_states_gui[i].pos)
+                .collect();
+
+            for pos_pair in train_pos.windows(2) {
+                shapes.push(egui::Shape::line_segment(
+                    [pos_pair[0], pos_pair[1]],
+                    egui::Stroke::new(3.0, drs_color),
+                ));
+            }
+        }
+
         // add car points
-        for car_state_gui in car_states_gui.iter() {
+        for (car_state, car_state_gui) in self
+            .racesim_interface
+            .race_state
+            .car_states
+            .iter()
+            .zip(car_states_gui.iter())
+        {
+            // retired cars fade out at their stopping position and remain as a grey cross
+            if car_state.is_dnf {
+                let t_retired = car_state.t_retired_s.unwrap_or(race_state.cur_racetime_s);
+                let fade = 1.0 - (race_state.cur_racetime_s - t_retired) / RETIRED_FADE_DURATION_S;
+
+                if fade > 0.0 {
+                    let color = &car_state.color;
+                    shapes.push(egui::Shape::circle_filled(
+                        to_screen * car_state_gui.pos,
+                        7.0,
+                        egui::Color32::from_rgba_unmultiplied(
+                            color.r,
+                            color.g,
+                            color.b,
+                            (fade * 255.0) as u8,
+                        ),
+                    ));
+                } else {
+                    let pos = to_screen * car_state_gui.pos;
+                    let stroke = egui::Stroke::new(2.0, egui::Color32::GRAY);
+                    for dir in [egui::Vec2::new(1.0, 1.0), egui::Vec2::new(1.0, -1.0)].iter() {
+                        let offset = *dir * RETIRED_CROSS_HALF_SIZE;
+                        shapes.push(egui::Shape::line_segment(
+                            [pos - offset, pos + offset],
+                            stroke,
+                        ));
+                    }
+                }
+                continue;
+            }
+
             shapes.push(egui::Shape::circle_filled(
                 to_screen * car_state_gui.pos,
                 7.0,
                 car_state_gui.color,
             ));
 
+            // DRS indicator
+            if car_state.drs_act {
+                shapes.push(egui::Shape::circle_stroke(
+                    to_screen * car_state_gui.pos,
+                    9.0,
+                    egui::Stroke::new(2.0, drs_color),
+                ));
+            }
+
+            // mark the selected car
+            if Some(car_state.car_no) == self.selected_car {
+                shapes.push(egui::Shape::circle_stroke(
+                    to_screen * car_state_gui.pos,
+                    12.0,
+                    egui::Stroke::new(2.0, egui::Color32::WHITE),
+                ));
+            }
+
+            if !self.show_labels {
+                continue;
+            }
+
             shapes.push(egui::Shape::text(
                 ui.fonts(),
                 to_screen * car_state_gui.text_pos,
@@ -530,12 +1097,52 @@ impl RacePlot {
             .map(|car_state| car_state.race_prog)
             .collect();
         let cur_lap_leader = max(&race_progs).trunc() as u32 + 1;
-        let mut gen_info_text = format!("Lap: {}/{}\n", cur_lap_leader, self.race_info.tot_no_laps);
+        let mut gen_info_text = if self.racesim_interface.race_state.formation_lap_act {
+            format!("Lap: FORMATION/{}\n", self.race_info.tot_no_laps)
+        } else {
+            format!("Lap: {}/{}\n", cur_lap_leader, self.race_info.tot_no_laps)
+        };
+
+        // add simulation speed
+        if self.paused {
+            gen_info_text.push_str("PAUZA\n");
+        }
+        writeln!(&mut gen_info_text, "Speed: {}x (H - pomoc)", self.realtime_factor).unwrap();
+
+        // add incident clearance progress while the SC is out
+        if let Some(clearance_prog) = self.racesim_interface.race_state.sc_clearance_prog {
+            writeln!(&mut gen_info_text, "Incident clearance: {:.0}%", clearance_prog * 100.0).unwrap();
+        }
+
+        // add time of day and track temperature if the session start is set
+        if let (Some(time_of_day), Some(session_start_h)) =
+            (self.get_time_of_day(), self.race_info.session_start_h)
+        {
+            writeln!(
+                &mut gen_info_text,
+                "Local time: {} (session until {})",
+                format_time_of_day(time_of_day),
+                format_time_of_day(session_start_h + self.race_info.session_duration_h)
+            )
+            .unwrap();
+        }
+        if let Some(track_temp) = self.racesim_interface.race_state.track_temp_c {
+            writeln!(&mut gen_info_text, "Track: {:.1} °C", track_temp).unwrap();
+        }
+        if self.racesim_interface.race_state.formation_lap_act {
+            writeln!(&mut gen_info_text, "FORMATION").unwrap();
+        }
+        if matches!(self.racesim_interface.race_state.flag_state, FlagState::Red) {
+            writeln!(&mut gen_info_text, "RED FLAG - RACE SUSPENDED").unwrap();
+        }
+        if matches!(self.racesim_interface.race_state.flag_state, FlagState::Vsc) {
+            writeln!(&mut gen_info_text, "VIRTUAL SAFETY CAR").unwrap();
+        }
 
         // Add velocities
         gen_info_text.push_str("\nVelocities:\n");
         for car_state in self.racesim_interface.race_state.car_states.iter() {
-             writeln!(&mut gen_info_text, "{} ({}): {:.1} km/h", car_state.car_no, car_state.driver_initials, car_state.velocity * 3.6).unwrap();
+             writeln!(&mut gen_info_text, "{} ({}): {:.1} km/h, {} {}, {:.1} s, ERS {:.0}%{}{}", car_state.car_no, car_state.driver_initials, car_state.velocity * 3.6, car_state.no_pitstops, if car_state.no_pitstops == 1 { "stop" } else { "stops" }, car_state.t_pitlane, car_state.ers_soc * 100.0, if car_state.fuel_save_act { ", FUEL SAVE" } else { "" }, if car_state.overheat_act { ", OVERHEATING" } else { "" }).unwrap();
         }
 
         // add flag state
@@ -551,6 +1158,12 @@ impl RacePlot {
             .push(self.prev_update.elapsed().as_millis() as u32);
         self.prev_update = Instant::now();
 
+        // add the average frame preparation duration of the previous frames (shows the effect of
+        // the cached track layer and labels)
+        if let Some(t_prep_avg) = self.prev_prep_durations.get_avg() {
+            writeln!(&mut gen_info_text, "\nFrame preparation: {:.0} us", t_prep_avg).unwrap();
+        }
+
         // add update frequency
         // write!(
         //     &mut gen_info_text,
@@ -570,19 +1183,183 @@ impl RacePlot {
             egui::Align2::LEFT_TOP,
             &gen_info_text,
             egui::TextStyle::Body,
-            egui::Color32::WHITE,
+            ambience.text,
         ));
 
         // DRAWING ---------------------------------------------------------------------------------
         // update shapes in UI painter and return response
+        // append the frame preparation duration (in us) to the buffer
+        self.prev_prep_durations.push(t_prep_start.elapsed().as_micros() as u32);
+
         painter.extend(shapes);
         response
     }
 }
 
+/// show_result_diff shows the differences between the baseline and the current result: position
+/// and race time deltas of all cars, the differing stochastic events and the lap times of a
+/// selected car in both runs.
+fn show_result_diff(
+    ui: &mut egui::Ui,
+    baseline: &RaceResult,
+    result: &RaceResult,
+    diff_car: &mut Option<u32>,
+    out_fmt: &OutputFormat,
+) {
+    let result_diff = ResultDiff::new(baseline, result);
+    ui.monospace(result_diff.format(out_fmt).unwrap_or_default());
+
+    if result_diff.get_differing_events().is_empty() {
+        ui.label("SC i pogoda bez różnic");
+    }
+
+    // lap times of the selected car in both runs
+    let car_no = match diff_car.or_else(|| result_diff.car_diffs.first().map(|x| x.car_no)) {
+        Some(x) => x,
+        None => return,
+    };
+    *diff_car = Some(car_no);
+
+    egui::ComboBox::from_id_source("result_diff_car")
+        .selected_text(format!("#{}", car_no))
+        .show_ui(ui, |ui| {
+            for car_diff in result_diff.car_diffs.iter() {
+                ui.selectable_value(
+                    diff_car,
+                    Some(car_diff.car_no),
+                    format!("#{} ({})", car_diff.car_no, car_diff.driver_initials),
+                );
+            }
+        });
+
+    let laptime_pairs = ResultDiff::get_laptime_pairs(baseline, result, car_no);
+    let get_line = |laptimes: Vec<Value>, name: &str, color: egui::Color32| {
+        Line::new(Values::from_values(laptimes)).name(name).color(color)
+    };
+    let laptimes_prev = laptime_pairs
+        .iter()
+        .filter(|x| x.1.is_finite())
+        .map(|x| Value::new(x.0 as f64, x.1))
+        .collect();
+    let laptimes_cur = laptime_pairs
+        .iter()
+        .filter(|x| x.2.is_finite())
+        .map(|x| Value::new(x.0 as f64, x.2))
+        .collect();
+
+    ui.add(
+        Plot::new("result_diff_laptimes")
+            .line(get_line(laptimes_prev, "baza", egui::Color32::GRAY))
+            .line(get_line(laptimes_cur, "bieżący", egui::Color32::LIGHT_BLUE))
+            .legend(Legend::default())
+            .height(200.0),
+    );
+}
+
+/// show_retirements shows the retirements section (live side panel and results screen): the
+/// retired cars (car, driver, lap and reason) in order of retirement.
+fn show_retirements(ui: &mut egui::Ui, retirements: &[RetiredCar]) {
+    ui.heading("Wycofania");
+    egui::Grid::new("retirements").show(ui, |ui| {
+        for retired_car in retirements.iter() {
+            ui.label(format!("#{}", retired_car.car_no));
+            ui.label(retired_car.driver_initials.as_str());
+            ui.label(format!("Lap {}", retired_car.lap));
+            ui.label(retired_car.reason.as_str());
+            ui.end_row();
+        }
+    });
+}
+
+/// show_practice shows the lap time breakdown of the practicing car (live side panel and results
+/// screen): the lap time, the theoretical lap time and its components of every completed lap.
+fn show_practice(ui: &mut egui::Ui, practice: &PracticeResult, out_fmt: &OutputFormat) {
+    ui.heading(format!(
+        "Trening #{} ({}), rozbicie czasów okrążeń (s)",
+        practice.car_no, practice.driver_initials
+    ));
+    egui::Grid::new("practice").show(ui, |ui| {
+        for title in BREAKDOWN_HEADER.iter() {
+            ui.label(*title);
+        }
+        ui.end_row();
+
+        for lap in practice.laps.iter() {
+            ui.label(format!("{}", lap.lap));
+            ui.label(lap.compound.as_str());
+            for value in PracticeResult::get_breakdown_values(lap).iter() {
+                ui.monospace(out_fmt.fmt_num(*value, 0, 3));
+            }
+            ui.end_row();
+        }
+    });
+}
+
+/// show_events lists the race events and shows the running order before and after every
+/// significant event as small tables.
+fn show_events(ui: &mut egui::Ui, result: &RaceResult, out_fmt: &OutputFormat) {
+    egui::ScrollArea::auto_sized().show(ui, |ui| {
+        for (i, ev) in result.events.iter().enumerate() {
+            ui.label(format!(
+                "Okr. {}, t = {} s: {} {:?}{}",
+                ev.lap,
+                out_fmt.fmt_num(ev.time_s, 0, 1),
+                ev.kind,
+                ev.cars,
+                if ev.scripted { " (wymuszone)" } else { "" }
+            ));
+
+            let context = match &ev.context {
+                Some(x) => x,
+                None => continue,
+            };
+
+            ui.horizontal(|ui| {
+                for (j, snapshot) in [&context.before, &context.after].iter().enumerate() {
+                    let snapshot = match snapshot {
+                        Some(x) => x,
+                        None => continue,
+                    };
+
+                    ui.vertical(|ui| {
+                        ui.label(format!("Po okr. {}", snapshot.lap));
+                        egui::Grid::new(format!("event_context_{}_{}", i, j)).show(ui, |ui| {
+                            for (pos, (car_no, gap)) in
+                                snapshot.car_nos.iter().zip(snapshot.gaps.iter()).enumerate()
+                            {
+                                ui.label(format!("P{}", pos + 1));
+                                ui.label(format!("#{}", car_no));
+                                ui.label(format!("+{} s", out_fmt.fmt_num(*gap, 0, 3)));
+                                ui.end_row();
+                            }
+                        });
+                    });
+
+                    if j == 0 {
+                        ui.label("→");
+                    }
+                }
+            });
+        }
+    });
+}
+
 impl epi::App for RacePlot {
     /// Called each time the UI needs repainting, which may be many times per second.
     fn update(&mut self, ctx: &egui::CtxRef, _frame: &mut epi::Frame) {
+        // pre-race screen: edit strategies until the race is started
+        if let Some(strategy_editor) = &mut self.strategy_editor {
+            let mut started = false;
+            egui::CentralPanel::default().show(ctx, |ui| {
+                started = strategy_editor.show(ui);
+            });
+            if started {
+                // the editor is kept to run the same scenario again
+                self.started_editor = self.strategy_editor.take();
+            }
+            return;
+        }
+
         // update race interface
         self.racesim_interface.update();
 
@@ -600,6 +1377,9 @@ impl epi::App for RacePlot {
                     }
                 }
             }
+            let mut diff_action: Option<DiffAction> = None;
+            let mut diff_car = self.diff_car;
+
             egui::CentralPanel::default().show(ctx, |ui| {
                 egui::Frame::dark_canvas(ui.style()).show(ui, |ui| {
                     if let Some(path) = &self.export_path {
@@ -608,21 +1388,104 @@ impl epi::App for RacePlot {
                     } else {
                         ui.heading("Kończenie wyścigu...");
                     }
+
+                    ui.horizontal(|ui| {
+                        if self.started_editor.is_some() && ui.button("Uruchom ponownie").clicked()
+                        {
+                            diff_action = Some(DiffAction::RunAgain);
+                        }
+                        if let Some(baseline) = &self.baseline_result {
+                            let pin_text = if self.baseline_pinned {
+                                "Odepnij bazę"
+                            } else {
+                                "Przypnij bazę"
+                            };
+                            if ui.button(pin_text).clicked() {
+                                diff_action = Some(DiffAction::TogglePin);
+                            }
+                            if ui.button("Wyczyść bazę").clicked() {
+                                diff_action = Some(DiffAction::ClearBaseline);
+                            }
+                            ui.label(format!(
+                                "Baza: {} okr.{}",
+                                baseline.tot_no_laps,
+                                if self.baseline_pinned { " (przypięta)" } else { "" }
+                            ));
+                        }
+                    });
+
+                    ui.separator();
+                    ui.heading("Klasyfikacja");
+                    ui.monospace(result.format_classification(&self.out_fmt).unwrap_or_default());
+
+                    if let Some(practice) = &self.racesim_interface.race_state.practice {
+                        ui.separator();
+                        show_practice(ui, practice, &self.out_fmt);
+                    }
+
+                    if let Some(baseline) = &self.baseline_result {
+                        ui.separator();
+                        ui.heading("Porównanie z bazą");
+                        show_result_diff(ui, baseline, result, &mut diff_car, &self.out_fmt);
+                    }
+
+                    let retirements = &self.racesim_interface.race_state.retirements;
+                    if !retirements.is_empty() {
+                        ui.separator();
+                        show_retirements(ui, retirements);
+                    }
+
+                    ui.separator();
+                    ui.heading("Zdarzenia");
+                    show_events(ui, result, &self.out_fmt);
                 });
             });
+
+            self.diff_car = diff_car;
+            if let Some(diff_action) = diff_action {
+                self.handle_diff_action(diff_action);
+            }
         } else {
             // update UI content (live track)
+            // keyboard shortcuts are handled before the painter work
+            for action in read_actions(ctx) {
+                self.handle_action(action);
+            }
+
+            egui::TopBottomPanel::bottom("position_strip").show(ctx, |ui| {
+                self.show_position_strip(ui);
+            });
+
+            let retirements = &self.racesim_interface.race_state.retirements;
+            if !retirements.is_empty() {
+                egui::SidePanel::right("retirements").show(ctx, |ui| {
+                    show_retirements(ui, retirements);
+                });
+            }
+
+            if let Some(practice) = &self.racesim_interface.race_state.practice {
+                let out_fmt = &self.out_fmt;
+                egui::SidePanel::right("practice").show(ctx, |ui| {
+                    show_practice(ui, practice, out_fmt);
+                });
+            }
+
             egui::CentralPanel::default().show(ctx, |ui| {
                 let mut frame = egui::Frame::dark_canvas(ui.style());
+                let ambience = self.get_ambience();
                 if self.racesim_interface.race_state.weather_is_rain {
-                    frame.fill = egui::Color32::from_gray(60);
+                    frame.fill = ambience.canvas_rain;
                 } else {
-                    frame.fill = egui::Color32::from_rgb(20, 80, 20);
+                    frame.fill = ambience.canvas;
                 }
                 frame.show(ui, |ui| {
                     self.set_ui_content(ui);
                 });
             });
+
+            if self.show_help {
+                show_help_overlay(ctx);
+            }
         }
 
         // request repaint of the UI
@@ -1,2 +1,6 @@
+pub mod ambience;
 pub mod gui;
+pub mod render;
+pub mod shortcuts;
+pub mod strategy_editor;
 pub mod track;
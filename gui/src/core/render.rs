@@ -0,0 +1,156 @@
+use crate::core::track::Track;
+use plotters::prelude::*;
+use racesim::post::race_recording::RaceRecording;
+use std::path::Path;
+
+/// Maximum number of frames written to an animation. Longer races are downsampled to this number
+/// of frames to keep the output size reasonable.
+pub const MAX_RENDER_FRAMES: usize = 3000;
+
+const RENDER_SIZE_PX: u32 = 720;
+
+/// load_render_track loads the track geometry used for rendering a recording.
+pub fn load_render_track(recording: &RaceRecording, trackfile_path: &Path) -> anyhow::Result<Track> {
+    let track_pars = &recording.track_pars;
+
+    Track::from_csv(
+        trackfile_path,
+        track_pars.length,
+        track_pars.s12,
+        track_pars.s23,
+        track_pars.drs_measurement_points.to_owned(),
+        track_pars.pit_zone,
+        track_pars.overtaking_zones.to_owned(),
+        track_pars.corners.to_owned(),
+    )
+}
+
+/// get_banner returns the text and color of the flag banner shown for a flag state.
+fn get_banner(flag_state: &str) -> (&'static str, RGBColor) {
+    match flag_state {
+        "Sc" => ("SAFETY CAR", RGBColor(255, 165, 0)),
+        "Vsc" => ("VIRTUAL SAFETY CAR", RGBColor(255, 165, 0)),
+        "Y" => ("YELLOW", RGBColor(230, 200, 0)),
+        "C" => ("CHEQUERED", BLACK),
+        _ => ("GREEN", RGBColor(0, 150, 0)),
+    }
+}
+
+/// render_race_gif renders a recording frame by frame into an animated GIF. `fps` is the frame
+/// rate of the animation, `speedup` the number of race seconds shown per second of animation. The
+/// method returns the number of rendered frames.
+pub fn render_race_gif(
+    recording: &RaceRecording,
+    track: &Track,
+    out_path: &Path,
+    fps: f64,
+    speedup: f64,
+) -> anyhow::Result<usize> {
+    if recording.frames.is_empty() {
+        anyhow::bail!("Recording does not contain any frames!");
+    }
+    recording.validate()?;
+    if fps <= 0.0 || speedup <= 0.0 {
+        anyhow::bail!("Frame rate and speedup must be positive!");
+    }
+
+    if let Some(parent) = out_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    // determine race time step between two output frames (downsample long races)
+    let duration = recording.get_duration();
+    let mut dt_out = speedup / fps;
+
+    if duration / dt_out > MAX_RENDER_FRAMES as f64 {
+        dt_out = duration / MAX_RENDER_FRAMES as f64;
+    }
+
+    let no_frames = (duration / dt_out).ceil() as usize + 1;
+
+    // prepare static track data
+    let [x_min, x_max, y_min, y_max] = track.get_axes_expansion(100.0);
+    let centerline: Vec<(f64, f64)> = track
+        .track_cl
+        .iter()
+        .map(|el| (el.coords.x, el.coords.y))
+        .collect();
+
+    let root = BitMapBackend::gif(
+        out_path,
+        (RENDER_SIZE_PX, RENDER_SIZE_PX),
+        (1000.0 / fps).round() as u32,
+    )?
+    .into_drawing_area();
+
+    for k in 0..no_frames {
+        // pick the recorded frame that is closest to the output frame time
+        let t = k as f64 * dt_out;
+        let idx = ((t / recording.frame_dt).round() as usize).min(recording.frames.len() - 1);
+        let frame = &recording.frames[idx];
+
+        root.fill(&RGBColor(20, 80, 20))?;
+
+        let mut chart = ChartBuilder::on(&root)
+            .margin(10)
+            .build_cartesian_2d(x_min..x_max, y_min..y_max)?;
+
+        // track
+        chart.draw_series(LineSeries::new(
+            centerline.iter().copied(),
+            RGBColor(160, 160, 160).stroke_width(6),
+        ))?;
+
+        // cars
+        let dists = track.get_dists_for_race_progs(&frame.race_progs);
+        let coords = track.get_coords_for_dists(&dists);
+
+        for (car, point) in recording.cars.iter().zip(coords.iter()) {
+            let color = RGBColor(car.color[0], car.color[1], car.color[2]);
+
+            chart.draw_series(std::iter::once(
+                EmptyElement::at((point.x, point.y))
+                    + Circle::new((0, 0), 6, color.filled())
+                    + Text::new(
+                        car.car_no.to_string(),
+                        (8, -8),
+                        ("sans-serif", 14).into_font().color(&WHITE),
+                    ),
+            ))?;
+        }
+
+        // safety car
+        if let Some(sc_race_prog) = frame.sc_race_prog {
+            let sc_dists = track.get_dists_for_race_progs(&[sc_race_prog]);
+            if let Some(sc_point) = track.get_coords_for_dists(&sc_dists).first() {
+                chart.draw_series(std::iter::once(
+                    EmptyElement::at((sc_point.x, sc_point.y))
+                        + Rectangle::new([(-8, -8), (8, 8)], RED.filled())
+                        + Text::new("SC", (10, -20), ("sans-serif", 16).into_font().color(&RED)),
+                ))?;
+            }
+        }
+
+        // flag banner and lap counter
+        let (banner, banner_color) = get_banner(&frame.flag_state);
+
+        root.draw(&Text::new(
+            format!(
+                "Lap {}/{}",
+                frame.lap_leader.min(recording.tot_no_laps),
+                recording.tot_no_laps
+            ),
+            (20, 20),
+            ("sans-serif", 24).into_font().color(&WHITE),
+        ))?;
+        root.draw(&Text::new(
+            banner,
+            (20, 50),
+            ("sans-serif", 24).into_font().color(&banner_color),
+        ))?;
+
+        root.present()?;
+    }
+
+    Ok(no_frames)
+}
@@ -0,0 +1,107 @@
+use eframe::egui;
+
+/// GuiAction is an action of the race screen that can be triggered by a keyboard shortcut.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GuiAction {
+    TogglePause,
+    SpeedUp,
+    SlowDown,
+    SelectPosition(usize),
+    ToggleFollow,
+    ToggleLabels,
+    ToggleZones,
+    ResetView,
+    ToggleHelp,
+}
+
+/// KEY_BINDINGS lists all keyboard shortcuts as (key, description) as shown in the help overlay.
+pub const KEY_BINDINGS: [(&str, &str); 8] = [
+    ("Spacja", "Pauza / wznowienie"),
+    ("+ / -", "Szybciej / wolniej (mnożnik czasu rzeczywistego)"),
+    ("1-9", "Wybór auta wg pozycji"),
+    ("F", "Kamera za wybranym autem"),
+    ("L", "Podpisy aut"),
+    ("G", "Strefy (aleja, zakręty)"),
+    ("R", "Reset widoku"),
+    ("H", "Pomoc (ta lista)"),
+];
+
+/// get_key_action maps a pressed key to its action.
+fn get_key_action(key: egui::Key) -> Option<GuiAction> {
+    let action = match key {
+        egui::Key::Space => GuiAction::TogglePause,
+        egui::Key::Num1 => GuiAction::SelectPosition(1),
+        egui::Key::Num2 => GuiAction::SelectPosition(2),
+        egui::Key::Num3 => GuiAction::SelectPosition(3),
+        egui::Key::Num4 => GuiAction::SelectPosition(4),
+        egui::Key::Num5 => GuiAction::SelectPosition(5),
+        egui::Key::Num6 => GuiAction::SelectPosition(6),
+        egui::Key::Num7 => GuiAction::SelectPosition(7),
+        egui::Key::Num8 => GuiAction::SelectPosition(8),
+        egui::Key::Num9 => GuiAction::SelectPosition(9),
+        egui::Key::F => GuiAction::ToggleFollow,
+        egui::Key::L => GuiAction::ToggleLabels,
+        egui::Key::G => GuiAction::ToggleZones,
+        egui::Key::R => GuiAction::ResetView,
+        egui::Key::H => GuiAction::ToggleHelp,
+        _ => return None,
+    };
+
+    Some(action)
+}
+
+/// read_actions returns the actions of the keys pressed since the last frame. Nothing is returned
+/// while a text field has the keyboard focus such that typing does not trigger shortcuts.
+pub fn read_actions(ctx: &egui::CtxRef) -> Vec<GuiAction> {
+    if ctx.wants_keyboard_input() {
+        return vec![];
+    }
+
+    let mut actions = vec![];
+
+    for event in ctx.input().events.iter() {
+        match event {
+            egui::Event::Key {
+                key,
+                pressed: true,
+                ..
+            } => {
+                if let Some(action) = get_key_action(*key) {
+                    actions.push(action);
+                }
+            }
+            // egui has no keys for + and -, they are received as text
+            egui::Event::Text(text) => match text.as_str() {
+                "+" | "=" => actions.push(GuiAction::SpeedUp),
+                "-" => actions.push(GuiAction::SlowDown),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    actions
+}
+
+/// show_help_overlay shows the key bindings as a semi-transparent panel in the window center.
+pub fn show_help_overlay(ctx: &egui::CtxRef) {
+    let frame = egui::Frame::popup(&ctx.style()).fill(egui::Color32::from_black_alpha(200));
+
+    egui::Window::new("Skróty klawiszowe")
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .collapsible(false)
+        .resizable(false)
+        .frame(frame)
+        .show(ctx, show_key_bindings);
+}
+
+/// show_key_bindings lists the key bindings in a grid.
+pub fn show_key_bindings(ui: &mut egui::Ui) {
+    egui::Grid::new("key_bindings").show(ui, |ui| {
+        for (key, description) in KEY_BINDINGS.iter() {
+            ui.monospace(*key);
+            ui.label(*description);
+            ui.end_row();
+        }
+    });
+}
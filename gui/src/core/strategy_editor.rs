@@ -0,0 +1,156 @@
+use eframe::egui;
+use flume::Sender;
+use racesim::core::car::StrategyEntry;
+use racesim::core::tireset::COMPOUNDS;
+use racesim::pre::read_sim_pars::{validate_strategy, write_sim_pars, SimPars};
+use std::path::Path;
+
+/// StrategyEditor is shown before the race start. It allows to edit the planned pit stops of all
+/// cars. The edited parameters are sent to the simulation thread when the race is started.
+#[derive(Debug)]
+pub struct StrategyEditor {
+    pub sim_pars: SimPars,
+    tx_pars: Sender<SimPars>,
+    car_nos: Vec<u32>,
+    save_path: String,
+    status_msg: Option<String>,
+}
+
+impl StrategyEditor {
+    pub fn new(sim_pars: SimPars, tx_pars: Sender<SimPars>) -> StrategyEditor {
+        let mut car_nos = sim_pars.race_pars.participants.to_owned();
+        car_nos.sort_unstable();
+
+        StrategyEditor {
+            sim_pars,
+            tx_pars,
+            car_nos,
+            save_path: String::from("output/edited_scenario.json"),
+            status_msg: None,
+        }
+    }
+
+    /// restart sends the parameters of the started scenario to the simulation thread once more,
+    /// i.e. runs the same scenario again. Returns false if the simulation is not available.
+    pub fn restart(&self) -> bool {
+        self.tx_pars.send(self.sim_pars.to_owned()).is_ok()
+    }
+
+    /// show draws the editor and returns true as soon as the race was started.
+    pub fn show(&mut self, ui: &mut egui::Ui) -> bool {
+        let tot_no_laps = self.sim_pars.race_pars.tot_no_laps;
+        let max_inlap = tot_no_laps.saturating_sub(1).max(1);
+
+        ui.heading("Strategie przed startem");
+
+        egui::ScrollArea::auto_sized().show(ui, |ui| {
+            for car_no in self.car_nos.iter() {
+                let car_pars = match self.sim_pars.car_pars_all.get_mut(car_no) {
+                    Some(x) => x,
+                    None => continue,
+                };
+
+                ui.separator();
+                ui.label(format!(
+                    "#{} ({})",
+                    car_no, car_pars.strategy[0].driver_initials
+                ));
+
+                // table of the planned stops (entry 0 contains the start compound)
+                let mut del_idx: Option<usize> = None;
+
+                egui::Grid::new(format!("strategy_grid_{}", car_no)).show(ui, |ui| {
+                    ui.label("Okrążenie");
+                    ui.label("Mieszanka");
+                    ui.end_row();
+
+                    for (i, entry) in car_pars.strategy.iter_mut().enumerate() {
+                        if i == 0 {
+                            ui.label("start");
+                        } else {
+                            ui.add(egui::DragValue::new(&mut entry.inlap).clamp_range(1..=max_inlap));
+                        }
+
+                        egui::ComboBox::from_id_source(format!("compound_{}_{}", car_no, i))
+                            .selected_text(entry.compound.to_owned())
+                            .show_ui(ui, |ui| {
+                                for compound in COMPOUNDS.iter() {
+                                    ui.selectable_value(
+                                        &mut entry.compound,
+                                        compound.to_string(),
+                                        *compound,
+                                    );
+                                }
+                            });
+
+                        if i > 0 && ui.button("Usuń").clicked() {
+                            del_idx = Some(i);
+                        }
+                        ui.end_row();
+                    }
+                });
+
+                if let Some(i) = del_idx {
+                    car_pars.strategy.remove(i);
+                }
+
+                if ui.button("Dodaj postój").clicked() {
+                    let last_inlap = car_pars.strategy.iter().map(|x| x.inlap).max().unwrap_or(0);
+                    car_pars.strategy.push(StrategyEntry {
+                        inlap: (last_inlap + 1).min(max_inlap),
+                        tire_start_age: 0,
+                        compound: String::from("MEDIUM"),
+                        driver_initials: String::new(),
+                        tire_set: None,
+                        engine_mode: None,
+                    });
+                }
+
+                // inline validation
+                let report = validate_strategy(&car_pars.strategy, tot_no_laps);
+                for msg in report.errors.iter() {
+                    ui.colored_label(egui::Color32::RED, msg.as_str());
+                }
+                for msg in report.warnings.iter() {
+                    ui.colored_label(egui::Color32::YELLOW, msg.as_str());
+                }
+            }
+        });
+
+        ui.separator();
+
+        // save edited scenario
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.save_path);
+            if ui.button("Zapisz jako…").clicked() {
+                self.status_msg = Some(
+                    match write_sim_pars(&self.sim_pars, Path::new(&self.save_path)) {
+                        Ok(()) => format!("Zapisano scenariusz: {}", self.save_path),
+                        Err(e) => format!("Błąd zapisu scenariusza: {:#}", e),
+                    },
+                );
+            }
+        });
+
+        // start race
+        let mut started = false;
+
+        if ui.button("Start").clicked() {
+            let report = self.sim_pars.validate();
+
+            if !report.errors.is_empty() {
+                self.status_msg = Some(report.errors.join("\n"));
+            } else if self.tx_pars.send(self.sim_pars.to_owned()).is_err() {
+                self.status_msg = Some(String::from("Symulator nie jest dostępny!"));
+            } else {
+                started = true;
+            }
+        }
+
+        if let Some(msg) = &self.status_msg {
+            ui.label(msg.as_str());
+        }
+
+        started
+    }
+}
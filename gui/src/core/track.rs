@@ -1,8 +1,7 @@
 use anyhow::Context;
 use helpers::general::{lin_interp, InputValueError};
 use helpers::geometry::{Point2d, Vector2d};
-use serde::Deserialize;
-use std::fs::OpenOptions;
+use racesim::core::track::read_track_csv;
 use std::path::Path;
 
 #[derive(Debug)]
@@ -10,6 +9,9 @@ pub enum ZoneType {
     PitZone,
     OvertakingZone,
     Corner,
+    Debris,
+    Yellow,
+    Sector,
 }
 
 #[derive(Debug)]
@@ -18,18 +20,11 @@ pub struct Zone {
     pub centerline: Vec<Point2d>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
-pub struct CsvTrackEl {
-    pub x_m: f64,
-    pub y_m: f64,
-    pub w_tr_left_m: f64,
-    pub w_tr_right_m: f64,
-}
-
 #[derive(Debug, Clone)]
 pub struct TrackEl {
     pub s: f64,
     pub coords: Point2d,
+    pub z: Option<f64>,
 }
 
 #[derive(Debug)]
@@ -87,23 +82,8 @@ impl Track {
             );
         }
 
-        // open file
-        let fh = OpenOptions::new()
-            .read(true)
-            .open(trackfile_path)
-            .context(format!(
-                "Failed to open track file {}!",
-                trackfile_path.to_str().unwrap()
-            ))?;
-
         // read and parse csv track data
-        let mut csv_reader = csv::Reader::from_reader(&fh);
-        let mut csv_track_cl: Vec<CsvTrackEl> = vec![];
-
-        for result in csv_reader.deserialize() {
-            let csv_track_el: CsvTrackEl = result?;
-            csv_track_cl.push(csv_track_el);
-        }
+        let csv_track_cl = read_track_csv(trackfile_path)?;
 
         // create track and close it
         let mut track_cl: Vec<TrackEl> = csv_track_cl
@@ -114,6 +94,7 @@ impl Track {
                     x: el.x_m,
                     y: el.y_m,
                 },
+                z: el.z_m,
             })
             .collect();
 
@@ -165,6 +146,22 @@ impl Track {
         })
     }
 
+    /// get_elevation_range returns the minimum and maximum elevation of the track (None if the
+    /// track file does not contain elevation data).
+    pub fn get_elevation_range(&self) -> Option<(f64, f64)> {
+        let mut range: Option<(f64, f64)> = None;
+
+        for track_el in self.track_cl.iter() {
+            let z = track_el.z?;
+            range = Some(match range {
+                Some((z_min, z_max)) => (z_min.min(z), z_max.max(z)),
+                None => (z, z),
+            });
+        }
+
+        range
+    }
+
     pub fn get_axes_expansion(&self, border: f64) -> [f64; 4] {
         // determine min and max x and y values
         let (mut x_min, mut x_max, mut y_min, mut y_max) = self.track_cl.iter().fold(
@@ -348,6 +345,40 @@ impl Track {
         tan_vec.normalized().normal_vector()
     }
 
+    pub fn get_debris_zones(&self, s_ranges: &[[f64; 2]]) -> Vec<Zone> {
+        s_ranges
+            .iter()
+            .map(|s_range| Zone {
+                zone_type: ZoneType::Debris,
+                centerline: self.get_zone_centerline(s_range),
+            })
+            .collect()
+    }
+
+    pub fn get_yellow_zones(&self, s_ranges: &[[f64; 2]]) -> Vec<Zone> {
+        s_ranges
+            .iter()
+            .map(|s_range| Zone {
+                zone_type: ZoneType::Yellow,
+                centerline: self.get_zone_centerline(s_range),
+            })
+            .collect()
+    }
+
+    /// get_sector_zones returns the three sectors of the track (finish line to `s12`, `s12` to
+    /// `s23` and `s23` to the finish line).
+    pub fn get_sector_zones(&self) -> Vec<Zone> {
+        let track_len = self.track_cl.last().map(|el| el.s).unwrap_or(0.0);
+
+        [[0.0, self.s12], [self.s12, self.s23], [self.s23, track_len]]
+            .iter()
+            .map(|s_range| Zone {
+                zone_type: ZoneType::Sector,
+                centerline: self.get_zone_centerline(s_range),
+            })
+            .collect()
+    }
+
     pub fn get_corner_zones(&self) -> Vec<Zone> {
         let mut zones: Vec<Zone> = Vec::new();
 
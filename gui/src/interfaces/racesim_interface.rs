@@ -1,13 +1,22 @@
-use flume::Receiver;
-use racesim::interfaces::gui_interface::RaceState;
+use flume::{Receiver, Sender};
+use racesim::interfaces::gui_interface::{RaceState, SimControl};
 
 #[derive(Debug)]
 pub struct RacesimInterface {
     pub rx: Receiver<RaceState>,
+    pub tx_control: Option<Sender<SimControl>>,
     pub race_state: RaceState,
 }
 
 impl RacesimInterface {
+    /// send_control sends a control command to the simulation (ignored if there is no control
+    /// channel or the simulation has already finished).
+    pub fn send_control(&self, control: SimControl) {
+        if let Some(tx_control) = &self.tx_control {
+            let _ = tx_control.send(control);
+        }
+    }
+
     pub fn update(&mut self) {
         // loop to obtain the latest race state in the channel
         let mut tmp_message = self.rx.try_recv();
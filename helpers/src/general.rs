@@ -1,3 +1,4 @@
+use std::cmp::Ordering;
 use std::error::Error;
 use std::fmt;
 
@@ -14,15 +15,28 @@ impl fmt::Display for InputValueError {
 
 impl Error for InputValueError {}
 
-/// argmax returns the index of the maximum value in the array x.
-pub fn argmax<T: std::cmp::PartialOrd + std::marker::Copy>(x: &[T]) -> usize {
-    let mut idx_max = 0;
-    let mut val_max = x[0];
+/// is_nan returns true if a value is not comparable to itself (e.g. a NaN float).
+fn is_nan<T: std::cmp::PartialOrd>(val: &T) -> bool {
+    val.partial_cmp(val).is_none()
+}
+
+/// argmax returns the index of the maximum value in the array x. NaNs are ignored, None is
+/// returned if x is empty or contains only NaNs. In case of equal values, the first index is
+/// returned.
+pub fn argmax<T: std::cmp::PartialOrd + std::marker::Copy>(x: &[T]) -> Option<usize> {
+    let mut idx_max: Option<usize> = None;
 
-    for (i, &val) in x.iter().enumerate().skip(1) {
-        if val > val_max {
-            val_max = val;
-            idx_max = i;
+    for (i, val) in x.iter().enumerate() {
+        if is_nan(val) {
+            continue;
+        }
+
+        let is_new_max = match idx_max {
+            Some(idx) => *val > x[idx],
+            None => true,
+        };
+        if is_new_max {
+            idx_max = Some(i);
         }
     }
 
@@ -50,12 +64,28 @@ pub enum SortOrder {
     Descending,
 }
 
-/// argsort returns the indices that would sort an array.
+/// cmp_nan_last compares two values such that NaNs are considered greater than all other values
+/// and equal to each other, i.e. it defines a total order for floats.
+fn cmp_nan_last<T: std::cmp::PartialOrd>(a: &T, b: &T) -> Ordering {
+    match a.partial_cmp(b) {
+        Some(ordering) => ordering,
+        None => is_nan(a).cmp(&is_nan(b)),
+    }
+}
+
+/// argsort returns the indices that would sort an array. NaNs are sorted last, independent of the
+/// sort order, and the sort is stable, i.e. equal values keep their original order.
 pub fn argsort<T: std::cmp::PartialOrd>(x: &[T], order: SortOrder) -> Vec<usize> {
     let mut indices: Vec<usize> = (0..x.len()).collect();
     match order {
-        SortOrder::Ascending => indices.sort_by(|&a, &b| x[a].partial_cmp(&x[b]).unwrap()),
-        SortOrder::Descending => indices.sort_by(|&a, &b| x[b].partial_cmp(&x[a]).unwrap()),
+        SortOrder::Ascending => indices.sort_by(|&a, &b| cmp_nan_last(&x[a], &x[b])),
+        SortOrder::Descending => indices.sort_by(|&a, &b| {
+            if is_nan(&x[a]) || is_nan(&x[b]) {
+                cmp_nan_last(&x[a], &x[b])
+            } else {
+                cmp_nan_last(&x[b], &x[a])
+            }
+        }),
     }
     indices
 }
@@ -40,12 +40,27 @@ mod general_tests {
     #[test]
     fn test_argmax_1() {
         let x: Vec<i32> = vec![3, -1, 5, 8, -2];
-        assert_eq!(argmax(&x), 3);
+        assert_eq!(argmax(&x), Some(3));
     }
     #[test]
     fn test_argmax_2() {
         let x: Vec<f64> = vec![3.0, -1.0, 5.0, 8.0, -2.0];
-        assert_eq!(argmax(&x), 3);
+        assert_eq!(argmax(&x), Some(3));
+    }
+    #[test]
+    fn test_argmax_3() {
+        let x: Vec<f64> = vec![];
+        assert_eq!(argmax(&x), None);
+    }
+    #[test]
+    fn test_argmax_4() {
+        let x: Vec<f64> = vec![f64::NAN, -1.0, f64::NAN, 8.0, 8.0];
+        assert_eq!(argmax(&x), Some(3));
+    }
+    #[test]
+    fn test_argmax_5() {
+        let x: Vec<f64> = vec![f64::NAN, f64::NAN];
+        assert_eq!(argmax(&x), None);
     }
 
     #[test]
@@ -79,6 +94,81 @@ mod general_tests {
         let x: Vec<f64> = vec![3.0, -1.0, 5.0, 8.0, -2.0];
         assert_eq!(argsort(&x, SortOrder::Descending), vec![3, 2, 0, 1, 4]);
     }
+    #[test]
+    fn test_argsort_5() {
+        let x: Vec<f64> = vec![3.0, f64::NAN, 5.0, f64::INFINITY, f64::NAN, -2.0];
+        assert_eq!(argsort(&x, SortOrder::Ascending), vec![5, 0, 2, 3, 1, 4]);
+        assert_eq!(argsort(&x, SortOrder::Descending), vec![3, 2, 0, 5, 1, 4]);
+    }
+    #[test]
+    fn test_argsort_6() {
+        let x: Vec<f64> = vec![];
+        assert!(argsort(&x, SortOrder::Descending).is_empty());
+    }
+
+    /// get_random_vecs returns pseudo-random vectors (xorshift, fixed seed) of varying length in
+    /// which some values are replaced by NaNs and infinities.
+    fn get_random_vecs() -> Vec<Vec<f64>> {
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        (0..200)
+            .map(|_| {
+                let len = (next() % 20) as usize;
+                (0..len)
+                    .map(|_| match next() % 10 {
+                        0 => f64::NAN,
+                        1 => f64::INFINITY,
+                        2 => f64::NEG_INFINITY,
+                        _ => (next() % 2000) as f64 / 10.0 - 100.0,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_argsort_random_with_nans() {
+        for x in get_random_vecs().iter() {
+            for &order in [SortOrder::Ascending, SortOrder::Descending].iter() {
+                let idxs = argsort(x, order);
+
+                // permutation of all indices
+                let mut idxs_check = idxs.clone();
+                idxs_check.sort_unstable();
+                assert_eq!(idxs_check, (0..x.len()).collect::<Vec<usize>>());
+
+                // NaNs last, all other values ordered
+                let no_nans = x.iter().filter(|val| !val.is_nan()).count();
+                assert!(idxs[no_nans..].iter().all(|&idx| x[idx].is_nan()));
+                for pair in idxs[..no_nans].windows(2) {
+                    match order {
+                        SortOrder::Ascending => assert!(x[pair[0]] <= x[pair[1]]),
+                        SortOrder::Descending => assert!(x[pair[0]] >= x[pair[1]]),
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_argmax_random_with_nans() {
+        for x in get_random_vecs().iter() {
+            match argmax(x) {
+                Some(idx) => {
+                    assert!(!x[idx].is_nan());
+                    assert!(x.iter().all(|val| val.is_nan() || *val <= x[idx]));
+                    assert!(x[..idx].iter().all(|val| val.is_nan() || *val < x[idx]));
+                }
+                None => assert!(x.iter().all(|val| val.is_nan())),
+            }
+        }
+    }
 
     #[test]
     fn test_lin_interp_1() {
@@ -0,0 +1,70 @@
+//! Measures the per-run setup time of a race with and without a shared track, and checks that
+//! a seeded race gives the same result in both cases.
+//!
+//! Run from the repository root in release mode (the track geometry is read from `input/`):
+//! `cargo run -p racesim --release --example bench_track_setup`
+
+use racesim::core::handle_race::run_race_on_track;
+use racesim::core::track::Track;
+use racesim::prelude::*;
+use racesim::Race;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::Instant;
+
+const NO_RUNS: u32 = 200;
+const TIMESTEP_SIZE: f64 = 0.1;
+
+fn main() -> anyhow::Result<()> {
+    let mut sim_pars = read_sim_pars_flexible(Path::new("input/parameters/test_race.json"))?;
+    let sim_consts = read_sim_constants(Path::new("input/parameters/sim_constants.json"))?;
+    let tire_config = read_tire_config(Path::new("input/parameters/tires.json"))?;
+    sim_pars.race_pars.seed = Some(1);
+
+    // setup with the track geometry created in every run (as before)
+    let t_start = Instant::now();
+    for _ in 0..NO_RUNS {
+        Race::new(
+            &sim_pars.race_pars,
+            &sim_consts,
+            &tire_config,
+            &sim_pars.track_pars,
+            &sim_pars.driver_pars_all,
+            &sim_pars.car_pars_all,
+            TIMESTEP_SIZE,
+        );
+    }
+    let t_per_run_new = t_start.elapsed().as_secs_f64() / NO_RUNS as f64;
+
+    // setup with the track geometry created once and shared between the runs
+    let t_start = Instant::now();
+    let track = Rc::new(Track::new(&sim_pars.track_pars));
+    for _ in 0..NO_RUNS {
+        Race::new_with_track(
+            &sim_pars.race_pars,
+            &sim_consts,
+            &tire_config,
+            Rc::clone(&track),
+            &sim_pars.driver_pars_all,
+            &sim_pars.car_pars_all,
+            TIMESTEP_SIZE,
+        );
+    }
+    let t_per_run_shared = t_start.elapsed().as_secs_f64() / NO_RUNS as f64;
+
+    println!("Setup time per run ({} runs):", NO_RUNS);
+    println!("  track created per run: {:.3} ms", t_per_run_new * 1000.0);
+    println!("  shared track:          {:.3} ms", t_per_run_shared * 1000.0);
+
+    // a seeded race must not depend on how the track was created
+    let result_new = run_race(&sim_pars, &sim_consts, &tire_config, TIMESTEP_SIZE)?;
+    let result_shared =
+        run_race_on_track(&sim_pars, &sim_consts, &tire_config, &track, TIMESTEP_SIZE)?;
+
+    if result_new.racetimes != result_shared.racetimes {
+        anyhow::bail!("Race times differ between the run with its own track and the shared track!");
+    }
+    println!("Race times are identical for both setups.");
+
+    Ok(())
+}
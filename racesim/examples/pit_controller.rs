@@ -0,0 +1,46 @@
+//! Runs a race with a naive strategy controller that is invoked after every leader lap: a car
+//! without a planned pit stop pits as soon as its tires are older than a given age.
+//!
+//! Run from the repository root (track and constant files are read from `input/`):
+//! `cargo run -p racesim --example pit_controller`
+
+use racesim::prelude::*;
+use std::path::Path;
+
+/// (laps) Tire age after which the controller calls a car in.
+const MAX_TIRE_AGE: f64 = 18.0;
+
+fn main() -> anyhow::Result<()> {
+    let sim_pars = read_sim_pars_flexible(Path::new("input/parameters/test_race.json"))?;
+    let sim_consts = read_sim_constants(Path::new("input/parameters/sim_constants.json"))?;
+    let tire_config = read_tire_config(Path::new("input/parameters/tires.json"))?;
+
+    let controller = |ctx: LapContext, controls: &mut StrategyControls| {
+        for car in ctx.cars.iter() {
+            // pit in the lap after the next one, the current lap might already be too late
+            let inlap = car.compl_lap + 2;
+
+            if !car.dnf
+                && car.tire_age > MAX_TIRE_AGE
+                && car.planned_inlaps.is_empty()
+                && inlap < ctx.tot_no_laps
+            {
+                println!(
+                    "CONTROLLER: Car {} ({:.1} laps old {}) pits in lap {}",
+                    car.car_no, car.tire_age, car.compound, inlap
+                );
+                controls.schedule_pit(car.car_no, inlap, "HARD");
+            }
+        }
+    };
+
+    let run_opts = RunOptions {
+        on_lap_complete: Some(Box::new(controller)),
+        ..Default::default()
+    };
+
+    let race_result = run_race_with_options(&sim_pars, &sim_consts, &tire_config, run_opts)?;
+    print!("{}", race_result.format_classification(&OutputFormat::default())?);
+
+    Ok(())
+}
@@ -0,0 +1,148 @@
+//! Builds a two-car race scenario in code and simulates it without the CLI.
+//!
+//! Run from the repository root (track and constant files are read from `input/`):
+//! `cargo run -p racesim --example run_from_code`
+
+use racesim::prelude::*;
+use std::collections::HashMap;
+use std::path::Path;
+
+fn create_driver_pars(initials: &str, name: &str, t_driver: f64) -> DriverPars {
+    let mut degr_pars_all = HashMap::new();
+
+    for compound in ["SOFT", "MEDIUM", "HARD"].iter() {
+        degr_pars_all.insert(
+            compound.to_string(),
+            DegrPars {
+                degr_model: DegrModel::Lin,
+                k_0: 0.0,
+                k_1_lin: 0.04,
+                cliff_age: None,
+                k_2_cliff: None,
+            },
+        );
+    }
+
+    DriverPars {
+        initials: initials.to_owned(),
+        name: name.to_owned(),
+        t_driver,
+        t_driver_quali: None,
+        consistency: 0.95,
+        aggression: 0.5,
+        start_skill: 0.5,
+        vel_max: 330.0,
+        degr_pars_all,
+    }
+}
+
+fn create_car_pars(car_no: u32, color: &str, initials: &str, p_grid: u32, inlap: u32) -> CarPars {
+    CarPars {
+        car_no,
+        color: color.to_owned(),
+        t_car: 0.5,
+        b_fuel_per_lap: 1.6,
+        m_fuel: 100.0,
+        m_fuel_fixed: None,
+        t_pit_tirechange: 2.5,
+        pit_location: 5700.0,
+        strategy: vec![
+            StrategyEntry {
+                inlap: 0,
+                tire_start_age: 2,
+                compound: String::from("MEDIUM"),
+                driver_initials: initials.to_owned(),
+                tire_set: None,
+                engine_mode: None,
+            },
+            StrategyEntry {
+                inlap,
+                tire_start_age: 0,
+                compound: String::from("HARD"),
+                driver_initials: String::new(),
+                tire_set: None,
+                engine_mode: None,
+            },
+        ],
+        p_grid,
+        start_from_back: false,
+        component_change_penalty: None,
+        pit_crew_sigma: None,
+        pit_crew_mean_offset: None,
+        hold_strategy_laps: 0,
+        failure_rate_per_hour: None,
+        failure_rates_per_hour: None,
+        downforce_level: 0.5,
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    // read track, physics constants, and tire configuration from the default input files
+    let track_pars = read_track_pars(Path::new("input/parameters/tracks/Monza.json"))?;
+    let sim_consts = read_sim_constants(Path::new("input/parameters/sim_constants.json"))?;
+    let tire_config = read_tire_config(Path::new("input/parameters/tires.json"))?;
+
+    // build the scenario
+    let mut driver_pars_all = HashMap::new();
+    driver_pars_all.insert(String::from("AAA"), create_driver_pars("AAA", "Driver A", 0.0));
+    driver_pars_all.insert(String::from("BBB"), create_driver_pars("BBB", "Driver B", 0.2));
+
+    let mut car_pars_all = HashMap::new();
+    car_pars_all.insert(1, create_car_pars(1, "#FF0000", "AAA", 2, 18));
+    car_pars_all.insert(2, create_car_pars(2, "#0000FF", "BBB", 1, 22));
+
+    let sim_pars = SimPars {
+        race_pars: RacePars {
+            season: 2024,
+            tot_no_laps: 40,
+            track_name: Some(track_pars.name.to_owned()),
+            initial_weather: String::from("Dry"),
+            initial_wetness: None,
+            rain_probability: 0.0,
+            drs_allowed_lap: 2,
+            use_drs: true,
+            participants: vec![1, 2],
+            scripted_events: vec![],
+            session_start_h: None,
+            session_duration_h: 2.0,
+            sunset_h: 18.0,
+            vsc_probability: 0.0,
+            vsc_delta_s: 35.0,
+            vsc_duration_s: 60.0,
+            red_flag_probability: 0.0,
+            red_flag_min_cars: None,
+            heavy_rain_red_flag_probability: 0.0,
+            red_flag_duration_s: 1200.0,
+            red_flag_laps_deducted: 0,
+            red_flag_compound: None,
+            pit_closed_on_sc_deploy: false,
+            formation_lap: false,
+            model_toggles: Default::default(),
+            max_race_time_s: None,
+            sc_pit_window_laps: None,
+            start_behind_sc_laps: 0,
+            start_behind_sc_forced: false,
+            grid_penalties: HashMap::new(),
+            overtake_contact_probability: 0.0,
+            seed: None,
+        },
+        track_pars,
+        driver_pars_all,
+        car_pars_all,
+    };
+
+    // check the strategies before running the race
+    let report = sim_pars.validate();
+    for msg in report.warnings.iter() {
+        println!("WARNING: {}", msg);
+    }
+    if !report.errors.is_empty() {
+        anyhow::bail!("Invalid scenario: {}", report.errors.join(", "));
+    }
+
+    // run the race and print the results
+    let race_result = run_race(&sim_pars, &sim_consts, &tire_config, 0.1)?;
+    race_result.print_lap_and_race_times(&OutputFormat::default());
+
+    Ok(())
+}
@@ -1,22 +1,212 @@
 use crate::core::driver::Driver;
 use crate::core::state_handler::StateHandler;
+use crate::core::tire_inventory::TireInventory;
 use crate::core::tireset::{Tireset, TireConfig};
-use serde::Deserialize;
+use crate::core::track::Track;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::rc::Rc;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rand_distr::{Distribution, Normal};
+
+const FUEL_TOLERANCE: f64 = 1.0e-6; // (kg) tolerancja błędów zaokrągleń przy odejmowaniu zużycia paliwa
 
 /// Uproszczona strategia: dodano z powrotem `driver_initials` tylko dla startu.
 /// * `inlap` - Okrążenie zjazdowe pit stopu (0 dla info o oponach na starcie)
 /// * `tire_start_age` - Wiek opon przy montażu
 /// * `compound` - Mieszanka montowana podczas pit stopu
 /// * `driver_initials` - Inicjały kierowcy (używane tylko w wpisie 0 do ustawienia startowego kierowcy)
-#[derive(Debug, Deserialize, Clone)]
+/// * `tire_set` - Numer kompletu z przydziału opon, który jest ponownie montowany (OPCJONALNIE,
+///   tylko z przydziałem opon; brak -> nowy komplet)
+/// * `engine_mode` - Tryb silnika w stincie rozpoczynanym tym wpisem (OPCJONALNIE, brak -> wybór
+///   automatyczny w każdym okrążeniu)
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct StrategyEntry {
     pub inlap: u32,
     pub tire_start_age: u32,
     pub compound: String,
     pub driver_initials: String, // Przywrócone na potrzeby inicjalizacji
+    #[serde(default)]
+    pub tire_set: Option<u32>,
+    #[serde(default)]
+    pub engine_mode: Option<EngineMode>,
+}
+
+/// EngineMode to tryb pracy silnika w okrążeniu: szybciej kosztem niezawodności i paliwa (push)
+/// albo wolniej, oszczędzając silnik i paliwo (conserve).
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize, Serialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum EngineMode {
+    Push,
+    #[default]
+    Standard,
+    Conserve,
+}
+
+/// EngineModePars zawiera wpływ trybów silnika na czas okrążenia, częstość awarii i zużycie
+/// paliwa (tryb standardowy bez zmian).
+/// * `t_gain_push` - (s) Zysk czasu okrążenia w trybie push
+/// * `failure_factor_push` - Mnożnik częstości awarii w trybie push
+/// * `fuel_factor_push` - Mnożnik zużycia paliwa w trybie push
+/// * `t_loss_conserve` - (s) Strata czasu okrążenia w trybie conserve
+/// * `failure_factor_conserve` - Mnożnik częstości awarii w trybie conserve
+/// * `fuel_factor_conserve` - Mnożnik zużycia paliwa w trybie conserve
+#[derive(Debug, Clone, Copy)]
+pub struct EngineModePars {
+    pub t_gain_push: f64,
+    pub failure_factor_push: f64,
+    pub fuel_factor_push: f64,
+    pub t_loss_conserve: f64,
+    pub failure_factor_conserve: f64,
+    pub fuel_factor_conserve: f64,
+}
+
+impl Default for EngineModePars {
+    fn default() -> Self {
+        EngineModePars {
+            t_gain_push: 0.0,
+            failure_factor_push: 1.0,
+            fuel_factor_push: 1.0,
+            t_loss_conserve: 0.0,
+            failure_factor_conserve: 1.0,
+            fuel_factor_conserve: 1.0,
+        }
+    }
+}
+
+impl EngineModePars {
+    /// get_t_delta zwraca zmianę czasu okrążenia (s) w danym trybie (ujemna - szybciej).
+    pub fn get_t_delta(&self, engine_mode: EngineMode) -> f64 {
+        match engine_mode {
+            EngineMode::Push => -self.t_gain_push,
+            EngineMode::Standard => 0.0,
+            EngineMode::Conserve => self.t_loss_conserve,
+        }
+    }
+
+    /// get_failure_factor zwraca mnożnik częstości awarii w danym trybie.
+    pub fn get_failure_factor(&self, engine_mode: EngineMode) -> f64 {
+        match engine_mode {
+            EngineMode::Push => self.failure_factor_push,
+            EngineMode::Standard => 1.0,
+            EngineMode::Conserve => self.failure_factor_conserve,
+        }
+    }
+
+    /// get_fuel_factor zwraca mnożnik zużycia paliwa w danym trybie.
+    pub fn get_fuel_factor(&self, engine_mode: EngineMode) -> f64 {
+        match engine_mode {
+            EngineMode::Push => self.fuel_factor_push,
+            EngineMode::Standard => 1.0,
+            EngineMode::Conserve => self.fuel_factor_conserve,
+        }
+    }
+}
+
+/// Neutralny poziom docisku (ustawienie, dla którego obowiązują parametry toru bez zmian)
+pub const DOWNFORCE_NEUTRAL: f64 = 0.5;
+
+/// SetupPars zawiera wpływ poziomu docisku aerodynamicznego (0.0 - minimalny, 1.0 - maksymalny)
+/// na tempo w zakrętach i na prostych, zużycie opon oraz wyprzedzanie. Strata w zakrętach maleje
+/// kwadratowo z dociskiem, a strata na prostych kwadratowo rośnie, dlatego dla każdego toru
+/// istnieje optymalny poziom docisku zależny od udziału zakrętów i stref wyprzedzania w okrążeniu.
+/// * `corner_t_loss` - (s) Dodatek do czasu okrążenia w zakręcie przy neutralnym docisku (0 przy
+///   maksymalnym docisku, 4x przy minimalnym)
+/// * `drag_t_loss` - (s) Strata czasu na prostych na okrążenie złożone z samych stref wyprzedzania
+///   przy neutralnym docisku (skalowana udziałem stref wyprzedzania w okrążeniu)
+/// * `wear_sensitivity` - Względna zmiana zużycia opon przy maksymalnym (mniej) i minimalnym
+///   (więcej) docisku
+/// * `overtake_sensitivity` - Względna zmiana progu wyprzedzania przy maksymalnym i minimalnym
+///   docisku (duży docisk utrudnia wyprzedzanie i obronę przed autem z DRS)
+#[derive(Debug, Clone, Copy)]
+pub struct SetupPars {
+    pub corner_t_loss: f64,
+    pub drag_t_loss: f64,
+    pub wear_sensitivity: f64,
+    pub overtake_sensitivity: f64,
+}
+
+impl Default for SetupPars {
+    fn default() -> Self {
+        SetupPars {
+            corner_t_loss: 0.5,
+            drag_t_loss: 0.0,
+            wear_sensitivity: 0.0,
+            overtake_sensitivity: 0.0,
+        }
+    }
+}
+
+impl SetupPars {
+    /// get_corner_t_loss zwraca dodatek do czasu okrążenia w zakręcie (s) przy danym docisku.
+    pub fn get_corner_t_loss(&self, downforce_level: f64) -> f64 {
+        let x = 1.0 - downforce_level.clamp(0.0, 1.0);
+        self.corner_t_loss * (x / (1.0 - DOWNFORCE_NEUTRAL)).powi(2)
+    }
+
+    /// get_drag_t_delta zwraca zmianę czasu okrążenia (s) na prostych względem neutralnego
+    /// docisku (ujemna - szybciej) przy danym udziale stref wyprzedzania w okrążeniu.
+    pub fn get_drag_t_delta(&self, downforce_level: f64, overtaking_zones_lap_frac: f64) -> f64 {
+        let x = downforce_level.clamp(0.0, 1.0) / DOWNFORCE_NEUTRAL;
+        self.drag_t_loss * overtaking_zones_lap_frac * (x.powi(2) - 1.0)
+    }
+
+    /// get_wear_factor zwraca mnożnik zużycia opon przy danym docisku.
+    pub fn get_wear_factor(&self, downforce_level: f64) -> f64 {
+        let x = (downforce_level.clamp(0.0, 1.0) - DOWNFORCE_NEUTRAL) / DOWNFORCE_NEUTRAL;
+        1.0 - self.wear_sensitivity * x
+    }
+
+    /// calc_t_delta zwraca oczekiwaną zmianę czasu okrążenia (s) na danym torze względem
+    /// neutralnego docisku (zakręty wg udziału w długości okrążenia i proste wg stref
+    /// wyprzedzania). Minimum po poziomie docisku to optymalne ustawienie na tym torze.
+    pub fn calc_t_delta(&self, downforce_level: f64, track: &Track) -> f64 {
+        (self.get_corner_t_loss(downforce_level) - self.corner_t_loss) * track.corners_lap_frac
+            + self.get_drag_t_delta(downforce_level, track.overtaking_zones_lap_frac)
+    }
+}
+
+/// LaptimeBreakdown zawiera składniki teoretycznego czasu okrążenia (ich suma to czas okrążenia).
+/// * `t_base` - (s) Czas bazowy toru (t_q + t_gap_racepace)
+/// * `t_car` - (s) Strata z powodu parametrów bolidu
+/// * `t_driver` - (s) Strata z powodu kierowcy
+/// * `t_tire` - (s) Strata z powodu mieszanki i degradacji opon
+/// * `t_fuel` - (s) Strata z powodu masy paliwa (z karą za oszczędzanie paliwa)
+/// * `t_weather` - (s) Strata z powodu pogody (mokry tor, opony deszczowe na suchym torze)
+/// * `t_damage` - (s) Strata z powodu uszkodzeń
+/// * `t_noise` - (s) Losowy rozrzut kierowcy (zależny od konsystencji)
+/// * `t_launch` - (s) Strata startu z miejsca (`t_loss_firstlap` i wylosowana delta startu
+///   kierowcy, tylko 1. okrążenie)
+/// * `t_start` - (s) Opóźnienie ruszenia ze startu (reakcja na światła i anti-stall, tylko 1.
+///   okrążenie). Auto w tym czasie stoi, dlatego składnik nie wchodzi do tempa (`get_total`).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+pub struct LaptimeBreakdown {
+    pub t_base: f64,
+    pub t_car: f64,
+    pub t_driver: f64,
+    pub t_tire: f64,
+    pub t_fuel: f64,
+    pub t_weather: f64,
+    pub t_damage: f64,
+    pub t_noise: f64,
+    pub t_launch: f64,
+    pub t_start: f64,
+}
+
+impl LaptimeBreakdown {
+    /// get_total zwraca teoretyczny czas okrążenia (suma wszystkich składników poza `t_start`).
+    pub fn get_total(&self) -> f64 {
+        self.t_base
+            + self.t_car
+            + self.t_driver
+            + self.t_tire
+            + self.t_fuel
+            + self.t_weather
+            + self.t_damage
+            + self.t_noise
+            + self.t_launch
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -25,12 +215,223 @@ pub enum CarStatus{
     DNF,
 }
 
+/// FailureType to rodzaj awarii mechanicznej bolidu.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize, Serialize)]
+pub enum FailureType {
+    Engine,
+    Gearbox,
+    Hydraulics,
+    Brakes,
+}
+
+impl FailureType {
+    /// Wszystkie rodzaje awarii (w kolejności losowania).
+    pub const ALL: [FailureType; 4] = [
+        FailureType::Engine,
+        FailureType::Gearbox,
+        FailureType::Hydraulics,
+        FailureType::Brakes,
+    ];
+
+    /// get_event_kind zwraca rodzaj zdarzenia zapisywanego przy awarii.
+    pub fn get_event_kind(&self) -> &'static str {
+        match self {
+            FailureType::Engine => "ENGINE_FAILURE",
+            FailureType::Gearbox => "GEARBOX_FAILURE",
+            FailureType::Hydraulics => "HYDRAULICS_FAILURE",
+            FailureType::Brakes => "BRAKE_FAILURE",
+        }
+    }
+
+    /// get_sc_factor zwraca mnożnik prawdopodobieństwa SC po awarii. Z awarią silnika kierowca
+    /// zwykle zjeżdża bezpiecznie z toru (częściej VSC lub lokalna żółta flaga), bez hamulców auto
+    /// ląduje zwykle w barierze albo staje na torze.
+    pub fn get_sc_factor(&self) -> f64 {
+        match self {
+            FailureType::Engine => 0.5,
+            FailureType::Gearbox => 1.0,
+            FailureType::Hydraulics => 0.8,
+            FailureType::Brakes => 2.0,
+        }
+    }
+}
+
+/// FailureRates zawiera częstości awarii bolidu osobno dla każdego rodzaju awarii (rodzaj
+/// pominięty w pliku parametrów nie występuje).
+/// * `engine` - (1/h) Częstość awarii silnika
+/// * `gearbox` - (1/h) Częstość awarii skrzyni biegów
+/// * `hydraulics` - (1/h) Częstość awarii hydrauliki
+/// * `brakes` - (1/h) Częstość awarii hamulców
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct FailureRates {
+    #[serde(default)]
+    pub engine: f64,
+    #[serde(default)]
+    pub gearbox: f64,
+    #[serde(default)]
+    pub hydraulics: f64,
+    #[serde(default)]
+    pub brakes: f64,
+}
+
+impl FailureRates {
+    /// Udziały rodzajów awarii w łącznej częstości awarii (kolejność jak w `FailureType::ALL`).
+    pub const DEFAULT_SHARES: [f64; 4] = [0.4, 0.25, 0.2, 0.15];
+
+    /// from_total dzieli łączną częstość awarii (1/h) na rodzaje według `DEFAULT_SHARES`.
+    pub fn from_total(failure_rate_per_hour: f64) -> FailureRates {
+        let [engine, gearbox, hydraulics, brakes] =
+            FailureRates::DEFAULT_SHARES.map(|share| share * failure_rate_per_hour);
+        FailureRates {
+            engine,
+            gearbox,
+            hydraulics,
+            brakes,
+        }
+    }
+
+    /// get zwraca częstość awarii podanego rodzaju (1/h).
+    pub fn get(&self, failure_type: FailureType) -> f64 {
+        match failure_type {
+            FailureType::Engine => self.engine,
+            FailureType::Gearbox => self.gearbox,
+            FailureType::Hydraulics => self.hydraulics,
+            FailureType::Brakes => self.brakes,
+        }
+    }
+
+    /// get_total zwraca łączną częstość awarii wszystkich rodzajów (1/h).
+    pub fn get_total(&self) -> f64 {
+        FailureType::ALL.iter().map(|&failure_type| self.get(failure_type)).sum()
+    }
+
+    /// scaled zwraca częstości awarii pomnożone przez podany mnożnik (np. mnożnik incydentów).
+    pub fn scaled(&self, factor: f64) -> FailureRates {
+        FailureRates {
+            engine: self.engine * factor,
+            gearbox: self.gearbox * factor,
+            hydraulics: self.hydraulics * factor,
+            brakes: self.brakes * factor,
+        }
+    }
+
+    /// is_valid zwraca true, jeśli wszystkie częstości są skończone i nieujemne.
+    pub fn is_valid(&self) -> bool {
+        FailureType::ALL
+            .iter()
+            .all(|&failure_type| self.get(failure_type).is_finite() && self.get(failure_type) >= 0.0)
+    }
+}
+
+/// RetirementCause to przyczyna wycofania auta z wyścigu (DNF).
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RetirementCause {
+    Crash,
+    Failure(FailureType),
+    OutOfFuel,
+    Scripted,
+}
+
+impl RetirementCause {
+    /// get_event_kind zwraca rodzaj zdarzenia zapisywanego przy wycofaniu.
+    pub fn get_event_kind(&self) -> &'static str {
+        match self {
+            RetirementCause::Crash => "Crash",
+            RetirementCause::Failure(failure_type) => failure_type.get_event_kind(),
+            RetirementCause::OutOfFuel => "OUT_OF_FUEL",
+            RetirementCause::Scripted => "Retirement",
+        }
+    }
+
+    /// get_failure_type zwraca rodzaj awarii, jeśli wycofanie było spowodowane awarią.
+    pub fn get_failure_type(&self) -> Option<FailureType> {
+        match self {
+            RetirementCause::Failure(failure_type) => Some(*failure_type),
+            _ => None,
+        }
+    }
+}
+
+/// DamagePars zawiera parametry modelu uszkodzeń bolidu (poziom uszkodzeń jest bezwymiarowy, np.
+/// 0.3 po drobnym kontakcie).
+/// * `t_loss` - (s) Strata czasu okrążenia na jednostkę uszkodzeń
+/// * `wear_factor` - Dodatkowe zużycie opon na jednostkę uszkodzeń (mnożnik zużycia
+///   `1 + uszkodzenia * wear_factor`)
+/// * `repair_threshold` - Poziom uszkodzeń, od którego zaplanowany pit stop obejmuje naprawę
+/// * `t_repair` - (s) Dodatkowy czas postoju za naprawę (np. wymiana przedniego skrzydła)
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DamagePars {
+    pub t_loss: f64,
+    pub wear_factor: f64,
+    pub repair_threshold: f64,
+    pub t_repair: f64,
+}
+
+/// FuelSavePars zawiera parametry trybu oszczędzania paliwa (lift and coast).
+/// * `reduction` - Względne zmniejszenie zużycia paliwa na okrążenie w trybie oszczędzania
+/// * `t_loss` - (s) Strata czasu okrążenia w trybie oszczędzania
+/// * `reserve` - (kg) Minimalna ilość paliwa na mecie (mniej oznacza dyskwalifikację), tryb
+///   oszczędzania celuje w tę rezerwę
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FuelSavePars {
+    pub reduction: f64,
+    pub t_loss: f64,
+    pub reserve: f64,
+}
+
+/// ErsPars zawiera parametry modelu ERS (bateria ładowana w trakcie jazdy, energia używana w
+/// pojedynkach).
+/// * `capacity` - (MJ) Pojemność baterii (0.0 - model wyłączony)
+/// * `t_gain` - (s) Zysk czasu okrążenia przy użyciu energii
+/// * `deploy_rate` - (MJ/s) Zużycie energii przy jej użyciu
+/// * `harvest_per_lap` - (MJ) Energia odzyskiwana w ciągu okrążenia
+/// * `t_harvest_loss` - (s) Strata czasu okrążenia przy ładowaniu baterii
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ErsPars {
+    pub capacity: f64,
+    pub t_gain: f64,
+    pub deploy_rate: f64,
+    pub harvest_per_lap: f64,
+    pub t_harvest_loss: f64,
+}
+
+/// ThermalPars zawiera parametry przegrzewania bolidu przy jeździe blisko za innym autem (brudne
+/// powietrze). Temperatura jest względna: 0.0 - normalna, 1.0 - maksymalna.
+/// * `heat_rate` - (1/s) Wzrost temperatury przy jeździe blisko za innym autem (0.0 - model wył.)
+/// * `cool_rate` - (1/s) Spadek temperatury w czystym powietrzu
+/// * `threshold` - Temperatura, powyżej której auto traci tempo (musi odpuścić)
+/// * `t_loss_max` - (s) Strata czasu okrążenia przy maksymalnej temperaturze
+/// * `penalty_exponent` - Wykładnik krzywej straty (względem nadwyżki temperatury ponad próg)
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ThermalPars {
+    pub heat_rate: f64,
+    pub cool_rate: f64,
+    pub threshold: f64,
+    pub t_loss_max: f64,
+    pub penalty_exponent: f64,
+}
+
 /// Uproszczone parametry bolidu.
 /// * `t_car` - (s) Strata czasu na okrążenie z powodu parametrów bolidu
 /// * `t_pit_tirechange` - (s) Czas postoju na zmianę opon
 /// * `pit_location` - (m) Lokalizacja pit stopu
-/// ... reszta parametrów
-#[derive(Debug, Deserialize, Clone)]
+/// * `m_fuel` - (kg) Masa paliwa na starcie, uzupełniana do dystansu wyścigu plus `fuel_margin` z
+///   `SimConstants`, jeśli jest mniejsza
+/// * `m_fuel_fixed` - (kg) Stała masa paliwa na starcie (OPCJONALNIE, zamiast `m_fuel`). Jest
+///   używana bez uzupełniania - bolid, któremu zabraknie paliwa, odpada (zdarzenie `OUT_OF_FUEL`).
+/// * `failure_rate_per_hour` - (1/h) Częstość awarii bolidu (OPCJONALNIE). Zastępuje globalną
+///   wartość z `SimConstants` (nie jest przez nią mnożona), np. 0.0 - bolid bez awarii. Globalna
+///   wartość 0.0 (np. trening, wymuszone wycofania) ma pierwszeństwo i wyłącza awarie wszystkich
+///   bolidów, także tych z własną częstością, a mnożnik
+///   incydentów (mokry tor) dotyczy obu wartości. Częstość jest dzielona na rodzaje awarii według
+///   `FailureRates::DEFAULT_SHARES`.
+/// * `failure_rates_per_hour` - (1/h) Częstości awarii bolidu według rodzaju (OPCJONALNIE,
+///   pierwszeństwo przed `failure_rate_per_hour`)
+/// * `downforce_level` - Poziom docisku aerodynamicznego od 0.0 (minimalny) do 1.0 (maksymalny),
+///   domyślnie 0.5 (neutralny, `t_car` bez zmian). Większy docisk zmniejsza stratę w zakrętach i
+///   zużycie opon, ale zwiększa stratę na prostych i utrudnia wyprzedzanie (zob. `SetupPars`).
+///   ... reszta parametrów
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct CarPars {
     pub car_no: u32,
     //pub team: String,
@@ -39,33 +440,75 @@ pub struct CarPars {
     pub t_car: f64, // referencyjny czas okrążenia bolidu (bazowy performance)
     pub b_fuel_per_lap: f64, // zużycie paliwa na okrążenie (fuel/lap)
     pub m_fuel: f64, // aktualna masa/ilość paliwa (kg)
+    #[serde(default)]
+    pub m_fuel_fixed: Option<f64>, // (kg) masa paliwa na starcie bez uzupełniania do dystansu wyścigu
     pub t_pit_tirechange: f64, // czas samej wymiany opon w boksie
     //pub t_pit_driverchange: Option<f64>, // (Opcjonalny) - czas samej zmiany kierowcy w boksie, jeśli bez zmiany to none
     pub pit_location: f64, // Pozycja pit stopu na torze (metry)
     pub strategy: Vec<StrategyEntry>, // strategia wyścigu
     pub p_grid: u32, // pozycja startowa na polach startowych
+    #[serde(default)]
+    pub start_from_back: bool, // start z końca stawki (np. zmiana ustawień w parc fermé)
+    #[serde(default)]
+    pub component_change_penalty: Option<String>, // "back_of_grid" -> start z końca stawki
+    #[serde(default)]
+    pub pit_crew_sigma: Option<f64>, // (s) rozrzut czasu postoju ekipy (zamiast wartości globalnej)
+    #[serde(default)]
+    pub pit_crew_mean_offset: Option<f64>, // (s) średnie odchylenie czasu postoju od t_pit_tirechange
+    #[serde(default)]
+    pub hold_strategy_laps: u32, // pełne okrążenia na dotychczasowych oponach po zmianie pogody (hazard)
+    #[serde(default)]
+    pub failure_rate_per_hour: Option<f64>, // (1/h) częstość awarii bolidu (zamiast globalnej)
+    #[serde(default)]
+    pub failure_rates_per_hour: Option<FailureRates>, // (1/h) częstości awarii według rodzaju
+    #[serde(default = "default_downforce_level")]
+    pub downforce_level: f64, // poziom docisku aerodynamicznego (0.0 - 1.0)
 }
 
-#[derive(Debug)]
+fn default_downforce_level() -> f64 { DOWNFORCE_NEUTRAL }
+
+#[derive(Debug, Clone)]
 pub struct Car {
     pub car_no: u32,
     pub color: String,
     pub status: CarStatus,
-    pub reliability: f64,
+    failure_rates: Option<FailureRates>, // (1/h) częstości awarii bolidu (brak - wartości globalne)
     t_car: f64,
     m_fuel: f64,              
     b_fuel_per_lap: f64,  
     t_pit_tirechange: f64,
+    pit_crew_sigma: Option<f64>,
+    pit_crew_mean_offset: Option<f64>,
+    pub no_pitstops: u32,
     pub pit_location: f64,
     strategy: Vec<StrategyEntry>,
+    executed_inlaps: Vec<u32>, // okrążenia zjazdowe wykonanych pit stopów
+    superseded_stops: Vec<StrategyEntry>, // pit stopy usunięte ze strategii w trakcie wyścigu
     pub p_grid: u32,
+    pub start_from_back: bool,
     pub driver: Rc<Driver>,
-    pub sh: StateHandler,
+    pub(crate) sh: StateHandler,
     tireset: Tireset,
     pub dirty_air_wear_factor: f64,
+    pub temp_wear_factor: f64, // mnożnik zużycia opon od temperatury toru (1.0 bez modelu)
     pub last_slick_compound: Option<String>,
-    pub accumulated_damage_penalty: f64,
-
+    damage: f64, // poziom uszkodzeń (strata czasu i zużycie opon do naprawy w alei)
+    damage_pars: DamagePars,
+    punctured: bool, // przebita opona (auto dojeżdża do alei, do zmiany opon)
+    fuel_save: bool, // tryb oszczędzania paliwa (mniejsze zużycie kosztem tempa)
+    fuel_save_pars: FuelSavePars,
+    ers_energy: f64, // (MJ) energia w baterii ERS
+    ers_pars: ErsPars,
+    engine_mode: EngineMode, // tryb silnika w bieżącym okrążeniu
+    engine_mode_pars: EngineModePars,
+    temperature: f64, // względna temperatura bolidu (przegrzewanie w brudnym powietrzu)
+    thermal_pars: ThermalPars,
+    downforce_level: f64, // poziom docisku aerodynamicznego (ustawienie bolidu)
+    setup_pars: SetupPars,
+    pub t_defending_cur_lap: f64, // czas obrony pozycji w bieżącym okrążeniu (s)
+    pub hold_strategy_laps: u32, // pełne okrążenia na dotychczasowych oponach po zmianie pogody
+    tire_inventory: Option<TireInventory>, // przydział opon na cały weekend (opcjonalnie)
+    rng: StdRng, // generator losowań bolidu (rozrzut czasów okrążeń i postojów, awarie)
 }
 
 impl Car {
@@ -74,14 +517,23 @@ impl Car {
             car_no: car_pars.car_no,
             color: car_pars.color.to_owned(),
             status: CarStatus::Running,
-            reliability: 0.99, // 1% na awarie silnika
+            failure_rates: car_pars
+                .failure_rates_per_hour
+                .or_else(|| car_pars.failure_rate_per_hour.map(FailureRates::from_total)),
             t_car: car_pars.t_car,
-            m_fuel: car_pars.m_fuel,
+            m_fuel: car_pars.m_fuel_fixed.unwrap_or(car_pars.m_fuel),
             b_fuel_per_lap: car_pars.b_fuel_per_lap, 
             t_pit_tirechange: car_pars.t_pit_tirechange,
+            pit_crew_sigma: car_pars.pit_crew_sigma,
+            pit_crew_mean_offset: car_pars.pit_crew_mean_offset,
+            no_pitstops: 0,
             pit_location: car_pars.pit_location,
             strategy: car_pars.strategy.to_owned(),
+            executed_inlaps: Vec::new(),
+            superseded_stops: Vec::new(),
             p_grid: car_pars.p_grid,
+            start_from_back: car_pars.start_from_back
+                || car_pars.component_change_penalty.as_deref() == Some("back_of_grid"),
             driver,
             sh: StateHandler::default(),
             tireset: Tireset::new(
@@ -89,85 +541,206 @@ impl Car {
                 car_pars.strategy[0].tire_start_age,
             ),
             dirty_air_wear_factor: 1.0,
+            temp_wear_factor: 1.0,
             last_slick_compound: match car_pars.strategy[0].compound.as_str() {
                 "SOFT" | "MEDIUM" | "HARD" => Some(car_pars.strategy[0].compound.to_owned()),
                 _ => None,
             },
-            accumulated_damage_penalty: 0.0,
+            damage: 0.0,
+            damage_pars: DamagePars::default(),
+            fuel_save: false,
+            fuel_save_pars: FuelSavePars::default(),
+            ers_energy: 0.0,
+            ers_pars: ErsPars::default(),
+            engine_mode: car_pars.strategy[0].engine_mode.unwrap_or_default(),
+            engine_mode_pars: EngineModePars::default(),
+            temperature: 0.0,
+            thermal_pars: ThermalPars::default(),
+            downforce_level: car_pars.downforce_level,
+            setup_pars: SetupPars::default(),
+            punctured: false,
+            t_defending_cur_lap: 0.0,
+            hold_strategy_laps: car_pars.hold_strategy_laps,
+            tire_inventory: None,
+            rng: StdRng::from_entropy(),
         }
     }
 
+    /// Metoda ustawia ziarno generatora losowań bolidu (powtarzalny przebieg wyścigu).
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// Metoda przypisuje bolidowi przydział opon i montuje komplet startowy z przydziału (wiek
+    /// kompletu zastępuje `tire_start_age`).
+    pub fn attach_tire_inventory(&mut self, mut inventory: TireInventory) -> anyhow::Result<()> {
+        let compound = self.tireset.compound.to_uppercase();
+        self.tireset.age_tot = inventory.fit_set(&compound, self.strategy[0].tire_set, 0)?;
+        self.tire_inventory = Some(inventory);
+        Ok(())
+    }
+
+    /// Metoda zwraca kopię przydziału opon z aktualnym wiekiem zamontowanego kompletu (komplet
+    /// jest zdejmowany w kopii po `compl_lap` okrążeniach, np. na koniec sesji).
+    pub fn get_tire_inventory_snapshot(&self, compl_lap: u32) -> Option<TireInventory> {
+        self.tire_inventory.as_ref().map(|inventory| {
+            let mut inventory = inventory.to_owned();
+            inventory.remove_fitted(self.tireset.age_tot, compl_lap);
+            inventory
+        })
+    }
+
 
-    pub fn calc_basic_timeloss(&self, s_mass: f64, is_wet: bool, tire_cfg: &TireConfig) -> f64 {
+    /// Metoda zwraca składniki teoretycznego czasu okrążenia na danym torze i przy danej
+    /// wilgotności toru (0.0 - sucho, 1.0 - całkowicie mokro, z wylosowanym rozrzutem kierowcy).
+    /// Używana przez wyścig (suma) i trening (podgląd).
+    pub fn laptime_breakdown(
+        &mut self,
+        track: &Track,
+        wetness: f64,
+        tire_cfg: &TireConfig,
+    ) -> LaptimeBreakdown {
         let degr_pars = self.driver.get_degr_pars(&self.tireset.compound);
         let tire_loss = self.tireset.t_add_tireset(&degr_pars, tire_cfg);
 
-        let mut weather_penalty = 0.0;
         let compound = self.tireset.compound.to_uppercase();
         let compound_str = compound.as_str();
 
-        if is_wet {
-            // Bazowe spowolnienie mokrego toru
-            let wet_track_base_penalty = 12.0;
-
-            weather_penalty = match compound_str {
-                // Slicki: baza + bardzo duża kara
-                "SOFT" | "MEDIUM" | "HARD" => wet_track_base_penalty + 30.0,
-                // Intery: tylko baza
-                "INTERMEDIATE" => wet_track_base_penalty,
-                // Wety: baza + niewielka kara
-                "WET" => wet_track_base_penalty + 2.0,
-                _ => wet_track_base_penalty,
-            };
+        // Bazowe spowolnienie mokrego toru
+        let wet_track_base_penalty = 12.0;
+
+        // kary na suchym i na całkowicie mokrym torze
+        let (dry_penalty, wet_penalty) = match compound_str {
+            // Slicki: na mokrym baza + bardzo duża kara
+            "SOFT" | "MEDIUM" | "HARD" => (0.0, wet_track_base_penalty + 30.0),
+            // Intery: na suchym wyraźnie wolniejsze, na mokrym tylko baza
+            "INTERMEDIATE" => (5.0, wet_track_base_penalty),
+            // Wety: na suchym wyraźnie wolniejsze, na mokrym baza + niewielka kara
+            "WET" => (5.0, wet_track_base_penalty + 2.0),
+            _ => (0.0, wet_track_base_penalty),
+        };
+
+        // kara interpolowana liniowo wg wilgotności (np. slicki na wysychającym torze)
+        let wetness = wetness.clamp(0.0, 1.0);
+        let weather_penalty = dry_penalty + (wet_penalty - dry_penalty) * wetness;
+
+        // rozrzut czasu okrążenia zależny od konsystencji kierowcy
+        let std_dev = (1.0 - self.driver.consistency) * 2.0;
+        let t_noise = if std_dev > 0.0 {
+            Normal::new(0.0, std_dev).unwrap().sample(&mut self.rng)
         } else {
-            // Sucho: deszczowe opony są wyraźnie wolniejsze
-            if compound_str == "INTERMEDIATE" || compound_str == "WET" {
-                weather_penalty = 5.0;
-            }
+            0.0
+        };
+
+        LaptimeBreakdown {
+            t_base: track.t_q + track.t_gap_racepace,
+            t_car: self.t_car
+                + self.engine_mode_pars.get_t_delta(self.engine_mode)
+                + self
+                    .setup_pars
+                    .get_drag_t_delta(self.downforce_level, track.overtaking_zones_lap_frac),
+            t_driver: self.driver.t_driver,
+            t_tire: tire_loss,
+            t_fuel: self.m_fuel * track.s_mass
+                + if self.fuel_save { self.fuel_save_pars.t_loss } else { 0.0 },
+            t_weather: weather_penalty,
+            t_damage: self.damage * self.damage_pars.t_loss,
+            t_noise,
+            t_launch: 0.0,
+            t_start: 0.0,
         }
+    }
 
-        self.t_car
-            + self.driver.t_driver
-            + tire_loss
-            + self.m_fuel * s_mass
-            + weather_penalty
-            + self.accumulated_damage_penalty
+    /// Metoda zwraca częstości awarii bolidu (1/h): własne wartości bolidu zamiast globalnych
+    /// `failure_rates_global` (bez mnożenia obu). Globalne częstości równe 0.0 wyłączają awarie
+    /// wszystkich bolidów.
+    pub fn get_failure_rates(&self, failure_rates_global: &FailureRates) -> FailureRates {
+        if failure_rates_global.get_total() <= 0.0 {
+            return FailureRates::default();
+        }
+
+        self.failure_rates.unwrap_or(*failure_rates_global)
     }
 
-    /// Metoda zwiększa wiek opon.
-    /// Usunięto spalanie paliwa.
-    pub fn drive_lap(&mut self, lap_time_s: f64, failure_rate_per_hour: f64, print_events: bool) {
+    /// Metoda zwiększa wiek opon (z uwzględnieniem brudnego powietrza i obrony pozycji). Zwraca
+    /// przyczynę wycofania, jeśli w okrążeniu wystąpiła awaria (status zmienia wyścig). Każdy
+    /// rodzaj awarii jest losowany niezależnie z własną częstością.
+    pub fn drive_lap(
+        &mut self,
+        lap_time_s: f64,
+        failure_rates: &FailureRates,
+        tire_cfg: &TireConfig,
+    ) -> Option<RetirementCause> {
 
         //obsługa awarii
-        if (self.status == CarStatus::DNF){
-            return;
+        if self.status == CarStatus::DNF {
+            return None;
         }
-        let mut rng = rand::thread_rng();
-        if failure_rate_per_hour > 0.0 {
-            // Model Poissona: p_awarii_w_okrazeniu = 1 - exp(-lambda * t_okrazenia)
+        let mut retirement = None;
+        // częstości awarii zależne od trybu silnika w tym okrążeniu
+        let failure_rates =
+            failure_rates.scaled(self.engine_mode_pars.get_failure_factor(self.engine_mode));
+        for &failure_type in FailureType::ALL.iter() {
+            let failure_rate_per_hour = failure_rates.get(failure_type);
+            if failure_rate_per_hour <= 0.0 {
+                continue;
+            }
+
+            // Model Poissona (osobno dla każdego rodzaju awarii):
+            // p_awarii_w_okrazeniu = 1 - exp(-lambda * t_okrazenia)
             // lambda [1/s] = failure_rate_per_hour / 3600
+            // auto jest wycofywane przez wyścig (zdarzenie z czasem i miejscem awarii), przy kilku
+            // awariach w jednym okrążeniu liczy się pierwsza wylosowana
             let lambda = failure_rate_per_hour / 3600.0;
             let p_fail = 1.0 - (-lambda * lap_time_s).exp();
-            if rng.gen::<f64>() < p_fail {
-                self.status = CarStatus::DNF;
-                if print_events {
-                    println!(
-                        "CRASH: Car {} has retired from the race due to engine failure",
-                        self.car_no
-                    );
-                }
+            if self.rng.gen::<f64>() < p_fail && retirement.is_none() {
+                retirement = Some(RetirementCause::Failure(failure_type));
             }
         }
 
+        // Paliwa nie starczyło na całe okrążenie: auto staje tuż za linią jak po awarii.
+        let b_fuel_cur_lap = self.get_fuel_per_lap();
+        if b_fuel_cur_lap > 0.0
+            && self.m_fuel < b_fuel_cur_lap - FUEL_TOLERANCE
+            && retirement.is_none()
+        {
+            retirement = Some(RetirementCause::OutOfFuel);
+        }
+
         // Spalanie paliwa: zmniejsz masę paliwa o zużycie na okrążenie.
         // (Brak tankowania w wyścigu w F1 – jedynie ubywa paliwa.)
         if self.m_fuel > 0.0 {
-            self.m_fuel = (self.m_fuel - self.b_fuel_per_lap).max(0.0);
+            self.m_fuel = (self.m_fuel - b_fuel_cur_lap).max(0.0);
         }
 
-        self.tireset.drive_lap(self.dirty_air_wear_factor);
+        // Obrona pozycji: dodatkowe zużycie proporcjonalne do udziału czasu obrony w okrążeniu
+        let defending_frac = if lap_time_s.is_finite() && lap_time_s > 0.0 {
+            (self.t_defending_cur_lap / lap_time_s).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let wear_factor = (self.dirty_air_wear_factor
+            + tire_cfg.defending_wear_add * defending_frac)
+            * self.temp_wear_factor
+            * self.setup_pars.get_wear_factor(self.downforce_level)
+            * (1.0 + self.damage * self.damage_pars.wear_factor);
+
+        self.tireset.drive_lap(wear_factor);
 
-        self.dirty_air_wear_factor = 1.0
+        self.dirty_air_wear_factor = 1.0;
+        self.t_defending_cur_lap = 0.0;
+
+        retirement
+    }
+
+    /// Metoda zużywa opony i paliwo na okrążeniu formującym (ułamek `wear_factor` okrążenia).
+    pub fn drive_formation_lap(&mut self, wear_factor: f64) {
+        if self.status == CarStatus::DNF {
+            return;
+        }
+
+        self.m_fuel = (self.m_fuel - self.b_fuel_per_lap * wear_factor).max(0.0);
+        self.tireset.drive_lap(wear_factor);
     }
 
     /// Metoda sprawdza, czy bolid zjeżdża do alei w tym okrążeniu.
@@ -177,6 +750,43 @@ impl Car {
             .any(|strat_entry| strat_entry.inlap == cur_lap)
     }
 
+    /// Metoda zwraca strategię bolidu (wpis 0 zawiera mieszankę startową).
+    pub fn get_strategy(&self) -> &[StrategyEntry] {
+        &self.strategy
+    }
+
+    /// Metoda sprawdza, czy pit stop z okrążeniem zjazdowym `inlap` został wykonany.
+    pub fn is_stop_executed(&self, inlap: u32) -> bool {
+        self.executed_inlaps.contains(&inlap)
+    }
+
+    /// Metoda zwraca pit stopy usunięte ze strategii przez zmianę w trakcie wyścigu.
+    pub fn get_superseded_stops(&self) -> &[StrategyEntry] {
+        &self.superseded_stops
+    }
+
+    /// Metoda zastępuje wszystkie przyszłe pit stopy (okrążenie zjazdowe po `compl_lap`) podanymi
+    /// wpisami. Wpis startowy oraz wykonane już pit stopy pozostają bez zmian. Usunięte pit stopy
+    /// (bez nowego wpisu w tym samym okrążeniu) są zapisywane jako zastąpione.
+    pub fn replace_future_strategy(&mut self, compl_lap: u32, future: &[StrategyEntry]) {
+        let superseded = self.strategy.iter().skip(1).filter(|entry| {
+            entry.inlap > compl_lap && !future.iter().any(|x| x.inlap == entry.inlap)
+        });
+        self.superseded_stops.extend(superseded.cloned());
+
+        let mut strategy: Vec<StrategyEntry> = self
+            .strategy
+            .iter()
+            .enumerate()
+            .filter(|(i, entry)| *i == 0 || entry.inlap <= compl_lap)
+            .map(|(_, entry)| entry.to_owned())
+            .collect();
+
+        strategy.extend(future.iter().filter(|entry| entry.inlap > compl_lap).cloned());
+        strategy[1..].sort_by_key(|entry| entry.inlap);
+        self.strategy = strategy;
+    }
+
     /// Metoda pobiera wpis strategii dla bieżącego okrążenia zjazdowego.
     /// Zwraca `None`, jeśli brak wpisu dla danego `inlap`.
     fn get_strategy_entry(&self, inlap: u32) -> Option<StrategyEntry> {
@@ -186,17 +796,93 @@ impl Car {
             .cloned()
     }
 
-    /// Metoda wykonuje pit stop: tylko zmiana opon.
+    /// Metoda ustawia parametry modelu uszkodzeń.
+    pub fn set_damage_pars(&mut self, damage_pars: DamagePars) {
+        self.damage_pars = damage_pars;
+    }
+
+    /// Metoda zwiększa poziom uszkodzeń bolidu (np. po kontakcie).
+    pub fn add_damage(&mut self, damage: f64) {
+        self.damage += damage.max(0.0);
+    }
+
+    pub fn get_damage(&self) -> f64 {
+        self.damage
+    }
+
+    /// Metoda zwraca true, jeśli uszkodzenia są na tyle duże, że zaplanowany pit stop obejmuje
+    /// naprawę.
+    pub fn needs_repair(&self) -> bool {
+        self.damage > 0.0 && self.damage >= self.damage_pars.repair_threshold
+    }
+
+    /// Metoda oznacza przebicie opony (do najbliższej zmiany opon).
+    pub fn set_punctured(&mut self) {
+        self.punctured = true;
+    }
+
+    pub fn is_punctured(&self) -> bool {
+        self.punctured
+    }
+
+    /// Metoda zwraca liczbę okrążeń bieżącego stintu ponad wiek cliffu mieszanki.
+    pub fn get_tire_age_over_cliff(&self, tire_cfg: &TireConfig) -> f64 {
+        let degr_pars = self.driver.get_degr_pars(&self.tireset.compound);
+        self.tireset.get_age_over_cliff(&degr_pars, tire_cfg)
+    }
+
+    /// Metoda zwraca mieszankę kompletu montowanego po przebiciu opony: mieszankę bieżącego
+    /// stintu albo `fallback_compound`, jeśli w przydziale opon nie ma już nowego kompletu tej
+    /// mieszanki.
+    pub fn get_puncture_compound(&self, fallback_compound: &str) -> String {
+        let compound = self.tireset.compound.to_uppercase();
+
+        match self.tire_inventory.as_ref() {
+            Some(inventory) if inventory.get_no_new_sets(&compound) == 0 => {
+                fallback_compound.to_uppercase()
+            }
+            _ => self.tireset.compound.to_owned(),
+        }
+    }
+
+    /// Metoda wykonuje pit stop: zmiana opon i (przy dużych uszkodzeniach) naprawa. Zwraca true,
+    /// jeśli uszkodzenia zostały naprawione.
     /// Usunięto tankowanie i zmiany kierowców.
-    pub fn perform_pitstop(&mut self, inlap: u32, _drivers_list: &HashMap<String, Rc<Driver>>) {
+    pub fn perform_pitstop(
+        &mut self,
+        inlap: u32,
+        _drivers_list: &HashMap<String, Rc<Driver>>,
+    ) -> bool {
+        self.executed_inlaps.push(inlap);
+
+        // naprawa tylko podczas zaplanowanego postoju (czas naprawy w `t_add_pit_standstill`)
+        let repaired = self.get_strategy_entry(inlap).is_some() && self.needs_repair();
+        if repaired {
+            self.damage = 0.0;
+        }
+
         // get strategy entry (opcjonalnie)
         if let Some(strategy_entry) = self.get_strategy_entry(inlap) {
             // handle tire change
             if !strategy_entry.compound.is_empty() {
+                let age_removed = self.tireset.age_tot;
                 self.tireset = Tireset::new(
                     strategy_entry.compound.to_owned(),
                     strategy_entry.tire_start_age,
                 );
+                self.punctured = false;
+
+                // Z przydziałem opon: zdjęty komplet wraca do przydziału, montowany komplet
+                // zachowuje swój wiek. Gdy brak kompletu danej mieszanki (np. nieplanowana zmiana
+                // na opony deszczowe), montowany jest nowy komplet spoza przydziału.
+                if let Some(inventory) = self.tire_inventory.as_mut() {
+                    inventory.remove_fitted(age_removed, inlap);
+
+                    let compound = strategy_entry.compound.to_uppercase();
+                    if let Ok(age) = inventory.fit_set(&compound, strategy_entry.tire_set, inlap) {
+                        self.tireset.age_tot = age;
+                    }
+                }
                 // Standaryzuj rozpoznawanie slicków do UPPERCASE
                 match self.tireset.compound.to_uppercase().as_str() {
                     "SOFT" | "MEDIUM" | "HARD" => {
@@ -212,18 +898,59 @@ impl Car {
         
         // Tankowanie usunięte – brak modyfikacji m_fuel w pit stopie
 
-        
+        repaired
+    }
+
+    /// Metoda wykonuje darmową zmianę opon podczas czerwonej flagi: montowany jest nowy komplet
+    /// podanej mieszanki albo (bez niej) mieszanki bieżącego stintu.
+    pub fn change_tires_red_flag(&mut self, compound: Option<&str>, compl_lap: u32) {
+        let compound = compound.map_or_else(|| self.tireset.compound.to_owned(), |c| c.to_owned());
+        let age_removed = self.tireset.age_tot;
+        self.tireset = Tireset::new(compound, 0);
+        self.punctured = false;
+
+        // z przydziałem opon zdjęty komplet wraca do przydziału (jak przy pit stopie)
+        if let Some(inventory) = self.tire_inventory.as_mut() {
+            inventory.remove_fitted(age_removed, compl_lap);
+
+            let compound = self.tireset.compound.to_uppercase();
+            if let Ok(age) = inventory.fit_set(&compound, None, compl_lap) {
+                self.tireset.age_tot = age;
+            }
+        }
+
+        if let "SOFT" | "MEDIUM" | "HARD" = self.tireset.compound.to_uppercase().as_str() {
+            self.last_slick_compound = Some(self.tireset.compound.to_owned());
+        }
     }
 
     /// Metoda zwraca czas postoju w alei.
-    /// Tylko czas zmiany opon.
-    pub fn t_add_pit_standstill(&self, inlap: u32) -> f64 {
+    /// Czas zmiany opon, losowany wg parametrów ekipy (średnia, rozrzut, zmęczenie), oraz czas
+    /// naprawy przy dużych uszkodzeniach. Parametry bolidu mają pierwszeństwo przed wartościami
+    /// globalnymi.
+    pub fn t_add_pit_standstill(
+        &mut self,
+        inlap: u32,
+        pit_crew_sigma: f64,
+        pit_crew_mean_offset: f64,
+        pit_crew_fatigue: f64,
+    ) -> f64 {
         let strategy_entry_opt = self.get_strategy_entry(inlap);
 
         // Czas zmiany opon (tylko jeśli strategia przewiduje zmianę)
-        let t_standstill = if let Some(strategy_entry) = strategy_entry_opt {
+        let t_standstill = if let Some(strategy_entry) = strategy_entry_opt.as_ref() {
             if !strategy_entry.compound.is_empty() {
-                self.t_pit_tirechange
+                let sigma = self.pit_crew_sigma.unwrap_or(pit_crew_sigma);
+                let mean = self.t_pit_tirechange
+                    + self.pit_crew_mean_offset.unwrap_or(pit_crew_mean_offset)
+                    + pit_crew_fatigue * self.no_pitstops as f64;
+
+                let t_tirechange = if sigma > 0.0 {
+                    Normal::new(mean, sigma).unwrap().sample(&mut self.rng)
+                } else {
+                    mean
+                };
+                t_tirechange.max(0.0)
             } else {
                 0.0
             }
@@ -232,15 +959,27 @@ impl Car {
             0.0
         };
 
+        // naprawa uszkodzeń (np. wymiana przedniego skrzydła) wydłuża zaplanowany postój
+        let t_repair = if strategy_entry_opt.is_some() && self.needs_repair() {
+            self.damage_pars.t_repair
+        } else {
+            0.0
+        };
+
         // Tankowanie usunięte – brak dodatkowego czasu za tankowanie
 
-        t_standstill
+        t_standstill + t_repair
     }
 
     pub fn get_current_compound(&self) -> &str {
         self.tireset.compound.as_str()
     }
 
+    /// Metoda zwraca wiek opon w bieżącym stincie (w okrążeniach, z uwzględnieniem zużycia).
+    pub fn get_tire_age_cur_stint(&self) -> f64 {
+        self.tireset.age_cur_stint
+    }
+
     pub fn schedule_weather_strategy(&mut self, inlap: u32, compound: &str) {
         if let Some(entry) = self.strategy.iter_mut().find(|e| e.inlap == inlap) {
             entry.compound = compound.to_owned();
@@ -250,10 +989,63 @@ impl Car {
                 tire_start_age: 0,
                 compound: compound.to_owned(),
                 driver_initials: String::new(),
+                tire_set: None,
+                engine_mode: None,
             });
         }
     }
 
+    /// Metoda usuwa zaplanowany pit stop na końcu okrążenia `inlap` (wpis startowy pozostaje) i
+    /// zapisuje go jako zastąpiony. Zwraca false, jeśli taki pit stop nie był zaplanowany.
+    pub fn cancel_pit(&mut self, inlap: u32) -> bool {
+        match self.strategy.iter().skip(1).position(|e| e.inlap == inlap) {
+            Some(pos) => {
+                let entry = self.strategy.remove(pos + 1);
+                self.superseded_stops.push(entry);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Metoda przesuwa pit stop z okrążenia zjazdowego `inlap` na następne okrążenie (np. przy
+    /// zamkniętym wjeździe do alei). Jeśli na następne okrążenie zaplanowany jest już pit stop,
+    /// przesuwany wpis jest usuwany jako zastąpiony. Zwraca false, jeśli pit stop nie był
+    /// zaplanowany.
+    pub fn postpone_pit(&mut self, inlap: u32) -> bool {
+        if self.pit_this_lap(inlap + 1) {
+            return self.cancel_pit(inlap);
+        }
+
+        match self.strategy.iter_mut().skip(1).find(|e| e.inlap == inlap) {
+            Some(entry) => {
+                entry.inlap += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Metoda przenosi najbliższy pit stop zaplanowany w ciągu `max_laps` okrążeń po okrążeniu
+    /// `inlap_target` na okrążenie `inlap_target` (np. tańszy postój za SC). Zwraca pierwotne
+    /// okrążenie zjazdowe albo None, jeśli żaden pit stop nie został przeniesiony.
+    pub fn advance_pit(&mut self, inlap_target: u32, max_laps: u32) -> Option<u32> {
+        if self.pit_this_lap(inlap_target) {
+            return None;
+        }
+
+        let entry = self
+            .strategy
+            .iter_mut()
+            .skip(1)
+            .filter(|e| e.inlap > inlap_target && e.inlap <= inlap_target + max_laps)
+            .min_by_key(|e| e.inlap)?;
+        let inlap_planned = entry.inlap;
+        entry.inlap = inlap_target;
+
+        Some(inlap_planned)
+    }
+
     pub fn set_fuel_mass(&mut self, mass: f64) {
         self.m_fuel = mass.max(0.0);
     }
@@ -262,7 +1054,167 @@ impl Car {
         self.m_fuel
     }
 
+    /// Metoda skaluje zużycie paliwa na okrążenie (np. dla torów z dużymi podjazdami).
+    pub fn scale_fuel_consumption(&mut self, factor: f64) {
+        self.b_fuel_per_lap *= factor;
+    }
+
     pub fn fuel_needed_for_laps(&self, laps: u32) -> f64 {
         self.b_fuel_per_lap * laps as f64
     }
+
+    /// Metoda ustawia parametry trybu oszczędzania paliwa.
+    pub fn set_fuel_save_pars(&mut self, fuel_save_pars: FuelSavePars) {
+        self.fuel_save_pars = fuel_save_pars;
+    }
+
+    /// Metoda zwraca zużycie paliwa w bieżącym okrążeniu (mniejsze w trybie oszczędzania, zależne
+    /// od trybu silnika).
+    pub fn get_fuel_per_lap(&self) -> f64 {
+        let b_fuel_per_lap =
+            self.b_fuel_per_lap * self.engine_mode_pars.get_fuel_factor(self.engine_mode);
+        if self.fuel_save {
+            b_fuel_per_lap * (1.0 - self.fuel_save_pars.reduction)
+        } else {
+            b_fuel_per_lap
+        }
+    }
+
+    /// Metoda zwraca prognozę paliwa na mecie (kg) przy normalnym zużyciu przez pozostałe
+    /// okrążenia (ujemna - paliwa nie wystarczy).
+    pub fn calc_fuel_at_finish(&self, laps_remaining: u32) -> f64 {
+        self.m_fuel - self.fuel_needed_for_laps(laps_remaining)
+    }
+
+    /// Metoda włącza tryb oszczędzania paliwa, gdy przy normalnym zużyciu paliwa nie wystarczy do
+    /// mety (z zachowaniem rezerwy), i wyłącza go, gdy prognoza znów jest wystarczająca. Bez
+    /// zmniejszenia zużycia (`reduction` 0.0) tryb jest wyłączony.
+    pub fn update_fuel_save(&mut self, laps_remaining: u32) {
+        self.fuel_save = self.fuel_save_pars.reduction > 0.0
+            && self.calc_fuel_at_finish(laps_remaining)
+                < self.fuel_save_pars.reserve - FUEL_TOLERANCE;
+    }
+
+    /// Metoda sprawdza, czy w bolidzie zostało mniej paliwa niż wymagana rezerwa (kontrola po
+    /// wyścigu). Bez modelowanego zużycia paliwa kontrola nie ma zastosowania.
+    pub fn is_below_fuel_reserve(&self) -> bool {
+        self.b_fuel_per_lap > 0.0 && self.m_fuel < self.fuel_save_pars.reserve - FUEL_TOLERANCE
+    }
+
+    pub fn is_fuel_save(&self) -> bool {
+        self.fuel_save
+    }
+
+    /// Metoda ustawia parametry trybów silnika.
+    pub fn set_engine_mode_pars(&mut self, engine_mode_pars: EngineModePars) {
+        self.engine_mode_pars = engine_mode_pars;
+    }
+
+    /// Metoda zwraca referencyjny czas okrążenia bolidu (bez trybu silnika i ustawienia).
+    pub fn get_t_car(&self) -> f64 {
+        self.t_car
+    }
+
+    /// Metoda ustawia parametry wpływu ustawienia bolidu (docisku aerodynamicznego).
+    pub fn set_setup_pars(&mut self, setup_pars: SetupPars) {
+        self.setup_pars = setup_pars;
+    }
+
+    /// Metoda zwraca poziom docisku aerodynamicznego bolidu.
+    pub fn get_downforce_level(&self) -> f64 {
+        self.downforce_level
+    }
+
+    /// Metoda zwraca dodatek do czasu okrążenia w zakręcie (s) przy docisku bolidu.
+    pub fn get_corner_t_loss(&self) -> f64 {
+        self.setup_pars.get_corner_t_loss(self.downforce_level)
+    }
+
+    /// Metoda ustawia tryb silnika na następne okrążenie.
+    pub fn set_engine_mode(&mut self, engine_mode: EngineMode) {
+        self.engine_mode = engine_mode;
+    }
+
+    pub fn get_engine_mode(&self) -> EngineMode {
+        self.engine_mode
+    }
+
+    /// Metoda zwraca tryb silnika ustawiony w strategii dla bieżącego stintu (None - wybór
+    /// automatyczny).
+    pub fn get_stint_engine_mode(&self) -> Option<EngineMode> {
+        let inlap_last = self.executed_inlaps.iter().max().copied().unwrap_or(0);
+        self.strategy
+            .iter()
+            .find(|e| e.inlap == inlap_last)
+            .and_then(|e| e.engine_mode)
+    }
+
+    /// Metoda ustawia parametry modelu ERS (bateria na starcie jest pełna).
+    pub fn set_ers_pars(&mut self, ers_pars: ErsPars) {
+        self.ers_pars = ers_pars;
+        self.ers_energy = ers_pars.capacity.max(0.0);
+    }
+
+    pub fn get_ers_energy(&self) -> f64 {
+        self.ers_energy
+    }
+
+    /// Metoda zwraca stan naładowania baterii ERS (0.0 - 1.0, 0.0 bez modelu ERS).
+    pub fn get_ers_soc(&self) -> f64 {
+        if self.ers_pars.capacity > 0.0 {
+            self.ers_energy / self.ers_pars.capacity
+        } else {
+            0.0
+        }
+    }
+
+    /// Metoda zużywa energię ERS przez krok czasowy `timestep_size` (s).
+    pub fn deploy_ers(&mut self, timestep_size: f64) {
+        self.ers_energy = (self.ers_energy - self.ers_pars.deploy_rate * timestep_size).max(0.0);
+    }
+
+    /// Metoda ładuje baterię ERS przez krok czasowy `timestep_size` (s) przy czasie okrążenia
+    /// `laptime` (s). Zwraca true, jeśli bateria była ładowana (nie była pełna).
+    pub fn harvest_ers(&mut self, timestep_size: f64, laptime: f64) -> bool {
+        if self.ers_energy >= self.ers_pars.capacity || !laptime.is_finite() || laptime <= 0.0 {
+            return false;
+        }
+
+        self.ers_energy = (self.ers_energy
+            + self.ers_pars.harvest_per_lap * timestep_size / laptime)
+            .min(self.ers_pars.capacity);
+        true
+    }
+
+    /// Metoda ustawia parametry przegrzewania bolidu.
+    pub fn set_thermal_pars(&mut self, thermal_pars: ThermalPars) {
+        self.thermal_pars = thermal_pars;
+    }
+
+    /// Metoda zwraca względną temperaturę bolidu (0.0 - 1.0).
+    pub fn get_temperature(&self) -> f64 {
+        self.temperature
+    }
+
+    /// Metoda aktualizuje temperaturę bolidu w kroku czasowym: rośnie przy jeździe blisko za
+    /// innym autem (`heating`), w przeciwnym razie spada.
+    pub fn update_temperature(&mut self, timestep_size: f64, heating: bool) {
+        self.temperature = if heating {
+            (self.temperature + self.thermal_pars.heat_rate * timestep_size).min(1.0)
+        } else {
+            (self.temperature - self.thermal_pars.cool_rate * timestep_size).max(0.0)
+        };
+    }
+
+    /// Metoda zwraca stratę czasu okrążenia (s) z powodu przegrzania (0.0 poniżej progu). Strata
+    /// rośnie z nadwyżką temperatury ponad próg aż do `t_loss_max` przy maksymalnej temperaturze.
+    pub fn get_overheat_t_loss(&self) -> f64 {
+        let pars = &self.thermal_pars;
+        if self.temperature <= pars.threshold || pars.threshold >= 1.0 {
+            return 0.0;
+        }
+
+        let excess = (self.temperature - pars.threshold) / (1.0 - pars.threshold);
+        pars.t_loss_max * excess.powf(pars.penalty_exponent)
+    }
 }
\ No newline at end of file
@@ -1,22 +1,36 @@
 use crate::core::tireset::DegrPars;
-use serde::Deserialize;
+use crate::pre::units::deserialize_speed_km_h;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 /// * `initials` - Driver initials, e.g. BOT
 /// * `name` - Driver name, e.g. Valtteri Bottas
-/// * `t_driver` - (s) Time loss per lap due to driver abilities
-/// * `vel_max` - (km/h) Maximum velocity during qualifying
+/// * `t_driver` - (s) Time loss per lap due to driver abilities (race pace)
+/// * `t_driver_quali` - (s) Time loss per lap due to driver abilities in qualifying (OPTIONAL: if
+///   not set, `t_driver` is used)
+/// * `consistency` - (-) Lap time consistency of the driver (1.0 = no lap time scatter)
+/// * `aggression` - (-) Aggression of the driver in duels
+/// * `start_skill` - (-) Launch skill of the driver at a standing start (1.0 = best starter, gains a
+///   few tenths, 0.0 = worst starter, loses up to a second)
+/// * `vel_max` - (km/h) Maximum velocity, limits the straight-line speed in the simulation and the
+///   velocity shown in the GUI, can alternatively be given with a unit suffix (e.g. "330 km/h",
+///   "91.7 m/s")
 /// * `degr_pars_all` - Map containing the degradation parameters for all relevant tire compounds
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct DriverPars {
     pub initials: String,
     pub name: String,
     pub t_driver: f64,
+    #[serde(default)]
+    pub t_driver_quali: Option<f64>,
     #[serde(default = "default_consistency")]
     pub consistency: f64,
     #[serde(default = "default_aggression")]
     pub aggression: f64,
+    #[serde(default = "default_start_skill")]
+    pub start_skill: f64,
     // Usunięto t_teamorder
+    #[serde(deserialize_with = "deserialize_speed_km_h")]
     pub vel_max: f64,
     pub degr_pars_all: HashMap<String, DegrPars>,
 }
@@ -29,15 +43,21 @@ fn default_aggression() -> f64 {
     0.5
 }
 
+fn default_start_skill() -> f64 {
+    0.5
+}
+
 #[derive(Debug)]
 pub struct Driver {
     pub initials: String,
-    name: String,
+    pub name: String,
     pub t_driver: f64,
+    pub t_driver_quali: f64,
     pub consistency: f64,
     pub aggression: f64,
+    pub start_skill: f64,
     // Usunięto t_teamorder
-    vel_max: f64,
+    pub vel_max: f64,
     degr_pars_all: HashMap<String, DegrPars>,
 }
 
@@ -47,8 +67,10 @@ impl Driver {
             initials: driver_pars.initials.to_owned(),
             name: driver_pars.name.to_owned(),
             t_driver: driver_pars.t_driver,
+            t_driver_quali: driver_pars.t_driver_quali.unwrap_or(driver_pars.t_driver),
             consistency: driver_pars.consistency,
             aggression: driver_pars.aggression,
+            start_skill: driver_pars.start_skill,
             // Usunięto t_teamorder
             vel_max: driver_pars.vel_max,
             degr_pars_all: driver_pars.degr_pars_all.to_owned(),
@@ -1,26 +1,345 @@
-use crate::core::race::{Race, WeatherState, SimConstants};
+use crate::core::race::{EventObserver, Race, SimConstants};
+use crate::core::strategy_controls::{LapCallback, StrategyControls};
 use crate::core::tireset::TireConfig;
-use crate::interfaces::gui_interface::{CarState, RaceState, RgbColor, MAX_GUI_UPDATE_FREQUENCY};
+use crate::core::track::Track;
+use crate::interfaces::gui_interface::{GuiSender, RaceState, RaceStateBuilder, SimControl};
+use crate::post::history_config::{fmt_bytes, HistoryBudget, HistoryConfig};
+use crate::post::race_recording::{check_frame_dt, RaceRecording, RecordedCar, RecordedFrame};
 use crate::post::race_result::RaceResult;
 use crate::pre::read_sim_pars::SimPars;
 use anyhow::Context;
-use css_color_parser;
-use flume::Sender;
+use std::rc::Rc;
 use std::thread::sleep;
 use std::time::{Duration, Instant};
 
+/// run_race simulates a race without GUI and event printing and returns the results. This is the
+/// simplest entry point when using the simulator as a library.
+pub fn run_race(
+    sim_pars: &SimPars,
+    sim_consts: &SimConstants,
+    tire_config: &TireConfig,
+    timestep_size: f64,
+) -> anyhow::Result<RaceResult> {
+    handle_race(
+        sim_pars,
+        sim_consts,
+        tire_config,
+        timestep_size,
+        false,
+        None,
+        1.0,
+        false,
+        Vec::new(),
+    )
+}
+
+/// run_race_on_track simulates a race like `run_race` on an already created track. Use it for
+/// batches of runs such that the track geometry is read and processed only once.
+pub fn run_race_on_track(
+    sim_pars: &SimPars,
+    sim_consts: &SimConstants,
+    tire_config: &TireConfig,
+    track: &Rc<Track>,
+    timestep_size: f64,
+) -> anyhow::Result<RaceResult> {
+    sim_pars.check_sim_consts(sim_consts)?;
+
+    let mut race = Race::new_with_track(
+        &sim_pars.race_pars,
+        sim_consts,
+        tire_config,
+        Rc::clone(track),
+        &sim_pars.driver_pars_all,
+        &sim_pars.car_pars_all,
+        timestep_size,
+    );
+    race.print_events = false;
+
+    while !race.get_all_finished() {
+        race.simulate_timestep();
+    }
+
+    Ok(race.get_race_result())
+}
+
+/// RunOptions contains the options of a library race run.
+/// * `timestep_size` - (s) Simulation timestep size
+/// * `print_events` - Print race events to the console
+/// * `on_lap_complete` - Callback invoked after every leader lap with a snapshot of the race and
+///   controls to change the strategies (OPTIONAL)
+pub struct RunOptions {
+    pub timestep_size: f64,
+    pub print_events: bool,
+    pub on_lap_complete: Option<LapCallback>,
+}
+
+impl Default for RunOptions {
+    fn default() -> Self {
+        RunOptions {
+            timestep_size: 0.1,
+            print_events: false,
+            on_lap_complete: None,
+        }
+    }
+}
+
+/// run_race_with_options simulates a race without GUI like `run_race`. If a lap callback is set,
+/// it is invoked whenever the leader completed a lap and the requested strategy changes are
+/// validated and applied before the next timestep. Rejected changes are recorded in
+/// `StrategyControls::rejected` (the callback sees them from its next invocation on) and printed
+/// as warnings if `print_events` is set.
+pub fn run_race_with_options(
+    sim_pars: &SimPars,
+    sim_consts: &SimConstants,
+    tire_config: &TireConfig,
+    mut run_opts: RunOptions,
+) -> anyhow::Result<RaceResult> {
+    sim_pars.check_sim_consts(sim_consts)?;
+
+    // create the race
+    let mut race = Race::new(
+        &sim_pars.race_pars,
+        sim_consts,
+        tire_config,
+        &sim_pars.track_pars,
+        &sim_pars.driver_pars_all,
+        &sim_pars.car_pars_all,
+        run_opts.timestep_size,
+    );
+    race.print_events = run_opts.print_events;
+
+    let mut lap_leader_prev = race.cur_lap_leader;
+    let mut controls = StrategyControls::default();
+
+    while !race.get_all_finished() {
+        race.simulate_timestep();
+
+        // the seam between two laps: hand the snapshot to the callback and apply its changes
+        if race.cur_lap_leader > lap_leader_prev {
+            lap_leader_prev = race.cur_lap_leader;
+
+            if let Some(on_lap_complete) = run_opts.on_lap_complete.as_mut() {
+                on_lap_complete(race.get_lap_context(), &mut controls);
+
+                for command in controls.take_commands() {
+                    if let Err(e) = race.apply_strategy_command(&command) {
+                        let msg = format!("Rejected strategy command {:?}: {}", command, e);
+                        if run_opts.print_events {
+                            println!("WARNING: {}", msg);
+                        }
+                        controls.rejected.push(msg);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(race.get_race_result())
+}
+
+/// record_race simulates a race without GUI like `run_race` but additionally samples the race
+/// state every `recording_frame_dt` seconds of race time (see `HistoryConfig`). The returned
+/// recording can be rendered offline. If the recorded frames would exceed the memory budget, they
+/// are moved to the spill file (if set) or the frame time step is doubled (with a warning).
+pub fn record_race(
+    sim_pars: &SimPars,
+    sim_consts: &SimConstants,
+    tire_config: &TireConfig,
+    timestep_size: f64,
+    history_config: &HistoryConfig,
+    print_events: bool,
+) -> anyhow::Result<(RaceResult, RaceRecording)> {
+    sim_pars.check_sim_consts(sim_consts)?;
+    check_frame_dt(history_config.recording_frame_dt)?;
+
+    // create the race
+    let mut race = Race::new(
+        &sim_pars.race_pars,
+        sim_consts,
+        tire_config,
+        &sim_pars.track_pars,
+        &sim_pars.driver_pars_all,
+        &sim_pars.car_pars_all,
+        timestep_size,
+    );
+    race.print_events = print_events;
+
+    // collect car information (color is converted once)
+    let race_state_builder = RaceStateBuilder::new(&race)?;
+    let cars = race
+        .cars_list
+        .iter()
+        .zip(race_state_builder.get_car_colors().iter())
+        .map(|(car, color)| RecordedCar {
+            car_no: car.car_no,
+            driver_initials: car.driver.initials.to_owned(),
+            color: [color.r, color.g, color.b],
+        })
+        .collect();
+
+    let mut recording = RaceRecording {
+        track_pars: sim_pars.track_pars.to_owned(),
+        tot_no_laps: sim_pars.race_pars.tot_no_laps,
+        frame_dt: history_config.recording_frame_dt,
+        cars,
+        frames: Vec::new(),
+        spill_path: history_config.recording_spill_path.to_owned(),
+        no_spilled_frames: 0,
+    };
+
+    // simulate the race and sample a frame whenever the frame time step has passed
+    let mut budget = HistoryBudget::new(history_config.max_memory_bytes);
+    let mut bytes_race = 0;
+    let mut t_race_update_frame = f64::NEG_INFINITY;
+
+    while !race.get_all_finished() {
+        race.simulate_timestep();
+
+        // histories of the race itself
+        budget.release(bytes_race);
+        bytes_race = race.estimate_history_bytes();
+        budget.add(bytes_race);
+
+        if race.cur_racetime > t_race_update_frame + recording.frame_dt - 0.001 {
+            let frame = RecordedFrame {
+                t: race.cur_racetime,
+                lap_leader: race.cur_lap_leader,
+                race_progs: race.cars_list.iter().map(|car| car.sh.get_race_prog()).collect(),
+                sc_race_prog: RaceStateBuilder::get_sc_race_prog(&race),
+                flag_state: format!("{:?}", race.get_most_severe_flag_state()),
+            };
+            let bytes_frame = frame.estimate_bytes();
+            let mut frame_due = true;
+
+            // free memory until the frame fits into the budget
+            while !budget.fits(bytes_frame) && !recording.frames.is_empty() {
+                if recording.spill_path.is_some() {
+                    budget.release(recording.spill_frames()?);
+                } else {
+                    budget.release(recording.decimate());
+                    eprintln!(
+                        "WARNING: Recording exceeds the memory budget of {}, frame time step \
+                        increased to {:.2}s!",
+                        fmt_bytes(budget.max_bytes),
+                        recording.frame_dt
+                    );
+
+                    // keep the frame time step constant after the decimation
+                    if let Some(frame_last) = recording.frames.last() {
+                        t_race_update_frame = frame_last.t;
+                    }
+                    frame_due =
+                        race.cur_racetime > t_race_update_frame + recording.frame_dt - 0.001;
+                }
+            }
+
+            if frame_due && budget.fits(bytes_frame) {
+                recording.frames.push(frame);
+                budget.add(bytes_frame);
+                t_race_update_frame = race.cur_racetime;
+            }
+        }
+    }
+
+    if print_events {
+        println!(
+            "INFO: Peak history memory: {} (budget {}), {} frames recorded",
+            fmt_bytes(budget.peak_bytes),
+            fmt_bytes(budget.max_bytes),
+            recording.frames.len() + recording.no_spilled_frames
+        );
+    }
+
+    Ok((race.get_race_result(), recording))
+}
+
+/// simulate_realtime simulates the race in real-time until it is finished and sends the race
+/// states to the GUI (pause and speed commands of the GUI are applied). `add_to_state` can add
+/// further data to every race state before it is sent, the final results are sent once at the end.
+pub fn simulate_realtime(
+    race: &mut Race,
+    gui_sender: &mut GuiSender,
+    mut realtime_factor: f64,
+    add_to_state: impl Fn(&Race, &mut RaceState),
+) -> anyhow::Result<()> {
+    let mut t_race_update_print = 0.0;
+
+    // car colors are converted once, they do not change during the race
+    let race_state_builder = RaceStateBuilder::new(race)?;
+
+    let mut paused = false;
+
+    while !race.get_all_finished() {
+        let t_start = Instant::now();
+
+        // apply commands from the GUI (pause, speed)
+        for control in gui_sender.get_controls() {
+            match control {
+                SimControl::Pause(pause) => paused = pause,
+                SimControl::SetRealtimeFactor(factor) => realtime_factor = factor.max(0.01),
+            }
+        }
+
+        if paused {
+            sleep(Duration::from_millis(20));
+            continue;
+        }
+
+        // simulate time step
+        race.simulate_timestep();
+
+        // print status (with a maximum of 1 Hz)
+        if race.cur_racetime > t_race_update_print + 0.9999 {
+            println!(
+                "INFO: Simulating... Current race time is {:.3}s, current lap is {}",
+                race.cur_racetime, race.cur_lap_leader
+            );
+            t_race_update_print = race.cur_racetime;
+        }
+
+        // update GUI (race state is only assembled if it will be sent)
+        if gui_sender.is_due() {
+            let mut race_state = race_state_builder.build_state(race);
+            add_to_state(race, &mut race_state);
+            gui_sender.send(race_state)?;
+        }
+
+        // sleep until time step is finished in real-time as well (calculation in ms)
+        let t_sleep = (race.timestep_size * 1000.0 / realtime_factor) as i64
+            - t_start.elapsed().as_millis() as i64;
+
+        if t_sleep > 0 {
+            sleep(Duration::from_millis(t_sleep as u64));
+        } else {
+            println!("WARNING: Could not keep up with real-time!")
+        }
+    }
+
+    // after real-time loop finishes, send final result once
+    let mut final_msg = race_state_builder.build_final(race, race.get_race_result());
+    add_to_state(race, &mut final_msg);
+    gui_sender
+        .send(final_msg)
+        .context("Failed to send final race result to GUI!")
+}
+
 /// handle_race creates and simulates a race on the basis of the inserted parameters, and returns
-/// the results for post-processing.
+/// the results for post-processing. The event observers are subscribed to the race before the
+/// simulation starts and receive every event as soon as it happens.
+#[allow(clippy::too_many_arguments)]
 pub fn handle_race(
     sim_pars: &SimPars,
     sim_consts: &SimConstants,
     tire_config: &TireConfig,
     timestep_size: f64,
     print_debug: bool,
-    tx: Option<&Sender<RaceState>>,
+    gui_sender: Option<&mut GuiSender>,
     realtime_factor: f64,
     print_events: bool,
+    event_observers: Vec<EventObserver>,
 ) -> anyhow::Result<RaceResult> {
+    sim_pars.check_sim_consts(sim_consts)?;
+
     // create the race
     let mut race = Race::new(
         &sim_pars.race_pars,
@@ -35,11 +354,32 @@ pub fn handle_race(
     // control verbosity for internal race events
     race.print_events = print_events;
 
-    // check if sender was inserted -> in that case use real-time simulation for GUI
-    let sim_realtime = tx.is_some();
+    // feed the event sinks (e.g. live events log, JSONL stream)
+    for observer in event_observers {
+        race.subscribe_with_replay(observer);
+    }
+
+    // print the effective starting grid (after grid penalties) if indicated
+    if print_debug {
+        for slot in race.starting_grid.iter() {
+            let penalty = if slot.back_of_grid {
+                String::from(", back of grid")
+            } else if slot.penalty_places > 0 {
+                format!(", +{} places", slot.penalty_places)
+            } else {
+                String::new()
+            };
 
-    // simulate the race -> execute simulation steps until race is finished for all cars
-    if !sim_realtime {
+            println!(
+                "DEBUG: Grid P{}: car {} (nominal P{}{})",
+                slot.p_grid, slot.car_no, slot.p_grid_nominal, penalty
+            );
+        }
+    }
+
+    // simulate the race -> execute simulation steps until race is finished for all cars. If a GUI
+    // sender was inserted the race is simulated in real-time.
+    if gui_sender.is_none() {
         // NORMAL SIMULATION -----------------------------------------------------------------------
         let mut t_race_update_print = 0.0;
         let mut last_printed_lap = 0u32;
@@ -50,8 +390,11 @@ pub fn handle_race(
             // Optional live progress printing in non-GUI mode when --debug is set
             if print_debug && race.cur_racetime > t_race_update_print + 0.9999 {
                 println!(
-                    "INFO: Simulating... Current race time is {:.3}s, current lap is {}",
-                    race.cur_racetime, race.cur_lap_leader
+                    "INFO: Simulating... Current race time is {:.3}s, current lap is {}, \
+                    history memory is {}",
+                    race.cur_racetime,
+                    race.cur_lap_leader,
+                    fmt_bytes(race.estimate_history_bytes())
                 );
                 t_race_update_print = race.cur_racetime;
             }
@@ -62,103 +405,9 @@ pub fn handle_race(
                 last_printed_lap = race.cur_lap_leader;
             }
         }
-    } else {
+    } else if let Some(gui_sender) = gui_sender {
         // REAL-TIME SIMULATION --------------------------------------------------------------------
-        let mut t_race_update_print = 0.0;
-        let mut t_race_update_gui = 0.0;
-
-        while !race.get_all_finished() {
-            let t_start = Instant::now();
-
-            // simulate time step
-            race.simulate_timestep();
-
-            // print status (with a maximum of 1 Hz)
-            if race.cur_racetime > t_race_update_print + 0.9999 {
-                println!(
-                    "INFO: Simulating... Current race time is {:.3}s, current lap is {}",
-                    race.cur_racetime, race.cur_lap_leader
-                );
-                t_race_update_print = race.cur_racetime;
-            }
-
-            // update GUI
-            if race.cur_racetime > t_race_update_gui + 1.0 / MAX_GUI_UPDATE_FREQUENCY - 0.001 {
-
-                let sc_prog = if race.safety_car.active{
-                    race.safety_car.lap as f64 + race.safety_car.s_track / race.track.length
-                } else {
-                    0.0
-                };
-                // create RaceState struct and set data
-                let mut race_state = RaceState {
-                    car_states: Vec::with_capacity(race.cars_list.len()),
-                    flag_state: race.flag_state.to_owned(),
-                    sc_active: race.safety_car.active,
-                    sc_race_prog: sc_prog,
-                    weather_is_rain: matches!(race.weather_state, WeatherState::Rain),
-                    final_result: None,
-                };
-
-                for (i, car) in race.cars_list.iter().enumerate() {
-                    // convert hex color to a rgb color
-                    let tmp_color = car
-                        .color
-                        .parse::<css_color_parser::Color>()
-                        .context("Could not parse hex color!")?;
-
-                    let velocity = if car.sh.pit_standstill_act {
-                        0.0
-                    } else if car.sh.pit_act {
-                        race.track.pit_speedlimit
-                    } else {
-                        race.track.length / race.cur_laptimes[i]
-                    };
-
-                    race_state.car_states.push(CarState {
-                        car_no: car.car_no,
-                        driver_initials: car.driver.initials.to_owned(),
-                        color: RgbColor {
-                            r: tmp_color.r,
-                            g: tmp_color.g,
-                            b: tmp_color.b,
-                        },
-                        race_prog: car.sh.get_race_prog(),
-                        velocity,
-                    });
-                }
-
-                // send current race state
-                tx.unwrap()
-                    .send(race_state)
-                    .context("Failed to send race state to GUI!")?;
-                t_race_update_gui = race.cur_racetime;
-            }
-
-            // sleep until time step is finished in real-time as well (calculation in ms)
-            let t_sleep = (race.timestep_size * 1000.0 / realtime_factor) as i64
-                - t_start.elapsed().as_millis() as i64;
-
-            if t_sleep > 0 {
-                sleep(Duration::from_millis(t_sleep as u64));
-            } else {
-                println!("WARNING: Could not keep up with real-time!")
-            }
-        }
-
-        // after real-time loop finishes, send final result once
-        if let Some(tx) = tx {
-            let result = race.get_race_result();
-            let final_msg = RaceState {
-                car_states: Vec::new(),
-                flag_state: race.flag_state.to_owned(),
-                sc_active: result.sc_active,
-                sc_race_prog: if result.sc_active { result.sc_position / race.track.length } else { 0.0 },
-                weather_is_rain: matches!(race.weather_state, WeatherState::Rain),
-                final_result: Some(result),
-            };
-            tx.send(final_msg).context("Failed to send final race result to GUI!")?;
-        }
+        simulate_realtime(&mut race, gui_sender, realtime_factor, |_, _| {})?;
     }
 
     // print debug information if indicated
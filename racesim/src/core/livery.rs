@@ -0,0 +1,73 @@
+use anyhow::Context;
+use css_color_parser;
+
+/// LIVERY_PALETTE contains visually distinct colors (Tableau 20) from which the fallback liveries
+/// of cars without a color are chosen.
+const LIVERY_PALETTE: [[u8; 3]; 20] = [
+    [0x1f, 0x77, 0xb4],
+    [0xff, 0x7f, 0x0e],
+    [0x2c, 0xa0, 0x2c],
+    [0xd6, 0x27, 0x28],
+    [0x94, 0x67, 0xbd],
+    [0x8c, 0x56, 0x4b],
+    [0xe3, 0x77, 0xc2],
+    [0x7f, 0x7f, 0x7f],
+    [0xbc, 0xbd, 0x22],
+    [0x17, 0xbe, 0xcf],
+    [0xae, 0xc7, 0xe8],
+    [0xff, 0xbb, 0x78],
+    [0x98, 0xdf, 0x8a],
+    [0xff, 0x98, 0x96],
+    [0xc5, 0xb0, 0xd5],
+    [0xc4, 0x9c, 0x94],
+    [0xf7, 0xb6, 0xd2],
+    [0xc7, 0xc7, 0xc7],
+    [0xdb, 0xdb, 0x8d],
+    [0x9e, 0xda, 0xe5],
+];
+
+/// CarColor is the resolved color of a car.
+/// * `rgb` - Color as RGB values
+/// * `fallback` - True if the car has no color in the parameter file and the fallback livery is
+///   used
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CarColor {
+    pub rgb: [u8; 3],
+    pub fallback: bool,
+}
+
+impl CarColor {
+    /// to_hex returns the color in the hex notation, e.g. "#1F77B4".
+    pub fn to_hex(&self) -> String {
+        format!("#{:02X}{:02X}{:02X}", self.rgb[0], self.rgb[1], self.rgb[2])
+    }
+}
+
+/// get_fallback_color returns the fallback livery of a car. It is determined from a hash of the
+/// car number only, such that a car has the same color in every run and in every output (GUI,
+/// plots, report).
+pub fn get_fallback_color(car_no: u32) -> [u8; 3] {
+    // Fibonacci hashing spreads consecutive car numbers over the palette
+    let hash = car_no.wrapping_mul(0x9E37_79B9) >> 16;
+    LIVERY_PALETTE[hash as usize % LIVERY_PALETTE.len()]
+}
+
+/// resolve_car_color returns the color of a car from its color in the parameter file. An empty
+/// color is replaced by the fallback livery, an invalid color is an error.
+pub fn resolve_car_color(color: &str, car_no: u32) -> anyhow::Result<CarColor> {
+    if color.trim().is_empty() {
+        return Ok(CarColor {
+            rgb: get_fallback_color(car_no),
+            fallback: true,
+        });
+    }
+
+    let tmp_color = color
+        .parse::<css_color_parser::Color>()
+        .with_context(|| format!("Could not parse hex color '{}' of car {}!", color, car_no))?;
+
+    Ok(CarColor {
+        rgb: [tmp_color.r, tmp_color.g, tmp_color.b],
+        fallback: false,
+    })
+}
@@ -1,7 +1,17 @@
 pub mod car;
 pub mod driver;
 pub mod handle_race;
+pub mod livery;
+pub mod model_toggles;
+pub mod practice;
 pub mod race;
-pub mod state_handler;
+pub mod scripted_event;
+pub mod season;
+pub(crate) mod state_handler;
+pub mod strategy_controls;
+pub mod time_of_day;
+pub mod tire_inventory;
 pub mod tireset;
 pub mod track;
+pub mod weekend;
+pub mod whatif;
@@ -0,0 +1,124 @@
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+/// ModelToggles contains the model components that are switched off for the whole race, e.g. to
+/// find out in an ablation study which modeled effect drives a result. The components are stored
+/// as bit flags, all components are enabled by default. In the parameter files the toggles are
+/// given as list of the disabled component names (see `COMPONENTS`).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "Vec<String>", into = "Vec<String>")]
+pub struct ModelToggles {
+    disabled: u8,
+}
+
+impl ModelToggles {
+    /// Tire degradation (the tire term of the lap time is zero, no punctures of worn tires)
+    pub const TIRE_DEGRADATION: u8 = 1 << 0;
+    /// Dirty air behind another car (aero penalty and tire overheating)
+    pub const DIRTY_AIR: u8 = 1 << 1;
+    /// Neutralizations after retirements (SC, VSC, red flag), stopped cars are only covered by
+    /// local yellow flags
+    pub const SC: u8 = 1 << 2;
+    /// Random weather changes
+    pub const WEATHER: u8 = 1 << 3;
+    /// Driver mistakes under pressure and driver-error crashes
+    pub const MISTAKES: u8 = 1 << 4;
+    /// Time losses in duels for position
+    pub const DUELS: u8 = 1 << 5;
+
+    /// Names of the components as used on the command line and in the parameter files.
+    pub const COMPONENTS: [(&'static str, u8); 6] = [
+        ("tire-degradation", ModelToggles::TIRE_DEGRADATION),
+        ("dirty-air", ModelToggles::DIRTY_AIR),
+        ("sc", ModelToggles::SC),
+        ("weather", ModelToggles::WEATHER),
+        ("mistakes", ModelToggles::MISTAKES),
+        ("duels", ModelToggles::DUELS),
+    ];
+
+    /// is_enabled returns true if the given component (one of the flags) is switched on.
+    pub fn is_enabled(&self, component: u8) -> bool {
+        self.disabled & component == 0
+    }
+
+    /// disable switches off the given component (one of the flags).
+    pub fn disable(&mut self, component: u8) {
+        self.disabled |= component;
+    }
+
+    /// disable_all switches off all components that are switched off in the other toggles.
+    pub fn disable_all(&mut self, other: &ModelToggles) {
+        self.disabled |= other.disabled;
+    }
+
+    /// is_any_disabled returns true if at least one component is switched off.
+    pub fn is_any_disabled(&self) -> bool {
+        self.disabled != 0
+    }
+
+    /// get_disabled_names returns the names of the switched off components.
+    pub fn get_disabled_names(&self) -> Vec<&'static str> {
+        ModelToggles::COMPONENTS
+            .iter()
+            .filter(|(_, component)| !self.is_enabled(*component))
+            .map(|(name, _)| *name)
+            .collect()
+    }
+
+    /// get_component returns the flag of the component with the given name.
+    fn get_component(name: &str) -> Result<u8, String> {
+        ModelToggles::COMPONENTS
+            .iter()
+            .find(|(component_name, _)| *component_name == name)
+            .map(|(_, component)| *component)
+            .ok_or_else(|| {
+                let valid: Vec<&str> = ModelToggles::COMPONENTS.iter().map(|x| x.0).collect();
+                format!(
+                    "Unknown model component '{}' (valid: {})!",
+                    name,
+                    valid.join(", ")
+                )
+            })
+    }
+}
+
+impl FromStr for ModelToggles {
+    type Err = String;
+
+    /// from_str parses a comma-separated list of the components to switch off, e.g.
+    /// `tire-degradation,dirty-air`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut model_toggles = ModelToggles::default();
+
+        for name in s.split(',').map(|x| x.trim()).filter(|x| !x.is_empty()) {
+            model_toggles.disable(ModelToggles::get_component(name)?);
+        }
+
+        Ok(model_toggles)
+    }
+}
+
+impl TryFrom<Vec<String>> for ModelToggles {
+    type Error = String;
+
+    fn try_from(names: Vec<String>) -> Result<Self, Self::Error> {
+        let mut model_toggles = ModelToggles::default();
+
+        for name in names.iter() {
+            model_toggles.disable(ModelToggles::get_component(name.trim())?);
+        }
+
+        Ok(model_toggles)
+    }
+}
+
+impl From<ModelToggles> for Vec<String> {
+    fn from(model_toggles: ModelToggles) -> Self {
+        model_toggles
+            .get_disabled_names()
+            .iter()
+            .map(|name| name.to_string())
+            .collect()
+    }
+}
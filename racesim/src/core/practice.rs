@@ -0,0 +1,227 @@
+use crate::core::car::LaptimeBreakdown;
+use crate::core::handle_race::simulate_realtime;
+use crate::core::race::{Race, SimConstants};
+use crate::core::tireset::TireConfig;
+use crate::interfaces::gui_interface::GuiSender;
+use crate::post::output_fmt::OutputFormat;
+use crate::post::race_result::write_output_file;
+use crate::pre::read_sim_pars::SimPars;
+use std::fmt::Write;
+
+/// PracticeLap contains a lap of a practice run.
+/// * `lap` - Lap number (1-based)
+/// * `laptime` - (s) Driven lap time
+/// * `compound` - Compound the lap was started on
+/// * `breakdown` - Components of the theoretical lap time
+#[derive(Debug, Clone)]
+pub struct PracticeLap {
+    pub lap: u32,
+    pub laptime: f64,
+    pub compound: String,
+    pub breakdown: LaptimeBreakdown,
+}
+
+/// PracticeResult contains the laps of a practice run of a single car.
+#[derive(Debug, Clone)]
+pub struct PracticeResult {
+    pub car_no: u32,
+    pub driver_initials: String,
+    pub laps: Vec<PracticeLap>,
+}
+
+/// create_practice_race creates the race of a practice run: the given car circulates alone for the
+/// given number of laps. The car follows its strategy (pit stops within the laps are made),
+/// failures and the start procedure are disabled.
+fn create_practice_race(
+    sim_pars: &SimPars,
+    sim_consts: &SimConstants,
+    tire_config: &TireConfig,
+    car_no: u32,
+    no_laps: u32,
+    timestep_size: f64,
+) -> anyhow::Result<Race> {
+    if !sim_pars.car_pars_all.contains_key(&car_no) {
+        anyhow::bail!("Car {} is not part of the parameter file!", car_no)
+    }
+    if no_laps == 0 {
+        anyhow::bail!("Practice must have at least one lap!")
+    }
+
+    sim_pars.check_sim_consts(sim_consts)?;
+
+    let mut race_pars = sim_pars.race_pars.to_owned();
+    race_pars.participants = vec![car_no];
+    race_pars.tot_no_laps = no_laps;
+    race_pars.scripted_events.clear();
+
+    let mut sim_consts = sim_consts.to_owned();
+    sim_consts.failure_rate_per_hour = 0.0;
+    sim_consts.puncture_probability_over_cliff = 0.0;
+    sim_consts.puncture_debris_probability = 0.0;
+    // no start procedure in practice (the car leaves the grid immediately)
+    sim_consts.start_reaction_mean_s = 0.0;
+    sim_consts.start_reaction_sigma_s = 0.0;
+    sim_consts.jump_start_threshold_s = 0.0;
+    sim_consts.anti_stall_probability = 0.0;
+
+    let mut race = Race::new(
+        &race_pars,
+        &sim_consts,
+        tire_config,
+        &sim_pars.track_pars,
+        &sim_pars.driver_pars_all,
+        &sim_pars.car_pars_all,
+        timestep_size,
+    );
+    race.print_events = false;
+
+    Ok(race)
+}
+
+/// get_practice_result returns the laps of the practice race completed so far together with the
+/// components of their theoretical lap times, which are calculated the same way as in the race.
+pub fn get_practice_result(race: &Race) -> PracticeResult {
+    let laptimes = &race.laptimes[0];
+    let compounds = race.get_compounds(0);
+    let breakdowns = race.get_laptime_breakdowns(0);
+
+    let laps = (1..laptimes.len())
+        .filter(|&lap| laptimes[lap] > 0.0)
+        .map(|lap| PracticeLap {
+            lap: lap as u32,
+            laptime: laptimes[lap],
+            compound: compounds[lap].to_owned(),
+            breakdown: breakdowns[lap],
+        })
+        .collect();
+
+    PracticeResult {
+        car_no: race.cars_list[0].car_no,
+        driver_initials: race.cars_list[0].driver.initials.to_owned(),
+        laps,
+    }
+}
+
+/// run_practice lets a single car circulate alone for the given number of laps (track day / free
+/// practice) and returns every lap together with the components of its theoretical lap time.
+pub fn run_practice(
+    sim_pars: &SimPars,
+    sim_consts: &SimConstants,
+    tire_config: &TireConfig,
+    car_no: u32,
+    no_laps: u32,
+    timestep_size: f64,
+) -> anyhow::Result<PracticeResult> {
+    let mut race =
+        create_practice_race(sim_pars, sim_consts, tire_config, car_no, no_laps, timestep_size)?;
+
+    while !race.get_all_finished() {
+        race.simulate_timestep();
+    }
+
+    Ok(get_practice_result(&race))
+}
+
+/// handle_practice simulates a practice run like `run_practice` in real-time and sends the race
+/// states together with the lap time breakdowns of the laps completed so far to the GUI.
+#[allow(clippy::too_many_arguments)]
+pub fn handle_practice(
+    sim_pars: &SimPars,
+    sim_consts: &SimConstants,
+    tire_config: &TireConfig,
+    car_no: u32,
+    no_laps: u32,
+    timestep_size: f64,
+    gui_sender: &mut GuiSender,
+    realtime_factor: f64,
+) -> anyhow::Result<PracticeResult> {
+    let mut race =
+        create_practice_race(sim_pars, sim_consts, tire_config, car_no, no_laps, timestep_size)?;
+
+    simulate_realtime(&mut race, gui_sender, realtime_factor, |race, race_state| {
+        race_state.practice = Some(get_practice_result(race))
+    })?;
+
+    Ok(get_practice_result(&race))
+}
+
+/// BREAKDOWN_HEADER contains the column titles of the lap time breakdown table.
+pub const BREAKDOWN_HEADER: [&str; 13] = [
+    "lap", "compound", "laptime", "th_laptime", "base", "car", "driver", "tire", "fuel", "weather",
+    "damage", "noise", "launch",
+];
+
+impl PracticeResult {
+    /// get_breakdown_values returns the lap time, the theoretical lap time and its components of a
+    /// lap in the order of the breakdown table.
+    pub fn get_breakdown_values(lap: &PracticeLap) -> [f64; 11] {
+        let breakdown = &lap.breakdown;
+        [
+            lap.laptime,
+            breakdown.get_total(),
+            breakdown.t_base,
+            breakdown.t_car,
+            breakdown.t_driver,
+            breakdown.t_tire,
+            breakdown.t_fuel,
+            breakdown.t_weather,
+            breakdown.t_damage,
+            breakdown.t_noise,
+            breakdown.t_launch,
+        ]
+    }
+
+    /// format returns the lap time breakdown of every lap as a table.
+    pub fn format(&self, out_fmt: &OutputFormat) -> Result<String, std::fmt::Error> {
+        let mut tmp_string = String::new();
+
+        writeln!(
+            &mut tmp_string,
+            "Practice of car {} ({}), lap time breakdown in s:",
+            self.car_no, self.driver_initials
+        )?;
+        write!(&mut tmp_string, "{:>4} {:<12}", BREAKDOWN_HEADER[0], BREAKDOWN_HEADER[1])?;
+        for title in BREAKDOWN_HEADER[2..].iter() {
+            write!(&mut tmp_string, " {:>10}", title)?;
+        }
+        writeln!(&mut tmp_string)?;
+
+        for lap in self.laps.iter() {
+            write!(&mut tmp_string, "{:>4} {:<12}", lap.lap, lap.compound)?;
+            for value in PracticeResult::get_breakdown_values(lap).iter() {
+                write!(&mut tmp_string, " {}", out_fmt.fmt_num(*value, 10, 3))?;
+            }
+            writeln!(&mut tmp_string)?;
+        }
+
+        Ok(tmp_string)
+    }
+
+    /// format_breakdown_csv returns the lap time breakdown of every lap in CSV format.
+    pub fn format_breakdown_csv(&self, out_fmt: &OutputFormat) -> Result<String, std::fmt::Error> {
+        let sep = out_fmt.csv_separator();
+        let mut content = BREAKDOWN_HEADER.join(&sep.to_string());
+        writeln!(&mut content)?;
+
+        for lap in self.laps.iter() {
+            write!(&mut content, "{}{}{}", lap.lap, sep, lap.compound)?;
+            for value in PracticeResult::get_breakdown_values(lap).iter() {
+                write!(&mut content, "{}{}", sep, out_fmt.fmt_num(*value, 0, 3))?;
+            }
+            writeln!(&mut content)?;
+        }
+
+        Ok(content)
+    }
+
+    /// write_breakdown_to_csv writes the lap time breakdown to the given path or to
+    /// output/practice_breakdown.csv. Returns the path to the written file.
+    pub fn write_breakdown_to_csv(
+        &self,
+        path: Option<&std::path::Path>,
+        out_fmt: &OutputFormat,
+    ) -> anyhow::Result<String> {
+        let content = self.format_breakdown_csv(out_fmt)?;
+        write_output_file(path, "practice_breakdown.csv", &content)
+    }
+}
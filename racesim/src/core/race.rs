@@ -1,34 +1,203 @@
-use crate::core::car::{Car, CarPars, CarStatus, StrategyEntry};
+use crate::core::car::{
+    Car, CarPars, CarStatus, DamagePars, EngineMode, EngineModePars, ErsPars, FailureRates,
+    FuelSavePars, LaptimeBreakdown, RetirementCause, SetupPars, ThermalPars,
+    DOWNFORCE_NEUTRAL,
+};
 use crate::core::driver::{Driver, DriverPars};
-use crate::core::track::{Track, TrackPars};
-use crate::core::tireset::TireConfig;
-use crate::post::race_result::{CarDriverPair, RaceEvent, RaceResult};
-use serde::Deserialize;
+use crate::core::livery::{get_fallback_color, resolve_car_color};
+use crate::core::model_toggles::ModelToggles;
+use crate::core::scripted_event::{ScriptedAction, ScriptedEvent, ScriptedOverrides};
+use crate::core::strategy_controls::{CarLapInfo, LapContext, StrategyCommand};
+use crate::core::time_of_day::{get_time_of_day, TrackTempPars};
+use crate::core::tire_inventory::TireInventory;
+use crate::core::track::{MiniSector, Track, TrackPars};
+use crate::core::tireset::{TireConfig, COMPOUNDS};
+use crate::post::race_result::{
+    is_retirement_event_kind, CarDriverPair, GridSlot, IncidentLocation, PitStopInfo, RaceEvent, RaceResult,
+    RetirementInfo, ScDecision, ScOutcome, ScPeriod, ScPeriodKind, TimeLossBreakdown,
+    TimeLossCause, UnexecutedStop, UnexecutedStopReason, DSQ_FUEL_EVENT_KIND,
+    DSQ_TECHNICAL_EVENT_KIND, LAP_FLAG_DAMAGE, LAP_FLAG_FUEL_SAVE, LAP_FLAG_MISTAKE, LAP_FLAG_PIT,
+    LAP_FLAG_SC, LAP_FLAG_TRAFFIC, LAP_FLAG_YELLOW,
+};
+use serde::{Deserialize, Serialize};
 use core::f64;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 // use std::f32::INFINITY; // unused
 use std::rc::Rc;
-use helpers::general::{argmax, argsort, SortOrder};
+use helpers::general::{argmax, argsort, lin_interp, SortOrder};
 use rand_distr::{Normal, Distribution}; 
-use rand::Rng; // bring Rng trait into scope for thread_rng().gen::<T>()
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
 /// * `season` - Sezon
 /// * `tot_no_laps` - Całkowita liczba okrążeń
-/// * `drs_allowed_lap` - (Nieużywane po uproszczeniu)
-/// * `min_t_dist` - (Nieużywane po uproszczeniu)
-/// * `t_duel` - (Nieużywane po uproszczeniu)
+/// * `drs_allowed_lap` - Okrążenie lidera, od którego DRS jest dozwolony
+/// * `min_t_dist` - (s) Odstęp, poniżej którego auta walczą o pozycję
+/// * `t_duel` - (s) Bazowa strata czasu w pojedynku (skalowana głębokością i rodzajem strefy)
 /// * `t_overtake_loser` - (Nieużywane po uproszczeniu)
-/// * `drs_window` - (Nieużywane po uproszczeniu)
-/// * `use_drs` - (Nieużywane po uproszczeniu)
+/// * `drs_window` - (s) Okno DRS do auta bezpośrednio z przodu w punkcie detekcji
+/// * `use_drs` - Czy DRS jest używany
 /// * `participants` - Lista uczestników
+/// * `scripted_events` - Zdarzenia wymuszone w podanych okrążeniach lidera (pogoda, DNF, SC, kary)
+/// * `session_start_h` - (h) Godzina startu wyścigu (czas lokalny, np. 17.0); bez niej pora dnia i
+///   model temperatury toru są wyłączone
+/// * `session_duration_h` - (h) Planowany czas trwania sesji (GUI pokazuje planowany koniec)
+/// * `sunset_h` - (h) Godzina zachodu słońca, od której tor stygnie, a GUI przechodzi w noc
+/// * `vsc_probability` - Prawdopodobieństwo VSC (zamiast SC) przy incydencie jednego auta
+///   (domyślnie 0.0 - VSC tylko z decyzji o SC dla auta w zakręcie, np. 0.3 - realistyczny udział VSC)
+/// * `vsc_delta_s` - (s) Dodatek do teoretycznego czasu okrążenia każdego auta podczas VSC
+/// * `vsc_duration_s` - (s) Czas trwania VSC
+/// * `red_flag_probability` - Prawdopodobieństwo czerwonej flagi (zamiast SC) przy wypadku
+/// * `red_flag_min_cars` - Liczba aut w incydencie, od której wyścig jest zawsze przerywany (brak -
+///   tylko losowo wg `red_flag_probability`)
+/// * `heavy_rain_red_flag_probability` - Prawdopodobieństwo czerwonej flagi na początku ulewy
+///   (zdarzenie `HeavyRain`)
+/// * `red_flag_duration_s` - (s) Czas zawieszenia wyścigu (nie wlicza się do czasu wyścigu)
+/// * `red_flag_laps_deducted` - Liczba okrążeń odejmowana od `tot_no_laps` przy restarcie
+/// * `red_flag_compound` - Mieszanka zakładana wszystkim autom podczas przerwy (brak - nowy komplet
+///   mieszanki bieżącego stintu)
+/// * `pit_closed_on_sc_deploy` - Wjazd do alei zamknięty od wypuszczenia SC do końca pierwszego
+///   pełnego okrążenia lidera za SC (zaplanowane zjazdy są przesuwane na kolejne okrążenie)
+/// * `formation_lap` - Okrążenie formujące przed startem (nie wlicza się do `tot_no_laps`, opony i
+///   paliwo zużywają się w niewielkim stopniu)
+/// * `model_toggles` - Komponenty modelu wyłączone w całym wyścigu (np. `["tire-degradation"]`)
+/// * `max_race_time_s` - (s) Limit czasu wyścigu (np. 7200 s); po jego przekroczeniu wyścig kończy
+///   się z końcem bieżącego okrążenia lidera (brak - bez limitu)
+/// * `sc_pit_window_laps` - Auta, które mają zaplanowany pit stop w ciągu tylu okrążeń, przy
+///   wyjeździe SC zjeżdżają od razu (tańszy postój za SC, brak - zjazdy bez zmian)
+/// * `start_behind_sc_laps` - Liczba okrążeń za SC na starcie, jeśli wyścig startuje w deszczu
+///   (0 - zawsze start z miejsca)
+/// * `start_behind_sc_forced` - Start za SC (przez `start_behind_sc_laps` okrążeń) także na suchym
+///   torze
+/// * `grid_penalties` - Kary na polach startowych: numer auta -> liczba pól przesunięcia do tyłu
+///   (kara co najmniej równa liczbie aut, np. 99, oznacza start z końca stawki)
+/// * `overtake_contact_probability` - (1/s) Prawdopodobieństwo kontaktu na sekundę nieudanej próby
+///   wyprzedzenia w zakręcie przy agresji kierowców 0.5 i konsystencji 1.0 (0.0 - bez kontaktów, zob.
+///   `OvertakeContactPars`)
+/// * `seed` - Ziarno losowań wyścigu; ten sam plik z tym samym ziarnem daje ten sam przebieg wyścigu
+///   (brak - losowe ziarno przy każdym uruchomieniu)
 fn default_initial_weather() -> String { "Dry".to_string() }
 fn default_rain_probability() -> f64 { 0.0 }
 fn default_min_weather_duration_s() -> f64 { 200.0 }
 fn default_fuel_margin() -> f64 { 0.05 }
+fn default_fuel_save_reduction() -> f64 { 0.1 }
+fn default_fuel_save_t_loss_s() -> f64 { 0.6 }
+fn default_ers_capacity_mj() -> f64 { 0.0 }
+fn default_ers_deploy_gain_s() -> f64 { 0.5 }
+fn default_ers_deploy_rate_mj_per_s() -> f64 { 0.12 }
+fn default_ers_harvest_mj_per_lap() -> f64 { 2.0 }
+fn default_ers_harvest_t_loss_s() -> f64 { 0.1 }
+fn default_engine_push_t_gain_s() -> f64 { 0.3 }
+fn default_engine_push_failure_factor() -> f64 { 2.0 }
+fn default_engine_push_fuel_factor() -> f64 { 1.05 }
+fn default_engine_conserve_t_loss_s() -> f64 { 0.3 }
+fn default_engine_conserve_failure_factor() -> f64 { 0.5 }
+fn default_engine_conserve_fuel_factor() -> f64 { 0.95 }
+fn default_engine_mode_auto() -> bool { false }
+fn default_engine_push_gap_s() -> f64 { 3.0 }
+fn default_engine_conserve_gap_s() -> f64 { 10.0 }
+fn default_engine_conserve_laps() -> u32 { 10 }
+fn default_setup_corner_t_loss_s() -> f64 { 0.5 }
+fn default_setup_drag_t_loss_s() -> f64 { 0.5 }
+fn default_setup_wear_sensitivity() -> f64 { 0.1 }
+fn default_setup_overtake_sensitivity() -> f64 { 0.3 }
+fn default_overheat_gap_s() -> f64 { 1.0 }
+fn default_overheat_heat_rate() -> f64 { 0.0 }
+fn default_overheat_cool_rate() -> f64 { 0.01 }
+fn default_overheat_threshold() -> f64 { 0.6 }
+fn default_overheat_t_loss_s() -> f64 { 1.5 }
+fn default_overheat_penalty_exponent() -> f64 { 2.0 }
+fn default_fuel_reserve_min_kg() -> f64 { 0.0 }
+fn default_dsq_technical_probability() -> f64 { 0.0 }
+fn default_overtake_contact_t_loss_s() -> [f64; 2] { [1.5, 5.0] }
+fn default_overtake_contact_spin_probability() -> f64 { 0.3 }
+fn default_overtake_contact_spin_t_loss_s() -> [f64; 2] { [8.0, 15.0] }
+fn default_overtake_contact_dnf_probability() -> f64 { 0.15 }
+fn default_overtake_contact_double_dnf_probability() -> f64 { 0.3 }
+fn default_overtake_contact_attacker_share() -> f64 { 0.7 }
 fn default_failure_rate_per_hour() -> f64 { 0.02 }
 fn default_collision_factor() -> f64 { 20.0 }
-
-#[derive(Debug, Deserialize, Clone)]
+fn default_pit_crew_sigma() -> f64 { 0.0 }
+fn default_pit_crew_mean_offset() -> f64 { 0.0 }
+fn default_pit_crew_fatigue() -> f64 { 0.0 }
+fn default_min_spacing_m() -> f64 { 10.0 }
+fn default_clearance_mean_s() -> f64 { 90.0 }
+fn default_clearance_crash_factor() -> f64 { 0.8 }
+fn default_clearance_corner_factor() -> f64 { 1.0 }
+fn default_clearance_sigma_rel() -> f64 { 0.3 }
+fn default_traffic_flag_t_min() -> f64 { 2.0 }
+fn default_drs_train_overtake_factor() -> f64 { 3.0 }
+fn default_mistake_lockup_t_loss_s() -> f64 { 1.2 }
+fn default_mistake_wide_t_loss_s() -> f64 { 0.8 }
+fn default_mistake_wide_t_gain_s() -> f64 { 0.3 }
+fn default_debris_probability() -> f64 { 0.3 }
+fn default_debris_slowdown_factor() -> f64 { 1.03 }
+fn default_debris_yellow_probability() -> f64 { 0.0 }
+fn default_debris_yellow_no_laps() -> [u32; 2] { [1, 2] }
+fn default_yellow_zone_length_m() -> f64 { 300.0 }
+fn default_yellow_zone_no_laps() -> u32 { 2 }
+fn default_yellow_slowdown_factor() -> f64 { 1.05 }
+fn default_incident_wetness_curve() -> Vec<[f64; 2]> { vec![[0.0, 1.0], [1.0, 2.5]] }
+fn default_wetness_time_constant_s() -> f64 { 300.0 }
+fn default_heavy_rain_wetness() -> f64 { 0.95 }
+fn default_wet_restart_factor() -> f64 { 1.5 }
+fn default_slick_wet_first_lap_factor() -> f64 { 2.0 }
+fn default_duel_corner_factor() -> f64 { 1.5 }
+fn default_duel_straight_factor() -> f64 { 0.5 }
+fn default_duel_defender_share() -> f64 { 0.65 }
+fn default_duel_aggression_weight() -> f64 { 0.5 }
+fn default_start_reaction_mean_s() -> f64 { 0.2 }
+fn default_start_reaction_sigma_s() -> f64 { 0.04 }
+fn default_jump_start_threshold_s() -> f64 { 0.1 }
+fn default_jump_start_penalty_s() -> f64 { 5.0 }
+fn default_anti_stall_probability() -> f64 { 0.01 }
+fn default_anti_stall_delay_s() -> [f64; 2] { [2.0, 4.0] }
+fn default_launch_delta_s() -> [f64; 2] { [-0.3, 0.7] }
+fn default_launch_sigma_s() -> f64 { 0.15 }
+fn default_first_corner_probability() -> f64 { 0.0 }
+fn default_first_corner_dnf_probability() -> f64 { 0.4 }
+fn default_first_corner_t_loss_s() -> [f64; 2] { [3.0, 10.0] }
+fn default_first_corner_damage_s() -> f64 { 0.5 }
+fn default_damage_t_loss_s() -> f64 { 1.0 }
+fn default_damage_wear_factor() -> f64 { 0.5 }
+fn default_damage_repair_threshold() -> f64 { 0.5 }
+fn default_damage_repair_t_s() -> f64 { 4.0 }
+fn default_session_duration_h() -> f64 { 2.0 }
+fn default_sunset_h() -> f64 { 18.0 }
+fn default_vsc_probability() -> f64 { 0.0 }
+fn default_vsc_delta_s() -> f64 { 35.0 }
+fn default_vsc_duration_s() -> f64 { 60.0 }
+fn default_red_flag_probability() -> f64 { 0.0 }
+fn default_red_flag_duration_s() -> f64 { 1200.0 }
+fn default_overtake_contact_probability() -> f64 { 0.0 }
+fn default_track_temp_day_c() -> f64 { 40.0 }
+fn default_track_temp_night_c() -> f64 { 28.0 }
+fn default_track_temp_cooling_time_h() -> f64 { 1.0 }
+fn default_track_temp_ref_c() -> f64 { 35.0 }
+fn default_crash_avoidance_distance_m() -> f64 { 200.0 }
+fn default_crash_avoidance_slowdown_factor() -> f64 { 3.0 }
+fn default_crash_avoidance_duration_s() -> f64 { 10.0 }
+fn default_secondary_collision_probability() -> f64 { 0.05 }
+fn default_driver_crash_probability() -> f64 { 0.0025 }
+fn default_driver_crash_corner_factor() -> f64 { 4.0 }
+fn default_driver_crash_duel_factor() -> f64 { 3.0 }
+fn default_driver_crash_wet_factor() -> f64 { 2.0 }
+fn default_puncture_probability_over_cliff() -> f64 { 0.002 }
+fn default_puncture_debris_probability() -> f64 { 0.05 }
+fn default_puncture_debris_window_s() -> f64 { 120.0 }
+fn default_puncture_fallback_compound() -> String { "HARD".to_string() }
+fn default_sc_probability() -> f64 { 0.8 }
+fn default_sc_corner_factor() -> f64 { 1.25 }
+fn default_sc_overtaking_zone_factor() -> f64 { 0.5 }
+fn default_sc_delta_factor() -> f64 { 1.2 }
+fn default_sc_target_gap_m() -> f64 { 15.0 }
+fn default_vsc_no_mini_sectors() -> usize { 20 }
+fn default_restart_pace_margin() -> f64 { 0.1 }
+fn default_finish_min_pace_factor() -> f64 { 1.5 }
+fn default_formation_pace_factor() -> f64 { 1.6 }
+fn default_formation_wear_factor() -> f64 { 0.3 }
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct RacePars {
     pub season: u32,
     pub tot_no_laps: u32,
@@ -36,44 +205,1224 @@ pub struct RacePars {
     pub track_name: Option<String>,
     #[serde(default = "default_initial_weather")]
     pub initial_weather: String,
+    #[serde(default)]
+    pub initial_wetness: Option<f64>, // wilgotność toru na starcie (domyślnie wg pogody)
     #[serde(default = "default_rain_probability")]
     pub rain_probability: f64,
     pub drs_allowed_lap: u32, 
 
     pub use_drs: bool,        
     pub participants: Vec<u32>,
+    #[serde(default)]
+    pub scripted_events: Vec<ScriptedEvent>,
+    #[serde(default)]
+    pub session_start_h: Option<f64>,
+    #[serde(default = "default_session_duration_h")]
+    pub session_duration_h: f64,
+    #[serde(default = "default_sunset_h")]
+    pub sunset_h: f64,
+    #[serde(default = "default_vsc_probability")]
+    pub vsc_probability: f64,
+    #[serde(default = "default_vsc_delta_s")]
+    pub vsc_delta_s: f64,
+    #[serde(default = "default_vsc_duration_s")]
+    pub vsc_duration_s: f64,
+    #[serde(default = "default_red_flag_probability")]
+    pub red_flag_probability: f64,
+    #[serde(default)]
+    pub red_flag_min_cars: Option<u32>,
+    #[serde(default)]
+    pub heavy_rain_red_flag_probability: f64,
+    #[serde(default = "default_red_flag_duration_s")]
+    pub red_flag_duration_s: f64,
+    #[serde(default)]
+    pub red_flag_laps_deducted: u32,
+    #[serde(default)]
+    pub red_flag_compound: Option<String>,
+    #[serde(default)]
+    pub pit_closed_on_sc_deploy: bool,
+    #[serde(default)]
+    pub formation_lap: bool,
+    #[serde(default)]
+    pub model_toggles: ModelToggles,
+    #[serde(default)]
+    pub max_race_time_s: Option<f64>,
+    #[serde(default)]
+    pub sc_pit_window_laps: Option<u32>,
+    #[serde(default)]
+    pub start_behind_sc_laps: u32,
+    #[serde(default)]
+    pub start_behind_sc_forced: bool,
+    #[serde(default)]
+    pub grid_penalties: HashMap<u32, u32>,
+    #[serde(default = "default_overtake_contact_probability")]
+    pub overtake_contact_probability: f64,
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+/// resolve_grid_order zwraca indeksy aut posortowane według ostatecznej pozycji startowej.
+///
+/// Przesunięcia na starcie są już zawarte w `p_grids`. Auta startujące z końca stawki
+/// (`back_of_grid`) ustawiane są za wszystkimi pozostałymi autami, z zachowaniem ich wzajemnej
+/// kolejności wg `p_grid`.
+pub fn resolve_grid_order(p_grids: &[u32], back_of_grid: &[bool]) -> Vec<usize> {
+    let mut idxs: Vec<usize> = (0..p_grids.len()).collect();
+    idxs.sort_by_key(|&i| (back_of_grid[i], p_grids[i]));
+    idxs
+}
+
+/// apply_grid_penalties zwraca pozycje startowe (1-based) po zastosowaniu kar przesunięcia o
+/// `penalties[i]` pól (0 - brak kary). Auta bez kary przesuwają się do przodu na zwolnione pola.
+/// Kolizje (dwa auta z kary na tym samym polu) rozstrzygane są deterministycznie: pierwszeństwo ma
+/// auto z lepszą nominalną pozycją startową, drugie ustawiane jest bezpośrednio za nim. Kary
+/// wykraczające poza stawkę kończą się na ostatnich polach.
+pub fn apply_grid_penalties(p_grids: &[u32], penalties: &[u32]) -> Vec<u32> {
+    let mut order: Vec<usize> = (0..p_grids.len()).filter(|&i| penalties[i] == 0).collect();
+    order.sort_by_key(|&i| p_grids[i]);
+
+    let mut penalized: Vec<usize> = (0..p_grids.len()).filter(|&i| penalties[i] > 0).collect();
+    penalized.sort_by_key(|&i| (p_grids[i].saturating_add(penalties[i]), p_grids[i]));
+
+    let mut prev_slot: Option<usize> = None;
+
+    for i in penalized {
+        let target = p_grids[i].saturating_add(penalties[i]).saturating_sub(1) as usize;
+        let slot = match prev_slot {
+            Some(prev) => target.max(prev + 1),
+            None => target,
+        }
+        .min(order.len());
+        order.insert(slot, i);
+        prev_slot = Some(slot);
+    }
+
+    let mut p_grids_penalized = vec![0; p_grids.len()];
+    for (pos, &i) in order.iter().enumerate() {
+        p_grids_penalized[i] = pos as u32 + 1;
+    }
+    p_grids_penalized
+}
+
+/// * `mean_s` - (s) Średni czas usuwania skutków incydentu jednego auta stojącego na prostej
+/// * `crash_factor` - (-) Względny wzrost czasu za każde kolejne auto biorące udział w incydencie
+/// * `corner_factor` - (-) Względny wzrost czasu w zakręcie (mało miejsca na poboczu, dźwig)
+/// * `sigma_rel` - (-) Względne odchylenie standardowe czasu usuwania skutków incydentu
+#[derive(Debug, Clone)]
+pub struct ClearancePars {
+    pub mean_s: f64,
+    pub crash_factor: f64,
+    pub corner_factor: f64,
+    pub sigma_rel: f64,
+}
+
+impl ClearancePars {
+    /// calc_mean_clearance_time zwraca oczekiwany czas pracy porządkowych. Wypadek kilku aut trwa
+    /// dłużej niż zatrzymanie jednego auta, a incydent w zakręcie (mnożnik prędkości < 1.0)
+    /// dłużej niż na prostej.
+    pub fn calc_mean_clearance_time(&self, no_cars: usize, multiplier: f64) -> f64 {
+        let severity = 1.0 + self.crash_factor * no_cars.saturating_sub(1) as f64;
+        let location = 1.0 + self.corner_factor * (1.0 - multiplier).max(0.0);
+        self.mean_s * severity * location
+    }
+
+    /// draw_clearance_time losuje czas pracy porządkowych wokół wartości oczekiwanej.
+    pub fn draw_clearance_time<R: Rng + ?Sized>(&self, no_cars: usize, multiplier: f64, rng: &mut R) -> f64 {
+        let mean = self.calc_mean_clearance_time(no_cars, multiplier);
+        let sigma = self.sigma_rel * mean;
+
+        let t_clearance = if sigma > 0.0 {
+            Normal::new(mean, sigma).unwrap().sample(rng)
+        } else {
+            mean
+        };
+
+        // porządkowi zawsze potrzebują chwili czasu
+        t_clearance.max(0.25 * mean)
+    }
+}
+
+/// ScDeploymentPars zawiera parametry losowania SC po wycofaniu auta.
+/// * `probability` - Prawdopodobieństwo SC, gdy auto stanęło poza zakrętem i strefą wyprzedzania
+/// * `corner_factor` - Mnożnik prawdopodobieństwa w zakręcie (auto w żwirze, dźwig na torze)
+/// * `overtaking_zone_factor` - Mnożnik prawdopodobieństwa w strefie wyprzedzania (długa prosta z
+///   szerokim poboczem)
+#[derive(Debug, Clone, Copy)]
+pub struct ScDeploymentPars {
+    pub probability: f64,
+    pub corner_factor: f64,
+    pub overtaking_zone_factor: f64,
+}
+
+impl ScDeploymentPars {
+    /// get_probability zwraca prawdopodobieństwo SC po wycofaniu auta w podanym miejscu toru.
+    pub fn get_probability(&self, location: IncidentLocation) -> f64 {
+        let factor = match location {
+            IncidentLocation::Corner => self.corner_factor,
+            IncidentLocation::OvertakingZone => self.overtaking_zone_factor,
+            IncidentLocation::Other => 1.0,
+        };
+        (self.probability * factor).clamp(0.0, 1.0)
+    }
+}
+
+/// PitClosure opisuje zamknięcie wjazdu do alei po wypuszczeniu SC.
+/// * `t_start` - (s) Czas wyścigu, w którym wypuszczono SC (auta, które minęły wjazd wcześniej,
+///   mogą zjechać)
+/// * `lap_reopen` - Okrążenie lidera, od którego wjazd jest ponownie otwarty
+#[derive(Debug, Clone, Copy)]
+pub struct PitClosure {
+    pub t_start: f64,
+    pub lap_reopen: u32,
+}
+
+/// TimingHistory przechowuje czasy przekroczenia ostatnich punktów pomiaru czasu przez auto (linia
+/// mety oraz granice sektorów), na podstawie których wyznaczany jest odstęp na żywo.
+/// * `no_crossings` - Liczba wszystkich dotychczas przekroczonych punktów pomiaru czasu
+/// * `t_crossings` - (s) Czasy ostatnich przekroczeń (od najstarszego, z doliczonymi karami)
+#[derive(Debug, Clone, Default)]
+pub struct TimingHistory {
+    pub no_crossings: usize,
+    pub t_crossings: VecDeque<f64>,
+}
+
+/// TIMING_HISTORY_LEN to liczba przechowywanych przekroczeń punktów pomiaru czasu (3 okrążenia).
+pub const TIMING_HISTORY_LEN: usize = 9;
+
+impl TimingHistory {
+    /// get_t_crossing zwraca czas przekroczenia punktu pomiaru czasu o podanym numerze (None, jeśli
+    /// nie został jeszcze przekroczony lub nie ma go już w historii).
+    pub fn get_t_crossing(&self, timing_point_no: usize) -> Option<f64> {
+        if timing_point_no >= self.no_crossings {
+            return None;
+        }
+
+        let no_dropped = self.no_crossings - self.t_crossings.len();
+        timing_point_no
+            .checked_sub(no_dropped)
+            .and_then(|idx| self.t_crossings.get(idx).copied())
+    }
+}
+
+/// Incident opisuje zdarzenie (awaria lub wypadek), którego skutki muszą usunąć porządkowi.
+/// * `cars` - Numery aut biorących udział w incydencie
+/// * `s_track` - (m) Miejsce incydentu na torze
+/// * `t_clearance` - (s) Wylosowany czas usuwania skutków incydentu
+/// * `t_elapsed` - (s) Czas pracy porządkowych, który już upłynął
+#[derive(Debug, Clone)]
+pub struct Incident {
+    pub cars: Vec<u32>,
+    pub s_track: f64,
+    pub t_clearance: f64,
+    pub t_elapsed: f64,
+}
+
+impl Incident {
+    /// get_progress zwraca postęp usuwania skutków incydentu (0.0 - 1.0).
+    pub fn get_progress(&self) -> f64 {
+        if self.t_clearance > 0.0 {
+            (self.t_elapsed / self.t_clearance).min(1.0)
+        } else {
+            1.0
+        }
+    }
+
+    pub fn is_cleared(&self) -> bool {
+        self.t_elapsed >= self.t_clearance
+    }
+}
+
+/// CrashSite opisuje świeży wypadek (auto stojące na torze), zanim flaga jest oficjalnie
+/// wywieszona. Auta zbliżające się do niego mocno zwalniają i mogą najechać na rozbite auto.
+/// * `idx` - Indeks rozbitego auta
+/// * `s_track` - (m) Miejsce wypadku na torze
+/// * `t_elapsed` - (s) Czas od wypadku
+/// * `checked` - Auta, dla których wylosowano już najechanie na rozbite auto
+#[derive(Debug, Clone)]
+pub struct CrashSite {
+    pub idx: usize,
+    pub s_track: f64,
+    pub t_elapsed: f64,
+    pub checked: Vec<bool>,
+}
+
+/// CrashAvoidancePars zawiera parametry omijania świeżego wypadku.
+/// * `distance_m` - (m) Odległość za wypadkiem, w której auta zwalniają
+/// * `slowdown_factor` - Mnożnik czasu okrążenia przy omijaniu wypadku
+/// * `duration_s` - (s) Czas od wypadku do oficjalnej flagi
+/// * `collision_probability` - Szansa najechania na rozbite auto tuż za nim na suchym torze (maleje
+///   liniowo z odległością, rośnie z wilgotnością toru)
+#[derive(Debug, Clone, Copy)]
+pub struct CrashAvoidancePars {
+    pub distance_m: f64,
+    pub slowdown_factor: f64,
+    pub duration_s: f64,
+    pub collision_probability: f64,
+}
+
+/// calc_secondary_collision_prob zwraca szansę najechania na rozbite auto z podanej odległości za
+/// nim (0 poza odległością omijania).
+pub fn calc_secondary_collision_prob(
+    dist: f64,
+    incident_factor: f64,
+    pars: &CrashAvoidancePars,
+) -> f64 {
+    if pars.distance_m <= 0.0 || dist > pars.distance_m {
+        return 0.0;
+    }
+
+    let proximity = 1.0 - dist.max(0.0) / pars.distance_m;
+    (pars.collision_probability * proximity * incident_factor).clamp(0.0, 1.0)
+}
+
+/// DriverCrashPars zawiera parametry wypadków z winy kierowcy (błąd kończący się DNF).
+/// * `probability` - Prawdopodobieństwo wypadku na okrążenie kierowcy o konsystencji 0.0 na
+///   suchym torze poza zakrętami i pojedynkami (skalowane przez `1 - konsystencja`)
+/// * `corner_factor` - Mnożnik prawdopodobieństwa w zakręcie
+/// * `duel_factor` - Mnożnik prawdopodobieństwa w pojedynku o pozycję
+/// * `wet_factor` - Mnożnik prawdopodobieństwa na całkowicie mokrym torze (liniowo z wilgotnością,
+///   dodatkowo do mnożnika incydentów)
+#[derive(Debug, Clone, Copy)]
+pub struct DriverCrashPars {
+    pub probability: f64,
+    pub corner_factor: f64,
+    pub duel_factor: f64,
+    pub wet_factor: f64,
+}
+
+/// calc_driver_crash_prob zwraca prawdopodobieństwo wypadku kierowcy na okrążenie przy bieżącym
+/// stanie auta (zakręt, pojedynek) i toru (wilgotność, mnożnik incydentów).
+pub fn calc_driver_crash_prob(
+    consistency: f64,
+    corner_act: bool,
+    duel_act: bool,
+    wetness: f64,
+    incident_factor: f64,
+    pars: &DriverCrashPars,
+) -> f64 {
+    let mut prob = pars.probability * (1.0 - consistency).clamp(0.0, 1.0) * incident_factor;
+
+    if corner_act {
+        prob *= pars.corner_factor;
+    }
+    if duel_act {
+        prob *= pars.duel_factor;
+    }
+    prob *= 1.0 + (pars.wet_factor - 1.0) * wetness.clamp(0.0, 1.0);
+
+    prob.clamp(0.0, 1.0)
+}
+
+/// PuncturePars zawiera parametry przebić opon (nieplanowany zjazd do alei).
+/// * `probability_over_cliff` - Prawdopodobieństwo przebicia na okrążenie za każde okrążenie
+///   stintu ponad wiek cliffu mieszanki (np. 0.002 - 0.01 na okrążenie 5 okrążeń po cliffie)
+/// * `debris_probability` - Prawdopodobieństwo przebicia przy przejeździe przez miejsce wypadku
+/// * `debris_window_s` - (s) Czas od wypadku, przez który w jego miejscu leży gruz
+/// * `fallback_compound` - Mieszanka montowana po przebiciu, gdy w przydziale opon nie ma nowego
+///   kompletu bieżącej mieszanki
+#[derive(Debug, Clone)]
+pub struct PuncturePars {
+    pub probability_over_cliff: f64,
+    pub debris_probability: f64,
+    pub debris_window_s: f64,
+    pub fallback_compound: String,
+}
+
+/// calc_puncture_prob zwraca prawdopodobieństwo przebicia opony na okrążenie przy danej liczbie
+/// okrążeń stintu ponad wiek cliffu mieszanki.
+pub fn calc_puncture_prob(age_over_cliff: f64, pars: &PuncturePars) -> f64 {
+    (pars.probability_over_cliff * age_over_cliff.max(0.0)).clamp(0.0, 1.0)
+}
+
+/// DebrisZone opisuje gruz po drobnym kontakcie. Do czasu usunięcia obowiązuje na tym odcinku
+/// lokalna żółta flaga (zakaz wyprzedzania, niewielkie spowolnienie).
+/// * `s_range` - (m) Odcinek toru z gruzem (może przechodzić przez linię mety)
+/// * `cars` - Numery aut biorących udział w kontakcie
+/// * `lap_cleared` - Okrążenie lidera, od którego gruz jest usunięty
+#[derive(Debug, Clone)]
+pub struct DebrisZone {
+    pub s_range: [f64; 2],
+    pub cars: Vec<u32>,
+    pub lap_cleared: u32,
+}
+
+/// YellowZone opisuje lokalną żółtą flagę wokół auta, które odpadło na torze. Do czasu usunięcia
+/// auta obowiązuje na tym odcinku zakaz wyprzedzania i pojedynków, a auta zwalniają.
+/// * `s_range` - (m) Odcinek toru z żółtą flagą (może przechodzić przez linię mety)
+/// * `car_no` - Numer auta, które odpadło
+/// * `lap_cleared` - Okrążenie lidera, od którego żółta flaga nie obowiązuje
+#[derive(Debug, Clone)]
+pub struct YellowZone {
+    pub s_range: [f64; 2],
+    pub car_no: u32,
+    pub lap_cleared: u32,
+}
+
+/// is_in_s_range sprawdza, czy koordynat s leży w odcinku toru (również przechodzącym przez linię
+/// mety).
+pub fn is_in_s_range(s_track: f64, s_range: [f64; 2]) -> bool {
+    if s_range[0] <= s_range[1] {
+        s_range[0] <= s_track && s_track <= s_range[1]
+    } else {
+        s_range[0] <= s_track || s_track <= s_range[1]
+    }
+}
+
+/// calc_velocity_capped_laptime ogranicza czas okrążenia tak, aby prędkość chwilowa
+/// (długość toru / czas okrążenia) nie przekraczała prędkości maksymalnej `vel_max` (km/h). Dotyczy
+/// to w praktyce tylko szybkich fragmentów toru (duży mnożnik prędkości).
+pub fn calc_velocity_capped_laptime(laptime: f64, track_length: f64, vel_max: f64) -> f64 {
+    if vel_max <= 0.0 {
+        return laptime;
+    }
+
+    laptime.max(track_length / (vel_max / 3.6))
+}
+
+/// calc_crossing_time zwraca czas, w którym postęp wyścigu `race_prog_target` (np. linia mety)
+/// został przekroczony w kroku czasowym rozpoczętym w `t_step_start`, w którym postęp zmienił się z
+/// `race_prog_prev` na `race_prog_cur`. W obrębie kroku zakładana jest stała prędkość (interpolacja
+/// liniowa), więc czas nie jest kwantyzowany do wielkości kroku.
+pub fn calc_crossing_time(
+    t_step_start: f64,
+    timestep_size: f64,
+    race_prog_prev: f64,
+    race_prog_cur: f64,
+    race_prog_target: f64,
+) -> f64 {
+    let delta_race_prog = race_prog_cur - race_prog_prev;
+
+    if delta_race_prog <= 0.0 {
+        return t_step_start + timestep_size;
+    }
+
+    let step_frac = ((race_prog_target - race_prog_prev) / delta_race_prog).clamp(0.0, 1.0);
+    t_step_start + step_frac * timestep_size
+}
+
+/// calc_sc_target_laptime zwraca docelowy czas okrążenia auta za SC na podstawie jego odstępu
+/// `gap` (m) do poprzednika w kolejce (SC lub auto). Przy odstępie `target_gap` auto jedzie w
+/// tempie SC, przy większym przyspiesza proporcjonalnie do różnicy (`catchup_factor`), jednak nie
+/// szybciej niż w czasie `laptime_min` (delta SC), a przy mniejszym zwalnia (najwyżej do 10 m/s).
+pub fn calc_sc_target_laptime(
+    gap: f64,
+    target_gap: f64,
+    catchup_factor: f64,
+    sc_speed: f64,
+    track_length: f64,
+    laptime_min: f64,
+) -> f64 {
+    let speed_max = track_length / laptime_min;
+    let target_speed = (sc_speed + (gap - target_gap) * catchup_factor).min(speed_max).max(10.0);
+    track_length / target_speed
+}
+
+/// find_drs_trains zwraca pociągi DRS, tj. grupy co najmniej 3 kolejnych aut (w kolejności na
+/// torze), z których każde ma DRS (uprawnienie z punktu detekcji). Kolejność jest traktowana
+/// cyklicznie: auta na początku listy (tuż przed linią mety) jadą za autami z końca listy (tuż za
+/// linią), dzięki czemu pociąg nie rozpada się przy przekraczaniu linii mety.
+pub fn find_drs_trains(car_nos: &[u32], drs_eligible: &[bool]) -> Vec<Vec<u32>> {
+    let mut segments: Vec<Vec<u32>> = vec![];
+    let mut cur_train: Vec<u32> = vec![];
+
+    for (car_no, &eligible) in car_nos.iter().zip(drs_eligible.iter()) {
+        if eligible {
+            cur_train.push(*car_no);
+        } else if !cur_train.is_empty() {
+            segments.push(cur_train.to_owned());
+            cur_train.clear();
+        }
+    }
+
+    // połączenie odcinka na końcu listy z odcinkiem na jej początku (o ile nie są jednym odcinkiem)
+    let wraps = drs_eligible.first() == Some(&true)
+        && drs_eligible.last() == Some(&true)
+        && !segments.is_empty();
+
+    if wraps {
+        cur_train.append(&mut segments[0]);
+        segments[0] = cur_train;
+    } else if !cur_train.is_empty() {
+        segments.push(cur_train);
+    }
+
+    segments.into_iter().filter(|x| x.len() >= 3).collect()
+}
+
+/// compare_drs_trains zwraca pociągi DRS, które powstały, oraz te, które się rozpadły między
+/// `prev_drs_trains` a `drs_trains`. Pociąg mający wspólne auto z pociągiem z drugiej listy jest
+/// jego kontynuacją (np. po dołączeniu lub odpadnięciu jednego auta) i nie jest zwracany.
+pub fn compare_drs_trains(
+    prev_drs_trains: &[Vec<u32>],
+    drs_trains: &[Vec<u32>],
+) -> (Vec<Vec<u32>>, Vec<Vec<u32>>) {
+    let shares_car = |a: &[u32], b: &[u32]| a.iter().any(|car_no| b.contains(car_no));
+
+    let formed: Vec<Vec<u32>> = drs_trains
+        .iter()
+        .filter(|x| !prev_drs_trains.iter().any(|y| shares_car(x, y)))
+        .cloned()
+        .collect();
+    let ended: Vec<Vec<u32>> = prev_drs_trains
+        .iter()
+        .filter(|y| !drs_trains.iter().any(|x| shares_car(x, y)))
+        .cloned()
+        .collect();
+
+    (formed, ended)
+}
+
+/// clamp_race_progs_min_spacing zwraca postępy wyścigu po wymuszeniu minimalnego odstępu
+/// `min_spacing` (w ułamku okrążenia) między kolejnymi autami.
+///
+/// Auta są przetwarzane od prowadzącego do ostatniego, tak aby przesunięcie jednego auta do tyłu
+/// zostało uwzględnione przy następnym. Auta nieaktywne (`active == false`, np. DNF lub w alei
+/// serwisowej) są pomijane, a auta z `may_overlap == true` (wyprzedzanie w strefie wyprzedzania)
+/// nie są przesuwane. Auto nigdy nie jest cofane za swoją pozycję z poprzedniego kroku. Auto, które
+/// już w poprzednim kroku było bliżej niż `min_spacing` (np. pola startowe), nie może się zbliżyć
+/// bardziej, ale jedzie dalej, gdy auto przed nim odjeżdża.
+pub fn clamp_race_progs_min_spacing(
+    race_progs_prev: &[f64],
+    race_progs: &[f64],
+    active: &[bool],
+    may_overlap: &[bool],
+    min_spacing: f64,
+) -> Vec<f64> {
+    let idxs: Vec<usize> = argsort(race_progs, SortOrder::Descending)
+        .into_iter()
+        .filter(|&i| active[i])
+        .collect();
+
+    let mut race_progs_clamped = race_progs.to_owned();
+    let mut idx_front: Option<usize> = None;
+
+    for &i in idxs.iter() {
+        if let Some(idx_front) = idx_front {
+            let spacing = min_spacing
+                .min(race_progs_prev[idx_front] - race_progs_prev[i])
+                .max(0.0);
+            let limit = race_progs_clamped[idx_front] - spacing;
+
+            if !may_overlap[i] && race_progs_clamped[i] > limit {
+                race_progs_clamped[i] = limit.max(race_progs_prev[i]);
+            }
+        }
+
+        idx_front = Some(i);
+    }
+
+    race_progs_clamped
+}
+
+/// StartPars zawiera parametry procedury startu.
+/// * `reaction_mean_s` - (s) Średni czas reakcji kierowcy o pełnej konsystencji
+/// * `reaction_sigma_s` - (s) Odchylenie standardowe czasu reakcji kierowcy o pełnej konsystencji
+/// * `jump_start_threshold_s` - (s) Czas reakcji, poniżej którego start jest falstartem
+/// * `anti_stall_probability` - (-) Prawdopodobieństwo włączenia anti-stall
+/// * `anti_stall_delay_s` - (s) Zakres [min, max] dodatkowej straty przy anti-stall
+/// * `launch_delta_s` - (s) Średnia delta startu (strata w 1. okrążeniu ponad `t_loss_firstlap`)
+///   kierowcy o `start_skill` 1.0 i 0.0
+/// * `launch_sigma_s` - (s) Odchylenie standardowe delty startu
+#[derive(Debug, Clone, Copy)]
+pub struct StartPars {
+    pub reaction_mean_s: f64,
+    pub reaction_sigma_s: f64,
+    pub jump_start_threshold_s: f64,
+    pub anti_stall_probability: f64,
+    pub anti_stall_delay_s: [f64; 2],
+    pub launch_delta_s: [f64; 2],
+    pub launch_sigma_s: f64,
+}
+
+/// (s) Największy zysk i największa strata przez deltę startu
+const LAUNCH_DELTA_MAX_S: f64 = 1.0;
+
+/// (s) Postój na polu startowym, od którego start jest zapisywany jako zdarzenie (wolna reakcja)
+const START_NOTABLE_DELAY_S: f64 = 0.5;
+/// (s) Zysk lub strata przez deltę startu, od których start jest zapisywany jako zdarzenie
+const START_NOTABLE_LAUNCH_DELTA_S: f64 = 0.5;
+
+/// StartReaction to wylosowany przebieg startu jednego auta.
+/// * `t_reaction` - (s) Czas reakcji na zgaśnięcie świateł
+/// * `t_anti_stall` - (s) Dodatkowa strata przez anti-stall (0 bez anti-stall)
+/// * `jump_start` - Czy reakcja była szybsza niż granica falstartu
+/// * `launch_delta` - (s) Delta startu w 1. okrążeniu ponad `t_loss_firstlap` (ujemna - zysk)
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub struct StartReaction {
+    pub t_reaction: f64,
+    pub t_anti_stall: f64,
+    pub jump_start: bool,
+    #[serde(default)]
+    pub launch_delta: f64,
+}
+
+impl StartReaction {
+    /// get_delay zwraca czas, przez który auto stoi na polu startowym po zgaśnięciu świateł.
+    pub fn get_delay(&self) -> f64 {
+        self.t_reaction + self.t_anti_stall
+    }
+
+    /// is_notable zwraca true dla startów wartych odnotowania: falstartu, anti-stall, postoju na
+    /// polu startowym co najmniej `START_NOTABLE_DELAY_S` albo delty startu co najmniej
+    /// `START_NOTABLE_LAUNCH_DELTA_S`.
+    pub fn is_notable(&self) -> bool {
+        self.jump_start
+            || self.t_anti_stall > 0.0
+            || self.get_delay() >= START_NOTABLE_DELAY_S
+            || self.launch_delta.abs() >= START_NOTABLE_LAUNCH_DELTA_S
+    }
+}
+
+impl StartPars {
+    /// draw_start_reaction losuje start kierowcy. Mniej konsystentny kierowca reaguje średnio
+    /// wolniej i z większym rozrzutem (średnia i odchylenie skalowane `2 - consistency`). Falstart
+    /// oceniany jest na podstawie samej reakcji, anti-stall dokłada stratę już po ruszeniu, a delta
+    /// startu (zależna od `start_skill`) wydłuża lub skraca 1. okrążenie.
+    pub fn draw_start_reaction<R: Rng + ?Sized>(
+        &self,
+        consistency: f64,
+        start_skill: f64,
+        rng: &mut R,
+    ) -> StartReaction {
+        let scale = 2.0 - consistency.clamp(0.0, 1.0);
+        let mean = self.reaction_mean_s * scale;
+        let sigma = self.reaction_sigma_s * scale;
+
+        let t_reaction = if sigma > 0.0 {
+            Normal::new(mean, sigma).unwrap().sample(rng)
+        } else {
+            mean
+        }
+        .max(0.0);
+
+        let t_anti_stall = if rng.gen::<f64>() < self.anti_stall_probability {
+            let [t_min, t_max] = self.anti_stall_delay_s;
+            t_min + rng.gen::<f64>() * (t_max - t_min)
+        } else {
+            0.0
+        };
+
+        StartReaction {
+            t_reaction,
+            t_anti_stall,
+            jump_start: t_reaction < self.jump_start_threshold_s,
+            launch_delta: self.draw_launch_delta(start_skill, rng),
+        }
+    }
+
+    /// draw_launch_delta losuje deltę startu kierowcy (ujemna - zysk). Średnia zmienia się
+    /// liniowo od `launch_delta_s[0]` (start_skill 1.0) do `launch_delta_s[1]` (start_skill 0.0),
+    /// a wynik jest ograniczony do jednej sekundy zysku lub straty.
+    pub fn draw_launch_delta<R: Rng + ?Sized>(&self, start_skill: f64, rng: &mut R) -> f64 {
+        let [delta_best, delta_worst] = self.launch_delta_s;
+        let mean = delta_best + (delta_worst - delta_best) * (1.0 - start_skill.clamp(0.0, 1.0));
+
+        let launch_delta = if self.launch_sigma_s > 0.0 {
+            Normal::new(mean, self.launch_sigma_s).unwrap().sample(rng)
+        } else {
+            mean
+        };
+
+        launch_delta.clamp(-LAUNCH_DELTA_MAX_S, LAUNCH_DELTA_MAX_S)
+    }
+}
+
+/// (-) Liczba aut i agresja kierowców, dla których obowiązuje `FirstCornerPars::probability`
+const FIRST_CORNER_REF_NO_CARS: f64 = 20.0;
+const FIRST_CORNER_REF_AGGRESSION: f64 = 0.5;
+
+/// FirstCornerPars zawiera parametry karambolu w pierwszym zakręcie po starcie.
+/// * `probability` - Prawdopodobieństwo karambolu przy 20 autach o średniej agresji 0.5
+/// * `dnf_probability` - Prawdopodobieństwo, że auto biorące udział w karambolu odpada z wyścigu
+/// * `t_loss_s` - (s) Zakres [min, max] straty auta, które jedzie dalej (obrót, wyjazd poza tor)
+#[derive(Debug, Clone, Copy)]
+pub struct FirstCornerPars {
+    pub probability: f64,
+    pub dnf_probability: f64,
+    pub t_loss_s: [f64; 2],
+}
+
+/// FirstCornerIncident to wylosowany karambol w pierwszym zakręcie.
+/// * `idxs` - Indeksy aut biorących udział w karambolu (w kolejności pola startowego)
+/// * `t_losses` - (s) Strata każdego auta, które jedzie dalej (None - auto odpada z wyścigu)
+#[derive(Debug, Clone, PartialEq)]
+pub struct FirstCornerIncident {
+    pub idxs: Vec<usize>,
+    pub t_losses: Vec<Option<f64>>,
+}
+
+impl FirstCornerPars {
+    /// get_midfield zwraca środek stawki (w kolejności pola startowego): bez pierwszego rzędu,
+    /// który wchodzi w zakręt z czystym torem, i bez ostatniej piątej części pola, która ma czas
+    /// na ominięcie karambolu.
+    pub fn get_midfield(grid_order: &[usize]) -> &[usize] {
+        let no_cars = grid_order.len();
+        let end = no_cars - no_cars / 5;
+
+        if end > 2 {
+            &grid_order[2..end]
+        } else {
+            &[]
+        }
+    }
+
+    /// calc_probability zwraca prawdopodobieństwo karambolu. Rośnie ono liniowo z liczbą aut na
+    /// starcie i ze średnią agresją kierowców ze środka stawki.
+    pub fn calc_probability(&self, no_cars: usize, aggressions_midfield: &[f64]) -> f64 {
+        if aggressions_midfield.is_empty() {
+            return 0.0;
+        }
+
+        let mean_aggression =
+            aggressions_midfield.iter().sum::<f64>() / aggressions_midfield.len() as f64;
+
+        (self.probability * no_cars as f64 / FIRST_CORNER_REF_NO_CARS * mean_aggression
+            / FIRST_CORNER_REF_AGGRESSION)
+            .clamp(0.0, 1.0)
+    }
+
+    /// draw_incident losuje, czy w pierwszym zakręcie dojdzie do karambolu. Karambol zaczyna
+    /// losowe auto ze środka stawki i obejmuje jeszcze jedno lub dwa auta startujące tuż za nim.
+    /// Każde auto z prawdopodobieństwem `dnf_probability` odpada, w przeciwnym razie traci czas i
+    /// jedzie dalej z uszkodzeniami.
+    /// * `grid_order` - Indeksy aut w kolejności pola startowego
+    /// * `aggressions` - Agresja kierowców (wg indeksów aut)
+    pub fn draw_incident<R: Rng + ?Sized>(
+        &self,
+        grid_order: &[usize],
+        aggressions: &[f64],
+        rng: &mut R,
+    ) -> Option<FirstCornerIncident> {
+        let midfield = FirstCornerPars::get_midfield(grid_order);
+        let aggressions_midfield: Vec<f64> = midfield.iter().map(|&i| aggressions[i]).collect();
+        let probability = self.calc_probability(grid_order.len(), &aggressions_midfield);
+
+        if midfield.is_empty() || rng.gen::<f64>() >= probability {
+            return None;
+        }
+
+        // pierwsze auto karambolu i auta startujące tuż za nim
+        let pos_first = 2 + rng.gen_range(0..midfield.len());
+        let no_involved = rng.gen_range(2..=3);
+        let idxs: Vec<usize> = grid_order
+            .iter()
+            .skip(pos_first)
+            .take(no_involved)
+            .copied()
+            .collect();
+
+        let t_losses = idxs
+            .iter()
+            .map(|_| {
+                if rng.gen::<f64>() < self.dnf_probability {
+                    None
+                } else {
+                    let [t_min, t_max] = self.t_loss_s;
+                    Some(t_min + rng.gen::<f64>() * (t_max - t_min))
+                }
+            })
+            .collect();
+
+        Some(FirstCornerIncident { idxs, t_losses })
+    }
+}
+
+/// RestartLine to linia, którą lider musi minąć po zjeździe SC, zanim wyścig zostanie wznowiony
+/// (do tego czasu lider dyktuje tempo, a wyprzedzanie jest zabronione).
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, Default)]
+pub enum RestartLine {
+    #[default]
+    FinishLine,
+    LastOvertakingZone,
+}
+
+/// get_restart_s_track zwraca położenie (m) linii restartu na torze. Bez stref wyprzedzania
+/// restart następuje na linii mety.
+pub fn get_restart_s_track(track: &Track, restart_line: RestartLine) -> f64 {
+    match restart_line {
+        RestartLine::FinishLine => 0.0,
+        RestartLine::LastOvertakingZone => track
+            .overtaking_zones
+            .iter()
+            .map(|zone| zone[0])
+            .fold(0.0, f64::max),
+    }
+}
+
+/// DuelZone to rodzaj odcinka toru, na którym toczy się pojedynek.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DuelZone {
+    Corner,
+    Straight,
+    Other,
+}
+
+/// DuelPars zawiera współczynniki straty czasu w pojedynkach.
+/// * `min_t_dist` - (s) Odstęp, poniżej którego auta walczą o pozycję
+/// * `corner_factor` - Mnożnik straty w zakręcie
+/// * `straight_factor` - Mnożnik straty na prostej
+/// * `defender_share` - Udział broniącego w stracie pary przy równej agresji kierowców
+/// * `aggression_weight` - Przesunięcie udziału na rzecz różnicy agresji (atakujący, który jest
+///   agresywniejszy, psuje swój tor jazdy i traci więcej)
+#[derive(Debug, Clone, Copy)]
+pub struct DuelPars {
+    pub min_t_dist: f64,
+    pub corner_factor: f64,
+    pub straight_factor: f64,
+    pub defender_share: f64,
+    pub aggression_weight: f64,
+}
+
+/// calc_duel_timelosses zwraca stratę czasu okrążenia (broniący, atakujący) pary aut w pojedynku.
+/// Strata pary `2 * t_duel` rośnie liniowo z głębokością wejścia w `min_t_dist` (0 na granicy,
+/// pełna przy zderzaku), jest ważona rodzajem strefy i dzielona między auta: broniący traci więcej,
+/// chyba że atakujący jest wyraźnie agresywniejszy.
+pub fn calc_duel_timelosses(
+    gap: f64,
+    zone: DuelZone,
+    aggression_front: f64,
+    aggression_rear: f64,
+    t_duel: f64,
+    duel_pars: &DuelPars,
+) -> (f64, f64) {
+    if duel_pars.min_t_dist <= 0.0 || gap >= duel_pars.min_t_dist {
+        return (0.0, 0.0);
+    }
+
+    let depth = (1.0 - gap.max(0.0) / duel_pars.min_t_dist).clamp(0.0, 1.0);
+    let zone_factor = match zone {
+        DuelZone::Corner => duel_pars.corner_factor,
+        DuelZone::Straight => duel_pars.straight_factor,
+        DuelZone::Other => 1.0,
+    };
+    let t_pair = 2.0 * t_duel * depth * zone_factor;
+
+    let defender_share = (duel_pars.defender_share
+        - duel_pars.aggression_weight * (aggression_rear - aggression_front))
+        .clamp(0.0, 1.0);
+
+    (t_pair * defender_share, t_pair * (1.0 - defender_share))
+}
+
+/// (-) Agresja kierowców, dla której obowiązuje `OvertakeContactPars::probability`
+const OVERTAKE_CONTACT_REF_AGGRESSION: f64 = 0.5;
+
+/// OvertakeContactPars zawiera parametry kontaktu przy nieudanej próbie wyprzedzenia w zakręcie
+/// (szybsze auto z tyłu nie może wyprzedzić w zakręcie).
+/// * `probability` - (1/s) Prawdopodobieństwo kontaktu na sekundę nieudanej próby przy agresji obu
+///   kierowców 0.5 i konsystencji 1.0 (0.0 - model wyłączony)
+/// * `t_loss_s` - (s) Zakres [min, max] straty każdego z aut przy lekkim kontakcie
+/// * `spin_probability` - Prawdopodobieństwo, że jedno z aut się obraca
+/// * `spin_t_loss_s` - (s) Zakres [min, max] postoju auta po obrocie (auto spada w stawce)
+/// * `dnf_probability` - Prawdopodobieństwo, że kontakt kończy się wycofaniem
+/// * `double_dnf_probability` - Prawdopodobieństwo, że przy wycofaniu odpadają oba auta
+/// * `attacker_share` - Udział kontaktów, w których obraca się lub odpada atakujący (auto z tyłu)
+#[derive(Debug, Clone, Copy)]
+pub struct OvertakeContactPars {
+    pub probability: f64,
+    pub t_loss_s: [f64; 2],
+    pub spin_probability: f64,
+    pub spin_t_loss_s: [f64; 2],
+    pub dnf_probability: f64,
+    pub double_dnf_probability: f64,
+    pub attacker_share: f64,
+}
+
+/// OvertakeContactOutcome to wylosowany skutek kontaktu (`rear` - dotyczy auta z tyłu, czyli
+/// atakującego).
+/// * `TimeLoss` - Oba auta tracą czas (s) i jadą dalej
+/// * `Spin` - Jedno auto obraca się i stoi przez podany czas (s)
+/// * `Dnf` - Jedno auto odpada z wyścigu (`both` - odpadają oba)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OvertakeContactOutcome {
+    TimeLoss { t_loss_front: f64, t_loss_rear: f64 },
+    Spin { rear: bool, t_loss: f64 },
+    Dnf { rear: bool, both: bool },
+}
+
+impl OvertakeContactPars {
+    /// calc_probability zwraca prawdopodobieństwo kontaktu w kroku czasowym. Rośnie liniowo ze
+    /// średnią agresją pary i maleje z jej średnią konsystencją (skalowanie `2 - konsystencja`).
+    pub fn calc_probability(
+        &self,
+        aggressions: [f64; 2],
+        consistencies: [f64; 2],
+        incident_factor: f64,
+        timestep_size: f64,
+    ) -> f64 {
+        let mean_aggression = (aggressions[0] + aggressions[1]) / 2.0;
+        let mean_consistency = ((consistencies[0] + consistencies[1]) / 2.0).clamp(0.0, 1.0);
+        let rate = self.probability * mean_aggression / OVERTAKE_CONTACT_REF_AGGRESSION
+            * (2.0 - mean_consistency)
+            * incident_factor;
+
+        (1.0 - (-rate.max(0.0) * timestep_size).exp()).clamp(0.0, 1.0)
+    }
+
+    /// draw_outcome losuje skutek kontaktu: wycofanie (`dnf_probability`), obrót
+    /// (`spin_probability`) lub stratę czasu obu aut. Obraca się lub odpada częściej atakujący.
+    pub fn draw_outcome<R: Rng + ?Sized>(&self, rng: &mut R) -> OvertakeContactOutcome {
+        let rear = rng.gen::<f64>() < self.attacker_share;
+        let draw = rng.gen::<f64>();
+
+        if draw < self.dnf_probability {
+            OvertakeContactOutcome::Dnf {
+                rear,
+                both: rng.gen::<f64>() < self.double_dnf_probability,
+            }
+        } else if draw < self.dnf_probability + self.spin_probability {
+            let [t_min, t_max] = self.spin_t_loss_s;
+            OvertakeContactOutcome::Spin {
+                rear,
+                t_loss: t_min + rng.gen::<f64>() * (t_max - t_min),
+            }
+        } else {
+            let [t_min, t_max] = self.t_loss_s;
+            OvertakeContactOutcome::TimeLoss {
+                t_loss_front: t_min + rng.gen::<f64>() * (t_max - t_min),
+                t_loss_rear: t_min + rng.gen::<f64>() * (t_max - t_min),
+            }
+        }
+    }
+}
+
+/// EngineModeSelectPars zawiera parametry automatycznego wyboru trybu silnika.
+/// * `auto` - Automatyczny wybór trybu (false - tryb standardowy, jeśli strategia nie podaje trybu)
+/// * `push_gap_s` - (s) Odstęp do auta z przodu (zasięg undercutu), poniżej którego auto jedzie w
+///   trybie push
+/// * `conserve_gap_s` - (s) Odstęp od auta z tyłu, powyżej którego auto oszczędza silnik
+/// * `conserve_laps` - Liczba ostatnich okrążeń, w których auto może oszczędzać silnik
+#[derive(Debug, Clone, Copy)]
+pub struct EngineModeSelectPars {
+    pub auto: bool,
+    pub push_gap_s: f64,
+    pub conserve_gap_s: f64,
+    pub conserve_laps: u32,
+}
+
+/// select_engine_mode zwraca automatycznie wybrany tryb silnika na następne okrążenie: push w
+/// zasięgu undercutu auta z przodu, conserve pod koniec wyścigu z dużą przewagą nad autem z tyłu
+/// (również bez auta z tyłu), w pozostałych przypadkach tryb standardowy.
+pub fn select_engine_mode(
+    gap_ahead: Option<f64>,
+    gap_behind: Option<f64>,
+    laps_remaining: u32,
+    pars: &EngineModeSelectPars,
+) -> EngineMode {
+    if matches!(gap_ahead, Some(gap) if gap < pars.push_gap_s) {
+        EngineMode::Push
+    } else if laps_remaining <= pars.conserve_laps
+        && gap_behind.is_none_or(|gap| gap > pars.conserve_gap_s)
+    {
+        EngineMode::Conserve
+    } else {
+        EngineMode::Standard
+    }
+}
+
+/// calc_ers_duel zwraca decyzje o użyciu ERS w pojedynku (broniący, atakujący) oraz różnicę tempa
+/// (s, dodatnia - atakujący szybszy) z uwzględnieniem zysku z ERS. Atakujący używa energii, jeśli
+/// dzięki niej może wyprzedzić (różnica powyżej `overtake_threshold`), broniący odpowiada własną
+/// energią, gdy jest atakowany. Przy równych zyskach obu aut decyduje tempo bazowe.
+pub fn calc_ers_duel(
+    pace_diff: f64,
+    overtake_threshold: f64,
+    ers_energy_front: f64,
+    ers_energy_rear: f64,
+    ers_pars: &ErsPars,
+) -> (bool, bool, f64) {
+    let deploy_rear =
+        ers_energy_rear > 0.0 && pace_diff + ers_pars.t_gain > overtake_threshold;
+    let deploy_front =
+        ers_energy_front > 0.0 && (deploy_rear || pace_diff > overtake_threshold);
+
+    let mut pace_diff_ers = pace_diff;
+    if deploy_rear {
+        pace_diff_ers += ers_pars.t_gain;
+    }
+    if deploy_front {
+        pace_diff_ers -= ers_pars.t_gain;
+    }
+
+    (deploy_front, deploy_rear, pace_diff_ers)
+}
+
+/// calc_setup_overtake_factor zwraca mnożnik progu wyprzedzania wynikający z docisku obu aut.
+/// Duży docisk atakującego (wolniejszy na prostej) podnosi próg, a duży docisk broniącego obniża
+/// go, gdy atakujący jedzie z DRS (słabsza obrona na prostej). Przy neutralnym docisku mnożnik
+/// wynosi 1.0.
+pub fn calc_setup_overtake_factor(
+    downforce_front: f64,
+    downforce_rear: f64,
+    drs_rear: bool,
+    setup_pars: &SetupPars,
+) -> f64 {
+    let x_front = (downforce_front.clamp(0.0, 1.0) - DOWNFORCE_NEUTRAL) / DOWNFORCE_NEUTRAL;
+    let x_rear = (downforce_rear.clamp(0.0, 1.0) - DOWNFORCE_NEUTRAL) / DOWNFORCE_NEUTRAL;
+
+    let mut factor = 1.0 + setup_pars.overtake_sensitivity * x_rear;
+    if drs_rear {
+        factor *= 1.0 - setup_pars.overtake_sensitivity * x_front;
+    }
+
+    factor.max(0.0)
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct SimConstants {
     #[serde(default = "default_fuel_margin")] 
     pub fuel_margin: f64,
+    #[serde(default = "default_fuel_save_reduction")]
+    pub fuel_save_reduction: f64, // względne zmniejszenie zużycia paliwa przy oszczędzaniu (0.0 - wył.)
+    #[serde(default = "default_fuel_save_t_loss_s")]
+    pub fuel_save_t_loss_s: f64, // (s) strata czasu okrążenia przy oszczędzaniu paliwa (lift and coast)
+    #[serde(default = "default_ers_capacity_mj")]
+    pub ers_capacity_mj: f64, // (MJ) pojemność baterii ERS (0.0 - model ERS wyłączony)
+    #[serde(default = "default_ers_deploy_gain_s")]
+    pub ers_deploy_gain_s: f64, // (s) zysk czasu okrążenia przy użyciu energii ERS w pojedynku
+    #[serde(default = "default_ers_deploy_rate_mj_per_s")]
+    pub ers_deploy_rate_mj_per_s: f64, // (MJ/s) zużycie energii ERS przy jej użyciu
+    #[serde(default = "default_ers_harvest_mj_per_lap")]
+    pub ers_harvest_mj_per_lap: f64, // (MJ) energia ERS odzyskiwana w ciągu okrążenia
+    #[serde(default = "default_ers_harvest_t_loss_s")]
+    pub ers_harvest_t_loss_s: f64, // (s) strata czasu okrążenia przy ładowaniu baterii ERS
+    #[serde(default = "default_engine_push_t_gain_s")]
+    pub engine_push_t_gain_s: f64, // (s) zysk czasu okrążenia w trybie silnika push
+    #[serde(default = "default_engine_push_failure_factor")]
+    pub engine_push_failure_factor: f64, // mnożnik częstości awarii w trybie push
+    #[serde(default = "default_engine_push_fuel_factor")]
+    pub engine_push_fuel_factor: f64, // mnożnik zużycia paliwa w trybie push
+    #[serde(default = "default_engine_conserve_t_loss_s")]
+    pub engine_conserve_t_loss_s: f64, // (s) strata czasu okrążenia w trybie silnika conserve
+    #[serde(default = "default_engine_conserve_failure_factor")]
+    pub engine_conserve_failure_factor: f64, // mnożnik częstości awarii w trybie conserve
+    #[serde(default = "default_engine_conserve_fuel_factor")]
+    pub engine_conserve_fuel_factor: f64, // mnożnik zużycia paliwa w trybie conserve
+    #[serde(default = "default_engine_mode_auto")]
+    pub engine_mode_auto: bool, // automatyczny wybór trybu silnika (bez trybu w strategii)
+    #[serde(default = "default_engine_push_gap_s")]
+    pub engine_push_gap_s: f64, // (s) odstęp do auta z przodu, poniżej którego auto jedzie w trybie push
+    #[serde(default = "default_engine_conserve_gap_s")]
+    pub engine_conserve_gap_s: f64, // (s) odstęp od auta z tyłu, powyżej którego auto oszczędza silnik
+    #[serde(default = "default_engine_conserve_laps")]
+    pub engine_conserve_laps: u32, // liczba ostatnich okrążeń, w których auto może oszczędzać silnik
+    #[serde(default = "default_setup_corner_t_loss_s")]
+    pub setup_corner_t_loss_s: f64, // (s) dodatek do czasu okrążenia w zakręcie przy neutralnym docisku
+    #[serde(default = "default_setup_drag_t_loss_s")]
+    pub setup_drag_t_loss_s: f64, // (s) strata na prostych (na okrążenie stref wyprzedzania) przy neutralnym docisku
+    #[serde(default = "default_setup_wear_sensitivity")]
+    pub setup_wear_sensitivity: f64, // względna zmiana zużycia opon przy skrajnym docisku
+    #[serde(default = "default_setup_overtake_sensitivity")]
+    pub setup_overtake_sensitivity: f64, // względna zmiana progu wyprzedzania przy skrajnym docisku
+    #[serde(default = "default_overheat_gap_s")]
+    pub overheat_gap_s: f64, // (s) odstęp do auta z przodu w pojedynku, poniżej którego auto się przegrzewa
+    #[serde(default = "default_overheat_heat_rate")]
+    pub overheat_heat_rate: f64, // (1/s) wzrost względnej temperatury w brudnym powietrzu (0.0 - wył.)
+    #[serde(default = "default_overheat_cool_rate")]
+    pub overheat_cool_rate: f64, // (1/s) spadek względnej temperatury w czystym powietrzu
+    #[serde(default = "default_overheat_threshold")]
+    pub overheat_threshold: f64, // względna temperatura, powyżej której auto traci tempo
+    #[serde(default = "default_overheat_t_loss_s")]
+    pub overheat_t_loss_s: f64, // (s) strata czasu okrążenia przy maksymalnej temperaturze
+    #[serde(default = "default_overheat_penalty_exponent")]
+    pub overheat_penalty_exponent: f64, // wykładnik krzywej straty ponad progiem temperatury
+    #[serde(default = "default_fuel_reserve_min_kg")]
+    pub fuel_reserve_min_kg: f64, // (kg) minimalna ilość paliwa na mecie, mniej oznacza dyskwalifikację
+    #[serde(default = "default_dsq_technical_probability")]
+    pub dsq_technical_probability: f64, // prawdopodobieństwo dyskwalifikacji auta za naruszenie techniczne
+    #[serde(default = "default_overtake_contact_t_loss_s")]
+    pub overtake_contact_t_loss_s: [f64; 2], // (s) zakres straty obu aut przy lekkim kontakcie w ataku
+    #[serde(default = "default_overtake_contact_spin_probability")]
+    pub overtake_contact_spin_probability: f64, // szansa, że kontakt w ataku kończy się obrotem auta
+    #[serde(default = "default_overtake_contact_spin_t_loss_s")]
+    pub overtake_contact_spin_t_loss_s: [f64; 2], // (s) zakres postoju auta po obrocie
+    #[serde(default = "default_overtake_contact_dnf_probability")]
+    pub overtake_contact_dnf_probability: f64, // szansa, że kontakt w ataku kończy się wycofaniem
+    #[serde(default = "default_overtake_contact_double_dnf_probability")]
+    pub overtake_contact_double_dnf_probability: f64, // szansa, że przy wycofaniu odpadają oba auta
+    #[serde(default = "default_overtake_contact_attacker_share")]
+    pub overtake_contact_attacker_share: f64, // udział kontaktów, w których obraca się lub odpada atakujący
     #[serde(default = "default_failure_rate_per_hour")] 
-    pub failure_rate_per_hour: f64,
+    pub failure_rate_per_hour: f64, // (1/h) globalna częstość awarii; 0.0 wyłącza awarie wszystkich aut, także tych z własną częstością (CarPars)
+    #[serde(default)]
+    pub failure_rates_per_hour: Option<FailureRates>, // (1/h) częstości awarii według rodzaju (brak - podział failure_rate_per_hour)
     #[serde(default = "default_collision_factor")] 
     pub collision_factor: f64,
     #[serde(default = "default_min_weather_duration_s")] 
     pub min_weather_duration_s: f64,
+    #[serde(default = "default_pit_crew_sigma")]
+    pub pit_crew_sigma: f64,
+    #[serde(default = "default_pit_crew_mean_offset")]
+    pub pit_crew_mean_offset: f64,
+    #[serde(default = "default_pit_crew_fatigue")]
+    pub pit_crew_fatigue: f64,
+    #[serde(default = "default_min_spacing_m")]
+    pub min_spacing_m: f64,
+    #[serde(default = "default_clearance_mean_s")]
+    pub clearance_mean_s: f64,
+    #[serde(default = "default_clearance_crash_factor")]
+    pub clearance_crash_factor: f64,
+    #[serde(default = "default_clearance_corner_factor")]
+    pub clearance_corner_factor: f64,
+    #[serde(default = "default_clearance_sigma_rel")]
+    pub clearance_sigma_rel: f64,
+    #[serde(default = "default_traffic_flag_t_min")]
+    pub traffic_flag_t_min: f64, // (s) czas za wolniejszym autem w okrążeniu, od którego okrążenie ma flagę ruchu
+    #[serde(default = "default_drs_train_overtake_factor")]
+    pub drs_train_overtake_factor: f64, // mnożnik wymaganej przewagi tempa, gdy atakujący i broniący mają DRS
+    #[serde(default = "default_mistake_lockup_t_loss_s")]
+    pub mistake_lockup_t_loss_s: f64, // (s) strata czasu okrążenia broniącego przy zablokowaniu kół
+    #[serde(default = "default_mistake_wide_t_loss_s")]
+    pub mistake_wide_t_loss_s: f64, // (s) strata czasu okrążenia broniącego przy wyjeździe szeroko
+    #[serde(default = "default_mistake_wide_t_gain_s")]
+    pub mistake_wide_t_gain_s: f64, // (s) zysk czasu okrążenia atakującego, gdy broniący wyjechał szeroko
+    #[serde(default = "default_debris_probability")]
+    pub debris_probability: f64, // prawdopodobieństwo gruzu (lokalna żółta flaga) po drobnym kontakcie
+    #[serde(default = "default_debris_slowdown_factor")]
+    pub debris_slowdown_factor: f64, // mnożnik czasu okrążenia przy przejeździe przez odcinek z gruzem
+    #[serde(default = "default_debris_yellow_probability")]
+    pub debris_yellow_probability: f64, // prawdopodobieństwo drobnego incydentu (gruz, żwir) na okrążenie lidera
+    #[serde(default = "default_debris_yellow_no_laps")]
+    pub debris_yellow_no_laps: [u32; 2], // zakres [min, max] okrążeń lidera z żółtą flagą po takim incydencie
+    #[serde(default = "default_yellow_zone_length_m")]
+    pub yellow_zone_length_m: f64, // (m) długość żółtej flagi wokół stojącego auta (0 wyłącza)
+    #[serde(default = "default_yellow_zone_no_laps")]
+    pub yellow_zone_no_laps: u32, // liczba okrążeń lidera, przez które obowiązuje ta żółta flaga
+    #[serde(default = "default_yellow_slowdown_factor")]
+    pub yellow_slowdown_factor: f64, // mnożnik czasu okrążenia na odcinku z żółtą flagą
+    #[serde(default = "default_incident_wetness_curve")]
+    pub incident_wetness_curve: Vec<[f64; 2]>, // punkty [wilgotność toru, mnożnik] prawdopodobieństw incydentów
+    #[serde(default = "default_wetness_time_constant_s")]
+    pub wetness_time_constant_s: f64, // (s) stała czasowa nasiąkania i wysychania toru
+    #[serde(default = "default_heavy_rain_wetness")]
+    pub heavy_rain_wetness: f64, // wilgotność toru, od której deszcz jest ulewą (zdarzenie HeavyRain)
+    #[serde(default = "default_wet_restart_factor")]
+    pub wet_restart_factor: f64, // dodatkowy mnożnik incydentów przez okrążenie po restarcie w deszczu
+    #[serde(default = "default_slick_wet_first_lap_factor")]
+    pub slick_wet_first_lap_factor: f64, // mnożnik incydentów slicków w 1. okrążeniu na mokrym
+    #[serde(default = "default_duel_corner_factor")]
+    pub duel_corner_factor: f64, // mnożnik straty w pojedynku w zakręcie
+    #[serde(default = "default_duel_straight_factor")]
+    pub duel_straight_factor: f64, // mnożnik straty w pojedynku na prostej (strefa wyprzedzania)
+    #[serde(default = "default_duel_defender_share")]
+    pub duel_defender_share: f64, // udział broniącego w stracie pary przy równej agresji
+    #[serde(default = "default_duel_aggression_weight")]
+    pub duel_aggression_weight: f64, // przesunięcie straty na agresywniejszego kierowcę
+    #[serde(default = "default_start_reaction_mean_s")]
+    pub start_reaction_mean_s: f64, // (s) średni czas reakcji na światła (konsystencja 1.0)
+    #[serde(default = "default_start_reaction_sigma_s")]
+    pub start_reaction_sigma_s: f64, // (s) rozrzut czasu reakcji (konsystencja 1.0)
+    #[serde(default = "default_jump_start_threshold_s")]
+    pub jump_start_threshold_s: f64, // (s) reakcja szybsza niż ta granica fizjologiczna to falstart
+    #[serde(default = "default_jump_start_penalty_s")]
+    pub jump_start_penalty_s: f64, // (s) kara czasowa za falstart
+    #[serde(default = "default_anti_stall_probability")]
+    pub anti_stall_probability: f64, // prawdopodobieństwo włączenia anti-stall na starcie
+    #[serde(default = "default_anti_stall_delay_s")]
+    pub anti_stall_delay_s: [f64; 2], // (s) zakres [min, max] straty przy anti-stall
+    #[serde(default = "default_launch_delta_s")]
+    pub launch_delta_s: [f64; 2], // (s) średnia delta startu [najlepszy, najgorszy startujący]
+    #[serde(default = "default_launch_sigma_s")]
+    pub launch_sigma_s: f64, // (s) rozrzut delty startu
+    #[serde(default = "default_first_corner_probability")]
+    pub first_corner_probability: f64, // szansa karambolu w 1. zakręcie (20 aut o agresji 0.5, 0.0 - wył.)
+    #[serde(default = "default_first_corner_dnf_probability")]
+    pub first_corner_dnf_probability: f64, // szansa, że auto z karambolu odpada z wyścigu
+    #[serde(default = "default_first_corner_t_loss_s")]
+    pub first_corner_t_loss_s: [f64; 2], // (s) zakres [min, max] straty auta, które jedzie dalej
+    #[serde(default = "default_first_corner_damage_s")]
+    pub first_corner_damage_s: f64, // (s) strata na okrążeniu przez uszkodzenia po karambolu
+    #[serde(default = "default_damage_t_loss_s")]
+    pub damage_t_loss_s: f64, // (s) strata czasu okrążenia na jednostkę uszkodzeń
+    #[serde(default = "default_damage_wear_factor")]
+    pub damage_wear_factor: f64, // dodatkowe zużycie opon na jednostkę uszkodzeń
+    #[serde(default = "default_damage_repair_threshold")]
+    pub damage_repair_threshold: f64, // uszkodzenia, od których pit stop obejmuje naprawę
+    #[serde(default = "default_damage_repair_t_s")]
+    pub damage_repair_t_s: f64, // (s) dodatkowy czas postoju za naprawę (np. przednie skrzydło)
+    #[serde(default = "default_track_temp_day_c")]
+    pub track_temp_day_c: f64, // (°C) temperatura toru za dnia (tylko z `session_start_h`)
+    #[serde(default = "default_track_temp_night_c")]
+    pub track_temp_night_c: f64, // (°C) temperatura, do której tor stygnie w nocy
+    #[serde(default = "default_track_temp_cooling_time_h")]
+    pub track_temp_cooling_time_h: f64, // (h) stała czasowa stygnięcia toru po zachodzie słońca
+    #[serde(default = "default_track_temp_ref_c")]
+    pub track_temp_ref_c: f64, // (°C) temperatura toru, dla której obowiązują parametry degradacji
+    #[serde(default = "default_crash_avoidance_distance_m")]
+    pub crash_avoidance_distance_m: f64, // (m) odległość za świeżym wypadkiem, w której auta zwalniają
+    #[serde(default = "default_crash_avoidance_slowdown_factor")]
+    pub crash_avoidance_slowdown_factor: f64, // mnożnik czasu okrążenia przy omijaniu wypadku
+    #[serde(default = "default_crash_avoidance_duration_s")]
+    pub crash_avoidance_duration_s: f64, // (s) czas od wypadku do ogłoszenia flagi (koniec omijania)
+    #[serde(default = "default_secondary_collision_probability")]
+    pub secondary_collision_probability: f64, // szansa najechania na rozbite auto tuż za nim (sucho)
+    #[serde(default = "default_driver_crash_probability")]
+    pub driver_crash_probability: f64, // szansa wypadku z winy kierowcy na okrążenie (konsystencja 0.0, sucho, solo)
+    #[serde(default = "default_driver_crash_corner_factor")]
+    pub driver_crash_corner_factor: f64, // mnożnik szansy wypadku kierowcy w zakręcie
+    #[serde(default = "default_driver_crash_duel_factor")]
+    pub driver_crash_duel_factor: f64, // mnożnik szansy wypadku kierowcy w pojedynku
+    #[serde(default = "default_driver_crash_wet_factor")]
+    pub driver_crash_wet_factor: f64, // mnożnik szansy wypadku kierowcy na całkowicie mokrym torze
+    #[serde(default = "default_puncture_probability_over_cliff")]
+    pub puncture_probability_over_cliff: f64, // szansa przebicia opony na okrążenie za każde okrążenie ponad cliff
+    #[serde(default = "default_puncture_debris_probability")]
+    pub puncture_debris_probability: f64, // szansa przebicia opony przy przejeździe przez miejsce wypadku
+    #[serde(default = "default_puncture_debris_window_s")]
+    pub puncture_debris_window_s: f64, // (s) czas od wypadku, przez który na torze leży gruz
+    #[serde(default = "default_puncture_fallback_compound")]
+    pub puncture_fallback_compound: String, // mieszanka po przebiciu, gdy brak nowego kompletu bieżącej
+    #[serde(default = "default_sc_probability")]
+    pub sc_probability: f64, // bazowe prawdopodobieństwo SC po wycofaniu auta
+    #[serde(default = "default_sc_corner_factor")]
+    pub sc_corner_factor: f64, // mnożnik prawdopodobieństwa SC, gdy auto stanęło w zakręcie
+    #[serde(default = "default_sc_overtaking_zone_factor")]
+    pub sc_overtaking_zone_factor: f64, // mnożnik prawdopodobieństwa SC w strefie wyprzedzania
+    #[serde(default = "default_sc_delta_factor")]
+    pub sc_delta_factor: f64, // mnożnik tempa wyścigowego = najkrótszy czas okrążenia za SC (delta)
+    #[serde(default = "default_sc_target_gap_m")]
+    pub sc_target_gap_m: f64, // m, odstęp między autami w kolejce za SC
+    #[serde(default = "default_restart_pace_margin")]
+    pub restart_pace_margin: f64, // o ile (ułamek) lider jedzie wolniej od delty SC przed restartem
+    #[serde(default)]
+    pub restart_line: RestartLine, // linia, od której po zjeździe SC obowiązuje tempo wyścigowe
+    #[serde(default = "default_finish_min_pace_factor")]
+    pub finish_min_pace_factor: f64, // mnożnik tempa wyścigowego = limit okrążenia po szachownicy
+    #[serde(default = "default_formation_pace_factor")]
+    pub formation_pace_factor: f64, // mnożnik tempa wyścigowego = czas okrążenia formującego
+    #[serde(default = "default_formation_wear_factor")]
+    pub formation_wear_factor: f64, // zużycie opon i paliwa na okrążeniu formującym (ułamek okrążenia)
+    #[serde(default = "default_vsc_no_mini_sectors")]
+    pub vsc_no_mini_sectors: usize, // liczba minisektorów, w których kontrolowane jest tempo VSC
     pub min_t_dist: f64,
     pub t_duel: f64,
     pub t_overtake_loser: f64,
     pub drs_window: f64,
 }
 
+/// SafetyCar opisuje samochód bezpieczeństwa na torze.
+/// * `in_this_lap` - Okres SC się skończył: SC jedzie do wjazdu do alei lub linii mety i tam
+///   zjeżdża
+/// * `leader_caught` - Lider dogonił SC po jego wyjeździe z alei (do tego czasu auta jadą w tempie
+///   delty SC)
 #[derive(Debug, Clone)]
 pub struct SafetyCar{
     pub active: bool,
     pub s_track: f64,
     pub speed: f64,
     pub lap: u32,
+    pub in_this_lap: bool,
+    pub leader_caught: bool,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
 pub enum FlagState {
+    #[default]
     G,   // green
     Y,   // yellow
     Vsc, // virtual safety car
     Sc,  // safety car
+    Red, // red flag (wyścig zawieszony)
     C,   // chequered
 }
 
@@ -83,18 +1432,77 @@ pub enum WeatherState {
     Rain,
 }
 
-impl Default for FlagState {
-    fn default() -> Self {
-        FlagState::G
+impl FlagState {
+    /// get_severity zwraca wagę flagi (większa - poważniejsza), np. do wyboru flagi pokazywanej
+    /// dla całego toru.
+    pub fn get_severity(&self) -> u8 {
+        match self {
+            FlagState::G => 0,
+            FlagState::Y => 1,
+            FlagState::Vsc => 2,
+            FlagState::Sc => 3,
+            FlagState::C => 4,
+            FlagState::Red => 5,
+        }
     }
 }
 impl SafetyCar {
     pub fn new() -> Self{
-        SafetyCar { active: false, s_track: 0.0, speed: 50.0, lap: 0 }
+        SafetyCar {
+            active: false,
+            s_track: 0.0,
+            speed: 50.0,
+            lap: 0,
+            in_this_lap: false,
+            leader_caught: false,
+        }
+    }
+}
+
+impl Default for SafetyCar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// EventObserver jest obserwatorem zdarzeń wyścigu (np. wtyczką), wywoływanym synchronicznie przy
+/// każdym nowym zdarzeniu. Dostaje tylko referencję do zdarzenia, więc nie może zmienić wyścigu.
+/// Panika obserwatora nie jest przechwytywana: przerywa symulację (propaguje się z metody wyścigu,
+/// która zapisała zdarzenie, np. `simulate_timestep`), a pozostali obserwatorzy nie dostają już
+/// tego zdarzenia. Samo zdarzenie jest wtedy już zapisane w wyścigu.
+pub type EventObserver = Box<dyn FnMut(&RaceEvent)>;
+
+/// EventSubscribers przechowuje obserwatorów zdarzeń wyścigu. Klon wyścigu nie przejmuje
+/// obserwatorów, aby te same zdarzenia nie trafiały do nich wielokrotnie.
+#[derive(Default)]
+struct EventSubscribers {
+    observers: Vec<EventObserver>,
+}
+
+impl EventSubscribers {
+    /// notify przekazuje zdarzenie wszystkim obserwatorom.
+    fn notify(&mut self, event: &RaceEvent) {
+        for observer in self.observers.iter_mut() {
+            observer(event);
+        }
+    }
+}
+
+impl Clone for EventSubscribers {
+    fn clone(&self) -> Self {
+        EventSubscribers::default()
     }
 }
 
-#[derive(Debug)]
+impl std::fmt::Debug for EventSubscribers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventSubscribers")
+            .field("no_observers", &self.observers.len())
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Race {
     pub sc_timer: f64,
     pub timestep_size: f64,
@@ -103,18 +1511,125 @@ pub struct Race {
     rain_probability: f64,
     min_weather_duration_s: f64,
     last_weather_change: f64,
-    failure_rate_per_hour: f64,
+    failure_rates: FailureRates, // (1/h) globalne częstości awarii według rodzaju (zerowe wyłączają awarie wszystkich aut, patrz Car::get_failure_rates)
     collision_factor: f64,
+    pit_crew_sigma: f64,
+    pit_crew_mean_offset: f64,
+    pit_crew_fatigue: f64,
     weather_history_log: Vec<String>,
     events: Vec<RaceEvent>,
+    event_subscribers: EventSubscribers, // obserwatorzy powiadamiani o każdym nowym zdarzeniu
+    lead_change_lap: u32, // ostatnie okrążenie sprawdzone pod kątem zmiany lidera
+    lap_leader_prev: Option<u32>, // numer auta prowadzącego po okrążeniu `lead_change_lap`
+    rng: StdRng, // generator losowań wyścigu (ziarno z `RacePars::seed`)
+    pit_stops: Vec<PitStopInfo>,
+    t_pitlane: Vec<f64>, // (s) łączny czas w alei serwisowej (przejazd + postój) każdego auta
+    retirements: Vec<Option<RetirementInfo>>, // miejsce i czas wycofania każdego auta
+    lap_flags: Vec<Vec<u8>>, // flagi kontekstu (LAP_FLAG_*) każdego ukończonego okrążenia każdego auta
+    lap_flags_cur: Vec<u8>, // flagi kontekstu zebrane w bieżącym okrążeniu
+    t_traffic_cur_lap: Vec<f64>, // (s) czas za wolniejszym autem w bieżącym okrążeniu
+    traffic_flag_t_min: f64,
+    drs_train_overtake_factor: f64,
+    mistake_lockup_t_loss_s: f64,
+    mistake_wide_t_loss_s: f64,
+    mistake_wide_t_gain_s: f64,
+    debris_probability: f64,
+    debris_slowdown_factor: f64,
+    debris_yellow_probability: f64,
+    debris_yellow_no_laps: [u32; 2],
+    duel_pars: DuelPars,
+    debris_zones: Vec<DebrisZone>, // aktywne lokalne żółte flagi (gruz)
+    yellow_zone_length_m: f64,
+    yellow_zone_no_laps: u32,
+    yellow_slowdown_factor: f64,
+    yellow_zones: Vec<YellowZone>, // aktywne lokalne żółte flagi (auta stojące na torze)
+    crash_avoidance_pars: CrashAvoidancePars,
+    driver_crash_pars: DriverCrashPars,
+    overtake_contact_pars: OvertakeContactPars,
+    puncture_pars: PuncturePars,
+    puncture_debris: Vec<(f64, f64)>, // (m, s) miejsce i czas wypadków (gruz grożący przebiciem)
+    crash_sites: Vec<CrashSite>, // świeże wypadki przed oficjalną flagą
+    crash_site_registered: Vec<bool>, // auta, których wypadek został już zarejestrowany
+    wetness: f64, // wilgotność toru (0.0 - sucho, 1.0 - całkowicie mokro)
+    incident_wetness_xp: Vec<f64>,
+    incident_wetness_fp: Vec<f64>,
+    wetness_time_constant_s: f64,
+    heavy_rain_wetness: f64,
+    heavy_rain_act: bool,
+    wet_restart_factor: f64,
+    slick_wet_first_lap_factor: f64,
+    restart_lap: Option<u32>, // okrążenie lidera po zjeździe SC (restart)
+    rolling_restart_act: bool, // lider dyktuje tempo po zjeździe SC, aż minie linię restartu
+    finish_under_sc: bool, // meta za SC: SC prowadzi auta, aż ostatnie jadące auto minie metę
+    t_chequered: Option<f64>, // (s) czas wyścigu, w którym lider minął metę
+    formation_lap_act: bool, // okrążenie formujące w toku (czas wyścigu nie płynie)
+    max_race_time_s: Option<f64>,
+    time_limit_lap: Option<u32>, // ostatnie okrążenie po przekroczeniu limitu czasu wyścigu
+    sc_pit_window_laps: Option<u32>, // zjazd za SC, jeśli pit stop był planowany w tylu okrążeniach
+    sc_periods: Vec<ScPeriod>, // zakończone okresy SC i VSC
+    cur_sc_period: Option<ScPeriod>, // trwający okres SC lub VSC
+    formation_lap_prog: f64, // postęp okrążenia formującego (0.0 - 1.0)
+    t_formation_lap: f64, // (s) czas okrążenia formującego
+    formation_wear_factor: f64,
+    model_toggles: ModelToggles, // komponenty modelu wyłączone w całym wyścigu (ablacja)
+    finish_min_pace_factor: f64,
+    restart_pace_margin: f64,
+    restart_s_track: f64, // (m) linia restartu
+    drs_trains: Vec<Vec<u32>>, // aktualne pociągi DRS (numery aut w kolejności na torze)
+    scripted_events: Vec<ScriptedEvent>, // zdarzenia wymuszone, które jeszcze nie nastąpiły (wg okrążeń)
+    scripted_overrides: ScriptedOverrides, // modele losowe wyłączone przez zdarzenia wymuszone
+    sc_scripted_lap_in: Option<u32>, // okrążenie lidera, w którym zjeżdża wymuszony SC
+    t_penalties: Vec<f64>, // (s) suma kar czasowych każdego auta (doliczana do czasów wyścigu)
+    t_stopped_until: Vec<f64>, // (s) czas wyścigu, do którego auto stoi (start: reakcja, anti-stall; obrót po kontakcie)
+    launch_deltas: Vec<f64>, // (s) wylosowana delta startu każdego auta (1. okrążenie)
+    first_corner_incident: Option<FirstCornerIncident>, // wylosowany karambol w 1. zakręcie
+    first_corner_incident_act: bool, // karambol w 1. zakręcie już nastąpił
+    rolling_start: bool, // start za SC (bez startu z miejsca i straty 1. okrążenia)
+    first_corner_damage_s: f64,
+    damage_pars: DamagePars,
+    ers_pars: ErsPars,
+    ers_deploy_cur: Vec<bool>, // auta używające energii ERS w bieżącym kroku
+    engine_mode_select_pars: EngineModeSelectPars,
+    setup_pars: SetupPars,
+    overheat_gap_s: f64, // (s) odstęp w pojedynku, poniżej którego auto się przegrzewa
+    dsq_technical: Vec<bool>, // auta, u których kontrola po wyścigu wykaże naruszenie techniczne
+    engine_modes: Vec<Vec<EngineMode>>, // tryb silnika każdego ukończonego okrążenia każdego auta
+    compounds: Vec<Vec<String>>, // mieszanka opon, na której każde auto rozpoczęło każde okrążenie
+    pub starting_grid: Vec<GridSlot>, // pola startowe po zastosowaniu kar (w kolejności startu)
+    session_start_h: Option<f64>, // (h) godzina startu (None - pora dnia nie jest modelowana)
+    sunset_h: f64,
+    track_temp_pars: TrackTempPars,
     pub cur_racetime: f64,
     pub safety_car: SafetyCar,
-    sc_triggers: Vec<bool>, // auta które triggerowały safety car żeby w pętli tego nie robiły
+    incident_queue: Vec<usize>, // wycofane auta, dla których nie zdecydowano jeszcze o SC/VSC
     // Safety Car control
     sc_target_gap_m: f64,
     sc_lineup_tolerance_m: f64,
     sc_release_delay_s: f64,
-    season: u32,
+    min_spacing_m: f64,
+    clearance_pars: ClearancePars,
+    sc_deployment_pars: ScDeploymentPars,
+    sc_delta_factor: f64,
+    incident: Option<Incident>,
+    sc_bunching_lap: Option<u32>, // okrążenie SC, po którym może zjechać (po usunięciu skutków incydentu)
+    vsc_probability: f64,
+    vsc_delta_s: f64,
+    vsc_duration_s: f64,
+    vsc_timer: f64, // (s) pozostały czas VSC
+    vsc_mini_sectors: Vec<MiniSector>, // minisektory z czasami docelowymi podczas VSC
+    t_vsc_debts: Vec<f64>, // (s) czas, o który auto przejechało minisektory za szybko podczas VSC
+    t_vsc_givebacks: Vec<f64>, // (s) czas oddany w bieżącym minisektorze (spłata długu VSC)
+    laptimes_vsc_mini_sector: Vec<f64>, // (s) czas okrążenia VSC auta przy wjeździe w minisektor
+    red_flag_probability: f64,
+    red_flag_min_cars: Option<u32>,
+    heavy_rain_red_flag_probability: f64,
+    red_flag_duration_s: f64,
+    red_flag_laps_deducted: u32,
+    red_flag_compound: Option<String>,
+    red_flag_timer: f64, // (s) pozostały czas zawieszenia wyścigu
+    pit_closed_on_sc_deploy: bool,
+    pit_closure: Option<PitClosure>, // zamknięcie wjazdu do alei po wypuszczeniu SC
+    timing_histories: Vec<TimingHistory>, // ostatnie przekroczenia punktów pomiaru czasu
     pub tot_no_laps: u32,
     pub drs_allowed_lap: u32, 
     pub cur_lap_leader: u32,
@@ -123,19 +1638,29 @@ pub struct Race {
     pub t_overtake_loser: f64,
     pub drs_window: f64,
     pub use_drs: bool,
-    pub flag_state: FlagState,
-    pub track: Track,
+    pub flag_state: FlagState, // stan flagi na całym torze (SC, VSC, czerwona flaga, szachownica)
+    pub track: Rc<Track>,
     race_finished: Vec<bool>,
     pub laptimes: Vec<Vec<f64>>,
     pub racetimes: Vec<Vec<f64>>,
     pub cur_laptimes: Vec<f64>,
     cur_th_laptimes: Vec<f64>,
+    laptime_breakdowns: Vec<Vec<LaptimeBreakdown>>, // składniki teoretycznego czasu każdego okrążenia
+    cur_th_breakdowns: Vec<LaptimeBreakdown>, // składniki bieżącego teoretycznego czasu okrążenia
+    t_clean_laptimes: Vec<f64>, // (s) czas okrążenia czystego przejazdu (bez opon, paliwa, ...)
+    time_loss_deltas_cur: Vec<TimeLossBreakdown>, // (s) dodatki do czasu okrążenia wg przyczyn
+    time_losses_cur: Vec<TimeLossBreakdown>, // strata względem czystego przejazdu (bieżąca)
+    time_losses: Vec<TimeLossBreakdown>, // strata względem czystego przejazdu na ostatniej linii
+    race_progs_time_loss: Vec<f64>, // postęp wyścigu rozliczony już w stracie czasu
+    missed_inlaps: Vec<Vec<u32>>, // okrążenia zjazdowe minięte bez pit stopu (zdarzenie MissedStop)
     pub cars_list: Vec<Car>,
     drivers_list: HashMap<String, Rc<Driver>>,
     pub tire_config: TireConfig,
 }
 
 impl Race {
+    /// new tworzy wyścig i wczytuje geometrię toru z pliku. Przy wielu przebiegach lepiej utworzyć
+    /// tor raz i użyć `new_with_track`.
     pub fn new(
         race_pars: &RacePars,
         sim_consts: &SimConstants,
@@ -145,8 +1670,30 @@ impl Race {
         car_pars_all: &HashMap<u32, CarPars>,
         timestep_size: f64,
     ) -> Race {
-        // create drivers
-        let mut drivers_list = HashMap::with_capacity(driver_pars_all.len());
+        Race::new_with_track(
+            race_pars,
+            sim_consts,
+            tire_config,
+            Rc::new(Track::new(track_pars)),
+            driver_pars_all,
+            car_pars_all,
+            timestep_size,
+        )
+    }
+
+    /// new_with_track tworzy wyścig na już utworzonym torze (geometria i mnożniki prędkości są
+    /// współdzielone, np. między przebiegami Monte Carlo).
+    pub fn new_with_track(
+        race_pars: &RacePars,
+        sim_consts: &SimConstants,
+        tire_config: &TireConfig,
+        track: Rc<Track>,
+        driver_pars_all: &HashMap<String, DriverPars>,
+        car_pars_all: &HashMap<u32, CarPars>,
+        timestep_size: f64,
+    ) -> Race {
+        // create drivers
+        let mut drivers_list = HashMap::with_capacity(driver_pars_all.len());
 
         for (initials, driver_pars) in driver_pars_all.iter() {
             drivers_list.insert(initials.to_owned(), Rc::new(Driver::new(driver_pars)));
@@ -161,6 +1708,12 @@ impl Race {
         // Debug: list available driver initials
         // debug listing removed to avoid noisy output in multi-run scenarios
 
+        // generator losowań wyścigu (bolidy dostają własne generatory z ziarnami z niego)
+        let mut rng = match race_pars.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
         // create cars
         let no_cars = race_pars.participants.len();
         let mut cars_list: Vec<Car> = Vec::with_capacity(no_cars);
@@ -184,42 +1737,382 @@ impl Race {
 
         // sort cars list by car number
         cars_list.sort_unstable_by(|a, b| a.car_no.partial_cmp(&b.car_no).unwrap());
+        for car in cars_list.iter_mut() {
+            car.seed_rng(rng.gen());
+        }
+
+        // model uszkodzeń (wspólny dla wszystkich aut)
+        let damage_pars = DamagePars {
+            t_loss: sim_consts.damage_t_loss_s,
+            wear_factor: sim_consts.damage_wear_factor,
+            repair_threshold: sim_consts.damage_repair_threshold,
+            t_repair: sim_consts.damage_repair_t_s,
+        };
+        for car in cars_list.iter_mut() {
+            car.set_damage_pars(damage_pars);
+        }
+
+        // tryb oszczędzania paliwa (wspólny dla wszystkich aut)
+        let fuel_save_pars = FuelSavePars {
+            reduction: sim_consts.fuel_save_reduction,
+            t_loss: sim_consts.fuel_save_t_loss_s,
+            reserve: sim_consts.fuel_reserve_min_kg,
+        };
+        for car in cars_list.iter_mut() {
+            car.set_fuel_save_pars(fuel_save_pars);
+        }
+
+        // model ERS (wspólny dla wszystkich aut, bateria na starcie jest pełna)
+        let ers_pars = ErsPars {
+            capacity: sim_consts.ers_capacity_mj,
+            t_gain: sim_consts.ers_deploy_gain_s,
+            deploy_rate: sim_consts.ers_deploy_rate_mj_per_s,
+            harvest_per_lap: sim_consts.ers_harvest_mj_per_lap,
+            t_harvest_loss: sim_consts.ers_harvest_t_loss_s,
+        };
+        for car in cars_list.iter_mut() {
+            car.set_ers_pars(ers_pars);
+        }
+
+        // tryby silnika (wspólne dla wszystkich aut)
+        let engine_mode_pars = EngineModePars {
+            t_gain_push: sim_consts.engine_push_t_gain_s,
+            failure_factor_push: sim_consts.engine_push_failure_factor,
+            fuel_factor_push: sim_consts.engine_push_fuel_factor,
+            t_loss_conserve: sim_consts.engine_conserve_t_loss_s,
+            failure_factor_conserve: sim_consts.engine_conserve_failure_factor,
+            fuel_factor_conserve: sim_consts.engine_conserve_fuel_factor,
+        };
+        for car in cars_list.iter_mut() {
+            car.set_engine_mode_pars(engine_mode_pars);
+        }
+
+        // ustawienie bolidów (docisk aerodynamiczny, wspólne parametry dla wszystkich aut)
+        let setup_pars = SetupPars {
+            corner_t_loss: sim_consts.setup_corner_t_loss_s,
+            drag_t_loss: sim_consts.setup_drag_t_loss_s,
+            wear_sensitivity: sim_consts.setup_wear_sensitivity,
+            overtake_sensitivity: sim_consts.setup_overtake_sensitivity,
+        };
+        for car in cars_list.iter_mut() {
+            car.set_setup_pars(setup_pars);
+        }
+
+        // przegrzewanie w brudnym powietrzu (wspólne dla wszystkich aut)
+        let thermal_pars = ThermalPars {
+            heat_rate: sim_consts.overheat_heat_rate,
+            cool_rate: sim_consts.overheat_cool_rate,
+            threshold: sim_consts.overheat_threshold,
+            t_loss_max: sim_consts.overheat_t_loss_s,
+            penalty_exponent: sim_consts.overheat_penalty_exponent,
+        };
+        for car in cars_list.iter_mut() {
+            car.set_thermal_pars(thermal_pars);
+        }
+
+        // grid penalties from the race parameters: a penalty of at least the field size (e.g. 99)
+        // sends the car to the back of the grid, all other penalties move the car by the given
+        // number of places
+        let p_grids_nominal: Vec<u32> = cars_list.iter().map(|car| car.p_grid).collect();
+        let mut penalties: Vec<u32> = vec![0; cars_list.len()];
+
+        for (i, car) in cars_list.iter_mut().enumerate() {
+            if let Some(&places) = race_pars.grid_penalties.get(&car.car_no) {
+                if places as usize >= p_grids_nominal.len() {
+                    car.start_from_back = true;
+                } else {
+                    penalties[i] = places;
+                }
+            }
+        }
+
+        // resolve the starting grid (place penalties, back-of-grid penalties)
+        let p_grids = apply_grid_penalties(&p_grids_nominal, &penalties);
+        let back_of_grid: Vec<bool> = cars_list.iter().map(|car| car.start_from_back).collect();
+        let mut grid_events: Vec<RaceEvent> = Vec::new();
+        let mut starting_grid: Vec<GridSlot> = Vec::with_capacity(cars_list.len());
+
+        for (pos, &idx) in resolve_grid_order(&p_grids, &back_of_grid).iter().enumerate() {
+            cars_list[idx].p_grid = pos as u32 + 1;
+
+            starting_grid.push(GridSlot {
+                car_no: cars_list[idx].car_no,
+                p_grid_nominal: p_grids_nominal[idx],
+                p_grid: pos as u32 + 1,
+                penalty_places: penalties[idx],
+                back_of_grid: cars_list[idx].start_from_back,
+            });
+
+            if cars_list[idx].start_from_back || penalties[idx] > 0 {
+                grid_events.push(RaceEvent::new(
+                    "GridPenalty",
+                    0,
+                    0.0,
+                    vec![cars_list[idx].car_no],
+                ));
+            }
+        }
+
+        // climb-heavy tracks increase the fuel consumption
+        for car in cars_list.iter_mut() {
+            car.scale_fuel_consumption(track.fuel_mult);
+        }
 
-        // Ensure starting fuel is sufficient for the race distance (no refueling era)
+        // Starting fuel (no refueling era): the car is topped up to the race distance plus the
+        // margin and the required reserve, a fixed fuel mass is used as given and a car that would
+        // not reach the finish with the reserve gets a warning on the grid
         for car in cars_list.iter_mut() {
-            let required = car.fuel_needed_for_laps(race_pars.tot_no_laps);
-            let target = required * (1.0 + sim_consts.fuel_margin);
-            if car.get_fuel_mass() < target {
-                car.set_fuel_mass(target);
+            let mut required = car.fuel_needed_for_laps(race_pars.tot_no_laps);
+            if race_pars.formation_lap {
+                required += car.fuel_needed_for_laps(1) * sim_consts.formation_wear_factor;
+            }
+
+            match car_pars_all[&car.car_no].m_fuel_fixed {
+                None => {
+                    let target =
+                        required * (1.0 + sim_consts.fuel_margin) + sim_consts.fuel_reserve_min_kg;
+                    if car.get_fuel_mass() < target {
+                        car.set_fuel_mass(target);
+                    }
+                }
+                Some(m_fuel) if m_fuel < required + sim_consts.fuel_reserve_min_kg => {
+                    grid_events.push(RaceEvent::new("FUEL_WARNING", 0, 0.0, vec![car.car_no]));
+                }
+                Some(_) => {}
             }
         }
 
+        // naruszenia techniczne wykrywane w kontroli po wyścigu są losowane z góry, żeby wynik
+        // wyścigu był taki sam przy każdym wywołaniu get_race_result
+        let dsq_technical: Vec<bool> = (0..cars_list.len())
+            .map(|_| rng.gen::<f64>() < sim_consts.dsq_technical_probability)
+            .collect();
+
         //set the weather
         let start_weather = match race_pars.initial_weather.as_str() {
             "Rain" => WeatherState::Rain,
             _ => WeatherState::Dry // domyślnie jest sucho            
         };
 
+        // start w deszczu na mokrym torze (bez zdarzenia ulewy na starcie), chyba że wilgotność
+        // na starcie jest podana (np. wysychający tor - część stawki na slickach, część na
+        // interach)
+        let start_wetness = match race_pars.initial_wetness {
+            Some(wetness) => wetness.clamp(0.0, 1.0),
+            None if start_weather == WeatherState::Rain => 1.0,
+            None => 0.0,
+        };
+
+        // start za SC w deszczu (lub wymuszony) zamiast startu z miejsca
+        let rolling_start = race_pars.start_behind_sc_laps > 0
+            && (start_weather == WeatherState::Rain || race_pars.start_behind_sc_forced);
+
+        // zdarzenia wymuszone wyłączają losowe modele swoich kategorii (brak podwójnych zdarzeń)
+        let mut scripted_events = race_pars.scripted_events.to_owned();
+        scripted_events.sort_by_key(|x| x.lap);
+        let scripted_overrides = ScriptedOverrides::from_events(&scripted_events);
+
+        // przebicia opon nie zależą od częstości awarii, wyłączają je tylko wymuszone wycofania
+        // (brak podwójnych zdarzeń) i zerowe prawdopodobieństwa przebicia
+        let punctures_act = !scripted_overrides.dnf;
+
         // create race
         let mut race = Race {
             timestep_size,
             cur_racetime: 0.0,
             weather_state: start_weather,
             print_events: true,
-            rain_probability: race_pars.rain_probability,
+            rain_probability: if scripted_overrides.weather { 0.0 } else { race_pars.rain_probability },
             min_weather_duration_s: sim_consts.min_weather_duration_s,
             last_weather_change: 0.0,
-            failure_rate_per_hour: sim_consts.failure_rate_per_hour,
-            collision_factor: sim_consts.collision_factor,
+            failure_rates: if scripted_overrides.dnf || sim_consts.failure_rate_per_hour <= 0.0 {
+                FailureRates::default()
+            } else {
+                sim_consts
+                    .failure_rates_per_hour
+                    .unwrap_or_else(|| FailureRates::from_total(sim_consts.failure_rate_per_hour))
+            },
+            collision_factor: if scripted_overrides.dnf { 0.0 } else { sim_consts.collision_factor },
+            pit_crew_sigma: sim_consts.pit_crew_sigma,
+            pit_crew_mean_offset: sim_consts.pit_crew_mean_offset,
+            pit_crew_fatigue: sim_consts.pit_crew_fatigue,
             weather_history_log: Vec::new(),
-            events: Vec::new(),
+            events: grid_events,
+            event_subscribers: EventSubscribers::default(),
+            lead_change_lap: 0,
+            lap_leader_prev: None,
+            rng,
+            pit_stops: Vec::new(),
+            t_pitlane: vec![0.0; no_cars],
+            retirements: vec![None; no_cars],
+            lap_flags: vec![vec![0; race_pars.tot_no_laps as usize + 1]; no_cars],
+            lap_flags_cur: vec![0; no_cars],
+            t_traffic_cur_lap: vec![0.0; no_cars],
+            traffic_flag_t_min: sim_consts.traffic_flag_t_min,
+            drs_train_overtake_factor: sim_consts.drs_train_overtake_factor,
+            mistake_lockup_t_loss_s: sim_consts.mistake_lockup_t_loss_s,
+            mistake_wide_t_loss_s: sim_consts.mistake_wide_t_loss_s,
+            mistake_wide_t_gain_s: sim_consts.mistake_wide_t_gain_s,
+            debris_probability: sim_consts.debris_probability,
+            debris_slowdown_factor: sim_consts.debris_slowdown_factor,
+            debris_yellow_probability: sim_consts.debris_yellow_probability,
+            debris_yellow_no_laps: sim_consts.debris_yellow_no_laps,
+            duel_pars: DuelPars {
+                min_t_dist: sim_consts.min_t_dist,
+                corner_factor: sim_consts.duel_corner_factor,
+                straight_factor: sim_consts.duel_straight_factor,
+                defender_share: sim_consts.duel_defender_share,
+                aggression_weight: sim_consts.duel_aggression_weight,
+            },
+            debris_zones: Vec::new(),
+            yellow_zone_length_m: sim_consts.yellow_zone_length_m,
+            yellow_zone_no_laps: sim_consts.yellow_zone_no_laps,
+            yellow_slowdown_factor: sim_consts.yellow_slowdown_factor,
+            yellow_zones: Vec::new(),
+            crash_avoidance_pars: CrashAvoidancePars {
+                distance_m: sim_consts.crash_avoidance_distance_m,
+                slowdown_factor: sim_consts.crash_avoidance_slowdown_factor,
+                duration_s: sim_consts.crash_avoidance_duration_s,
+                collision_probability: sim_consts.secondary_collision_probability,
+            },
+            driver_crash_pars: DriverCrashPars {
+                probability: if scripted_overrides.dnf { 0.0 } else { sim_consts.driver_crash_probability },
+                corner_factor: sim_consts.driver_crash_corner_factor,
+                duel_factor: sim_consts.driver_crash_duel_factor,
+                wet_factor: sim_consts.driver_crash_wet_factor,
+            },
+            overtake_contact_pars: OvertakeContactPars {
+                probability: if scripted_overrides.dnf {
+                    0.0
+                } else {
+                    race_pars.overtake_contact_probability
+                },
+                t_loss_s: sim_consts.overtake_contact_t_loss_s,
+                spin_probability: sim_consts.overtake_contact_spin_probability,
+                spin_t_loss_s: sim_consts.overtake_contact_spin_t_loss_s,
+                dnf_probability: sim_consts.overtake_contact_dnf_probability,
+                double_dnf_probability: sim_consts.overtake_contact_double_dnf_probability,
+                attacker_share: sim_consts.overtake_contact_attacker_share,
+            },
+            puncture_pars: PuncturePars {
+                probability_over_cliff: if punctures_act {
+                    sim_consts.puncture_probability_over_cliff
+                } else {
+                    0.0
+                },
+                debris_probability: if punctures_act {
+                    sim_consts.puncture_debris_probability
+                } else {
+                    0.0
+                },
+                debris_window_s: sim_consts.puncture_debris_window_s,
+                fallback_compound: sim_consts.puncture_fallback_compound.to_uppercase(),
+            },
+            puncture_debris: Vec::new(),
+            crash_sites: Vec::new(),
+            crash_site_registered: vec![false; no_cars],
+            wetness: start_wetness,
+            incident_wetness_xp: sim_consts.incident_wetness_curve.iter().map(|x| x[0]).collect(),
+            incident_wetness_fp: sim_consts.incident_wetness_curve.iter().map(|x| x[1]).collect(),
+            wetness_time_constant_s: sim_consts.wetness_time_constant_s,
+            heavy_rain_wetness: sim_consts.heavy_rain_wetness,
+            heavy_rain_act: start_wetness >= sim_consts.heavy_rain_wetness,
+            wet_restart_factor: sim_consts.wet_restart_factor,
+            slick_wet_first_lap_factor: sim_consts.slick_wet_first_lap_factor,
+            restart_lap: None,
+            rolling_restart_act: false,
+            finish_under_sc: false,
+            t_chequered: None,
+            formation_lap_act: race_pars.formation_lap,
+            max_race_time_s: race_pars.max_race_time_s,
+            time_limit_lap: None,
+            sc_pit_window_laps: race_pars.sc_pit_window_laps,
+            sc_periods: Vec::new(),
+            cur_sc_period: None,
+            formation_lap_prog: 0.0,
+            t_formation_lap: sim_consts.formation_pace_factor
+                * (track.t_q + track.t_gap_racepace),
+            formation_wear_factor: sim_consts.formation_wear_factor,
+            model_toggles: race_pars.model_toggles,
+            finish_min_pace_factor: sim_consts.finish_min_pace_factor,
+            restart_pace_margin: sim_consts.restart_pace_margin,
+            restart_s_track: get_restart_s_track(&track, sim_consts.restart_line),
+            drs_trains: Vec::new(),
+            scripted_events,
+            scripted_overrides,
+            sc_scripted_lap_in: None,
+            t_penalties: vec![0.0; no_cars],
+            t_stopped_until: vec![0.0; no_cars],
+            launch_deltas: vec![0.0; no_cars],
+            first_corner_incident: None,
+            first_corner_incident_act: false,
+            rolling_start,
+            first_corner_damage_s: sim_consts.first_corner_damage_s,
+            damage_pars,
+            ers_pars,
+            ers_deploy_cur: vec![false; no_cars],
+            engine_mode_select_pars: EngineModeSelectPars {
+                auto: sim_consts.engine_mode_auto,
+                push_gap_s: sim_consts.engine_push_gap_s,
+                conserve_gap_s: sim_consts.engine_conserve_gap_s,
+                conserve_laps: sim_consts.engine_conserve_laps,
+            },
+            setup_pars,
+            overheat_gap_s: sim_consts.overheat_gap_s,
+            dsq_technical,
+            engine_modes: vec![
+                vec![EngineMode::default(); race_pars.tot_no_laps as usize + 1];
+                no_cars
+            ],
+            compounds: vec![vec![String::new(); race_pars.tot_no_laps as usize + 1]; no_cars],
+            starting_grid,
+            session_start_h: race_pars.session_start_h,
+            sunset_h: race_pars.sunset_h,
+            track_temp_pars: TrackTempPars {
+                temp_day_c: sim_consts.track_temp_day_c,
+                temp_night_c: sim_consts.track_temp_night_c,
+                cooling_time_h: sim_consts.track_temp_cooling_time_h,
+                temp_ref_c: sim_consts.track_temp_ref_c,
+            },
             safety_car: SafetyCar::new(),
             sc_timer: 0.0,
-            sc_triggers: vec![false; no_cars], //na start wszystkie false
-            sc_target_gap_m: 15.0,
+            incident_queue: Vec::new(),
+            sc_target_gap_m: sim_consts.sc_target_gap_m,
             sc_lineup_tolerance_m: 5.0,
             sc_release_delay_s: 5.0,
-            season: race_pars.season,
+            min_spacing_m: sim_consts.min_spacing_m,
+            clearance_pars: ClearancePars {
+                mean_s: sim_consts.clearance_mean_s,
+                crash_factor: sim_consts.clearance_crash_factor,
+                corner_factor: sim_consts.clearance_corner_factor,
+                sigma_rel: sim_consts.clearance_sigma_rel,
+            },
+            sc_deployment_pars: ScDeploymentPars {
+                probability: sim_consts.sc_probability,
+                corner_factor: sim_consts.sc_corner_factor,
+                overtaking_zone_factor: sim_consts.sc_overtaking_zone_factor,
+            },
+            sc_delta_factor: sim_consts.sc_delta_factor,
+            incident: None,
+            sc_bunching_lap: None,
+            vsc_probability: race_pars.vsc_probability,
+            vsc_delta_s: race_pars.vsc_delta_s,
+            vsc_duration_s: race_pars.vsc_duration_s,
+            vsc_timer: 0.0,
+            vsc_mini_sectors: track.get_mini_sectors(sim_consts.vsc_no_mini_sectors),
+            t_vsc_debts: vec![0.0; no_cars],
+            t_vsc_givebacks: vec![0.0; no_cars],
+            laptimes_vsc_mini_sector: vec![f64::INFINITY; no_cars],
+            red_flag_probability: race_pars.red_flag_probability,
+            red_flag_min_cars: race_pars.red_flag_min_cars,
+            heavy_rain_red_flag_probability: race_pars.heavy_rain_red_flag_probability,
+            red_flag_duration_s: race_pars.red_flag_duration_s,
+            red_flag_laps_deducted: race_pars.red_flag_laps_deducted,
+            red_flag_compound: race_pars.red_flag_compound.to_owned(),
+            red_flag_timer: 0.0,
+            pit_closed_on_sc_deploy: race_pars.pit_closed_on_sc_deploy,
+            pit_closure: None,
+            timing_histories: vec![TimingHistory::default(); no_cars],
             tot_no_laps: race_pars.tot_no_laps,
             drs_allowed_lap: race_pars.drs_allowed_lap,
             cur_lap_leader: 1,
@@ -229,20 +2122,65 @@ impl Race {
             drs_window: sim_consts.drs_window,
             use_drs: race_pars.use_drs,
             flag_state: FlagState::G,
-            track: Track::new(track_pars),
+            track,
             race_finished: vec![false; no_cars],
             laptimes: vec![vec![0.0; race_pars.tot_no_laps as usize + 1]; no_cars],
             racetimes: vec![vec![0.0; race_pars.tot_no_laps as usize + 1]; no_cars],
             cur_laptimes: vec![0.0; no_cars],
             cur_th_laptimes: vec![0.0; no_cars],
+            laptime_breakdowns: vec![
+                vec![LaptimeBreakdown::default(); race_pars.tot_no_laps as usize + 1];
+                no_cars
+            ],
+            cur_th_breakdowns: vec![LaptimeBreakdown::default(); no_cars],
+            t_clean_laptimes: vec![0.0; no_cars],
+            time_loss_deltas_cur: vec![TimeLossBreakdown::default(); no_cars],
+            time_losses_cur: vec![TimeLossBreakdown::default(); no_cars],
+            time_losses: vec![TimeLossBreakdown::default(); no_cars],
+            race_progs_time_loss: vec![0.0; no_cars],
+            missed_inlaps: vec![Vec::new(); no_cars],
             cars_list,
             drivers_list,
             tire_config: tire_config.clone(),
         };
 
+        // procedura startu: czasy reakcji, anti-stall i falstarty
+        let start_pars = StartPars {
+            reaction_mean_s: sim_consts.start_reaction_mean_s,
+            reaction_sigma_s: sim_consts.start_reaction_sigma_s,
+            jump_start_threshold_s: sim_consts.jump_start_threshold_s,
+            anti_stall_probability: sim_consts.anti_stall_probability,
+            anti_stall_delay_s: sim_consts.anti_stall_delay_s,
+            launch_delta_s: sim_consts.launch_delta_s,
+            launch_sigma_s: sim_consts.launch_sigma_s,
+        };
+        if !race.rolling_start {
+            race.handle_start_procedure(&start_pars, sim_consts.jump_start_penalty_s);
+        }
+
+        // karambol w pierwszym zakręcie (losowy model wyłączony przez wymuszone wycofanie, brak
+        // przy starcie za SC)
+        if !race.scripted_overrides.dnf && !race.rolling_start {
+            let first_corner_pars = FirstCornerPars {
+                probability: sim_consts.first_corner_probability,
+                dnf_probability: sim_consts.first_corner_dnf_probability,
+                t_loss_s: sim_consts.first_corner_t_loss_s,
+            };
+            let mut grid_order: Vec<usize> = (0..race.cars_list.len()).collect();
+            grid_order.sort_by_key(|&i| race.cars_list[i].p_grid);
+            let aggressions: Vec<f64> =
+                race.cars_list.iter().map(|car| car.driver.aggression).collect();
+
+            race.first_corner_incident = first_corner_pars.draw_incident(
+                &grid_order,
+                &aggressions,
+                &mut race.rng,
+            );
+        }
+
         // initialize race for each car
         for idx in 0..race.cars_list.len() {
-            // calculate theoretical lap time for first lap
+            // calculate theoretical lap time for first lap (wilgotność toru na starcie)
             race.calc_th_laptime(idx);
 
             // initialize state handler of the car
@@ -262,6 +2200,23 @@ impl Race {
                 race.track.overtaking_zones.to_owned(),         // 8. Strefy wyprzedzania
                 race.track.corners.to_owned(),                  // 9. Zakręty
             );
+            car.sh.init_mini_sectors(
+                race.vsc_mini_sectors.iter().map(|mini_sector| mini_sector.s_start).collect(),
+            );
+
+            // czysty przejazd liczony jest od linii startu, pole startowe daje więc zysk/stratę
+            race.t_clean_laptimes[idx] = race.track.t_q
+                + race.track.t_gap_racepace
+                + car.get_t_car()
+                + car.driver.t_driver;
+            race.race_progs_time_loss[idx] = car.sh.get_race_prog();
+            race.time_losses_cur[idx].t_start =
+                -race.t_clean_laptimes[idx] * race.race_progs_time_loss[idx];
+            race.compounds[idx][1] = car.get_current_compound().to_owned();
+        }
+
+        if race.rolling_start {
+            race.start_behind_sc(race_pars.start_behind_sc_laps);
         }
 
         race
@@ -274,75 +2229,51 @@ impl Race {
     /// Metoda symuluje jeden krok czasowy.
     pub fn simulate_timestep(&mut self) {
 
+        // czerwona flaga: wyścig jest zawieszony - auta stoją w miejscu, a czas wyścigu nie płynie
+        if matches!(self.flag_state, FlagState::Red) {
+            self.red_flag_timer -= self.timestep_size;
+
+            if self.red_flag_timer <= 0.0 {
+                self.restart_after_red_flag();
+            }
+            return;
+        }
+
+        // okrążenie formujące: stawka jedzie w kolejności pola startowego, czas wyścigu nie płynie
+        if self.formation_lap_act {
+            self.formation_lap_prog += self.timestep_size / self.t_formation_lap;
+
+            if self.formation_lap_prog >= 1.0 {
+                self.end_formation_lap();
+            }
+            return;
+        }
+
         // Pogoda: skaluj prawdopodobieństwo zmian do kroku czasu i wymuś minimalny czas trwania
         // Interpretacja: `rain_probability` to prawdopodobieństwo zmiany na minutę (nie na krok).
-        let mut rng = rand::thread_rng();
+        let mut rng = self.fork_rng();
         let eligible_for_change = (self.cur_racetime - self.last_weather_change) >= self.min_weather_duration_s;
-        if eligible_for_change {
+        if eligible_for_change && self.model_toggles.is_enabled(ModelToggles::WEATHER) {
             let p_step = self.rain_probability * (self.timestep_size / 60.0);
             if rng.gen::<f64>() < p_step {
-                self.weather_state = match self.weather_state {
-                    WeatherState::Dry => {
-                        if self.print_events { println!("WEATHER CHANGE: Rain started at {:.2}s!", self.cur_racetime); }
-                        self.last_weather_change = self.cur_racetime;
-                        // event: rain start
-                        self.events.push(RaceEvent {
-                            kind: "WeatherRainStart".to_string(),
-                            lap: self.cur_lap_leader,
-                            time_s: self.cur_racetime,
-                            cars: vec![],
-                        });
-                        // Zaplanuj pit na najbliższe okrążenie dla slicków → Intermediate
-                        for (i, car) in self.cars_list.iter_mut().enumerate() {
-                            if car.status == CarStatus::DNF { continue; }
-                            let comp = car.get_current_compound();
-                            match comp {
-                                "SOFT" | "MEDIUM" | "HARD" => {
-                                    car.last_slick_compound = Some(comp.to_owned());
-                                    let target_lap = car.sh.get_compl_lap() + 1;
-                                    car.schedule_weather_strategy(target_lap, "INTERMEDIATE");
-                                },
-                                _ => {},
-                            }
-                        }
-                        WeatherState::Rain
-                    },
-                    WeatherState::Rain => {
-                        if self.print_events { println!("WEATHER CHANGE: Rain stopped at {:.2}s!", self.cur_racetime); }
-                        self.last_weather_change = self.cur_racetime;
-                        // event: dry start
-                        self.events.push(RaceEvent {
-                            kind: "WeatherDryStart".to_string(),
-                            lap: self.cur_lap_leader,
-                            time_s: self.cur_racetime,
-                            cars: vec![],
-                        });
-                        // Zaplanuj pit na najbliższe okrążenia dla Inter/Wet → powrót do slicków
-                        for (i, car) in self.cars_list.iter_mut().enumerate() {
-                            if car.status == CarStatus::DNF { continue; }
-                            let comp = car.get_current_compound();
-                            let target_slick = car.last_slick_compound.clone().unwrap_or_else(|| "MEDIUM".to_string());
-                            match comp {
-                                "INTERMEDIATE" => {
-                                    let target_lap = car.sh.get_compl_lap() + 1;
-                                    car.schedule_weather_strategy(target_lap, &target_slick);
-                                },
-                                "WET" => {
-                                    let target_lap = car.sh.get_compl_lap() + 2;
-                                    car.schedule_weather_strategy(target_lap, &target_slick);
-                                },
-                                _ => {},
-                            }
-                        }
-                        WeatherState::Dry
-                    },
-                };
+                self.change_weather(false);
             }
         }
 
         // increment discretization variable
         self.cur_racetime += self.timestep_size;
 
+        // nasiąkanie/wysychanie toru, ulewa może przerwać wyścig (reszta kroku jest pomijana)
+        if self.update_wetness()
+            && !matches!(self.flag_state, FlagState::C)
+            && !self.finish_under_sc
+            && self.heavy_rain_red_flag_probability > 0.0
+            && rng.gen::<f64>() < self.heavy_rain_red_flag_probability
+        {
+            self.suspend_race(&[]);
+            return;
+        }
+
         if matches!(self.flag_state, FlagState::Sc){
             if self.sc_timer.is_finite() {
                 self.sc_timer -= self.timestep_size;
@@ -350,6 +2281,8 @@ impl Race {
 
             if !self.safety_car.active{
                 self.safety_car.active = true;
+                self.safety_car.in_this_lap = false;
+                self.safety_car.leader_caught = false;
                 // safety car startuje z poziomu lidera
                 let mut leader_idx = 0;
                 let mut max_prog = -1.0;
@@ -363,133 +2296,438 @@ impl Race {
                     }
                 }
 
-                self.safety_car.s_track = self.cars_list[leader_idx].sh.get_s_tracks().1 + 500.0;
-                // Obsługa przypadku, gdy dodanie 500m wyrzuca SC na następne okrążenie
-                if self.safety_car.s_track > self.track.length {
-                    self.safety_car.s_track -= self.track.length;
-                    self.safety_car.lap = self.cur_lap_leader + 1;
+                // SC wyjeżdża na tor na końcu alei serwisowej - najbliższym przed liderem (jeśli
+                // lider minął już wyjazd z alei, SC jest przed nim o prawie całe okrążenie) - i jedzie
+                // w swoim tempie, aż lider go dogoni
+                let leader_sh = &self.cars_list[leader_idx].sh;
+                let pit_exit = self.track.pit_zone[1];
+                self.safety_car.s_track = pit_exit;
+                self.safety_car.lap = if leader_sh.get_s_tracks().1 < pit_exit {
+                    leader_sh.get_compl_lap() + 1
                 } else {
-                    self.safety_car.lap = self.cur_lap_leader;
-                }
+                    leader_sh.get_compl_lap() + 2
+                };
+                let cause_car_no =
+                    self.incident.as_ref().and_then(|incident| incident.cars.first().copied());
+                self.open_sc_period(ScPeriodKind::Sc, cause_car_no);
                 // event: SC deployed
-                self.events.push(RaceEvent {
-                    kind: "SC_DEPLOYED".to_string(),
-                    lap: self.cur_lap_leader,
-                    time_s: self.cur_racetime,
-                    cars: vec![],
+                self.push_event(RaceEvent {
+                    scripted: self.sc_scripted_lap_in.is_some(),
+                    ..RaceEvent::new("SC_DEPLOYED", self.cur_lap_leader, self.cur_racetime, vec![])
                 });
+                self.advance_pit_stops_under_sc();
             }
 
             // przecunięcie SC do przodu
-            self.safety_car.s_track += self.safety_car.speed * self.timestep_size;
-
-            if self.safety_car.s_track > self.track.length {
-                self.safety_car.s_track -= self.track.length;
-                self.safety_car.lap +=1;
+            let sc_s_track_prev = self.safety_car.s_track;
+            let sc_lap_prev = self.safety_car.lap;
+            self.move_safety_car();
+
+            // praca porządkowych - po jej zakończeniu SC robi jeszcze jedno okrążenie (zbieranie stawki)
+            if let Some(incident) = self.incident.as_mut() {
+                if !incident.is_cleared() {
+                    incident.t_elapsed += self.timestep_size;
+
+                    if incident.is_cleared() {
+                        self.sc_bunching_lap = Some(self.safety_car.lap + 1);
+                        if self.print_events {
+                            println!("INCIDENT CLEARED after {:.1}s - SAFETY CAR IN AFTER ONE MORE LAP", incident.t_clearance);
+                        }
+                    }
+                }
             }
 
-            if self.sc_timer.is_finite() && self.sc_timer <= 0.00 {
-                if self.print_events { println!("SAFETY CAR IN THIS LAP - RACE RESUMING"); }
+            // koniec okresu SC: SC jedzie dalej i zjeżdża dopiero przy wjeździe do alei lub na linii
+            // mety, do tego czasu auta jadą za nim w kolejce
+            if self.sc_timer.is_finite() && self.sc_timer <= 0.00 && !self.safety_car.in_this_lap {
+                if self.print_events { println!("SAFETY CAR IN THIS LAP"); }
+                self.safety_car.in_this_lap = true;
+            } else if self.safety_car.in_this_lap
+                && self.is_sc_peel_off_passed(sc_s_track_prev, sc_lap_prev)
+            {
+                if self.print_events { println!("SAFETY CAR IN - RACE RESUMING"); }
                 self.flag_state = FlagState::G;
                 self.safety_car.active = false;
+                self.safety_car.in_this_lap = false;
+                self.incident = None;
+                self.sc_bunching_lap = None;
+                self.restart_lap = Some(self.cur_lap_leader + 1);
+                self.rolling_restart_act = true;
+                self.close_sc_period();
                 // event: SC in
-                self.events.push(RaceEvent {
-                    kind: "SC_IN".to_string(),
-                    lap: self.cur_lap_leader,
-                    time_s: self.cur_racetime,
-                    cars: vec![],
+                let scripted = self.sc_scripted_lap_in.take().is_some();
+                self.push_event(RaceEvent {
+                    scripted,
+                    ..RaceEvent::new("SC_IN", self.cur_lap_leader, self.cur_racetime, vec![])
                 });
             }
+        } else if self.finish_under_sc {
+            // meta za SC: SC prowadzi dalej auta, które nie ukończyły jeszcze ostatniego okrążenia
+            self.move_safety_car();
         } else{
             self.safety_car.active = false;
         }
 
+        // VSC: po upływie czasu trwania wyścig wraca na zieloną flagę
+        if matches!(self.flag_state, FlagState::Vsc) {
+            self.vsc_timer -= self.timestep_size;
+
+            if self.vsc_timer <= 0.0 {
+                if self.print_events { println!("VIRTUAL SAFETY CAR ENDING - RACE RESUMING"); }
+                self.flag_state = FlagState::G;
+                self.incident = None;
+                self.close_sc_period();
+                self.push_event(RaceEvent::new(
+                    "VSC_ENDING",
+                    self.cur_lap_leader,
+                    self.cur_racetime,
+                    vec![],
+                ));
+            }
+        }
+
         let active_sc = matches!(self.flag_state, FlagState::Sc);
-        if !active_sc {
-            for (i, car) in self.cars_list.iter().enumerate() {
-                // Sprawdzamy czy auto ma DNF i czy nie skończyło wyścigu (zabezpieczenie przed ciągłym wywoływaniem SC)
-                if car.status == CarStatus::DNF && !self.race_finished[i] && !self.sc_triggers[i] {
-                     // Tutaj prosta logika: jak ktoś ma DNF i nie dojechał do mety (czyli rozbił się), wywołaj SC.
-                     // W pełnej wersji trzeba by sprawdzać czy ten DNF nastąpił *teraz*.
-                    if self.print_events { println!("SAFETY CAR DEPLOYED (Caused by car #{})", car.car_no); }
-                    self.flag_state = FlagState::Sc;
-                    // Tryb dynamiczny: odjazd po ustawieniu kolejki kierowców
-                    self.sc_timer = f64::INFINITY; // włącz licznik dopiero po lineup
-                    // Oznacz wszystkie aktualne DNFs jako już obsłużone (unikamy podwójnego SC)
-                    for (j, c) in self.cars_list.iter().enumerate() {
-                        if c.status == CarStatus::DNF { self.sc_triggers[j] = true; }
-                    }
-                    break;
-                }
+        // po szachownicy wyścig nie jest już neutralizowany (wycofania są tylko zapisywane)
+        let chequered = matches!(self.flag_state, FlagState::C);
+        if !self.model_toggles.is_enabled(ModelToggles::SC) {
+            // ablacja: wycofania bez neutralizacji, stojące auta osłania tylko lokalna żółta flaga
+            self.incident_queue.clear();
+        } else if !active_sc && !chequered && !self.scripted_overrides.safety_car {
+            // wycofania zapisane od ostatniej decyzji (każde wycofanie jest rozpatrywane raz), bez
+            // aut, które już ukończyły wyścig
+            let incident_idxs: Vec<usize> = std::mem::take(&mut self.incident_queue)
+                .into_iter()
+                .filter(|&i| !self.race_finished[i])
+                .collect();
+
+            // mniejszy incydent (jedno auto, np. awaria silnika) - z szansą `vsc_probability` VSC
+            // zamiast SC
+            // (karambol w pierwszym zakręcie zawsze jest poważnym incydentem)
+            let vsc = incident_idxs.len() == 1
+                && !self.is_first_corner_pileup(&incident_idxs)
+                && rng.gen::<f64>() < self.vsc_probability;
+            // poważny wypadek - czerwona flaga zamiast SC
+            let red_flag = !vsc
+                && !incident_idxs.is_empty()
+                && self.is_red_flag_incident(&incident_idxs, &mut rng);
+
+            // SC tylko z prawdopodobieństwem zależnym od miejsca, w którym stanęło auto - w
+            // przeciwnym razie VSC (zakręt) lub tylko lokalna żółta flaga
+            let sc_outcome = if vsc || red_flag || incident_idxs.is_empty() {
+                None
+            } else {
+                Some(self.decide_sc(&incident_idxs, &mut rng))
+            };
+
+            if vsc || sc_outcome == Some(ScOutcome::Vsc) {
+                self.deploy_vsc(self.cars_list[incident_idxs[0]].car_no);
+            } else if red_flag {
+                // wyścig przerwany, reszta kroku jest pomijana
+                self.suspend_race(&incident_idxs);
+                return;
+            } else if sc_outcome == Some(ScOutcome::LocalYellow) {
+                // stojące auto jest osłonięte tylko lokalną żółtą flagą (update_crash_sites)
+            } else if !incident_idxs.is_empty() {
+                let car = &self.cars_list[incident_idxs[0]];
+                if self.print_events { println!("SAFETY CAR DEPLOYED (Caused by car #{})", car.car_no); }
+                // Czas pracy porządkowych zależy od liczby aut i miejsca incydentu (zakręt/prosta)
+                let s_track = car.sh.get_s_tracks().1;
+                self.set_flag_state(FlagState::Sc);
+                // Tryb dynamiczny: odjazd po usunięciu skutków incydentu i ustawieniu kolejki kierowców
+                self.sc_timer = f64::INFINITY; // włącz licznik dopiero po lineup
+
+                let multiplier = self.track.get_multiplier(s_track);
+                let t_clearance = self.clearance_pars.draw_clearance_time(
+                    incident_idxs.len(),
+                    multiplier,
+                    &mut rng,
+                );
+                self.incident = Some(Incident {
+                    cars: incident_idxs.iter().map(|&i| self.cars_list[i].car_no).collect(),
+                    s_track,
+                    t_clearance,
+                    t_elapsed: 0.0,
+                });
+                self.sc_bunching_lap = None;
+                self.close_pit_entry();
             }
         }
 
+        // świeże wypadki (omijanie przez auta z tyłu do czasu oficjalnej flagi)
+        self.update_crash_sites();
+
         // adjust current lap times
         self.calc_cur_laptimes();
+        debug_assert!(
+            self.cur_laptimes.iter().all(|t| !t.is_nan()),
+            "NaN in current lap times: {:?}",
+            self.cur_laptimes
+        );
 
         // handle state transitions
         self.handle_state_transitions();
 
-        // update race progress
+        // czas w alei serwisowej (przejazd + postój, również przejazdy bez postoju)
+        for (i, car) in self.cars_list.iter().enumerate() {
+            if car.sh.pit_act && !self.race_finished[i] {
+                self.t_pitlane[i] += self.timestep_size;
+            }
+            if car.sh.pit_act {
+                self.lap_flags_cur[i] |= LAP_FLAG_PIT;
+            }
+        }
+
+        // neutralizacja (SC, VSC) albo żółta flaga w sektorze auta w części okrążenia
+        for i in 0..self.cars_list.len() {
+            let flag_state = self.get_car_flag_state(i);
+            if matches!(flag_state, FlagState::Vsc | FlagState::Sc) || self.finish_under_sc {
+                self.lap_flags_cur[i] |= LAP_FLAG_SC;
+            } else if matches!(flag_state, FlagState::Y) {
+                self.lap_flags_cur[i] |= LAP_FLAG_YELLOW;
+            }
+        }
+
+        // update race progress (auta ruszają ze startu dopiero po swoim czasie reakcji)
         for (i, car) in self.cars_list.iter_mut().enumerate() {
-            car.sh
-                .update_race_prog(self.cur_laptimes[i], self.timestep_size)
+            let t_driving =
+                (self.cur_racetime - self.t_stopped_until[i]).clamp(0.0, self.timestep_size);
+            car.sh.update_race_prog(self.cur_laptimes[i], t_driving)
         }
 
+        // czasy minisektorów (kontrola tempa podczas VSC)
+        self.enforce_vsc_mini_sectors();
+
+        // auta nie mogą na siebie najeżdżać
+        self.enforce_min_spacing();
+
+        // karambol w pierwszym zakręcie (wylosowany przy tworzeniu wyścigu)
+        self.handle_first_corner_incident();
+
+        // wypadki z winy kierowcy
+        self.handle_driver_crashes();
+
+        // przebicia opon (zużyte opony, gruz po wypadkach)
+        self.handle_punctures();
+
         // handle pit stop standstill part (uncommon case)
         if !self.track.pits_aft_finishline {
             self.handle_pit_standstill()
         }
 
+        // limit czasu wyścigu - bieżące okrążenie lidera staje się ostatnim
+        self.check_time_limit();
+
         // handle lap transitions
         self.handle_lap_transitions();
 
+        // koniec restartu po SC, gdy lider minie linię restartu
+        self.update_rolling_restart();
+
+        // czasy przekroczenia linii mety i granic sektorów (odstępy na żywo)
+        self.update_timing_histories();
+
+        // usuwanie gruzu i stojących aut (lokalne żółte flagi) po upływie okrążeń
+        self.update_debris_zones();
+        self.update_yellow_zones();
+
         // handle pit stop standstill part (common case)
         if self.track.pits_aft_finishline {
             self.handle_pit_standstill()
         }
+
+        // rozliczenie straty czasu względem czystego przejazdu
+        self.update_time_losses();
     }
 
     // ---------------------------------------------------------------------------------------------
     // RACE SIMULATOR PARTS ------------------------------------------------------------------------
     // ---------------------------------------------------------------------------------------------
 
-    /// Oblicza teoretyczny czas okrążenia
+    /// retire_cars wycofuje auta o podanych indeksach z wyścigu (DNF) w bieżącej chwili wyścigu i
+    /// zapisuje jedno zdarzenie wycofania (zob. `record_retirement`).
+    pub fn retire_cars(&mut self, idxs: &[usize], cause: RetirementCause) {
+        let cars = idxs.iter().map(|&idx| self.cars_list[idx].car_no).collect();
+        self.record_retirement(idxs, cause, cars, self.cur_racetime);
+    }
+
+    /// record_retirement wycofuje auta (DNF) i zapisuje zdarzenie z przyczyną, okrążeniem lidera,
+    /// czasem wyścigu i miejscem na torze (pierwszego wycofanego auta) oraz liczbę ukończonych
+    /// okrążeń, miejsce i czas wycofania każdego auta. Wszystkie przyczyny DNF (awaria, kolizja,
+    /// zdarzenie wymuszone) są obsługiwane w tym jednym miejscu. Wycofania trafiają do kolejki
+    /// incydentów, na podstawie której w następnym kroku zapada decyzja o SC/VSC/czerwonej fladze
+    /// (wymuszone wycofanie tylko, jeśli scenariusz nie wymusza SC).
+    /// * `cars` - Auta biorące udział w zdarzeniu (np. również auto, w które wjechało wycofane)
+    fn record_retirement(
+        &mut self,
+        idxs: &[usize],
+        cause: RetirementCause,
+        cars: Vec<u32>,
+        time_s: f64,
+    ) {
+        let idxs: Vec<usize> = idxs
+            .iter()
+            .copied()
+            .filter(|&idx| {
+                self.cars_list[idx].status != CarStatus::DNF && !self.race_finished[idx]
+            })
+            .collect();
+
+        if idxs.is_empty() {
+            return;
+        }
+
+        for &idx in idxs.iter() {
+            let car = &mut self.cars_list[idx];
+            car.status = CarStatus::DNF;
+            self.retirements[idx] = Some(RetirementInfo {
+                lap: car.sh.get_compl_lap(),
+                s_track: car.sh.get_s_tracks().1,
+                time_s,
+                failure: cause.get_failure_type(),
+            });
+
+            if cause != RetirementCause::Scripted || !self.scripted_overrides.safety_car {
+                self.incident_queue.push(idx);
+            }
+
+            // po wypadku w jego miejscu leży gruz (ryzyko przebicia opon)
+            if cause == RetirementCause::Crash {
+                self.puncture_debris.push((car.sh.get_s_tracks().1, time_s));
+            }
+        }
+
+        let s_track = self.cars_list[idxs[0]].sh.get_s_tracks().1;
+        self.push_event(RaceEvent {
+            scripted: cause == RetirementCause::Scripted,
+            s_range: Some([s_track, s_track]),
+            ..RaceEvent::new(cause.get_event_kind(), self.cur_lap_leader, time_s, cars)
+        });
+    }
+
+    /// update_time_losses rozlicza stratę czasu każdego auta w bieżącym kroku względem czystego
+    /// przejazdu. Strata kroku (czas kroku minus czas czystego przejazdu przejechanego dystansu)
+    /// jest dzielona na przyczyny proporcjonalnie do dodatków do czasu okrążenia oznaczonych w
+    /// `calc_cur_laptimes`. Na linii mety strata jest zapamiętywana, tak aby suma przyczyn była
+    /// równa różnicy czasu wyścigu i czasu czystego przejazdu ukończonych okrążeń.
+    fn update_time_losses(&mut self) {
+        for i in 0..self.cars_list.len() {
+            let car = &self.cars_list[i];
+            let new_lap = car.sh.get_new_lap();
+
+            // auta wycofane wcześniej nie jadą (wycofane na linii rozliczają jeszcze okrążenie)
+            if car.status == CarStatus::DNF && !new_lap {
+                continue;
+            }
+
+            let compl_lap = car.sh.get_compl_lap();
+            let race_prog = car.sh.get_race_prog();
+            let race_prog_prev = self.race_progs_time_loss[i];
+            let delta_race_prog = race_prog - race_prog_prev;
+
+            // czekanie na starcie (reakcja, anti-stall) to w całości strata startu, postój po
+            // kontakcie w ataku na dalszych okrążeniach to strata pojedynków
+            let t_driving =
+                (self.cur_racetime - self.t_stopped_until[i]).clamp(0.0, self.timestep_size);
+            if compl_lap == 0 {
+                self.time_losses_cur[i].t_start += self.timestep_size - t_driving;
+            } else {
+                self.time_losses_cur[i].t_duels += self.timestep_size - t_driving;
+            }
+
+            if new_lap && compl_lap <= self.tot_no_laps {
+                // podział kroku na linii mety (jak przy interpolacji czasu przekroczenia linii)
+                let race_prog_line = compl_lap as f64;
+                let step_frac = if delta_race_prog > 0.0 {
+                    ((race_prog_line - race_prog_prev) / delta_race_prog).clamp(0.0, 1.0)
+                } else {
+                    1.0
+                };
+
+                self.account_time_loss(i, t_driving * step_frac, race_prog_line - race_prog_prev);
+                self.time_losses[i] = self.time_losses_cur[i];
+                self.time_losses[i].t_penalties = self.t_penalties[i];
+                self.account_time_loss(
+                    i,
+                    t_driving * (1.0 - step_frac),
+                    race_prog - race_prog_line,
+                );
+            } else {
+                self.account_time_loss(i, t_driving, delta_race_prog);
+            }
+
+            self.race_progs_time_loss[i] = race_prog;
+        }
+    }
+
+    /// account_time_loss dolicza stratę czasu auta na odcinku kroku (czas jazdy `t_driving`,
+    /// przejechany postęp wyścigu `delta_race_prog`).
+    fn account_time_loss(&mut self, idx: usize, t_driving: f64, delta_race_prog: f64) {
+        let t_clean = self.t_clean_laptimes[idx];
+        let t_loss = t_driving - t_clean * delta_race_prog;
+        let cur_laptime = self.cur_laptimes[idx];
+        let pit_act = self.cars_list[idx].sh.pit_act;
+
+        if !cur_laptime.is_finite() || cur_laptime <= 0.0 {
+            // postój (np. w alei serwisowej) - cała strata z jednej przyczyny
+            let cause = if pit_act { TimeLossCause::PitStops } else { TimeLossCause::Other };
+            self.time_losses_cur[idx].add(cause, t_loss);
+            return;
+        }
+
+        let deltas = self.time_loss_deltas_cur[idx];
+        self.time_losses_cur[idx].add_scaled(&deltas, t_driving / cur_laptime);
+
+        // różnica między postępem z czasu okrążenia a faktycznym (aleja serwisowa, odstępy)
+        let t_loss_rest = t_loss - t_driving * (cur_laptime - t_clean) / cur_laptime;
+        let cause = if pit_act { TimeLossCause::PitStops } else { TimeLossCause::Other };
+        self.time_losses_cur[idx].add(cause, t_loss_rest);
+    }
+
+    /// Oblicza teoretyczny czas okrążenia (suma składników z `Car::laptime_breakdown`)
     fn calc_th_laptime(&mut self, idx: usize) {
         if self.cars_list[idx].status == CarStatus::DNF {
             self.cur_th_laptimes[idx] = f64::INFINITY;
             return;
         }
-        let consistency = self.cars_list[idx].driver.consistency; 
-
-        let std_dev = (1.0 - consistency) * 2.0;
 
-        let random_factor = if std_dev > 0.0 {
-            let normal = Normal::new(0.0, std_dev).unwrap();
-            normal.sample(&mut rand::thread_rng())
-        } else {
-            0.0
-        };
+        // Pogoda: kara zależy od wilgotności toru na początku okrążenia
+        let mut breakdown =
+            self.cars_list[idx].laptime_breakdown(&self.track, self.wetness, &self.tire_config);
+        if !self.model_toggles.is_enabled(ModelToggles::TIRE_DEGRADATION) {
+            breakdown.t_tire = 0.0;
+        }
 
-        //Pogoda
-        let is_wet = self.weather_state == WeatherState::Rain;
+        // start z miejsca: strata toru i delta startu kierowcy tylko w 1. okrążeniu (brak przy
+        // starcie za SC)
+        let lap = self.cars_list[idx].sh.get_compl_lap() as usize + 1;
+        if lap == 1 && !self.rolling_start {
+            breakdown.t_launch = self.track.t_loss_firstlap + self.launch_deltas[idx];
+        }
 
-        
-        // Bazowy czas
-        let lap_time_base = self.track.t_q
-        + self.track.t_gap_racepace
-        + self.cars_list[idx].calc_basic_timeloss(self.track.s_mass, is_wet, &self.tire_config);
+        self.cur_th_laptimes[idx] = breakdown.get_total();
+        self.cur_th_breakdowns[idx] = breakdown;
 
-        self.cur_th_laptimes[idx] = lap_time_base + random_factor;
+        // składniki zapisywane dla bieżącego okrążenia (ostatnie obliczenie, np. po pit stopie)
+        if lap == 1 {
+            breakdown.t_start = self.t_stopped_until[idx];
+        }
+        if let Some(x) = self.laptime_breakdowns[idx].get_mut(lap) {
+            *x = breakdown;
+        }
     }
 
     /// Dostosowuje teoretyczne czasy okrążeń (uproszczone).
 /// Dostosowuje teoretyczne czasy okrążeń (uproszczone + SC logic).
     fn calc_cur_laptimes(&mut self) {
-        // Sprawdź czy Safety Car jest fizycznie na torze i aktywny
-        let sc_active = matches!(self.flag_state, FlagState::Sc) && self.safety_car.active;
+        // Sprawdź czy Safety Car jest fizycznie na torze i aktywny (również na mecie za SC)
+        let sc_active = (matches!(self.flag_state, FlagState::Sc) || self.finish_under_sc)
+            && self.safety_car.active;
+        // VSC: wszystkie auta w tym samym tempie (odstępy zamrożone, bez pojedynków i wyprzedzania)
+        let vsc_active = matches!(self.flag_state, FlagState::Vsc);
+        // restart po SC: lider dyktuje tempo, pozostałe auta jadą w kolejce bez wyprzedzania
+        let restart_active = self.is_rolling_restart();
         
-        let sc_speed = if sc_active { self.safety_car.speed } else { 0.0 };
+        let sc_speed = if sc_active { self.get_sc_speed() } else { 0.0 };
 
         // Oblicz całkowity dystans Safety Cara od startu wyścigu.
         // Safety Car `lap` to numer aktualnego okrążenia (od 1).
@@ -501,6 +2739,11 @@ impl Race {
             0.0
         };
 
+        // dodatki do czasu okrążenia wg przyczyn (do rozliczenia straty czasu)
+        for deltas in self.time_loss_deltas_cur.iter_mut() {
+            *deltas = TimeLossBreakdown::default();
+        }
+
         // --- CZĘŚĆ 1: PODSTAWOWE OBLICZENIA (FIZYKA + PIT STOPY) ---
         // (SC Logic wyrzucone stąd do osobnego bloku niżej, żeby obsłużyć kolejkowanie)
         for (i, car) in self.cars_list.iter().enumerate() {
@@ -515,55 +2758,126 @@ impl Race {
             // NOWY KOD
             let s_track = car.sh.get_s_tracks().1;
 
-            // KROK 2: Mapujemy metry na indeks tablicy multipliers
-            // Dzielimy pozycję przez długość toru (ułamek 0.0-1.0) i mnożymy przez liczbę punktów pomiarowych
-            let mult_count = self.track.multipliers.len();
-            let mut idx_m = ((s_track / self.track.length) * mult_count as f64) as usize;
-
-            // Zabezpieczenie: jeśli idx_m wyjdzie poza zakres (np. na samej mecie), bierzemy ostatni element
-            if idx_m >= mult_count { 
-                idx_m = mult_count.saturating_sub(1); 
-            }
-
-            // KROK 3: Pobieramy wartość mnożnika dla tego fragmentu toru
-            // Jeśli wektor jest pusty (błąd pliku), ustawiamy bezpieczne 1.0
-            let multiplier = if mult_count > 0 { 
-                self.track.multipliers[idx_m] 
-            } else { 
-                1.0 
-            };
+            // KROK 2: Pobieramy mnożnik prędkości dla tego fragmentu toru
+            let multiplier = self.track.get_multiplier(s_track);
 
-            // KROK 4: Modyfikujemy czas okrążenia (odwrotność prędkości)
+            // KROK 3: Modyfikujemy czas okrążenia (odwrotność prędkości)
             // Dzielimy, ponieważ:
             // - Jeśli multiplier > 1 (prosta) -> mianownik duży -> czas mały -> AUTO PRZYSPIESZA
             // - Jeśli multiplier < 1 (zakręt) -> mianownik mały -> czas duży -> AUTO ZWALNIA
             self.cur_laptimes[i] = self.cur_th_laptimes[i] / multiplier;
+
+            // składniki teoretycznego czasu skalowane tak jak czas okrążenia, "Other" to odchyłka
+            // tempa bazowego (kształt toru, rozrzut, tryb silnika, ustawienie) od czystego przejazdu
+            let th_breakdown = &self.cur_th_breakdowns[i];
+            let deltas = &mut self.time_loss_deltas_cur[i];
+            deltas.t_tire = th_breakdown.t_tire / multiplier;
+            deltas.t_fuel = th_breakdown.t_fuel / multiplier;
+            deltas.t_weather = th_breakdown.t_weather / multiplier;
+            deltas.t_damage = th_breakdown.t_damage / multiplier;
+            deltas.t_start = th_breakdown.t_launch / multiplier;
+            deltas.t_other = (th_breakdown.t_base
+                + th_breakdown.t_car
+                + th_breakdown.t_driver
+                + th_breakdown.t_noise)
+                / multiplier
+                - self.t_clean_laptimes[i];
+
+            // Prędkość maksymalna kierowcy/auta ogranicza prędkość na prostych
+            let laptime_uncapped = self.cur_laptimes[i];
+            self.cur_laptimes[i] = calc_velocity_capped_laptime(
+                self.cur_laptimes[i],
+                self.track.length,
+                car.driver.vel_max,
+            );
+            self.time_loss_deltas_cur[i].t_other += self.cur_laptimes[i] - laptime_uncapped;
+
+            // Przebita opona: auto dojeżdża do alei w tempie alei serwisowej (również za SC/VSC)
+            if car.is_punctured() && !car.sh.pit_act {
+                let laptime_puncture = self.get_puncture_laptime();
+                if self.cur_laptimes[i] < laptime_puncture {
+                    self.time_loss_deltas_cur[i].t_damage +=
+                        laptime_puncture - self.cur_laptimes[i];
+                    self.cur_laptimes[i] = laptime_puncture;
+                }
+                self.lap_flags_cur[i] |= LAP_FLAG_DAMAGE;
+            }
+
+            if vsc_active && !car.sh.pit_act && !car.is_punctured() {
+                let mut laptime_vsc = self.get_min_laptime_flag_state(i) / multiplier;
+
+                // za szybko przejechany minisektor: auto oddaje różnicę (najwyżej pół kroku na krok)
+                if self.t_vsc_debts[i] > 0.0 {
+                    let t_giveback = self.t_vsc_debts[i].min(0.5 * self.timestep_size);
+                    laptime_vsc /= 1.0 - t_giveback / self.timestep_size;
+                    self.t_vsc_debts[i] -= t_giveback;
+                    self.t_vsc_givebacks[i] += t_giveback;
+                }
+
+                self.time_loss_deltas_cur[i].t_neutralization += laptime_vsc - self.cur_laptimes[i];
+                self.cur_laptimes[i] = laptime_vsc;
+                continue;
+            }
             
             // NOWY KOD
 
 
             // Obsługa Flag (jeśli nie SC)
             if !sc_active && !car.sh.pit_act {
-                if self.cur_laptimes[i] < self.get_min_laptime_flag_state() {
-                    self.cur_laptimes[i] = self.get_min_laptime_flag_state();
+                let min_laptime = self.get_min_laptime_flag_state(i);
+                if self.cur_laptimes[i] < min_laptime {
+                    self.time_loss_deltas_cur[i].t_neutralization +=
+                        min_laptime - self.cur_laptimes[i];
+                    self.cur_laptimes[i] = min_laptime;
                 }
-                // Dodatki wyścigowe (DRS, Duel) tylko gdy nie ma SC
-                // DRS wyłączony podczas deszczu
-                if car.sh.drs_act && self.weather_state == WeatherState::Dry {
-                    self.cur_laptimes[i] += self.track.t_drseffect / self.track.overtaking_zones_lap_frac;
-                }
-                if car.sh.duel_act {
-                    self.cur_laptimes[i] += self.t_duel / self.track.overtaking_zones_lap_frac;
+                // Dodatek DRS tylko gdy nie ma SC (pojedynki liczone niżej dla par aut)
+                // DRS wyłączony podczas deszczu, w sektorze z żółtą flagą i z przebitą oponą
+                if car.sh.drs_act
+                    && !car.is_punctured()
+                    && self.weather_state == WeatherState::Dry
+                    && !matches!(self.get_car_flag_state(i), FlagState::Y)
+                {
+                    let t_drs = self.track.t_drseffect / self.track.overtaking_zones_lap_frac;
+                    self.cur_laptimes[i] += t_drs;
+                    self.time_loss_deltas_cur[i].t_duels += t_drs;
                 }
             }
 
-            // Kary za zakręty
+            // Kary za zakręty (mniejsze przy dużym docisku)
             if car.sh.corner_act {
-                self.cur_laptimes[i] += 0.5;
+                let t_corner = car.get_corner_t_loss();
+                self.cur_laptimes[i] += t_corner;
+                self.time_loss_deltas_cur[i].t_other += t_corner;
+            }
+
+            // Przegrzanie po jeździe blisko za innym autem: auto musi odpuścić, aż ostygnie
+            if !sc_active && !car.sh.pit_act {
+                let t_overheat = car.get_overheat_t_loss();
+                self.cur_laptimes[i] += t_overheat;
+                self.time_loss_deltas_cur[i].t_traffic += t_overheat;
+            }
+
+            // Lokalna żółta flaga (gruz): niewielkie spowolnienie na odcinku
+            if !sc_active && !car.sh.pit_act && self.is_in_debris_zone(s_track) {
+                self.time_loss_deltas_cur[i].t_neutralization +=
+                    self.cur_laptimes[i] * (self.debris_slowdown_factor - 1.0);
+                self.cur_laptimes[i] *= self.debris_slowdown_factor;
+                self.lap_flags_cur[i] |= LAP_FLAG_YELLOW;
+            }
+
+            // Lokalna żółta flaga (auto stojące na torze): spowolnienie na odcinku
+            if !sc_active && !car.sh.pit_act && self.is_in_yellow_zone(s_track) {
+                self.time_loss_deltas_cur[i].t_neutralization +=
+                    self.cur_laptimes[i] * (self.yellow_slowdown_factor - 1.0);
+                self.cur_laptimes[i] *= self.yellow_slowdown_factor;
+                self.lap_flags_cur[i] |= LAP_FLAG_YELLOW;
             }
 
-            // Obsługa Pit Stopów
+            // Obsługa Pit Stopów (przejazd alei z ograniczeniem prędkości niezależnie od flagi - auto
+            // w alei nie jest dodatkowo spowalniane do tempa SC/VSC)
             if car.sh.pit_act {
+                let laptime_prev = self.cur_laptimes[i];
+
                 if !car.sh.pit_standstill_act {
                     self.cur_laptimes[i] = self.track.length / self.track.pit_speedlimit * self.track.real_length_pit_zone / self.track.track_length_pit_zone;
                 } else {
@@ -573,12 +2887,15 @@ impl Race {
                         self.cur_laptimes[i] = f64::INFINITY;
                     }
                 }
+
+                self.time_loss_deltas_cur[i].t_pit += self.cur_laptimes[i] - laptime_prev;
             }
         }
 
-        if !sc_active {
+        if !sc_active && !vsc_active && !restart_active {
             // 1. Ustal kolejność bolidów na torze
-            let idxs_sorted = self.get_car_order_on_track(); // [Lider, P2, P3, ...]
+            // (tylko auta biorące udział w interakcjach - bez DNF i postoju w boksie)
+            let idxs_sorted = self.get_interacting_car_order_on_track(); // [Lider, P2, P3, ...]
             
             // 2. Iterujemy przez pary (samochód z przodu vs samochód z tyłu)
             // Używamy indeksów, żeby mieć dostęp do &mut self.laptimes i car.dirty_air_wear_factor
@@ -586,7 +2903,7 @@ impl Race {
                 let idx_front = idxs_sorted[i];
                 // Samochód za nim (obsługa pętli - ostatni ściga pierwszego przy dublowaniu, 
                 // ale dla uproszczenia pomińmy dublowanie lidera przez marudera w logice blokowania)
-                if i == idxs_sorted.len() - 1 { continue; } 
+                if i + 1 >= idxs_sorted.len() { continue; }
                 let idx_rear = idxs_sorted[i + 1];
 
                 // Pomijamy auta w boksach
@@ -598,19 +2915,45 @@ impl Race {
                 // Funkcja pomocnicza, którą już masz w kodzie (ewentualnie upewnij się, że zwraca poprawny gap)
                 let gap_time = self.calc_projected_delta_t(idx_front, idx_rear, 0.0);
 
+                // Strata w pojedynku: zależna od głębokości wejścia w min_t_dist, strefy i agresji
+                if self.model_toggles.is_enabled(ModelToggles::DUELS) {
+                    let (t_duel_front, t_duel_rear) = calc_duel_timelosses(
+                        gap_time,
+                        self.get_duel_zone(idx_front, idx_rear),
+                        self.cars_list[idx_front].driver.aggression,
+                        self.cars_list[idx_rear].driver.aggression,
+                        self.t_duel / self.track.overtaking_zones_lap_frac,
+                        &self.duel_pars,
+                    );
+                    self.cur_laptimes[idx_front] += t_duel_front;
+                    self.cur_laptimes[idx_rear] += t_duel_rear;
+                    self.time_loss_deltas_cur[idx_front].t_duels += t_duel_front;
+                    self.time_loss_deltas_cur[idx_rear].t_duels += t_duel_rear;
+                }
+
+                // czas w ruchu: blisko wolniejszego auta z przodu (do flag kontekstu okrążenia)
+                if gap_time < self.min_t_dist
+                    && self.cur_th_laptimes[idx_front] > self.cur_th_laptimes[idx_rear]
+                {
+                    self.t_traffic_cur_lap[idx_rear] += self.timestep_size;
+                }
+
                 // PARAMETRY INTERAKCJI
                 let dirty_air_threshold = 2.0; // Poniżej 2s zaczyna się brudne powietrze
                 let blocking_threshold = 0.5;  // Poniżej 0.5s można próbować wyprzedzać (lub utknąć)
                 let overtake_speed_delta = 0.15; // Wymagana różnica prędkości (w sekundach na kółko), żeby wyprzedzić
 
                 // A. EFEKT BRUDNEGO POWIETRZA (Dirty Air)
-                if gap_time < dirty_air_threshold {
+                if gap_time < dirty_air_threshold
+                    && self.model_toggles.is_enabled(ModelToggles::DIRTY_AIR)
+                {
                     // Im bliżej, tym gorzej. Skalujemy efekt od 0.0 do 1.0
                     let intensity = 1.0 - (gap_time / dirty_air_threshold);
                     
                     // 1. Kara aerodynamiczna (trudniej skręcać)
                     let aero_penalty = 0.3 * intensity; 
                     self.cur_laptimes[idx_rear] += aero_penalty;
+                    self.time_loss_deltas_cur[idx_rear].t_traffic += aero_penalty;
 
                     // 2. Kara termiczna dla opon (przegrzewanie)
                     // Mnożnik od 1.0 do 2.0 (przy zderzaku)
@@ -628,19 +2971,39 @@ impl Race {
                         
                         // Pobieramy pozycję auta z tyłu
                         let s_track_rear = self.cars_list[idx_rear].sh.get_s_tracks().1;
-                        let in_overtaking_zone = self.track.is_in_overtaking_zone(s_track_rear);
+                        // lokalna żółta flaga (gruz, stojące auto) - zakaz wyprzedzania
+                        let in_overtaking_zone = self.track.is_in_overtaking_zone(s_track_rear)
+                            && !self.is_under_local_yellow(s_track_rear);
                         
                         // Warunek wyprzedzania:
                         // 1. Jest w strefie wyprzedzania (prosta/DRS)
                         // 2. Jest znacząco szybszy (delta > threshold) LUB używa DRS (można dodać warunek)
                         let speed_advantage = time_front - time_rear_potential;
                         
+                        // oba auta z DRS (pociąg DRS) - przewaga DRS się znosi
+                        let overtake_speed_delta = if self.cars_list[idx_front].sh.drs_act
+                            && self.cars_list[idx_rear].sh.drs_act
+                        {
+                            overtake_speed_delta * self.drs_train_overtake_factor
+                        } else {
+                            overtake_speed_delta
+                        };
+                        // docisk obu aut (prędkość na prostej)
+                        let overtake_speed_delta = overtake_speed_delta
+                            * calc_setup_overtake_factor(
+                                self.cars_list[idx_front].get_downforce_level(),
+                                self.cars_list[idx_rear].get_downforce_level(),
+                                self.cars_list[idx_rear].sh.drs_act,
+                                &self.setup_pars,
+                            );
                         let can_overtake = in_overtaking_zone && (speed_advantage > overtake_speed_delta);
 
                         if !can_overtake {
                             // BLOKADA! (Pociąg Trullego)
                             // Auto z tyłu musi zwolnić do tempa auta z przodu (plus minimalny dystans)
                             // Ustawiamy czas okrążenia na czas lidera (nie może pojechać szybciej)
+                            self.time_loss_deltas_cur[idx_rear].t_traffic +=
+                                time_front - self.cur_laptimes[idx_rear];
                             self.cur_laptimes[idx_rear] = time_front; 
                             
                             // Opcjonalnie: Dodatkowa frustracja/zużycie opon za jazdę "na zderzaku"
@@ -657,71 +3020,102 @@ impl Race {
         }
 
         // --- CZĘŚĆ 2: LOGIKA SAFETY CAR (KOLEJKOWANIE) ---
-        if sc_active {
-            // 1. Sortujemy auta według pozycji na torze (kto jest pierwszy)
-            let mut car_indices: Vec<usize> = (0..self.cars_list.len()).collect();
-            car_indices.sort_by(|&a, &b| {
-                // Sortowanie malejące po postępie wyścigu
-                self.cars_list[b].sh.get_race_prog().partial_cmp(&self.cars_list[a].sh.get_race_prog()).unwrap()
-            });
-
-            // 2. Ustalamy punkt odniesienia dla lidera (jest nim Safety Car)
-            let mut front_obj_pos = sc_total_dist;
-            // Prędkość obiektu z przodu (bazowa prędkość pociągu)
-            let _front_obj_speed = sc_speed;
+        if sc_active || restart_active {
+            // 1. Kolejność aut na torze wg postępu wyścigu (bez DNF i aut w alei). Auta z przebitą
+            // oponą nie dołączają do kolejki (dojeżdżają do alei we własnym tempie).
+            let mut car_indices = self.get_car_order_on_track();
+            car_indices.retain(|&i| !self.cars_list[i].is_punctured());
 
             // Parametry kolejkowania
             let target_gap = self.sc_target_gap_m; // Metrów odstępu między autami
             let catchup_factor = 0.5; // Jak agresywnie nadrabiać dystans
 
-            for &i in &car_indices {
-                // Pomijamy auta w boksach i DNF
-                if self.cars_list[i].status == CarStatus::DNF || self.cars_list[i].sh.pit_act {
-                    continue;
+            // delta SC: auta daleko za kolejką nie mogą jechać szybciej niż ten czas okrążenia
+            let laptime_sc_delta = self.calc_sc_delta_laptime();
+
+            // 2. Ustalamy punkt odniesienia dla lidera (jest nim Safety Car). Podczas restartu
+            // lider sam dyktuje tempo (wolniej od delty SC o `restart_pace_margin`), a kolejka
+            // jedzie za nim.
+            let (mut front_obj_pos, mut queue_speed) = if sc_active {
+                (sc_total_dist, sc_speed)
+            } else if !car_indices.is_empty() {
+                let idx_leader = car_indices.remove(0);
+                let laptime_restart = laptime_sc_delta * (1.0 + self.restart_pace_margin);
+
+                if self.cur_laptimes[idx_leader] < laptime_restart {
+                    self.time_loss_deltas_cur[idx_leader].t_neutralization +=
+                        laptime_restart - self.cur_laptimes[idx_leader];
+                    self.cur_laptimes[idx_leader] = laptime_restart;
                 }
 
+                (
+                    self.cars_list[idx_leader].sh.get_race_prog() * self.track.length,
+                    self.track.length / self.cur_laptimes[idx_leader],
+                )
+            } else {
+                (0.0, 0.0)
+            };
+
+            // lider dogonił SC - od tej chwili kolejka jedzie w tempie SC
+            if sc_active && !self.safety_car.leader_caught {
+                if let Some(&idx_leader) = car_indices.first() {
+                    let leader_pos =
+                        self.cars_list[idx_leader].sh.get_race_prog() * self.track.length;
+                    if sc_total_dist - leader_pos <= target_gap + self.sc_lineup_tolerance_m {
+                        self.safety_car.leader_caught = true;
+                    }
+                }
+            }
+
+            for &i in &car_indices {
                 // Oblicz dystans tego auta
                 let car_pos = self.cars_list[i].sh.get_race_prog() * self.track.length;
-                
-                // Dystans do obiektu przed nami (SC lub inne auto)
-                let gap = front_obj_pos - car_pos;
 
-                // Obliczamy docelową prędkość, żeby utrzymać 5m odstępu
-                // Wzór: v_target = v_sc + (różnica_dystansu * współczynnik)
-                // Jeśli gap > 5m -> jedź szybciej niż SC
-                // Jeśli gap < 5m -> jedź wolniej niż SC
-                let speed_correction = (gap - target_gap) * catchup_factor;
-                let mut target_speed = sc_speed + speed_correction;
-
-                // ZABEZPIECZENIA:
-                // 1. Nie możemy jechać szybciej niż pozwala na to bolid (max speed)
-                let max_phys_speed = self.track.length / self.cur_th_laptimes[i];
-                if target_speed > max_phys_speed {
-                    target_speed = max_phys_speed;
-                }
+                // Dystans do obiektu przed nami (SC lub inne auto w kolejce)
+                let gap = front_obj_pos - car_pos;
 
-                // 2. Nie możemy jechać do tyłu ani stać w miejscu (chyba że korek totalny)
-                if target_speed < 10.0 {
-                    target_speed = 10.0; 
-                }
+                // Docelowy czas okrążenia auta wynika z jego odstępu do poprzednika w kolejce:
+                // auta w kolejce trzymają odstęp `target_gap` w tempie SC, auta dalej z tyłu
+                // nadrabiają stopniowo, najwyżej w tempie delty SC
+                let laptime_target = calc_sc_target_laptime(
+                    gap,
+                    target_gap,
+                    catchup_factor,
+                    queue_speed,
+                    self.track.length,
+                    laptime_sc_delta.max(self.cur_th_laptimes[i]),
+                );
 
                 // Aplikujemy prędkość (zamiana na czas okrążenia)
-                self.cur_laptimes[i] = self.track.length / target_speed;
+                self.time_loss_deltas_cur[i].t_neutralization +=
+                    laptime_target - self.cur_laptimes[i];
+                self.cur_laptimes[i] = laptime_target;
 
                 // Aktualizujemy pozycję "obiektu z przodu" dla NASTĘPNEGO auta w kolejce.
-                // Następne auto ma trzymać 5m odstępu od TEGO auta.
+                // Następne auto ma trzymać odstęp od TEGO auta.
                 front_obj_pos = car_pos;
+
+                // zanim lider dogoni SC, auta jadą w tempie delty SC za swoim poprzednikiem (a nie
+                // w tempie SC, który jest jeszcze daleko z przodu)
+                if sc_active && !self.safety_car.leader_caught {
+                    queue_speed = self.track.length / laptime_target;
+                }
+            }
+
+            // podczas restartu nie ma SC, którego zjazd zależałby od ustawienia kolejki, na mecie
+            // za SC SC zjeżdża dopiero po ostatnim aucie, a w okrążeniu zjazdu SC jedzie już do
+            // wjazdu do alei
+            if !sc_active || self.finish_under_sc || self.safety_car.in_this_lap {
+                return;
             }
 
             // --- Sprawdzenie ustawienia kolejki za SC ---
             // Warunek lineup: wszystkie aktywne auta (nie DNF, nie pit) trzymają odstęp ~ target_gap z tolerancją
             let tol = self.sc_lineup_tolerance_m;
-            let mut positions: Vec<(usize, f64)> = Vec::new();
-            for &i in &car_indices {
-                if self.cars_list[i].status == CarStatus::DNF || self.cars_list[i].sh.pit_act { continue; }
-                let car_pos = self.cars_list[i].sh.get_race_prog() * self.track.length;
-                positions.push((i, car_pos));
-            }
+            let positions: Vec<(usize, f64)> = car_indices
+                .iter()
+                .map(|&i| (i, self.cars_list[i].sh.get_race_prog() * self.track.length))
+                .collect();
             let mut lineup_ok = !positions.is_empty();
             if lineup_ok {
                 // Sprawdź lidera względem SC
@@ -741,8 +3135,9 @@ impl Race {
                 }
             }
 
-            // Jeśli mamy lineup i nie odliczamy jeszcze, uruchom krótki licznik do zjazdu SC
-            if lineup_ok {
+            // Jeśli mamy lineup (i skutki incydentu są usunięte) i nie odliczamy jeszcze, uruchom
+            // krótki licznik do zjazdu SC
+            if lineup_ok && self.is_sc_release_allowed() {
                 if !self.sc_timer.is_finite() {
                     self.sc_timer = self.sc_release_delay_s;
                 }
@@ -753,11 +3148,11 @@ impl Race {
                 }
             }
         } 
-        // --- CZĘŚĆ 3: INTERAKCJE (TYLKO BEZ SC) ---
-        else {
+        // --- CZĘŚĆ 3: INTERAKCJE (TYLKO BEZ SC I VSC) ---
+        else if !vsc_active {
             let idxs_sorted = self.get_idx_list_sorted_by_biggest_gap();
             let car_pair_idxs_list = self.get_car_pair_idxs_list(&idxs_sorted, true);
-            let mut laptimes_updates: Vec<(usize, f64)> = Vec::new();
+            let mut laptimes_updates: Vec<(usize, f64, TimeLossCause)> = Vec::new();
 
             for pair_idxs in car_pair_idxs_list.iter() {
                 let idx_front = pair_idxs[0];
@@ -770,12 +3165,17 @@ impl Race {
                     && !self.cars_list[idx_front].sh.pit_act
                     && !self.cars_list[idx_rear].sh.pit_act
                 {
-                    let mut rng = rand::thread_rng();
+                    let mut rng = self.fork_rng();
 
                     // 1) Presja i błędy kierowcy z przodu (lock-up lub wyjazd szeroko)
                     let pressure_intensity = (1.0 - gap_time_close).clamp(0.0, 1.0);
                     let defender_consistency = self.cars_list[idx_front].driver.consistency;
-                    let mistake_prob = (1.0 - defender_consistency) * pressure_intensity * 0.05;
+                    let mistake_prob = if self.model_toggles.is_enabled(ModelToggles::MISTAKES) {
+                        (1.0 - defender_consistency) * pressure_intensity * 0.05
+                            * self.get_car_incident_factor(idx_front)
+                    } else {
+                        0.0
+                    };
 
                     if rng.gen::<f64>() < mistake_prob {
                         if rng.gen::<bool>() {
@@ -784,16 +3184,25 @@ impl Race {
                                 "MISTAKE: Car {} locked up under pressure!",
                                 self.cars_list[idx_front].car_no
                             ); }
-                            self.cur_laptimes[idx_front] += 1.2;
+                            self.cur_laptimes[idx_front] += self.mistake_lockup_t_loss_s;
+                            self.time_loss_deltas_cur[idx_front].t_mistakes +=
+                                self.mistake_lockup_t_loss_s;
                             self.cars_list[idx_front].dirty_air_wear_factor += 2.0;
+                            self.lap_flags_cur[idx_front] |= LAP_FLAG_MISTAKE;
                         } else {
                             // Wyjazd szeroko: strata u broniącego, mały zysk atakującego
                             if self.print_events { println!(
                                 "MISTAKE: Car {} went wide!",
                                 self.cars_list[idx_front].car_no
                             ); }
-                            self.cur_laptimes[idx_front] += 0.8;
-                            self.cur_laptimes[idx_rear] -= 0.3;
+                            self.cur_laptimes[idx_front] += self.mistake_wide_t_loss_s;
+                            self.time_loss_deltas_cur[idx_front].t_mistakes +=
+                                self.mistake_wide_t_loss_s;
+                            self.cur_laptimes[idx_rear] -= self.mistake_wide_t_gain_s;
+                            self.time_loss_deltas_cur[idx_rear].t_duels -=
+                                self.mistake_wide_t_gain_s;
+                            self.lap_flags_cur[idx_front] |= LAP_FLAG_MISTAKE;
+                            self.record_track_limits(idx_front, false);
                         }
                     }
 
@@ -801,7 +3210,8 @@ impl Race {
                     if gap_time_close < 0.3 {
                         let agg_factor = self.cars_list[idx_front].driver.aggression
                             + self.cars_list[idx_rear].driver.aggression;
-                        let contact_prob = 0.005 * agg_factor; // niewielka szansa
+                        let contact_prob = 0.005 * agg_factor // niewielka szansa
+                            * self.get_pair_incident_factor(idx_front, idx_rear);
 
                         if rng.gen::<f64>() < contact_prob {
                             if self.print_events { println!(
@@ -811,7 +3221,26 @@ impl Race {
                             ); }
                             // Częściej obrywa atakujący (z tyłu)
                             let victim_idx = if rng.gen::<f64>() > 0.3 { idx_rear } else { idx_front };
-                            self.cars_list[victim_idx].accumulated_damage_penalty += 0.3;
+                            self.add_damage(victim_idx, 0.3);
+
+                            let cars = vec![
+                                self.cars_list[idx_front].car_no,
+                                self.cars_list[idx_rear].car_no,
+                            ];
+                            // event: drobny kontakt
+                            self.push_event(RaceEvent::new(
+                                "Contact",
+                                self.cur_lap_leader,
+                                self.cur_racetime,
+                                cars.to_owned(),
+                            ));
+
+                            // gruz po kontakcie - lokalna żółta flaga na 1-2 okrążenia (bez SC)
+                            if rng.gen::<f64>() < self.debris_probability {
+                                let s_track = self.cars_list[idx_rear].sh.get_s_tracks().1;
+                                let no_laps = rng.gen_range(1..=2);
+                                self.add_debris_zone(s_track, cars, no_laps);
+                            }
                         }
                     }
                 }
@@ -838,73 +3267,704 @@ impl Race {
                         let ag_sum = (ag_a + ag_b).clamp(0.0, 2.0);
                         let ag_mult = 1.0 + 0.8 * (ag_sum - 1.0); // 0.2..1.8x
 
-                        let lambda = base_lambda_per_s * corner_mult * ag_mult * self.collision_factor;
+                        let lambda = base_lambda_per_s * corner_mult * ag_mult * self.collision_factor
+                            * self.get_pair_incident_factor(idx_front, idx_rear);
                         let p_step = 1.0 - (-lambda * dt).exp();
 
-                        let mut rng = rand::thread_rng();
+                        let mut rng = self.fork_rng();
                         if rng.gen::<f64>() < p_step {
-                            self.cars_list[idx_front].status = CarStatus::DNF;
-                            self.cars_list[idx_rear].status = CarStatus::DNF;
-                            // Reflect immediate removal from pace this step
-                            self.cur_laptimes[idx_front] = f64::INFINITY;
-                            self.cur_laptimes[idx_rear] = f64::INFINITY;
                             if self.print_events { println!(
                                 "CRASH: Car {} and Car {} collided in Turn!",
                                 self.cars_list[idx_front].car_no,
                                 self.cars_list[idx_rear].car_no
                             ); }
                             // event: crash
-                            self.events.push(RaceEvent {
-                                kind: "Crash".to_string(),
-                                lap: self.cur_lap_leader,
-                                time_s: self.cur_racetime,
-                                cars: vec![self.cars_list[idx_front].car_no, self.cars_list[idx_rear].car_no],
-                            });
+                            self.retire_cars(&[idx_front, idx_rear], RetirementCause::Crash);
+                            // Reflect immediate removal from pace this step
+                            self.cur_laptimes[idx_front] = f64::INFINITY;
+                            self.cur_laptimes[idx_rear] = f64::INFINITY;
                             // Skip further interaction handling for this pair
                             continue;
                         }
                     }
 
+                    // Obrona pozycji: auto z przodu broni się przed szybszym autem z tyłu
+                    if self.cars_list[idx_front].sh.duel_act
+                        && self.cur_th_laptimes[idx_rear] < self.cur_th_laptimes[idx_front]
+                    {
+                        self.cars_list[idx_front].t_defending_cur_lap += self.timestep_size;
+                    }
+
                     let overtake_threshold = 0.2;
-                    let potential_pace_diff = self.cur_th_laptimes[idx_front] - self.cur_th_laptimes[idx_rear];
+                    // przegrzane auto nie jest w stanie zaatakować (ani skutecznie się bronić)
+                    let mut potential_pace_diff = self.cur_th_laptimes[idx_front]
+                        + self.cars_list[idx_front].get_overheat_t_loss()
+                        - self.cur_th_laptimes[idx_rear]
+                        - self.cars_list[idx_rear].get_overheat_t_loss();
                     // Aggression influence: more aggressive rear lowers required pace delta,
                     // aggressive front slightly raises it (defending).
                     let ag_front = self.cars_list[idx_front].driver.aggression;
                     let ag_rear = self.cars_list[idx_rear].driver.aggression;
-                    let mut eff_overtake_threshold = overtake_threshold * (1.0 - 0.7 * ag_rear + 0.3 * ag_front);
+                    let mut eff_overtake_threshold = overtake_threshold
+                        * (1.0 - 0.7 * ag_rear + 0.3 * ag_front)
+                        * calc_setup_overtake_factor(
+                            self.cars_list[idx_front].get_downforce_level(),
+                            self.cars_list[idx_rear].get_downforce_level(),
+                            self.cars_list[idx_rear].sh.drs_act,
+                            &self.setup_pars,
+                        );
                     if eff_overtake_threshold < 0.05 { eff_overtake_threshold = 0.05; }
+                    // oba auta z DRS (pociąg DRS) - wyprzedzanie znacznie trudniejsze
+                    if self.cars_list[idx_front].sh.drs_act && self.cars_list[idx_rear].sh.drs_act {
+                        eff_overtake_threshold *= self.drs_train_overtake_factor;
+                    }
                     let in_corner = self.cars_list[idx_front].sh.corner_act || self.cars_list[idx_rear].sh.corner_act;
+                    // lokalna żółta flaga (gruz, stojące auto) - zakaz wyprzedzania
+                    let s_track_front = self.cars_list[idx_front].sh.get_s_tracks().1;
+                    let s_track_rear = self.cars_list[idx_rear].sh.get_s_tracks().1;
+                    let in_yellow = self.is_under_local_yellow(s_track_front)
+                        || self.is_under_local_yellow(s_track_rear);
+
+                    // ERS: atakujący używa energii przy próbie wyprzedzenia, broniący odpowiada
+                    if !in_corner && !in_yellow && !self.cars_list[idx_rear].sh.pit_act {
+                        let (deploy_front, deploy_rear, pace_diff_ers) = calc_ers_duel(
+                            potential_pace_diff,
+                            eff_overtake_threshold,
+                            self.cars_list[idx_front].get_ers_energy(),
+                            self.cars_list[idx_rear].get_ers_energy(),
+                            &self.ers_pars,
+                        );
+                        potential_pace_diff = pace_diff_ers;
+
+                        let deploys = [(idx_front, deploy_front), (idx_rear, deploy_rear)];
+                        for &(idx, deploy) in deploys.iter() {
+                            if deploy && !self.ers_deploy_cur[idx] {
+                                self.ers_deploy_cur[idx] = true;
+                                self.cur_laptimes[idx] -= self.ers_pars.t_gain;
+                                self.time_loss_deltas_cur[idx].t_duels -= self.ers_pars.t_gain;
+                            }
+                        }
+                    }
 
-                    if potential_pace_diff > eff_overtake_threshold && !in_corner {
-                        laptimes_updates.push((idx_rear, 0.1));
-                        laptimes_updates.push((idx_front, self.t_overtake_loser));
+                    if potential_pace_diff > eff_overtake_threshold && !in_corner && !in_yellow {
+                        laptimes_updates.push((idx_rear, 0.1, TimeLossCause::Duels));
+                        laptimes_updates.push((
+                            idx_front,
+                            self.t_overtake_loser,
+                            TimeLossCause::Duels,
+                        ));
                     } else {
+                        // nieudana próba wyprzedzenia szybszego auta w zakręcie może skończyć się
+                        // kontaktem
+                        if in_corner
+                            && !in_yellow
+                            && potential_pace_diff > 0.0
+                            && !self.cars_list[idx_rear].sh.pit_act
+                            && self.overtake_contact_pars.probability > 0.0
+                        {
+                            let p_step = self.overtake_contact_pars.calc_probability(
+                                [
+                                    self.cars_list[idx_front].driver.aggression,
+                                    self.cars_list[idx_rear].driver.aggression,
+                                ],
+                                [
+                                    self.cars_list[idx_front].driver.consistency,
+                                    self.cars_list[idx_rear].driver.consistency,
+                                ],
+                                self.get_pair_incident_factor(idx_front, idx_rear),
+                                self.timestep_size,
+                            );
+
+                            let mut rng = self.fork_rng();
+                            if rng.gen::<f64>() < p_step {
+                                let outcome = self.overtake_contact_pars.draw_outcome(&mut rng);
+                                self.handle_overtake_contact(idx_front, idx_rear, outcome);
+                                continue;
+                            }
+                        }
+
                         let delta_t_cur = self.calc_projected_delta_t(idx_front, idx_rear, 0.0);
                         let t_gap_add = (self.min_t_dist - delta_t_cur) / 5.0 * self.cur_laptimes[idx_rear];
                         let target_time = self.cur_laptimes[idx_front] + t_gap_add;
                         if self.cur_laptimes[idx_rear] < target_time {
                             let diff = target_time - self.cur_laptimes[idx_rear];
-                            laptimes_updates.push((idx_rear, diff));
+                            laptimes_updates.push((idx_rear, diff, TimeLossCause::Traffic));
                         }
                     }
                 }
             }
-            for (idx, time_add) in laptimes_updates {
+            for (idx, time_add, cause) in laptimes_updates {
                 self.cur_laptimes[idx] += time_add;
+                self.time_loss_deltas_cur[idx].add(cause, time_add);
+            }
+
+            // wypadki z tego kroku są omijane już w tym samym kroku
+            self.register_crash_sites();
+            self.apply_crash_avoidance();
+        }
+
+        // ERS: bateria ładuje się, gdy auto nie używa energii (strata czasu tylko przy zielonej
+        // fladze, za SC/VSC tempo i tak jest ograniczone)
+        self.update_ers(!sc_active && !vsc_active && !restart_active);
+    }
+
+    /// update_ers zużywa energię ERS aut, które jej użyły w bieżącym kroku, i ładuje baterie
+    /// pozostałych jadących aut (ze stratą czasu okrążenia, jeśli `harvest_loss_act`).
+    fn update_ers(&mut self, harvest_loss_act: bool) {
+        for i in 0..self.cars_list.len() {
+            let car = &mut self.cars_list[i];
+            if car.status == CarStatus::DNF {
+                self.ers_deploy_cur[i] = false;
+                continue;
+            }
+
+            if self.ers_deploy_cur[i] {
+                car.deploy_ers(self.timestep_size);
+                self.ers_deploy_cur[i] = false;
+            } else if car.harvest_ers(self.timestep_size, self.cur_th_laptimes[i])
+                && harvest_loss_act
+                && !car.sh.pit_act
+            {
+                self.cur_laptimes[i] += self.ers_pars.t_harvest_loss;
+                self.time_loss_deltas_cur[i].t_other += self.ers_pars.t_harvest_loss;
+            }
+        }
+    }
+
+    /// update_crash_sites postarza świeże wypadki, usuwa te, dla których flaga jest już oficjalna,
+    /// i rejestruje nowe wypadki (np. awarie z poprzedniego kroku).
+    fn update_crash_sites(&mut self) {
+        let timestep_size = self.timestep_size;
+        let duration_s = self.crash_avoidance_pars.duration_s;
+
+        for crash_site in self.crash_sites.iter_mut() {
+            crash_site.t_elapsed += timestep_size;
+        }
+        self.crash_sites.retain(|x| x.t_elapsed < duration_s);
+
+        self.register_crash_sites();
+    }
+
+    /// register_crash_sites dodaje świeży wypadek i lokalną żółtą flagę dla każdego auta, które
+    /// właśnie odpadło na torze (nie w alei serwisowej i nie po mecie).
+    fn register_crash_sites(&mut self) {
+        for idx in 0..self.cars_list.len() {
+            if self.cars_list[idx].status != CarStatus::DNF || self.crash_site_registered[idx] {
+                continue;
+            }
+            self.crash_site_registered[idx] = true;
+
+            if self.race_finished[idx] || self.cars_list[idx].sh.pit_act {
+                continue;
+            }
+
+            self.crash_sites.push(CrashSite {
+                idx,
+                s_track: self.cars_list[idx].sh.get_s_tracks().1,
+                t_elapsed: 0.0,
+                checked: vec![false; self.cars_list.len()],
+            });
+            self.add_yellow_zone(idx);
+        }
+    }
+
+    /// apply_crash_avoidance spowalnia auta zbliżające się do świeżego wypadku (w odległości
+    /// `distance_m` za nim). Przy pierwszym zbliżeniu losowane jest najechanie na rozbite auto.
+    fn apply_crash_avoidance(&mut self) {
+        if self.crash_sites.is_empty() {
+            return;
+        }
+
+        let mut rng = self.fork_rng();
+        let incident_factor = self.get_incident_factor();
+
+        for site_idx in 0..self.crash_sites.len() {
+            for i in 0..self.cars_list.len() {
+                if !self.is_car_interacting(i) || self.cars_list[i].sh.pit_act {
+                    continue;
+                }
+
+                let s_track = self.cars_list[i].sh.get_s_tracks().1;
+                let dist =
+                    (self.crash_sites[site_idx].s_track - s_track).rem_euclid(self.track.length);
+                if dist <= 0.0 || dist > self.crash_avoidance_pars.distance_m {
+                    continue;
+                }
+
+                self.time_loss_deltas_cur[i].t_neutralization +=
+                    self.cur_laptimes[i] * (self.crash_avoidance_pars.slowdown_factor - 1.0);
+                self.cur_laptimes[i] *= self.crash_avoidance_pars.slowdown_factor;
+                self.lap_flags_cur[i] |= LAP_FLAG_YELLOW;
+
+                if self.crash_sites[site_idx].checked[i] {
+                    continue;
+                }
+                self.crash_sites[site_idx].checked[i] = true;
+
+                let p_collision = calc_secondary_collision_prob(
+                    dist,
+                    incident_factor,
+                    &self.crash_avoidance_pars,
+                );
+
+                if rng.gen::<f64>() < p_collision {
+                    let car_no_stricken = self.cars_list[self.crash_sites[site_idx].idx].car_no;
+                    if self.print_events {
+                        println!(
+                            "CRASH: Car {} collected the stricken car {}!",
+                            self.cars_list[i].car_no, car_no_stricken
+                        );
+                    }
+                    self.record_retirement(
+                        &[i],
+                        RetirementCause::Crash,
+                        vec![car_no_stricken, self.cars_list[i].car_no],
+                        self.cur_racetime,
+                    );
+                    self.cur_laptimes[i] = f64::INFINITY;
+                }
+            }
+        }
+    }
+
+    /// get_duel_zone zwraca rodzaj odcinka, na którym walczy para aut: zakręt, jeśli którekolwiek z
+    /// aut jest w zakręcie, prosta, jeśli auto atakujące jest w strefie wyprzedzania.
+    fn get_duel_zone(&self, idx_front: usize, idx_rear: usize) -> DuelZone {
+        if self.cars_list[idx_front].sh.corner_act || self.cars_list[idx_rear].sh.corner_act {
+            DuelZone::Corner
+        } else if self
+            .track
+            .is_in_overtaking_zone(self.cars_list[idx_rear].sh.get_s_tracks().1)
+        {
+            DuelZone::Straight
+        } else {
+            DuelZone::Other
+        }
+    }
+
+    /// Wymusza minimalny odstęp między autami na tym samym okrążeniu. Czas okrążenia przesuniętych
+    /// aut w tym kroku jest dostosowywany do faktycznie przejechanego dystansu, tak aby
+    /// interpolacja czasów okrążeń pozostała spójna.
+    fn enforce_min_spacing(&mut self) {
+        if self.min_spacing_m <= 0.0 {
+            return;
+        }
+
+        let sc_active = matches!(self.flag_state, FlagState::Sc | FlagState::Vsc);
+        let no_cars = self.cars_list.len();
+
+        let race_progs_prev: Vec<f64> = self.cars_list.iter().map(|car| car.sh.get_race_prog_prev()).collect();
+        let race_progs: Vec<f64> =
+            self.cars_list.iter().map(|car| car.sh.get_race_prog()).collect();
+        let active: Vec<bool> = (0..no_cars)
+            .map(|i| {
+                let car = &self.cars_list[i];
+                car.status != CarStatus::DNF
+                    && !car.sh.pit_act
+                    && !self.race_finished[i]
+                    && !self.is_stopped(i)
+            })
+            .collect();
+        // wyprzedzanie w strefach wyprzedzania jest dozwolone (poza SC i VSC)
+        let may_overlap: Vec<bool> = self
+            .cars_list
+            .iter()
+            .map(|car| !sc_active && self.track.is_in_overtaking_zone(car.sh.get_s_tracks().1))
+            .collect();
+
+        let race_progs_clamped = clamp_race_progs_min_spacing(
+            &race_progs_prev,
+            &race_progs,
+            &active,
+            &may_overlap,
+            self.min_spacing_m / self.track.length,
+        );
+
+        for i in 0..no_cars {
+            if race_progs_clamped[i] == race_progs[i] {
+                continue;
+            }
+
+            self.cars_list[i].sh.set_race_prog(race_progs_clamped[i]);
+
+            let dist_driven = (race_progs_clamped[i] - race_progs_prev[i]) * self.track.length;
+            let laptime_prev = self.cur_laptimes[i];
+            self.cur_laptimes[i] = if dist_driven > 0.0 {
+                self.timestep_size * self.track.length / dist_driven
+            } else {
+                f64::INFINITY
+            };
+            if laptime_prev.is_finite() {
+                self.time_loss_deltas_cur[i].t_traffic += self.cur_laptimes[i] - laptime_prev;
+            }
+        }
+    }
+
+    /// Sprawdza, czy incydent przerywa wyścig (czerwona flaga): incydent co najmniej
+    /// `red_flag_min_cars` aut albo, z prawdopodobieństwem `red_flag_probability`, wypadek
+    /// (zdarzenie `Crash`) dowolnej liczby aut.
+    fn is_red_flag_incident<R: Rng + ?Sized>(&self, incident_idxs: &[usize], rng: &mut R) -> bool {
+        if let Some(min_cars) = self.red_flag_min_cars {
+            if incident_idxs.len() as u32 >= min_cars {
+                return true;
+            }
+        }
+
+        let crash = incident_idxs.iter().any(|&i| {
+            let car_no = self.cars_list[i].car_no;
+            self.events
+                .iter()
+                .any(|event| event.kind == "Crash" && event.cars.contains(&car_no))
+        });
+
+        crash && self.red_flag_probability > 0.0 && rng.gen::<f64>() < self.red_flag_probability
+    }
+
+    /// suspend_race przerywa wyścig czerwoną flagą. Auta zatrzymują się w miejscu (kolejność jest
+    /// zamrożona) na `red_flag_duration_s`, czas wyścigu w tym czasie nie płynie.
+    fn suspend_race(&mut self, incident_idxs: &[usize]) {
+        let cars: Vec<u32> = incident_idxs.iter().map(|&i| self.cars_list[i].car_no).collect();
+        if self.print_events { println!("RED FLAG - RACE SUSPENDED (Caused by cars {:?})", cars); }
+
+        self.push_event(RaceEvent::new("RED_FLAG", self.cur_lap_leader, self.cur_racetime, cars));
+        self.close_sc_period();
+        self.set_flag_state(FlagState::Red);
+        self.red_flag_timer = self.red_flag_duration_s;
+        self.safety_car.active = false;
+        self.incident = None;
+        self.sc_bunching_lap = None;
+
+        // wycofania do tej chwili są obsłużone przez czerwoną flagę
+        self.incident_queue.clear();
+    }
+
+    /// restart_after_red_flag kończy zawieszenie wyścigu: jadące auta dostają darmową zmianę opon,
+    /// wyścig jest skracany o `red_flag_laps_deducted` okrążeń (nie poniżej bieżącego okrążenia
+    /// lidera) i wznawiany za samochodem bezpieczeństwa w kolejności z chwili przerwania.
+    fn restart_after_red_flag(&mut self) {
+        for (i, car) in self.cars_list.iter_mut().enumerate() {
+            // auta w alei kończą swój pit stop normalnie
+            if car.status != CarStatus::DNF && !self.race_finished[i] && !car.sh.pit_act {
+                let compl_lap = car.sh.get_compl_lap();
+                car.change_tires_red_flag(self.red_flag_compound.as_deref(), compl_lap);
+            }
+        }
+
+        if self.red_flag_laps_deducted > 0 {
+            self.tot_no_laps = self
+                .tot_no_laps
+                .saturating_sub(self.red_flag_laps_deducted)
+                .max(self.cur_lap_leader);
+
+            // dane okrążeń zapisywane razem z czasami okrążeń
+            let no_entries = self.tot_no_laps as usize + 1;
+            for i in 0..self.cars_list.len() {
+                self.laptimes[i].truncate(no_entries);
+                self.racetimes[i].truncate(no_entries);
+                self.laptime_breakdowns[i].truncate(no_entries);
+                self.lap_flags[i].truncate(no_entries);
+                self.engine_modes[i].truncate(no_entries);
+                self.compounds[i].truncate(no_entries);
+            }
+        }
+
+        if self.print_events {
+            println!("RACE RESTARTING BEHIND THE SAFETY CAR ({} laps)", self.tot_no_laps);
+        }
+        self.push_event(RaceEvent::new("RESTART", self.cur_lap_leader, self.cur_racetime, vec![]));
+
+        // SC ustawia się przed liderem w następnym kroku i zjeżdża po ustawieniu kolejki
+        self.set_flag_state(FlagState::Sc);
+        self.sc_timer = f64::INFINITY;
+        self.safety_car.active = false;
+        self.incident = None;
+        self.sc_bunching_lap = None;
+    }
+
+    /// start_behind_sc rozpoczyna wyścig za SC (np. w deszczu): SC stoi w odstępie kolejki przed
+    /// pierwszym autem i prowadzi stawkę przez `no_laps` okrążeń lidera. Auta ruszają razem, bez
+    /// startu z miejsca, i jadą w kolejce w kolejności startowej bez wyprzedzania.
+    fn start_behind_sc(&mut self, no_laps: u32) {
+        self.set_flag_state(FlagState::Sc);
+        self.sc_timer = f64::INFINITY;
+        self.incident = None;
+        self.sc_bunching_lap = None;
+        self.sc_scripted_lap_in = Some(no_laps);
+
+        // pola startowe za linią mety - SC czeka na linii
+        let race_prog_first = self
+            .cars_list
+            .iter()
+            .map(|car| car.sh.get_race_prog())
+            .fold(f64::NEG_INFINITY, f64::max);
+        let sc_dist = (race_prog_first * self.track.length + self.sc_target_gap_m).max(0.0);
+
+        self.safety_car.active = true;
+        self.safety_car.in_this_lap = false;
+        self.safety_car.leader_caught = true;
+        self.safety_car.lap = (sc_dist / self.track.length).floor() as u32 + 1;
+        self.safety_car.s_track = sc_dist - (self.safety_car.lap - 1) as f64 * self.track.length;
+        self.open_sc_period(ScPeriodKind::Sc, None);
+
+        if self.print_events {
+            println!("RACE STARTS BEHIND THE SAFETY CAR FOR {} LAPS", no_laps);
+        }
+        self.push_event(RaceEvent::new("START_BEHIND_SC", 1, 0.0, vec![]));
+    }
+
+    /// end_formation_lap kończy okrążenie formujące: auta stają na swoich polach startowych, opony
+    /// i paliwo są zużyte o `formation_wear_factor` okrążenia, a teoretyczny czas pierwszego
+    /// okrążenia (ze stratą na starcie z miejsca) jest liczony ponownie z nowym wiekiem opon.
+    fn end_formation_lap(&mut self) {
+        self.formation_lap_act = false;
+        self.formation_lap_prog = 1.0;
+
+        for idx in 0..self.cars_list.len() {
+            self.cars_list[idx].drive_formation_lap(self.formation_wear_factor);
+            self.calc_th_laptime(idx);
+        }
+
+        if self.print_events {
+            println!("FORMATION LAP COMPLETED - RACE START");
+        }
+    }
+
+    /// get_model_toggles zwraca komponenty modelu wyłączone w wyścigu.
+    pub fn get_model_toggles(&self) -> &ModelToggles {
+        &self.model_toggles
+    }
+
+    /// is_formation_lap zwraca true podczas okrążenia formującego.
+    pub fn is_formation_lap(&self) -> bool {
+        self.formation_lap_act
+    }
+
+    /// get_formation_race_prog zwraca postęp auta na okrążeniu formującym (okrążenie przed startem,
+    /// od pola startowego z powrotem do niego), None poza okrążeniem formującym.
+    pub fn get_formation_race_prog(&self, idx: usize) -> Option<f64> {
+        if self.formation_lap_act {
+            Some(self.cars_list[idx].sh.get_race_prog() - 1.0 + self.formation_lap_prog)
+        } else {
+            None
+        }
+    }
+
+    /// get_t_formation_lap zwraca czas (s) okrążenia formującego.
+    pub fn get_t_formation_lap(&self) -> f64 {
+        self.t_formation_lap
+    }
+
+    /// is_rolling_restart zwraca true, jeśli po zjeździe SC lider wciąż dyktuje tempo (przed
+    /// linią restartu).
+    pub fn is_rolling_restart(&self) -> bool {
+        self.rolling_restart_act && matches!(self.flag_state, FlagState::G)
+    }
+
+    /// update_rolling_restart kończy restart po SC, gdy lider minie linię restartu. Nowa
+    /// neutralizacja (SC, VSC, czerwona flaga) przerywa restart.
+    fn update_rolling_restart(&mut self) {
+        if !self.rolling_restart_act {
+            return;
+        }
+
+        if !matches!(self.flag_state, FlagState::G) {
+            self.rolling_restart_act = false;
+            return;
+        }
+
+        let idx_leader = match self.get_car_order_on_track().first() {
+            Some(&idx) => idx,
+            None => {
+                self.rolling_restart_act = false;
+                return;
+            }
+        };
+
+        if self.cars_list[idx_leader]
+            .sh
+            .get_s_track_passed_this_step(self.restart_s_track)
+        {
+            self.rolling_restart_act = false;
+
+            if self.print_events {
+                println!(
+                    "RACE RESTARTED: Lap {}, leader #{} passed the restart line",
+                    self.cur_lap_leader, self.cars_list[idx_leader].car_no
+                );
+            }
+        }
+    }
+
+    /// Sprawdza czy SC może zjechać: skutki incydentu muszą być usunięte, a SC musi dodatkowo
+    /// przejechać jedno okrążenie zbierające stawkę.
+    fn is_sc_release_allowed(&self) -> bool {
+        // wymuszony SC zjeżdża dopiero w swoim ostatnim okrążeniu
+        if matches!(self.sc_scripted_lap_in, Some(lap_in) if self.cur_lap_leader < lap_in) {
+            return false;
+        }
+
+        match (&self.incident, self.sc_bunching_lap) {
+            (None, _) => true,
+            (Some(_), Some(bunching_lap)) => self.safety_car.lap >= bunching_lap,
+            (Some(_), None) => false,
+        }
+    }
+
+    /// get_laptime_breakdowns zwraca składniki teoretycznego czasu każdego okrążenia auta (indeks
+    /// 0 nieużywany, jak w `laptimes`).
+    pub fn get_laptime_breakdowns(&self, idx: usize) -> &[LaptimeBreakdown] {
+        &self.laptime_breakdowns[idx]
+    }
+
+    /// get_compounds zwraca mieszankę opon, na której auto rozpoczęło każde okrążenie (indeks 0
+    /// nieużywany, jak w `laptimes`).
+    pub fn get_compounds(&self, idx: usize) -> &[String] {
+        &self.compounds[idx]
+    }
+
+    /// Zwraca lokalną porę dnia (h) w bieżącej chwili wyścigu (None bez godziny startu).
+    pub fn get_time_of_day(&self) -> Option<f64> {
+        self.session_start_h
+            .map(|session_start_h| get_time_of_day(session_start_h, self.cur_racetime))
+    }
+
+    /// Zwraca bieżącą temperaturę toru (°C), jeśli pora dnia jest modelowana.
+    pub fn get_track_temp(&self) -> Option<f64> {
+        self.get_time_of_day()
+            .map(|time_of_day| self.track_temp_pars.calc_track_temp(time_of_day, self.sunset_h))
+    }
+
+    /// Zwraca postęp usuwania skutków incydentu (None, jeśli żaden incydent nie jest obsługiwany).
+    pub fn get_incident_clearance_prog(&self) -> Option<f64> {
+        self.incident.as_ref().map(|incident| incident.get_progress())
+    }
+
+    /// get_sc_speed zwraca prędkość SC. Gdy lider dogonił SC, SC nie jedzie szybciej, niż lider
+    /// może jechać (np. na mokrym torze), żeby nie odjechał kolejce.
+    fn get_sc_speed(&self) -> f64 {
+        if !self.safety_car.leader_caught {
+            return self.safety_car.speed;
+        }
+
+        match self.get_car_order_on_track().first() {
+            Some(&idx_leader) if self.cur_th_laptimes[idx_leader] > 0.0 => self
+                .safety_car
+                .speed
+                .min(self.track.length / self.cur_th_laptimes[idx_leader]),
+            _ => self.safety_car.speed,
+        }
+    }
+
+    /// move_safety_car przesuwa SC do przodu o jeden krok czasowy.
+    fn move_safety_car(&mut self) {
+        self.safety_car.s_track += self.get_sc_speed() * self.timestep_size;
+
+        if self.safety_car.s_track > self.track.length {
+            self.safety_car.s_track -= self.track.length;
+            self.safety_car.lap += 1;
+        }
+    }
+
+    /// is_sc_peel_off_passed sprawdza, czy SC w ostatnim kroku (z pozycji `s_track_prev` w
+    /// okrążeniu `lap_prev`) minął wjazd do alei (`pit_zone[0]`) lub linię mety - tylko tam SC może
+    /// zjechać z toru.
+    fn is_sc_peel_off_passed(&self, s_track_prev: f64, lap_prev: u32) -> bool {
+        let pit_entry = self.track.pit_zone[0];
+
+        self.safety_car.lap > lap_prev
+            || (s_track_prev < pit_entry && pit_entry <= self.safety_car.s_track)
+    }
+
+    /// set_flag_state ustawia stan flagi na całym torze (SC, VSC, czerwona flaga, szachownica).
+    /// Stan obowiązuje we wszystkich sektorach.
+    fn set_flag_state(&mut self, flag_state: FlagState) {
+        self.flag_state = flag_state;
+    }
+
+    /// get_sector_flag_states zwraca flagę obowiązującą w każdym sektorze (poważniejsza z flagi
+    /// na całym torze i lokalnej żółtej flagi). Sektor ma żółtą flagę, jeśli leży w nim choć część
+    /// odcinka z gruzem albo z autem stojącym na torze.
+    pub fn get_sector_flag_states(&self) -> [FlagState; 3] {
+        let mut sector_flag_states = [FlagState::G; 3];
+
+        for s_range in self
+            .debris_zones
+            .iter()
+            .map(|debris_zone| debris_zone.s_range)
+            .chain(self.yellow_zones.iter().map(|yellow_zone| yellow_zone.s_range))
+        {
+            // odcinek biegnie od s_range[0] do s_range[1] (ewentualnie przez linię mety)
+            let sector_start = self.track.get_sector(s_range[0]);
+            let sector_end = self.track.get_sector(s_range[1]);
+            let mut sector = sector_start;
+
+            loop {
+                sector_flag_states[sector] = FlagState::Y;
+                if sector == sector_end {
+                    break;
+                }
+                sector = (sector + 1) % 3;
             }
         }
+
+        for sector_flag_state in sector_flag_states.iter_mut() {
+            if self.flag_state.get_severity() >= sector_flag_state.get_severity() {
+                *sector_flag_state = self.flag_state;
+            }
+        }
+
+        sector_flag_states
+    }
+
+    /// get_most_severe_flag_state zwraca najpoważniejszą flagę obowiązującą w którymkolwiek
+    /// sektorze (np. do wyświetlenia jednej flagi dla całego toru).
+    pub fn get_most_severe_flag_state(&self) -> FlagState {
+        self.get_sector_flag_states()
+            .iter()
+            .copied()
+            .max_by_key(|flag_state| flag_state.get_severity())
+            .unwrap_or(self.flag_state)
+    }
+
+    /// get_car_flag_state zwraca flagę obowiązującą w sektorze, w którym znajduje się auto.
+    fn get_car_flag_state(&self, idx: usize) -> FlagState {
+        let sector = self.track.get_sector(self.cars_list[idx].sh.get_s_tracks().1);
+        self.get_sector_flag_states()[sector]
+    }
+
+    /// Zwraca minimalny czas okrążenia auta w zależności od flagi w sektorze, w którym auto się
+    /// znajduje (podczas VSC jest to czas okrążenia auta: jego teoretyczne tempo plus
+    /// `vsc_delta_s`, zob. `calc_vsc_laptime`)
+    fn get_min_laptime_flag_state(&self, idx: usize) -> f64 {
+        match self.get_car_flag_state(idx) {
+            FlagState::Vsc => self.calc_vsc_laptime(idx),
+            flag_state => self.calc_min_laptime_flag_state(flag_state),
+        }
     }
 
-    /// Zwraca minimalny czas okrążenia w zależności od flagi
-    fn get_min_laptime_flag_state(&self) -> f64 {
-        match self.flag_state {
-            FlagState::Y => (self.track.t_q + self.track.t_gap_racepace) * 1.1,
-            FlagState::Vsc => (self.track.t_q + self.track.t_gap_racepace) * 1.4,
-            FlagState::Sc => (self.track.t_q + self.track.t_gap_racepace) * 1.4,
+    /// calc_vsc_laptime zwraca czas okrążenia auta za VSC: jego bieżący teoretyczny czas okrążenia
+    /// plus `vsc_delta_s`, więc każde auto traci tyle samo względem własnego tempa.
+    fn calc_vsc_laptime(&self, idx: usize) -> f64 {
+        self.cur_th_laptimes[idx] + self.vsc_delta_s
+    }
+
+    /// Zwraca minimalny czas okrążenia przy podanej fladze (za VSC w tempie wyścigowym toru, bez
+    /// tempa konkretnego auta).
+    fn calc_min_laptime_flag_state(&self, flag_state: FlagState) -> f64 {
+        match flag_state {
+            FlagState::Vsc => self.track.t_q + self.track.t_gap_racepace + self.vsc_delta_s,
+            FlagState::Sc => self.calc_sc_delta_laptime(),
+            FlagState::C if self.finish_under_sc => self.calc_sc_delta_laptime(),
             _ => 0.0,
         }
     }
 
+    /// calc_sc_delta_laptime zwraca najkrótszy czas okrążenia za SC (delta SC): tempo wyścigowe
+    /// razy `sc_delta_factor`.
+    fn calc_sc_delta_laptime(&self) -> f64 {
+        (self.track.t_q + self.track.t_gap_racepace) * self.sc_delta_factor
+    }
+
     /// Obsługuje logikę postoju w alei serwisowej
     fn handle_pit_standstill(&mut self) {
         for i in 0..self.cars_list.len() {
@@ -929,10 +3989,27 @@ impl Race {
                 }
 
                 let compl_lap_cur = car.sh.get_compl_lap();
-                let t_standstill_target = if self.track.pits_aft_finishline {
-                    car.t_add_pit_standstill(compl_lap_cur)
+                let inlap = if self.track.pits_aft_finishline {
+                    compl_lap_cur
+                } else {
+                    compl_lap_cur + 1
+                };
+                let t_standstill_target = car.t_add_pit_standstill(
+                    inlap,
+                    self.pit_crew_sigma,
+                    self.pit_crew_mean_offset,
+                    self.pit_crew_fatigue,
+                );
+
+                let pit_stop = if t_standstill_target > 0.0 {
+                    car.no_pitstops += 1;
+                    Some(PitStopInfo {
+                        car_no: car.car_no,
+                        lap: inlap,
+                        t_standstill: t_standstill_target,
+                    })
                 } else {
-                    car.t_add_pit_standstill(compl_lap_cur + 1)
+                    None
                 };
 
                 car.sh
@@ -944,10 +4021,47 @@ impl Race {
                 } else {
                     compl_lap_cur + 1
                 };
+                let car_no = car.car_no;
                 let pit_location = car.pit_location;
-                car.perform_pitstop(compl_lap_for_pitstop, &self.drivers_list);
+                let repaired = car.perform_pitstop(compl_lap_for_pitstop, &self.drivers_list);
 
                 car.sh.set_s_track(pit_location);
+
+                // nowy stint zaczyna się po okrążeniu zjazdowym (także przy alei za linią mety)
+                if let Some(compound) =
+                    self.compounds[i].get_mut(compl_lap_for_pitstop as usize + 1)
+                {
+                    *compound = car.get_current_compound().to_owned();
+                }
+
+                // zdarzenie z wylosowanym czasem postoju (przejazd przez aleję bez postoju go nie ma)
+                if let Some(pit_stop) = pit_stop {
+                    self.pit_stops.push(pit_stop.to_owned());
+                    self.push_event(RaceEvent {
+                        pit_stop: Some(pit_stop),
+                        ..RaceEvent::new(
+                            "PIT_STOP",
+                            self.cur_lap_leader,
+                            self.cur_racetime,
+                            vec![car_no],
+                        )
+                    });
+                }
+
+                if repaired {
+                    if self.print_events {
+                        println!("PIT: Car {} has its damage repaired", car_no);
+                    }
+                    self.push_event(RaceEvent {
+                        s_range: Some([pit_location, pit_location]),
+                        ..RaceEvent::new(
+                            "DAMAGE_REPAIRED",
+                            self.cur_lap_leader,
+                            self.cur_racetime,
+                            vec![car_no],
+                        )
+                    });
+                }
                 
                 // Recalculate theoretical lap time immediately after tire change
                 self.calc_th_laptime(i);
@@ -965,122 +4079,1946 @@ impl Race {
         }
     }
 
-    /// Obsługuje przejścia między okrążeniami
-    fn handle_lap_transitions(&mut self) {
-        for car in self.cars_list.iter() {
-            let compl_lap_cur = car.sh.get_compl_lap();
+    /// check_missed_stop sprawdza po przekroczeniu linii mety, czy auto minęło zaplanowane
+    /// okrążenie zjazdowe bez pit stopu, i zapisuje wtedy ostrzeżenie (zdarzenie `MissedStop`).
+    /// Pit stopy usunięte ze strategii w trakcie wyścigu nie są już w niej, więc nie są zgłaszane.
+    fn check_missed_stop(&mut self, idx: usize) {
+        let car = &self.cars_list[idx];
 
-            if compl_lap_cur >= self.cur_lap_leader {
-                self.cur_lap_leader = compl_lap_cur + 1
-            }
+        if car.status == CarStatus::DNF || self.race_finished[idx] {
+            return;
         }
 
-        //zapisanie pogody do logów
-        if self.cur_lap_leader > self.weather_history_log.len() as u32 {
-            let weather_str = match self.weather_state {
-                WeatherState::Rain => "Rain".to_string(),
-                WeatherState::Dry => "Dry".to_string(),
-            };
-            self.weather_history_log.push(weather_str);
-        }
+        // z boksami za linią mety postój okrążenia zjazdowego następuje dopiero po linii
+        let compl_lap = car.sh.get_compl_lap();
+        let inlap = if self.track.pits_aft_finishline {
+            compl_lap.saturating_sub(1)
+        } else {
+            compl_lap
+        };
 
-        if self.cur_lap_leader > self.tot_no_laps && !matches!(self.flag_state, FlagState::C) {
-            self.flag_state = FlagState::C;
-            // Oznacz wszystkie auta jako ukończone, aby pętla główna mogła się zakończyć
-            for finished in self.race_finished.iter_mut() {
-                *finished = true;
-            }
+        if inlap == 0 || !car.pit_this_lap(inlap) || car.is_stop_executed(inlap) {
+            return;
         }
 
-        for i in 0..self.cars_list.len() {
-            let car = &mut self.cars_list[i];
+        let car_no = car.car_no;
+        if self.print_events {
+            println!("WARNING: Car {} missed its planned pit stop in lap {}", car_no, inlap);
+        }
+        self.missed_inlaps[idx].push(inlap);
+        self.push_event(RaceEvent::new(
+            "MissedStop",
+            self.cur_lap_leader,
+            self.cur_racetime,
+            vec![car_no],
+        ));
+    }
 
-            if car.sh.get_new_lap() {
-                let lap_frac_prev = car.sh.get_lap_fracs().0;
-                let t_part_old = (1.0 - lap_frac_prev) * self.cur_laptimes[i];
+    /// get_unexecuted_stops zestawia zaplanowane pit stopy, które nie zostały wykonane, wraz z
+    /// przyczyną: pominięcie (MissedStop), wycofanie auta, koniec wyścigu albo zastąpienie przez
+    /// zmianę strategii. Przyszłe pit stopy aut, które jeszcze jadą, nie są uwzględniane.
+    fn get_unexecuted_stops(&self) -> Vec<UnexecutedStop> {
+        let mut unexecuted_stops = Vec::new();
 
-                let compl_lap_cur = car.sh.get_compl_lap();
+        for (idx, car) in self.cars_list.iter().enumerate() {
+            let retired = car.status == CarStatus::DNF;
 
-                if compl_lap_cur <= self.tot_no_laps {
-                    self.laptimes[i][compl_lap_cur as usize] =
-                        self.cur_racetime - self.timestep_size + t_part_old
-                            - self.racetimes[i][compl_lap_cur as usize - 1];
-                    self.racetimes[i][compl_lap_cur as usize] = self.racetimes[i]
-                        [compl_lap_cur as usize - 1]
-                        + self.laptimes[i][compl_lap_cur as usize];
+            for entry in car.get_strategy().iter().skip(1) {
+                if car.is_stop_executed(entry.inlap) {
+                    continue;
                 }
 
-                if matches!(self.flag_state, FlagState::C) {
-                    self.race_finished[i] = true
-                }
+                let reason = if self.missed_inlaps[idx].contains(&entry.inlap) {
+                    UnexecutedStopReason::Missed
+                } else if retired {
+                    UnexecutedStopReason::Retired
+                } else if self.race_finished[idx] {
+                    UnexecutedStopReason::RaceEnded
+                } else {
+                    continue;
+                };
+
+                unexecuted_stops.push(UnexecutedStop {
+                    car_no: car.car_no,
+                    inlap: entry.inlap,
+                    compound: entry.compound.to_owned(),
+                    reason,
+                });
+            }
 
-                // Track potential engine failure event
-                let prev_status = car.status.clone();
-                car.drive_lap(self.cur_laptimes[i], self.failure_rate_per_hour, self.print_events);
-                if prev_status != car.status && car.status == CarStatus::DNF {
-                    // Log as an EngineFailure event (treated as crash on plots)
-                    self.events.push(RaceEvent {
-                        kind: "EngineFailure".to_string(),
-                        lap: self.cur_lap_leader, // current leader's lap after crossing
-                        time_s: self.cur_racetime,
-                        cars: vec![car.car_no],
+            for entry in car.get_superseded_stops() {
+                if !car.is_stop_executed(entry.inlap) {
+                    unexecuted_stops.push(UnexecutedStop {
+                        car_no: car.car_no,
+                        inlap: entry.inlap,
+                        compound: entry.compound.to_owned(),
+                        reason: UnexecutedStopReason::Superseded,
                     });
                 }
-
-                // update theoretical lap time
-                self.calc_th_laptime(i);
             }
         }
+
+        unexecuted_stops
     }
 
-    /// Przygotowuje dane i wywołuje maszynę stanów (uproszczone).
-    fn handle_state_transitions(&mut self) {
-        let idxs_sorted = self.get_car_order_on_track();
-        let car_pair_idxs_list = self.get_car_pair_idxs_list(&idxs_sorted, false);
+    /// Obsługuje przejścia między okrążeniami
+    fn handle_lap_transitions(&mut self) {
+        let cur_lap_leader_prev = self.cur_lap_leader;
 
-        let mut delta_ts = vec![0.0; self.cars_list.len()];
-        let mut lapping = vec![false; self.cars_list.len()];
+        for (i, car) in self.cars_list.iter().enumerate() {
+            // auta po mecie jadą dalej, ale nie zmieniają już okrążenia lidera
+            if self.race_finished[i] {
+                continue;
+            }
 
-        for (i, pair_idxs) in car_pair_idxs_list.iter().enumerate() {
-            delta_ts[i] = self.calc_projected_delta_t(pair_idxs[0], pair_idxs[1], 0.0);
+            let compl_lap_cur = car.sh.get_compl_lap();
 
-            if self.cars_list[pair_idxs[0]].sh.get_race_prog()
-                < self.cars_list[pair_idxs[1]].sh.get_race_prog()
-            {
-                lapping[i] = true;
+            if compl_lap_cur >= self.cur_lap_leader {
+                self.cur_lap_leader = compl_lap_cur + 1
             }
         }
 
-        for (i, pair_idxs) in car_pair_idxs_list.iter().enumerate() {
-            let car_idx = pair_idxs[1]; 
+        if self.cur_lap_leader > self.get_finish_lap() && !matches!(self.flag_state, FlagState::C) {
+            // szachownica ma pierwszeństwo przed SC: SC nie zjeżdża, tylko prowadzi pozostałe auta
+            // do mety (odstępy na mecie wynikają z kolejki za SC)
+            if matches!(self.flag_state, FlagState::Sc) && self.safety_car.active {
+                self.finish_under_sc = true;
+                self.sc_timer = f64::INFINITY;
+                self.incident = None;
+                self.sc_bunching_lap = None;
+
+                if self.print_events { println!("RACE FINISHES UNDER THE SAFETY CAR"); }
+                self.push_event(RaceEvent::new(
+                    "FINISH_UNDER_SC",
+                    self.get_finish_lap(),
+                    self.cur_racetime,
+                    vec![],
+                ));
+            }
+            // VSC kończy się z szachownicą, okres SC dopiero gdy ostatnie auto minie metę
+            if !self.finish_under_sc {
+                self.close_sc_period();
+            }
+            // każde jadące auto kończy wyścig, gdy następnym razem minie linię mety po liderze
+            self.set_flag_state(FlagState::C);
+            self.t_chequered = Some(self.calc_t_chequered());
+        }
+
+        let incident_factor = self.get_incident_factor();
+        let track_temp = self.get_track_temp();
+
+        // odstępy do aut z przodu i z tyłu (wybór trybu silnika na następne okrążenie)
+        let intervals = if self.cars_list.iter().any(|car| car.sh.get_new_lap()) {
+            self.get_intervals_ahead_behind()
+        } else {
+            Vec::new()
+        };
+
+        for i in 0..self.cars_list.len() {
+            let car = &mut self.cars_list[i];
+
+            // auto po mecie jedzie dalej, ale jego kolejne okrążenia nie są już zapisywane
+            if car.sh.get_new_lap() && !self.race_finished[i] {
+                let compl_lap_cur = car.sh.get_compl_lap();
+
+                // czas przekroczenia linii interpolowany w kroku (pełna precyzja, również na mecie),
+                // z doliczonymi karami czasowymi
+                let t_crossing_track = calc_crossing_time(
+                    self.cur_racetime - self.timestep_size,
+                    self.timestep_size,
+                    car.sh.get_race_prog_prev(),
+                    car.sh.get_race_prog(),
+                    compl_lap_cur as f64,
+                );
+                let t_crossing = t_crossing_track + self.t_penalties[i];
+
+                if compl_lap_cur <= self.tot_no_laps {
+                    self.laptimes[i][compl_lap_cur as usize] =
+                        t_crossing - self.racetimes[i][compl_lap_cur as usize - 1];
+                    self.racetimes[i][compl_lap_cur as usize] = self.racetimes[i]
+                        [compl_lap_cur as usize - 1]
+                        + self.laptimes[i][compl_lap_cur as usize];
+                    debug_assert!(
+                        self.laptimes[i][compl_lap_cur as usize].is_finite(),
+                        "Non-finite lap time of car {} in lap {}!",
+                        car.car_no,
+                        compl_lap_cur
+                    );
+
+                    // flagi kontekstu ukończonego okrążenia
+                    let mut flags = self.lap_flags_cur[i];
+                    if self.t_traffic_cur_lap[i] > self.traffic_flag_t_min {
+                        flags |= LAP_FLAG_TRAFFIC;
+                    }
+                    if car.get_damage() > 0.0 {
+                        flags |= LAP_FLAG_DAMAGE;
+                    }
+                    if car.is_fuel_save() {
+                        flags |= LAP_FLAG_FUEL_SAVE;
+                    }
+                    self.lap_flags[i][compl_lap_cur as usize] = flags;
+                    self.engine_modes[i][compl_lap_cur as usize] = car.get_engine_mode();
+                    if let Some(compound) = self.compounds[i].get_mut(compl_lap_cur as usize + 1) {
+                        *compound = car.get_current_compound().to_owned();
+                    }
+                }
+                self.lap_flags_cur[i] = 0;
+                self.t_traffic_cur_lap[i] = 0.0;
+
+                // auto zdublowane, które w kroku szachownicy minęło linię tuż przed liderem,
+                // przejeżdża jeszcze jedno pełne okrążenie
+                if matches!(self.t_chequered, Some(t_chequered) if t_crossing_track >= t_chequered)
+                {
+                    self.race_finished[i] = true
+                }
+
+                // temperatura toru na koniec okrążenia zmienia zużycie opon w tym okrążeniu
+                car.temp_wear_factor = match track_temp {
+                    Some(temp) => self.track_temp_pars.calc_wear_factor(
+                        temp,
+                        self.tire_config
+                            .for_compound(car.get_current_compound())
+                            .temp_wear_sensitivity,
+                    ),
+                    None => 1.0,
+                };
+
+                // awaria jest losowana na koniec okrążenia - auto staje tuż za linią, zdarzenie ma
+                // czas przekroczenia linii (bez kar czasowych)
+                // po mecie auto nie może już odpaść
+                let failure_rates = if self.race_finished[i] {
+                    FailureRates::default()
+                } else {
+                    car.get_failure_rates(&self.failure_rates).scaled(incident_factor)
+                };
+                let retirement =
+                    car.drive_lap(self.cur_laptimes[i], &failure_rates, &self.tire_config);
+                if let Some(cause) = retirement {
+                    if self.print_events {
+                        println!(
+                            "CRASH: Car {} has retired from the race ({})",
+                            car.car_no,
+                            cause.get_event_kind()
+                        );
+                    }
+                    let car_no = car.car_no;
+                    self.record_retirement(
+                        &[i],
+                        cause,
+                        vec![car_no],
+                        t_crossing - self.t_penalties[i],
+                    );
+                }
+
+                self.check_missed_stop(i);
+
+                // oszczędzanie paliwa w następnym okrążeniu wg prognozy paliwa na mecie
+                let laps_remaining = self.get_finish_lap().saturating_sub(compl_lap_cur);
+                let car = &mut self.cars_list[i];
+                if car.status == CarStatus::Running && !self.race_finished[i] {
+                    car.update_fuel_save(laps_remaining);
+
+                    // tryb silnika w następnym okrążeniu: ze strategii bieżącego stintu albo
+                    // automatycznie (auto oszczędzające paliwo nie przyspiesza)
+                    let engine_mode = match car.get_stint_engine_mode() {
+                        Some(engine_mode) => engine_mode,
+                        None if self.engine_mode_select_pars.auto => {
+                            let (gap_ahead, gap_behind) =
+                                intervals.get(i).copied().unwrap_or_default();
+                            match select_engine_mode(
+                                gap_ahead,
+                                gap_behind,
+                                laps_remaining,
+                                &self.engine_mode_select_pars,
+                            ) {
+                                EngineMode::Push if car.is_fuel_save() => EngineMode::Standard,
+                                engine_mode => engine_mode,
+                            }
+                        }
+                        None => EngineMode::Standard,
+                    };
+                    car.set_engine_mode(engine_mode);
+                }
+
+                // update theoretical lap time
+                self.calc_th_laptime(i);
+            }
+        }
+
+        self.force_classify_stragglers();
+
+        // meta za SC: SC zjeżdża, gdy ostatnie jadące auto minie linię mety
+        if self.finish_under_sc && self.get_all_finished() {
+            self.finish_under_sc = false;
+            self.safety_car.active = false;
+            self.close_sc_period();
+        }
+
+        // zdarzenia wymuszone przez scenariusz (na początku okrążenia lidera, po zapisaniu czasów
+        // ukończonych okrążeń i przed zapisem pogody)
+        self.apply_scripted_events();
+
+        // drobne incydenty bez wycofania (gruz w losowym miejscu toru) - najpierw usunięcie gruzu,
+        // którego czas minął, żeby nowy incydent nie przedłużył starego odcinka
+        if self.cur_lap_leader > cur_lap_leader_prev {
+            self.update_debris_zones();
+            self.draw_debris_incident();
+        }
+
+        //zapisanie pogody do logów
+        if self.cur_lap_leader > self.weather_history_log.len() as u32 {
+            let weather_str = match self.weather_state {
+                WeatherState::Rain => "Rain".to_string(),
+                WeatherState::Dry => "Dry".to_string(),
+            };
+            self.weather_history_log.push(weather_str);
+        }
+
+        self.update_lead_changes();
+    }
+
+    /// update_lead_changes zapisuje zdarzenie "LeadChange" dla każdego okrążenia, po którym
+    /// prowadzi inne auto niż po poprzednim okrążeniu. Okrążenie jest sprawdzane dopiero wtedy, gdy
+    /// ukończyły je wszystkie jadące auta, bo dopiero wtedy wiadomo, kto miał najkrótszy czas
+    /// wyścigu (z karami) na jego końcu.
+    fn update_lead_changes(&mut self) {
+        while self.lead_change_lap < self.tot_no_laps {
+            let lap = self.lead_change_lap + 1;
+
+            let lap_completed = self.cars_list.iter().enumerate().all(|(i, car)| {
+                self.race_finished[i] || car.status == CarStatus::DNF || car.sh.get_compl_lap() >= lap
+            });
+            if !lap_completed {
+                return;
+            }
+
+            let leader = (0..self.cars_list.len())
+                .map(|i| (i, self.racetimes[i][lap as usize]))
+                .filter(|(_, racetime)| racetime.is_finite() && *racetime > 0.0)
+                .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+            let (idx_leader, t_leader) = match leader {
+                Some(x) => x,
+                None => return,
+            };
+
+            self.lead_change_lap = lap;
+            let leader = self.cars_list[idx_leader].car_no;
+
+            if let Some(leader_prev) = self.lap_leader_prev {
+                if leader != leader_prev {
+                    self.push_event(RaceEvent::new(
+                        "LeadChange",
+                        lap,
+                        t_leader,
+                        vec![leader, leader_prev],
+                    ));
+                }
+            }
+            self.lap_leader_prev = Some(leader);
+        }
+    }
+
+    /// calc_t_chequered zwraca czas wyścigu, w którym lider minął linię mety na końcu ostatniego
+    /// okrążenia (interpolowany w kroku, bez kar czasowych). Jeśli żadne auto nie ukończyło
+    /// ostatniego okrążenia w tym kroku (np. wyścig skrócony po czerwonej fladze), jest to bieżący
+    /// czas wyścigu.
+    fn calc_t_chequered(&self) -> f64 {
+        let finish_lap = self.get_finish_lap();
+
+        self.cars_list
+            .iter()
+            .enumerate()
+            .filter(|(i, car)| {
+                !self.race_finished[*i]
+                    && car.sh.get_new_lap()
+                    && car.sh.get_compl_lap() == finish_lap
+            })
+            .map(|(_, car)| {
+                calc_crossing_time(
+                    self.cur_racetime - self.timestep_size,
+                    self.timestep_size,
+                    car.sh.get_race_prog_prev(),
+                    car.sh.get_race_prog(),
+                    car.sh.get_compl_lap() as f64,
+                )
+            })
+            .fold(self.cur_racetime, f64::min)
+    }
+
+    /// force_classify_stragglers kończy wyścig aut, które po szachownicy nie minęły linii mety w
+    /// limicie czasu: czas lidera na mecie plus jedno okrążenie w najwolniejszym tempie po
+    /// szachownicy (tempo wyścigowe razy `finish_min_pace_factor`, za SC co najmniej tempo SC),
+    /// np. auto unieruchomione w alei serwisowej. Takie auta są klasyfikowane wg dotychczas
+    /// ukończonych okrążeń (zdarzenie ForceClassified), a symulacja się kończy.
+    fn force_classify_stragglers(&mut self) {
+        let t_chequered = match self.t_chequered {
+            Some(t_chequered) => t_chequered,
+            None => return,
+        };
+
+        let laptime_max = ((self.track.t_q + self.track.t_gap_racepace)
+            * self.finish_min_pace_factor)
+            .max(self.calc_min_laptime_flag_state(self.flag_state));
+
+        if self.cur_racetime <= t_chequered + laptime_max {
+            return;
+        }
+
+        for i in 0..self.cars_list.len() {
+            if self.race_finished[i] || self.cars_list[i].status == CarStatus::DNF {
+                continue;
+            }
+
+            self.race_finished[i] = true;
+            let car_no = self.cars_list[i].car_no;
+            let s_track = self.cars_list[i].sh.get_s_tracks().1;
+
+            if self.print_events {
+                println!(
+                    "FORCE CLASSIFIED: Car {} did not finish within {:.1}s after the leader",
+                    car_no, laptime_max
+                );
+            }
+            self.push_event(RaceEvent {
+                s_range: Some([s_track, s_track]),
+                ..RaceEvent::new(
+                    "ForceClassified",
+                    self.get_finish_lap(),
+                    self.cur_racetime,
+                    vec![car_no],
+                )
+            });
+        }
+    }
+
+    /// check_time_limit sprawdza limit czasu wyścigu `max_race_time_s`. Po jego przekroczeniu
+    /// bieżące okrążenie lidera staje się ostatnim okrążeniem wyścigu (szachownica, gdy lider je
+    /// ukończy), niezależnie od liczby pozostałych okrążeń. Zapisywane jest zdarzenie
+    /// TIME_LIMIT_REACHED.
+    fn check_time_limit(&mut self) {
+        let max_race_time_s = match self.max_race_time_s {
+            Some(x) => x,
+            None => return,
+        };
+
+        if self.time_limit_lap.is_some()
+            || self.cur_racetime <= max_race_time_s
+            || self.cur_lap_leader >= self.tot_no_laps
+        {
+            return;
+        }
+
+        self.time_limit_lap = Some(self.cur_lap_leader);
+
+        if self.print_events {
+            println!(
+                "TIME LIMIT REACHED - LAP {} IS THE LAST LAP (instead of {})",
+                self.cur_lap_leader, self.tot_no_laps
+            );
+        }
+        self.push_event(RaceEvent::new(
+            "TIME_LIMIT_REACHED",
+            self.cur_lap_leader,
+            self.cur_racetime,
+            vec![],
+        ));
+    }
+
+    /// get_finish_lap zwraca ostatnie okrążenie wyścigu (`tot_no_laps` lub okrążenie lidera, w
+    /// którym przekroczono limit czasu wyścigu).
+    pub fn get_finish_lap(&self) -> u32 {
+        self.time_limit_lap
+            .unwrap_or(self.tot_no_laps)
+            .min(self.tot_no_laps)
+    }
+
+    /// get_timing_point_race_prog zwraca postęp wyścigu punktu pomiaru czasu o podanym numerze
+    /// (kolejno linia mety oraz granice sektorów 1/2 i 2/3 każdego okrążenia).
+    fn get_timing_point_race_prog(&self, timing_point_no: usize) -> f64 {
+        let s_track = match timing_point_no % 3 {
+            0 => 0.0,
+            1 => self.track.s12,
+            _ => self.track.s23,
+        };
+        (timing_point_no / 3) as f64 + s_track / self.track.length
+    }
+
+    /// update_timing_histories zapisuje czasy przekroczenia punktów pomiaru czasu w tym kroku.
+    /// Czasy są interpolowane w kroku i zawierają kary czasowe, tak jak czasy wyścigu.
+    fn update_timing_histories(&mut self) {
+        let t_step_start = self.cur_racetime - self.timestep_size;
+
+        for i in 0..self.cars_list.len() {
+            let race_prog_prev = self.cars_list[i].sh.get_race_prog_prev();
+            let race_prog = self.cars_list[i].sh.get_race_prog();
+
+            loop {
+                let timing_point_no = self.timing_histories[i].no_crossings;
+                let race_prog_target = self.get_timing_point_race_prog(timing_point_no);
+
+                if race_prog_target > race_prog {
+                    break;
+                }
+
+                let t_crossing = calc_crossing_time(
+                    t_step_start,
+                    self.timestep_size,
+                    race_prog_prev,
+                    race_prog,
+                    race_prog_target,
+                ) + self.t_penalties[i];
+
+                let timing_history = &mut self.timing_histories[i];
+                if timing_history.t_crossings.len() >= TIMING_HISTORY_LEN {
+                    timing_history.t_crossings.pop_front();
+                }
+                timing_history.t_crossings.push_back(t_crossing);
+                timing_history.no_crossings += 1;
+            }
+        }
+    }
+
+    /// get_live_gap zwraca odstęp (s) auta `idx_rear` od auta `idx_front` zmierzony w ostatnim
+    /// punkcie pomiaru czasu, który minęły oba auta (tak jak w systemach pomiaru czasu). Przy
+    /// ukończeniu okrążenia odpowiada on różnicy czasów wyścigu. Zwraca None, jeśli takiego punktu
+    /// nie ma (jeszcze) w historii obu aut.
+    pub fn get_live_gap(&self, idx_front: usize, idx_rear: usize) -> Option<f64> {
+        let no_common = self.timing_histories[idx_front]
+            .no_crossings
+            .min(self.timing_histories[idx_rear].no_crossings);
+
+        if no_common == 0 {
+            return None;
+        }
+
+        let t_front = self.timing_histories[idx_front].get_t_crossing(no_common - 1)?;
+        let t_rear = self.timing_histories[idx_rear].get_t_crossing(no_common - 1)?;
+        Some(t_rear - t_front)
+    }
+
+    /// estimate_history_bytes zwraca szacowaną pamięć historii gromadzonych w trakcie wyścigu
+    /// (przekroczenia punktów pomiaru czasu i zdarzenia). Historia przekroczeń ma stałą długość.
+    pub fn estimate_history_bytes(&self) -> usize {
+        let bytes_timing: usize = self
+            .timing_histories
+            .iter()
+            .map(|x| std::mem::size_of::<TimingHistory>() + x.t_crossings.capacity() * 8)
+            .sum();
+        let bytes_events: usize = self
+            .events
+            .iter()
+            .map(|x| std::mem::size_of::<RaceEvent>() + x.kind.capacity() + x.cars.capacity() * 4)
+            .sum();
+
+        bytes_timing + bytes_events
+    }
+
+    /// get_intervals_ahead zwraca odstęp na żywo każdego auta od auta bezpośrednio przed nim w
+    /// kolejności wyścigu (None dla lidera, aut wycofanych oraz gdy odstępu nie można wyznaczyć).
+    pub fn get_intervals_ahead(&self) -> Vec<Option<f64>> {
+        self.get_intervals_ahead_behind()
+            .into_iter()
+            .map(|(interval_ahead, _)| interval_ahead)
+            .collect()
+    }
+
+    /// get_intervals_ahead_behind zwraca odstępy na żywo każdego auta (do auta z przodu, od auta z
+    /// tyłu) w kolejności wyścigu (None bez sąsiada, dla aut wycofanych oraz gdy odstępu nie można
+    /// wyznaczyć).
+    fn get_intervals_ahead_behind(&self) -> Vec<(Option<f64>, Option<f64>)> {
+        let race_progs: Vec<f64> =
+            self.cars_list.iter().map(|car| car.sh.get_race_prog()).collect();
+        let idxs_sorted: Vec<usize> = argsort(&race_progs, SortOrder::Descending)
+            .into_iter()
+            .filter(|&idx| self.cars_list[idx].status != CarStatus::DNF)
+            .collect();
+        let mut intervals = vec![(None, None); self.cars_list.len()];
+
+        for idx_pair in idxs_sorted.windows(2) {
+            let interval = self.get_live_gap(idx_pair[0], idx_pair[1]);
+            intervals[idx_pair[1]].0 = interval;
+            intervals[idx_pair[0]].1 = interval;
+        }
+
+        intervals
+    }
+
+    /// change_weather zmienia pogodę (sucho <-> deszcz), zapisuje zdarzenie i planuje zjazdy po
+    /// opony odpowiednie do nowej pogody.
+    fn change_weather(&mut self, scripted: bool) {
+        self.weather_state = match self.weather_state {
+            WeatherState::Dry => {
+                if self.print_events { println!("WEATHER CHANGE: Rain started at {:.2}s!", self.cur_racetime); }
+                self.last_weather_change = self.cur_racetime;
+                // event: rain start
+                self.push_event(RaceEvent {
+                    scripted,
+                    ..RaceEvent::new(
+                        "WeatherRainStart",
+                        self.cur_lap_leader,
+                        self.cur_racetime,
+                        vec![],
+                    )
+                });
+                // Zaplanuj pit na najbliższe okrążenie dla slicków → Intermediate
+                for car in self.cars_list.iter_mut() {
+                    if car.status == CarStatus::DNF { continue; }
+                    let comp = car.get_current_compound();
+                    match comp {
+                        "SOFT" | "MEDIUM" | "HARD" => {
+                            car.last_slick_compound = Some(comp.to_owned());
+                            // świadomy wybór opon jest zachowany przez `hold_strategy_laps` okrążeń
+                            let target_lap = car.sh.get_compl_lap() + 1 + car.hold_strategy_laps;
+                            car.schedule_weather_strategy(target_lap, "INTERMEDIATE");
+                        },
+                        _ => {},
+                    }
+                }
+                WeatherState::Rain
+            },
+            WeatherState::Rain => {
+                if self.print_events { println!("WEATHER CHANGE: Rain stopped at {:.2}s!", self.cur_racetime); }
+                self.last_weather_change = self.cur_racetime;
+                // event: dry start
+                self.push_event(RaceEvent {
+                    scripted,
+                    ..RaceEvent::new(
+                        "WeatherDryStart",
+                        self.cur_lap_leader,
+                        self.cur_racetime,
+                        vec![],
+                    )
+                });
+                // Zaplanuj pit na najbliższe okrążenia dla Inter/Wet → powrót do slicków
+                for car in self.cars_list.iter_mut() {
+                    if car.status == CarStatus::DNF { continue; }
+                    let comp = car.get_current_compound();
+                    let target_slick = car.last_slick_compound.clone().unwrap_or_else(|| "MEDIUM".to_string());
+                    match comp {
+                        "INTERMEDIATE" => {
+                            let target_lap = car.sh.get_compl_lap() + 1 + car.hold_strategy_laps;
+                            car.schedule_weather_strategy(target_lap, &target_slick);
+                        },
+                        "WET" => {
+                            let target_lap = car.sh.get_compl_lap() + 2 + car.hold_strategy_laps;
+                            car.schedule_weather_strategy(target_lap, &target_slick);
+                        },
+                        _ => {},
+                    }
+                }
+                WeatherState::Dry
+            },
+        };
+    }
+
+    /// apply_scripted_events wykonuje zdarzenia wymuszone, których okrążenie lidera się rozpoczęło.
+    fn apply_scripted_events(&mut self) {
+        let cur_lap_leader = self.cur_lap_leader;
+        let no_due = self
+            .scripted_events
+            .iter()
+            .take_while(|x| x.lap <= cur_lap_leader)
+            .count();
+        let due: Vec<ScriptedEvent> = self.scripted_events.drain(..no_due).collect();
+
+        for scripted_event in due.iter() {
+            self.apply_scripted_event(scripted_event);
+        }
+    }
+
+    /// apply_scripted_event wykonuje pojedyncze zdarzenie wymuszone.
+    fn apply_scripted_event(&mut self, scripted_event: &ScriptedEvent) {
+        match &scripted_event.action {
+            ScriptedAction::Weather { weather } => {
+                let is_rain = self.weather_state == WeatherState::Rain;
+                if (weather == "Rain") != is_rain {
+                    self.change_weather(true);
+                }
+            }
+            ScriptedAction::Dnf { car_no } => {
+                let idx = match self.cars_list.iter().position(|car| car.car_no == *car_no) {
+                    Some(idx) => idx,
+                    None => return,
+                };
+                if self.cars_list[idx].status == CarStatus::DNF || self.race_finished[idx] {
+                    return;
+                }
+
+                if self.print_events {
+                    println!("SCRIPTED: Car {} retires at {:.2}s", car_no, self.cur_racetime);
+                }
+                self.retire_cars(&[idx], RetirementCause::Scripted);
+                self.cur_laptimes[idx] = f64::INFINITY;
+            }
+            ScriptedAction::SafetyCar { no_laps } => {
+                // SC zjeżdża pod koniec ostatniego okrążenia (po ustawieniu kolejki)
+                self.sc_scripted_lap_in = Some(scripted_event.lap + no_laps - 1);
+
+                if !matches!(self.flag_state, FlagState::Sc) {
+                    if self.print_events {
+                        println!("SCRIPTED: Safety car for {} laps", no_laps);
+                    }
+                    self.set_flag_state(FlagState::Sc);
+                    self.sc_timer = f64::INFINITY;
+                    self.incident = None;
+                    self.sc_bunching_lap = None;
+                    self.close_pit_entry();
+
+                    // dotychczasowe wycofania nie wywołują kolejnego SC
+                    self.incident_queue.clear();
+                }
+            }
+            ScriptedAction::Penalty { car_no, t_penalty } => {
+                let idx = match self.cars_list.iter().position(|car| car.car_no == *car_no) {
+                    Some(idx) => idx,
+                    None => return,
+                };
+
+                if self.print_events {
+                    println!("SCRIPTED: Car {} receives a {:.1}s time penalty", car_no, t_penalty);
+                }
+                self.award_penalty(idx, *t_penalty, true);
+            }
+            ScriptedAction::TrackLimits { car_no } => {
+                let idx = match self.cars_list.iter().position(|car| car.car_no == *car_no) {
+                    Some(idx) => idx,
+                    None => return,
+                };
+
+                if self.print_events {
+                    println!("SCRIPTED: Car {} exceeds the track limits", car_no);
+                }
+                self.record_track_limits(idx, true);
+            }
+        }
+    }
+
+    /// award_penalty dolicza karę czasową do czasów wyścigu auta (od najbliższego przekroczenia
+    /// linii mety) i zapisuje zdarzenie kary.
+    fn award_penalty(&mut self, idx: usize, t_penalty: f64, scripted: bool) {
+        self.t_penalties[idx] += t_penalty;
+
+        self.push_event(RaceEvent {
+            scripted,
+            ..RaceEvent::new(
+                "Penalty",
+                self.cur_lap_leader,
+                self.cur_racetime,
+                vec![self.cars_list[idx].car_no],
+            )
+        });
+    }
+
+    /// record_track_limits zapisuje zdarzenie przekroczenia limitów toru (bez straty czasu, liczy
+    /// się do punktów karnych sezonu).
+    fn record_track_limits(&mut self, idx: usize, scripted: bool) {
+        self.push_event(RaceEvent {
+            scripted,
+            ..RaceEvent::new(
+                "TrackLimits",
+                self.cur_lap_leader,
+                self.cur_racetime,
+                vec![self.cars_list[idx].car_no],
+            )
+        });
+    }
+
+    /// handle_start_procedure losuje start każdego auta: czas reakcji na zgaśnięcie świateł
+    /// (zależny od konsystencji kierowcy), rzadkie włączenie anti-stall i deltę startu (zależną od
+    /// `start_skill`, doliczaną do tempa 1. okrążenia). Auto stoi na polu
+    /// startowym przez cały wylosowany czas, więc opóźnienie trafia do czasu 1. okrążenia
+    /// (liczonego od zgaśnięcia świateł) i do kolejności na wejściu w pierwszy zakręt. Reakcja
+    /// szybsza niż granica fizjologiczna to falstart karany karą czasową. Wywoływana przy tworzeniu
+    /// wyścigu, dlatego nietypowe starty są widoczne tylko w zdarzeniach (bez wypisywania na
+    /// konsolę).
+    fn handle_start_procedure(&mut self, start_pars: &StartPars, t_jump_start_penalty: f64) {
+        let mut rng = self.fork_rng();
+
+        for idx in 0..self.cars_list.len() {
+            let car_no = self.cars_list[idx].car_no;
+            let driver = &self.cars_list[idx].driver;
+            let reaction =
+                start_pars.draw_start_reaction(driver.consistency, driver.start_skill, &mut rng);
+            self.t_stopped_until[idx] = reaction.get_delay();
+            // delta startu wydłuża lub skraca 1. okrążenie, kolejność zmienia się więc przez
+            // zwykłe wyprzedzanie
+            self.launch_deltas[idx] = reaction.launch_delta;
+
+            // tylko nietypowe starty, przebieg startu jest w zdarzeniu (czas zdarzenia - start)
+            if reaction.is_notable() {
+                self.push_event(RaceEvent {
+                    start: Some(reaction),
+                    ..RaceEvent::new("StartReaction", 1, 0.0, vec![car_no])
+                });
+            }
+
+            if reaction.jump_start {
+                self.award_penalty(idx, t_jump_start_penalty, false);
+            }
+        }
+    }
+
+    /// Przygotowuje dane i wywołuje maszynę stanów (uproszczone).
+    fn handle_state_transitions(&mut self) {
+        // odstępy (z przodu, z tyłu) - auta bez interakcji (DNF, postój) nie mają sąsiadów
+        let mut delta_ts_car = vec![(f64::INFINITY, f64::INFINITY); self.cars_list.len()];
+
+        let idxs_sorted = self.get_interacting_car_order_on_track();
+
+        // DRS tylko od dozwolonego okrążenia, na zielonej fladze i na suchym torze
+        let drs_enabled = self.use_drs
+            && self.cur_lap_leader >= self.drs_allowed_lap
+            && matches!(self.flag_state, FlagState::G)
+            && self.weather_state == WeatherState::Dry;
+
+        // pojedyncze auto nie może tworzyć pary samo ze sobą
+        if idxs_sorted.len() >= 2 {
+            let car_pair_idxs_list = self.get_car_pair_idxs_list(&idxs_sorted, false);
+
+            for pair_idxs in car_pair_idxs_list.iter() {
+                let delta_t = self.calc_projected_delta_t(pair_idxs[0], pair_idxs[1], 0.0);
+                delta_ts_car[pair_idxs[1]].0 = delta_t;
+                delta_ts_car[pair_idxs[0]].1 = delta_t;
+            }
+        }
+
+        let pit_closed = self.is_pit_entry_closed();
+
+        // przegrzewanie tylko w walce przy zielonej fladze (za SC, VSC i podczas restartu auta
+        // jadą wolno i stygną)
+        let heating_possible = matches!(self.flag_state, FlagState::G | FlagState::Y)
+            && !self.is_rolling_restart()
+            && self.model_toggles.is_enabled(ModelToggles::DIRTY_AIR);
+
+        for (car_idx, (delta_t_front, delta_t_rear)) in delta_ts_car.into_iter().enumerate() {
             let compl_lap_cur = self.cars_list[car_idx].sh.get_compl_lap();
-            
-            let j = (i + 1) % car_pair_idxs_list.len();
+            let pit_this_lap = self.cars_list[car_idx].pit_this_lap(compl_lap_cur + 1);
+
+            // bez DRS w sektorze z żółtą flagą
+            let sector_yellow = matches!(self.get_car_flag_state(car_idx), FlagState::Y);
+
+            let pit_entry_refused = self.cars_list[car_idx].sh.check_state_transition(
+                delta_t_front,
+                delta_t_rear,
+                pit_this_lap,
+                pit_closed,
+                drs_enabled && !sector_yellow,
+            );
+
+            if pit_entry_refused {
+                self.postpone_pit_stop(car_idx, compl_lap_cur + 1);
+            }
+
+            // żółta flaga w sektorze lub lokalna wokół stojącego auta - bez pojedynków
+            if sector_yellow
+                || self.is_in_yellow_zone(self.cars_list[car_idx].sh.get_s_tracks().1)
+            {
+                self.cars_list[car_idx].sh.duel_act = false;
+            }
+
+            // brudne powietrze: auto nagrzewa się w pojedynku blisko za innym autem
+            let heating = heating_possible
+                && self.cars_list[car_idx].sh.duel_act
+                && delta_t_front < self.overheat_gap_s;
+            self.cars_list[car_idx].update_temperature(self.timestep_size, heating);
+        }
+
+        self.update_drs_trains();
+    }
+
+    /// close_pit_entry zamyka wjazd do alei po wypuszczeniu SC (jeśli włączone w `RacePars`) do
+    /// końca pierwszego pełnego okrążenia lidera za SC.
+    fn close_pit_entry(&mut self) {
+        if self.pit_closed_on_sc_deploy {
+            self.pit_closure = Some(PitClosure {
+                t_start: self.cur_racetime,
+                lap_reopen: self.cur_lap_leader + 2,
+            });
+        }
+    }
+
+    /// is_pit_entry_closed sprawdza, czy wjazd do alei jest zamknięty. Auta, które minęły wjazd
+    /// przed wypuszczeniem SC (w kroku, w którym SC wyjechał), mogą jeszcze zjechać.
+    fn is_pit_entry_closed(&self) -> bool {
+        matches!(
+            self.pit_closure,
+            Some(pit_closure) if self.cur_racetime > pit_closure.t_start
+                && self.cur_lap_leader < pit_closure.lap_reopen
+        )
+    }
+
+    /// advance_pit_stops_under_sc przenosi pit stopy zaplanowane w ciągu `sc_pit_window_laps`
+    /// okrążeń na najbliższy możliwy zjazd (postój za SC kosztuje mniej względem stawki) i zapisuje
+    /// zdarzenie PIT_UNDER_SC dla każdego auta, które skorzystało z okazji.
+    fn advance_pit_stops_under_sc(&mut self) {
+        let window_laps = match self.sc_pit_window_laps {
+            Some(window_laps) => window_laps,
+            None => return,
+        };
+        let pit_entry = self.track.pit_zone[0];
+
+        for idx in 0..self.cars_list.len() {
+            let car = &mut self.cars_list[idx];
+
+            if car.status == CarStatus::DNF || car.sh.pit_act {
+                continue;
+            }
+
+            // auto, które minęło już wjazd do alei, może zjechać dopiero na następnym okrążeniu
+            let inlap_target = if car.sh.get_s_tracks().1 < pit_entry {
+                car.sh.get_compl_lap() + 1
+            } else {
+                car.sh.get_compl_lap() + 2
+            };
+
+            if let Some(inlap_planned) = car.advance_pit(inlap_target, window_laps) {
+                let car_no = car.car_no;
+
+                if self.print_events {
+                    println!(
+                        "PIT UNDER SC: Car {} pits in lap {} instead of lap {}",
+                        car_no, inlap_target, inlap_planned
+                    );
+                }
+                self.push_event(RaceEvent::new(
+                    "PIT_UNDER_SC",
+                    self.cur_lap_leader,
+                    self.cur_racetime,
+                    vec![car_no],
+                ));
+            }
+        }
+    }
+
+    /// postpone_pit_stop przesuwa zjazd auta, które musiało zostać na torze z powodu zamkniętego
+    /// wjazdu do alei, na kolejne okrążenie i zapisuje zdarzenie PIT_CLOSED.
+    fn postpone_pit_stop(&mut self, idx: usize, inlap: u32) {
+        self.cars_list[idx].postpone_pit(inlap);
+        let car_no = self.cars_list[idx].car_no;
+
+        if self.print_events {
+            println!("PIT CLOSED: Car {} has to stay out in lap {}", car_no, inlap);
+        }
+        self.push_event(RaceEvent::new(
+            "PIT_CLOSED",
+            self.cur_lap_leader,
+            self.cur_racetime,
+            vec![car_no],
+        ));
+    }
+
+    /// update_drs_trains wykrywa pociągi DRS i zapisuje zdarzenie DrsTrain, gdy pociąg powstaje, oraz
+    /// DrsTrainEnd, gdy się rozpada (patrz compare_drs_trains).
+    fn update_drs_trains(&mut self) {
+        let idxs_sorted = self.get_interacting_car_order_on_track();
+        let car_nos: Vec<u32> = idxs_sorted.iter().map(|&i| self.cars_list[i].car_no).collect();
+        let drs_eligible: Vec<bool> = idxs_sorted
+            .iter()
+            .map(|&i| self.cars_list[i].sh.get_drs_eligible())
+            .collect();
+
+        let drs_trains = find_drs_trains(&car_nos, &drs_eligible);
+
+        let (formed_drs_trains, ended_drs_trains) =
+            compare_drs_trains(&self.drs_trains, &drs_trains);
+
+        for drs_train in formed_drs_trains {
+            if self.print_events {
+                println!("DRS TRAIN: Cars {:?} all within DRS range", drs_train);
+            }
+            self.push_event(RaceEvent::new(
+                "DrsTrain",
+                self.cur_lap_leader,
+                self.cur_racetime,
+                drs_train,
+            ));
+        }
+
+        for drs_train in ended_drs_trains {
+            if self.print_events {
+                println!("DRS TRAIN END: Cars {:?} no longer in a DRS train", drs_train);
+            }
+            self.push_event(RaceEvent::new(
+                "DrsTrainEnd",
+                self.cur_lap_leader,
+                self.cur_racetime,
+                drs_train,
+            ));
+        }
+
+        self.drs_trains = drs_trains;
+    }
+
+    /// update_wetness przybliża wilgotność toru wykładniczo do stanu pogody (deszcz: 1.0, sucho:
+    /// 0.0) i zapisuje zdarzenie HeavyRain po przekroczeniu progu ulewy. Zwraca true w kroku, w
+    /// którym zaczęła się ulewa.
+    fn update_wetness(&mut self) -> bool {
+        let wetness_target = if self.weather_state == WeatherState::Rain { 1.0 } else { 0.0 };
+        let step_frac = if self.wetness_time_constant_s > 0.0 {
+            1.0 - (-self.timestep_size / self.wetness_time_constant_s).exp()
+        } else {
+            1.0
+        };
+        self.wetness += (wetness_target - self.wetness) * step_frac;
+
+        let heavy_rain = self.wetness >= self.heavy_rain_wetness;
+
+        if heavy_rain && !self.heavy_rain_act {
+            if self.print_events {
+                println!("HEAVY RAIN: Track wetness {:.2} at {:.2}s", self.wetness, self.cur_racetime);
+            }
+            self.push_event(RaceEvent::new(
+                "HeavyRain",
+                self.cur_lap_leader,
+                self.cur_racetime,
+                vec![],
+            ));
+        }
+        let heavy_rain_start = heavy_rain && !self.heavy_rain_act;
+        self.heavy_rain_act = heavy_rain;
+        heavy_rain_start
+    }
+
+    /// get_wetness zwraca wilgotność toru (0.0 - sucho, 1.0 - całkowicie mokro).
+    pub fn get_wetness(&self) -> f64 {
+        self.wetness
+    }
+
+    /// get_incident_factor zwraca mnożnik prawdopodobieństw incydentów (błędy, kontakty, kolizje,
+    /// awarie) zależny od wilgotności toru, podwyższony przez okrążenie po restarcie w deszczu.
+    pub fn get_incident_factor(&self) -> f64 {
+        let mut incident_factor = if self.incident_wetness_xp.is_empty() {
+            1.0
+        } else {
+            lin_interp(self.wetness, &self.incident_wetness_xp, &self.incident_wetness_fp)
+        };
+
+        if let Some(restart_lap) = self.restart_lap {
+            if self.cur_lap_leader <= restart_lap && self.weather_state == WeatherState::Rain {
+                incident_factor *= self.wet_restart_factor;
+            }
+        }
+
+        incident_factor
+    }
+
+    /// get_car_incident_factor zwraca mnożnik prawdopodobieństw incydentów auta: w pierwszym
+    /// okrążeniu auta na slickach (hazard na wysychającym torze) ryzykują tym więcej, im bardziej
+    /// mokry jest tor (do `slick_wet_first_lap_factor` na całkowicie mokrym torze).
+    fn get_car_incident_factor(&self, idx: usize) -> f64 {
+        let car = &self.cars_list[idx];
+        let mut incident_factor = self.get_incident_factor();
+
+        if car.sh.get_compl_lap() == 0
+            && matches!(car.get_current_compound(), "SOFT" | "MEDIUM" | "HARD")
+        {
+            incident_factor *= 1.0 + (self.slick_wet_first_lap_factor - 1.0) * self.wetness;
+        }
+
+        incident_factor
+    }
+
+    /// get_pair_incident_factor zwraca mnożnik prawdopodobieństw incydentów pary aut (większy z
+    /// mnożników obu aut).
+    fn get_pair_incident_factor(&self, idx_front: usize, idx_rear: usize) -> f64 {
+        self.get_car_incident_factor(idx_front)
+            .max(self.get_car_incident_factor(idx_rear))
+    }
+
+    /// get_incident_location zwraca część toru (zakręt, strefa wyprzedzania, reszta), w której
+    /// znajduje się podane miejsce.
+    fn get_incident_location(&self, s_track: f64) -> IncidentLocation {
+        if self.track.corners.iter().any(|corner| is_in_s_range(s_track, *corner)) {
+            IncidentLocation::Corner
+        } else if self.track.is_in_overtaking_zone(s_track) {
+            IncidentLocation::OvertakingZone
+        } else {
+            IncidentLocation::Other
+        }
+    }
+
+    /// decide_sc losuje, czy wycofanie aut wywołuje SC. Prawdopodobieństwo zależy od miejsca, w
+    /// którym stanęło pierwsze auto. Jeśli SC nie zostanie wypuszczony, auto w zakręcie wywołuje
+    /// VSC, a w pozostałych miejscach tylko lokalną żółtą flagę. Karambol kilku aut w pierwszym
+    /// zakręcie zawsze wywołuje SC. Losowanie jest zapisywane jako zdarzenie SC_DECISION.
+    fn decide_sc<R: Rng + ?Sized>(&mut self, incident_idxs: &[usize], rng: &mut R) -> ScOutcome {
+        let s_track = self.cars_list[incident_idxs[0]].sh.get_s_tracks().1;
+        let location = self.get_incident_location(s_track);
+        let probability = if self.is_first_corner_pileup(incident_idxs) {
+            1.0
+        } else {
+            // rodzaj awarii pojedynczego auta decyduje, czy auto może bezpiecznie zjechać z toru
+            let failure_factor = match incident_idxs {
+                [idx] => self.retirements[*idx]
+                    .as_ref()
+                    .and_then(|retirement| retirement.failure)
+                    .map_or(1.0, |failure| failure.get_sc_factor()),
+                _ => 1.0,
+            };
+            (self.sc_deployment_pars.get_probability(location) * failure_factor).clamp(0.0, 1.0)
+        };
+
+        let outcome = if rng.gen::<f64>() < probability {
+            ScOutcome::Sc
+        } else if location == IncidentLocation::Corner {
+            ScOutcome::Vsc
+        } else {
+            ScOutcome::LocalYellow
+        };
+
+        if self.print_events {
+            println!("SC DECISION: {:?}, p = {:.2} -> {:?}", location, probability, outcome);
+        }
+        self.push_event(RaceEvent {
+            sc_decision: Some(ScDecision {
+                location,
+                probability,
+                outcome,
+            }),
+            ..RaceEvent::new(
+                "SC_DECISION",
+                self.cur_lap_leader,
+                self.cur_racetime,
+                incident_idxs.iter().map(|&i| self.cars_list[i].car_no).collect(),
+            )
+        });
+
+        outcome
+    }
+
+    /// is_first_corner_pileup zwraca true, jeśli wśród wycofanych aut jest auto, które odpadło na 1.
+    /// okrążeniu w karambolu kilku aut w pierwszym zakręcie.
+    fn is_first_corner_pileup(&self, incident_idxs: &[usize]) -> bool {
+        match &self.first_corner_incident {
+            Some(incident) if self.first_corner_incident_act && incident.idxs.len() >= 2 => {
+                incident_idxs.iter().any(|&i| {
+                    incident.idxs.contains(&i)
+                        && self.retirements[i].as_ref().is_some_and(|x| x.lap == 0)
+                })
+            }
+            _ => false,
+        }
+    }
 
-            let pit_this_lap = self.cars_list[car_idx].pit_this_lap(compl_lap_cur + 1);
+    /// handle_overtake_contact stosuje skutek kontaktu przy nieudanym ataku auta `idx_rear` na auto
+    /// `idx_front` w zakręcie i zapisuje zdarzenie z oboma autami (auto z przodu pierwsze, auto
+    /// z tyłu jest sprawcą). Auta po kontakcie lub obrocie stoją przez czas straty i są w tym
+    /// czasie omijane przez pozostałe auta. Wycofania są zapisywane jak każdy wypadek, więc trafiają
+    /// do decyzji o SC/VSC/czerwonej fladze.
+    fn handle_overtake_contact(
+        &mut self,
+        idx_front: usize,
+        idx_rear: usize,
+        outcome: OvertakeContactOutcome,
+    ) {
+        let cars = vec![self.cars_list[idx_front].car_no, self.cars_list[idx_rear].car_no];
+        let s_track = self.cars_list[idx_rear].sh.get_s_tracks().1;
+        if self.print_events { println!(
+            "CONTACT: Car {} attacked Car {} in a corner: {:?}",
+            cars[1], cars[0], outcome
+        ); }
+
+        let kind = match outcome {
+            OvertakeContactOutcome::TimeLoss { t_loss_front, t_loss_rear } => {
+                self.stop_car(idx_front, t_loss_front);
+                self.stop_car(idx_rear, t_loss_rear);
+                "Contact"
+            }
+            OvertakeContactOutcome::Spin { rear, t_loss } => {
+                self.stop_car(if rear { idx_rear } else { idx_front }, t_loss);
+                "Spin"
+            }
+            OvertakeContactOutcome::Dnf { rear, both } => {
+                let idxs = match (rear, both) {
+                    (_, true) => vec![idx_front, idx_rear],
+                    (true, false) => vec![idx_rear],
+                    (false, false) => vec![idx_front],
+                };
+                self.record_retirement(&idxs, RetirementCause::Crash, cars, self.cur_racetime);
+                for &idx in idxs.iter() {
+                    self.cur_laptimes[idx] = f64::INFINITY;
+                }
+                return;
+            }
+        };
 
-            self.cars_list[car_idx].sh.check_state_transition(
-                delta_ts[i],      
-                delta_ts[j],      
-                pit_this_lap,
+        self.push_event(RaceEvent {
+            s_range: Some([s_track, s_track]),
+            ..RaceEvent::new(kind, self.cur_lap_leader, self.cur_racetime, cars)
+        });
+    }
+
+    /// stop_car zatrzymuje auto na torze na podany czas (s), np. po obrocie. Postój wydłuża
+    /// ewentualny postój, który jeszcze trwa.
+    fn stop_car(&mut self, idx: usize, t_loss: f64) {
+        self.t_stopped_until[idx] = self.t_stopped_until[idx].max(self.cur_racetime) + t_loss;
+    }
+
+    /// add_damage zwiększa poziom uszkodzeń auta (auto jedzie dalej wolniej i szybciej zużywa
+    /// opony do naprawy w alei) i zapisuje zdarzenie `DAMAGE` z miejscem na torze.
+    fn add_damage(&mut self, idx: usize, damage: f64) {
+        let car = &mut self.cars_list[idx];
+        if damage <= 0.0 || car.status == CarStatus::DNF {
+            return;
+        }
+        car.add_damage(damage);
+
+        let car_no = car.car_no;
+        let s_track = car.sh.get_s_tracks().1;
+        self.push_event(RaceEvent {
+            s_range: Some([s_track, s_track]),
+            ..RaceEvent::new("DAMAGE", self.cur_lap_leader, self.cur_racetime, vec![car_no])
+        });
+    }
+
+    /// handle_driver_crashes losuje wypadki z winy kierowcy (DNF) na zielonej fladze.
+    /// Prawdopodobieństwo na okrążenie (zob. `calc_driver_crash_prob`) jest przeliczane na krok
+    /// czasu według bieżącego czasu okrążenia auta. Wypadek jest zapisywany jak każde wycofanie (zdarzenie `Crash` z
+    /// miejscem na torze) i trafia do decyzji o SC.
+    fn handle_driver_crashes(&mut self) {
+        if !matches!(self.flag_state, FlagState::G) || self.driver_crash_pars.probability <= 0.0 {
+            return;
+        }
+
+        let mut rng = self.fork_rng();
+
+        for i in 0..self.cars_list.len() {
+            let car = &self.cars_list[i];
+            let laptime = self.cur_laptimes[i];
+
+            if car.status == CarStatus::DNF
+                || self.race_finished[i]
+                || car.sh.pit_act
+                || self.cur_racetime <= self.t_stopped_until[i]
+                || !(laptime.is_finite() && laptime > 0.0)
+            {
+                continue;
+            }
+
+            let prob_lap = if self.model_toggles.is_enabled(ModelToggles::MISTAKES) {
+                calc_driver_crash_prob(
+                    car.driver.consistency,
+                    car.sh.corner_act,
+                    car.sh.duel_act,
+                    self.wetness,
+                    self.get_car_incident_factor(i),
+                    &self.driver_crash_pars,
+                )
+            } else {
+                0.0
+            };
+            let prob_step = 1.0 - (1.0 - prob_lap).powf(self.timestep_size / laptime);
+
+            if rng.gen::<f64>() < prob_step {
+                if self.print_events {
+                    println!("CRASH: Car {} crashed out (driver error)", car.car_no);
+                }
+                self.retire_cars(&[i], RetirementCause::Crash);
+            }
+        }
+    }
+
+    /// handle_punctures losuje przebicia opon jadących aut. Prawdopodobieństwo na okrążenie rośnie
+    /// z wiekiem opon ponad cliff mieszanki (zob. `calc_puncture_prob`, przeliczane na krok czasu
+    /// jak wypadki kierowców), dodatkowo każdy przejazd przez miejsce wypadku, dopóki leży tam gruz,
+    /// grozi przebiciem.
+    fn handle_punctures(&mut self) {
+        let cur_racetime = self.cur_racetime;
+        let debris_window_s = self.puncture_pars.debris_window_s;
+        self.puncture_debris
+            .retain(|&(_, time_s)| cur_racetime - time_s <= debris_window_s);
+
+        if self.puncture_pars.probability_over_cliff <= 0.0
+            && (self.puncture_pars.debris_probability <= 0.0 || self.puncture_debris.is_empty())
+        {
+            return;
+        }
+
+        let mut rng = self.fork_rng();
+        let mut idxs_punctured = Vec::new();
+
+        for i in 0..self.cars_list.len() {
+            let car = &self.cars_list[i];
+            let laptime = self.cur_laptimes[i];
+
+            if car.status == CarStatus::DNF
+                || self.race_finished[i]
+                || car.sh.pit_act
+                || car.is_punctured()
+                || self.cur_racetime <= self.t_stopped_until[i]
+                || !(laptime.is_finite() && laptime > 0.0)
+            {
+                continue;
+            }
+
+            // zużyte opony (bez modelu degradacji opony nie przekraczają cliffu)
+            let prob_lap = if self.model_toggles.is_enabled(ModelToggles::TIRE_DEGRADATION) {
+                calc_puncture_prob(
+                    car.get_tire_age_over_cliff(&self.tire_config),
+                    &self.puncture_pars,
+                )
+            } else {
+                0.0
+            };
+            let mut prob_intact = (1.0 - prob_lap).powf(self.timestep_size / laptime);
+
+            // gruz w miejscu wypadku (bez wypadku w bieżącym kroku)
+            for &(s_track, time_s) in self.puncture_debris.iter() {
+                if time_s < cur_racetime && car.sh.get_s_track_passed_this_step(s_track) {
+                    prob_intact *= 1.0 - self.puncture_pars.debris_probability;
+                }
+            }
+
+            if prob_intact < 1.0 && rng.gen::<f64>() >= prob_intact {
+                idxs_punctured.push(i);
+            }
+        }
+
+        for idx in idxs_punctured {
+            self.puncture_tire(idx);
+        }
+    }
+
+    /// puncture_tire przebija oponę auta i zapisuje zdarzenie PUNCTURE z miejscem na torze. Auto
+    /// dojeżdża do alei w tempie alei serwisowej (zob. `calc_cur_laptimes`) i zjeżdża na
+    /// najbliższym wjeździe do alei niezależnie od strategii. Montowany jest nowy komplet bieżącej
+    /// mieszanki albo mieszanki zastępczej (zob. `Car::get_puncture_compound`). Pit stop
+    /// zaplanowany już na to okrążenie pozostaje bez zmian.
+    fn puncture_tire(&mut self, idx: usize) {
+        let pit_entry = self.track.pit_zone[0];
+        let car = &mut self.cars_list[idx];
+        car.set_punctured();
+
+        // auto, które minęło już wjazd do alei, może zjechać dopiero na następnym okrążeniu
+        let s_track = car.sh.get_s_tracks().1;
+        let inlap = if s_track < pit_entry {
+            car.sh.get_compl_lap() + 1
+        } else {
+            car.sh.get_compl_lap() + 2
+        };
+
+        if !car.pit_this_lap(inlap) {
+            let compound = car.get_puncture_compound(&self.puncture_pars.fallback_compound);
+            car.schedule_weather_strategy(inlap, &compound);
+        }
+
+        let car_no = car.car_no;
+        if self.print_events {
+            println!("PUNCTURE: Car {} has a puncture and pits in lap {}", car_no, inlap);
+        }
+        self.push_event(RaceEvent {
+            s_range: Some([s_track, s_track]),
+            ..RaceEvent::new("PUNCTURE", self.cur_lap_leader, self.cur_racetime, vec![car_no])
+        });
+    }
+
+    /// get_puncture_laptime zwraca czas okrążenia auta z przebitą oponą (całe okrążenie z
+    /// ograniczeniem prędkości alei serwisowej).
+    fn get_puncture_laptime(&self) -> f64 {
+        self.track.length / self.track.pit_speedlimit
+    }
+
+    /// handle_first_corner_incident wykonuje wylosowany karambol, gdy pierwsze auto karambolu
+    /// wjeżdża w pierwszy zakręt. Auta, które odpadają, są wycofywane jednym zdarzeniem `Crash`
+    /// (z udziałem wszystkich aut karambolu), pozostałe stoją przez wylosowaną stratę (doliczaną
+    /// jak postój na starcie do straty startu) i jadą dalej z uszkodzeniami do końca wyścigu. Bez
+    /// wycofanych aut karambol jest zapisywany jako zdarzenie `Contact`.
+    fn handle_first_corner_incident(&mut self) {
+        if self.first_corner_incident_act {
+            return;
+        }
+
+        let incident = match &self.first_corner_incident {
+            Some(incident) => incident.clone(),
+            None => return,
+        };
+
+        let sh = &self.cars_list[incident.idxs[0]].sh;
+        if sh.get_compl_lap() == 0 && sh.get_s_tracks().1 < self.track.turn_1 {
+            return;
+        }
+
+        self.first_corner_incident_act = true;
+
+        let cars: Vec<u32> = incident
+            .idxs
+            .iter()
+            .map(|&i| self.cars_list[i].car_no)
+            .collect();
+        if self.print_events { println!("FIRST CORNER: Cars {:?} collided in Turn 1!", cars); }
+
+        let mut idxs_dnf = Vec::new();
+
+        for (&idx, t_loss) in incident.idxs.iter().zip(incident.t_losses.iter()) {
+            match t_loss {
+                Some(t_loss) => {
+                    // auto stoi (obrót, wyjazd poza tor), potem jedzie dalej z uszkodzeniami
+                    self.stop_car(idx, *t_loss);
+                    if self.damage_pars.t_loss > 0.0 {
+                        self.add_damage(idx, self.first_corner_damage_s / self.damage_pars.t_loss);
+                    }
+                    self.calc_th_laptime(idx);
+                }
+                None => idxs_dnf.push(idx),
+            }
+        }
+
+        if idxs_dnf.is_empty() {
+            self.push_event(RaceEvent::new(
+                "Contact",
+                self.cur_lap_leader,
+                self.cur_racetime,
+                cars,
+            ));
+        } else {
+            self.record_retirement(&idxs_dnf, RetirementCause::Crash, cars, self.cur_racetime);
+        }
+    }
+
+    /// enforce_vsc_mini_sectors mierzy czasy przejazdu minisektorów. Auto, które podczas VSC
+    /// przejechało minisektor szybciej niż w czasie docelowym (jego tempo VSC rozłożone wg mnożników
+    /// prędkości), oddaje różnicę w kolejnych krokach (`t_vsc_debts`). Dzięki temu każde auto traci
+    /// na okrążeniu za VSC tyle samo czasu, niezależnie od tego, gdzie jechało wolniej. Liczą się
+    /// tylko minisektory przejechane w całości podczas VSC i poza aleją serwisową.
+    fn enforce_vsc_mini_sectors(&mut self) {
+        let t_step_start = self.cur_racetime - self.timestep_size;
+        let t_vsc_start = match &self.cur_sc_period {
+            Some(sc_period) if sc_period.kind == ScPeriodKind::Vsc => Some(sc_period.t_start),
+            _ => None,
+        };
+        let laptimes_vsc: Vec<f64> =
+            (0..self.cars_list.len()).map(|i| self.calc_vsc_laptime(i)).collect();
+
+        for (i, car) in self.cars_list.iter_mut().enumerate() {
+            // czas jest mierzony zawsze, żeby znać czas wjazdu w minisektor na początku VSC
+            let (mini_sector_idx, t_entry, t_mini_sector) =
+                match car.sh.check_mini_sector_passed(t_step_start, self.timestep_size) {
+                    Some(x) => x,
+                    None => continue,
+                };
+            let t_giveback = std::mem::take(&mut self.t_vsc_givebacks[i]);
+
+            // czas okrążenia VSC rośnie po przejechaniu linii mety (zużycie opon), więc minisektor
+            // obejmujący zmianę okrążenia jest liczony z mniejszego z obu czasów
+            let laptime_vsc = laptimes_vsc[i].min(self.laptimes_vsc_mini_sector[i]);
+            self.laptimes_vsc_mini_sector[i] = laptimes_vsc[i];
+
+            let vsc_whole_mini_sector = matches!(t_vsc_start, Some(t) if t_entry >= t);
+            if !vsc_whole_mini_sector
+                || car.status == CarStatus::DNF
+                || car.sh.pit_act
+                || self.race_finished[i]
+            {
+                continue;
+            }
+
+            // krok na granicy odcinków o różnych mnożnikach prędkości jest liczony z mnożnikiem
+            // z początku kroku, więc minisektor bywa przejechany nieco szybciej albo wolniej niż w
+            // czasie docelowym - wolniejszy przejazd (bez czasu oddanego w nim celowo) pomniejsza
+            // dług, najwyżej o jeden krok, więc strata na okrążeniu zgadza się z tempem VSC
+            let t_target = laptime_vsc * self.vsc_mini_sectors[mini_sector_idx].laptime_frac;
+            self.t_vsc_debts[i] = (self.t_vsc_debts[i] + t_target - (t_mini_sector - t_giveback))
+                .max(-self.timestep_size);
+        }
+    }
+
+    /// open_sc_period rozpoczyna zapis okresu SC lub VSC (trwający okres, np. VSC zastąpiony przez
+    /// SC, jest wcześniej zamykany).
+    fn open_sc_period(&mut self, kind: ScPeriodKind, cause_car_no: Option<u32>) {
+        self.close_sc_period();
+        let lap = self.cur_lap_leader.min(self.get_finish_lap());
+        self.cur_sc_period = Some(ScPeriod {
+            kind,
+            start_lap: lap,
+            end_lap: lap,
+            t_start: self.cur_racetime,
+            t_end: self.cur_racetime,
+            cause_car_no,
+        });
+    }
+
+    /// close_sc_period kończy zapis trwającego okresu SC lub VSC w bieżącej chwili wyścigu.
+    fn close_sc_period(&mut self) {
+        if let Some(mut sc_period) = self.cur_sc_period.take() {
+            sc_period.end_lap = self.cur_lap_leader.min(self.get_finish_lap());
+            sc_period.t_end = self.cur_racetime;
+            self.sc_periods.push(sc_period);
+        }
+    }
+
+    /// get_sc_periods zwraca okresy SC i VSC do tej pory (trwający okres kończy się w bieżącej
+    /// chwili wyścigu).
+    pub fn get_sc_periods(&self) -> Vec<ScPeriod> {
+        let mut sc_periods = self.sc_periods.clone();
+
+        if let Some(mut sc_period) = self.cur_sc_period.clone() {
+            sc_period.end_lap = self.cur_lap_leader.min(self.get_finish_lap());
+            sc_period.t_end = self.cur_racetime;
+            sc_periods.push(sc_period);
+        }
+
+        sc_periods
+    }
+
+    /// get_first_corner_incident zwraca wylosowany karambol w pierwszym zakręcie (None - start bez
+    /// karambolu).
+    pub fn get_first_corner_incident(&self) -> Option<&FirstCornerIncident> {
+        self.first_corner_incident.as_ref()
+    }
+
+    /// deploy_vsc wypuszcza wirtualny samochód bezpieczeństwa (lub przedłuża trwający VSC).
+    fn deploy_vsc(&mut self, car_no: u32) {
+        if self.print_events {
+            println!("VIRTUAL SAFETY CAR DEPLOYED (Caused by car #{})", car_no);
+        }
+        // kolejny incydent podczas VSC przedłuża VSC
+        if !matches!(self.flag_state, FlagState::Vsc) {
+            self.open_sc_period(ScPeriodKind::Vsc, Some(car_no));
+            self.t_vsc_debts.iter_mut().for_each(|t_vsc_debt| *t_vsc_debt = 0.0);
+            self.t_vsc_givebacks.iter_mut().for_each(|t_vsc_giveback| *t_vsc_giveback = 0.0);
+            self.push_event(RaceEvent::new(
+                "VSC_DEPLOYED",
+                self.cur_lap_leader,
+                self.cur_racetime,
+                vec![car_no],
+            ));
+        }
+        self.set_flag_state(FlagState::Vsc);
+        self.vsc_timer = self.vsc_duration_s;
+        self.incident_queue.clear();
+    }
+
+    /// add_debris_zone tworzy odcinek z gruzem w miejscu kontaktu lub drobnego incydentu (cały
+    /// zakręt, jeśli miejsce leży w zakręcie, w przeciwnym razie 100 m przed i za nim) aktywny przez
+    /// `no_laps` okrążeń lidera. `cars` są puste, jeśli w incydencie nie brało udziału żadne auto.
+    pub fn add_debris_zone(&mut self, s_track: f64, cars: Vec<u32>, no_laps: u32) {
+        let s_range = match self
+            .track
+            .corners
+            .iter()
+            .find(|corner| is_in_s_range(s_track, **corner))
+        {
+            Some(corner) => *corner,
+            None => [
+                (s_track - 100.0).rem_euclid(self.track.length),
+                (s_track + 100.0).rem_euclid(self.track.length),
+            ],
+        };
+        let lap_cleared = self.cur_lap_leader + no_laps;
+
+        // gruz na tym samym odcinku - porządkowi potrzebują więcej czasu
+        if let Some(debris_zone) = self.debris_zones.iter_mut().find(|x| x.s_range == s_range) {
+            debris_zone.lap_cleared = debris_zone.lap_cleared.max(lap_cleared);
+            return;
+        }
+
+        if self.print_events {
+            println!(
+                "DEBRIS: Local yellow between s = {:.0}m and s = {:.0}m (cars {:?})",
+                s_range[0], s_range[1], cars
+            );
+        }
+        self.push_event(RaceEvent {
+            s_range: Some(s_range),
+            ..RaceEvent::new(
+                "DEBRIS_START",
+                self.cur_lap_leader,
+                self.cur_racetime,
+                cars.to_owned(),
+            )
+        });
+        self.debris_zones.push(DebrisZone {
+            s_range,
+            cars,
+            lap_cleared,
+        });
+    }
+
+    /// update_debris_zones usuwa gruz, którego czas minął, i zapisuje zdarzenie.
+    fn update_debris_zones(&mut self) {
+        let cur_lap_leader = self.cur_lap_leader;
+        let (cleared, active): (Vec<DebrisZone>, Vec<DebrisZone>) = self
+            .debris_zones
+            .drain(..)
+            .partition(|x| x.lap_cleared <= cur_lap_leader);
+        self.debris_zones = active;
+
+        for debris_zone in cleared {
+            if self.print_events {
+                println!(
+                    "DEBRIS CLEARED: s = {:.0}m - {:.0}m",
+                    debris_zone.s_range[0], debris_zone.s_range[1]
+                );
+            }
+            self.push_event(RaceEvent {
+                s_range: Some(debris_zone.s_range),
+                ..RaceEvent::new(
+                    "DEBRIS_END",
+                    self.cur_lap_leader,
+                    self.cur_racetime,
+                    debris_zone.cars,
+                )
+            });
+        }
+    }
+
+    /// draw_debris_incident losuje (z prawdopodobieństwem `debris_yellow_probability` na okrążenie
+    /// lidera) drobny incydent bez wycofania (gruz, żwir na torze) w losowym miejscu toru. Taki
+    /// incydent tworzy odcinek z gruzem (zob. `add_debris_zone`) na 1-2 okrążenia lidera
+    /// (`debris_yellow_no_laps`) i nigdy sam nie przechodzi w SC. Wywoływana na początku każdego
+    /// okrążenia lidera.
+    fn draw_debris_incident(&mut self) {
+        // podczas neutralizacji i restartu po SC tempo dyktuje lider - bez nowego incydentu
+        if !matches!(self.flag_state, FlagState::G)
+            || self.is_rolling_restart()
+            || self.debris_yellow_probability <= 0.0
+        {
+            return;
+        }
+
+        let mut rng = self.fork_rng();
+        if rng.gen::<f64>() >= self.debris_yellow_probability {
+            return;
+        }
+
+        let [no_laps_min, no_laps_max] = self.debris_yellow_no_laps;
+        let no_laps = rng.gen_range(no_laps_min.max(1)..=no_laps_max.max(no_laps_min).max(1));
+        let s_track = rng.gen::<f64>() * self.track.length;
+        self.add_debris_zone(s_track, vec![], no_laps);
+    }
+
+    /// is_in_debris_zone sprawdza, czy koordynat s leży na odcinku z gruzem.
+    fn is_in_debris_zone(&self, s_track: f64) -> bool {
+        self.debris_zones
+            .iter()
+            .any(|debris_zone| is_in_s_range(s_track, debris_zone.s_range))
+    }
+
+    /// get_debris_zones zwraca aktywne odcinki z gruzem (lokalne żółte flagi).
+    pub fn get_debris_zones(&self) -> &[DebrisZone] {
+        &self.debris_zones
+    }
+
+    /// add_yellow_zone tworzy lokalną żółtą flagę o długości `yellow_zone_length_m` (najwyżej pół
+    /// toru) wokół auta, które odpadło na torze. Obowiązuje przez `yellow_zone_no_laps` okrążeń
+    /// lidera.
+    fn add_yellow_zone(&mut self, idx: usize) {
+        if self.yellow_zone_length_m <= 0.0 || self.yellow_zone_no_laps == 0 {
+            return;
+        }
+
+        let s_track = self.cars_list[idx].sh.get_s_tracks().1;
+        let half_length = 0.5 * self.yellow_zone_length_m.min(0.5 * self.track.length);
+        let s_range = [
+            (s_track - half_length).rem_euclid(self.track.length),
+            (s_track + half_length).rem_euclid(self.track.length),
+        ];
+        let car_no = self.cars_list[idx].car_no;
+
+        if self.print_events {
+            println!(
+                "YELLOW FLAG: Car {} stopped, local yellow between s = {:.0}m and s = {:.0}m",
+                car_no, s_range[0], s_range[1]
             );
         }
+        self.push_event(RaceEvent {
+            s_range: Some(s_range),
+            ..RaceEvent::new("YELLOW_START", self.cur_lap_leader, self.cur_racetime, vec![car_no])
+        });
+        self.yellow_zones.push(YellowZone {
+            s_range,
+            car_no,
+            lap_cleared: self.cur_lap_leader + self.yellow_zone_no_laps,
+        });
+    }
+
+    /// update_yellow_zones usuwa żółte flagi wokół stojących aut, których czas minął, i zapisuje
+    /// zdarzenie.
+    fn update_yellow_zones(&mut self) {
+        let cur_lap_leader = self.cur_lap_leader;
+        let (cleared, active): (Vec<YellowZone>, Vec<YellowZone>) = self
+            .yellow_zones
+            .drain(..)
+            .partition(|x| x.lap_cleared <= cur_lap_leader);
+        self.yellow_zones = active;
+
+        for yellow_zone in cleared {
+            if self.print_events {
+                println!(
+                    "YELLOW FLAG CLEARED: s = {:.0}m - {:.0}m",
+                    yellow_zone.s_range[0], yellow_zone.s_range[1]
+                );
+            }
+            self.push_event(RaceEvent {
+                s_range: Some(yellow_zone.s_range),
+                ..RaceEvent::new(
+                    "YELLOW_END",
+                    self.cur_lap_leader,
+                    self.cur_racetime,
+                    vec![yellow_zone.car_no],
+                )
+            });
+        }
+    }
+
+    /// is_in_yellow_zone sprawdza, czy koordynat s leży na odcinku z żółtą flagą wokół stojącego
+    /// auta.
+    fn is_in_yellow_zone(&self, s_track: f64) -> bool {
+        self.yellow_zones
+            .iter()
+            .any(|yellow_zone| is_in_s_range(s_track, yellow_zone.s_range))
+    }
+
+    /// is_under_local_yellow sprawdza, czy na koordynacie s obowiązuje lokalna żółta flaga (gruz
+    /// albo stojące auto).
+    fn is_under_local_yellow(&self, s_track: f64) -> bool {
+        self.is_in_debris_zone(s_track) || self.is_in_yellow_zone(s_track)
+    }
+
+    /// get_yellow_zones zwraca aktywne żółte flagi wokół aut stojących na torze.
+    pub fn get_yellow_zones(&self) -> &[YellowZone] {
+        &self.yellow_zones
+    }
+
+    /// get_drs_trains zwraca aktualne pociągi DRS (numery aut w kolejności na torze).
+    pub fn get_drs_trains(&self) -> &[Vec<u32>] {
+        &self.drs_trains
     }
 
     // ---------------------------------------------------------------------------------------------
     // METHODS (HELPERS) ---------------------------------------------------------------------------
     // ---------------------------------------------------------------------------------------------
 
+    /// get_all_finished sprawdza, czy wszystkie jadące auta ukończyły wyścig (po szachownicy każde
+    /// auto kończy bieżące okrążenie, na auta wycofane się nie czeka).
     pub fn get_all_finished(&self) -> bool {
-        // Jeśli jest flaga szachownicy, kończymy natychmiast
-        if matches!(self.flag_state, FlagState::C) {
-            return true;
+        self.cars_list
+            .iter()
+            .zip(self.race_finished.iter())
+            .all(|(car, &finished)| finished || car.status == CarStatus::DNF)
+    }
+
+    /// simulate_until_leader_lap symuluje wyścig, aż lider ukończy podane okrążenie (lub wyścig się
+    /// zakończy). Stan wyścigu można potem sklonować i kontynuować wielokrotnie.
+    pub fn simulate_until_leader_lap(&mut self, lap: u32) {
+        while !self.get_all_finished() && self.cur_lap_leader <= lap {
+            self.simulate_timestep();
+        }
+    }
+
+    /// get_lap_context tworzy migawkę stanu wyścigu (tylko do odczytu) dla wywołania zwrotnego po
+    /// okrążeniu lidera.
+    pub fn get_lap_context(&self) -> LapContext {
+        let race_progs: Vec<f64> = self
+            .cars_list
+            .iter()
+            .map(|car| car.sh.get_race_prog())
+            .collect();
+        let idxs_sorted = argsort(&race_progs, SortOrder::Descending);
+        let race_prog_leader = idxs_sorted.first().map_or(0.0, |&idx| race_progs[idx]);
+
+        let cars = idxs_sorted
+            .iter()
+            .enumerate()
+            .map(|(pos, &idx)| {
+                let car = &self.cars_list[idx];
+                let compl_lap = car.sh.get_compl_lap();
+
+                CarLapInfo {
+                    car_no: car.car_no,
+                    position: pos as u32 + 1,
+                    delta_race_prog: race_prog_leader - race_progs[idx],
+                    compl_lap,
+                    compound: car.get_current_compound().to_owned(),
+                    tire_age: car.get_tire_age_cur_stint(),
+                    no_pitstops: car.no_pitstops,
+                    planned_inlaps: car
+                        .get_strategy()
+                        .iter()
+                        .skip(1)
+                        .map(|entry| entry.inlap)
+                        .filter(|&inlap| inlap >= compl_lap)
+                        .collect(),
+                    dnf: car.status == CarStatus::DNF,
+                }
+            })
+            .collect();
+
+        LapContext {
+            lap: self.cur_lap_leader.saturating_sub(1),
+            tot_no_laps: self.get_finish_lap(),
+            race_time: self.cur_racetime,
+            cars,
+            weather_is_rain: self.weather_state == WeatherState::Rain,
+            flag_state: self.get_most_severe_flag_state(),
+            drs_trains: self.drs_trains.clone(),
+        }
+    }
+
+    /// apply_strategy_command sprawdza i wykonuje zmianę strategii zleconą przez wywołanie zwrotne.
+    /// Zmienić można tylko pit stopy w przyszłych okrążeniach auta, które jest jeszcze w wyścigu.
+    pub fn apply_strategy_command(&mut self, command: &StrategyCommand) -> anyhow::Result<()> {
+        let (car_no, inlap, compound) = match command {
+            StrategyCommand::SchedulePit { car_no, inlap, compound }
+            | StrategyCommand::SetCompound { car_no, inlap, compound } => {
+                (*car_no, *inlap, Some(compound.as_str()))
+            }
+            StrategyCommand::CancelPit { car_no, inlap } => (*car_no, *inlap, None),
+        };
+
+        let idx = match self.get_car_idx(car_no) {
+            Some(x) => x,
+            None => anyhow::bail!("Car {} is not part of the race!", car_no),
+        };
+        let tot_no_laps = self.get_finish_lap();
+        let car = &mut self.cars_list[idx];
+        let compl_lap = car.sh.get_compl_lap();
+
+        if car.status == CarStatus::DNF || self.race_finished[idx] {
+            anyhow::bail!("Car {} is not running anymore!", car_no)
+        }
+        // pit stop w bieżącym okrążeniu mógłby już minąć wjazd do alei -> tylko kolejne okrążenia
+        if inlap <= compl_lap + 1 || inlap >= tot_no_laps {
+            anyhow::bail!(
+                "Inlap {} of car {} must be in the range [{}, {}]!",
+                inlap,
+                car_no,
+                compl_lap + 2,
+                tot_no_laps - 1
+            )
+        }
+        if let Some(compound) = compound {
+            if !COMPOUNDS.contains(&compound) {
+                anyhow::bail!("Unknown compound {}!", compound)
+            }
+        }
+
+        let planned = car.get_strategy().iter().skip(1).any(|entry| entry.inlap == inlap);
+
+        match command {
+            StrategyCommand::SchedulePit { .. } => {
+                if planned {
+                    anyhow::bail!("Car {} already pits in lap {}!", car_no, inlap)
+                }
+                car.schedule_weather_strategy(inlap, compound.unwrap());
+            }
+            StrategyCommand::SetCompound { .. } => {
+                if !planned {
+                    anyhow::bail!("Car {} has no pit stop planned in lap {}!", car_no, inlap)
+                }
+                car.schedule_weather_strategy(inlap, compound.unwrap());
+            }
+            StrategyCommand::CancelPit { .. } => {
+                if !car.cancel_pit(inlap) {
+                    anyhow::bail!("Car {} has no pit stop planned in lap {}!", car_no, inlap)
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// attach_tire_inventories przypisuje autom przydziały opon (z poprzedniej sesji weekendu)
+    /// i montuje komplety startowe. Musi być wywołana przed pierwszym krokiem symulacji. Auta bez
+    /// przydziału jeżdżą na kompletach spoza przydziału.
+    pub fn attach_tire_inventories(
+        &mut self,
+        inventories: Vec<TireInventory>,
+        session: &str,
+    ) -> anyhow::Result<()> {
+        for mut inventory in inventories.into_iter() {
+            let idx = self.get_car_idx(inventory.car_no).ok_or_else(|| {
+                anyhow::anyhow!("Car {} of the tire inventory is not in the race!", inventory.car_no)
+            })?;
+
+            inventory.session = session.to_owned();
+            self.cars_list[idx].attach_tire_inventory(inventory)?;
+        }
+
+        Ok(())
+    }
+
+    /// get_tire_inventories zwraca przydziały opon aut z aktualnym wiekiem zamontowanych kompletów
+    /// (do przekazania do następnej sesji).
+    pub fn get_tire_inventories(&self) -> Vec<TireInventory> {
+        self.cars_list
+            .iter()
+            .filter_map(|car| car.get_tire_inventory_snapshot(car.sh.get_compl_lap()))
+            .collect()
+    }
+
+    /// get_t_pitlane zwraca łączny czas w alei serwisowej każdego auta.
+    pub fn get_t_pitlane(&self) -> &[f64] {
+        &self.t_pitlane
+    }
+
+    /// get_pit_stops zwraca dotychczas wykonane postoje w alei serwisowej.
+    pub fn get_pit_stops(&self) -> &[PitStopInfo] {
+        &self.pit_stops
+    }
+
+    /// get_retirements zwraca miejsce i czas wycofania każdego auta (None, jeśli auto jedzie).
+    pub fn get_retirements(&self) -> &[Option<RetirementInfo>] {
+        &self.retirements
+    }
+
+    /// get_retirement_reason zwraca rodzaj zdarzenia, które spowodowało wycofanie auta (pierwsze
+    /// zdarzenie "Crash", awarii (np. "ENGINE_FAILURE") lub "Retirement" z jego udziałem).
+    pub fn get_retirement_reason(&self, car_no: u32) -> Option<&str> {
+        self.events
+            .iter()
+            .find(|event| is_retirement_event_kind(&event.kind) && event.cars.contains(&car_no))
+            .map(|event| event.kind.as_str())
+    }
+
+    /// get_car_idx zwraca indeks bolidu o podanym numerze.
+    pub fn get_car_idx(&self, car_no: u32) -> Option<usize> {
+        self.cars_list.iter().position(|car| car.car_no == car_no)
+    }
+
+    /// reseed ustawia nowe ziarno losowań wyścigu i wszystkich bolidów. Dotychczasowy przebieg
+    /// wyścigu pozostaje bez zmian, dalszy przebieg zależy tylko od nowego ziarna (np. kontynuacje
+    /// wyścigu wznowionego z punktu kontrolnego).
+    pub fn reseed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+        for car in self.cars_list.iter_mut() {
+            car.seed_rng(self.rng.gen());
+        }
+    }
+
+    /// fork_rng zwraca generator z ziarnem wylosowanym z generatora wyścigu (losowania w metodach,
+    /// które jednocześnie zmieniają stan wyścigu).
+    fn fork_rng(&mut self) -> StdRng {
+        StdRng::seed_from_u64(self.rng.gen())
+    }
+
+    /// subscribe rejestruje obserwatora zdarzeń wyścigu. Obserwator dostaje każde zdarzenie
+    /// zapisane od chwili subskrypcji dokładnie raz i w kolejności zapisu.
+    pub fn subscribe(&mut self, observer: EventObserver) {
+        self.event_subscribers.observers.push(observer);
+    }
+
+    /// subscribe_with_replay rejestruje obserwatora jak `subscribe`, ale najpierw przekazuje mu
+    /// zdarzenia zapisane przed subskrypcją (np. kary na polach startowych), więc obserwator widzi
+    /// wszystkie zdarzenia wyścigu.
+    pub fn subscribe_with_replay(&mut self, mut observer: EventObserver) {
+        for event in self.events.iter() {
+            observer(event);
+        }
+        self.event_subscribers.observers.push(observer);
+    }
+
+    /// push_event zapisuje zdarzenie i synchronicznie powiadamia o nim obserwatorów (po zapisie,
+    /// więc panika obserwatora nie gubi zdarzenia).
+    fn push_event(&mut self, event: RaceEvent) {
+        self.events.push(event);
+        if let Some(event) = self.events.last() {
+            self.event_subscribers.notify(event);
         }
-        self.race_finished.iter().all(|&x| x)
     }
 
     pub fn get_race_result(&self) -> RaceResult {
-        RaceResult {
+        let mut race_result = RaceResult {
             tot_no_laps: self.tot_no_laps,
             car_driver_pairs: self
                 .cars_list
@@ -1096,11 +6034,89 @@ impl Race {
             sc_position: self.safety_car.s_track,
             weather_history: self.weather_history_log.clone(),
             events: self.events.clone(),
+            pit_stops: self.pit_stops.clone(),
+            no_pitstops: self.cars_list.iter().map(|car| car.no_pitstops).collect(),
+            t_pitlane: self.t_pitlane.clone(),
+            tire_inventories: self.get_tire_inventories(),
+            lap_flags: self.lap_flags.clone(),
+            // nieprawidłowe kolory są odrzucane przy walidacji, tu zostaje tylko kolor zastępczy
+            car_colors: self
+                .cars_list
+                .iter()
+                .map(|car| {
+                    resolve_car_color(&car.color, car.car_no)
+                        .map(|color| color.rgb)
+                        .unwrap_or_else(|_| get_fallback_color(car.car_no))
+                })
+                .collect(),
+            track_length: self.track.length,
+            retirements: self.retirements.clone(),
+            time_losses: self.time_losses.clone(),
+            unexecuted_stops: self.get_unexecuted_stops(),
+            no_laps_completed: self
+                .cars_list
+                .iter()
+                .map(|car| car.sh.get_compl_lap().min(self.tot_no_laps))
+                .collect(),
+            sc_periods: self.get_sc_periods(),
+            engine_modes: self.engine_modes.clone(),
+            compounds: self.compounds.clone(),
+            starting_grid: self.starting_grid.clone(),
+            t_cars: self.cars_list.iter().map(|car| car.get_t_car()).collect(),
+            statuses: Vec::new(),
+        };
+
+        // kontrola po wyścigu: zdyskwalifikowane auta zachowują czasy okrążeń, ale trafiają na
+        // koniec klasyfikacji
+        let mut dsq_idxs = vec![];
+        for (idx, kind) in self.get_post_race_dsqs() {
+            let lap = race_result.get_last_driven_lap(idx);
+            let time_s = race_result.get_racetime(idx, lap);
+            race_result.events.push(RaceEvent::new(
+                kind,
+                lap as u32,
+                time_s,
+                vec![self.cars_list[idx].car_no],
+            ));
+            dsq_idxs.push(idx);
         }
+        race_result.calc_statuses(&dsq_idxs);
+
+        // zdarzenia w kolejności czasu wyścigu, wzbogacone o kolejność przed i po (na podstawie
+        // macierzy czasów wyścigu)
+        race_result
+            .events
+            .sort_by(|a, b| a.time_s.partial_cmp(&b.time_s).unwrap_or(std::cmp::Ordering::Equal));
+        race_result.add_event_contexts();
+        race_result
+    }
+
+    /// get_post_race_dsqs zwraca auta zdyskwalifikowane w kontroli po wyścigu wraz z rodzajem
+    /// zdarzenia: DSQ_FUEL_EVENT_KIND (na mecie zostało mniej paliwa niż wymagana rezerwa) lub
+    /// DSQ_TECHNICAL_EVENT_KIND (wylosowane naruszenie techniczne). Kontrolowane są tylko auta,
+    /// które dojechały do mety.
+    fn get_post_race_dsqs(&self) -> Vec<(usize, &'static str)> {
+        (0..self.cars_list.len())
+            .filter(|&idx| self.race_finished[idx] && self.cars_list[idx].status != CarStatus::DNF)
+            .filter_map(|idx| {
+                if self.cars_list[idx].is_below_fuel_reserve() {
+                    Some((idx, DSQ_FUEL_EVENT_KIND))
+                } else if self.dsq_technical[idx] {
+                    Some((idx, DSQ_TECHNICAL_EVENT_KIND))
+                } else {
+                    None
+                }
+            })
+            .collect()
     }
     
     fn get_idx_list_sorted_by_biggest_gap(&self) -> Vec<usize> {
-        let mut idx_list_sorted = self.get_car_order_on_track();
+        let mut idx_list_sorted = self.get_interacting_car_order_on_track();
+
+        if idx_list_sorted.len() < 2 {
+            return idx_list_sorted;
+        }
+
         let car_pair_idxs_list = self.get_car_pair_idxs_list(&idx_list_sorted, false);
 
         let delta_lap_fracs: Vec<f64> = car_pair_idxs_list
@@ -1108,14 +6124,52 @@ impl Race {
             .map(|x| self.calc_projected_delta_lap_frac(x[0], x[1], 0.0))
             .collect();
 
-        let pair_idx_biggest_gap = argmax(&delta_lap_fracs);
-        let start_idx = (pair_idx_biggest_gap + 1) % self.cars_list.len();
+        // bez porównywalnych odstępów (np. same NaN) pozostaje kolejność na torze
+        let pair_idx_biggest_gap = match argmax(&delta_lap_fracs) {
+            Some(idx) => idx,
+            None => return idx_list_sorted,
+        };
+        let start_idx = (pair_idx_biggest_gap + 1) % idx_list_sorted.len();
         idx_list_sorted.rotate_left(start_idx);
 
         idx_list_sorted
     }
 
+    /// is_car_interacting zwraca true, jeśli auto bierze udział w interakcjach z innymi autami
+    /// (odstępy, brudne powietrze, blokowanie, pojedynki). Auta z DNF, stojące w boksie lub z
+    /// nieskończonym czasem okrążenia są pomijane, bo ich odstępy byłyby nieskończone lub NaN.
+    /// Auta stojące jeszcze na polu startowym oraz auta z przebitą oponą są omijane przez auta z
+    /// tyłu.
+    fn is_car_interacting(&self, idx: usize) -> bool {
+        self.cars_list[idx].status != CarStatus::DNF
+            && !self.cars_list[idx].sh.pit_standstill_act
+            && !self.cars_list[idx].is_punctured()
+            && self.cur_laptimes[idx].is_finite()
+            && !self.is_stopped(idx)
+    }
+
+    /// is_stopped zwraca true, jeśli auto w bieżącym kroku stoi na torze: jeszcze nie ruszyło ze
+    /// startu albo stoi po obrocie lub kontakcie (zob. `stop_car`).
+    fn is_stopped(&self, idx: usize) -> bool {
+        self.cur_racetime <= self.t_stopped_until[idx]
+    }
+
+    /// get_car_order_on_track zwraca indeksy aut jadących po torze (bez DNF i aut w alei
+    /// serwisowej) posortowane malejąco wg postępu wyścigu, tj. w kolejności kolejki za SC.
     fn get_car_order_on_track(&self) -> Vec<usize> {
+        let race_progs: Vec<f64> =
+            self.cars_list.iter().map(|car| car.sh.get_race_prog()).collect();
+
+        argsort(&race_progs, SortOrder::Descending)
+            .into_iter()
+            .filter(|&idx| self.cars_list[idx].status != CarStatus::DNF)
+            .filter(|&idx| !self.cars_list[idx].sh.pit_act)
+            .collect()
+    }
+
+    /// get_interacting_car_order_on_track zwraca indeksy aut biorących udział w interakcjach
+    /// posortowane malejąco wg pozycji na okrążeniu.
+    fn get_interacting_car_order_on_track(&self) -> Vec<usize> {
         let s_tracks_cur: Vec<f64> = self
             .cars_list
             .iter()
@@ -1123,6 +6177,9 @@ impl Race {
             .collect();
 
         argsort(&s_tracks_cur, SortOrder::Descending)
+            .into_iter()
+            .filter(|&idx| self.is_car_interacting(idx))
+            .collect()
     }
 
     pub fn calc_projected_delta_t(
@@ -1171,7 +6228,7 @@ impl Race {
         }
 
         if del_last_pair {
-            car_pair_idxs_list.remove(car_pair_idxs_list.len() - 1);
+            car_pair_idxs_list.pop();
         }
 
         car_pair_idxs_list
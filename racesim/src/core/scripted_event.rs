@@ -0,0 +1,194 @@
+use serde::{Deserialize, Serialize};
+
+fn default_override_stochastic() -> bool {
+    true
+}
+
+/// ScriptedAction is the happening of a scripted event.
+/// * `Weather` - Weather change to `weather` (Rain or Dry)
+/// * `Dnf` - Retirement of car `car_no`
+/// * `SafetyCar` - Safety car for `no_laps` laps of the leader (it comes in at the end of the last
+///   one)
+/// * `Penalty` - Time penalty of `t_penalty` seconds added to the race time of car `car_no`
+/// * `TrackLimits` - Track limits strike of car `car_no` (no time loss, counted for the penalty
+///   points of a season)
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(tag = "type")]
+pub enum ScriptedAction {
+    Weather { weather: String },
+    Dnf { car_no: u32 },
+    SafetyCar { no_laps: u32 },
+    Penalty { car_no: u32, t_penalty: f64 },
+    TrackLimits { car_no: u32 },
+}
+
+/// ScriptedEvent is a happening that is applied deterministically at the start of a leader lap,
+/// regardless of the stochastic models.
+/// * `lap` - Leader lap in which the event is applied (1-based, a DNF in lap 0 withdraws the car
+///   right after the start)
+/// * `action` - Scripted happening
+/// * `override_stochastic` - If true, the stochastic model of the category (random weather changes,
+///   failures and collisions, safety car deployments after retirements) is disabled for the whole
+///   race to avoid double events
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct ScriptedEvent {
+    pub lap: u32,
+    #[serde(flatten)]
+    pub action: ScriptedAction,
+    #[serde(rename = "override", default = "default_override_stochastic")]
+    pub override_stochastic: bool,
+}
+
+impl ScriptedEvent {
+    /// get_car_no returns the car number of car-specific events.
+    pub fn get_car_no(&self) -> Option<u32> {
+        match self.action {
+            ScriptedAction::Dnf { car_no }
+            | ScriptedAction::Penalty { car_no, .. }
+            | ScriptedAction::TrackLimits { car_no } => Some(car_no),
+            _ => None,
+        }
+    }
+}
+
+/// ScriptedOverrides contains the stochastic models that are disabled by scripted events.
+/// * `weather` - Random weather changes
+/// * `dnf` - Random failures and collisions
+/// * `safety_car` - Safety car deployments after retirements
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ScriptedOverrides {
+    pub weather: bool,
+    pub dnf: bool,
+    pub safety_car: bool,
+}
+
+impl ScriptedOverrides {
+    /// from_events determines the disabled stochastic models from the scripted events.
+    pub fn from_events(scripted_events: &[ScriptedEvent]) -> ScriptedOverrides {
+        let mut overrides = ScriptedOverrides::default();
+
+        for scripted_event in scripted_events.iter().filter(|x| x.override_stochastic) {
+            match scripted_event.action {
+                ScriptedAction::Weather { .. } => overrides.weather = true,
+                ScriptedAction::Dnf { .. } => overrides.dnf = true,
+                ScriptedAction::SafetyCar { .. } => overrides.safety_car = true,
+                ScriptedAction::Penalty { .. } | ScriptedAction::TrackLimits { .. } => {}
+            }
+        }
+
+        overrides
+    }
+}
+
+/// validate_scripted_events checks that the scripted events are within the race and its
+/// participants and that they do not conflict with each other. The returned list contains one
+/// message per problem found.
+pub fn validate_scripted_events(
+    scripted_events: &[ScriptedEvent],
+    tot_no_laps: u32,
+    participants: &[u32],
+    initial_weather: &str,
+) -> Vec<String> {
+    let mut errors = vec![];
+
+    for scripted_event in scripted_events.iter() {
+        // cars can be withdrawn in lap 0, all other events happen during the race
+        let lap_min = match scripted_event.action {
+            ScriptedAction::Dnf { .. } => 0,
+            _ => 1,
+        };
+
+        if scripted_event.lap < lap_min || scripted_event.lap > tot_no_laps {
+            errors.push(format!(
+                "lap {} is not within the race length [{}, {}]",
+                scripted_event.lap, lap_min, tot_no_laps
+            ));
+        }
+
+        if let Some(car_no) = scripted_event.get_car_no() {
+            if !participants.contains(&car_no) {
+                errors.push(format!("car {} in lap {} is not a participant", car_no, scripted_event.lap));
+            }
+        }
+
+        match &scripted_event.action {
+            ScriptedAction::Weather { weather } if weather != "Rain" && weather != "Dry" => {
+                errors.push(format!("unknown weather '{}' in lap {}", weather, scripted_event.lap))
+            }
+            ScriptedAction::SafetyCar { no_laps } if *no_laps == 0 => errors.push(format!(
+                "safety car in lap {} must last at least one lap",
+                scripted_event.lap
+            )),
+            ScriptedAction::Penalty { t_penalty, .. } if *t_penalty <= 0.0 => errors.push(format!(
+                "penalty in lap {} must be positive",
+                scripted_event.lap
+            )),
+            _ => {}
+        }
+    }
+
+    let mut sorted: Vec<&ScriptedEvent> = scripted_events.iter().collect();
+    sorted.sort_by_key(|x| x.lap);
+
+    // weather changes must alternate, starting from the initial weather
+    let mut weather_cur = initial_weather;
+    let mut lap_last_change = None;
+
+    for scripted_event in sorted.iter() {
+        if let ScriptedAction::Weather { weather } = &scripted_event.action {
+            if lap_last_change == Some(scripted_event.lap) {
+                errors.push(format!("more than one weather change in lap {}", scripted_event.lap));
+            } else if weather == weather_cur {
+                errors.push(format!(
+                    "weather change in lap {} does not change the weather ({})",
+                    scripted_event.lap, weather
+                ));
+            }
+            weather_cur = weather.as_str();
+            lap_last_change = Some(scripted_event.lap);
+        }
+    }
+
+    // safety car periods must not overlap
+    let mut lap_sc_over: Option<u32> = None;
+
+    for scripted_event in sorted.iter() {
+        if let ScriptedAction::SafetyCar { no_laps } = scripted_event.action {
+            if matches!(lap_sc_over, Some(lap_sc_over) if scripted_event.lap < lap_sc_over) {
+                errors.push(format!(
+                    "safety car in lap {} overlaps the previous safety car",
+                    scripted_event.lap
+                ));
+            }
+            lap_sc_over = Some(scripted_event.lap + no_laps);
+        }
+    }
+
+    // a car can retire once and cannot be penalized afterwards
+    for &car_no in participants.iter() {
+        let laps_dnf: Vec<u32> = sorted
+            .iter()
+            .filter(|x| x.action == ScriptedAction::Dnf { car_no })
+            .map(|x| x.lap)
+            .collect();
+
+        if laps_dnf.len() > 1 {
+            errors.push(format!("car {} retires more than once", car_no));
+        }
+
+        if let Some(&lap_dnf) = laps_dnf.first() {
+            for scripted_event in sorted.iter() {
+                if let ScriptedAction::Penalty { car_no: car_no_penalty, .. } = scripted_event.action {
+                    if car_no_penalty == car_no && scripted_event.lap > lap_dnf {
+                        errors.push(format!(
+                            "car {} is penalized in lap {} after its retirement in lap {}",
+                            car_no, scripted_event.lap, lap_dnf
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    errors
+}
@@ -0,0 +1,886 @@
+use crate::core::driver::DriverPars;
+use crate::core::handle_race::{run_race_on_track, run_race_with_options, RunOptions};
+use crate::core::race::SimConstants;
+use crate::core::tireset::TireConfig;
+use crate::core::track::Track;
+use crate::post::output_fmt::OutputFormat;
+use crate::post::race_result::{write_output_file, ClassificationStatus, RaceResult};
+use crate::pre::read_sim_pars::{read_sim_pars_flexible, SimPars};
+use anyhow::Context;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::fs::OpenOptions;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+fn default_points() -> Vec<u32> {
+    vec![25, 18, 15, 12, 10, 8, 6, 4, 2, 1]
+}
+
+fn default_fastest_lap_max_pos() -> u32 {
+    10
+}
+
+fn default_predictor_final_rounds() -> u32 {
+    3
+}
+
+fn default_predictor_no_runs() -> u32 {
+    20
+}
+
+fn default_ban_threshold() -> u32 {
+    12
+}
+
+fn default_track_limit_strikes() -> u32 {
+    3
+}
+
+fn default_track_limit_points() -> u32 {
+    1
+}
+
+fn default_event_points() -> BTreeMap<String, u32> {
+    [("Crash", 3), ("Contact", 1), ("Penalty", 2)]
+        .iter()
+        .map(|&(kind, points)| (kind.to_string(), points))
+        .collect()
+}
+
+/// PenaltyPointsPars contains the parameters of the penalty points (license) system.
+/// * `ban_threshold` - Number of penalty points that leads to a ban for the next round
+/// * `event_points` - Penalty points per event kind (e.g. "Crash"), given to the car that caused
+///   the incident (see `get_causing_car`)
+/// * `track_limit_strikes` - Number of track limits strikes of a driver in a round that lead to
+///   penalty points (every further multiple counts again, 0 disables them)
+/// * `track_limit_points` - Penalty points for `track_limit_strikes` strikes
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PenaltyPointsPars {
+    #[serde(default = "default_ban_threshold")]
+    pub ban_threshold: u32,
+    #[serde(default = "default_event_points")]
+    pub event_points: BTreeMap<String, u32>,
+    #[serde(default = "default_track_limit_strikes")]
+    pub track_limit_strikes: u32,
+    #[serde(default = "default_track_limit_points")]
+    pub track_limit_points: u32,
+}
+
+impl Default for PenaltyPointsPars {
+    fn default() -> Self {
+        PenaltyPointsPars {
+            ban_threshold: default_ban_threshold(),
+            event_points: default_event_points(),
+            track_limit_strikes: default_track_limit_strikes(),
+            track_limit_points: default_track_limit_points(),
+        }
+    }
+}
+
+/// TitlePredictorPars contains the parameters of the championship predictor.
+/// * `final_rounds` - Number of final rounds before which the clinch scenarios and the title
+///   probabilities are reported
+/// * `no_runs` - Number of Monte Carlo simulations of the remaining rounds (kept small, every run
+///   simulates all remaining rounds)
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct TitlePredictorPars {
+    #[serde(default = "default_predictor_final_rounds")]
+    pub final_rounds: u32,
+    #[serde(default = "default_predictor_no_runs")]
+    pub no_runs: u32,
+}
+
+/// CarUpgrade contains an upgrade step of a car.
+/// * `round` - Round from which on the upgrade is used (1-based)
+/// * `t_gain` - (s) Reduction of `t_car` by the upgrade
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct CarUpgrade {
+    pub round: u32,
+    pub t_gain: f64,
+}
+
+/// CarDevelopment contains the development of a car over the season. It is applied to the `t_car`
+/// given in the parameter file of each round, i.e. nothing changes within a race.
+/// * `development_rate` - (s/round) Continuous reduction of `t_car` per round after the first
+///   round (negative values make the car slower)
+/// * `upgrades` - Upgrade steps at specified rounds
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+pub struct CarDevelopment {
+    #[serde(default)]
+    pub development_rate: f64,
+    #[serde(default)]
+    pub upgrades: Vec<CarUpgrade>,
+}
+
+impl CarDevelopment {
+    /// get_t_car returns the effective `t_car` in the given round (1-based) on the basis of the
+    /// `t_car` given in the parameter file of the round.
+    pub fn get_t_car(&self, t_car_base: f64, round: u32) -> f64 {
+        let t_gain_upgrades: f64 = self
+            .upgrades
+            .iter()
+            .filter(|upgrade| upgrade.round <= round)
+            .map(|upgrade| upgrade.t_gain)
+            .sum();
+
+        t_car_base - self.development_rate * round.saturating_sub(1) as f64 - t_gain_upgrades
+    }
+}
+
+/// SeasonPars contains the parameters of a season.
+/// * `rounds` - Paths to the parameter files of the rounds in their order (relative to the season
+///   file)
+/// * `points` - Championship points for the finishing positions (P1, P2, ...), retired cars do not
+///   score
+/// * `fastest_lap_points` - Championship points for the fastest lap of a round (0 disables them)
+/// * `fastest_lap_max_pos` - Worst finishing position that still receives the fastest lap points
+/// * `reserve_driver` - Driver replacing a banned driver (OPTIONAL: a ban without a reserve driver
+///   is an error)
+/// * `penalty_points` - Parameters of the penalty points system
+/// * `title_predictor` - Parameters of the championship predictor (OPTIONAL: no predictions if
+///   not set)
+/// * `car_development` - Development of the cars over the season (car number -> development,
+///   OPTIONAL: cars without entry keep the `t_car` of the parameter files)
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SeasonPars {
+    pub rounds: Vec<PathBuf>,
+    #[serde(default = "default_points")]
+    pub points: Vec<u32>,
+    #[serde(default)]
+    pub fastest_lap_points: u32,
+    #[serde(default = "default_fastest_lap_max_pos")]
+    pub fastest_lap_max_pos: u32,
+    #[serde(default)]
+    pub reserve_driver: Option<DriverPars>,
+    #[serde(default)]
+    pub penalty_points: PenaltyPointsPars,
+    #[serde(default)]
+    pub title_predictor: Option<TitlePredictorPars>,
+    #[serde(default)]
+    pub car_development: BTreeMap<u32, CarDevelopment>,
+}
+
+/// ClinchCondition contains the result a rival must not exceed for a clinch.
+/// * `rival` - Initials of the rival
+/// * `min_pos` - Best position the rival may finish in (positions beyond the points positions
+///   stand for finishing outside the points)
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct ClinchCondition {
+    pub rival: String,
+    pub min_pos: u32,
+}
+
+/// ClinchScenario contains a result with which a driver clinches the title in the next round.
+/// * `driver` - Initials of the driver
+/// * `position` - Finishing position of the driver (positions beyond the points positions stand
+///   for finishing outside the points)
+/// * `fastest_lap` - Whether the driver has to score the fastest lap points as well
+/// * `conditions` - Results the rivals must not exceed (empty if the title is clinched regardless
+///   of the rivals)
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct ClinchScenario {
+    pub driver: String,
+    pub position: u32,
+    pub fastest_lap: bool,
+    pub conditions: Vec<ClinchCondition>,
+}
+
+impl SeasonPars {
+    /// get_position_points returns the championship points for a finishing position (1-based, 0
+    /// for positions outside the points).
+    pub fn get_position_points(&self, position: u32) -> u32 {
+        match position {
+            0 => 0,
+            _ => self.points.get(position as usize - 1).copied().unwrap_or(0),
+        }
+    }
+
+    /// apply_car_development sets the effective `t_car` of the developed cars in the parameters of
+    /// the given round (1-based).
+    pub fn apply_car_development(&self, round: u32, sim_pars: &mut SimPars) {
+        for (car_no, development) in self.car_development.iter() {
+            if let Some(car_pars) = sim_pars.car_pars_all.get_mut(car_no) {
+                car_pars.t_car = development.get_t_car(car_pars.t_car, round);
+            }
+        }
+    }
+
+    /// get_max_round_points returns the maximum number of championship points of a round.
+    pub fn get_max_round_points(&self) -> u32 {
+        self.get_position_points(1) + self.fastest_lap_points
+    }
+
+    /// get_contenders returns the drivers that can still win the title with the given number of
+    /// rounds left (i.e. can at least equal the points of the leader).
+    pub fn get_contenders(
+        &self,
+        championship_points: &BTreeMap<String, u32>,
+        no_rounds_left: u32,
+    ) -> Vec<String> {
+        let points_leader = championship_points.values().copied().max().unwrap_or(0);
+        let points_max_left = no_rounds_left * self.get_max_round_points();
+
+        SeasonState::get_standings(championship_points)
+            .iter()
+            .filter(|(_, points)| points + points_max_left >= points_leader)
+            .map(|(initials, _)| initials.to_string())
+            .collect()
+    }
+
+    /// get_clinch_scenarios returns the results with which a driver clinches the title in the next
+    /// round, given the championship points before the round and the number of rounds left
+    /// (including the next round). For every finishing position of the driver (with and without
+    /// the fastest lap), the best positions of the rivals are determined such that no rival can
+    /// reach the points of the driver anymore, even when scoring the maximum in all later rounds.
+    /// Rivals are assumed to take the fastest lap whenever the driver does not, equal points count
+    /// as not clinched (no countback).
+    pub fn get_clinch_scenarios(
+        &self,
+        championship_points: &BTreeMap<String, u32>,
+        no_rounds_left: u32,
+    ) -> Vec<ClinchScenario> {
+        if no_rounds_left == 0 {
+            return vec![];
+        }
+
+        // positions beyond the points positions stand for finishing outside the points
+        let pos_no_points = self.points.len() as u32 + 1;
+        let points_later = (no_rounds_left - 1) * self.get_max_round_points();
+        let get_fastest_lap_points = |position: u32, fastest_lap: bool| {
+            if fastest_lap && position <= self.fastest_lap_max_pos {
+                self.fastest_lap_points
+            } else {
+                0
+            }
+        };
+        let mut clinch_scenarios = vec![];
+
+        for driver in self.get_contenders(championship_points, no_rounds_left).iter() {
+            let points_driver = championship_points[driver];
+
+            for position in 1..pos_no_points + 1 {
+                for &fastest_lap in [false, true].iter() {
+                    let points_fastest_lap = get_fastest_lap_points(position, fastest_lap);
+                    if fastest_lap && points_fastest_lap == 0 {
+                        continue;
+                    }
+                    let points_after =
+                        points_driver + self.get_position_points(position) + points_fastest_lap;
+
+                    let mut conditions = vec![];
+                    let mut feasible = true;
+
+                    for (rival, &points_rival) in championship_points.iter() {
+                        if rival == driver {
+                            continue;
+                        }
+
+                        // positions the rival can finish in (the position of the driver is taken,
+                        // except outside the points)
+                        let positions_rival: Vec<u32> = (1..pos_no_points + 1)
+                            .filter(|&x| x != position || x == pos_no_points)
+                            .collect();
+                        let min_pos = positions_rival.iter().copied().find(|&x| {
+                            points_rival
+                                + self.get_position_points(x)
+                                + get_fastest_lap_points(x, !fastest_lap)
+                                + points_later
+                                < points_after
+                        });
+
+                        match min_pos {
+                            Some(x) if x == positions_rival[0] => {}
+                            Some(x) => conditions.push(ClinchCondition {
+                                rival: rival.to_owned(),
+                                min_pos: x,
+                            }),
+                            None => {
+                                feasible = false;
+                                break;
+                            }
+                        }
+                    }
+
+                    if feasible {
+                        clinch_scenarios.push(ClinchScenario {
+                            driver: driver.to_owned(),
+                            position,
+                            fastest_lap,
+                            conditions,
+                        });
+                    }
+                }
+            }
+        }
+
+        clinch_scenarios
+    }
+}
+
+/// TitleOutlook contains the outlook on the championship before a round.
+/// * `round` - Round the outlook refers to (1-based)
+/// * `contenders` - Drivers that can still win the title
+/// * `clinch_scenarios` - Results with which a contender clinches the title in this round
+/// * `probabilities` - Probability of every driver to win the title, estimated by simulating the
+///   remaining rounds
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct TitleOutlook {
+    pub round: u32,
+    pub contenders: Vec<String>,
+    pub clinch_scenarios: Vec<ClinchScenario>,
+    pub probabilities: BTreeMap<String, f64>,
+}
+
+impl TitleOutlook {
+    /// format creates the outlook text: title probabilities and clinch scenarios. Finishing
+    /// positions that lead to the same conditions are only listed with the worst position.
+    pub fn format(
+        &self,
+        season_pars: &SeasonPars,
+        out_fmt: &OutputFormat,
+    ) -> Result<String, std::fmt::Error> {
+        let mut tmp_string = String::new();
+        writeln!(&mut tmp_string, "Title outlook before round {}:", self.round)?;
+
+        if self.contenders.len() == 1 {
+            writeln!(&mut tmp_string, "  Title decided: {} is champion", self.contenders[0])?;
+            return Ok(tmp_string);
+        }
+
+        let mut probabilities: Vec<(&String, &f64)> = self.probabilities.iter().collect();
+        probabilities.sort_by(|a, b| b.1.partial_cmp(a.1).unwrap());
+        for (initials, probability) in probabilities.iter().filter(|x| *x.1 > 0.0) {
+            writeln!(
+                &mut tmp_string,
+                "  {:4} {}% title probability",
+                initials,
+                out_fmt.fmt_num(100.0 * **probability, 5, 1)
+            )?;
+        }
+
+        if self.clinch_scenarios.is_empty() {
+            writeln!(&mut tmp_string, "  Nobody can clinch the title in this round")?;
+        }
+
+        for driver in self.contenders.iter() {
+            let scenarios: Vec<&ClinchScenario> =
+                self.clinch_scenarios.iter().filter(|x| &x.driver == driver).collect();
+
+            for scenario in scenarios.iter() {
+                // a scenario with a worse position or without the fastest lap but the same
+                // conditions covers this one
+                if scenarios.iter().any(|x| {
+                    x != scenario
+                        && x.position >= scenario.position
+                        && (!x.fastest_lap || scenario.fastest_lap)
+                        && x.conditions == scenario.conditions
+                }) {
+                    continue;
+                }
+
+                let conditions = if scenario.conditions.is_empty() {
+                    String::from("regardless of the rivals")
+                } else {
+                    let conditions: Vec<String> = scenario
+                        .conditions
+                        .iter()
+                        .map(|x| {
+                            if x.min_pos as usize > season_pars.points.len() {
+                                format!("{} outside the points", x.rival)
+                            } else {
+                                format!("{} P{} or worse", x.rival, x.min_pos)
+                            }
+                        })
+                        .collect();
+                    format!("if {}", conditions.join(" and "))
+                };
+
+                // better positions give more points, i.e. they clinch under the same conditions
+                let result = match scenario.position {
+                    1 => String::from("P1"),
+                    x if x as usize > season_pars.points.len() => String::from("any result"),
+                    x => format!("P{} or better", x),
+                };
+
+                writeln!(
+                    &mut tmp_string,
+                    "  {} clinches with {}{} {}",
+                    driver,
+                    result,
+                    if scenario.fastest_lap { " and the fastest lap" } else { "" },
+                    conditions
+                )?;
+            }
+        }
+
+        Ok(tmp_string)
+    }
+}
+
+/// read_season_pars reads the season file and the parameter files of all its rounds.
+pub fn read_season_pars(filepath: &Path) -> anyhow::Result<(SeasonPars, Vec<SimPars>)> {
+    let fh = OpenOptions::new().read(true).open(filepath).context(format!(
+        "Failed to open season file {}!",
+        filepath.to_str().unwrap()
+    ))?;
+
+    let season_pars: SeasonPars = serde_json::from_reader(&fh).context(format!(
+        "Failed to parse season file {}!",
+        filepath.to_str().unwrap()
+    ))?;
+
+    let base_dir = filepath.parent().unwrap_or_else(|| Path::new(""));
+    let rounds = season_pars
+        .rounds
+        .iter()
+        .map(|round_path| read_sim_pars_flexible(&base_dir.join(round_path)))
+        .collect::<anyhow::Result<Vec<SimPars>>>()?;
+
+    Ok((season_pars, rounds))
+}
+
+/// BanInfo contains a ban served by a driver.
+/// * `driver_initials` - Initials of the banned driver
+/// * `round` - Round in which the ban was served (1-based)
+/// * `car_no` - Car driven by the reserve driver instead (None if the driver had no car in the
+///   round)
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct BanInfo {
+    pub driver_initials: String,
+    pub round: u32,
+    pub car_no: Option<u32>,
+}
+
+/// SeasonState contains the state of a season that is carried from round to round.
+/// * `no_rounds_done` - Number of simulated rounds
+/// * `championship_points` - Championship points of every driver
+/// * `penalty_points` - Current penalty points of every driver (reduced by the threshold when a
+///   ban is served)
+/// * `pending_bans` - Drivers that are banned for the next round
+/// * `bans` - Bans served so far
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+pub struct SeasonState {
+    pub no_rounds_done: u32,
+    pub championship_points: BTreeMap<String, u32>,
+    pub penalty_points: BTreeMap<String, u32>,
+    pub pending_bans: BTreeSet<String>,
+    pub bans: Vec<BanInfo>,
+}
+
+/// get_causing_car returns the car that caused an incident: the rear car (attacker) of a two-car
+/// incident or the only car of a single-car incident.
+fn get_causing_car(cars: &[u32]) -> Option<u32> {
+    cars.last().copied()
+}
+
+impl SeasonState {
+    /// add_round_result adds the championship points and the penalty points of a round. Drivers
+    /// reaching the penalty points threshold are banned for the next round.
+    pub fn add_round_result(&mut self, season_pars: &SeasonPars, result: &RaceResult) {
+        self.no_rounds_done += 1;
+
+        // championship points (classified cars only)
+        for (idx, pos) in result.get_final_positions().iter().enumerate() {
+            let initials = &result.car_driver_pairs[idx].driver_initials;
+            let points = if result.get_status(idx) == ClassificationStatus::Finished {
+                season_pars.points.get(*pos as usize - 1).copied().unwrap_or(0)
+            } else {
+                0
+            };
+            *self.championship_points.entry(initials.to_owned()).or_insert(0) += points;
+        }
+
+        // fastest lap points (classified cars within `fastest_lap_max_pos` only)
+        if let Some((idx, _)) = result.get_fastest_lap() {
+            if result.get_status(idx) == ClassificationStatus::Finished
+                && result.get_final_positions()[idx] <= season_pars.fastest_lap_max_pos
+            {
+                let initials = &result.car_driver_pairs[idx].driver_initials;
+                *self.championship_points.entry(initials.to_owned()).or_insert(0) +=
+                    season_pars.fastest_lap_points;
+            }
+        }
+
+        // penalty points for the incidents
+        for ev in result.events.iter() {
+            let points = match season_pars.penalty_points.event_points.get(&ev.kind) {
+                Some(&points) if points > 0 => points,
+                _ => continue,
+            };
+            let pair = get_causing_car(&ev.cars)
+                .and_then(|car_no| result.car_driver_pairs.iter().find(|x| x.car_no == car_no));
+
+            if let Some(pair) = pair {
+                *self.penalty_points.entry(pair.driver_initials.to_owned()).or_insert(0) += points;
+            }
+        }
+
+        // penalty points for repeated track limits strikes within the round
+        let pars = &season_pars.penalty_points;
+        let mut strikes: BTreeMap<&str, u32> = BTreeMap::new();
+        for ev in result.events.iter().filter(|ev| ev.kind == "TrackLimits") {
+            let pair = get_causing_car(&ev.cars)
+                .and_then(|car_no| result.car_driver_pairs.iter().find(|x| x.car_no == car_no));
+            if let Some(pair) = pair {
+                *strikes.entry(pair.driver_initials.as_str()).or_insert(0) += 1;
+            }
+        }
+
+        // a strike threshold of 0 disables the penalty points for track limits
+        for (initials, no_strikes) in strikes.iter() {
+            let points = no_strikes.checked_div(pars.track_limit_strikes).unwrap_or(0)
+                * pars.track_limit_points;
+            if points > 0 {
+                *self.penalty_points.entry(initials.to_string()).or_insert(0) += points;
+            }
+        }
+
+        let threshold = season_pars.penalty_points.ban_threshold;
+        for (initials, points) in self.penalty_points.iter() {
+            if threshold > 0 && *points >= threshold {
+                self.pending_bans.insert(initials.to_owned());
+            }
+        }
+    }
+
+    /// apply_bans replaces the banned driver by the reserve driver in the parameters of the next
+    /// round (the drivers are swapped before the race is created, not during the race). The ban is
+    /// recorded as served and the threshold is removed from the penalty points of the banned
+    /// driver. The reserve driver replaces a single driver in a single car, more bans in the same
+    /// round or a reserve driver who already takes part in the round are an error.
+    pub fn apply_bans(
+        &mut self,
+        season_pars: &SeasonPars,
+        sim_pars: &mut SimPars,
+    ) -> anyhow::Result<()> {
+        if self.pending_bans.is_empty() {
+            return Ok(());
+        }
+
+        let reserve_driver = season_pars.reserve_driver.as_ref().context(format!(
+            "Drivers {:?} are banned, but the season file contains no reserve driver!",
+            self.pending_bans
+        ))?;
+
+        if self.pending_bans.len() > 1 {
+            anyhow::bail!(
+                "Drivers {:?} are banned for the same round, but there is only one reserve driver!",
+                self.pending_bans
+            );
+        }
+
+        let drives_round = |initials: &str| {
+            sim_pars.race_pars.participants.iter().any(|car_no| {
+                sim_pars.car_pars_all.get(car_no).is_some_and(|car_pars| {
+                    car_pars.strategy.iter().any(|x| x.driver_initials.trim() == initials)
+                })
+            })
+        };
+        if drives_round(&reserve_driver.initials) {
+            anyhow::bail!(
+                "Reserve driver {} already takes part in the round!",
+                reserve_driver.initials
+            );
+        }
+
+        let round = self.no_rounds_done + 1;
+        let initials = std::mem::take(&mut self.pending_bans).into_iter().next().unwrap();
+        let mut car_no_ban = None;
+
+        // the reserve driver takes over the first car of the banned driver only
+        for car_no in sim_pars.race_pars.participants.iter() {
+            let car_pars = match sim_pars.car_pars_all.get_mut(car_no) {
+                Some(x) => x,
+                None => continue,
+            };
+
+            for entry in car_pars.strategy.iter_mut() {
+                if entry.driver_initials.trim() == initials {
+                    entry.driver_initials = reserve_driver.initials.to_owned();
+                    car_no_ban = Some(*car_no);
+                }
+            }
+
+            if car_no_ban.is_some() {
+                break;
+            }
+        }
+
+        if car_no_ban.is_some() {
+            sim_pars
+                .driver_pars_all
+                .insert(reserve_driver.initials.to_owned(), reserve_driver.to_owned());
+        }
+
+        if let Some(points) = self.penalty_points.get_mut(&initials) {
+            *points = points.saturating_sub(season_pars.penalty_points.ban_threshold);
+        }
+
+        self.bans.push(BanInfo {
+            driver_initials: initials,
+            round,
+            car_no: car_no_ban,
+        });
+
+        Ok(())
+    }
+
+    /// get_standings returns the drivers with their values sorted descending by value (drivers
+    /// with equal values in alphabetical order).
+    fn get_standings(values: &BTreeMap<String, u32>) -> Vec<(&str, u32)> {
+        let mut standings: Vec<(&str, u32)> =
+            values.iter().map(|(initials, &value)| (initials.as_str(), value)).collect();
+        standings.sort_by_key(|x| std::cmp::Reverse(x.1));
+        standings
+    }
+
+    /// write_to_json writes the season state to a JSON file in output/. Returns the path to the
+    /// written file.
+    pub fn write_to_json(&self, path: Option<&Path>) -> anyhow::Result<String> {
+        let content = serde_json::to_string_pretty(self)?;
+        write_output_file(path, "season_state.json", &content)
+    }
+}
+
+/// SeasonResult contains the results of all rounds of a season, the final season state and the
+/// title outlooks before the final rounds (empty without title predictor).
+#[derive(Debug, Clone)]
+pub struct SeasonResult {
+    pub rounds: Vec<RaceResult>,
+    pub state: SeasonState,
+    pub title_outlooks: Vec<TitleOutlook>,
+}
+
+impl SeasonResult {
+    /// format_summary creates the season summary containing the championship standings, the
+    /// penalty points standings, the bans served, the round winners and the title outlooks.
+    pub fn format_summary(
+        &self,
+        season_pars: &SeasonPars,
+        out_fmt: &OutputFormat,
+    ) -> Result<String, std::fmt::Error> {
+        let mut tmp_string = String::new();
+
+        writeln!(&mut tmp_string, "RESULT: Season after {} rounds", self.state.no_rounds_done)?;
+        writeln!(&mut tmp_string, "Championship standings:")?;
+        for (pos, (initials, points)) in
+            SeasonState::get_standings(&self.state.championship_points).iter().enumerate()
+        {
+            writeln!(&mut tmp_string, "P{:<3} {:4} {:>4} pts", pos + 1, initials, points)?;
+        }
+
+        writeln!(&mut tmp_string, "Penalty points:")?;
+        let penalty_standings = SeasonState::get_standings(&self.state.penalty_points);
+        if penalty_standings.iter().all(|x| x.1 == 0) {
+            writeln!(&mut tmp_string, "  none")?;
+        }
+        for (initials, points) in penalty_standings.iter().filter(|x| x.1 > 0) {
+            writeln!(&mut tmp_string, "  {:4} {:>3}", initials, points)?;
+        }
+
+        writeln!(&mut tmp_string, "Bans served:")?;
+        if self.state.bans.is_empty() {
+            writeln!(&mut tmp_string, "  none")?;
+        }
+        for ban in self.state.bans.iter() {
+            match ban.car_no {
+                Some(car_no) => writeln!(
+                    &mut tmp_string,
+                    "  Round {}: {} (car {} driven by the reserve driver)",
+                    ban.round, ban.driver_initials, car_no
+                )?,
+                None => writeln!(
+                    &mut tmp_string,
+                    "  Round {}: {} (no car in this round)",
+                    ban.round, ban.driver_initials
+                )?,
+            }
+        }
+
+        // bans reached in the final round cannot be served within the season
+        if !self.state.pending_bans.is_empty() {
+            writeln!(
+                &mut tmp_string,
+                "Bans pending after the final round (not served): {}",
+                self.state.pending_bans.iter().cloned().collect::<Vec<String>>().join(", ")
+            )?;
+        }
+
+        // winners of the rounds
+        writeln!(&mut tmp_string, "Round winners:")?;
+        for (round, result) in self.rounds.iter().enumerate() {
+            let positions = result.get_final_positions();
+            if let Some(idx) = positions.iter().position(|&pos| pos == 1) {
+                let pair = &result.car_driver_pairs[idx];
+                let racetime = result.get_racetime(idx, result.tot_no_laps as usize);
+                writeln!(
+                    &mut tmp_string,
+                    "  Round {:2}: {} ({}), {} s",
+                    round + 1,
+                    pair.driver_initials,
+                    pair.car_no,
+                    out_fmt.fmt_num(racetime, 0, 3)
+                )?;
+            }
+        }
+
+        for title_outlook in self.title_outlooks.iter() {
+            write!(&mut tmp_string, "{}", title_outlook.format(season_pars, out_fmt)?)?;
+        }
+
+        Ok(tmp_string)
+    }
+}
+
+/// predict_title_probabilities estimates the probability of every driver to win the title by
+/// simulating the remaining rounds `no_runs` times in parallel, starting from the given season
+/// state (bans and car development included). Equal points at the end share the title run. Run i
+/// of a seeded round uses the seed of the round + i + 1.
+pub fn predict_title_probabilities(
+    season_pars: &SeasonPars,
+    rounds_left: &[SimPars],
+    state: &SeasonState,
+    sim_consts: &SimConstants,
+    tire_config: &TireConfig,
+    timestep_size: f64,
+    no_runs: u32,
+) -> anyhow::Result<BTreeMap<String, f64>> {
+    let mut probabilities: BTreeMap<String, f64> =
+        state.championship_points.keys().map(|initials| (initials.to_owned(), 0.0)).collect();
+
+    // the tracks are created once per round and worker thread and reused for all its runs
+    let champions_runs = (0..no_runs)
+        .into_par_iter()
+        .map_init(
+            || -> Vec<Rc<Track>> {
+                rounds_left
+                    .iter()
+                    .map(|sim_pars| Rc::new(Track::new(&sim_pars.track_pars)))
+                    .collect()
+            },
+            |tracks, run| -> anyhow::Result<Vec<String>> {
+                let mut state_run = state.to_owned();
+
+                for (sim_pars, track) in rounds_left.iter().zip(tracks.iter()) {
+                    let mut sim_pars_round = sim_pars.to_owned();
+                    sim_pars_round.race_pars.seed =
+                        sim_pars.race_pars.seed.map(|seed| seed.wrapping_add(run as u64 + 1));
+                    season_pars
+                        .apply_car_development(state_run.no_rounds_done + 1, &mut sim_pars_round);
+                    state_run.apply_bans(season_pars, &mut sim_pars_round)?;
+                    let result = run_race_on_track(
+                        &sim_pars_round,
+                        sim_consts,
+                        tire_config,
+                        track,
+                        timestep_size,
+                    )?;
+                    state_run.add_round_result(season_pars, &result);
+                }
+
+                let points_champion =
+                    state_run.championship_points.values().copied().max().unwrap_or(0);
+                Ok(state_run
+                    .championship_points
+                    .into_iter()
+                    .filter(|(_, points)| *points == points_champion)
+                    .map(|(initials, _)| initials)
+                    .collect())
+            },
+        )
+        .collect::<anyhow::Result<Vec<Vec<String>>>>()?;
+
+    for champions in champions_runs.iter() {
+        for initials in champions.iter() {
+            *probabilities.entry(initials.to_owned()).or_insert(0.0) +=
+                1.0 / (champions.len() as f64 * no_runs as f64);
+        }
+    }
+
+    Ok(probabilities)
+}
+
+/// run_season simulates the rounds of a season one after another (without GUI). The `t_car` of the
+/// developed cars is set for every round according to the development schedule. After every round
+/// the championship and penalty points are added to the season state, drivers reaching the penalty
+/// points threshold are replaced by the reserve driver in the next round (bans reached in the final
+/// round stay pending in the season state and are listed in the summary). If the title predictor
+/// is set, the title outlook is determined before each of the final rounds and returned in the
+/// season result.
+pub fn run_season(
+    season_pars: &SeasonPars,
+    rounds: &[SimPars],
+    sim_consts: &SimConstants,
+    tire_config: &TireConfig,
+    timestep_size: f64,
+) -> anyhow::Result<SeasonResult> {
+    let mut state = SeasonState::default();
+    let mut results = Vec::with_capacity(rounds.len());
+    let mut title_outlooks = vec![];
+
+    for (round, sim_pars) in rounds.iter().enumerate() {
+        let no_rounds_left = (rounds.len() - round) as u32;
+
+        match &season_pars.title_predictor {
+            Some(pars) if round > 0 && no_rounds_left <= pars.final_rounds => {
+                let contenders =
+                    season_pars.get_contenders(&state.championship_points, no_rounds_left);
+
+                // no simulations needed once the title is decided
+                let probabilities = if contenders.len() == 1 {
+                    [(contenders[0].to_owned(), 1.0)].iter().cloned().collect()
+                } else {
+                    predict_title_probabilities(
+                        season_pars,
+                        &rounds[round..],
+                        &state,
+                        sim_consts,
+                        tire_config,
+                        timestep_size,
+                        pars.no_runs,
+                    )
+                    .context(format!("Failed to predict the title before round {}!", round + 1))?
+                };
+
+                let title_outlook = TitleOutlook {
+                    round: round as u32 + 1,
+                    clinch_scenarios: season_pars
+                        .get_clinch_scenarios(&state.championship_points, no_rounds_left),
+                    contenders,
+                    probabilities,
+                };
+                title_outlooks.push(title_outlook);
+            }
+            _ => {}
+        }
+
+        let mut sim_pars_round = sim_pars.to_owned();
+        season_pars.apply_car_development(round as u32 + 1, &mut sim_pars_round);
+        state
+            .apply_bans(season_pars, &mut sim_pars_round)
+            .context(format!("Failed to prepare round {} of the season!", round + 1))?;
+
+        let result = run_race_with_options(
+            &sim_pars_round,
+            sim_consts,
+            tire_config,
+            RunOptions {
+                timestep_size,
+                ..Default::default()
+            },
+        )
+        .context(format!("Failed to simulate round {} of the season!", round + 1))?;
+
+        state.add_round_result(season_pars, &result);
+        results.push(result);
+    }
+
+    Ok(SeasonResult {
+        rounds: results,
+        state,
+        title_outlooks,
+    })
+}
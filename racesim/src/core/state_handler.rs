@@ -1,8 +1,8 @@
 // Usunięto FlagState, ponieważ interakcje są usunięte
 // use crate::core::race::FlagState; 
 
-#[derive(Debug)]
-pub enum State {
+#[derive(Debug, Clone)]
+pub(crate) enum State {
     OnTrack, // Uproszczono: Racestart, NormalZone, OvertakingZone połączone
     Pitlane,
     PitStandstill,
@@ -11,8 +11,8 @@ pub enum State {
 /// StateHandler został drastycznie uproszczony.
 /// Śledzi teraz tylko postęp na torze i podstawowe stany (tor, aleja, postój).
 /// Usunięto całą logikę DRS, wyprzedzania, pojedynków i stref.
-#[derive(Debug)]
-pub struct StateHandler {
+#[derive(Debug, Clone)]
+pub(crate) struct StateHandler {
     // parametry
     pit_zone: [f64; 2], // [start, end]
     track_length: f64,
@@ -33,11 +33,16 @@ pub struct StateHandler {
     pub pit_act: bool,
     pub pit_standstill_act: bool,
     pub drs_act: bool,
+    drs_eligible: bool, // uprawnienie do DRS ustalone w ostatnim punkcie detekcji
     pub duel_act: bool,
     pub corner_act: bool,
     // zmienne związane z postępem wyścigu
     compl_lap_prev: u32,
     compl_lap_cur: u32,
+    // minisektory (kontrola tempa podczas VSC)
+    mini_sector_bounds: Vec<f64>, // początki minisektorów
+    mini_sector_idx: usize, // bieżący minisektor
+    t_mini_sector_start: Option<f64>, // czas wjazdu w bieżący minisektor (None - nieznany)
 }
 
 impl StateHandler {
@@ -71,6 +76,62 @@ impl StateHandler {
         // Usunięto logikę 'first_zone_info'
     }
 
+    /// init_mini_sectors ustawia początki minisektorów, w których mierzony jest czas przejazdu.
+    pub fn init_mini_sectors(&mut self, mini_sector_bounds: Vec<f64>) {
+        self.mini_sector_bounds = mini_sector_bounds;
+        self.reset_mini_sector();
+    }
+
+    /// reset_mini_sector ustala bieżący minisektor na podstawie pozycji bolidu. Czas jego
+    /// przejazdu jest nieznany (np. po przestawieniu bolidu).
+    fn reset_mini_sector(&mut self) {
+        let s_track_cur = self.get_s_tracks().1;
+        self.mini_sector_idx = self
+            .mini_sector_bounds
+            .iter()
+            .rposition(|&s_start| s_start <= s_track_cur)
+            .unwrap_or(self.mini_sector_bounds.len().saturating_sub(1));
+        self.t_mini_sector_start = None;
+    }
+
+    /// check_mini_sector_passed sprawdza, czy bolid minął w tym kroku koniec bieżącego minisektora
+    /// (`t_step_start` - czas wyścigu na początku kroku). Zwraca numer ukończonego minisektora,
+    /// czas wjazdu w niego i czas jego przejazdu (interpolowany w kroku) lub None, jeśli minisektor
+    /// nie został ukończony albo czas wjazdu w niego jest nieznany.
+    pub fn check_mini_sector_passed(
+        &mut self,
+        t_step_start: f64,
+        timestep_size: f64,
+    ) -> Option<(usize, f64, f64)> {
+        if self.mini_sector_bounds.is_empty() {
+            return None;
+        }
+
+        let idx_next = (self.mini_sector_idx + 1) % self.mini_sector_bounds.len();
+        let s_bound = self.mini_sector_bounds[idx_next];
+
+        if !self.get_s_track_passed_this_step(s_bound) {
+            return None;
+        }
+
+        // przekroczenie granicy interpolowane po przejechanym w tym kroku dystansie
+        let delta_s = (self.get_race_prog() - self.get_race_prog_prev()) * self.track_length;
+        let step_frac = if delta_s > 0.0 {
+            ((s_bound - self.s_track_prev).rem_euclid(self.track_length) / delta_s).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        let t_crossing = t_step_start + step_frac * timestep_size;
+
+        let mini_sector_time = self
+            .t_mini_sector_start
+            .map(|t_start| (self.mini_sector_idx, t_start, t_crossing - t_start));
+        self.mini_sector_idx = idx_next;
+        self.t_mini_sector_start = Some(t_crossing);
+
+        mini_sector_time
+    }
+
     pub fn get_s_track_passed_this_step(&self, s_track: f64) -> bool {
         // Sprawdza, czy bolid przekroczył linię mety w tym kroku czasowym
         let new_lap = self.get_new_lap();
@@ -86,19 +147,20 @@ impl StateHandler {
 
     /// check_state_transition sprawdza, czy bolid zmienia stan (tylko wejście/wyjście z alei).
     /// Drastycznie uproszczone: usunięto logikę DRS, wyprzedzania, flag, okrążeń.
+    /// Zwraca true, jeśli bolid miał zjechać, ale wjazd do alei był zamknięty (`pit_closed`).
     #[allow(clippy::too_many_arguments)]
     pub fn check_state_transition(
         &mut self,
         delta_t_front: f64,
         delta_t_rear: f64,
         pit_this_lap: bool,
-    ) {
+        pit_closed: bool,
+        drs_enabled: bool,
+    ) -> bool {
+        let mut pit_entry_refused = false;
+
         // Prosta logika pojedynków: jeśli jesteśmy blisko kogoś (z przodu lub z tyłu), to walczymy
-        if delta_t_front < 1.0 || delta_t_rear < 1.0 {
-            self.duel_act = true;
-        } else {
-            self.duel_act = false;
-        }
+        self.duel_act = delta_t_front < 1.0 || delta_t_rear < 1.0;
 
         // Sprawdź czy jesteśmy w zakręcie
         self.corner_act = false;
@@ -110,12 +172,35 @@ impl StateHandler {
             }
         }
 
+        // DRS: uprawnienie ustalane w punktach detekcji wyłącznie na podstawie odstępu do auta
+        // bezpośrednio z przodu, używane w kolejnej strefie wyprzedzania
+        let passed_measurement_point = self
+            .drs_measurement_points
+            .iter()
+            .any(|&s_measurement| self.get_s_track_passed_this_step(s_measurement));
+
+        if !drs_enabled {
+            self.drs_eligible = false;
+        } else if passed_measurement_point {
+            self.drs_eligible = delta_t_front < self.drs_window;
+        }
+
+        self.drs_act = self.use_drs
+            && self.drs_eligible
+            && !self.pit_act
+            && self.overtaking_zones.iter().any(|zone| self.is_in_zone(*zone));
+
         match self.state {
             // Bolid jest na torze (łączy Racestart, NormalZone, OvertakingZone)
             State::OnTrack => {
                 if pit_this_lap && self.get_s_track_passed_this_step(self.pit_zone[0]) {
-                    self.state = State::Pitlane;
-                    self.pit_act = true;
+                    if pit_closed {
+                        // wjazd zamknięty - bolid zostaje na torze
+                        pit_entry_refused = true;
+                    } else {
+                        self.state = State::Pitlane;
+                        self.pit_act = true;
+                    }
                 }
             }
 
@@ -131,6 +216,22 @@ impl StateHandler {
             // Postój w alei (obsługiwany przez metody zewnętrzne)
             State::PitStandstill => {}
         }
+
+        pit_entry_refused
+    }
+
+    /// get_drs_eligible zwraca true, jeśli bolid był w oknie DRS w ostatnim punkcie detekcji.
+    pub fn get_drs_eligible(&self) -> bool {
+        self.drs_eligible
+    }
+
+    /// is_in_zone sprawdza, czy bolid jest w strefie [start, koniec] (również przez linię mety).
+    fn is_in_zone(&self, zone: [f64; 2]) -> bool {
+        if zone[0] <= zone[1] {
+            zone[0] <= self.s_track_cur && self.s_track_cur <= zone[1]
+        } else {
+            zone[0] <= self.s_track_cur || self.s_track_cur <= zone[1]
+        }
     }
 
     /// act_pit_standstill aktywuje stan postoju
@@ -225,6 +326,39 @@ impl StateHandler {
         self.compl_lap_cur as f64 + self.s_track_cur / self.track_length
     }
 
+    /// get_race_prog_prev zwraca postęp wyścigu z poprzedniego kroku czasowego
+    pub fn get_race_prog_prev(&self) -> f64 {
+        self.compl_lap_prev as f64 + self.s_track_prev / self.track_length
+    }
+
+    /// set_race_prog ustawia postęp wyścigu w bieżącym kroku (liczba ukończonych okrążeń i
+    /// koordynat s). Auto nie jest cofane za swoją pozycję z poprzedniego kroku, tzn. mniejszy postęp
+    /// jest podnoszony do postępu z poprzedniego kroku.
+    pub fn set_race_prog(&mut self, race_prog: f64) {
+        self.set_race_prog_cur(race_prog.max(self.get_race_prog_prev()));
+    }
+
+    /// place_race_prog przenosi auto w podane miejsce (postęp z poprzedniego i bieżącego kroku),
+    /// np. przy ustawianiu aut na torze. Auto nie przejeżdża przy tym linii mety.
+    #[cfg(test)]
+    pub fn place_race_prog(&mut self, race_prog: f64) {
+        self.set_race_prog_cur(race_prog);
+        self.compl_lap_prev = self.compl_lap_cur;
+        self.s_track_prev = self.s_track_cur;
+    }
+
+    fn set_race_prog_cur(&mut self, race_prog: f64) {
+        if race_prog < 0.0 {
+            // przed przekroczeniem linii startu (pola startowe za linią)
+            self.compl_lap_cur = 0;
+            self.s_track_cur = race_prog * self.track_length;
+        } else {
+            self.compl_lap_cur = race_prog.trunc() as u32;
+            self.s_track_cur = race_prog.fract() * self.track_length;
+        }
+        self.reset_mini_sector();
+    }
+
     /// get_new_lap zwraca bool, czy rozpoczęto nowe okrążenie
     pub fn get_new_lap(&self) -> bool {
         self.compl_lap_cur > self.compl_lap_prev
@@ -239,6 +373,7 @@ impl StateHandler {
             )
         }
         self.s_track_cur = s_track_cur;
+        self.reset_mini_sector();
     }
 
     /// update_race_prog aktualizuje postęp wyścigu
@@ -271,6 +406,7 @@ impl Default for StateHandler {
             pit_act: false,
             pit_standstill_act: false,
             drs_act: false,
+            drs_eligible: false,
             duel_act: false,
             corner_act: false,
             compl_lap_prev: 0,
@@ -281,6 +417,9 @@ impl Default for StateHandler {
             drs_measurement_points: Vec::new(),
             overtaking_zones: Vec::new(),
             corners: Vec::new(),
+            mini_sector_bounds: Vec::new(),
+            mini_sector_idx: 0,
+            t_mini_sector_start: None,
         }
     }
 }
\ No newline at end of file
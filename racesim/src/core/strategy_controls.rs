@@ -0,0 +1,111 @@
+use crate::core::race::FlagState;
+
+/// CarLapInfo is the read-only state of a single car after a leader lap.
+/// * `car_no` - Car number
+/// * `position` - Current position (1 = leader)
+/// * `delta_race_prog` - (laps) Distance to the leader
+/// * `compl_lap` - Number of completed laps of the car
+/// * `compound` - Currently fitted compound
+/// * `tire_age` - (laps) Age of the current tireset in the current stint
+/// * `no_pitstops` - Number of pit stops made so far
+/// * `planned_inlaps` - Inlaps of the pit stops that are still planned (including a stop at the end
+///   of the lap that was just completed, as the car might not have reached its pit box yet)
+/// * `dnf` - True if the car retired from the race
+#[derive(Debug, Clone)]
+pub struct CarLapInfo {
+    pub car_no: u32,
+    pub position: u32,
+    pub delta_race_prog: f64,
+    pub compl_lap: u32,
+    pub compound: String,
+    pub tire_age: f64,
+    pub no_pitstops: u32,
+    pub planned_inlaps: Vec<u32>,
+    pub dnf: bool,
+}
+
+/// LapContext is the read-only snapshot of the race that is passed to the lap callback.
+/// * `lap` - Leader lap that was just completed
+/// * `tot_no_laps` - Number of laps of the race
+/// * `race_time` - (s) Current race time
+/// * `cars` - Car states in running order
+/// * `weather_is_rain` - True if it is raining
+/// * `flag_state` - Current flag state (most severe flag of all sectors)
+/// * `drs_trains` - Current DRS trains (car numbers in running order), a car stuck in a train is a
+///   candidate for an undercut
+#[derive(Debug, Clone)]
+pub struct LapContext {
+    pub lap: u32,
+    pub tot_no_laps: u32,
+    pub race_time: f64,
+    pub cars: Vec<CarLapInfo>,
+    pub weather_is_rain: bool,
+    pub flag_state: FlagState,
+    pub drs_trains: Vec<Vec<u32>>,
+}
+
+impl LapContext {
+    /// is_in_drs_train returns true if the car is part of a DRS train.
+    pub fn is_in_drs_train(&self, car_no: u32) -> bool {
+        self.drs_trains.iter().any(|drs_train| drs_train.contains(&car_no))
+    }
+}
+
+/// StrategyCommand is a single strategy mutation requested by the lap callback.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StrategyCommand {
+    /// Pit at the end of `inlap` and fit `compound`
+    SchedulePit {
+        car_no: u32,
+        inlap: u32,
+        compound: String,
+    },
+    /// Cancel the planned pit stop at the end of `inlap`
+    CancelPit { car_no: u32, inlap: u32 },
+    /// Change the compound fitted during the planned pit stop at the end of `inlap`
+    SetCompound {
+        car_no: u32,
+        inlap: u32,
+        compound: String,
+    },
+}
+
+/// StrategyControls collects the commands of the lap callback. The commands are validated and
+/// applied by the race between two timesteps. Invalid commands are rejected, `rejected` contains a
+/// message for every command rejected so far in the race.
+#[derive(Debug, Default)]
+pub struct StrategyControls {
+    commands: Vec<StrategyCommand>,
+    pub rejected: Vec<String>,
+}
+
+impl StrategyControls {
+    pub fn schedule_pit(&mut self, car_no: u32, inlap: u32, compound: &str) {
+        self.commands.push(StrategyCommand::SchedulePit {
+            car_no,
+            inlap,
+            compound: compound.to_uppercase(),
+        });
+    }
+
+    pub fn cancel_pit(&mut self, car_no: u32, inlap: u32) {
+        self.commands
+            .push(StrategyCommand::CancelPit { car_no, inlap });
+    }
+
+    pub fn set_compound(&mut self, car_no: u32, inlap: u32, compound: &str) {
+        self.commands.push(StrategyCommand::SetCompound {
+            car_no,
+            inlap,
+            compound: compound.to_uppercase(),
+        });
+    }
+
+    /// take_commands returns the collected commands and clears the list.
+    pub fn take_commands(&mut self) -> Vec<StrategyCommand> {
+        std::mem::take(&mut self.commands)
+    }
+}
+
+/// LapCallback is invoked after every leader lap.
+pub type LapCallback = Box<dyn FnMut(LapContext, &mut StrategyControls)>;
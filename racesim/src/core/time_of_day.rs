@@ -0,0 +1,48 @@
+/// get_time_of_day returns the local time of day (h) at the given race time of a session that
+/// starts at `session_start_h`. The hours count from midnight of the session day without wrapping,
+/// i.e. values beyond 24.0 belong to the night after the session day.
+pub fn get_time_of_day(session_start_h: f64, racetime_s: f64) -> f64 {
+    session_start_h + racetime_s / 3600.0
+}
+
+/// format_time_of_day formats a time of day (h) as "HH:MM".
+pub fn format_time_of_day(time_of_day_h: f64) -> String {
+    let minutes = (time_of_day_h.rem_euclid(24.0) * 60.0).round() as u32 % (24 * 60);
+    format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+/// TrackTempPars contains the parameters of the track temperature model. The track keeps its
+/// day temperature until sunset and then cools down exponentially towards the night temperature.
+/// * `temp_day_c` - (°C) Track temperature in daylight
+/// * `temp_night_c` - (°C) Track temperature the track cools down to at night
+/// * `cooling_time_h` - (h) Time constant of the cooling after sunset
+/// * `temp_ref_c` - (°C) Track temperature the tire degradation parameters are valid for
+#[derive(Debug, Clone, Copy)]
+pub struct TrackTempPars {
+    pub temp_day_c: f64,
+    pub temp_night_c: f64,
+    pub cooling_time_h: f64,
+    pub temp_ref_c: f64,
+}
+
+impl TrackTempPars {
+    /// calc_track_temp returns the track temperature (°C) at the given time of day (h, counted
+    /// from midnight of the session day, see `get_time_of_day`).
+    pub fn calc_track_temp(&self, time_of_day_h: f64, sunset_h: f64) -> f64 {
+        let t_after_sunset = time_of_day_h - sunset_h;
+
+        if t_after_sunset <= 0.0 || self.cooling_time_h <= 0.0 {
+            return if t_after_sunset > 0.0 { self.temp_night_c } else { self.temp_day_c };
+        }
+
+        self.temp_night_c
+            + (self.temp_day_c - self.temp_night_c) * (-t_after_sunset / self.cooling_time_h).exp()
+    }
+
+    /// calc_wear_factor returns the factor of the tire wear at the given track temperature (°C)
+    /// for a compound with the given temperature sensitivity (1/°C). Hotter tracks wear the tires
+    /// faster (overheating), cooler tracks slower.
+    pub fn calc_wear_factor(&self, track_temp_c: f64, temp_wear_sensitivity: f64) -> f64 {
+        (1.0 + temp_wear_sensitivity * (track_temp_c - self.temp_ref_c)).max(0.1)
+    }
+}
@@ -0,0 +1,211 @@
+use crate::core::car::StrategyEntry;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// TireStint records one stint of a tire set such that the provenance of the rubber is auditable.
+/// * `session` - Session in which the set was fitted (e.g. Sprint, Race)
+/// * `lap_fitted` - Completed laps of the car when the set was fitted (0 = start)
+/// * `lap_removed` - Completed laps of the car when the set was removed (None while fitted)
+/// * `age_fitted` - (laps) Age of the set when it was fitted
+/// * `age_removed` - (laps) Age of the set when it was removed (None while fitted)
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct TireStint {
+    pub session: String,
+    pub lap_fitted: u32,
+    pub lap_removed: Option<u32>,
+    pub age_fitted: f64,
+    pub age_removed: Option<f64>,
+}
+
+/// TireSetRecord is a single set of the tire allocation of a car.
+/// * `set_id` - Set number (unique per car)
+/// * `compound` - Compound of the set
+/// * `age` - (laps) Accumulated age of the set over all sessions
+/// * `stints` - Stints driven on the set
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct TireSetRecord {
+    pub set_id: u32,
+    pub compound: String,
+    pub age: f64,
+    pub stints: Vec<TireStint>,
+}
+
+impl TireSetRecord {
+    /// is_new returns true if the set was not fitted yet.
+    pub fn is_new(&self) -> bool {
+        self.stints.is_empty()
+    }
+}
+
+/// TireInventory contains the tire sets of a car for a whole race weekend. It is handed from one
+/// session to the next such that used sets reduce the availability and keep their age.
+/// * `car_no` - Car number
+/// * `session` - Current session, used for the stint records
+/// * `sets` - Tire sets of the allocation
+/// * `fitted` - Set number of the currently fitted set
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct TireInventory {
+    pub car_no: u32,
+    pub session: String,
+    pub sets: Vec<TireSetRecord>,
+    pub fitted: Option<u32>,
+}
+
+impl TireInventory {
+    /// new creates the inventory of new sets according to the allocation (number of sets per
+    /// compound). The sets are numbered from 1 in the (alphabetical) order of the compounds.
+    pub fn new(car_no: u32, allocation: &BTreeMap<String, u32>) -> TireInventory {
+        let mut sets = vec![];
+
+        for (compound, no_sets) in allocation.iter() {
+            for _ in 0..*no_sets {
+                sets.push(TireSetRecord {
+                    set_id: sets.len() as u32 + 1,
+                    compound: compound.to_uppercase(),
+                    age: 0.0,
+                    stints: vec![],
+                });
+            }
+        }
+
+        TireInventory {
+            car_no,
+            session: String::new(),
+            sets,
+            fitted: None,
+        }
+    }
+
+    /// get_set returns the set with the given set number.
+    pub fn get_set(&self, set_id: u32) -> Option<&TireSetRecord> {
+        self.sets.iter().find(|set| set.set_id == set_id)
+    }
+
+    /// get_no_new_sets returns the number of new sets of the compound.
+    pub fn get_no_new_sets(&self, compound: &str) -> usize {
+        self.sets
+            .iter()
+            .filter(|set| set.compound == compound && set.is_new())
+            .count()
+    }
+
+    /// fit_set fits a set of the compound after `lap` completed laps and returns its age. If a set
+    /// number is given, exactly this set is fitted. Otherwise, the first new set of the compound is
+    /// taken and, if no new set is left, the least used set. The currently fitted set must have
+    /// been removed before.
+    pub fn fit_set(&mut self, compound: &str, set_id: Option<u32>, lap: u32) -> anyhow::Result<f64> {
+        if let Some(fitted) = self.fitted {
+            anyhow::bail!("Car {} still has set {} fitted!", self.car_no, fitted)
+        }
+
+        let idx = match set_id {
+            Some(set_id) => {
+                let idx = self
+                    .sets
+                    .iter()
+                    .position(|set| set.set_id == set_id)
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("Car {} has no tire set {}!", self.car_no, set_id)
+                    })?;
+
+                if self.sets[idx].compound != compound {
+                    anyhow::bail!(
+                        "Tire set {} of car {} is a {} set, not {}!",
+                        set_id,
+                        self.car_no,
+                        self.sets[idx].compound,
+                        compound
+                    )
+                }
+
+                idx
+            }
+            None => self
+                .sets
+                .iter()
+                .enumerate()
+                .filter(|(_, set)| set.compound == compound)
+                .min_by(|(_, a), (_, b)| a.age.partial_cmp(&b.age).unwrap())
+                .map(|(idx, _)| idx)
+                .ok_or_else(|| {
+                    anyhow::anyhow!("Car {} has no {} set left!", self.car_no, compound)
+                })?,
+        };
+
+        let set = &mut self.sets[idx];
+        set.stints.push(TireStint {
+            session: self.session.to_owned(),
+            lap_fitted: lap,
+            lap_removed: None,
+            age_fitted: set.age,
+            age_removed: None,
+        });
+        self.fitted = Some(set.set_id);
+
+        Ok(set.age)
+    }
+
+    /// remove_fitted removes the fitted set after `lap` completed laps and stores its age.
+    pub fn remove_fitted(&mut self, age: f64, lap: u32) {
+        let fitted = match self.fitted.take() {
+            Some(fitted) => fitted,
+            None => return,
+        };
+
+        if let Some(set) = self.sets.iter_mut().find(|set| set.set_id == fitted) {
+            set.age = age;
+
+            if let Some(stint) = set.stints.last_mut() {
+                stint.lap_removed = Some(lap);
+                stint.age_removed = Some(age);
+            }
+        }
+    }
+}
+
+/// validate_tire_allocation checks that the strategies of a car in all sessions of a weekend do
+/// not need more new sets of any compound than allocated. Every strategy entry without a set
+/// number takes a new set, entries with a set number re-fit a set of the allocation.
+pub fn validate_tire_allocation(
+    car_no: u32,
+    allocation: &BTreeMap<String, u32>,
+    strategies: &[&[StrategyEntry]],
+) -> anyhow::Result<()> {
+    let inventory = TireInventory::new(car_no, allocation);
+    let mut no_new_sets_needed: BTreeMap<String, u32> = BTreeMap::new();
+
+    for entry in strategies.iter().flat_map(|strategy| strategy.iter()) {
+        let compound = entry.compound.to_uppercase();
+
+        match entry.tire_set {
+            Some(set_id) => match inventory.get_set(set_id) {
+                Some(set) if set.compound == compound => {}
+                Some(set) => anyhow::bail!(
+                    "Car {} re-fits tire set {} as {}, but it is a {} set!",
+                    car_no,
+                    set_id,
+                    compound,
+                    set.compound
+                ),
+                None => anyhow::bail!("Car {} re-fits tire set {} that is not allocated!", car_no, set_id),
+            },
+            None => *no_new_sets_needed.entry(compound).or_insert(0) += 1,
+        }
+    }
+
+    for (compound, no_needed) in no_new_sets_needed.iter() {
+        let no_allocated = inventory.get_no_new_sets(compound);
+
+        if *no_needed as usize > no_allocated {
+            anyhow::bail!(
+                "Car {} needs {} {} sets in the weekend, but only {} are allocated!",
+                car_no,
+                no_needed,
+                compound,
+                no_allocated
+            )
+        }
+    }
+
+    Ok(())
+}
@@ -1,8 +1,11 @@
-use serde::{Deserialize, de};
+use serde::{Deserialize, Serialize};
 
 const MAX_TIRE_PENALTY: f64 = 25.0; // Maksymalna strata: 25 sekund na okrążenie
 
-#[derive(Debug, Deserialize, Clone)]
+/// Znane mieszanki opon (slicki oraz opony deszczowe)
+pub const COMPOUNDS: [&str; 5] = ["SOFT", "MEDIUM", "HARD", "INTERMEDIATE", "WET"];
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum DegrModel {
     Lin,
@@ -12,7 +15,7 @@ pub enum DegrModel {
 /// * `degr_model` - Uproszczony model degradacji -> tylko lin (liniowy)
 /// * `k_0` - (s) Parametr degradacji -> offset dla świeżych opon
 /// * `k_1_lin` - (s/lap) Parametr degradacji (model liniowy)
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct DegrPars {
     pub degr_model: DegrModel,
     pub k_0: f64,
@@ -21,28 +24,38 @@ pub struct DegrPars {
     pub k_2_cliff: Option<f64>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Tireset {
     pub compound: String,
     pub age_tot: f64,
     pub age_cur_stint: f64,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+/// * `temp_wear_sensitivity` - (1/°C) Względna zmiana zużycia opon na stopień różnicy temperatury
+///   toru od temperatury odniesienia (tylko gdy pora dnia jest modelowana)
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct TireCompoundConfig {
     pub k1_scale: f64,
     pub default_cliff_age: f64,
     pub default_k2: f64,
     pub base_offset: f64,
+    #[serde(default)]
+    pub temp_wear_sensitivity: f64,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+fn default_defending_wear_add() -> f64 { 1.0 }
+
+/// * `defending_wear_add` - Dodatkowy współczynnik zużycia opon za obronę pozycji przez całe
+///   okrążenie (skalowany udziałem czasu obrony w okrążeniu)
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct TireConfig {
     pub soft: TireCompoundConfig,
     pub medium: TireCompoundConfig,
     pub hard: TireCompoundConfig,
     pub intermediate: TireCompoundConfig,
     pub wet: TireCompoundConfig,
+    #[serde(default = "default_defending_wear_add")]
+    pub defending_wear_add: f64,
 }
 
 impl TireConfig {
@@ -79,6 +92,19 @@ impl Tireset {
         self.calc_tire_degr(degr_pars, tire_cfg)
     }
 
+    /// get_age_over_cliff zwraca liczbę okrążeń stintu ponad wiek cliffu mieszanki (0.0 przed
+    /// cliffem). Wiek cliffu jak w `calc_tire_degr`: model liniowy używa wartości domyślnej
+    /// mieszanki, model nieliniowy parametru kierowcy (jeśli podany).
+    pub fn get_age_over_cliff(&self, degr_pars: &DegrPars, tire_cfg: &TireConfig) -> f64 {
+        let default_cliff_age = tire_cfg.for_compound(&self.compound).default_cliff_age;
+        let cliff_age = match degr_pars.degr_model {
+            DegrModel::Lin => default_cliff_age,
+            DegrModel::NonlinWithCliff => degr_pars.cliff_age.unwrap_or(default_cliff_age),
+        };
+
+        (self.age_cur_stint - cliff_age).max(0.0)
+    }
+
     /// calc_tire_degr zwraca deltę czasu degradacji opon.
     ///
     /// * `model liniowy`: t_tire_degr = k_0 + k_1_lin * age
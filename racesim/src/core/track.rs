@@ -1,38 +1,50 @@
-use serde::Deserialize;
+use crate::pre::units::{deserialize_time_s, SpeedMPerS};
+use serde::{Deserialize, Serialize};
 use std::fs::OpenOptions;
 use anyhow::{Context, Result};
 use std::path::Path;
 
+fn default_gradient_weight() -> f64 { 2.0 }
+fn default_fuel_climb_sens() -> f64 { 0.01 }
+
 /// * `name` - Track name
 /// * `t_q` - (s) Best qualifying lap time
 /// * `t_gap_racepace` - (s) Estimated gap between t_q and best race lap time (due to engine mode
-/// etc.)
+///   etc.)
 /// * `s_mass` - (s/kg) Lap time mass sensitivity
 /// * `t_drseffect` - (s) Lap time reduction when using DRS in all available DRS zones (negative)
 /// * `pit_speedlimit` - (m/s) Speed limit when driving through the pit lane
 /// * `t_loss_firstlap` - (s) Lap time loss due to the start from standstill
 /// * `d_per_gridpos` - (m) Distance between two grid positions (negative)
 /// * `d_first_gridpos` - (m) Distance between the first grid position and the finish line (can be
-/// negative or positive)
+///   negative or positive)
 /// * `length` - (m) Length of the track
 /// * `real_length_pit_zone`- (m) Real length of pit zone (required to virtually adjust pit lane
-/// speed such that a shorter or longer pit lane can be considered)
+///   speed such that a shorter or longer pit lane can be considered)
 /// * `s12` - (m) Boundary between sectors 1 and 2
 /// * `s23` - (m) Boundary between sectors 2 and 3
 /// * `drs_measurement_points` - (m) DRS measurement points
 /// * `turn_1` - (m) Distance between finish line and the first corner of the track
 /// * `pit_zone` - (m) Start and end of the pit zone (in track coordinates)
 /// * `pits_aft_finishline` - True if pits are located after the finish line, false if located
-/// before
+///   before
 /// * `overtaking_zones` - (m) Start and end of the overtaking zones
-#[derive(Debug, Deserialize, Clone)]
+/// * `gradient_weight` - (-) Influence of the track gradient on the speed multipliers (only
+///   relevant if the track file contains a `z_m` elevation column)
+/// * `fuel_climb_sens` - (-) Relative increase of the fuel consumption per 100m of climb per lap
+///
+/// `t_q` and `t_gap_racepace` can alternatively be given as strings with minutes or a unit suffix
+/// (e.g. "1:21.500", "1.2 s"), `pit_speedlimit` with a unit suffix (e.g. "80 km/h", "22.2 m/s").
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct TrackPars {
     pub name: String,
+    #[serde(deserialize_with = "deserialize_time_s")]
     pub t_q: f64,
+    #[serde(deserialize_with = "deserialize_time_s")]
     pub t_gap_racepace: f64,
     pub s_mass: f64,
     pub t_drseffect: f64,
-    pub pit_speedlimit: f64,
+    pub pit_speedlimit: SpeedMPerS,
     pub t_loss_firstlap: f64,
     pub d_per_gridpos: f64,
     pub d_first_gridpos: f64,
@@ -47,9 +59,23 @@ pub struct TrackPars {
     pub overtaking_zones: Vec<[f64; 2]>,
     #[serde(default)]
     pub corners: Vec<[f64; 2]>,
+    #[serde(default = "default_gradient_weight")]
+    pub gradient_weight: f64,
+    #[serde(default = "default_fuel_climb_sens")]
+    pub fuel_climb_sens: f64,
+}
+
+/// MiniSector is a part of the lap in which the pace of the cars is checked under VSC.
+/// * `s_start` - (m) Start of the mini sector
+/// * `laptime_frac` - Time of the mini sector as fraction of the lap time (the speed multipliers
+///   are applied, therefore the fractions do not have to sum up to 1.0)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MiniSector {
+    pub s_start: f64,
+    pub laptime_frac: f64,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Track {
     pub name: String,
     pub t_q: f64,
@@ -73,31 +99,39 @@ pub struct Track {
     pub overtaking_zones: Vec<[f64; 2]>,
     pub overtaking_zones_lap_frac: f64,
     pub corners: Vec<[f64; 2]>,
+    pub corners_lap_frac: f64,
     pub multipliers: Vec<f64>,
+    pub climb_per_lap: f64,
+    pub fuel_mult: f64,
 }
 
 
+/// CsvTrackEl is a single row of a track file. The `z_m` elevation column is optional.
 #[derive(Debug, Deserialize, Clone)]
 pub struct CsvTrackEl {
     pub x_m: f64,
     pub y_m: f64,
     pub w_tr_left_m: f64,
     pub w_tr_right_m: f64,
+    #[serde(default)]
+    pub z_m: Option<f64>,
 }
 
-// CALCULATE TRACK MULTIPLIERS ON EACH POINT
-// Fixed: Return Result<Vec<f64>> because Track needs the vector, not just min/max
-pub fn calc_track_multipliers(track_name: &str) -> Result<Vec<f64>> {
-
+/// get_trackfile_path returns the path of the track file for the given track name.
+pub fn get_trackfile_path(track_name: &str) -> std::path::PathBuf {
     let mut trackfile_path = std::path::PathBuf::new();
     trackfile_path.push("input");
     trackfile_path.push("tracks");
-    trackfile_path.push(&track_name);
+    trackfile_path.push(track_name);
     trackfile_path.set_extension("csv");
+    trackfile_path
+}
 
+/// read_track_csv reads the track centerline (and elevation if available) from a track file.
+pub fn read_track_csv(trackfile_path: &Path) -> Result<Vec<CsvTrackEl>> {
     let fh = OpenOptions::new()
         .read(true)
-        .open(&trackfile_path)
+        .open(trackfile_path)
         .context(format!(
             "Failed to open track file {}!",
             trackfile_path.to_str().unwrap_or("unknown")
@@ -111,10 +145,60 @@ pub fn calc_track_multipliers(track_name: &str) -> Result<Vec<f64>> {
         csv_track_cl.push(csv_track_el);
     }
 
+    Ok(csv_track_cl)
+}
+
+/// calc_track_gradients returns the gradient (dz/ds) at each point of the track. All gradients
+/// are zero if the track file does not contain elevation data.
+pub fn calc_track_gradients(csv_track_cl: &[CsvTrackEl]) -> Vec<f64> {
+    let n = csv_track_cl.len();
+    let mut gradients = vec![0.0; n];
+
+    if n < 2 || csv_track_cl.iter().any(|el| el.z_m.is_none()) {
+        return gradients;
+    }
+
+    // use central differences (one-sided at the ends)
+    for (i, gradient) in gradients.iter_mut().enumerate() {
+        let i_prev = i.saturating_sub(1);
+        let i_next = (i + 1).min(n - 1);
+
+        let dx = csv_track_cl[i_next].x_m - csv_track_cl[i_prev].x_m;
+        let dy = csv_track_cl[i_next].y_m - csv_track_cl[i_prev].y_m;
+        let ds = (dx * dx + dy * dy).sqrt();
+
+        if ds > 0.0 {
+            *gradient =
+                (csv_track_cl[i_next].z_m.unwrap() - csv_track_cl[i_prev].z_m.unwrap()) / ds;
+        }
+    }
+
+    gradients
+}
+
+/// calc_climb_per_lap returns the summed up positive elevation change of one lap in meters.
+pub fn calc_climb_per_lap(csv_track_cl: &[CsvTrackEl]) -> f64 {
+    let n = csv_track_cl.len();
+
+    if n < 2 || csv_track_cl.iter().any(|el| el.z_m.is_none()) {
+        return 0.0;
+    }
+
+    (0..n)
+        .map(|i| csv_track_cl[(i + 1) % n].z_m.unwrap() - csv_track_cl[i].z_m.unwrap())
+        .filter(|dz| *dz > 0.0)
+        .sum()
+}
+
+// CALCULATE TRACK MULTIPLIERS ON EACH POINT
+// Fixed: Return Result<Vec<f64>> because Track needs the vector, not just min/max
+// Uphill sections (positive gradient) are slowed down, downhill sections sped up according to
+// gradient_weight.
+pub fn calc_track_multipliers(csv_track_cl: &[CsvTrackEl], gradient_weight: f64) -> Vec<f64> {
     let n = csv_track_cl.len();
     if n < 3 {
         // Return a default vector of 1.0s if track is too short
-        return Ok(vec![1.0; n.max(1)]); 
+        return vec![1.0; n.max(1)];
     }
 
     // Compute distances
@@ -157,6 +241,7 @@ pub fn calc_track_multipliers(track_name: &str) -> Result<Vec<f64>> {
     kappa[n - 1] = kappa[n - 2];
 
     // Compute raw multipliers
+    let gradients = calc_track_gradients(csv_track_cl);
     let mut raw_multi: Vec<f64> = vec![0.0; n];
     for i in 0..n {
         raw_multi[i] = 1.0 / (1.0 + kappa[i]);
@@ -164,6 +249,8 @@ pub fn calc_track_multipliers(track_name: &str) -> Result<Vec<f64>> {
         raw_multi[i] = raw_multi[i].powf(100.0);
         // minimum 0.1 multiplier
         raw_multi[i] = raw_multi[i].max(0.5);
+        // gradient: uphill slower, downhill faster
+        raw_multi[i] *= (1.0 - gradient_weight * gradients[i]).clamp(0.5, 1.5);
     }
 
     // Normalize multipliers
@@ -177,14 +264,18 @@ pub fn calc_track_multipliers(track_name: &str) -> Result<Vec<f64>> {
         };
     }
 
-    println!("Track Multipliers for {}: {:?}", track_name, multi);
-
-    Ok(multi) // Return the vector
+    multi
 }
 
 
 impl Track {
     pub fn new(track_pars: &TrackPars) -> Track {
+        Track::with_trackfile(track_pars, &get_trackfile_path(track_pars.name.as_str()))
+    }
+
+    /// with_trackfile creates the track with the speed multipliers calculated from the given track
+    /// file instead of the default track file of the track name.
+    pub fn with_trackfile(track_pars: &TrackPars, trackfile_path: &Path) -> Track {
         // determine track distance that is covered by the pit lane when driving through it
         let track_length_pit_zone = if track_pars.pit_zone[0] < track_pars.pit_zone[1] {
             track_pars.pit_zone[1] - track_pars.pit_zone[0]
@@ -205,15 +296,40 @@ impl Track {
 
         let overtaking_zones_lap_frac = len_overtaking_zones / track_pars.length;
 
+        // calculate corners lap fraction
+        let mut len_corners = 0.0;
+
+        for corner in track_pars.corners.iter() {
+            len_corners += if corner[0] < corner[1] {
+                corner[1] - corner[0]
+            } else {
+                track_pars.length - corner[0] + corner[1]
+            };
+        }
+
+        let corners_lap_frac = len_corners / track_pars.length;
+
         // calculate turn 1 lap fraction
         let turn_1_lap_frac = (track_pars.turn_1 - track_pars.d_first_gridpos) / track_pars.length;
 
-        // Calculate multipliers
+        // Calculate multipliers and climb per lap
         // We handle the error gracefully by defaulting to an empty vector or 1.0s if file fails
-        let multipliers = calc_track_multipliers(track_pars.name.as_str()).unwrap_or_else(|e| {
-            eprintln!("Warning: Could not calc multipliers: {}. Defaulting to 1.0", e);
-            vec![1.0] 
-        });
+        let (multipliers, climb_per_lap) =
+            match read_track_csv(trackfile_path) {
+                Ok(csv_track_cl) => {
+                    let multipliers =
+                        calc_track_multipliers(&csv_track_cl, track_pars.gradient_weight);
+                    println!("Track Multipliers for {}: {:?}", track_pars.name, multipliers);
+                    (multipliers, calc_climb_per_lap(&csv_track_cl))
+                }
+                Err(e) => {
+                    eprintln!("Warning: Could not calc multipliers: {}. Defaulting to 1.0", e);
+                    (vec![1.0], 0.0)
+                }
+            };
+
+        // climb-heavy tracks increase the fuel consumption
+        let fuel_mult = 1.0 + track_pars.fuel_climb_sens * climb_per_lap / 100.0;
 
         // create track
         Track {
@@ -222,7 +338,7 @@ impl Track {
             t_gap_racepace: track_pars.t_gap_racepace,
             s_mass: track_pars.s_mass,
             t_drseffect: track_pars.t_drseffect,
-            pit_speedlimit: track_pars.pit_speedlimit,
+            pit_speedlimit: track_pars.pit_speedlimit.m_s,
             t_loss_firstlap: track_pars.t_loss_firstlap,
             d_per_gridpos: track_pars.d_per_gridpos,
             d_first_gridpos: track_pars.d_first_gridpos,
@@ -239,7 +355,10 @@ impl Track {
             pit_zone: track_pars.pit_zone,
             overtaking_zones: track_pars.overtaking_zones.to_owned(),
             corners: track_pars.corners.to_owned(),
+            corners_lap_frac,
             multipliers,
+            climb_per_lap,
+            fuel_mult,
         }
     }
 
@@ -260,6 +379,63 @@ impl Track {
         false
     }
 
+    /// The method returns the sector (0, 1 or 2) in which the given track position is located.
+    /// Positions behind the finish line (negative, e.g. on the grid) belong to the last sector.
+    pub fn get_sector(&self, s_track: f64) -> usize {
+        let s_track = s_track.rem_euclid(self.length);
+
+        if s_track < self.s12 {
+            0
+        } else if s_track < self.s23 {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// The method returns the speed multiplier (curvature based) at the given track position. A
+    /// value below 1.0 indicates a corner, a value above 1.0 a straight.
+    pub fn get_multiplier(&self, s_track: f64) -> f64 {
+        let mult_count = self.multipliers.len();
+
+        if mult_count == 0 {
+            return 1.0;
+        }
+
+        let idx_m = ((s_track / self.length) * mult_count as f64) as usize;
+        self.multipliers[idx_m.min(mult_count - 1)]
+    }
+
+    /// get_mini_sectors splits the lap into `no_mini_sectors` mini sectors of equal length. The
+    /// time of every mini sector follows from the speed multipliers it covers (at the resolution of
+    /// `multipliers`), such that a car driving the lap time `t` needs `t * laptime_frac` for it.
+    pub fn get_mini_sectors(&self, no_mini_sectors: usize) -> Vec<MiniSector> {
+        let no_mini_sectors = no_mini_sectors.max(1);
+        let mult_count = self.multipliers.len().max(1);
+
+        (0..no_mini_sectors)
+            .map(|i| {
+                let lap_frac_start = i as f64 / no_mini_sectors as f64;
+                let lap_frac_end = (i + 1) as f64 / no_mini_sectors as f64;
+
+                // overlap with every multiplier section (1/multiplier = time per distance)
+                let laptime_frac = (0..mult_count)
+                    .map(|k| {
+                        let overlap = (lap_frac_end.min((k + 1) as f64 / mult_count as f64)
+                            - lap_frac_start.max(k as f64 / mult_count as f64))
+                        .max(0.0);
+                        overlap / self.multipliers.get(k).copied().unwrap_or(1.0)
+                    })
+                    .sum();
+
+                MiniSector {
+                    s_start: lap_frac_start * self.length,
+                    laptime_frac,
+                }
+            })
+            .collect()
+    }
+
     /// The method returns the approximate time loss when driving through the pit lane.
     pub fn get_pit_drive_timeloss(&self) -> f64 {
         let pit_zone_lap_frac = self.track_length_pit_zone / self.length;
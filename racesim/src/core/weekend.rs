@@ -0,0 +1,142 @@
+use crate::core::car::StrategyEntry;
+use crate::core::race::{Race, SimConstants};
+use crate::core::tire_inventory::{validate_tire_allocation, TireInventory};
+use crate::core::tireset::TireConfig;
+use crate::post::race_result::RaceResult;
+use crate::pre::read_sim_pars::SimPars;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// WeekendPars contains the parameters of a sprint weekend.
+/// * `sprint` - Parameters of the sprint
+/// * `race` - Parameters of the main race
+/// * `tire_allocation` - Number of tire sets per compound available to every car for both sessions
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct WeekendPars {
+    pub sprint: SimPars,
+    pub race: SimPars,
+    pub tire_allocation: BTreeMap<String, u32>,
+}
+
+/// WeekendResult contains the results of both sessions of a sprint weekend. The tire inventories
+/// in the results show the state of the allocation at the end of each session.
+#[derive(Debug, Clone)]
+pub struct WeekendResult {
+    pub sprint: RaceResult,
+    pub race: RaceResult,
+}
+
+impl WeekendPars {
+    /// get_car_nos returns the car numbers of all participants of the weekend.
+    pub fn get_car_nos(&self) -> BTreeSet<u32> {
+        self.sprint
+            .race_pars
+            .participants
+            .iter()
+            .chain(self.race.race_pars.participants.iter())
+            .copied()
+            .collect()
+    }
+
+    /// validate checks that no car needs more tire sets than allocated across both sessions.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for car_no in self.get_car_nos().iter() {
+            let strategies: Vec<&[StrategyEntry]> = [&self.sprint, &self.race]
+                .iter()
+                .copied()
+                .filter(|sim_pars| sim_pars.race_pars.participants.contains(car_no))
+                .filter_map(|sim_pars| sim_pars.car_pars_all.get(car_no))
+                .map(|car_pars| car_pars.strategy.as_slice())
+                .collect();
+
+            validate_tire_allocation(*car_no, &self.tire_allocation, &strategies)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// run_weekend simulates the sprint and the main race of a weekend (without GUI). The tire
+/// inventory of every car is carried over from the sprint to the main race, i.e. sets used in the
+/// sprint are no longer new and keep their age when re-fitted.
+pub fn run_weekend(
+    weekend_pars: &WeekendPars,
+    sim_consts: &SimConstants,
+    tire_config: &TireConfig,
+    timestep_size: f64,
+) -> anyhow::Result<WeekendResult> {
+    weekend_pars
+        .validate()
+        .context("Weekend plan does not fit the tire allocation!")?;
+
+    let mut inventories: BTreeMap<u32, TireInventory> = weekend_pars
+        .get_car_nos()
+        .into_iter()
+        .map(|car_no| (car_no, TireInventory::new(car_no, &weekend_pars.tire_allocation)))
+        .collect();
+
+    let sprint = run_session(
+        &weekend_pars.sprint,
+        sim_consts,
+        tire_config,
+        timestep_size,
+        &mut inventories,
+        "Sprint",
+    )?;
+    let race = run_session(
+        &weekend_pars.race,
+        sim_consts,
+        tire_config,
+        timestep_size,
+        &mut inventories,
+        "Race",
+    )?;
+
+    Ok(WeekendResult { sprint, race })
+}
+
+/// run_session simulates a single session with the tire inventories of its participants and
+/// updates the inventories afterwards.
+fn run_session(
+    sim_pars: &SimPars,
+    sim_consts: &SimConstants,
+    tire_config: &TireConfig,
+    timestep_size: f64,
+    inventories: &mut BTreeMap<u32, TireInventory>,
+    session: &str,
+) -> anyhow::Result<RaceResult> {
+    sim_pars.check_sim_consts(sim_consts)?;
+
+    let mut race = Race::new(
+        &sim_pars.race_pars,
+        sim_consts,
+        tire_config,
+        &sim_pars.track_pars,
+        &sim_pars.driver_pars_all,
+        &sim_pars.car_pars_all,
+        timestep_size,
+    );
+    race.print_events = false;
+
+    let inventories_session: Vec<TireInventory> = sim_pars
+        .race_pars
+        .participants
+        .iter()
+        .filter_map(|car_no| inventories.get(car_no).cloned())
+        .collect();
+    race.attach_tire_inventories(inventories_session, session)
+        .context(format!("Failed to fit the start tires of the {}!", session))?;
+
+    while !race.get_all_finished() {
+        race.simulate_timestep();
+    }
+
+    let race_result = race.get_race_result();
+
+    for inventory in race_result.tire_inventories.iter() {
+        inventories.insert(inventory.car_no, inventory.to_owned());
+    }
+
+    Ok(race_result)
+}
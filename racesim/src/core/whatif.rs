@@ -0,0 +1,380 @@
+use crate::core::race::{Race, SimConstants};
+use crate::core::tireset::TireConfig;
+use crate::post::race_result::RaceResult;
+use crate::pre::read_sim_pars::SimPars;
+use anyhow::Context;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::fs::OpenOptions;
+use std::path::Path;
+
+/// Checkpoint describes a race interrupted after a leader lap. It contains everything needed to
+/// simulate the history up to this lap again in exactly the same way (inputs and seed), the
+/// randomness after the checkpoint is drawn anew on resumption.
+/// * `sim_pars` - Simulation parameters of the race
+/// * `sim_consts` - Simulation constants
+/// * `tire_config` - Tire configuration
+/// * `timestep_size` - (s) Simulation timestep size
+/// * `seed` - Seed of the history
+/// * `lap` - Leader lap after which the race is resumed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub sim_pars: SimPars,
+    pub sim_consts: SimConstants,
+    pub tire_config: TireConfig,
+    pub timestep_size: f64,
+    pub seed: u64,
+    pub lap: u32,
+}
+
+impl Checkpoint {
+    /// create simulates the race until the leader completed `lap` and returns the checkpoint of
+    /// this moment. The seed of the race parameters is used for the history if given, otherwise a
+    /// random one.
+    pub fn create(
+        sim_pars: &SimPars,
+        sim_consts: &SimConstants,
+        tire_config: &TireConfig,
+        timestep_size: f64,
+        lap: u32,
+    ) -> anyhow::Result<Checkpoint> {
+        let mut sim_pars = sim_pars.to_owned();
+        let seed = sim_pars.fix_seed(None);
+
+        let checkpoint = Checkpoint {
+            sim_pars,
+            sim_consts: sim_consts.to_owned(),
+            tire_config: tire_config.to_owned(),
+            timestep_size,
+            seed,
+            lap,
+        };
+
+        // the race must still be running at the checkpoint
+        checkpoint.restore()?;
+        Ok(checkpoint)
+    }
+
+    /// restore simulates the history of the checkpoint and returns the race at the end of the
+    /// checkpoint lap.
+    pub fn restore(&self) -> anyhow::Result<Race> {
+        self.sim_pars.check_sim_consts(&self.sim_consts)?;
+
+        let mut race_pars = self.sim_pars.race_pars.to_owned();
+        race_pars.seed = Some(self.seed);
+
+        let mut race = Race::new(
+            &race_pars,
+            &self.sim_consts,
+            &self.tire_config,
+            &self.sim_pars.track_pars,
+            &self.sim_pars.driver_pars_all,
+            &self.sim_pars.car_pars_all,
+            self.timestep_size,
+        );
+        race.print_events = false;
+        race.simulate_until_leader_lap(self.lap);
+
+        if race.get_all_finished() {
+            anyhow::bail!("Race was already finished before lap {}!", self.lap)
+        }
+
+        Ok(race)
+    }
+
+    /// write_to_file writes the checkpoint as JSON file.
+    pub fn write_to_file(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let fh = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+            .context(format!("Failed to open checkpoint file {}!", path.display()))?;
+
+        serde_json::to_writer(&fh, self)
+            .context(format!("Failed to write checkpoint file {}!", path.display()))?;
+        Ok(())
+    }
+}
+
+/// read_checkpoint reads a checkpoint written by `Checkpoint::write_to_file`.
+pub fn read_checkpoint(path: &Path) -> anyhow::Result<Checkpoint> {
+    let fh = OpenOptions::new()
+        .read(true)
+        .open(path)
+        .context(format!("Failed to open checkpoint file {}!", path.display()))?;
+
+    let checkpoint = serde_json::from_reader(&fh)
+        .context(format!("Failed to parse checkpoint file {}!", path.display()))?;
+    Ok(checkpoint)
+}
+
+/// WhatIfChange describes a single modified strategy decision, e.g. parsed from
+/// `car_pars_all.44.strategy.2.inlap=34`.
+/// * `car_no` - Car number
+/// * `stop_idx` - Index of the strategy entry (entry 0 contains the start compound and cannot be
+///   changed)
+/// * `inlap` - New inlap of the pit stop (OPTIONAL)
+/// * `compound` - New compound fitted during the pit stop (OPTIONAL)
+#[derive(Debug, Clone, PartialEq)]
+pub struct WhatIfChange {
+    pub car_no: u32,
+    pub stop_idx: usize,
+    pub inlap: Option<u32>,
+    pub compound: Option<String>,
+}
+
+/// parse_whatif_change parses a change of the form
+/// `car_pars_all.<car_no>.strategy.<stop_idx>.<inlap|compound>=<value>`.
+pub fn parse_whatif_change(change: &str) -> anyhow::Result<WhatIfChange> {
+    let (path, value) = change
+        .split_once('=')
+        .context(format!("Change {} must have the form <path>=<value>!", change))?;
+    let parts: Vec<&str> = path.trim().split('.').collect();
+
+    if parts.len() != 5 || parts[0] != "car_pars_all" || parts[2] != "strategy" {
+        anyhow::bail!(
+            "Change {} must have the form car_pars_all.<car_no>.strategy.<stop>.<field>=<value>!",
+            change
+        )
+    }
+
+    let car_no = parts[1]
+        .parse::<u32>()
+        .context(format!("Invalid car number {}!", parts[1]))?;
+    let stop_idx = parts[3]
+        .parse::<usize>()
+        .context(format!("Invalid strategy index {}!", parts[3]))?;
+    let value = value.trim();
+
+    let (inlap, compound) = match parts[4] {
+        "inlap" => (
+            Some(value.parse::<u32>().context(format!("Invalid inlap {}!", value))?),
+            None,
+        ),
+        "compound" => (None, Some(value.to_uppercase())),
+        field => anyhow::bail!("Field {} cannot be changed (only inlap and compound)!", field),
+    };
+
+    Ok(WhatIfChange {
+        car_no,
+        stop_idx,
+        inlap,
+        compound,
+    })
+}
+
+/// apply_whatif_change applies the change to the remaining strategy of the car in the live race.
+/// Pit stops that were already made cannot be changed and two pit stops cannot share an inlap.
+pub fn apply_whatif_change(race: &mut Race, change: &WhatIfChange) -> anyhow::Result<()> {
+    let tot_no_laps = race.tot_no_laps;
+    let idx = race
+        .get_car_idx(change.car_no)
+        .context(format!("Car {} is not part of the race!", change.car_no))?;
+    let car = &mut race.cars_list[idx];
+    let compl_lap = car.sh.get_compl_lap();
+    let mut strategy = car.get_strategy().to_vec();
+
+    if change.stop_idx == 0 || change.stop_idx >= strategy.len() {
+        anyhow::bail!(
+            "Car {} has no pit stop with strategy index {}!",
+            change.car_no,
+            change.stop_idx
+        )
+    }
+
+    if strategy[change.stop_idx].inlap <= compl_lap {
+        anyhow::bail!(
+            "Pit stop {} of car {} was already made in lap {}!",
+            change.stop_idx,
+            change.car_no,
+            strategy[change.stop_idx].inlap
+        )
+    }
+
+    if let Some(inlap) = change.inlap {
+        if inlap <= compl_lap || inlap >= tot_no_laps {
+            anyhow::bail!(
+                "Inlap {} must be in the range [{}, {}]!",
+                inlap,
+                compl_lap + 1,
+                tot_no_laps - 1
+            )
+        }
+
+        let duplicate = strategy
+            .iter()
+            .enumerate()
+            .any(|(i, entry)| i > 0 && i != change.stop_idx && entry.inlap == inlap);
+        if duplicate {
+            anyhow::bail!("Car {} already has a pit stop in lap {}!", change.car_no, inlap)
+        }
+
+        strategy[change.stop_idx].inlap = inlap;
+    }
+
+    if let Some(compound) = &change.compound {
+        strategy[change.stop_idx].compound = compound.to_owned();
+    }
+
+    car.replace_future_strategy(compl_lap, &strategy[1..]);
+    Ok(())
+}
+
+/// OutcomeStats summarizes the outcomes of a single car over several runs.
+/// * `no_runs` - Number of runs
+/// * `positions` - Final position in every run
+/// * `mean_position` - Mean final position
+/// * `mean_racetime` - (s) Mean final race time of the runs in which the car finished
+/// * `no_dnf` - Number of runs in which the car did not finish
+#[derive(Debug, Clone)]
+pub struct OutcomeStats {
+    pub no_runs: usize,
+    pub positions: Vec<u32>,
+    pub mean_position: f64,
+    pub mean_racetime: f64,
+    pub no_dnf: usize,
+}
+
+impl OutcomeStats {
+    /// from_results collects the outcomes of the car with the given index.
+    pub fn from_results(results: &[RaceResult], idx: usize) -> OutcomeStats {
+        let positions: Vec<u32> = results
+            .iter()
+            .map(|result| result.get_final_positions()[idx])
+            .collect();
+
+        let racetimes_finished: Vec<f64> = results
+            .iter()
+            .map(|result| result.racetimes[idx][result.tot_no_laps as usize])
+            .filter(|racetime| *racetime > 0.0)
+            .collect();
+
+        OutcomeStats {
+            no_runs: results.len(),
+            mean_position: positions.iter().map(|&x| x as f64).sum::<f64>()
+                / positions.len().max(1) as f64,
+            positions,
+            mean_racetime: racetimes_finished.iter().sum::<f64>()
+                / racetimes_finished.len().max(1) as f64,
+            no_dnf: results.len() - racetimes_finished.len(),
+        }
+    }
+}
+
+/// WhatIfComparison contains the outcome distributions of the unmodified and the modified
+/// continuation of the race.
+#[derive(Debug, Clone)]
+pub struct WhatIfComparison {
+    pub car_no: u32,
+    pub resume_lap: u32,
+    pub baseline: OutcomeStats,
+    pub modified: OutcomeStats,
+}
+
+impl WhatIfComparison {
+    /// get_prob_modified_ahead returns the probability that a random modified outcome ends ahead
+    /// of a random baseline outcome (ties count half).
+    pub fn get_prob_modified_ahead(&self) -> f64 {
+        let mut score = 0.0;
+
+        for pos_mod in self.modified.positions.iter() {
+            for pos_base in self.baseline.positions.iter() {
+                if pos_mod < pos_base {
+                    score += 1.0;
+                } else if pos_mod == pos_base {
+                    score += 0.5;
+                }
+            }
+        }
+
+        let no_pairs = self.modified.positions.len() * self.baseline.positions.len();
+        score / no_pairs.max(1) as f64
+    }
+
+    /// format creates the comparison table as it is printed to the console.
+    pub fn format(&self) -> Result<String, std::fmt::Error> {
+        let mut tmp_string = String::new();
+
+        writeln!(
+            &mut tmp_string,
+            "What-if for car {} resumed after lap {} ({} runs each)",
+            self.car_no, self.resume_lap, self.baseline.no_runs
+        )?;
+        writeln!(
+            &mut tmp_string,
+            "{:10} {:>10} {:>16} {:>5}",
+            "", "mean pos", "mean racetime", "DNF"
+        )?;
+
+        for (label, stats) in [("baseline", &self.baseline), ("modified", &self.modified)].iter() {
+            writeln!(
+                &mut tmp_string,
+                "{:10} {:10.2} {:14.3} s {:5}",
+                label, stats.mean_position, stats.mean_racetime, stats.no_dnf
+            )?;
+        }
+
+        writeln!(
+            &mut tmp_string,
+            "P(modified ahead of baseline): {:.1}%",
+            100.0 * self.get_prob_modified_ahead()
+        )?;
+
+        Ok(tmp_string)
+    }
+}
+
+/// finish_race continues a copy of the race with the given seed until it is finished and returns
+/// the results.
+fn finish_race(race_start: &Race, seed: u64) -> RaceResult {
+    let mut race = race_start.clone();
+    race.reseed(seed);
+
+    while !race.get_all_finished() {
+        race.simulate_timestep();
+    }
+
+    race.get_race_result()
+}
+
+/// run_whatif resumes the race of the checkpoint and continues it `no_runs` times with and without
+/// the change. The history up to the checkpoint is the same in all runs, the remaining stochastic
+/// elements are drawn with a fresh seed in every run (the same seed for both variants of a run).
+pub fn run_whatif(
+    checkpoint: &Checkpoint,
+    change: &WhatIfChange,
+    no_runs: u32,
+) -> anyhow::Result<WhatIfComparison> {
+    let race_baseline = checkpoint.restore()?;
+
+    let idx = race_baseline
+        .get_car_idx(change.car_no)
+        .context(format!("Car {} is not part of the race!", change.car_no))?;
+
+    let mut race_modified = race_baseline.clone();
+    apply_whatif_change(&mut race_modified, change)?;
+
+    // continue both variants of the race
+    let mut results_baseline = Vec::with_capacity(no_runs as usize);
+    let mut results_modified = Vec::with_capacity(no_runs as usize);
+    let mut rng = rand::thread_rng();
+
+    for _ in 0..no_runs {
+        let seed = rng.gen();
+        results_baseline.push(finish_race(&race_baseline, seed));
+        results_modified.push(finish_race(&race_modified, seed));
+    }
+
+    Ok(WhatIfComparison {
+        car_no: change.car_no,
+        resume_lap: checkpoint.lap,
+        baseline: OutcomeStats::from_results(&results_baseline, idx),
+        modified: OutcomeStats::from_results(&results_modified, idx),
+    })
+}
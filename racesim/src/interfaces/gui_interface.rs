@@ -1,7 +1,11 @@
-use crate::core::race::FlagState;
+use crate::core::car::CarStatus;
+use crate::core::livery::resolve_car_color;
+use crate::core::practice::PracticeResult;
+use crate::core::race::{FlagState, Race, WeatherState};
 use crate::post::race_result::RaceResult;
-
-pub const MAX_GUI_UPDATE_FREQUENCY: f64 = 20.0;
+use anyhow::Context;
+use flume::{Receiver, Sender};
+use std::time::Instant;
 
 #[derive(Debug, Clone, Default)]
 pub struct RgbColor {
@@ -17,19 +21,318 @@ pub struct CarState {
     pub color: RgbColor,
     pub race_prog: f64,
     pub velocity: f64,
+    pub drs_act: bool,
+    pub no_pitstops: u32,
+    pub t_pitlane: f64,
+    // inlaps of the pit stops performed so far
+    pub pit_stop_laps: Vec<u32>,
+    // live gap (s) to the car ahead, measured at the last timing point passed by both cars
+    pub interval_ahead_s: Option<f64>,
+    // true if the car retired (it stays at its stopping position)
+    pub is_dnf: bool,
+    // race time (s) of the retirement
+    pub t_retired_s: Option<f64>,
+    // true if the car saves fuel (lift and coast) to reach the finish
+    pub fuel_save_act: bool,
+    // state of charge (0.0 - 1.0) of the ERS battery (0.0 without the ERS model)
+    pub ers_soc: f64,
+    // true if the car overheated in dirty air and has to drop back
+    pub overheat_act: bool,
+}
+
+/// RetiredCar contains a retirement as listed in the GUI.
+/// * `car_no` - Car number
+/// * `driver_initials` - Initials of the driver
+/// * `lap` - Number of laps completed before the retirement
+/// * `time_s` - (s) Race time of the retirement
+/// * `reason` - Kind of the event that caused the retirement (e.g. "Crash")
+#[derive(Debug, Clone, Default)]
+pub struct RetiredCar {
+    pub car_no: u32,
+    pub driver_initials: String,
+    pub lap: u32,
+    pub time_s: f64,
+    pub reason: String,
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct RaceState {
     pub car_states: Vec<CarState>,
+    // most severe flag of all sectors (e.g. for the banner)
     pub flag_state: FlagState,
+    // flag of each sector (sectors 1 to 3) to color the centerline in the GUI
+    pub sector_flag_states: [FlagState; 3],
+
+    // current race time (s), e.g. for the time of day in the GUI
+    pub cur_racetime_s: f64,
+    // track temperature (°C) if the time of day is modeled
+    pub track_temp_c: Option<f64>,
 
     pub sc_active: bool,
     pub sc_race_prog: f64,
+    // the SC period is over, the SC drives to the pit entry (or the finish line) and peels off
+    pub sc_in_this_lap: bool,
+    // progress of the incident clearance by the marshals (0.0 - 1.0) while the SC is out
+    pub sc_clearance_prog: Option<f64>,
+
+    // formation lap before the start (the cars drive in grid order, the race time does not run)
+    pub formation_lap_act: bool,
 
     // true if raining (for GUI effects)
     pub weather_is_rain: bool,
 
+    // DRS trains (car numbers in running order) to link their DRS indicators
+    pub drs_trains: Vec<Vec<u32>>,
+
+    // track sections (m) with debris (local yellow) to color them in the GUI
+    pub debris_zones: Vec<[f64; 2]>,
+
+    // track sections (m) with a local yellow flag around a stopped car
+    pub yellow_zones: Vec<[f64; 2]>,
+
+    // retired cars in order of retirement
+    pub retirements: Vec<RetiredCar>,
+
+    // lap time breakdown of the laps completed so far (practice mode only)
+    pub practice: Option<PracticeResult>,
+
     // final results payload (sent once when race finishes)
     pub final_result: Option<RaceResult>,
 }
+
+/// RaceStateBuilder assembles the race states sent to the GUI (and to the recording) from the
+/// current state of a race. The car colors are resolved once when the builder is created, since
+/// they do not change during the race.
+#[derive(Debug, Clone)]
+pub struct RaceStateBuilder {
+    car_colors: Vec<RgbColor>,
+}
+
+impl RaceStateBuilder {
+    /// new creates the builder for the given race (fails if a car color cannot be parsed).
+    pub fn new(race: &Race) -> anyhow::Result<RaceStateBuilder> {
+        let mut car_colors = Vec::with_capacity(race.cars_list.len());
+
+        for car in race.cars_list.iter() {
+            let tmp_color = resolve_car_color(&car.color, car.car_no)?;
+
+            car_colors.push(RgbColor {
+                r: tmp_color.rgb[0],
+                g: tmp_color.rgb[1],
+                b: tmp_color.rgb[2],
+            });
+        }
+
+        Ok(RaceStateBuilder { car_colors })
+    }
+
+    /// build assembles the race state of the given race in a single call. Use `new` and
+    /// `build_state` to assemble the states of every timestep.
+    pub fn build(race: &Race) -> anyhow::Result<RaceState> {
+        Ok(RaceStateBuilder::new(race)?.build_state(race))
+    }
+
+    /// get_car_colors returns the resolved car colors (same order as the cars of the race).
+    pub fn get_car_colors(&self) -> &[RgbColor] {
+        &self.car_colors
+    }
+
+    /// get_sc_race_prog returns the race progress of the safety car (None if it is not on track).
+    pub fn get_sc_race_prog(race: &Race) -> Option<f64> {
+        if race.safety_car.active {
+            Some(race.safety_car.lap as f64 + race.safety_car.s_track / race.track.length)
+        } else {
+            None
+        }
+    }
+
+    /// get_retirements returns the retired cars of the race in order of retirement.
+    pub fn get_retirements(race: &Race) -> Vec<RetiredCar> {
+        let mut retirements: Vec<RetiredCar> = race
+            .cars_list
+            .iter()
+            .zip(race.get_retirements().iter())
+            .filter_map(|(car, retirement)| {
+                retirement.as_ref().map(|retirement| RetiredCar {
+                    car_no: car.car_no,
+                    driver_initials: car.driver.initials.to_owned(),
+                    lap: retirement.lap,
+                    time_s: retirement.time_s,
+                    reason: race.get_retirement_reason(car.car_no).unwrap_or("DNF").to_owned(),
+                })
+            })
+            .collect();
+
+        retirements.sort_by(|a, b| {
+            a.time_s
+                .partial_cmp(&b.time_s)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        retirements
+    }
+
+    /// build_state assembles the race state of the current timestep of the race.
+    pub fn build_state(&self, race: &Race) -> RaceState {
+        let mut race_state = RaceState {
+            car_states: Vec::with_capacity(race.cars_list.len()),
+            flag_state: race.get_most_severe_flag_state(),
+            sector_flag_states: race.get_sector_flag_states(),
+            cur_racetime_s: race.cur_racetime,
+            track_temp_c: race.get_track_temp(),
+            sc_active: race.safety_car.active,
+            sc_race_prog: RaceStateBuilder::get_sc_race_prog(race).unwrap_or(0.0),
+            sc_in_this_lap: race.safety_car.active && race.safety_car.in_this_lap,
+            sc_clearance_prog: race.get_incident_clearance_prog(),
+            formation_lap_act: race.is_formation_lap(),
+            weather_is_rain: matches!(race.weather_state, WeatherState::Rain),
+            drs_trains: race.get_drs_trains().to_vec(),
+            debris_zones: race.get_debris_zones().iter().map(|x| x.s_range).collect(),
+            yellow_zones: race.get_yellow_zones().iter().map(|x| x.s_range).collect(),
+            retirements: RaceStateBuilder::get_retirements(race),
+            practice: None,
+            final_result: None,
+        };
+
+        let intervals_ahead = race.get_intervals_ahead();
+
+        for (i, car) in race.cars_list.iter().enumerate() {
+            let velocity = if race.is_formation_lap() {
+                race.track.length / race.get_t_formation_lap()
+            } else if car.sh.pit_standstill_act {
+                0.0
+            } else if car.sh.pit_act {
+                race.track.pit_speedlimit
+            } else {
+                (race.track.length / race.cur_laptimes[i]).min(car.driver.vel_max / 3.6)
+            };
+
+            race_state.car_states.push(CarState {
+                car_no: car.car_no,
+                driver_initials: car.driver.initials.to_owned(),
+                color: self.car_colors[i].to_owned(),
+                race_prog: race
+                    .get_formation_race_prog(i)
+                    .unwrap_or_else(|| car.sh.get_race_prog()),
+                velocity,
+                drs_act: car.sh.drs_act,
+                no_pitstops: car.no_pitstops,
+                t_pitlane: race.get_t_pitlane()[i],
+                pit_stop_laps: race
+                    .get_pit_stops()
+                    .iter()
+                    .filter(|pit_stop| pit_stop.car_no == car.car_no)
+                    .map(|pit_stop| pit_stop.lap)
+                    .collect(),
+                interval_ahead_s: intervals_ahead[i],
+                is_dnf: car.status == CarStatus::DNF,
+                t_retired_s: race.get_retirements()[i].as_ref().map(|x| x.time_s),
+                fuel_save_act: car.status == CarStatus::Running && car.is_fuel_save(),
+                ers_soc: car.get_ers_soc(),
+                overheat_act: car.status == CarStatus::Running && car.get_overheat_t_loss() > 0.0,
+            });
+        }
+
+        race_state
+    }
+
+    /// build_final assembles the race state that is sent once after the race has finished. It
+    /// contains no car states, but the final results.
+    pub fn build_final(&self, race: &Race, result: RaceResult) -> RaceState {
+        RaceState {
+            car_states: Vec::new(),
+            flag_state: race.get_most_severe_flag_state(),
+            sector_flag_states: race.get_sector_flag_states(),
+            cur_racetime_s: race.cur_racetime,
+            track_temp_c: race.get_track_temp(),
+            sc_active: result.sc_active,
+            sc_race_prog: if result.sc_active {
+                result.sc_position / race.track.length
+            } else {
+                0.0
+            },
+            sc_in_this_lap: false,
+            sc_clearance_prog: None,
+            formation_lap_act: false,
+            weather_is_rain: matches!(race.weather_state, WeatherState::Rain),
+            drs_trains: Vec::new(),
+            debris_zones: Vec::new(),
+            yellow_zones: Vec::new(),
+            retirements: RaceStateBuilder::get_retirements(race),
+            practice: None,
+            final_result: Some(result),
+        }
+    }
+}
+
+/// SimControl contains the commands the GUI can send to the real-time simulation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SimControl {
+    Pause(bool),
+    SetRealtimeFactor(f64),
+}
+
+/// GuiSender owns the channel to the GUI and throttles the race state updates to the configured
+/// update frequency (in wall-clock time, i.e. independent of the real-time factor).
+#[derive(Debug)]
+pub struct GuiSender {
+    tx: Sender<RaceState>,
+    rx_control: Option<Receiver<SimControl>>,
+    update_frequency: f64,
+    t_last_send: Option<Instant>,
+}
+
+impl GuiSender {
+    /// new creates the sender for the given update frequency (Hz), which must be positive.
+    pub fn new(tx: Sender<RaceState>, update_frequency: f64) -> anyhow::Result<GuiSender> {
+        check_gui_update_frequency(update_frequency)?;
+
+        Ok(GuiSender {
+            tx,
+            rx_control: None,
+            update_frequency,
+            t_last_send: None,
+        })
+    }
+
+    /// with_control sets the channel on which the GUI sends control commands (pause, speed).
+    pub fn with_control(mut self, rx_control: Receiver<SimControl>) -> GuiSender {
+        self.rx_control = Some(rx_control);
+        self
+    }
+
+    /// get_controls returns the control commands received since the last call.
+    pub fn get_controls(&self) -> Vec<SimControl> {
+        match &self.rx_control {
+            Some(rx_control) => rx_control.try_iter().collect(),
+            None => vec![],
+        }
+    }
+
+    /// is_due returns true if the next race state should be sent to the GUI.
+    pub fn is_due(&self) -> bool {
+        match self.t_last_send {
+            Some(t_last_send) => {
+                t_last_send.elapsed().as_secs_f64() >= 1.0 / self.update_frequency - 0.001
+            }
+            None => true,
+        }
+    }
+
+    /// send sends a race state to the GUI (without throttling).
+    pub fn send(&mut self, race_state: RaceState) -> anyhow::Result<()> {
+        self.tx
+            .send(race_state)
+            .context("Failed to send race state to GUI!")?;
+        self.t_last_send = Some(Instant::now());
+        Ok(())
+    }
+}
+
+/// check_gui_update_frequency returns an error if the update frequency (Hz) of the GUI is not a
+/// positive number.
+pub fn check_gui_update_frequency(update_frequency: f64) -> anyhow::Result<()> {
+    if !(update_frequency.is_finite() && update_frequency > 0.0) {
+        anyhow::bail!("GUI update frequency must be positive (got {} Hz)!", update_frequency)
+    }
+    Ok(())
+}
@@ -2,3 +2,7640 @@ pub mod core;
 pub mod interfaces;
 pub mod post;
 pub mod pre;
+
+// stable entry points and types (deep module paths may change between versions)
+pub use crate::core::handle_race::{
+    handle_race, record_race, run_race, run_race_with_options, RunOptions,
+};
+pub use crate::core::race::{FlagState, Race, RacePars, SimConstants, WeatherState};
+pub use crate::core::scripted_event::{ScriptedAction, ScriptedEvent};
+pub use crate::core::season::{
+    read_season_pars, run_season, BanInfo, CarDevelopment, CarUpgrade, PenaltyPointsPars,
+    SeasonPars, SeasonResult, SeasonState,
+};
+pub use crate::core::tire_inventory::{TireInventory, TireSetRecord, TireStint};
+pub use crate::core::tireset::TireConfig;
+pub use crate::core::track::TrackPars;
+pub use crate::core::weekend::{run_weekend, WeekendPars, WeekendResult};
+pub use crate::post::history_config::HistoryConfig;
+pub use crate::post::output_fmt::{Lang, OutputFormat};
+pub use crate::post::race_recording::{read_race_recording, RaceRecording};
+pub use crate::post::race_result::{
+    CarDriverPair, CarDistanceStats, DistanceStats, EventContext, EventSnapshot, IdentityMap,
+    IdentityMapEntry, IncidentRates, PitStopInfo, RaceEvent, RaceResult, RetirementInfo, SanityRule,
+    SanityWarning,
+};
+pub use crate::pre::read_sim_pars::{
+    read_race_scenario, read_sim_constants, read_sim_pars, read_sim_pars_flexible,
+    read_tire_config, read_track_pars, write_sim_pars, SimPars, ValidationReport,
+};
+pub use crate::pre::sim_opts::SimOpts;
+
+/// prelude contains everything required to build a scenario in code and run it.
+pub mod prelude {
+    pub use crate::core::car::{CarPars, StrategyEntry};
+    pub use crate::core::driver::DriverPars;
+    pub use crate::core::strategy_controls::{CarLapInfo, LapContext, StrategyControls};
+    pub use crate::core::tireset::{DegrModel, DegrPars, COMPOUNDS};
+    pub use crate::{
+        read_sim_constants, read_sim_pars_flexible, read_tire_config, read_track_pars, run_race,
+        run_race_with_options, OutputFormat, RaceEvent, RacePars, RaceResult, RunOptions,
+        SimConstants, SimPars, TireConfig, TrackPars,
+    };
+}
+
+#[cfg(test)]
+mod test_support {
+    use crate::core::race::{Race, SimConstants};
+    use crate::core::tireset::TireConfig;
+    use crate::core::track::{Track, TrackPars};
+    use crate::pre::read_sim_pars::SimPars;
+    use std::path::PathBuf;
+    use std::rc::Rc;
+
+    /// load_test_inputs returns the parameters of the test race, the simulation constants and the
+    /// tire configuration as shipped in the input folder. The test drivers use their medium
+    /// compound degradation also for the rain compounds, such that cars can change tires in rain.
+    pub fn load_test_inputs() -> (SimPars, SimConstants, TireConfig) {
+        let mut sim_pars: SimPars =
+            serde_json::from_str(include_str!("../../input/parameters/test_race.json")).unwrap();
+        for driver_pars in sim_pars.driver_pars_all.values_mut() {
+            let degr_pars = driver_pars.degr_pars_all["MEDIUM"].to_owned();
+            for compound in ["INTERMEDIATE", "WET"].iter() {
+                driver_pars
+                    .degr_pars_all
+                    .entry(compound.to_string())
+                    .or_insert_with(|| degr_pars.to_owned());
+            }
+        }
+        let sim_consts: SimConstants =
+            serde_json::from_str(include_str!("../../input/parameters/sim_constants.json")).unwrap();
+        let tire_config: TireConfig =
+            serde_json::from_str(include_str!("../../input/parameters/tires.json")).unwrap();
+        (sim_pars, sim_consts, tire_config)
+    }
+
+    /// load_test_pars returns the test inputs without random failures, punctures and collisions,
+    /// such that a test only sees the incidents it provokes itself.
+    pub fn load_test_pars() -> (SimPars, SimConstants, TireConfig) {
+        let (sim_pars, mut sim_consts, tire_config) = load_test_inputs();
+        sim_consts.failure_rate_per_hour = 0.0;
+        sim_consts.puncture_probability_over_cliff = 0.0;
+        sim_consts.puncture_debris_probability = 0.0;
+        sim_consts.collision_factor = 0.0;
+        (sim_pars, sim_consts, tire_config)
+    }
+
+    /// create_race creates the race of the given inputs with a timestep size of 0.1 s and without
+    /// printing the race events.
+    pub fn create_race(
+        sim_pars: &SimPars,
+        sim_consts: &SimConstants,
+        tire_config: &TireConfig,
+    ) -> Race {
+        create_race_with_timestep(sim_pars, sim_consts, tire_config, 0.1)
+    }
+
+    /// get_trackfile_path returns the path of the track file of the given track in the input folder
+    /// (independent of the current directory, which is the crate directory under `cargo test`).
+    pub fn get_trackfile_path(track_name: &str) -> PathBuf {
+        let trackfile_path = PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/../input/tracks"))
+            .join(track_name)
+            .with_extension("csv");
+        assert!(
+            trackfile_path.is_file(),
+            "Track file {} of the test race is missing!",
+            trackfile_path.display()
+        );
+        trackfile_path
+    }
+
+    /// create_track creates the real track of the given parameters (speed multipliers from the
+    /// track file), e.g. for `run_race_on_track`.
+    pub fn create_track(track_pars: &TrackPars) -> Rc<Track> {
+        Rc::new(Track::with_trackfile(track_pars, &get_trackfile_path(&track_pars.name)))
+    }
+
+    /// create_race_with_timestep creates the race of the given inputs on the real track (see
+    /// `create_track`) with the given timestep size and without printing the race events.
+    pub fn create_race_with_timestep(
+        sim_pars: &SimPars,
+        sim_consts: &SimConstants,
+        tire_config: &TireConfig,
+        timestep_size: f64,
+    ) -> Race {
+        let mut race = Race::new_with_track(
+            &sim_pars.race_pars,
+            sim_consts,
+            tire_config,
+            create_track(&sim_pars.track_pars),
+            &sim_pars.driver_pars_all,
+            &sim_pars.car_pars_all,
+            timestep_size,
+        );
+        race.print_events = false;
+        race
+    }
+}
+
+#[cfg(test)]
+mod output_fmt_tests {
+    use crate::post::output_fmt::{Lang, OutputFormat};
+    use crate::post::race_result::{CarDriverPair, RaceResult};
+
+    fn get_test_result() -> RaceResult {
+        RaceResult::new(
+            2,
+            vec![
+                CarDriverPair {
+                    car_no: 44,
+                    driver_initials: String::from("HAM"),
+                },
+                CarDriverPair {
+                    car_no: 33,
+                    driver_initials: String::from("VER"),
+                },
+            ],
+            vec![vec![0.0, 85.125, 82.5], vec![0.0, 86.0, 81.75]],
+            vec![vec![0.0, 85.125, 167.625], vec![0.0, 86.0, 167.75]],
+        )
+    }
+
+    #[test]
+    fn test_text_decimal_point() {
+        let content = get_test_result()
+            .format_lap_and_race_times(&OutputFormat::default())
+            .unwrap();
+        assert!(content.contains("  1,   85.125s,   86.000s"));
+        assert!(content.contains("  2,  167.625s,  167.750s"));
+    }
+    #[test]
+    fn test_text_decimal_comma() {
+        let out_fmt = OutputFormat {
+            lang: Lang::Pl,
+            decimal_comma: true,
+        };
+        let content = get_test_result().format_lap_and_race_times(&out_fmt).unwrap();
+        assert!(content.contains("  1,   85,125s,   86,000s"));
+        assert!(content.contains("  2,  167,625s,  167,750s"));
+    }
+    #[test]
+    fn test_csv_decimal_comma_parseable() {
+        let out_fmt = OutputFormat {
+            lang: Lang::En,
+            decimal_comma: true,
+        };
+        let content = get_test_result().format_lap_times_csv(&out_fmt).unwrap();
+        let mut csv_reader = csv::ReaderBuilder::new()
+            .delimiter(b';')
+            .from_reader(content.as_bytes());
+        assert_eq!(csv_reader.headers().unwrap().len(), 3);
+        let rows: Vec<csv::StringRecord> = csv_reader.records().map(|r| r.unwrap()).collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(&rows[1][1], "82,500");
+        assert_eq!(&rows[1][2], "81,750");
+    }
+    #[test]
+    fn test_lang_from_str() {
+        assert_eq!("en".parse::<Lang>().unwrap(), Lang::En);
+        assert_eq!("PL".parse::<Lang>().unwrap(), Lang::Pl);
+        assert!("de".parse::<Lang>().is_err());
+    }
+}
+
+#[cfg(test)]
+mod car_tests {
+    use crate::core::car::{
+        Car, CarPars, DamagePars, EngineMode, EngineModePars, ErsPars, FailureRates, FailureType,
+        RetirementCause, ThermalPars,
+    };
+    use crate::core::driver::{Driver, DriverPars};
+    use crate::core::tireset::{TireCompoundConfig, TireConfig};
+    use crate::pre::read_sim_pars::SimPars;
+    use std::collections::HashMap;
+    use std::rc::Rc;
+
+    fn get_test_tire_config() -> TireConfig {
+        let compound_cfg = TireCompoundConfig {
+            k1_scale: 1.0,
+            default_cliff_age: 28.0,
+            default_k2: 0.02,
+            base_offset: 0.0,
+            temp_wear_sensitivity: 0.0,
+        };
+        TireConfig {
+            soft: compound_cfg.clone(),
+            medium: compound_cfg.clone(),
+            hard: compound_cfg.clone(),
+            intermediate: compound_cfg.clone(),
+            wet: compound_cfg,
+            defending_wear_add: 1.0,
+        }
+    }
+
+    /// get_test_car returns the test car with a linear degradation on the medium compound.
+    fn get_test_car() -> Car {
+        get_test_car_with("")
+    }
+
+    /// get_test_car_with returns the test car with additional car parameters, given as JSON
+    /// fields with a leading comma, e.g. `, "pit_crew_sigma": 0.2`.
+    fn get_test_car_with(car_pars_extra: &str) -> Car {
+        let driver_pars: DriverPars = serde_json::from_str(
+            r#"{
+                "initials": "TST",
+                "name": "Test Driver",
+                "t_driver": 0.0,
+                "vel_max": 330.0,
+                "degr_pars_all": {
+                    "MEDIUM": { "degr_model": "lin", "k_0": 0.0, "k_1_lin": 0.04 }
+                }
+            }"#,
+        )
+        .unwrap();
+        let car_pars: CarPars = serde_json::from_str(&format!(
+            r##"{{
+                "car_no": 1,
+                "color": "#FF0000",
+                "t_car": 0.0,
+                "b_fuel_per_lap": 1.5,
+                "m_fuel": 100.0,
+                "t_pit_tirechange": 2.5,
+                "pit_location": 0.0,
+                "strategy": [
+                    {{ "inlap": 0, "tire_start_age": 0, "compound": "MEDIUM", "driver_initials": "TST" }}
+                ],
+                "p_grid": 1{}
+            }}"##,
+            car_pars_extra
+        ))
+        .unwrap();
+        Car::new(&car_pars, Rc::new(Driver::new(&driver_pars)))
+    }
+
+    #[test]
+    fn test_defending_wears_tires() {
+        let tire_cfg = get_test_tire_config();
+        let mut car_free = get_test_car();
+        let mut car_defending = get_test_car();
+
+        for _ in 0..10 {
+            car_defending.t_defending_cur_lap = 40.0;
+            car_free.drive_lap(80.0, &FailureRates::default(), &tire_cfg);
+            car_defending.drive_lap(80.0, &FailureRates::default(), &tire_cfg);
+        }
+
+        assert!(car_defending.get_tire_age_cur_stint() > car_free.get_tire_age_cur_stint() + 4.0);
+        assert!(car_defending.t_defending_cur_lap.abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_pit_crew_mean_recovered() {
+        let mut car =
+            get_test_car_with(r#", "pit_crew_mean_offset": -0.3, "pit_crew_sigma": 0.2"#);
+        let no_draws = 5000;
+        let t_sum: f64 = (0..no_draws)
+            .map(|_| car.t_add_pit_standstill(0, 0.5, 1.0, 0.0))
+            .sum();
+        assert!((t_sum / no_draws as f64 - 2.2).abs() < 0.03);
+    }
+    #[test]
+    fn test_pit_crew_fastest_stop_depends_on_sigma() {
+        let mut car_consistent = get_test_car_with(r#", "pit_crew_sigma": 0.05"#);
+        let mut car_erratic = get_test_car_with(r#", "pit_crew_sigma": 0.6"#);
+        let calc_avg_fastest = |car: &mut Car| -> f64 {
+            let no_races = 500;
+            let mut sum = 0.0;
+            for _ in 0..no_races {
+                sum += (0..5)
+                    .map(|_| car.t_add_pit_standstill(0, 0.0, 0.0, 0.0))
+                    .fold(f64::INFINITY, f64::min);
+            }
+            sum / no_races as f64
+        };
+        assert!(calc_avg_fastest(&mut car_erratic) < calc_avg_fastest(&mut car_consistent) - 0.3);
+    }
+
+    #[test]
+    fn test_car_failure_rate_replaces_global_rate() {
+        let tire_cfg = get_test_tire_config();
+        let car_default = get_test_car();
+        let car_bulletproof = get_test_car_with(r#", "failure_rate_per_hour": 0.0"#);
+        let car_flaky = get_test_car_with(r#", "failure_rate_per_hour": 1.0e6"#);
+        let rates_global = FailureRates::from_total(0.02);
+
+        // the car rate replaces the global rate, a global rate of zero disables all failures
+        assert_eq!(car_default.get_failure_rates(&rates_global), rates_global);
+        assert_eq!(car_bulletproof.get_failure_rates(&rates_global).get_total(), 0.0);
+        assert!((car_flaky.get_failure_rates(&rates_global).get_total() - 1.0e6).abs() < 1e-6);
+        assert_eq!(car_flaky.get_failure_rates(&FailureRates::default()).get_total(), 0.0);
+
+        for _ in 0..100 {
+            let mut car = car_bulletproof.clone();
+            let rates = car.get_failure_rates(&FailureRates::from_total(1.0e6));
+            assert!(car.drive_lap(80.0, &rates, &tire_cfg).is_none());
+
+            let mut car = car_flaky.clone();
+            let rates = car.get_failure_rates(&rates_global);
+            assert!(car.drive_lap(80.0, &rates, &tire_cfg).is_some());
+        }
+    }
+
+    #[test]
+    fn test_car_failure_rates_per_type() {
+        let tire_cfg = get_test_tire_config();
+        let car = get_test_car_with(
+            r#", "failure_rate_per_hour": 1.0e6, "failure_rates_per_hour": {"hydraulics": 1.0e6}"#,
+        );
+
+        // the rates per type take precedence over the total rate of the car
+        let rates = car.get_failure_rates(&FailureRates::from_total(0.02));
+        assert_eq!(rates.get(FailureType::Engine), 0.0);
+        assert_eq!(rates.get(FailureType::Hydraulics), 1.0e6);
+
+        for _ in 0..100 {
+            let mut car = car.clone();
+            assert_eq!(
+                car.drive_lap(80.0, &rates, &tire_cfg),
+                Some(RetirementCause::Failure(FailureType::Hydraulics))
+            );
+        }
+    }
+
+    #[test]
+    fn test_damage_slows_car_until_repaired() {
+        let tire_cfg = get_test_tire_config();
+        let damage_pars = DamagePars {
+            t_loss: 1.0,
+            wear_factor: 1.0,
+            repair_threshold: 0.5,
+            t_repair: 4.0,
+        };
+        let mut car_clean = get_test_car();
+        let mut car_damaged = get_test_car();
+        car_clean.set_damage_pars(damage_pars);
+        car_damaged.set_damage_pars(damage_pars);
+        car_clean.schedule_weather_strategy(5, "MEDIUM");
+        car_damaged.schedule_weather_strategy(5, "MEDIUM");
+
+        // damage wears the tires faster
+        car_damaged.add_damage(0.3);
+        car_clean.drive_lap(80.0, &FailureRates::default(), &tire_cfg);
+        car_damaged.drive_lap(80.0, &FailureRates::default(), &tire_cfg);
+        assert!((car_damaged.get_tire_age_cur_stint() - 1.3).abs() < 1e-9);
+        assert!((car_clean.get_tire_age_cur_stint() - 1.0).abs() < 1e-9);
+
+        // small damage is not repaired
+        assert!(!car_damaged.needs_repair());
+        assert!((car_damaged.t_add_pit_standstill(5, 0.0, 0.0, 0.0) - 2.5).abs() < 1e-9);
+
+        // larger damage is repaired during the planned stop at the cost of a longer standstill
+        car_damaged.add_damage(0.3);
+        assert!(car_damaged.needs_repair());
+        assert!((car_damaged.t_add_pit_standstill(5, 0.0, 0.0, 0.0) - 6.5).abs() < 1e-9);
+        assert!((car_clean.t_add_pit_standstill(5, 0.0, 0.0, 0.0) - 2.5).abs() < 1e-9);
+        assert!(car_damaged.perform_pitstop(5, &HashMap::new()));
+        assert_eq!(car_damaged.get_damage(), 0.0);
+        assert!(!car_clean.perform_pitstop(5, &HashMap::new()));
+    }
+
+    #[test]
+    fn test_puncture_cleared_by_tire_change() {
+        let tire_cfg = get_test_tire_config();
+        let mut car = get_test_car();
+
+        // the linear model uses the default cliff age of the compound (28 laps)
+        for _ in 0..28 {
+            car.drive_lap(80.0, &FailureRates::default(), &tire_cfg);
+        }
+        assert_eq!(car.get_tire_age_over_cliff(&tire_cfg), 0.0);
+        for _ in 0..2 {
+            car.drive_lap(80.0, &FailureRates::default(), &tire_cfg);
+        }
+        assert!((car.get_tire_age_over_cliff(&tire_cfg) - 2.0).abs() < 1e-9);
+
+        // without a tire inventory a new set of the same compound is fitted
+        car.set_punctured();
+        let compound = car.get_puncture_compound("HARD");
+        assert_eq!(compound, "MEDIUM");
+        car.schedule_weather_strategy(31, &compound);
+        car.perform_pitstop(31, &HashMap::new());
+        assert!(!car.is_punctured());
+        assert_eq!(car.get_current_compound(), "MEDIUM");
+        assert_eq!(car.get_tire_age_over_cliff(&tire_cfg), 0.0);
+    }
+
+    #[test]
+    fn test_ers_deploy_and_harvest() {
+        let mut car = get_test_car();
+        car.set_ers_pars(ErsPars {
+            capacity: 4.0,
+            t_gain: 0.5,
+            deploy_rate: 0.12,
+            harvest_per_lap: 2.0,
+            t_harvest_loss: 0.1,
+        });
+        assert_eq!(car.get_ers_soc(), 1.0);
+
+        // the battery is full, nothing to harvest
+        assert!(!car.harvest_ers(0.1, 80.0));
+
+        // 10 s of deployment
+        for _ in 0..100 {
+            car.deploy_ers(0.1);
+        }
+        assert!((car.get_ers_energy() - 2.8).abs() < 1e-9);
+
+        // half a lap refills 1 MJ, the battery is never charged above its capacity
+        for _ in 0..400 {
+            assert!(car.harvest_ers(0.1, 80.0));
+        }
+        assert!((car.get_ers_energy() - 3.8).abs() < 1e-9);
+        for _ in 0..400 {
+            car.harvest_ers(0.1, 80.0);
+        }
+        assert_eq!(car.get_ers_soc(), 1.0);
+    }
+
+    #[test]
+    fn test_engine_mode_adjusts_failure_rate_and_fuel() {
+        let tire_cfg = get_test_tire_config();
+        let mut car = get_test_car();
+        car.set_engine_mode_pars(EngineModePars {
+            t_gain_push: 0.3,
+            failure_factor_push: 2.0,
+            fuel_factor_push: 1.1,
+            t_loss_conserve: 0.3,
+            failure_factor_conserve: 0.0,
+            fuel_factor_conserve: 0.9,
+        });
+        let failure_rates = FailureRates::from_total(1.0e9);
+
+        // conserve mode switches the (certain) failures off and burns less fuel
+        car.set_engine_mode(EngineMode::Conserve);
+        let m_fuel = car.get_fuel_mass();
+        assert_eq!(car.drive_lap(80.0, &failure_rates, &tire_cfg), None);
+        assert!((m_fuel - car.get_fuel_mass() - 1.35).abs() < 1e-9);
+
+        car.set_engine_mode(EngineMode::Push);
+        assert!((car.get_fuel_per_lap() - 1.65).abs() < 1e-9);
+        assert!(car.drive_lap(80.0, &failure_rates, &tire_cfg).is_some());
+    }
+
+    #[test]
+    fn test_overheating_in_dirty_air() {
+        let mut car = get_test_car();
+        car.set_thermal_pars(ThermalPars {
+            heat_rate: 0.02,
+            cool_rate: 0.01,
+            threshold: 0.6,
+            t_loss_max: 1.5,
+            penalty_exponent: 2.0,
+        });
+
+        // 30 s close behind another car: at the threshold, no loss yet
+        for _ in 0..300 {
+            car.update_temperature(0.1, true);
+        }
+        assert!((car.get_temperature() - 0.6).abs() < 1e-6);
+        assert!(car.get_overheat_t_loss() < 1e-6);
+
+        // the loss increases with the temperature up to the maximum
+        for _ in 0..100 {
+            car.update_temperature(0.1, true);
+        }
+        let t_loss = car.get_overheat_t_loss();
+        assert!((t_loss - 1.5 * 0.5_f64.powi(2)).abs() < 1e-6);
+        for _ in 0..1000 {
+            car.update_temperature(0.1, true);
+        }
+        assert!((car.get_overheat_t_loss() - 1.5).abs() < 1e-9);
+
+        // the car recovers in clean air
+        for _ in 0..400 {
+            car.update_temperature(0.1, false);
+        }
+        assert!(car.get_overheat_t_loss() < 1e-6);
+        for _ in 0..1000 {
+            car.update_temperature(0.1, false);
+        }
+        assert_eq!(car.get_temperature(), 0.0);
+    }
+
+    #[test]
+    fn test_negative_failure_rate_rejected() {
+        let mut sim_pars: SimPars =
+            serde_json::from_str(include_str!("../../input/parameters/test_race.json")).unwrap();
+        sim_pars.car_pars_all.get_mut(&44).unwrap().failure_rate_per_hour = Some(0.0);
+        assert!(sim_pars.validate().errors.is_empty());
+
+        sim_pars.car_pars_all.get_mut(&44).unwrap().failure_rate_per_hour = Some(-0.1);
+        let errors = sim_pars.validate().errors;
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("Car 44: failure_rate_per_hour"));
+    }
+}
+
+#[cfg(test)]
+mod race_tests {
+    use crate::core::race::{
+        calc_velocity_capped_laptime, clamp_race_progs_min_spacing, resolve_grid_order,
+        ClearancePars,
+    };
+    use crate::core::state_handler::StateHandler;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn get_mean_clearance_time(pars: &ClearancePars, no_cars: usize, multiplier: f64, seed: u64) -> f64 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let no_draws = 2000;
+        (0..no_draws)
+            .map(|_| pars.draw_clearance_time(no_cars, multiplier, &mut rng))
+            .sum::<f64>()
+            / no_draws as f64
+    }
+
+    #[test]
+    fn test_resolve_grid_order_1() {
+        let p_grids = vec![1, 2, 3, 4];
+        let back_of_grid = vec![false, false, false, false];
+        assert_eq!(resolve_grid_order(&p_grids, &back_of_grid), vec![0, 1, 2, 3]);
+    }
+    #[test]
+    fn test_resolve_grid_order_2() {
+        let p_grids = vec![3, 1, 4, 2, 5];
+        let back_of_grid = vec![true, false, false, true, false];
+        assert_eq!(resolve_grid_order(&p_grids, &back_of_grid), vec![1, 2, 4, 3, 0]);
+    }
+    #[test]
+    fn test_min_spacing_sc_train() {
+        // train of 5 cars behind the SC, every follower is faster than the car ahead and would run
+        // into it without the spacing constraint
+        let track_length = 5000.0;
+        let min_spacing = 10.0 / track_length;
+        let speeds = [50.0, 55.0, 60.0, 65.0, 70.0];
+        let active = vec![true; 5];
+        let may_overlap = vec![false; 5];
+        let mut race_progs: Vec<f64> = (0..5).map(|i| 1.0 - i as f64 * 0.01).collect();
+
+        for _ in 0..2000 {
+            let race_progs_prev = race_progs.to_owned();
+            let race_progs_new: Vec<f64> = race_progs
+                .iter()
+                .zip(speeds.iter())
+                .map(|(prog, speed)| prog + speed * 0.1 / track_length)
+                .collect();
+
+            race_progs = clamp_race_progs_min_spacing(
+                &race_progs_prev,
+                &race_progs_new,
+                &active,
+                &may_overlap,
+                min_spacing,
+            );
+
+            for i in 1..5 {
+                assert!(race_progs[i - 1] - race_progs[i] >= min_spacing - 1e-12);
+                assert!(race_progs[i] >= race_progs_prev[i]);
+            }
+        }
+    }
+    #[test]
+    fn test_min_spacing_overtake_allowed() {
+        let race_progs = clamp_race_progs_min_spacing(
+            &[1.0, 0.999],
+            &[1.001, 1.0009],
+            &[true, true],
+            &[false, true],
+            0.002,
+        );
+        assert_eq!(race_progs, vec![1.001, 1.0009]);
+    }
+    #[test]
+    fn test_min_spacing_cars_closer_than_minimum_keep_moving() {
+        // grid slots closer than the minimum spacing: the rear car keeps its gap instead of
+        // standing still
+        let race_progs = clamp_race_progs_min_spacing(
+            &[0.0, -0.001],
+            &[0.0002, -0.0006],
+            &[true, true],
+            &[false, false],
+            0.002,
+        );
+        assert_eq!(race_progs[0], 0.0002);
+        assert!((race_progs[1] - -0.0008).abs() < 1e-12);
+    }
+    #[test]
+    fn test_set_race_prog_never_moves_car_backwards() {
+        let mut sh = StateHandler::default();
+        sh.initialize_state_handler(
+            false,
+            500.0,
+            1.0,
+            4990.0,
+            5000.0,
+            vec![],
+            [4800.0, 100.0],
+            vec![],
+            vec![],
+        );
+
+        // the car crosses the line in the step, a spacing clamp behind its previous position keeps
+        // it at the previous position (before the line)
+        sh.update_race_prog(100.0, 0.4);
+        let race_prog_prev = sh.get_race_prog_prev();
+        assert_eq!(sh.get_compl_lap(), 1);
+        sh.set_race_prog(race_prog_prev - 0.01);
+        assert_eq!(sh.get_race_prog(), race_prog_prev);
+        assert_eq!(sh.get_compl_lap(), 0);
+
+        // placing a car moves it without crossing the line
+        sh.place_race_prog(0.5);
+        assert_eq!(sh.get_race_prog_prev(), 0.5);
+        assert_eq!(sh.get_race_prog(), 0.5);
+    }
+    #[test]
+    fn test_clearance_time_severity_ordering() {
+        let pars = ClearancePars {
+            mean_s: 90.0,
+            crash_factor: 0.8,
+            corner_factor: 1.0,
+            sigma_rel: 0.3,
+        };
+
+        for seed in 0..20 {
+            let stopped_straight = get_mean_clearance_time(&pars, 1, 1.2, seed);
+            let stopped_corner = get_mean_clearance_time(&pars, 1, 0.6, seed);
+            let crash_straight = get_mean_clearance_time(&pars, 2, 1.2, seed);
+            let crash_corner = get_mean_clearance_time(&pars, 2, 0.6, seed);
+
+            assert!(stopped_straight < stopped_corner);
+            assert!(stopped_straight < crash_straight);
+            assert!(crash_straight < crash_corner);
+            assert!(stopped_corner < crash_corner);
+        }
+    }
+    #[test]
+    fn test_vel_max_only_affects_fast_segments() {
+        let track_length = 5000.0;
+        let th_laptime = 90.0;
+        let vel_max_low = 250.0; // km/h -> minimum lap time 72 s
+
+        // straight: 90 s / 1.4 = 64.3 s would be faster than the velocity cap allows
+        let laptime_fast = th_laptime / 1.4;
+        let laptime_fast_capped =
+            calc_velocity_capped_laptime(laptime_fast, track_length, vel_max_low);
+        assert!(laptime_fast_capped > laptime_fast);
+        assert!((laptime_fast_capped - 72.0).abs() < 1e-9);
+
+        // corner: 90 s / 0.7 = 128.6 s is not affected by the velocity cap
+        let laptime_slow = th_laptime / 0.7;
+        let laptime_slow_capped =
+            calc_velocity_capped_laptime(laptime_slow, track_length, vel_max_low);
+        assert_eq!(laptime_slow_capped, laptime_slow);
+
+        // a realistic maximum velocity does not affect the straight either
+        assert_eq!(
+            calc_velocity_capped_laptime(laptime_fast, track_length, 340.0),
+            laptime_fast
+        );
+    }
+}
+
+#[cfg(test)]
+mod track_tests {
+    use crate::core::race::Race;
+    use crate::core::track::{calc_climb_per_lap, calc_track_multipliers, CsvTrackEl, Track};
+    use crate::test_support;
+    use std::rc::Rc;
+
+    /// get_hill_track returns a straight synthetic track climbing 20m in the first half and
+    /// descending in the second half.
+    fn get_hill_track(with_elevation: bool) -> Vec<CsvTrackEl> {
+        (0..100)
+            .map(|i| CsvTrackEl {
+                x_m: i as f64 * 10.0,
+                y_m: 0.0,
+                w_tr_left_m: 5.0,
+                w_tr_right_m: 5.0,
+                z_m: if with_elevation {
+                    Some(20.0 - (i as f64 - 50.0).abs() * 0.4)
+                } else {
+                    None
+                },
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_hill_track_climb_sector_slower() {
+        let multipliers = calc_track_multipliers(&get_hill_track(true), 2.0);
+
+        // sector times are proportional to the summed up inverse multipliers
+        let t_climb: f64 = multipliers[1..49].iter().map(|m| 1.0 / m).sum();
+        let t_descent: f64 = multipliers[51..99].iter().map(|m| 1.0 / m).sum();
+        assert!(t_climb > t_descent);
+    }
+
+    #[test]
+    fn test_track_without_elevation_unchanged() {
+        let multipliers_flat = calc_track_multipliers(&get_hill_track(false), 0.0);
+        let multipliers = calc_track_multipliers(&get_hill_track(false), 2.0);
+        assert_eq!(multipliers_flat, multipliers);
+        assert_eq!(calc_climb_per_lap(&get_hill_track(false)), 0.0);
+        assert!((calc_climb_per_lap(&get_hill_track(true)) - 20.0).abs() < 1e-6);
+    }
+    #[test]
+    fn test_track_shared_between_races() {
+        let (sim_pars, sim_consts, tire_config) = test_support::load_test_inputs();
+        let trackfile_path = test_support::get_trackfile_path(&sim_pars.track_pars.name);
+        let track = Rc::new(Track::with_trackfile(&sim_pars.track_pars, &trackfile_path));
+        let races: Vec<Race> = (0..2)
+            .map(|_| {
+                Race::new_with_track(
+                    &sim_pars.race_pars,
+                    &sim_consts,
+                    &tire_config,
+                    Rc::clone(&track),
+                    &sim_pars.driver_pars_all,
+                    &sim_pars.car_pars_all,
+                    0.1,
+                )
+            })
+            .collect();
+
+        // geometry is built once, every race uses the same multipliers as a freshly built track
+        assert!(races.iter().all(|race| Rc::ptr_eq(&race.track, &track)));
+        assert_eq!(Rc::strong_count(&track), 3);
+
+        let race_compat = test_support::create_race(&sim_pars, &sim_consts, &tire_config);
+        assert_eq!(race_compat.track.multipliers, track.multipliers);
+        assert_eq!(race_compat.track.fuel_mult, track.fuel_mult);
+    }
+}
+
+#[cfg(test)]
+mod sanity_check_tests {
+    use crate::post::race_result::{CarDriverPair, RaceResult, SanityRule};
+
+    /// get_test_result creates a result for two cars with consistent race times from the lap
+    /// times (index 0 is the start).
+    fn get_test_result(laptimes: Vec<Vec<f64>>) -> RaceResult {
+        let racetimes = laptimes
+            .iter()
+            .map(|car_laptimes| {
+                car_laptimes
+                    .iter()
+                    .scan(0.0, |racetime, laptime| {
+                        *racetime += laptime;
+                        Some(*racetime)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        RaceResult::new(
+            laptimes[0].len() as u32 - 1,
+            vec![
+                CarDriverPair {
+                    car_no: 1,
+                    driver_initials: String::from("AAA"),
+                },
+                CarDriverPair {
+                    car_no: 2,
+                    driver_initials: String::from("BBB"),
+                },
+            ],
+            laptimes,
+            racetimes,
+        )
+    }
+
+    fn get_rules(result: &RaceResult) -> Vec<SanityRule> {
+        result
+            .sanity_check(80.0, 25.0)
+            .iter()
+            .map(|warning| warning.rule)
+            .collect()
+    }
+
+    #[test]
+    fn test_sanity_check_valid() {
+        let result = get_test_result(vec![
+            vec![0.0, 85.0, 82.0, 82.5],
+            vec![0.0, 86.0, 82.3, 82.1],
+        ]);
+        assert!(get_rules(&result).is_empty());
+    }
+    #[test]
+    fn test_sanity_check_fast_lap() {
+        let result = get_test_result(vec![
+            vec![0.0, 85.0, 70.0, 82.5],
+            vec![0.0, 86.0, 82.3, 82.1],
+        ]);
+        assert_eq!(get_rules(&result), vec![SanityRule::FastLap]);
+    }
+    #[test]
+    fn test_sanity_check_negative_lap_time() {
+        let mut result = get_test_result(vec![
+            vec![0.0, 85.0, 82.0, 82.5],
+            vec![0.0, 86.0, 82.3, 82.1],
+        ]);
+        result.laptimes[1][2] = -1.0;
+        assert!(get_rules(&result).contains(&SanityRule::NegativeLapTime));
+    }
+    #[test]
+    fn test_sanity_check_decreasing_race_time() {
+        let mut result = get_test_result(vec![
+            vec![0.0, 85.0, 82.0, 82.5],
+            vec![0.0, 86.0, 82.3, 82.1],
+        ]);
+        result.racetimes[0][2] = 80.0;
+        assert!(get_rules(&result).contains(&SanityRule::DecreasingRaceTime));
+    }
+    #[test]
+    fn test_sanity_check_gap_jump() {
+        let result = get_test_result(vec![
+            vec![0.0, 85.0, 82.0, 82.5],
+            vec![0.0, 86.0, 82.3, 170.0],
+        ]);
+        assert_eq!(get_rules(&result), vec![SanityRule::GapJump]);
+    }
+    #[test]
+    fn test_sanity_check_race_time_mismatch() {
+        let mut result = get_test_result(vec![
+            vec![0.0, 85.0, 82.0, 82.5],
+            vec![0.0, 86.0, 82.3, 82.1],
+        ]);
+        result.racetimes[1][3] += 1.0;
+        assert_eq!(get_rules(&result), vec![SanityRule::RaceTimeMismatch]);
+    }
+    #[test]
+    fn test_sanity_check_dnf_skipped() {
+        let mut result = get_test_result(vec![
+            vec![0.0, 85.0, 82.0, 82.5],
+            vec![0.0, 86.0, 0.0, 0.0],
+        ]);
+        // race times are not set for laps that were not driven
+        result.racetimes[1][2] = 0.0;
+        result.racetimes[1][3] = 0.0;
+        assert!(get_rules(&result).is_empty());
+    }
+}
+
+#[cfg(test)]
+mod event_context_tests {
+    use crate::post::race_result::{CarDriverPair, RaceEvent, RaceResult};
+
+    /// get_test_result creates a result in which car 2 passes car 1 for the lead in lap 3 after
+    /// car 1 crashed in lap 2 (race times, index 0 is the start).
+    fn get_test_result() -> RaceResult {
+        RaceResult {
+            events: vec![RaceEvent::new("Crash", 3, 200.0, vec![1])],
+            ..RaceResult::new(
+                4,
+                vec![
+                    CarDriverPair {
+                        car_no: 1,
+                        driver_initials: String::from("AAA"),
+                    },
+                    CarDriverPair {
+                        car_no: 2,
+                        driver_initials: String::from("BBB"),
+                    },
+                ],
+                vec![
+                    vec![0.0, 90.0, 90.0, 100.0, 90.0],
+                    vec![0.5, 90.0, 90.0, 90.0, 90.0],
+                ],
+                vec![
+                    vec![0.0, 90.0, 180.0, 280.0, 370.0],
+                    vec![0.5, 90.5, 180.5, 270.5, 360.5],
+                ],
+            )
+        }
+    }
+
+    #[test]
+    fn test_event_context_before_after() {
+        let mut result = get_test_result();
+        result.add_event_contexts();
+
+        let context = result.events[0].context.as_ref().unwrap();
+        let before = context.before.as_ref().unwrap();
+        let after = context.after.as_ref().unwrap();
+
+        assert_eq!(before.lap, 2);
+        assert_eq!(before.car_nos, vec![1, 2]);
+        assert!((before.gaps[1] - 0.5).abs() < 1e-9);
+
+        assert_eq!(after.lap, 4);
+        assert_eq!(after.car_nos, vec![2, 1]);
+        assert!((after.gaps[1] - 9.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_event_context_outside_race() {
+        let mut result = get_test_result();
+        result.events[0].lap = 4;
+        result.add_event_contexts();
+
+        let context = result.events[0].context.as_ref().unwrap();
+        assert!(context.before.is_some());
+        assert!(context.after.is_none());
+    }
+}
+
+#[cfg(test)]
+mod scenario_randomizer_tests {
+    use crate::pre::read_sim_pars::SimPars;
+    use crate::pre::scenario_randomizer::{randomize_scenario, RandomizerSpec};
+
+    fn get_base_pars() -> SimPars {
+        serde_json::from_str(include_str!("../../input/parameters/test_race.json")).unwrap()
+    }
+
+    fn get_spec() -> RandomizerSpec {
+        toml::from_str(include_str!("../../input/parameters/randomizer_spec.toml")).unwrap()
+    }
+
+    #[test]
+    fn test_variants_pass_validation() {
+        let base_pars = get_base_pars();
+        assert!(base_pars.validate().errors.is_empty());
+
+        // large inlap shifts to check that the pit stops are kept in the valid range
+        let spec = RandomizerSpec {
+            inlap_shift: 60,
+            ..get_spec()
+        };
+
+        for variant in randomize_scenario(&base_pars, &spec, 50, 7).iter() {
+            let report = variant.validate();
+            assert!(report.errors.is_empty(), "{:?}", report.errors);
+        }
+    }
+    #[test]
+    fn test_variants_reproducible() {
+        let base_pars = get_base_pars();
+        let variants_1 = randomize_scenario(&base_pars, &get_spec(), 5, 42);
+        let variants_2 = randomize_scenario(&base_pars, &get_spec(), 5, 42);
+
+        for (variant_1, variant_2) in variants_1.iter().zip(variants_2.iter()) {
+            assert_eq!(
+                serde_json::to_string(&variant_1.car_pars_all[&44]).unwrap(),
+                serde_json::to_string(&variant_2.car_pars_all[&44]).unwrap()
+            );
+            assert_eq!(
+                variant_1.driver_pars_all["HAM"].consistency,
+                variant_2.driver_pars_all["HAM"].consistency
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod whatif_tests {
+    use crate::core::car::{Car, StrategyEntry};
+    use crate::core::driver::Driver;
+    use crate::core::whatif::{
+        apply_whatif_change, parse_whatif_change, read_checkpoint, run_whatif, Checkpoint,
+    };
+    use crate::pre::read_sim_pars::SimPars;
+    use crate::test_support;
+    use std::rc::Rc;
+
+    /// create_checkpoint creates a checkpoint after lap 3 of the shortened test race (with all
+    /// stochastic elements, seeded such that the race is still running at the checkpoint). Car 44
+    /// gets a second stop in lap 8.
+    fn create_checkpoint() -> Checkpoint {
+        let (mut sim_pars, sim_consts, tire_config) = test_support::load_test_inputs();
+        sim_pars.race_pars.tot_no_laps = 10;
+        sim_pars.race_pars.seed = Some(1);
+        let car_pars_44 = sim_pars.car_pars_all.get_mut(&44).unwrap();
+        car_pars_44.strategy.truncate(1);
+        for &inlap in [5, 8].iter() {
+            car_pars_44.strategy.push(StrategyEntry {
+                inlap,
+                tire_start_age: 0,
+                compound: "MEDIUM".to_string(),
+                driver_initials: String::new(),
+                tire_set: None,
+                engine_mode: None,
+            });
+        }
+
+        Checkpoint::create(&sim_pars, &sim_consts, &tire_config, 0.1, 3).unwrap()
+    }
+
+    fn get_car_ham() -> Car {
+        let sim_pars: SimPars =
+            serde_json::from_str(include_str!("../../input/parameters/test_race.json")).unwrap();
+        let driver = Rc::new(Driver::new(&sim_pars.driver_pars_all["HAM"]));
+        Car::new(&sim_pars.car_pars_all[&44], driver)
+    }
+
+    #[test]
+    fn test_parse_whatif_change() {
+        let change = parse_whatif_change("car_pars_all.44.strategy.2.inlap=34").unwrap();
+        assert_eq!(change.car_no, 44);
+        assert_eq!(change.stop_idx, 2);
+        assert_eq!(change.inlap, Some(34));
+        assert_eq!(change.compound, None);
+
+        let change = parse_whatif_change("car_pars_all.33.strategy.1.compound=medium").unwrap();
+        assert_eq!(change.compound.as_deref(), Some("MEDIUM"));
+
+        assert!(parse_whatif_change("car_pars_all.44.strategy.1.tire_start_age=3").is_err());
+        assert!(parse_whatif_change("race_pars.tot_no_laps=10").is_err());
+        assert!(parse_whatif_change("car_pars_all.44.strategy.1.inlap").is_err());
+    }
+
+    #[test]
+    fn test_replace_future_strategy_keeps_history() {
+        let mut car = get_car_ham();
+        let mut future = car.get_strategy()[1..].to_vec();
+        future[0].inlap = 20;
+        future[0].compound = String::from("HARD");
+
+        // stop in lap 13 was not made yet after 10 laps -> it is replaced
+        car.replace_future_strategy(10, &future);
+        assert_eq!(car.get_strategy().len(), 2);
+        assert_eq!(car.get_strategy()[1].inlap, 20);
+        assert_eq!(car.get_strategy()[1].compound, "HARD");
+
+        // stop in lap 20 was already made after 25 laps -> it is kept, later stops are added
+        future[0].inlap = 30;
+        car.replace_future_strategy(25, &future);
+        let inlaps: Vec<u32> = car.get_strategy().iter().map(|entry| entry.inlap).collect();
+        assert_eq!(inlaps, vec![0, 20, 30]);
+    }
+
+    #[test]
+    fn test_checkpoint_restores_the_same_history() {
+        let checkpoint = create_checkpoint();
+        let dir = std::env::temp_dir().join(format!("racesim_checkpoint_{}", std::process::id()));
+        let path = dir.join("lap3.ckpt");
+        checkpoint.write_to_file(&path).unwrap();
+        let checkpoint_read = read_checkpoint(&path);
+        std::fs::remove_dir_all(&dir).unwrap();
+        let checkpoint_read = checkpoint_read.unwrap();
+        assert_eq!(checkpoint_read.seed, checkpoint.seed);
+        assert_eq!(checkpoint_read.lap, 3);
+
+        // the history is simulated again in exactly the same way
+        let race_a = checkpoint.restore().unwrap();
+        let race_b = checkpoint_read.restore().unwrap();
+        assert!(race_a.cur_lap_leader > 3);
+        assert_eq!(race_a.cur_racetime, race_b.cur_racetime);
+        assert_eq!(race_a.get_race_result().racetimes, race_b.get_race_result().racetimes);
+
+        // the same seed continues the race in the same way
+        let mut race_a = race_a;
+        let mut race_b = race_b;
+        race_a.reseed(7);
+        race_b.reseed(7);
+        while !race_a.get_all_finished() {
+            race_a.simulate_timestep();
+            race_b.simulate_timestep();
+        }
+        assert_eq!(race_a.get_race_result().racetimes, race_b.get_race_result().racetimes);
+    }
+
+    #[test]
+    fn test_duplicate_inlap_rejected() {
+        let checkpoint = create_checkpoint();
+        let mut race = checkpoint.restore().unwrap();
+
+        let change = parse_whatif_change("car_pars_all.44.strategy.1.inlap=8").unwrap();
+        assert!(apply_whatif_change(&mut race, &change).is_err());
+        let change = parse_whatif_change("car_pars_all.44.strategy.2.inlap=5").unwrap();
+        assert!(apply_whatif_change(&mut race, &change).is_err());
+
+        // moving a stop to its own inlap or to a free lap is allowed
+        let change = parse_whatif_change("car_pars_all.44.strategy.1.inlap=5").unwrap();
+        assert!(apply_whatif_change(&mut race, &change).is_ok());
+        let change = parse_whatif_change("car_pars_all.44.strategy.1.inlap=6").unwrap();
+        assert!(apply_whatif_change(&mut race, &change).is_ok());
+        let idx = race.get_car_idx(44).unwrap();
+        let inlaps: Vec<u32> =
+            race.cars_list[idx].get_strategy().iter().map(|entry| entry.inlap).collect();
+        assert_eq!(inlaps, vec![0, 6, 8]);
+    }
+
+    #[test]
+    fn test_run_whatif_from_checkpoint() {
+        let checkpoint = create_checkpoint();
+        let change = parse_whatif_change("car_pars_all.44.strategy.1.inlap=6").unwrap();
+        let comparison = run_whatif(&checkpoint, &change, 2).unwrap();
+
+        assert_eq!(comparison.resume_lap, 3);
+        assert_eq!(comparison.baseline.no_runs, 2);
+        assert_eq!(comparison.modified.positions.len(), 2);
+    }
+}
+
+#[cfg(test)]
+mod pit_summary_tests {
+    use crate::core::handle_race::run_race;
+    use crate::post::output_fmt::OutputFormat;
+    use crate::post::race_result::RaceEvent;
+    use crate::test_support;
+
+    #[test]
+    fn test_pit_summary_known_strategies() {
+        // no retirements and punctures such that both strategies are driven completely
+        let (sim_pars, sim_consts, tire_config) = test_support::load_test_pars();
+
+        let result = run_race(&sim_pars, &sim_consts, &tire_config, 0.1).unwrap();
+        let idx_ham = result.car_driver_pairs.iter().position(|x| x.car_no == 44).unwrap();
+        let idx_ver = result.car_driver_pairs.iter().position(|x| x.car_no == 33).unwrap();
+
+        // HAM stops once (lap 13): pit lane drive through plus standstill
+        assert_eq!(result.no_pitstops[idx_ham], 1);
+        let t_standstill: f64 = result
+            .pit_stops
+            .iter()
+            .filter(|pit_stop| pit_stop.car_no == 44)
+            .map(|pit_stop| pit_stop.t_standstill)
+            .sum();
+        let t_drive =
+            sim_pars.track_pars.real_length_pit_zone / sim_pars.track_pars.pit_speedlimit.m_s;
+        assert!((result.t_pitlane[idx_ham] - (t_drive + t_standstill)).abs() < 0.5);
+
+        // VER does not stop
+        assert_eq!(result.no_pitstops[idx_ver], 0);
+        assert_eq!(result.t_pitlane[idx_ver], 0.0);
+
+        let summary = result.format_pit_summary(idx_ham, &OutputFormat::default()).unwrap();
+        assert!(summary.starts_with("1 stop, "));
+        assert!(result
+            .format_classification(&OutputFormat::default())
+            .unwrap()
+            .contains("#33  VER  0 stops, 0.0 s"));
+    }
+
+    #[test]
+    fn test_pit_stop_events_carry_drawn_times() {
+        let (sim_pars, sim_consts, tire_config) = test_support::load_test_pars();
+        let result = run_race(&sim_pars, &sim_consts, &tire_config, 0.1).unwrap();
+
+        // one event per stop with the same drawn standstill time as the pit stop report
+        let pit_stop_events: Vec<&RaceEvent> =
+            result.events.iter().filter(|ev| ev.kind == "PIT_STOP").collect();
+        assert!(!result.pit_stops.is_empty());
+        assert_eq!(pit_stop_events.len(), result.pit_stops.len());
+
+        for (ev, pit_stop) in pit_stop_events.iter().zip(result.pit_stops.iter()) {
+            let pit_stop_ev = ev.pit_stop.as_ref().unwrap();
+            assert_eq!(ev.cars, vec![pit_stop.car_no]);
+            assert_eq!(pit_stop_ev.lap, pit_stop.lap);
+            assert_eq!(pit_stop_ev.t_standstill, pit_stop.t_standstill);
+        }
+
+        let fastest = result.get_fastest_pit_stop().unwrap();
+        let formatted = result.format_events(&OutputFormat::default()).unwrap();
+        assert!(formatted.contains(&format!(
+            "standstill = {} s",
+            OutputFormat::default().fmt_num(fastest.t_standstill, 0, 2)
+        )));
+    }
+
+    #[test]
+    fn test_stints_follow_the_driven_strategies() {
+        // HAM stops in lap 13 for the same compound, the stop still ends the first stint
+        let (mut sim_pars, sim_consts, tire_config) = test_support::load_test_pars();
+        let result = run_race(&sim_pars, &sim_consts, &tire_config, 0.1).unwrap();
+        let idx_ham = result.car_driver_pairs.iter().position(|x| x.car_no == 44).unwrap();
+        let idx_ver = result.car_driver_pairs.iter().position(|x| x.car_no == 33).unwrap();
+        let last_lap_ham = result.get_last_driven_lap(idx_ham) as u32;
+        let last_lap_ver = result.get_last_driven_lap(idx_ver) as u32;
+
+        assert_eq!(
+            result.get_stints(idx_ham),
+            vec![(1, 13, String::from("SOFT")), (14, last_lap_ham, String::from("SOFT"))]
+        );
+        assert_eq!(result.get_stints(idx_ver), vec![(1, last_lap_ver, String::from("HARD"))]);
+
+        // with a change of the compound the second stint is driven on the new compound
+        sim_pars.car_pars_all.get_mut(&44).unwrap().strategy[1].compound = String::from("MEDIUM");
+        let result = run_race(&sim_pars, &sim_consts, &tire_config, 0.1).unwrap();
+        let last_lap_ham = result.get_last_driven_lap(idx_ham) as u32;
+
+        assert_eq!(
+            result.get_stints(idx_ham),
+            vec![(1, 13, String::from("SOFT")), (14, last_lap_ham, String::from("MEDIUM"))]
+        );
+    }
+}
+
+#[cfg(test)]
+mod lap_callback_tests {
+    use crate::core::handle_race::{run_race_with_options, RunOptions};
+    use crate::core::strategy_controls::{LapContext, StrategyControls};
+    use crate::test_support;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_controller_stops_occur() {
+        let (sim_pars, sim_consts, tire_config) = test_support::load_test_pars();
+
+        // VER has no planned stop, the controller calls him in when the tires are 15 laps old
+        let scheduled: Rc<RefCell<Vec<u32>>> = Rc::new(RefCell::new(Vec::new()));
+        let scheduled_cb = Rc::clone(&scheduled);
+
+        let controller = move |ctx: LapContext, controls: &mut StrategyControls| {
+            for car in ctx.cars.iter().filter(|car| car.car_no == 33) {
+                let inlap = car.compl_lap + 2;
+                if car.tire_age > 15.0 && car.planned_inlaps.is_empty() && inlap < ctx.tot_no_laps {
+                    controls.schedule_pit(car.car_no, inlap, "MEDIUM");
+                    scheduled_cb.borrow_mut().push(inlap);
+                }
+            }
+        };
+
+        let run_opts = RunOptions {
+            on_lap_complete: Some(Box::new(controller)),
+            ..Default::default()
+        };
+        let result = run_race_with_options(&sim_pars, &sim_consts, &tire_config, run_opts).unwrap();
+
+        let inlaps_ver: Vec<u32> = result
+            .pit_stops
+            .iter()
+            .filter(|pit_stop| pit_stop.car_no == 33)
+            .map(|pit_stop| pit_stop.lap)
+            .collect();
+
+        assert!(!scheduled.borrow().is_empty());
+        assert_eq!(*scheduled.borrow(), inlaps_ver);
+    }
+
+    #[test]
+    fn test_rejected_commands_are_recorded() {
+        let (mut sim_pars, sim_consts, tire_config) = test_support::load_test_pars();
+        sim_pars.race_pars.tot_no_laps = 5;
+
+        // the controller sends invalid commands (unknown car, pit stop in the past) after lap 1
+        // and sees their rejection after lap 2
+        let no_rejected: Rc<RefCell<Vec<usize>>> = Rc::new(RefCell::new(Vec::new()));
+        let no_rejected_cb = Rc::clone(&no_rejected);
+
+        let controller = move |ctx: LapContext, controls: &mut StrategyControls| {
+            no_rejected_cb.borrow_mut().push(controls.rejected.len());
+            if ctx.lap == 1 {
+                controls.schedule_pit(99, 3, "MEDIUM");
+                controls.schedule_pit(33, 1, "MEDIUM");
+            }
+        };
+
+        let run_opts = RunOptions {
+            on_lap_complete: Some(Box::new(controller)),
+            ..Default::default()
+        };
+        let result = run_race_with_options(&sim_pars, &sim_consts, &tire_config, run_opts).unwrap();
+
+        assert_eq!(no_rejected.borrow()[0], 0);
+        assert_eq!(*no_rejected.borrow().last().unwrap(), 2);
+        assert!(result.pit_stops.iter().all(|pit_stop| pit_stop.car_no != 33));
+    }
+}
+
+#[cfg(test)]
+mod anonymize_tests {
+    use crate::post::race_result::{CarDriverPair, PitStopInfo, RaceEvent, RaceResult};
+
+    fn get_test_result() -> RaceResult {
+        RaceResult {
+            events: vec![RaceEvent::new("Crash", 2, 100.0, vec![44])],
+            pit_stops: vec![PitStopInfo {
+                car_no: 33,
+                lap: 1,
+                t_standstill: 2.5,
+            }],
+            no_pitstops: vec![0, 1],
+            t_pitlane: vec![0.0, 20.0],
+            ..RaceResult::new(
+                2,
+                vec![
+                    CarDriverPair {
+                        car_no: 44,
+                        driver_initials: String::from("HAM"),
+                    },
+                    CarDriverPair {
+                        car_no: 33,
+                        driver_initials: String::from("VER"),
+                    },
+                ],
+                vec![vec![0.0, 90.0, 90.0], vec![0.5, 90.0, 90.0]],
+                vec![vec![0.0, 90.0, 180.0], vec![0.5, 90.5, 180.5]],
+            )
+        }
+    }
+
+    #[test]
+    fn test_anonymized_identities() {
+        let mut result = get_test_result();
+        result.add_event_contexts();
+        let (anonymized, identity_map) = result.anonymized();
+
+        // aliases are assigned in the order of the real car numbers
+        assert_eq!(anonymized.car_driver_pairs[0].car_no, 2);
+        assert_eq!(anonymized.car_driver_pairs[0].driver_initials, "Car B");
+        assert_eq!(anonymized.car_driver_pairs[1].car_no, 1);
+        assert_eq!(anonymized.car_driver_pairs[1].driver_initials, "Car A");
+
+        assert_eq!(anonymized.events[0].cars, vec![2]);
+        let before = anonymized.events[0].context.as_ref().unwrap().before.as_ref().unwrap();
+        assert_eq!(before.car_nos, vec![2, 1]);
+        assert_eq!(anonymized.pit_stops[0].car_no, 1);
+
+        // timing data is unchanged
+        assert_eq!(anonymized.racetimes, result.racetimes);
+
+        let entry = identity_map.get_entry(44).unwrap();
+        assert_eq!(entry.driver_initials, "HAM");
+        assert_eq!(entry.alias, "Car B");
+    }
+
+    #[test]
+    fn test_anonymized_is_deterministic() {
+        let result = get_test_result();
+        assert_eq!(result.anonymized().1, result.anonymized().1);
+    }
+}
+
+#[cfg(test)]
+mod non_running_cars_tests {
+    use crate::core::car::RetirementCause;
+    use crate::test_support;
+
+    #[test]
+    fn test_standstill_and_dnf_cars_excluded_from_interactions() {
+        let (mut sim_pars, sim_consts, tire_config) = test_support::load_test_pars();
+
+        // four cars: HAM (44) stands in the pits for 10 minutes after lap 13, car 34 retires at
+        // the start, cars 33 and 45 keep racing
+        let mut car_pars_45 = sim_pars.car_pars_all[&44].clone();
+        car_pars_45.car_no = 45;
+        car_pars_45.p_grid = 3;
+        car_pars_45.strategy.truncate(1);
+        let mut car_pars_34 = sim_pars.car_pars_all[&33].clone();
+        car_pars_34.car_no = 34;
+        car_pars_34.p_grid = 4;
+        sim_pars.car_pars_all.insert(45, car_pars_45);
+        sim_pars.car_pars_all.insert(34, car_pars_34);
+        sim_pars.car_pars_all.get_mut(&44).unwrap().t_pit_tirechange = 600.0;
+        sim_pars.race_pars.participants = vec![44, 33, 45, 34];
+
+        let mut race = test_support::create_race(&sim_pars, &sim_consts, &tire_config);
+        let idx_dnf = race.get_car_idx(34).unwrap();
+        let idx_standstill = race.get_car_idx(44).unwrap();
+        race.retire_cars(&[idx_dnf], RetirementCause::Crash);
+
+        let mut standstill_seen = false;
+
+        while !race.get_all_finished() && race.cur_racetime < 20000.0 {
+            race.simulate_timestep();
+
+            standstill_seen |= race.cars_list[idx_standstill].sh.pit_standstill_act;
+
+            for (i, car) in race.cars_list.iter().enumerate() {
+                assert!(!race.cur_laptimes[i].is_nan());
+
+                if i != idx_dnf && !car.sh.pit_standstill_act {
+                    assert!(race.cur_laptimes[i].is_finite(), "car {} stuck", car.car_no);
+                }
+            }
+        }
+
+        assert!(standstill_seen);
+        assert!(race.get_all_finished());
+
+        for car_no in [33, 45].iter() {
+            let idx = race.get_car_idx(*car_no).unwrap();
+            assert!(race.laptimes[idx].iter().all(|t| t.is_finite()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tire_inventory_tests {
+    use crate::core::car::StrategyEntry;
+    use crate::core::race::SimConstants;
+    use crate::core::tireset::TireConfig;
+    use crate::core::weekend::{run_weekend, WeekendPars};
+    use crate::pre::read_sim_pars::SimPars;
+    use std::collections::BTreeMap;
+
+    fn get_entry(inlap: u32, compound: &str, tire_set: Option<u32>) -> StrategyEntry {
+        StrategyEntry {
+            inlap,
+            tire_start_age: 0,
+            compound: String::from(compound),
+            driver_initials: String::from("HAM"),
+            tire_set,
+            engine_mode: None,
+        }
+    }
+
+    /// get_weekend_pars creates a weekend of HAM alone: the sprint is started on a new soft set
+    /// that is replaced after 15 laps, the race is started on the same soft set again. The
+    /// allocation numbers the sets alphabetically, i.e. HARD = 1, MEDIUM = 2, SOFT = 3.
+    fn get_weekend_pars(no_soft_sets: u32, race_start_set: Option<u32>) -> WeekendPars {
+        let mut sim_pars: SimPars =
+            serde_json::from_str(include_str!("../../input/parameters/test_race.json")).unwrap();
+        sim_pars.race_pars.participants = vec![44];
+
+        let mut sprint = sim_pars.clone();
+        sprint.race_pars.tot_no_laps = 20;
+        sprint.car_pars_all.get_mut(&44).unwrap().strategy =
+            vec![get_entry(0, "SOFT", None), get_entry(15, "HARD", None)];
+
+        let mut race = sim_pars;
+        race.race_pars.tot_no_laps = 25;
+        race.car_pars_all.get_mut(&44).unwrap().strategy =
+            vec![get_entry(0, "SOFT", race_start_set), get_entry(10, "MEDIUM", None)];
+
+        let mut tire_allocation = BTreeMap::new();
+        tire_allocation.insert(String::from("HARD"), 1);
+        tire_allocation.insert(String::from("MEDIUM"), 1);
+        tire_allocation.insert(String::from("SOFT"), no_soft_sets);
+
+        WeekendPars {
+            sprint,
+            race,
+            tire_allocation,
+        }
+    }
+
+    #[test]
+    fn test_sprint_set_refitted_with_age() {
+        let mut sim_consts: SimConstants =
+            serde_json::from_str(include_str!("../../input/parameters/sim_constants.json")).unwrap();
+        let tire_config: TireConfig =
+            serde_json::from_str(include_str!("../../input/parameters/tires.json")).unwrap();
+        sim_consts.failure_rate_per_hour = 0.0;
+
+        let weekend_pars = get_weekend_pars(1, Some(3));
+        let result = run_weekend(&weekend_pars, &sim_consts, &tire_config, 0.1).unwrap();
+
+        // after the sprint the soft set is used and 15 laps old
+        let soft_set_sprint = result.sprint.tire_inventories[0].get_set(3).unwrap();
+        assert_eq!(soft_set_sprint.age, 15.0);
+        assert_eq!(result.sprint.tire_inventories[0].get_no_new_sets("SOFT"), 0);
+
+        // the race is started on the same set with its age from the sprint
+        let soft_set_race = result.race.tire_inventories[0].get_set(3).unwrap();
+        assert_eq!(soft_set_race.stints.len(), 2);
+        assert_eq!(soft_set_race.stints[1].session, "Race");
+        assert_eq!(soft_set_race.stints[1].age_fitted, 15.0);
+        assert_eq!(soft_set_race.stints[1].age_removed, Some(25.0));
+    }
+
+    #[test]
+    fn test_plan_exceeding_allocation_rejected() {
+        // both sessions start on a new soft set, but only one is allocated
+        assert!(get_weekend_pars(1, None).validate().is_err());
+        assert!(get_weekend_pars(2, None).validate().is_ok());
+        assert!(get_weekend_pars(1, Some(3)).validate().is_ok());
+        // set 2 is the medium set
+        assert!(get_weekend_pars(2, Some(2)).validate().is_err());
+    }
+}
+
+#[cfg(test)]
+mod lap_flags_tests {
+    use crate::core::handle_race::run_race;
+    use crate::post::output_fmt::OutputFormat;
+    use crate::post::race_result::{
+        format_lap_flags, LAP_FLAG_PIT, LAP_FLAG_SC, LAP_FLAG_TRAFFIC, LAP_FLAG_YELLOW,
+    };
+    use crate::test_support;
+
+    #[test]
+    fn test_format_lap_flags() {
+        assert_eq!(format_lap_flags(0), "");
+        assert_eq!(format_lap_flags(LAP_FLAG_TRAFFIC | LAP_FLAG_SC), "[T][SC]");
+        assert_eq!(format_lap_flags(LAP_FLAG_SC | LAP_FLAG_YELLOW), "[SC][Y]");
+    }
+
+    #[test]
+    fn test_pit_laps_flagged() {
+        let (sim_pars, sim_consts, tire_config) = test_support::load_test_pars();
+
+        let result = run_race(&sim_pars, &sim_consts, &tire_config, 0.1).unwrap();
+        let idx_ham = result.car_driver_pairs.iter().position(|x| x.car_no == 44).unwrap();
+        let idx_ver = result.car_driver_pairs.iter().position(|x| x.car_no == 33).unwrap();
+
+        // HAM's in lap (13) and out lap (14) are pit laps, VER never stops
+        let pit_laps_ham: Vec<usize> = (1..=result.tot_no_laps as usize)
+            .filter(|&lap| result.get_lap_flags(idx_ham, lap) & LAP_FLAG_PIT != 0)
+            .collect();
+        assert_eq!(pit_laps_ham, vec![13, 14]);
+        assert!((1..=result.tot_no_laps as usize)
+            .all(|lap| result.get_lap_flags(idx_ver, lap) & LAP_FLAG_PIT == 0));
+
+        let out_fmt = OutputFormat::default();
+        assert!(result.format_lap_and_race_times(&out_fmt).unwrap().contains("[P]"));
+
+        let csv = result.format_lap_times_csv(&out_fmt).unwrap();
+        let header: Vec<&str> = csv.lines().next().unwrap().split(',').collect();
+        assert_eq!(header.len(), 5);
+        let col_flags_ham = header.iter().position(|x| *x == "44 flags").unwrap();
+        let row_lap_13: Vec<&str> = csv.lines().nth(13).unwrap().split(',').collect();
+        assert!(row_lap_13[col_flags_ham].contains("[P]"));
+    }
+}
+
+#[cfg(test)]
+mod drs_train_tests {
+    use crate::core::race::{compare_drs_trains, find_drs_trains};
+    use crate::core::state_handler::StateHandler;
+
+    #[test]
+    fn test_find_drs_trains() {
+        let car_nos = [1, 2, 3, 4, 5, 6, 7];
+
+        assert_eq!(
+            find_drs_trains(&car_nos, &[false, true, true, true, false, true, true]),
+            vec![vec![2, 3, 4]]
+        );
+        assert!(find_drs_trains(&car_nos, &[true, true, false, true, true, false, false]).is_empty());
+        assert_eq!(
+            find_drs_trains(&car_nos, &[false, false, false, false, true, true, true]),
+            vec![vec![5, 6, 7]]
+        );
+        // a train is not split when its cars cross the finish line (end of the list)
+        assert_eq!(
+            find_drs_trains(&car_nos, &[true, true, false, false, false, true, true]),
+            vec![vec![6, 7, 1, 2]]
+        );
+        assert_eq!(
+            find_drs_trains(&car_nos, &[true; 7]),
+            vec![vec![1, 2, 3, 4, 5, 6, 7]]
+        );
+    }
+
+    #[test]
+    fn test_drs_train_events_only_on_change() {
+        let trains = vec![vec![1, 2, 3]];
+
+        // unchanged and continued trains (a car joining or dropping out) produce no event
+        assert_eq!(compare_drs_trains(&trains, &trains), (vec![], vec![]));
+        assert_eq!(compare_drs_trains(&trains, &[vec![1, 2, 3, 4]]), (vec![], vec![]));
+        assert_eq!(compare_drs_trains(&[vec![1, 2, 3, 4]], &trains), (vec![], vec![]));
+
+        // formation and break-up
+        assert_eq!(compare_drs_trains(&[], &trains), (trains.clone(), vec![]));
+        assert_eq!(compare_drs_trains(&trains, &[]), (vec![], trains.clone()));
+        assert_eq!(
+            compare_drs_trains(&trains, &[vec![5, 6, 7]]),
+            (vec![vec![5, 6, 7]], trains.clone())
+        );
+    }
+
+    #[test]
+    fn test_drs_eligibility_at_measurement_point() {
+        let mut sh = StateHandler::default();
+        sh.initialize_state_handler(
+            true,
+            500.0,
+            1.0,
+            1990.0,
+            5000.0,
+            vec![2000.0],
+            [4800.0, 100.0],
+            vec![[2000.0, 2500.0]],
+            vec![],
+        );
+
+        // passes the measurement point 0.5s behind the car ahead -> DRS in the following zone
+        sh.update_race_prog(100.0, 0.4);
+        sh.check_state_transition(0.5, f64::INFINITY, false, false, true);
+        assert!(sh.get_drs_eligible());
+        assert!(sh.drs_act);
+
+        // falling out of the window after the measurement point does not remove the eligibility
+        sh.update_race_prog(100.0, 0.4);
+        sh.check_state_transition(1.5, f64::INFINITY, false, false, true);
+        assert!(sh.drs_act);
+
+        // DRS disabled by race control (e.g. yellow flag)
+        sh.check_state_transition(0.5, f64::INFINITY, false, false, false);
+        assert!(!sh.drs_act);
+    }
+}
+
+#[cfg(test)]
+mod debris_tests {
+    use crate::core::race::is_in_s_range;
+    use crate::test_support;
+
+    #[test]
+    fn test_is_in_s_range() {
+        assert!(is_in_s_range(150.0, [100.0, 200.0]));
+        assert!(!is_in_s_range(250.0, [100.0, 200.0]));
+        // section across the finish line
+        assert!(is_in_s_range(5750.0, [5700.0, 50.0]));
+        assert!(is_in_s_range(20.0, [5700.0, 50.0]));
+        assert!(!is_in_s_range(100.0, [5700.0, 50.0]));
+    }
+
+    #[test]
+    fn test_debris_zone_created_and_cleared() {
+        let (sim_pars, mut sim_consts, tire_config) = test_support::load_test_pars();
+        sim_consts.debris_probability = 0.0;
+
+        let mut race = test_support::create_race(&sim_pars, &sim_consts, &tire_config);
+
+        // contact in a corner -> the whole corner, on a straight -> 100 m around the contact
+        race.add_debris_zone(2150.0, vec![44, 33], 1);
+        race.add_debris_zone(3500.0, vec![44, 33], 2);
+        let s_ranges: Vec<[f64; 2]> = race.get_debris_zones().iter().map(|x| x.s_range).collect();
+        assert_eq!(s_ranges, vec![[2100.0, 2220.0], [3400.0, 3600.0]]);
+
+        race.simulate_until_leader_lap(4);
+        assert!(race.get_debris_zones().is_empty());
+
+        let result = race.get_race_result();
+        let debris_events: Vec<(&str, u32, Option<[f64; 2]>)> = result
+            .events
+            .iter()
+            .filter(|ev| ev.kind.starts_with("DEBRIS"))
+            .map(|ev| (ev.kind.as_str(), ev.lap, ev.s_range))
+            .collect();
+        assert_eq!(
+            debris_events,
+            vec![
+                ("DEBRIS_START", 1, Some([2100.0, 2220.0])),
+                ("DEBRIS_START", 1, Some([3400.0, 3600.0])),
+                ("DEBRIS_END", 2, Some([2100.0, 2220.0])),
+                ("DEBRIS_END", 3, Some([3400.0, 3600.0])),
+            ]
+        );
+    }
+}
+
+#[cfg(test)]
+mod photo_finish_tests {
+    use crate::core::race::calc_crossing_time;
+    use crate::post::output_fmt::OutputFormat;
+    use crate::post::race_result::{CarDriverPair, RaceResult, PHOTO_FINISH_THRESHOLD};
+    use crate::test_support;
+
+    /// simulate_last_lap drives the cars with constant lap times from their race progress at
+    /// t = 0 s to the finish line (race progress 1.0) and returns the interpolated finish times.
+    fn simulate_last_lap(timestep_size: f64, race_progs_start: &[f64], laptimes: &[f64]) -> Vec<f64> {
+        race_progs_start
+            .iter()
+            .zip(laptimes.iter())
+            .map(|(&race_prog_start, &laptime)| {
+                let mut race_prog = race_prog_start;
+                let mut t_step_start = 0.0;
+
+                loop {
+                    let race_prog_prev = race_prog;
+                    race_prog += timestep_size / laptime;
+
+                    if race_prog >= 1.0 {
+                        return calc_crossing_time(
+                            t_step_start,
+                            timestep_size,
+                            race_prog_prev,
+                            race_prog,
+                            1.0,
+                        );
+                    }
+
+                    t_step_start += timestep_size;
+                }
+            })
+            .collect()
+    }
+
+    fn get_test_result(t_finish: &[f64]) -> RaceResult {
+        RaceResult::new(
+            1,
+            vec![
+                CarDriverPair {
+                    car_no: 1,
+                    driver_initials: String::from("AAA"),
+                },
+                CarDriverPair {
+                    car_no: 2,
+                    driver_initials: String::from("BBB"),
+                },
+            ],
+            t_finish.iter().map(|&t| vec![0.0, t]).collect(),
+            t_finish.iter().map(|&t| vec![0.0, t]).collect(),
+        )
+    }
+
+    #[test]
+    fn test_calc_crossing_time() {
+        assert!((calc_crossing_time(10.0, 0.2, 0.9, 1.1, 1.0) - 10.1).abs() < 1e-12);
+        assert!((calc_crossing_time(10.0, 0.2, 2.95, 3.0, 3.0) - 10.2).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_photo_finish_independent_of_timestep() {
+        // car 2 starts the last lap slightly ahead but is slower (tuned t_car) such that it
+        // finishes 5 ms after car 1
+        let laptimes = [80.0, 80.1];
+        let t_finish_exp = [80.0, 80.005];
+        let race_progs_start = [0.0, 1.0 - t_finish_exp[1] / laptimes[1]];
+
+        for &timestep_size in [0.2, 0.02].iter() {
+            let t_finish = simulate_last_lap(timestep_size, &race_progs_start, &laptimes);
+            let result = get_test_result(&t_finish);
+
+            assert_eq!(result.get_final_positions(), vec![1, 2]);
+            let gap = result.get_gap_to_winner(1).unwrap();
+            assert!((gap - (t_finish_exp[1] - t_finish_exp[0])).abs() < 0.001, "gap {}", gap);
+
+            let classification = result.format_classification(&OutputFormat::default()).unwrap();
+            assert!(classification.contains("+0.005 s"));
+            assert!(classification.contains("Photo finish #1 - #2"));
+        }
+    }
+
+    /// run_photo_finish_race simulates the first ten seconds of a one-lap race with the given
+    /// timestep size, puts car 44 20 m and the slower car 33 20.3 m before the finish line and
+    /// returns the result after both cars finished. All stochastic model components that could
+    /// change the order (also jump start penalties) are switched off and the cars may run side by
+    /// side.
+    fn run_photo_finish_race(timestep_size: f64) -> RaceResult {
+        let (mut sim_pars, mut sim_consts, tire_config) = test_support::load_test_pars();
+        sim_consts.min_spacing_m = 0.0;
+        sim_consts.jump_start_threshold_s = 0.0;
+        sim_pars.race_pars.tot_no_laps = 1;
+        sim_pars.race_pars.model_toggles = "dirty-air,sc,weather,mistakes,duels".parse().unwrap();
+        for driver_pars in sim_pars.driver_pars_all.values_mut() {
+            driver_pars.aggression = 0.0;
+        }
+        let t_car_44 = sim_pars.car_pars_all[&44].t_car;
+        sim_pars.car_pars_all.get_mut(&33).unwrap().t_car = t_car_44 + 1.0;
+
+        let mut race = test_support::create_race_with_timestep(
+            &sim_pars,
+            &sim_consts,
+            &tire_config,
+            timestep_size,
+        );
+
+        while race.cur_racetime < 10.0 {
+            race.simulate_timestep();
+        }
+
+        for (car_no, dist_to_line) in [(44, 20.0), (33, 20.3)].iter() {
+            let idx = race.get_car_idx(*car_no).unwrap();
+            race.cars_list[idx].sh.place_race_prog(1.0 - dist_to_line / race.track.length);
+        }
+
+        while !race.get_all_finished() {
+            race.simulate_timestep();
+        }
+
+        race.get_race_result()
+    }
+
+    #[test]
+    fn test_photo_finish_in_race_independent_of_timestep() {
+        let result_coarse = run_photo_finish_race(0.2);
+        let result_fine = run_photo_finish_race(0.02);
+
+        // car 44 wins by its 0.3 m lead with both timestep sizes
+        for result in [&result_coarse, &result_fine].iter() {
+            let positions = result.get_final_positions();
+            let idx_44 = result.car_driver_pairs.iter().position(|x| x.car_no == 44).unwrap();
+            assert_eq!(positions[idx_44], 1);
+
+            let classification = result.format_classification(&OutputFormat::default()).unwrap();
+            assert!(classification.contains("Photo finish #44 - #33"), "{}", classification);
+        }
+        assert_eq!(result_coarse.get_final_positions(), result_fine.get_final_positions());
+
+        let idx_33 = result_fine.car_driver_pairs.iter().position(|x| x.car_no == 33).unwrap();
+        let gap_coarse = result_coarse.get_gap_to_winner(idx_33).unwrap();
+        let gap_fine = result_fine.get_gap_to_winner(idx_33).unwrap();
+        assert!(gap_fine > 0.0 && gap_fine < PHOTO_FINISH_THRESHOLD, "gap {}", gap_fine);
+        // the gap is interpolated, not quantized to the coarse timestep
+        assert!((gap_coarse - gap_fine).abs() < 0.005, "gaps {} {}", gap_coarse, gap_fine);
+    }
+}
+
+#[cfg(test)]
+mod wet_incident_tests {
+    use crate::core::handle_race::run_race;
+    use crate::core::race::SimConstants;
+    use crate::core::tireset::TireConfig;
+    use crate::post::race_result::{IncidentRates, RaceEvent};
+    use crate::pre::read_sim_pars::SimPars;
+    use crate::test_support;
+
+    fn get_test_pars() -> (SimPars, SimConstants, TireConfig) {
+        let (sim_pars, sim_consts, tire_config) = test_support::load_test_pars();
+        (sim_pars, sim_consts, tire_config)
+    }
+
+    #[test]
+    fn test_incident_factor_follows_wetness() {
+        let (mut sim_pars, mut sim_consts, tire_config) = get_test_pars();
+        sim_consts.incident_wetness_curve = vec![[0.0, 1.0], [0.5, 1.5], [1.0, 3.0]];
+
+        let race_dry = test_support::create_race(&sim_pars, &sim_consts, &tire_config);
+        assert_eq!(race_dry.get_wetness(), 0.0);
+        assert_eq!(race_dry.get_incident_factor(), 1.0);
+
+        sim_pars.race_pars.initial_weather = String::from("Rain");
+        let race_wet = test_support::create_race(&sim_pars, &sim_consts, &tire_config);
+        assert_eq!(race_wet.get_wetness(), 1.0);
+        assert_eq!(race_wet.get_incident_factor(), 3.0);
+    }
+
+    #[test]
+    fn test_incident_rates_by_weather() {
+        let (sim_pars, sim_consts, tire_config) = get_test_pars();
+        let mut result = run_race(&sim_pars, &sim_consts, &tire_config, 0.1).unwrap();
+
+        // rain from lap 11 to lap 20, one incident in the dry and two in the wet
+        for lap in 11..=20 {
+            result.weather_history[lap - 1] = String::from("Rain");
+        }
+        result.events.clear();
+        for &(kind, lap) in [("Contact", 5), ("Crash", 12), ("GEARBOX_FAILURE", 20), ("SC_IN", 15)].iter() {
+            result.events.push(RaceEvent::new(kind, lap, 0.0, vec![44]));
+        }
+
+        let incident_rates = IncidentRates::from_results(&[result.clone(), result]);
+        assert_eq!(incident_rates.no_laps_wet, 20);
+        assert_eq!(incident_rates.no_laps_dry, 86);
+        assert_eq!(incident_rates.no_incidents_dry, 2);
+        assert_eq!(incident_rates.no_incidents_wet, 4);
+        assert!((incident_rates.get_rate_wet().unwrap() - 20.0).abs() < 1e-9);
+    }
+}
+
+#[cfg(test)]
+mod report_tests {
+    use crate::core::handle_race::run_race;
+    use crate::post::output_fmt::OutputFormat;
+    use crate::post::report::{create_html_report, encode_base64, escape_html, ReportInput, ReportPlot};
+    use crate::test_support;
+
+    #[test]
+    fn test_encode_base64() {
+        assert_eq!(encode_base64(b"Man"), "TWFu");
+        assert_eq!(encode_base64(b"Ma"), "TWE=");
+        assert_eq!(encode_base64(b"M"), "TQ==");
+        assert_eq!(encode_base64(b""), "");
+    }
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(escape_html("<a & \"b\">"), "&lt;a &amp; &quot;b&quot;&gt;");
+    }
+
+    #[test]
+    fn test_create_html_report() {
+        let (sim_pars, sim_consts, tire_config) = test_support::load_test_pars();
+
+        let result = run_race(&sim_pars, &sim_consts, &tire_config, 0.1).unwrap();
+        let plots = vec![ReportPlot {
+            title: String::from("Lap time"),
+            png: vec![0x89, 0x50, 0x4e, 0x47],
+        }];
+        let report_input = ReportInput {
+            result: &result,
+            sim_pars: &sim_pars,
+            sim_consts: &sim_consts,
+            sanity_warnings: &[],
+            plots: &plots,
+            provenance: None,
+        };
+
+        let html = create_html_report(&report_input, &OutputFormat::default()).unwrap();
+        assert!(html.contains("<table>"));
+        assert!(html.contains("data:image/png;base64,iVBORw=="));
+        assert!(html.contains("<details>"));
+        assert!(html.contains("HAM"));
+    }
+}
+
+#[cfg(test)]
+mod scripted_event_tests {
+    use crate::core::handle_race::run_race;
+    use crate::core::scripted_event::{
+        validate_scripted_events, ScriptedAction, ScriptedEvent, ScriptedOverrides,
+    };
+    use crate::post::race_result::ScPeriodKind;
+    use crate::test_support;
+
+    fn get_scripted_events(json: &str) -> Vec<ScriptedEvent> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_scripted_event_parsing() {
+        let scripted_events = get_scripted_events(
+            r#"[
+                {"lap": 12, "type": "Weather", "weather": "Rain"},
+                {"lap": 31, "type": "Dnf", "car_no": 63, "override": false},
+                {"lap": 40, "type": "SafetyCar", "no_laps": 4}
+            ]"#,
+        );
+
+        assert_eq!(
+            scripted_events[0].action,
+            ScriptedAction::Weather { weather: String::from("Rain") }
+        );
+        assert!(scripted_events[0].override_stochastic);
+        assert!(!scripted_events[1].override_stochastic);
+        assert_eq!(scripted_events[1].get_car_no(), Some(63));
+        assert_eq!(
+            ScriptedOverrides::from_events(&scripted_events),
+            ScriptedOverrides { weather: true, dnf: false, safety_car: true }
+        );
+    }
+
+    #[test]
+    fn test_scripted_event_validation() {
+        let valid = get_scripted_events(
+            r#"[
+                {"lap": 20, "type": "Weather", "weather": "Dry"},
+                {"lap": 12, "type": "Weather", "weather": "Rain"},
+                {"lap": 40, "type": "SafetyCar", "no_laps": 4},
+                {"lap": 44, "type": "SafetyCar", "no_laps": 1},
+                {"lap": 30, "type": "Penalty", "car_no": 44, "t_penalty": 5.0},
+                {"lap": 31, "type": "Dnf", "car_no": 44}
+            ]"#,
+        );
+        assert!(validate_scripted_events(&valid, 53, &[44, 33], "Dry").is_empty());
+
+        let invalid = get_scripted_events(
+            r#"[
+                {"lap": 0, "type": "Weather", "weather": "Rain"},
+                {"lap": 5, "type": "Weather", "weather": "Rain"},
+                {"lap": 40, "type": "SafetyCar", "no_laps": 4},
+                {"lap": 43, "type": "SafetyCar", "no_laps": 2},
+                {"lap": 10, "type": "Dnf", "car_no": 63},
+                {"lap": 10, "type": "Dnf", "car_no": 33},
+                {"lap": 12, "type": "Penalty", "car_no": 33, "t_penalty": 5.0}
+            ]"#,
+        );
+        let errors = validate_scripted_events(&invalid, 53, &[44, 33], "Dry");
+        // lap 0, rain while raining, overlapping safety cars, unknown car, penalty after DNF
+        assert_eq!(errors.len(), 5, "{:?}", errors);
+    }
+
+    #[test]
+    fn test_scripted_race() {
+        let (mut sim_pars, sim_consts, tire_config) = test_support::load_test_inputs();
+        // the scripted DNF disables failures and collisions, the scripted SC the stochastic SC
+        sim_pars.race_pars.scripted_events = get_scripted_events(
+            r#"[
+                {"lap": 5, "type": "Weather", "weather": "Rain"},
+                {"lap": 10, "type": "Weather", "weather": "Dry"},
+                {"lap": 8, "type": "Dnf", "car_no": 33},
+                {"lap": 15, "type": "SafetyCar", "no_laps": 3},
+                {"lap": 30, "type": "Penalty", "car_no": 44, "t_penalty": 10.0}
+            ]"#,
+        );
+        assert!(sim_pars.validate().errors.is_empty());
+
+        let result = run_race(&sim_pars, &sim_consts, &tire_config, 0.1).unwrap();
+
+        assert_eq!(result.weather_history[3], "Dry");
+        assert_eq!(result.weather_history[4], "Rain");
+        assert_eq!(result.weather_history[8], "Rain");
+        assert_eq!(result.weather_history[9], "Dry");
+
+        let events: Vec<(&str, u32, bool)> = result
+            .events
+            .iter()
+            .filter(|ev| ev.kind != "LeadChange")
+            .map(|ev| (ev.kind.as_str(), ev.lap, ev.scripted))
+            .collect();
+        assert!(events.contains(&("WeatherRainStart", 5, true)));
+        assert!(events.contains(&("WeatherDryStart", 10, true)));
+        assert!(events.contains(&("Retirement", 8, true)));
+        assert!(events.contains(&("SC_DEPLOYED", 15, true)));
+        assert!(events.contains(&("Penalty", 30, true)));
+        assert_eq!(events.iter().filter(|x| x.0 == "SC_DEPLOYED").count(), 1);
+
+        let sc_in = events.iter().find(|x| x.0 == "SC_IN").unwrap();
+        assert!(sc_in.1 >= 17 && sc_in.2);
+
+        // the scripted SC is the only neutralization, it has no causing car
+        assert_eq!(result.sc_periods.len(), 1);
+        let sc_period = &result.sc_periods[0];
+        assert_eq!(sc_period.kind, ScPeriodKind::Sc);
+        assert_eq!((sc_period.start_lap, sc_period.end_lap), (15, sc_in.1));
+        assert_eq!(sc_period.cause_car_no, None);
+        assert!(sc_period.t_end > sc_period.t_start);
+
+        // the penalty is served in lap 30 of the (only remaining) car 44
+        let idx = result.car_driver_pairs.iter().position(|x| x.car_no == 44).unwrap();
+        let laptimes = &result.laptimes[idx];
+        assert!(laptimes[30] > laptimes[28].max(laptimes[29]) + 8.0);
+    }
+}
+
+#[cfg(test)]
+mod practice_tests {
+    use crate::core::practice::run_practice;
+    use crate::post::output_fmt::OutputFormat;
+    use crate::test_support;
+
+    #[test]
+    fn test_practice_breakdown() {
+        let (sim_pars, sim_consts, tire_config) = test_support::load_test_inputs();
+        let practice_result = run_practice(&sim_pars, &sim_consts, &tire_config, 33, 10, 0.1).unwrap();
+        assert_eq!(practice_result.laps.len(), 10);
+
+        for lap in practice_result.laps.iter() {
+            let breakdown = &lap.breakdown;
+            assert!(lap.laptime > 0.0);
+            assert!(breakdown.t_base > 0.0);
+            assert!((breakdown.get_total() - breakdown.t_base - breakdown.t_car - breakdown.t_driver
+                - breakdown.t_tire - breakdown.t_fuel - breakdown.t_weather - breakdown.t_damage
+                - breakdown.t_noise - breakdown.t_launch)
+                .abs()
+                < 1e-9);
+        }
+
+        // the standing start is booked in the first lap only
+        assert!(practice_result.laps[0].breakdown.t_launch > 0.0);
+        assert!(practice_result.laps[1..].iter().all(|lap| lap.breakdown.t_launch == 0.0));
+
+        // the fuel burns off, the tires degrade
+        let first = &practice_result.laps[1].breakdown;
+        let last = &practice_result.laps[9].breakdown;
+        assert!(last.t_fuel < first.t_fuel);
+        assert!(last.t_tire >= first.t_tire);
+
+        let csv = practice_result.format_breakdown_csv(&OutputFormat::default()).unwrap();
+        assert_eq!(csv.lines().count(), 11);
+        assert!(csv.starts_with("lap,compound,laptime,th_laptime"));
+
+        assert!(run_practice(&sim_pars, &sim_consts, &tire_config, 99, 10, 0.1).is_err());
+    }
+}
+
+#[cfg(test)]
+mod race_length_tests {
+    use crate::core::race::SimConstants;
+    use crate::post::output_fmt::OutputFormat;
+    use crate::post::race_result::{get_lap_axis, CarDriverPair, RaceResult, LAPS_PER_TEXT_BLOCK};
+    use crate::post::report::{create_html_report, ReportInput};
+    use crate::pre::read_sim_pars::SimPars;
+
+    /// get_test_result creates a result of two cars over the given number of laps, car 2 is half
+    /// a second per lap slower than car 1.
+    fn get_test_result(tot_no_laps: u32) -> RaceResult {
+        let laptimes: Vec<Vec<f64>> = [80.0, 80.5]
+            .iter()
+            .map(|&laptime| {
+                (0..tot_no_laps as usize + 1)
+                    .map(|lap| if lap == 0 { 0.0 } else { laptime })
+                    .collect()
+            })
+            .collect();
+        let racetimes = laptimes
+            .iter()
+            .map(|car_laptimes| {
+                car_laptimes
+                    .iter()
+                    .scan(0.0, |racetime, laptime| {
+                        *racetime += laptime;
+                        Some(*racetime)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut result = RaceResult {
+            weather_history: vec![String::from("Dry"); tot_no_laps as usize],
+            no_pitstops: vec![0, 0],
+            t_pitlane: vec![0.0, 0.0],
+            ..RaceResult::new(
+                tot_no_laps,
+                vec![
+                    CarDriverPair {
+                        car_no: 1,
+                        driver_initials: String::from("AAA"),
+                    },
+                    CarDriverPair {
+                        car_no: 2,
+                        driver_initials: String::from("BBB"),
+                    },
+                ],
+                laptimes,
+                racetimes,
+            )
+        };
+        result.add_event_contexts();
+        result
+    }
+
+    /// check_exporters runs the result through every text exporter and the HTML report.
+    fn check_exporters(result: &RaceResult) {
+        let out_fmt = OutputFormat::default();
+
+        let text = result.format_lap_and_race_times(&out_fmt).unwrap();
+        assert!(text.contains("RESULT: Race times"));
+        let csv = result.format_lap_times_csv(&out_fmt).unwrap();
+        assert_eq!(csv.lines().count(), result.tot_no_laps as usize + 1);
+        result.format_events(&out_fmt).unwrap();
+        result.format_classification(&out_fmt).unwrap();
+        result.sanity_check(75.0, 25.0);
+        result.get_incident_rates();
+
+        let json = serde_json::to_string(result).unwrap();
+        let result_json: RaceResult = serde_json::from_str(&json).unwrap();
+        assert_eq!(result_json.tot_no_laps, result.tot_no_laps);
+
+        let sim_pars: SimPars =
+            serde_json::from_str(include_str!("../../input/parameters/test_race.json")).unwrap();
+        let sim_consts: SimConstants =
+            serde_json::from_str(include_str!("../../input/parameters/sim_constants.json")).unwrap();
+        let report_input = ReportInput {
+            result,
+            sim_pars: &sim_pars,
+            sim_consts: &sim_consts,
+            sanity_warnings: &[],
+            plots: &[],
+            provenance: None,
+        };
+        assert!(create_html_report(&report_input, &out_fmt).unwrap().contains("AAA"));
+    }
+
+    #[test]
+    fn test_single_lap_race() {
+        let result = get_test_result(1);
+        check_exporters(&result);
+
+        let classification = result.format_classification(&OutputFormat::default()).unwrap();
+        assert!(classification.contains("+0.500 s"));
+        assert!(result.sanity_check(75.0, 25.0).is_empty());
+
+        let lap_axis = get_lap_axis(1);
+        assert!(lap_axis.range.contains(&1));
+        assert_eq!(lap_axis.step, 1);
+    }
+
+    #[test]
+    fn test_single_lap_race_without_completed_lap() {
+        // car 2 retired in the first lap and its lap times were never extended
+        let mut result = get_test_result(1);
+        result.laptimes[1] = vec![0.0];
+        result.racetimes[1] = vec![0.0];
+        check_exporters(&result);
+
+        assert_eq!(result.get_final_positions(), vec![1, 2]);
+        assert_eq!(result.get_gap_to_winner(1), None);
+        let classification = result.format_classification(&OutputFormat::default()).unwrap();
+        assert!(classification.contains("+1 lap"));
+    }
+
+    #[test]
+    fn test_long_race() {
+        let result = get_test_result(500);
+        check_exporters(&result);
+
+        // the lap times are written in blocks, each with its own header line
+        let text = result.format_lap_and_race_times(&OutputFormat::default()).unwrap();
+        let no_headers = text.lines().filter(|line| line.starts_with("lap, ")).count();
+        assert_eq!(no_headers, 2 * 500_usize.div_ceil(LAPS_PER_TEXT_BLOCK));
+        assert!(text.contains("500, "));
+
+        let lap_axis = get_lap_axis(500);
+        assert_eq!(lap_axis.range, 1..500);
+        assert_eq!(lap_axis.step, 50);
+        assert!(lap_axis.no_labels <= 20);
+    }
+
+    #[test]
+    fn test_lap_axis_of_very_long_race() {
+        let lap_axis = get_lap_axis(100000);
+        assert!(lap_axis.no_labels <= 20);
+        assert!(lap_axis.step * (lap_axis.no_labels as u32 - 1) < 100000);
+    }
+}
+
+#[cfg(test)]
+mod livery_tests {
+    use crate::core::handle_race::run_race;
+    use crate::core::livery::{get_fallback_color, resolve_car_color};
+    use crate::core::race::SimConstants;
+    use crate::core::tireset::TireConfig;
+    use crate::pre::read_sim_pars::SimPars;
+    use crate::test_support;
+
+    fn get_test_pars() -> (SimPars, SimConstants, TireConfig) {
+        let (sim_pars, sim_consts, tire_config) = test_support::load_test_pars();
+        (sim_pars, sim_consts, tire_config)
+    }
+
+    #[test]
+    fn test_fallback_color() {
+        // stable for a car number and different for neighbouring car numbers
+        assert_eq!(get_fallback_color(44), get_fallback_color(44));
+        assert_ne!(get_fallback_color(1), get_fallback_color(2));
+
+        let color = resolve_car_color("", 44).unwrap();
+        assert!(color.fallback);
+        assert_eq!(color.rgb, get_fallback_color(44));
+
+        let color = resolve_car_color("#FF0000", 44).unwrap();
+        assert!(!color.fallback);
+        assert_eq!(color.rgb, [255, 0, 0]);
+        assert_eq!(color.to_hex(), "#FF0000");
+
+        assert!(resolve_car_color("not a color", 44).is_err());
+    }
+
+    #[test]
+    fn test_fallback_color_in_validation_and_result() {
+        let (mut sim_pars, sim_consts, tire_config) = get_test_pars();
+        sim_pars.car_pars_all.get_mut(&44).unwrap().color = String::new();
+
+        let report = sim_pars.validate();
+        assert!(report.errors.is_empty());
+        assert!(report
+            .warnings
+            .iter()
+            .any(|msg| msg.contains("Car 44") && msg.contains("fallback")));
+
+        let result = run_race(&sim_pars, &sim_consts, &tire_config, 0.1).unwrap();
+        let idx = result.car_driver_pairs.iter().position(|pair| pair.car_no == 44).unwrap();
+        assert_eq!(result.get_car_color(idx), get_fallback_color(44));
+
+        // anonymized results must not reveal the liveries
+        let (result_anonymized, _) = result.anonymized();
+        assert!(result_anonymized.car_colors.is_empty());
+
+        sim_pars.car_pars_all.get_mut(&44).unwrap().color = String::from("#GGGGGG");
+        assert!(sim_pars
+            .validate()
+            .errors
+            .iter()
+            .any(|msg| msg.contains("invalid color")));
+    }
+}
+
+#[cfg(test)]
+mod scenario_discovery_tests {
+    use crate::pre::read_sim_pars::{
+        discover_scenarios, is_probably_scenario, read_sim_pars_flexible, SimPars,
+    };
+    use std::path::Path;
+
+    #[test]
+    fn test_is_probably_scenario() {
+        assert!(is_probably_scenario(r#"{"race_pars": {"tot_no_laps": 10}}"#));
+        assert!(!is_probably_scenario(include_str!("../../input/parameters/tires.json")));
+        assert!(!is_probably_scenario(include_str!("../../input/parameters/sim_constants.json")));
+        // the key must be at the top level and the file must be valid JSON
+        assert!(!is_probably_scenario(r#"{"variants": [{"race_pars": {}}]}"#));
+        assert!(!is_probably_scenario(r#"{"race_pars": "#));
+
+        // TOML files with a top-level race_pars table
+        assert!(is_probably_scenario("[race_pars]\ntot_no_laps = 10\n"));
+        assert!(!is_probably_scenario("[variants.race_pars]\ntot_no_laps = 10\n"));
+        assert!(!is_probably_scenario(include_str!("../../input/parameters/randomizer_spec.toml")));
+    }
+
+    #[test]
+    fn test_discover_and_read_toml_scenario() {
+        let dir =
+            std::env::temp_dir().join(format!("racesim_toml_scenario_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // the test race as TOML scenario next to an unrelated TOML file
+        let json_value: serde_json::Value =
+            serde_json::from_str(include_str!("../../input/parameters/test_race.json")).unwrap();
+        let scenario_path = dir.join("test_race.toml");
+        std::fs::write(&scenario_path, toml::Value::try_from(&json_value).unwrap().to_string())
+            .unwrap();
+        std::fs::write(dir.join("other.toml"), "t_car = 0.15\n").unwrap();
+        std::fs::write(dir.join("notes.txt"), "race_pars").unwrap();
+
+        let scenario_paths = discover_scenarios(&[dir.as_path()]);
+        assert_eq!(scenario_paths, vec![scenario_path.to_owned()]);
+
+        // the TOML scenario is read like its JSON original
+        let sim_pars = read_sim_pars_flexible(&scenario_path).unwrap();
+        let sim_pars_json: SimPars = serde_json::from_value(json_value).unwrap();
+        assert_eq!(sim_pars.race_pars.tot_no_laps, sim_pars_json.race_pars.tot_no_laps);
+        assert_eq!(sim_pars.race_pars.participants, sim_pars_json.race_pars.participants);
+        assert_eq!(
+            sim_pars.car_pars_all[&44].strategy.len(),
+            sim_pars_json.car_pars_all[&44].strategy.len()
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_discover_scenarios() {
+        let par_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../input/parameters");
+        let scenario_paths = discover_scenarios(&[par_dir.as_path(), Path::new("does_not_exist")]);
+        let file_names: Vec<&str> = scenario_paths
+            .iter()
+            .map(|path| path.file_name().unwrap().to_str().unwrap())
+            .collect();
+
+        assert!(file_names.contains(&"test_race.json"));
+        assert!(!file_names.contains(&"tires.json"));
+        assert!(!file_names.contains(&"sim_constants.json"));
+        assert!(!file_names.contains(&"randomizer_spec.toml"));
+    }
+}
+
+#[cfg(test)]
+mod duel_timeloss_tests {
+    use crate::core::handle_race::run_race_on_track;
+    use crate::core::race::{calc_duel_timelosses, DuelPars, DuelZone};
+    use crate::test_support;
+
+    fn get_duel_pars() -> DuelPars {
+        DuelPars {
+            min_t_dist: 0.5,
+            corner_factor: 1.5,
+            straight_factor: 0.5,
+            defender_share: 0.65,
+            aggression_weight: 0.5,
+        }
+    }
+
+    /// calc_avg_timelosses returns the average time loss per lap (defender, attacker) of two cars
+    /// over a season of laps with the given gaps.
+    fn calc_avg_timelosses(gaps: &[f64], zone: DuelZone) -> (f64, f64) {
+        let duel_pars = get_duel_pars();
+        let (sum_front, sum_rear) = gaps.iter().fold((0.0, 0.0), |(sum_front, sum_rear), &gap| {
+            let (t_front, t_rear) = calc_duel_timelosses(gap, zone, 0.5, 0.5, 0.2, &duel_pars);
+            (sum_front + t_front, sum_rear + t_rear)
+        });
+        (sum_front / gaps.len() as f64, sum_rear / gaps.len() as f64)
+    }
+
+    #[test]
+    fn test_duel_timeloss_depth_and_split() {
+        let duel_pars = get_duel_pars();
+        let calc = |gap, zone, aggression_front, aggression_rear| {
+            calc_duel_timelosses(gap, zone, aggression_front, aggression_rear, 0.2, &duel_pars)
+        };
+
+        // no loss outside min_t_dist, full loss at zero gap
+        assert_eq!(calc(0.6, DuelZone::Other, 0.5, 0.5), (0.0, 0.0));
+        let (t_front, t_rear) = calc(0.0, DuelZone::Other, 0.5, 0.5);
+        assert!((t_front + t_rear - 0.4).abs() < 1e-9);
+        assert!(t_front > t_rear);
+
+        // corners cost more than straights
+        let t_corner = calc(0.25, DuelZone::Corner, 0.5, 0.5);
+        let t_straight = calc(0.25, DuelZone::Straight, 0.5, 0.5);
+        assert!(t_corner.0 > t_straight.0 && t_corner.1 > t_straight.1);
+
+        // an aggressive attacker compromises its own line and loses more than the defender
+        let (t_front, t_rear) = calc(0.25, DuelZone::Other, 0.0, 1.0);
+        assert!(t_rear > t_front);
+    }
+
+    #[test]
+    fn test_duel_timeloss_season_average() {
+        // gaps of a season of laps: close fight, loose following and far apart
+        let gaps_close: Vec<f64> = (0..1000).map(|i| 0.3 * i as f64 / 1000.0).collect();
+        let gaps_loose: Vec<f64> = (0..1000).map(|i| 0.3 + 0.6 * i as f64 / 1000.0).collect();
+        let gaps_far: Vec<f64> = (0..1000).map(|i| 1.0 + 2.0 * i as f64 / 1000.0).collect();
+
+        let avg_close = calc_avg_timelosses(&gaps_close, DuelZone::Other);
+        let avg_loose = calc_avg_timelosses(&gaps_loose, DuelZone::Other);
+        let avg_far = calc_avg_timelosses(&gaps_far, DuelZone::Other);
+
+        assert!(avg_close.0 > avg_loose.0 && avg_loose.0 > avg_far.0);
+        assert!(avg_close.1 > avg_loose.1 && avg_loose.1 > avg_far.1);
+        assert_eq!(avg_far, (0.0, 0.0));
+
+        // the same gaps in corners cost more than on straights
+        let avg_corner = calc_avg_timelosses(&gaps_close, DuelZone::Corner);
+        let avg_straight = calc_avg_timelosses(&gaps_close, DuelZone::Straight);
+        assert!(avg_corner.0 > avg_close.0 && avg_close.0 > avg_straight.0);
+    }
+
+    /// run_two_car_season runs a season of two-car races on the same strategy, in which HAM
+    /// starts behind VER and is `t_pace_adv` (s) per lap faster. It returns the share of laps the
+    /// cars finished within twice `min_t_dist` of each other and the average time (s) lost in duels
+    /// per race and car. A car held up behind another car follows it at `min_t_dist` (projected
+    /// with the current lap times), which is a larger gap at the line on the real track.
+    fn run_two_car_season(t_pace_adv: f64) -> (f64, f64) {
+        let (mut sim_pars, sim_consts, tire_config) = test_support::load_test_pars();
+        sim_pars.race_pars.use_drs = false;
+        sim_pars.race_pars.tot_no_laps = 20;
+        let mut strategy = sim_pars.car_pars_all[&33].strategy.to_owned();
+        strategy[0].driver_initials = "HAM".to_owned();
+        sim_pars.car_pars_all.get_mut(&44).unwrap().strategy = strategy;
+        for (initials, driver_pars) in sim_pars.driver_pars_all.iter_mut() {
+            // no contacts in the duels
+            driver_pars.aggression = 0.0;
+            driver_pars.t_driver = if initials == "HAM" { -t_pace_adv } else { 0.0 };
+        }
+
+        let track = test_support::create_track(&sim_pars.track_pars);
+        let no_rounds = 5;
+        let mut no_laps_close = 0;
+        let mut t_duels_sum = 0.0;
+
+        for round in 0..no_rounds {
+            sim_pars.race_pars.seed = Some(round);
+            let result =
+                run_race_on_track(&sim_pars, &sim_consts, &tire_config, &track, 0.1).unwrap();
+            no_laps_close += (1..=sim_pars.race_pars.tot_no_laps as usize)
+                .filter(|&lap| {
+                    (result.racetimes[0][lap] - result.racetimes[1][lap]).abs()
+                        < 2.0 * sim_consts.min_t_dist
+                })
+                .count();
+            t_duels_sum += result.time_losses.iter().map(|x| x.t_duels).sum::<f64>();
+        }
+
+        (
+            no_laps_close as f64 / (no_rounds * sim_pars.race_pars.tot_no_laps as u64) as f64,
+            t_duels_sum / (no_rounds * 2) as f64,
+        )
+    }
+
+    #[test]
+    fn test_duel_timeloss_two_car_season() {
+        // slower car behind: the cars separate, (almost) no duels
+        let (share_close_slower, t_duels_slower) = run_two_car_season(-1.0);
+        // faster car behind: it catches up and fights for the position
+        let (share_close_faster, t_duels_faster) = run_two_car_season(0.6);
+        let (share_close_much_faster, t_duels_much_faster) = run_two_car_season(2.0);
+
+        assert!(share_close_slower < share_close_faster);
+        assert!(share_close_faster <= share_close_much_faster);
+
+        // the time lost in duels follows the share of close laps (the slower car only loses time
+        // in the fight after the start)
+        assert!(t_duels_slower < 0.05);
+        assert!(t_duels_faster > 2.0 * t_duels_slower);
+        assert!(t_duels_much_faster > t_duels_faster);
+    }
+}
+
+#[cfg(test)]
+mod distance_stats_tests {
+    use crate::core::handle_race::run_race;
+    use crate::post::output_fmt::OutputFormat;
+    use crate::test_support;
+
+    #[test]
+    fn test_distance_of_retired_car() {
+        let (mut sim_pars, sim_consts, tire_config) = test_support::load_test_pars();
+        // no contacts in the duels, such that car 44 does not fall back before it retires
+        for driver_pars in sim_pars.driver_pars_all.values_mut() {
+            driver_pars.aggression = 0.0;
+        }
+
+        // car 44 retires at half distance
+        let tot_no_laps = sim_pars.race_pars.tot_no_laps;
+        sim_pars.race_pars.scripted_events = serde_json::from_str(&format!(
+            r#"[{{"lap": {}, "type": "Dnf", "car_no": 44}}]"#,
+            tot_no_laps / 2 + 1
+        ))
+        .unwrap();
+
+        let result = run_race(&sim_pars, &sim_consts, &tire_config, 0.1).unwrap();
+        let idx_dnf = result.car_driver_pairs.iter().position(|pair| pair.car_no == 44).unwrap();
+        let idx_winner = result.car_driver_pairs.iter().position(|pair| pair.car_no == 33).unwrap();
+
+        let retirement = result.get_retirement(idx_dnf).unwrap();
+        assert!(retirement.time_s > 0.0);
+        assert!(result.get_retirement(idx_winner).is_none());
+
+        let distance_winner = result.get_distance(idx_winner).unwrap();
+        assert!((distance_winner - tot_no_laps as f64 * sim_pars.track_pars.length).abs() < 1e-6);
+        let distance_ratio = result.get_distance(idx_dnf).unwrap() / distance_winner;
+        assert!(distance_ratio > 0.45 && distance_ratio < 0.55, "ratio {}", distance_ratio);
+
+        // average speeds are plausible, the race average speed is the one of the winner
+        let avg_speed_winner = result.get_avg_speed(idx_winner).unwrap();
+        assert!(avg_speed_winner > 100.0 && avg_speed_winner < 300.0);
+        assert!(result.get_avg_speed(idx_dnf).unwrap() > 100.0);
+        assert_eq!(result.get_race_avg_speed(), Some(avg_speed_winner));
+
+        let distance_stats = result.get_distance_stats();
+        assert!(distance_stats.cars[idx_dnf].retired);
+        assert_eq!(distance_stats.race_avg_speed_kmh, Some(avg_speed_winner));
+
+        let classification = result.format_classification(&OutputFormat::default()).unwrap();
+        assert!(classification.contains("km/h (DNF)"));
+        assert!(classification.contains("average race speed"));
+    }
+}
+
+#[cfg(test)]
+mod start_procedure_tests {
+    use crate::core::handle_race::run_race;
+    use crate::core::race::{Race, SimConstants, StartPars};
+    use crate::core::tireset::TireConfig;
+    use crate::pre::read_sim_pars::SimPars;
+    use crate::test_support;
+
+    fn get_test_pars() -> (SimPars, SimConstants, TireConfig) {
+        let (sim_pars, mut sim_consts, tire_config) = test_support::load_test_pars();
+        sim_consts.driver_crash_probability = 0.0;
+        sim_consts.anti_stall_probability = 0.0;
+        sim_consts.launch_sigma_s = 0.0;
+        (sim_pars, sim_consts, tire_config)
+    }
+
+    fn create_race(
+        sim_pars: &SimPars,
+        sim_consts: &SimConstants,
+        tire_config: &TireConfig,
+    ) -> Race {
+        test_support::create_race_with_timestep(sim_pars, sim_consts, tire_config, 0.05)
+    }
+
+    /// get_order_into_turn_1 returns the car numbers in running order at the moment the first car
+    /// reaches turn 1 together with the race.
+    fn get_order_into_turn_1(
+        sim_pars: &SimPars,
+        sim_consts: &SimConstants,
+        tire_config: &TireConfig,
+    ) -> (Vec<u32>, Race) {
+        let mut race = create_race(sim_pars, sim_consts, tire_config);
+        let turn_1 = race.track.turn_1;
+
+        while !race
+            .cars_list
+            .iter()
+            .any(|car| car.sh.get_compl_lap() == 0 && car.sh.get_s_tracks().1 >= turn_1)
+        {
+            race.simulate_timestep();
+        }
+
+        let mut order: Vec<(u32, f64)> = race
+            .cars_list
+            .iter()
+            .map(|car| (car.car_no, car.sh.get_race_prog()))
+            .collect();
+        order.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        (order.into_iter().map(|x| x.0).collect(), race)
+    }
+
+    #[test]
+    fn test_slow_reaction_changes_order_into_turn_1() {
+        let (mut sim_pars, mut sim_consts, tire_config) = get_test_pars();
+        sim_consts.start_reaction_mean_s = 1.0;
+        sim_consts.start_reaction_sigma_s = 0.0;
+        sim_consts.jump_start_threshold_s = 0.0;
+
+        // equal reactions: the order into turn 1 follows the grid
+        let (order_grid, _) = get_order_into_turn_1(&sim_pars, &sim_consts, &tire_config);
+
+        // the front car reacts slowly (reaction doubled by the lowest consistency)
+        let initials = sim_pars.car_pars_all[&order_grid[0]].strategy[0]
+            .driver_initials
+            .to_owned();
+        sim_pars.driver_pars_all.get_mut(&initials).unwrap().consistency = 0.0;
+
+        let (order_slow, race) = get_order_into_turn_1(&sim_pars, &sim_consts, &tire_config);
+        assert_eq!(order_slow[0], order_grid[1]);
+
+        // the delay is part of the lap 1 telemetry, but not of the pace
+        let idx = race.get_car_idx(order_grid[0]).unwrap();
+        let breakdown = race.get_laptime_breakdowns(idx)[1];
+        assert!((breakdown.t_start - 2.0).abs() < 1e-9);
+        assert!(breakdown.get_total() < 200.0);
+
+        let result = race.get_race_result();
+        assert!(result
+            .events
+            .iter()
+            .any(|ev| ev.kind == "StartReaction"
+                && ev.cars == vec![order_grid[0]]
+                && ev.time_s == 0.0
+                && matches!(ev.start, Some(start) if (start.t_reaction - 2.0).abs() < 1e-9)));
+    }
+
+    #[test]
+    fn test_only_notable_starts_are_recorded() {
+        let (sim_pars, sim_consts, tire_config) = get_test_pars();
+
+        // ordinary reactions of about 0.2 s
+        let result = create_race(&sim_pars, &sim_consts, &tire_config).get_race_result();
+        assert!(!result.events.iter().any(|ev| ev.kind == "StartReaction"));
+
+        // every anti-stall is recorded with its loss
+        let mut sim_consts = sim_consts;
+        sim_consts.anti_stall_probability = 1.0;
+        let result = create_race(&sim_pars, &sim_consts, &tire_config).get_race_result();
+        let starts: Vec<_> = result
+            .events
+            .iter()
+            .filter(|ev| ev.kind == "StartReaction")
+            .collect();
+        assert_eq!(starts.len(), sim_pars.race_pars.participants.len());
+        assert!(starts
+            .iter()
+            .all(|ev| matches!(ev.start, Some(start) if start.t_anti_stall >= 2.0)));
+    }
+
+    #[test]
+    fn test_jump_starts_are_penalized() {
+        let (sim_pars, mut sim_consts, tire_config) = get_test_pars();
+        sim_consts.start_reaction_mean_s = 0.1;
+        sim_consts.start_reaction_sigma_s = 0.05;
+        sim_consts.jump_start_threshold_s = 0.1;
+
+        // every jump start gets exactly one penalty, and there are no penalties without one
+        let mut no_jump_starts = 0;
+        for _ in 0..20 {
+            let result = create_race(&sim_pars, &sim_consts, &tire_config).get_race_result();
+
+            for car_no in sim_pars.race_pars.participants.iter() {
+                let count = |kind: &str| {
+                    result
+                        .events
+                        .iter()
+                        .filter(|ev| ev.kind == kind && ev.cars == vec![*car_no])
+                        .count()
+                };
+                let no_car_jump_starts = result
+                    .events
+                    .iter()
+                    .filter(|ev| ev.cars == vec![*car_no])
+                    .filter(|ev| matches!(ev.start, Some(start) if start.jump_start))
+                    .count();
+                assert!(no_car_jump_starts <= 1);
+                assert_eq!(no_car_jump_starts, count("Penalty"));
+                no_jump_starts += no_car_jump_starts;
+            }
+        }
+        assert!(no_jump_starts > 0);
+
+        // a jump start always costs the penalty in the race time
+        sim_consts.start_reaction_mean_s = 0.05;
+        sim_consts.start_reaction_sigma_s = 0.0;
+        sim_consts.jump_start_penalty_s = 5.0;
+        let result = run_race(&sim_pars, &sim_consts, &tire_config, 0.1).unwrap();
+        for (idx, pair) in result.car_driver_pairs.iter().enumerate() {
+            assert!(result
+                .events
+                .iter()
+                .any(|ev| ev.kind == "Penalty" && !ev.scripted && ev.cars == vec![pair.car_no]));
+            assert!(result.get_laptime(idx, 1) > 5.0 + sim_pars.track_pars.t_q);
+        }
+    }
+
+    #[test]
+    fn test_launch_delta_depends_on_start_skill() {
+        let (mut sim_pars, sim_consts, tire_config) = get_test_pars();
+        for (initials, start_skill) in [("HAM", 1.0), ("VER", 0.0)].iter() {
+            sim_pars.driver_pars_all.get_mut(*initials).unwrap().start_skill = *start_skill;
+        }
+
+        let race = create_race(&sim_pars, &sim_consts, &tire_config);
+        let result = race.get_race_result();
+
+        for (car_no, launch_delta) in [(44, -0.3), (33, 0.7)].iter() {
+            // only the bad start (a loss of at least 0.5 s) is recorded
+            let starts: Vec<_> = result
+                .events
+                .iter()
+                .filter(|ev| ev.kind == "StartReaction" && ev.cars == vec![*car_no])
+                .filter_map(|ev| ev.start)
+                .collect();
+            if *car_no == 33 {
+                assert_eq!(starts.len(), 1);
+                assert!((starts[0].launch_delta - launch_delta).abs() < 1e-9);
+            } else {
+                assert!(starts.is_empty());
+            }
+
+            // the launch is part of the lap 1 pace on top of the loss of the standing start
+            let idx = race.get_car_idx(*car_no).unwrap();
+            let breakdown = race.get_laptime_breakdowns(idx)[1];
+            let t_launch_exp = sim_pars.track_pars.t_loss_firstlap + launch_delta;
+            assert!((breakdown.t_launch - t_launch_exp).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_launch_delta_distribution() {
+        let (_, sim_consts, _) = get_test_pars();
+        let start_pars = StartPars {
+            reaction_mean_s: sim_consts.start_reaction_mean_s,
+            reaction_sigma_s: sim_consts.start_reaction_sigma_s,
+            jump_start_threshold_s: sim_consts.jump_start_threshold_s,
+            anti_stall_probability: 0.0,
+            anti_stall_delay_s: sim_consts.anti_stall_delay_s,
+            launch_delta_s: [-0.3, 0.7],
+            launch_sigma_s: 0.15,
+        };
+        let mut rng = rand::thread_rng();
+
+        for &(start_skill, mean_exp) in [(1.0, -0.3), (0.5, 0.2), (0.0, 0.7)].iter() {
+            let launch_deltas: Vec<f64> = (0..5000)
+                .map(|_| start_pars.draw_launch_delta(start_skill, &mut rng))
+                .collect();
+            let mean = launch_deltas.iter().sum::<f64>() / launch_deltas.len() as f64;
+
+            // clamping at one second shifts the mean of the bad starters slightly
+            assert!((mean - mean_exp).abs() < 0.05, "mean {} vs. {}", mean, mean_exp);
+            assert!(launch_deltas.iter().all(|x| x.abs() <= 1.0));
+        }
+    }
+}
+
+#[cfg(test)]
+mod season_tests {
+    use crate::core::handle_race::run_race;
+    use crate::core::race::SimConstants;
+    use crate::core::scripted_event::ScriptedEvent;
+    use crate::core::season::{
+        run_season, BanInfo, CarDevelopment, CarUpgrade, ClinchScenario, SeasonPars,
+        SeasonState, TitlePredictorPars,
+    };
+    use crate::core::tireset::TireConfig;
+    use crate::post::output_fmt::OutputFormat;
+    use crate::post::race_result::{ClassificationStatus, RaceResult};
+    use crate::pre::read_sim_pars::SimPars;
+    use crate::test_support;
+    use std::collections::BTreeMap;
+
+    fn get_test_pars() -> (SimPars, SimConstants, TireConfig) {
+        let (sim_pars, mut sim_consts, tire_config) = test_support::load_test_pars();
+        sim_consts.jump_start_threshold_s = 0.0;
+        (sim_pars, sim_consts, tire_config)
+    }
+
+    #[test]
+    fn test_ban_at_penalty_points_threshold() {
+        let (mut sim_pars, sim_consts, tire_config) = get_test_pars();
+        sim_pars.race_pars.tot_no_laps = 10;
+        sim_pars.race_pars.scripted_events = vec![serde_json::from_str::<ScriptedEvent>(
+            r#"{"lap": 3, "type": "Penalty", "car_no": 44, "t_penalty": 5.0}"#,
+        )
+        .unwrap()];
+
+        // only the scripted penalties give penalty points: 4 per round, the threshold of 12 is
+        // reached in round 3
+        let mut season_pars: SeasonPars =
+            serde_json::from_str(include_str!("../../input/parameters/test_season.json")).unwrap();
+        season_pars.penalty_points.event_points =
+            [(String::from("Penalty"), 4)].iter().cloned().collect();
+        let rounds = vec![sim_pars; 4];
+
+        let season_result = run_season(
+            &season_pars,
+            &rounds,
+            &sim_consts,
+            &tire_config,
+            0.1,
+        )
+        .unwrap();
+
+        assert_eq!(
+            season_result.state.bans,
+            vec![BanInfo {
+                driver_initials: String::from("HAM"),
+                round: 4,
+                car_no: Some(44),
+            }]
+        );
+
+        // car 44 is driven by the reserve driver only in round 4
+        let get_driver_44 = |round: usize| {
+            season_result.rounds[round]
+                .car_driver_pairs
+                .iter()
+                .find(|pair| pair.car_no == 44)
+                .unwrap()
+                .driver_initials
+                .to_owned()
+        };
+        for round in 0..3 {
+            assert_eq!(get_driver_44(round), "HAM");
+        }
+        assert_eq!(get_driver_44(3), "RES");
+
+        // the penalty of round 4 belongs to the reserve driver, the ban reduced the points of HAM
+        assert_eq!(season_result.state.penalty_points["HAM"], 0);
+        assert_eq!(season_result.state.penalty_points["RES"], 4);
+        assert!(season_result.state.pending_bans.is_empty());
+
+        let summary =
+            season_result.format_summary(&season_pars, &OutputFormat::default()).unwrap();
+        assert!(summary.contains("Round 4: HAM (car 44"));
+        assert!(summary.contains("RES"));
+    }
+
+    fn get_scripted_events(events: &str) -> Vec<ScriptedEvent> {
+        serde_json::from_str(events).unwrap()
+    }
+
+    #[test]
+    fn test_track_limit_strikes_give_penalty_points() {
+        let (mut sim_pars, sim_consts, tire_config) = get_test_pars();
+        sim_pars.race_pars.tot_no_laps = 10;
+        sim_pars.race_pars.model_toggles = "mistakes".parse().unwrap();
+        sim_pars.race_pars.scripted_events = get_scripted_events(
+            r#"[{"lap": 2, "type": "TrackLimits", "car_no": 44},
+                {"lap": 3, "type": "TrackLimits", "car_no": 44},
+                {"lap": 4, "type": "TrackLimits", "car_no": 44},
+                {"lap": 5, "type": "TrackLimits", "car_no": 44},
+                {"lap": 6, "type": "TrackLimits", "car_no": 44},
+                {"lap": 7, "type": "TrackLimits", "car_no": 33},
+                {"lap": 8, "type": "TrackLimits", "car_no": 33}]"#,
+        );
+
+        // only the track limits give penalty points: 5 strikes of HAM are one full set of 3, the 2
+        // strikes of VER are not
+        let mut season_pars = get_season_pars(0);
+        season_pars.penalty_points.event_points = BTreeMap::new();
+        season_pars.penalty_points.track_limit_points = 2;
+
+        let season_result = run_season(
+            &season_pars,
+            &[sim_pars],
+            &sim_consts,
+            &tire_config,
+            0.1,
+        )
+        .unwrap();
+
+        assert_eq!(season_result.state.penalty_points.get("HAM").copied().unwrap_or(0), 2);
+        assert_eq!(season_result.state.penalty_points.get("VER").copied().unwrap_or(0), 0);
+    }
+
+    #[test]
+    fn test_ban_in_final_round_stays_pending() {
+        let (mut sim_pars, sim_consts, tire_config) = get_test_pars();
+        sim_pars.race_pars.tot_no_laps = 10;
+        sim_pars.race_pars.scripted_events = get_scripted_events(
+            r#"[{"lap": 3, "type": "Penalty", "car_no": 44, "t_penalty": 5.0}]"#,
+        );
+
+        // the threshold of 12 is reached in the final round 3
+        let mut season_pars = get_season_pars(0);
+        season_pars.penalty_points.event_points =
+            [(String::from("Penalty"), 4)].iter().cloned().collect();
+
+        let season_result = run_season(
+            &season_pars,
+            &vec![sim_pars; 3],
+            &sim_consts,
+            &tire_config,
+            0.1,
+        )
+        .unwrap();
+
+        assert!(season_result.state.bans.is_empty());
+        assert!(season_result.state.pending_bans.contains("HAM"));
+        let summary =
+            season_result.format_summary(&season_pars, &OutputFormat::default()).unwrap();
+        assert!(summary.contains("Bans pending after the final round (not served): HAM"));
+    }
+
+    #[test]
+    fn test_reserve_driver_replaces_one_driver_only() {
+        let (mut sim_pars, _, _) = get_test_pars();
+        let season_pars = get_season_pars(0);
+
+        // two bans in the same round cannot be served by the only reserve driver
+        let mut state = SeasonState {
+            pending_bans: ["HAM", "VER"].iter().map(|x| x.to_string()).collect(),
+            ..SeasonState::default()
+        };
+        assert!(state.apply_bans(&season_pars, &mut sim_pars.to_owned()).is_err());
+
+        // a single ban replaces the banned driver in one car
+        let mut state = SeasonState::default();
+        state.pending_bans.insert(String::from("HAM"));
+        state.apply_bans(&season_pars, &mut sim_pars).unwrap();
+        let cars_reserve: Vec<u32> = sim_pars
+            .car_pars_all
+            .iter()
+            .filter(|(_, car_pars)| car_pars.strategy.iter().any(|x| x.driver_initials == "RES"))
+            .map(|(car_no, _)| *car_no)
+            .collect();
+        assert_eq!(cars_reserve, vec![44]);
+
+        // the reserve driver cannot replace a second driver once he takes part in the round
+        state.pending_bans.insert(String::from("VER"));
+        assert!(state.apply_bans(&season_pars, &mut sim_pars).is_err());
+    }
+
+    #[test]
+    fn test_no_points_for_disqualified_or_not_classified_cars() {
+        let (mut sim_pars, sim_consts, tire_config) = get_test_pars();
+        sim_pars.race_pars.tot_no_laps = 10;
+        let season_pars = get_season_pars(0);
+        let result = run_race(&sim_pars, &sim_consts, &tire_config, 0.1).unwrap();
+
+        let positions = result.get_final_positions();
+        let idx_first = positions.iter().position(|&pos| pos == 1).unwrap();
+        let idx_second = positions.iter().position(|&pos| pos == 2).unwrap();
+        let first = result.car_driver_pairs[idx_first].driver_initials.as_str();
+        let second = result.car_driver_pairs[idx_second].driver_initials.as_str();
+        let calc_points = |result: &RaceResult| {
+            let mut state = SeasonState::default();
+            state.add_round_result(&season_pars, result);
+            state.championship_points
+        };
+
+        assert_eq!(calc_points(&result), get_points(&[(first, 25), (second, 18)]));
+
+        // the second car takes the flag 2 laps down, i.e. below 90 % of the winner's laps
+        let mut result_nc = result.clone();
+        result_nc.racetimes[idx_second][9] = 0.0;
+        result_nc.racetimes[idx_second][10] = 0.0;
+        result_nc.calc_statuses(&[]);
+        assert_eq!(result_nc.get_status(idx_second), ClassificationStatus::NotClassified);
+        assert_eq!(result_nc.format_status_suffix(idx_second), " (NC)");
+        assert_eq!(calc_points(&result_nc), get_points(&[(first, 25), (second, 0)]));
+
+        // the winner is disqualified and classified last, the second car inherits the win
+        let mut result_dsq = result.clone();
+        result_dsq.calc_statuses(&[idx_first]);
+        assert_eq!(result_dsq.get_final_positions()[idx_second], 1);
+        assert_eq!(calc_points(&result_dsq), get_points(&[(first, 0), (second, 25)]));
+    }
+
+    fn get_season_pars(fastest_lap_points: u32) -> SeasonPars {
+        let mut season_pars: SeasonPars =
+            serde_json::from_str(include_str!("../../input/parameters/test_season.json")).unwrap();
+        season_pars.fastest_lap_points = fastest_lap_points;
+        season_pars
+    }
+
+    fn get_points(standings: &[(&str, u32)]) -> BTreeMap<String, u32> {
+        standings.iter().map(|&(initials, points)| (initials.to_string(), points)).collect()
+    }
+
+    fn get_conditions(
+        scenarios: &[ClinchScenario],
+        driver: &str,
+        position: u32,
+        fastest_lap: bool,
+    ) -> Option<Vec<(String, u32)>> {
+        scenarios
+            .iter()
+            .find(|x| x.driver == driver && x.position == position && x.fastest_lap == fastest_lap)
+            .map(|x| x.conditions.iter().map(|c| (c.rival.to_owned(), c.min_pos)).collect())
+    }
+
+    #[test]
+    fn test_clinch_scenarios_last_round() {
+        // 25-18-...-1 plus 1 point for the fastest lap (max. 26 points per round), position 11
+        // stands for finishing outside the points
+        let season_pars = get_season_pars(1);
+        let points = get_points(&[("HAM", 100), ("VER", 80), ("LEC", 60)]);
+
+        // LEC can reach 86 points only
+        assert_eq!(season_pars.get_contenders(&points, 1), vec!["HAM", "VER"]);
+
+        let scenarios = season_pars.get_clinch_scenarios(&points, 1);
+
+        // HAM: P2 (118) beats VER's maximum of 106, without points VER must not exceed P2 plus
+        // the fastest lap (99)
+        assert_eq!(get_conditions(&scenarios, "HAM", 2, false), Some(vec![]));
+        assert_eq!(
+            get_conditions(&scenarios, "HAM", 11, false),
+            Some(vec![(String::from("VER"), 2)])
+        );
+
+        // VER: win plus fastest lap (106) requires HAM below 6 points (P8 or worse), the win
+        // alone (105) requires HAM below 5 points including the fastest lap (P9 or worse)
+        assert_eq!(
+            get_conditions(&scenarios, "VER", 1, true),
+            Some(vec![(String::from("HAM"), 8)])
+        );
+        assert_eq!(
+            get_conditions(&scenarios, "VER", 1, false),
+            Some(vec![(String::from("HAM"), 9)])
+        );
+        assert_eq!(get_conditions(&scenarios, "VER", 2, true), None);
+        assert!(scenarios.iter().all(|x| x.driver != "LEC"));
+    }
+
+    #[test]
+    fn test_clinch_scenarios_ties_and_later_rounds() {
+        // without fastest lap points, equal points are not a clinch (no countback)
+        let season_pars = get_season_pars(0);
+        let points = get_points(&[("HAM", 100), ("VER", 75)]);
+        let scenarios = season_pars.get_clinch_scenarios(&points, 1);
+
+        assert_eq!(season_pars.get_contenders(&points, 1), vec!["HAM", "VER"]);
+        assert_eq!(
+            get_conditions(&scenarios, "HAM", 11, false),
+            Some(vec![(String::from("VER"), 2)])
+        );
+        assert!(scenarios.iter().all(|x| x.driver != "VER"));
+        assert!(scenarios.iter().all(|x| !x.fastest_lap));
+
+        // two rounds left: VER can still score 26 points in the last round, i.e. without points
+        // HAM needs VER below 14 points (P4 plus fastest lap or worse)
+        let season_pars = get_season_pars(1);
+        let points = get_points(&[("HAM", 100), ("VER", 60)]);
+        let scenarios = season_pars.get_clinch_scenarios(&points, 2);
+
+        assert_eq!(
+            get_conditions(&scenarios, "HAM", 11, false),
+            Some(vec![(String::from("VER"), 4)])
+        );
+        assert_eq!(get_conditions(&scenarios, "HAM", 1, false), Some(vec![]));
+
+        // a decided title has a single contender
+        assert_eq!(season_pars.get_contenders(&points, 1), vec!["HAM"]);
+    }
+
+    #[test]
+    fn test_title_outlooks_before_final_rounds() {
+        let (mut sim_pars, sim_consts, tire_config) = get_test_pars();
+        sim_pars.race_pars.tot_no_laps = 5;
+
+        let mut season_pars = get_season_pars(1);
+        season_pars.title_predictor = Some(TitlePredictorPars {
+            final_rounds: 2,
+            no_runs: 2,
+        });
+        let rounds = vec![sim_pars; 3];
+
+        let season_result = run_season(
+            &season_pars,
+            &rounds,
+            &sim_consts,
+            &tire_config,
+            0.1,
+        )
+        .unwrap();
+
+        let title_outlooks = &season_result.title_outlooks;
+        assert_eq!(title_outlooks.iter().map(|x| x.round).collect::<Vec<u32>>(), vec![2, 3]);
+        for title_outlook in title_outlooks.iter() {
+            let sum: f64 = title_outlook.probabilities.values().sum();
+            assert!((sum - 1.0).abs() < 1e-9);
+        }
+
+        let summary =
+            season_result.format_summary(&season_pars, &OutputFormat::default()).unwrap();
+        assert!(summary.contains("Title outlook before round 3"));
+    }
+
+    #[test]
+    fn test_car_development_schedule() {
+        let development: CarDevelopment = serde_json::from_str(
+            r#"{"development_rate": 0.02, "upgrades": [{"round": 3, "t_gain": 0.3}]}"#,
+        )
+        .unwrap();
+
+        assert!((development.get_t_car(0.5, 1) - 0.5).abs() < 1e-9);
+        assert!((development.get_t_car(0.5, 2) - 0.48).abs() < 1e-9);
+        assert!((development.get_t_car(0.5, 3) - 0.16).abs() < 1e-9);
+        assert!((development.get_t_car(0.5, 5) - 0.12).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_mid_season_upgrade_improves_positions() {
+        let (mut sim_pars, sim_consts, tire_config) = get_test_pars();
+        // car 44 stops in lap 13, which gives the faster car behind the lead
+        sim_pars.race_pars.tot_no_laps = 20;
+
+        // car 33 starts the season clearly slower and gets a large upgrade in round 3, it starts
+        // behind car 44 (the grid positions of the test track are counted towards the finish line)
+        // such that it does not simply hold the lead before the upgrade
+        for (car_no, p_grid) in [(44, 2), (33, 1)] {
+            sim_pars.car_pars_all.get_mut(&car_no).unwrap().p_grid = p_grid;
+        }
+        sim_pars.car_pars_all.get_mut(&33).unwrap().t_car = 3.0;
+        let mut season_pars = get_season_pars(0);
+        season_pars.car_development.insert(
+            33,
+            CarDevelopment {
+                development_rate: 0.0,
+                upgrades: vec![CarUpgrade {
+                    round: 3,
+                    t_gain: 6.0,
+                }],
+            },
+        );
+        let rounds = vec![sim_pars; 4];
+
+        let no_runs = 3;
+        let mut pos_sum_before = 0;
+        let mut pos_sum_after = 0;
+
+        for _ in 0..no_runs {
+            let season_result = run_season(
+                &season_pars,
+                &rounds,
+                &sim_consts,
+                &tire_config,
+                0.1,
+            )
+            .unwrap();
+
+            for (round, result) in season_result.rounds.iter().enumerate() {
+                let idx = result.car_driver_pairs.iter().position(|x| x.car_no == 33).unwrap();
+                let t_car_exp = if round < 2 { 3.0 } else { -3.0 };
+                assert!((result.t_cars[idx] - t_car_exp).abs() < 1e-9);
+
+                let pos = result.get_final_positions()[idx];
+                if round < 2 {
+                    pos_sum_before += pos;
+                } else {
+                    pos_sum_after += pos;
+                }
+            }
+        }
+
+        // the upgraded car finishes measurably better on average
+        assert!(pos_sum_after < pos_sum_before);
+    }
+}
+
+#[cfg(test)]
+mod race_state_builder_tests {
+    use crate::core::car::RetirementCause;
+    use crate::core::livery::resolve_car_color;
+    use crate::core::race::Race;
+    use crate::interfaces::gui_interface::{GuiSender, RaceStateBuilder};
+    use crate::test_support;
+
+    fn get_test_race() -> Race {
+        let (sim_pars, sim_consts, tire_config) = test_support::load_test_pars();
+
+        test_support::create_race(&sim_pars, &sim_consts, &tire_config)
+    }
+
+    #[test]
+    fn test_build_race_state() {
+        let mut race = get_test_race();
+        race.simulate_until_leader_lap(3);
+
+        let race_state = RaceStateBuilder::build(&race).unwrap();
+        assert_eq!(race_state.car_states.len(), race.cars_list.len());
+        assert!(!race_state.sc_active);
+        assert_eq!(race_state.sc_race_prog, 0.0);
+        assert!(race_state.final_result.is_none());
+
+        for (i, car_state) in race_state.car_states.iter().enumerate() {
+            let car = &race.cars_list[i];
+            let color = resolve_car_color(&car.color, car.car_no).unwrap().rgb;
+            assert_eq!(car_state.car_no, car.car_no);
+            assert_eq!([car_state.color.r, car_state.color.g, car_state.color.b], color);
+            assert_eq!(car_state.race_prog, car.sh.get_race_prog());
+            assert_eq!(car_state.t_pitlane, race.get_t_pitlane()[i]);
+
+            // velocity on track follows the current lap time, limited by the maximum velocity
+            assert!(car_state.velocity > 0.0);
+            assert!(car_state.velocity <= car.driver.vel_max / 3.6 + 1e-9);
+        }
+
+        // the incremental builder gives the same states as the single call
+        let builder = RaceStateBuilder::new(&race).unwrap();
+        race.simulate_timestep();
+        let race_state = builder.build_state(&race);
+        let race_state_single = RaceStateBuilder::build(&race).unwrap();
+        let race_progs: Vec<f64> = race_state.car_states.iter().map(|x| x.race_prog).collect();
+        let race_progs_single: Vec<f64> =
+            race_state_single.car_states.iter().map(|x| x.race_prog).collect();
+        assert_eq!(race_progs, race_progs_single);
+
+        // the race progress of the safety car is given in laps
+        race.safety_car.active = true;
+        race.safety_car.lap = 3;
+        race.safety_car.s_track = 0.5 * race.track.length;
+        let race_state = builder.build_state(&race);
+        assert!(race_state.sc_active);
+        assert!((race_state.sc_race_prog - 3.5).abs() < 1e-9);
+
+        let final_state = builder.build_final(&race, race.get_race_result());
+        assert!(final_state.car_states.is_empty());
+        assert!(final_state.final_result.is_some());
+    }
+
+    #[test]
+    fn test_build_race_state_retirements() {
+        let mut race = get_test_race();
+        race.simulate_until_leader_lap(3);
+
+        let idx_retired = race.get_car_idx(33).unwrap();
+        let t_retired = race.cur_racetime;
+        race.retire_cars(&[idx_retired], RetirementCause::Crash);
+        race.simulate_timestep();
+        race.simulate_timestep();
+
+        let race_state = RaceStateBuilder::build(&race).unwrap();
+        for (i, car_state) in race_state.car_states.iter().enumerate() {
+            assert_eq!(car_state.is_dnf, i == idx_retired);
+        }
+        let car_state = &race_state.car_states[idx_retired];
+        assert_eq!(car_state.t_retired_s, Some(t_retired));
+        assert!(car_state.interval_ahead_s.is_none());
+
+        // the retirement is listed with the lap and the kind of the retirement event
+        assert_eq!(race_state.retirements.len(), 1);
+        let retired_car = &race_state.retirements[0];
+        assert_eq!(retired_car.car_no, 33);
+        assert_eq!(retired_car.driver_initials, race.cars_list[idx_retired].driver.initials);
+        assert_eq!(retired_car.lap, race.cars_list[idx_retired].sh.get_compl_lap());
+        assert_eq!(retired_car.reason, "Crash");
+
+        // the final state carries the same list
+        let builder = RaceStateBuilder::new(&race).unwrap();
+        let final_state = builder.build_final(&race, race.get_race_result());
+        assert_eq!(final_state.retirements.len(), 1);
+        assert_eq!(final_state.retirements[0].car_no, 33);
+    }
+
+    #[test]
+    fn test_gui_sender_requires_positive_frequency() {
+        let (tx, _rx) = flume::unbounded();
+        for &update_frequency in [0.0, -20.0, f64::NAN, f64::INFINITY].iter() {
+            assert!(GuiSender::new(tx.clone(), update_frequency).is_err());
+        }
+
+        let gui_sender = GuiSender::new(tx, 60.0).unwrap();
+        assert!(gui_sender.is_due());
+    }
+}
+
+#[cfg(test)]
+mod lap_classification_tests {
+    use crate::post::output_fmt::OutputFormat;
+    use crate::post::race_result::{CarDriverPair, PitStopInfo, RaceResult, RetirementInfo};
+
+    /// get_test_result creates a result of four cars over six laps: car 2 leads until its pit stop
+    /// in lap 3, car 3 is lapped in lap 4 and car 4 retires in lap 3.
+    fn get_test_result() -> RaceResult {
+        let racetimes: Vec<Vec<f64>> = vec![
+            vec![0.0, 80.0, 160.0, 240.0, 320.0, 400.0, 480.0],
+            vec![0.0, 79.0, 158.0, 262.0, 341.0, 420.0, 499.0],
+            vec![0.0, 120.0, 240.0, 360.0, 480.0, 600.0, 720.0],
+            vec![0.0, 81.0, 162.0, 0.0, 0.0, 0.0, 0.0],
+        ];
+        let laptimes = racetimes
+            .iter()
+            .map(|car_racetimes| {
+                (0..car_racetimes.len())
+                    .map(|lap| {
+                        if lap == 0 || car_racetimes[lap] <= 0.0 {
+                            0.0
+                        } else {
+                            car_racetimes[lap] - car_racetimes[lap - 1]
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        RaceResult {
+            pit_stops: vec![PitStopInfo {
+                car_no: 2,
+                lap: 3,
+                t_standstill: 2.5,
+            }],
+            no_pitstops: vec![0, 1, 0, 0],
+            t_pitlane: vec![0.0, 25.0, 0.0, 0.0],
+            track_length: 5000.0,
+            retirements: vec![
+                None,
+                None,
+                None,
+                Some(RetirementInfo {
+                    lap: 2,
+                    s_track: 1500.0,
+                    time_s: 200.0,
+                    failure: None,
+                }),
+            ],
+            ..RaceResult::new(
+                6,
+                (1..5)
+                    .map(|car_no| CarDriverPair {
+                        car_no,
+                        driver_initials: format!("D{:02}", car_no),
+                    })
+                    .collect(),
+                laptimes,
+                racetimes,
+            )
+        }
+    }
+
+    #[test]
+    fn test_classification_before_crossover() {
+        let result = get_test_result();
+        let classification = result.classification_at_lap(2).unwrap();
+
+        let car_nos: Vec<u32> = classification.entries.iter().map(|entry| entry.car_no).collect();
+        assert_eq!(car_nos, vec![2, 1, 4, 3]);
+
+        // all cars completed lap 2, car 4 retires only later
+        let gaps: Vec<Option<f64>> = classification.entries.iter().map(|entry| entry.gap).collect();
+        assert_eq!(gaps, vec![Some(0.0), Some(2.0), Some(4.0), Some(82.0)]);
+        assert!(classification.entries.iter().all(|entry| !entry.retired));
+        assert!(classification.entries.iter().all(|entry| entry.no_pitstops == 0));
+    }
+
+    #[test]
+    fn test_classification_after_crossover() {
+        let result = get_test_result();
+        let classification = result.classification_at_lap(4).unwrap();
+
+        let car_nos: Vec<u32> = classification.entries.iter().map(|entry| entry.car_no).collect();
+        assert_eq!(car_nos, vec![1, 2, 3, 4]);
+
+        // car 2 lost the lead by its pit stop
+        assert_eq!(classification.entries[1].gap, Some(21.0));
+        assert_eq!(classification.entries[1].no_pitstops, 1);
+
+        // car 3 had completed only 3 laps when the leader completed lap 4
+        assert_eq!(classification.entries[2].laps, 3);
+        assert_eq!(classification.entries[2].gap, None);
+        assert!(!classification.entries[2].retired);
+
+        assert_eq!(classification.entries[3].laps, 2);
+        assert!(classification.entries[3].retired);
+
+        let text = result
+            .format_classification_at_lap(4, &OutputFormat::default())
+            .unwrap()
+            .unwrap();
+        assert!(text.contains("+21.000 s"));
+        assert!(text.contains("+1 lap"));
+        assert!(text.contains("DNF (lap 2)"));
+    }
+
+    #[test]
+    fn test_classification_without_retirements() {
+        // older results do not contain the retirements, car 4 is still classified as DNF
+        let mut result = get_test_result();
+        result.retirements.clear();
+
+        let classification = result.classification_at_lap(4).unwrap();
+        assert_eq!(classification.entries[3].car_no, 4);
+        assert!(classification.entries[3].retired);
+
+        assert!(result.classification_at_lap(0).is_none());
+        assert!(result.classification_at_lap(7).is_none());
+    }
+}
+
+#[cfg(test)]
+mod time_of_day_tests {
+    use crate::core::time_of_day::{format_time_of_day, get_time_of_day, TrackTempPars};
+    use crate::test_support;
+
+    fn get_track_temp_pars() -> TrackTempPars {
+        TrackTempPars {
+            temp_day_c: 40.0,
+            temp_night_c: 28.0,
+            cooling_time_h: 1.0,
+            temp_ref_c: 35.0,
+        }
+    }
+
+    #[test]
+    fn test_track_cools_after_sunset() {
+        let pars = get_track_temp_pars();
+
+        assert_eq!(pars.calc_track_temp(15.0, 18.0), 40.0);
+        assert_eq!(pars.calc_track_temp(18.0, 18.0), 40.0);
+
+        // monotonic cooling towards the night temperature, also past midnight
+        let temps: Vec<f64> = [18.5, 19.0, 21.0, 23.5, 25.0]
+            .iter()
+            .map(|&time_of_day| pars.calc_track_temp(time_of_day, 18.0))
+            .collect();
+        assert!(temps.windows(2).all(|x| x[1] < x[0]), "temps {:?}", temps);
+        assert!(temps[4] > 28.0 && temps[4] < 28.1);
+
+        // hotter tracks wear the tires faster
+        assert!(pars.calc_wear_factor(40.0, 0.03) > 1.0);
+        assert!(pars.calc_wear_factor(30.0, 0.03) < 1.0);
+        assert_eq!(pars.calc_wear_factor(30.0, 0.0), 1.0);
+
+        // a morning is daylight, not the night after the sunset
+        assert_eq!(pars.calc_track_temp(9.0, 18.0), 40.0);
+
+        assert!((get_time_of_day(23.5, 3600.0) - 24.5).abs() < 1e-9);
+        assert!((get_time_of_day(9.0, 1800.0) - 9.5).abs() < 1e-9);
+        assert_eq!(format_time_of_day(17.75), "17:45");
+        assert_eq!(format_time_of_day(23.999), "00:00");
+        assert_eq!(format_time_of_day(24.5), "00:30");
+    }
+
+    /// run_single_car_race lets HAM drive the test race alone on hards after its pit stop and
+    /// returns the tire age at the end of the race and the tire degradation of the last lap.
+    fn run_single_car_race(session_start_h: Option<f64>, sunset_h: f64) -> (f64, f64) {
+        let (mut sim_pars, sim_consts, mut tire_config) = test_support::load_test_pars();
+        tire_config.hard.temp_wear_sensitivity = 0.03;
+
+        sim_pars.race_pars.participants = vec![44];
+        sim_pars.race_pars.session_start_h = session_start_h;
+        sim_pars.race_pars.sunset_h = sunset_h;
+        sim_pars.car_pars_all.get_mut(&44).unwrap().strategy[1].compound = String::from("HARD");
+
+        let mut race = test_support::create_race(&sim_pars, &sim_consts, &tire_config);
+
+        while !race.get_all_finished() {
+            race.simulate_timestep();
+        }
+
+        let tot_no_laps = sim_pars.race_pars.tot_no_laps as usize;
+        (
+            race.cars_list[0].get_tire_age_cur_stint(),
+            race.get_laptime_breakdowns(0)[tot_no_laps].t_tire,
+        )
+    }
+
+    #[test]
+    fn test_hards_come_alive_at_night() {
+        let (age_plain, _) = run_single_car_race(None, 18.0);
+        let (age_day, t_tire_day) = run_single_car_race(Some(12.0), 18.0);
+        let (age_night, t_tire_night) = run_single_car_race(Some(17.0), 16.5);
+
+        // without the session start the tires wear as before, in the heat faster, after sunset
+        // slower
+        assert!(age_day > age_plain + 1.0, "day {}, plain {}", age_day, age_plain);
+        assert!(age_night < age_plain - 1.0, "night {}, plain {}", age_night, age_plain);
+        assert!(t_tire_night < t_tire_day);
+    }
+
+    #[test]
+    fn test_morning_start_runs_in_daylight() {
+        let (age_day, _) = run_single_car_race(Some(12.0), 18.0);
+        let (age_morning, _) = run_single_car_race(Some(9.0), 18.0);
+
+        // a morning session is as hot as a midday session
+        assert!((age_morning - age_day).abs() < 1e-6, "morning {}, day {}", age_morning, age_day);
+    }
+}
+
+#[cfg(test)]
+mod crash_avoidance_tests {
+    use crate::core::car::CarStatus;
+    use crate::core::race::{calc_secondary_collision_prob, CrashAvoidancePars};
+    use crate::core::scripted_event::{ScriptedAction, ScriptedEvent};
+    use crate::test_support;
+
+    #[test]
+    fn test_secondary_collision_prob() {
+        let pars = CrashAvoidancePars {
+            distance_m: 200.0,
+            slowdown_factor: 3.0,
+            duration_s: 10.0,
+            collision_probability: 0.05,
+        };
+
+        assert_eq!(calc_secondary_collision_prob(0.0, 1.0, &pars), 0.05);
+        assert_eq!(calc_secondary_collision_prob(250.0, 1.0, &pars), 0.0);
+
+        // closer and wetter is riskier
+        let p_far = calc_secondary_collision_prob(150.0, 1.0, &pars);
+        let p_near = calc_secondary_collision_prob(50.0, 1.0, &pars);
+        assert!(p_near > p_far);
+        assert!(calc_secondary_collision_prob(50.0, 2.0, &pars) > p_near);
+    }
+
+    /// crash_ahead_of_follower runs the test race until lap 2 of the leader, places car 44 just
+    /// before the finish line with car 33 half the avoidance distance behind it and lets car 44
+    /// crash by a scripted DNF at the start of lap 3. Returns whether car 33 was slowed down
+    /// sharply or collected the stricken car within the next timesteps.
+    fn crash_ahead_of_follower(collision_probability: f64) -> (bool, bool) {
+        let (mut sim_pars, mut sim_consts, tire_config) = test_support::load_test_pars();
+        sim_consts.secondary_collision_probability = collision_probability;
+        sim_pars.race_pars.scripted_events = vec![ScriptedEvent {
+            lap: 3,
+            action: ScriptedAction::Dnf { car_no: 44 },
+            override_stochastic: true,
+        }];
+
+        let mut race = test_support::create_race(&sim_pars, &sim_consts, &tire_config);
+        let idx_crash = race.get_car_idx(44).unwrap();
+        let idx_follower = race.get_car_idx(33).unwrap();
+
+        while race.cur_lap_leader < 2
+            || race.cars_list[idx_crash].sh.pit_act
+            || race.cars_list[idx_follower].sh.pit_act
+        {
+            race.simulate_timestep();
+        }
+
+        let dist_follower = 0.5 * sim_consts.crash_avoidance_distance_m;
+        for (idx, dist_to_line) in [(idx_crash, 1.0), (idx_follower, 1.0 + dist_follower)].iter() {
+            race.cars_list[*idx].sh.place_race_prog(2.0 - dist_to_line / race.track.length);
+        }
+        race.simulate_timestep();
+
+        let laptime_before = race.cur_laptimes[idx_follower];
+        while race.cars_list[idx_crash].status != CarStatus::DNF {
+            race.simulate_timestep();
+        }
+
+        let mut slowed = false;
+        for _ in 0..5 {
+            race.simulate_timestep();
+            slowed |= race.cur_laptimes[idx_follower] > 2.0 * laptime_before;
+        }
+
+        (slowed, race.cars_list[idx_follower].status == CarStatus::DNF)
+    }
+
+    #[test]
+    fn test_follower_slows_before_flag() {
+        let (slowed, collected) = crash_ahead_of_follower(0.0);
+        assert!(slowed);
+        assert!(!collected);
+    }
+
+    #[test]
+    fn test_follower_never_passes_unaffected() {
+        let (_, collected) = crash_ahead_of_follower(1.0e6);
+        assert!(collected);
+
+        for _ in 0..5 {
+            let (slowed, collected) = crash_ahead_of_follower(0.5);
+            assert!(slowed || collected);
+        }
+    }
+}
+
+#[cfg(test)]
+mod time_loss_tests {
+    use crate::post::race_result::{TimeLossBreakdown, TimeLossCause};
+    use crate::test_support;
+
+    #[test]
+    fn test_time_loss_breakdown() {
+        let mut time_losses = TimeLossBreakdown::default();
+        time_losses.add(TimeLossCause::Tire, 2.0);
+        time_losses.add(TimeLossCause::PitStops, 20.0);
+        time_losses.add(TimeLossCause::Other, -0.5);
+
+        let mut time_losses_sum = TimeLossBreakdown::default();
+        time_losses_sum.add_scaled(&time_losses, 2.0);
+
+        assert_eq!(time_losses.get(TimeLossCause::PitStops), 20.0);
+        assert_eq!(time_losses.t_tire, 2.0);
+        assert_eq!(time_losses.get_total(), 21.5);
+        assert_eq!(time_losses_sum.get_total(), 43.0);
+    }
+
+    #[test]
+    fn test_time_losses_sum_to_gap_to_clean_run() {
+        let (sim_pars, mut sim_consts, tire_config) = test_support::load_test_pars();
+        // the ERS deployment and harvesting are booked as well
+        sim_consts.ers_capacity_mj = 4.0;
+
+        let mut race = test_support::create_race(&sim_pars, &sim_consts, &tire_config);
+
+        while !race.get_all_finished() {
+            race.simulate_timestep();
+        }
+
+        let result = race.get_race_result();
+        assert_eq!(result.time_losses.len(), result.car_driver_pairs.len());
+
+        // clean lap time taken from the inputs, not from the simulated lap time breakdowns
+        let track_pars = &sim_pars.track_pars;
+        for idx in 0..result.car_driver_pairs.len() {
+            let pair = &result.car_driver_pairs[idx];
+            let t_clean = track_pars.t_q
+                + track_pars.t_gap_racepace
+                + sim_pars.car_pars_all[&pair.car_no].t_car
+                + sim_pars.driver_pars_all[&pair.driver_initials].t_driver;
+            let last_lap = (1..result.tot_no_laps as usize + 1)
+                .rev()
+                .find(|&lap| result.racetimes[idx][lap] > 0.0)
+                .unwrap();
+            let t_gap_to_clean_run = result.racetimes[idx][last_lap] - last_lap as f64 * t_clean;
+            let time_losses = &result.time_losses[idx];
+
+            assert!(
+                (time_losses.get_total() - t_gap_to_clean_run).abs() < 1e-6,
+                "car {}: sum {} vs. gap {}",
+                result.car_driver_pairs[idx].car_no,
+                time_losses.get_total(),
+                t_gap_to_clean_run
+            );
+
+            // HAM stops once (at least the standstill is lost), VER runs without a stop, both
+            // degrade their tires
+            if result.no_pitstops[idx] > 0 {
+                let t_standstill = sim_pars.car_pars_all[&pair.car_no].t_pit_tirechange;
+                assert!(time_losses.t_pit > t_standstill, "{:?}", time_losses);
+            } else {
+                assert_eq!(time_losses.t_pit, 0.0);
+            }
+            assert!(time_losses.t_tire > 0.0, "{:?}", time_losses);
+        }
+    }
+}
+
+#[cfg(test)]
+mod result_diff_tests {
+    use crate::post::output_fmt::OutputFormat;
+    use crate::post::race_result::{CarDriverPair, RaceEvent, RaceResult};
+    use crate::post::result_diff::{get_sc_laps, ResultDiff};
+
+    /// get_test_result creates a result over three laps from the car numbers and lap times of the
+    /// cars (index 0 is the start, a lap time of 0 marks a lap that was not driven).
+    fn get_test_result(laptimes: Vec<(u32, Vec<f64>)>) -> RaceResult {
+        let racetimes = laptimes
+            .iter()
+            .map(|(_, car_laptimes)| {
+                car_laptimes
+                    .iter()
+                    .scan(0.0, |racetime, &laptime| {
+                        *racetime += laptime;
+                        Some(if laptime > 0.0 { *racetime } else { 0.0 })
+                    })
+                    .collect()
+            })
+            .collect();
+
+        RaceResult::new(
+            3,
+            laptimes
+                .iter()
+                .map(|(car_no, _)| CarDriverPair {
+                    car_no: *car_no,
+                    driver_initials: format!("D{:02}", car_no),
+                })
+                .collect(),
+            laptimes.into_iter().map(|x| x.1).collect(),
+            racetimes,
+        )
+    }
+
+    fn get_sc_event(kind: &str, lap: u32) -> RaceEvent {
+        RaceEvent::new(kind, lap, 0.0, vec![])
+    }
+
+    #[test]
+    fn test_result_diff_positions_and_racetimes() {
+        let result_prev = get_test_result(vec![
+            (1, vec![2.0, 90.0, 90.0, 90.0]),
+            (2, vec![2.5, 91.0, 91.0, 91.0]),
+            (3, vec![3.0, 92.0, 92.0, 0.0]),
+        ]);
+        let result_cur = get_test_result(vec![
+            (1, vec![2.0, 92.0, 92.0, 92.0]),
+            (2, vec![2.5, 90.0, 90.0, 90.0]),
+            (4, vec![3.0, 93.0, 93.0, 93.0]),
+        ]);
+
+        let result_diff = ResultDiff::new(&result_prev, &result_cur);
+        let car_nos: Vec<u32> = result_diff.car_diffs.iter().map(|x| x.car_no).collect();
+        assert_eq!(car_nos, vec![2, 1, 4, 3]);
+
+        let car_1 = &result_diff.car_diffs[1];
+        assert_eq!(car_1.get_delta_position(), Some(1));
+        assert!((car_1.get_delta_racetime().unwrap() - 6.0).abs() < 1e-9);
+
+        let car_2 = &result_diff.car_diffs[0];
+        assert_eq!(car_2.get_delta_position(), Some(-1));
+        assert!((car_2.get_delta_racetime().unwrap() + 3.0).abs() < 1e-9);
+
+        // cars that are part of one result only are not comparable
+        assert_eq!(result_diff.car_diffs[2].get_delta_position(), None);
+        assert_eq!(result_diff.car_diffs[3].position_prev, Some(3));
+        assert_eq!(result_diff.car_diffs[3].get_delta_racetime(), None);
+
+        let laptime_pairs = ResultDiff::get_laptime_pairs(&result_prev, &result_cur, 3);
+        assert_eq!(laptime_pairs.len(), 3);
+        assert_eq!(laptime_pairs[0].1, 92.0);
+        assert!(laptime_pairs[0].2.is_nan());
+        assert!(laptime_pairs[2].1.is_nan());
+
+        let content = result_diff.format(&OutputFormat::default()).unwrap();
+        assert!(content.contains("+6.000"));
+        assert!(content.contains("-3.000"));
+    }
+
+    #[test]
+    fn test_result_diff_differing_events() {
+        let mut result_prev = get_test_result(vec![(1, vec![2.0, 90.0, 90.0, 90.0])]);
+        let mut result_cur = result_prev.clone();
+
+        // equal results do not differ
+        assert!(ResultDiff::new(&result_prev, &result_cur)
+            .get_differing_events()
+            .is_empty());
+
+        result_prev.events = vec![get_sc_event("SC_DEPLOYED", 1), get_sc_event("SC_IN", 2)];
+        result_prev.weather_history = vec![
+            String::from("Dry"),
+            String::from("Rain"),
+            String::from("Rain"),
+        ];
+        result_cur.events = vec![get_sc_event("SC_DEPLOYED", 3)];
+        result_cur.weather_history = result_prev.weather_history.clone();
+
+        assert_eq!(get_sc_laps(&result_prev), vec![1, 2]);
+        assert_eq!(get_sc_laps(&result_cur), vec![3]);
+
+        let result_diff = ResultDiff::new(&result_prev, &result_cur);
+        assert_eq!(result_diff.rain_laps_prev, vec![2, 3]);
+        assert_eq!(result_diff.get_differing_events(), vec!["SC laps: 1-2 -> 3"]);
+    }
+}
+
+#[cfg(test)]
+mod vsc_tests {
+    use crate::core::car::RetirementCause;
+    use crate::core::race::{FlagState, Race};
+    use crate::post::race_result::ScPeriodKind;
+    use crate::test_support;
+
+    /// create_race creates the test race with a third car (copy of car 44 without pit stops) and
+    /// simulates it until lap 3, such that car 44 can retire outside the pit lane.
+    fn create_race(vsc_probability: f64, vsc_duration_s: f64) -> Race {
+        let (mut sim_pars, mut sim_consts, tire_config) = test_support::load_test_pars();
+        sim_consts.sc_probability = 1.0;
+        sim_consts.sc_overtaking_zone_factor = 1.0;
+
+        let mut car_pars_45 = sim_pars.car_pars_all[&44].clone();
+        car_pars_45.car_no = 45;
+        car_pars_45.p_grid = 3;
+        car_pars_45.strategy.truncate(1);
+        sim_pars.car_pars_all.insert(45, car_pars_45);
+        sim_pars.race_pars.participants = vec![44, 33, 45];
+        sim_pars.race_pars.vsc_probability = vsc_probability;
+        sim_pars.race_pars.vsc_duration_s = vsc_duration_s;
+        // seeded, such that car 45 is not held up by car 33 under VSC (it could not run its own
+        // VSC pace then)
+        sim_pars.race_pars.seed = Some(1);
+
+        let mut race = test_support::create_race(&sim_pars, &sim_consts, &tire_config);
+        let idx_retire = race.get_car_idx(44).unwrap();
+
+        while race.cur_lap_leader < 3 || race.cars_list[idx_retire].sh.pit_act {
+            race.simulate_timestep();
+        }
+
+        race
+    }
+
+    #[test]
+    fn test_vsc_after_single_car_retirement() {
+        let mut race = create_race(1.0, 30.0);
+        let idx_retire = race.get_car_idx(44).unwrap();
+        let idxs_running = [race.get_car_idx(33).unwrap(), race.get_car_idx(45).unwrap()];
+        // VSC lap time of a car: its own theoretical lap time plus the VSC delta
+        let get_laptime_vsc = |race: &Race, idx: usize| {
+            let lap = race.cars_list[idx].sh.get_compl_lap() as usize + 1;
+            race.get_laptime_breakdowns(idx)[lap].get_total() + 35.0
+        };
+
+        race.retire_cars(&[idx_retire], RetirementCause::Crash);
+        race.simulate_timestep();
+        assert!(matches!(race.flag_state, FlagState::Vsc));
+
+        let get_order = |race: &Race| {
+            race.cars_list[idxs_running[0]].sh.get_race_prog()
+                > race.cars_list[idxs_running[1]].sh.get_race_prog()
+        };
+        let order_start = get_order(&race);
+
+        // every car runs the VSC delta over its own pace at every position (with the multiplier
+        // at the start of the step), no physical SC. A car is only slower while it gives back the
+        // time of a mini sector driven too fast at a change of the multiplier.
+        let mut no_steps_checked = 0;
+        let mut no_steps_giveback = 0;
+
+        for _ in 0..200 {
+            race.simulate_timestep();
+            assert!(!race.safety_car.active);
+
+            for &idx in idxs_running.iter() {
+                // the lap time of a step with a lap change was set in the previous lap
+                let (lap_frac_prev, lap_frac) = race.cars_list[idx].sh.get_lap_fracs();
+                if lap_frac < lap_frac_prev {
+                    continue;
+                }
+
+                let multiplier = race.track.get_multiplier(race.cars_list[idx].sh.get_s_tracks().0);
+                let laptime_vsc = get_laptime_vsc(&race, idx);
+                let laptime_cur = race.cur_laptimes[idx] * multiplier;
+                assert!(laptime_cur > laptime_vsc - 1e-6);
+                no_steps_checked += 1;
+                if laptime_cur > laptime_vsc + 1e-6 {
+                    no_steps_giveback += 1;
+                }
+            }
+        }
+        assert!(no_steps_giveback * 20 < no_steps_checked);
+        assert_eq!(get_order(&race), order_start);
+
+        // back to green after the VSC duration
+        for _ in 0..150 {
+            race.simulate_timestep();
+        }
+        assert!(matches!(race.flag_state, FlagState::G));
+
+        let result = race.get_race_result();
+        let kinds: Vec<&str> = result.events.iter().map(|ev| ev.kind.as_str()).collect();
+        assert!(kinds.contains(&"VSC_DEPLOYED"));
+        assert!(kinds.contains(&"VSC_ENDING"));
+        assert!(!kinds.contains(&"SC_DEPLOYED"));
+
+        // the VSC period is recorded with the retired car as cause
+        assert_eq!(result.sc_periods.len(), 1);
+        let sc_period = &result.sc_periods[0];
+        assert_eq!(sc_period.kind, ScPeriodKind::Vsc);
+        assert_eq!(sc_period.cause_car_no, Some(44));
+        assert!((sc_period.get_duration() - 30.0).abs() < 0.2);
+        assert_eq!(result.get_sc_periods_summary(ScPeriodKind::Sc), (0, 0.0));
+    }
+
+    #[test]
+    fn test_sc_without_vsc_probability() {
+        let mut race = create_race(0.0, 30.0);
+        let idx_retire = race.get_car_idx(44).unwrap();
+
+        race.retire_cars(&[idx_retire], RetirementCause::Crash);
+        race.simulate_timestep();
+        assert!(matches!(race.flag_state, FlagState::Sc));
+    }
+
+    #[test]
+    fn test_mini_sector_times() {
+        let mut track = (*create_race(0.0, 30.0).track).clone();
+        track.multipliers = vec![1.0, 0.5];
+
+        let mini_sectors = track.get_mini_sectors(4);
+        let s_starts: Vec<f64> = mini_sectors.iter().map(|x| x.s_start / track.length).collect();
+        assert_eq!(s_starts, vec![0.0, 0.25, 0.5, 0.75]);
+        let laptime_fracs: Vec<f64> = mini_sectors.iter().map(|x| x.laptime_frac).collect();
+        assert_eq!(laptime_fracs, vec![0.25, 0.25, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn test_identical_time_loss_per_vsc_lap() {
+        let mut race = create_race(1.0, 400.0);
+        let idx_retire = race.get_car_idx(44).unwrap();
+        let idxs_running = [race.get_car_idx(33).unwrap(), race.get_car_idx(45).unwrap()];
+        let laptime_frac_track: f64 =
+            race.track.get_mini_sectors(20).iter().map(|mini_sector| mini_sector.laptime_frac).sum();
+
+        race.retire_cars(&[idx_retire], RetirementCause::Crash);
+        race.simulate_timestep();
+        assert!(matches!(race.flag_state, FlagState::Vsc));
+
+        while matches!(race.flag_state, FlagState::Vsc) {
+            race.simulate_timestep();
+        }
+
+        // every lap of the running cars driven completely under VSC takes the VSC lap time (the
+        // theoretical lap time of the car plus the VSC delta) on the real track, up to the time
+        // still to be given back at the end of the lap (at most half a step per step)
+        let result = race.get_race_result();
+        let sc_period = &result.sc_periods[0];
+        let mut no_vsc_laps = 0;
+
+        for &idx in idxs_running.iter() {
+            for lap in 1..=result.get_last_driven_lap(idx) {
+                if result.get_racetime(idx, lap - 1) >= sc_period.t_start
+                    && result.get_racetime(idx, lap) <= sc_period.t_end
+                {
+                    let laptime_vsc = race.get_laptime_breakdowns(idx)[lap].get_total() + 35.0;
+                    let laptime_vsc_track = laptime_vsc * laptime_frac_track;
+                    assert!((result.get_laptime(idx, lap) - laptime_vsc_track).abs() < 0.1);
+                    no_vsc_laps += 1;
+                }
+            }
+        }
+        assert!(no_vsc_laps >= 2);
+    }
+}
+
+#[cfg(test)]
+mod merge_sim_pars_tests {
+    use crate::pre::read_sim_pars::{merge_sim_pars, PartialSimPars};
+
+    fn get_base_part() -> (String, PartialSimPars) {
+        (
+            String::from("base.json"),
+            serde_json::from_str(include_str!("../../input/parameters/test_race.json")).unwrap(),
+        )
+    }
+
+    /// get_team_part creates a partial file containing the car parameters of a new car 45 (a copy
+    /// of car 44) and a replacement of car 44 with the given car time and a single-stint strategy.
+    fn get_team_part(name: &str, t_car_44: f64) -> (String, PartialSimPars) {
+        let base: serde_json::Value =
+            serde_json::from_str(include_str!("../../input/parameters/test_race.json")).unwrap();
+        let mut car_45 = base["car_pars_all"]["44"].clone();
+        car_45["car_no"] = serde_json::json!(45);
+        let mut car_44 = base["car_pars_all"]["44"].clone();
+        car_44["t_car"] = serde_json::json!(t_car_44);
+        car_44["strategy"] = serde_json::json!([base["car_pars_all"]["44"]["strategy"][0]]);
+
+        let part = serde_json::json!({
+            "car_pars_all": {
+                "44": car_44,
+                "45": car_45
+            },
+            "participants": [45, 44]
+        });
+
+        (String::from(name), serde_json::from_value(part).unwrap())
+    }
+
+    #[test]
+    fn test_override_precedence() {
+        let parts = vec![
+            get_base_part(),
+            get_team_part("team_a.json", 0.5),
+            get_team_part("team_b.json", 0.7),
+        ];
+        let sim_pars = merge_sim_pars(&parts).unwrap();
+
+        // the entry of the last file replaces car 44 as a whole
+        assert!((sim_pars.car_pars_all[&44].t_car - 0.7).abs() < 1e-9);
+        assert_eq!(sim_pars.car_pars_all[&44].strategy.len(), 1);
+        assert_eq!(sim_pars.car_pars_all[&45].car_no, 45);
+        assert!(sim_pars.car_pars_all.contains_key(&33));
+        assert!(sim_pars.driver_pars_all.contains_key("HAM"));
+
+        // participants concatenated and de-duplicated
+        assert_eq!(sim_pars.race_pars.participants, vec![44, 33, 45]);
+        assert!(sim_pars.validate().errors.is_empty());
+    }
+
+    #[test]
+    fn test_conflicting_track_pars() {
+        let mut part_track = get_team_part("team_a.json", 0.5);
+        part_track.1.track_pars = get_base_part().1.track_pars;
+
+        let err = merge_sim_pars(&[get_base_part(), part_track]).unwrap_err();
+        assert!(err.to_string().contains("track_pars"));
+        assert!(err.to_string().contains("team_a.json"));
+
+        // race parameters in two files are rejected as well
+        let err = merge_sim_pars(&[get_base_part(), get_base_part()]).unwrap_err();
+        assert!(err.to_string().contains("race_pars"));
+
+        // missing race parameters
+        assert!(merge_sim_pars(&[get_team_part("team_a.json", 0.5)]).is_err());
+    }
+}
+
+#[cfg(test)]
+mod velocity_trace_tests {
+    use crate::core::track::{Track, TrackPars};
+    use crate::post::race_result::{CarDriverPair, PitStopInfo, RaceResult};
+    use crate::post::velocity_trace::get_velocity_trace;
+
+    /// get_oval_track creates a synthetic oval whose first half is a straight (multiplier 1.2) and
+    /// whose second half is a long corner (multiplier 0.8). The pit zone crosses the finish line.
+    fn get_oval_track() -> Track {
+        let base: serde_json::Value =
+            serde_json::from_str(include_str!("../../input/parameters/test_race.json")).unwrap();
+        let mut track_pars: TrackPars = serde_json::from_value(base["track_pars"].clone()).unwrap();
+        track_pars.name = String::from("SyntheticOval");
+
+        let mut track = Track::new(&track_pars);
+        track.multipliers = (0..100).map(|i| if i < 50 { 1.2 } else { 0.8 }).collect();
+        track
+    }
+
+    fn get_test_result(pit_stops: Vec<PitStopInfo>) -> RaceResult {
+        RaceResult {
+            pit_stops,
+            ..RaceResult::new(
+                3,
+                vec![CarDriverPair {
+                    car_no: 44,
+                    driver_initials: String::from("HAM"),
+                }],
+                vec![vec![0.0, 85.0, 80.0, 90.0]],
+                vec![vec![0.0, 85.0, 165.0, 255.0]],
+            )
+        }
+    }
+
+    #[test]
+    fn test_two_level_speed_profile() {
+        let track = get_oval_track();
+        let trace = get_velocity_trace(&get_test_result(Vec::new()), &track, 44, 2).unwrap();
+
+        // expected base speed such that the lap takes 80s
+        let v_base = (track.length / 2.0 / 1.2 + track.length / 2.0 / 0.8) / 80.0;
+
+        assert_eq!(trace.velocities.len(), 100);
+        for (i, v) in trace.velocities.iter().enumerate() {
+            let v_exp = v_base * if i < 50 { 1.2 } else { 0.8 };
+            assert!((v - v_exp).abs() < 1e-9, "sample {}: {} vs {}", i, v, v_exp);
+        }
+        assert!(trace.in_pitlane.iter().all(|&pit| !pit));
+        assert!((trace.get_modeled_laptime() - 80.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_pitlane_in_and_outlap() {
+        let track = get_oval_track();
+        let result = get_test_result(vec![PitStopInfo {
+            car_no: 44,
+            lap: 2,
+            t_standstill: 2.5,
+        }]);
+        let v_pit = track.pit_speedlimit * track.track_length_pit_zone / track.real_length_pit_zone;
+
+        // inlap: pit lane from the pit entry to the finish line, pits after the finish line
+        let trace_in = get_velocity_trace(&result, &track, 44, 2).unwrap();
+        assert_eq!(trace_in.t_standstill, 0.0);
+        for (s, (v, pit)) in trace_in
+            .dists
+            .iter()
+            .zip(trace_in.velocities.iter().zip(trace_in.in_pitlane.iter()))
+        {
+            assert_eq!(*pit, *s >= track.pit_zone[0]);
+            if *pit {
+                assert!((v - v_pit).abs() < 1e-9);
+            }
+        }
+        assert!((trace_in.get_modeled_laptime() - 80.0).abs() < 1e-9);
+
+        // outlap: pit lane from the finish line to the pit exit, including the standstill
+        let trace_out = get_velocity_trace(&result, &track, 44, 3).unwrap();
+        assert_eq!(trace_out.t_standstill, 2.5);
+        assert!(trace_out.in_pitlane[0]);
+        assert!(!trace_out.in_pitlane[50]);
+        assert!((trace_out.get_modeled_laptime() - 90.0).abs() < 1e-9);
+
+        // unknown car and lap not driven
+        assert!(get_velocity_trace(&result, &track, 33, 2).is_err());
+        assert!(get_velocity_trace(&result, &track, 44, 4).is_err());
+    }
+}
+
+#[cfg(test)]
+mod red_flag_tests {
+    use crate::core::car::{FailureType, RetirementCause};
+    use crate::core::race::{FlagState, Race};
+    use crate::core::scripted_event::{ScriptedAction, ScriptedEvent};
+    use crate::test_support;
+
+    /// create_race creates the test race with a third car (copy of car 44 on hard tires without
+    /// pit stops) and simulates it until lap 3, such that car 44 can retire outside the pit lane.
+    fn create_race(red_flag_min_cars: Option<u32>, red_flag_probability: f64) -> Race {
+        let (mut sim_pars, mut sim_consts, tire_config) = test_support::load_test_pars();
+        sim_consts.sc_probability = 1.0;
+        sim_consts.sc_overtaking_zone_factor = 1.0;
+
+        let mut car_pars_45 = sim_pars.car_pars_all[&44].clone();
+        car_pars_45.car_no = 45;
+        car_pars_45.p_grid = 3;
+        car_pars_45.strategy.truncate(1);
+        car_pars_45.strategy[0].compound = String::from("HARD");
+        sim_pars.car_pars_all.insert(45, car_pars_45);
+        sim_pars.race_pars.participants = vec![44, 33, 45];
+        sim_pars.race_pars.red_flag_min_cars = red_flag_min_cars;
+        sim_pars.race_pars.red_flag_probability = red_flag_probability;
+        sim_pars.race_pars.red_flag_duration_s = 20.0;
+        sim_pars.race_pars.red_flag_laps_deducted = 3;
+
+        let mut race = test_support::create_race(&sim_pars, &sim_consts, &tire_config);
+        let idx_retire = race.get_car_idx(44).unwrap();
+
+        while race.cur_lap_leader < 3 || race.cars_list[idx_retire].sh.pit_act {
+            race.simulate_timestep();
+        }
+
+        race
+    }
+
+    #[test]
+    fn test_red_flag_suspension_and_restart() {
+        let mut race = create_race(Some(1), 0.0);
+        let idx_retire = race.get_car_idx(44).unwrap();
+        let idxs_running = [race.get_car_idx(33).unwrap(), race.get_car_idx(45).unwrap()];
+
+        race.retire_cars(&[idx_retire], RetirementCause::Crash);
+        race.simulate_timestep();
+        assert!(matches!(race.flag_state, FlagState::Red));
+
+        // cars stand still and the race time is frozen during the suspension
+        let racetime = race.cur_racetime;
+        let race_progs: Vec<f64> =
+            idxs_running.iter().map(|&idx| race.cars_list[idx].sh.get_race_prog()).collect();
+
+        for _ in 0..100 {
+            race.simulate_timestep();
+            assert!(matches!(race.flag_state, FlagState::Red));
+        }
+        assert_eq!(race.cur_racetime, racetime);
+        for (&idx, &race_prog) in idxs_running.iter().zip(race_progs.iter()) {
+            assert_eq!(race.cars_list[idx].sh.get_race_prog(), race_prog);
+        }
+
+        // restart behind the SC on fresh tires, race shortened by three laps
+        for _ in 0..200 {
+            if !matches!(race.flag_state, FlagState::Red) {
+                break;
+            }
+            race.simulate_timestep();
+        }
+        assert!(matches!(race.flag_state, FlagState::Sc));
+        assert_eq!(race.tot_no_laps, 50);
+        for &idx in idxs_running.iter() {
+            if !race.cars_list[idx].sh.pit_act {
+                assert_eq!(race.cars_list[idx].get_tire_age_cur_stint(), 0.0);
+            }
+        }
+
+        for _ in 0..100000 {
+            if race.get_all_finished() {
+                break;
+            }
+            race.simulate_timestep();
+        }
+        assert!(race.get_all_finished());
+
+        // sensible lap times over the driven laps of the shortened race, the per-lap data is trimmed
+        // together with the lap times
+        let result = race.get_race_result();
+        assert_eq!(result.tot_no_laps, 50);
+        for idx in 0..result.car_driver_pairs.len() {
+            assert_eq!(result.laptimes[idx].len(), 51);
+            assert_eq!(result.lap_flags[idx].len(), 51);
+            assert_eq!(result.engine_modes[idx].len(), 51);
+            assert_eq!(race.get_laptime_breakdowns(idx).len(), 51);
+        }
+        for &idx in idxs_running.iter() {
+            for lap in 1..=result.get_last_driven_lap(idx) {
+                let laptime = result.get_laptime(idx, lap);
+                assert!(laptime > 60.0 && laptime < 300.0, "lap {}: {}", lap, laptime);
+            }
+        }
+
+        let kinds: Vec<String> = result.events.into_iter().map(|ev| ev.kind).collect();
+        let pos_red = kinds.iter().position(|kind| kind == "RED_FLAG").unwrap();
+        let pos_restart = kinds.iter().position(|kind| kind == "RESTART").unwrap();
+        let pos_sc = kinds.iter().position(|kind| kind == "SC_DEPLOYED").unwrap();
+        assert!(pos_red < pos_restart && pos_restart < pos_sc);
+    }
+
+    #[test]
+    fn test_no_red_flag_for_failure() {
+        // a retirement without a crash only triggers the red flag by the number of cars
+        let mut race = create_race(None, 1.0);
+        let idx_retire = race.get_car_idx(44).unwrap();
+
+        race.retire_cars(&[idx_retire], RetirementCause::Failure(FailureType::Engine));
+        race.simulate_timestep();
+        assert!(!matches!(race.flag_state, FlagState::Red));
+        assert!(!race.get_race_result().events.iter().any(|ev| ev.kind == "RED_FLAG"));
+    }
+
+    #[test]
+    fn test_heavy_rain_red_flag() {
+        let (mut sim_pars, mut sim_consts, tire_config) = test_support::load_test_pars();
+        sim_consts.wetness_time_constant_s = 30.0;
+        sim_pars.race_pars.heavy_rain_red_flag_probability = 1.0;
+        sim_pars.race_pars.red_flag_duration_s = 20.0;
+        sim_pars.race_pars.scripted_events = vec![ScriptedEvent {
+            lap: 2,
+            action: ScriptedAction::Weather { weather: "Rain".to_string() },
+            override_stochastic: true,
+        }];
+
+        let mut race = test_support::create_race(&sim_pars, &sim_consts, &tire_config);
+        while !matches!(race.flag_state, FlagState::Red) && !race.get_all_finished() {
+            race.simulate_timestep();
+        }
+
+        // the race is suspended in the step in which the heavy rain starts
+        assert!(matches!(race.flag_state, FlagState::Red));
+        let result = race.get_race_result();
+        let heavy_rain = result.events.iter().find(|ev| ev.kind == "HeavyRain").unwrap();
+        let red_flag = result.events.iter().find(|ev| ev.kind == "RED_FLAG").unwrap();
+        assert_eq!(red_flag.time_s, heavy_rain.time_s);
+        assert!(red_flag.cars.is_empty());
+        assert!(race.get_wetness() >= sim_consts.heavy_rain_wetness);
+
+        // the race is restarted behind the SC after the suspension
+        while matches!(race.flag_state, FlagState::Red) {
+            race.simulate_timestep();
+        }
+        assert!(matches!(race.flag_state, FlagState::Sc));
+    }
+}
+
+#[cfg(test)]
+mod event_subscription_tests {
+    use crate::core::race::Race;
+    use crate::core::scripted_event::{ScriptedAction, ScriptedEvent};
+    use crate::post::race_result::RaceEvent;
+    use crate::test_support;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    type EventKey = (String, u32, f64, Vec<u32>);
+
+    /// create_race creates the test race with scripted events (rain, penalty, safety car) such
+    /// that events also occur during the race and not only at the start.
+    fn create_race() -> Race {
+        let (mut sim_pars, sim_consts, tire_config) = test_support::load_test_pars();
+
+        let actions = vec![
+            (2, ScriptedAction::Weather { weather: "Rain".to_string() }),
+            (3, ScriptedAction::Penalty { car_no: 33, t_penalty: 5.0 }),
+            (4, ScriptedAction::SafetyCar { no_laps: 1 }),
+        ];
+        sim_pars.race_pars.scripted_events = actions
+            .into_iter()
+            .map(|(lap, action)| ScriptedEvent { lap, action, override_stochastic: true })
+            .collect();
+
+        test_support::create_race(&sim_pars, &sim_consts, &tire_config)
+    }
+
+    fn simulate_until_lap(race: &mut Race, lap: u32) {
+        while race.cur_lap_leader < lap {
+            race.simulate_timestep();
+        }
+    }
+
+    /// collect_events subscribes an observer that stores every received event. With replay, the
+    /// observer also receives the events recorded before the subscription.
+    fn collect_events(race: &mut Race, replay: bool) -> Rc<RefCell<Vec<RaceEvent>>> {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let events_observer = Rc::clone(&events);
+        let observer = Box::new(move |event: &RaceEvent| {
+            events_observer.borrow_mut().push(event.clone())
+        });
+        if replay {
+            race.subscribe_with_replay(observer);
+        } else {
+            race.subscribe(observer);
+        }
+        events
+    }
+
+    fn get_keys(events: &[RaceEvent]) -> Vec<EventKey> {
+        events
+            .iter()
+            .map(|event| (event.kind.to_owned(), event.lap, event.time_s, event.cars.to_owned()))
+            .collect()
+    }
+
+    /// get_expected_keys checks that the observed events are the events of the race result and
+    /// returns them in the recording order (the result is sorted by time).
+    fn get_expected_keys(race: &Race, observed: &[RaceEvent]) -> Vec<EventKey> {
+        let result_keys = get_keys(&race.get_race_result().events);
+
+        let mut observed_sorted = observed.to_vec();
+        observed_sorted.sort_by(|a, b| {
+            a.time_s.partial_cmp(&b.time_s).unwrap_or(std::cmp::Ordering::Equal)
+        });
+        assert_eq!(get_keys(&observed_sorted), result_keys);
+
+        get_keys(observed)
+    }
+
+    #[test]
+    fn test_subscribers_see_every_event_once_in_order() {
+        // the events of the start (e.g. start reactions) are recorded when the race is created,
+        // the first subscribers therefore ask for a replay as well
+        let mut race = create_race();
+        let events_a = collect_events(&mut race, true);
+        let events_b = collect_events(&mut race, true);
+        simulate_until_lap(&mut race, 2);
+
+        // a late subscriber only receives the events recorded after the subscription, unless it
+        // asks for a replay of the earlier ones
+        let no_events_before = race.get_race_result().events.len();
+        let events_c = collect_events(&mut race, false);
+        let events_d = collect_events(&mut race, true);
+        simulate_until_lap(&mut race, 7);
+
+        let keys_a = get_expected_keys(&race, &events_a.borrow());
+        assert!(keys_a.iter().any(|key| key.0 == "WeatherRainStart"));
+        assert!(keys_a.iter().any(|key| key.0 == "SC_DEPLOYED"));
+        assert_eq!(get_keys(&events_b.borrow()), keys_a);
+        assert_eq!(get_keys(&events_c.borrow()), keys_a[no_events_before..].to_vec());
+        assert_eq!(get_keys(&events_d.borrow()), keys_a);
+    }
+
+    #[test]
+    fn test_lead_changes_are_observed_live() {
+        let mut race = create_race();
+        let events = collect_events(&mut race, false);
+        while !race.get_all_finished() {
+            race.simulate_timestep();
+        }
+        let result = race.get_race_result();
+
+        // the leader after every lap follows from the race times in the result
+        let mut expected = Vec::new();
+        let mut leader_prev: Option<u32> = None;
+        for lap in 1..result.tot_no_laps + 1 {
+            let leader = match result.get_snapshot(lap) {
+                Some(snapshot) => snapshot.car_nos[0],
+                None => break,
+            };
+            if let Some(leader_prev) = leader_prev {
+                if leader != leader_prev {
+                    expected.push((lap, vec![leader, leader_prev]));
+                }
+            }
+            leader_prev = Some(leader);
+        }
+
+        let observed: Vec<(u32, Vec<u32>)> = events
+            .borrow()
+            .iter()
+            .filter(|event| event.kind == "LeadChange")
+            .map(|event| (event.lap, event.cars.to_owned()))
+            .collect();
+        assert!(!expected.is_empty());
+        assert_eq!(observed, expected);
+    }
+
+    #[test]
+    fn test_panicking_observer_aborts_simulation_but_keeps_event() {
+        let mut race = create_race();
+        let events = collect_events(&mut race, false);
+        race.subscribe(Box::new(|event: &RaceEvent| {
+            assert_ne!(event.kind, "WeatherRainStart", "observer failed");
+        }));
+
+        // the panic of the observer is not caught by the race
+        let outcome = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            simulate_until_lap(&mut race, 3);
+        }));
+        assert!(outcome.is_err());
+
+        // the event was recorded (and passed to the earlier observer) before the panic
+        let kinds_result: Vec<String> =
+            race.get_race_result().events.into_iter().map(|event| event.kind).collect();
+        assert_eq!(kinds_result.last().map(|kind| kind.as_str()), Some("WeatherRainStart"));
+        assert_eq!(events.borrow().last().unwrap().kind, "WeatherRainStart");
+    }
+}
+
+#[cfg(test)]
+mod unexecuted_stop_tests {
+    use crate::core::car::StrategyEntry;
+    use crate::core::race::Race;
+    use crate::core::strategy_controls::StrategyCommand;
+    use crate::post::output_fmt::OutputFormat;
+    use crate::post::race_result::{RaceResult, UnexecutedStop, UnexecutedStopReason};
+    use crate::test_support;
+
+    /// create_race creates the test race shortened to 8 laps, i.e. the planned stop of car 44 in
+    /// lap 13 lies beyond the race end. Car 33 gets a stop in lap 4.
+    fn create_race() -> Race {
+        let (mut sim_pars, sim_consts, tire_config) = test_support::load_test_pars();
+
+        sim_pars.race_pars.tot_no_laps = 8;
+        let car_pars_33 = sim_pars.car_pars_all.get_mut(&33).unwrap();
+        car_pars_33.strategy.push(StrategyEntry {
+            inlap: 4,
+            tire_start_age: 0,
+            compound: "MEDIUM".to_string(),
+            driver_initials: String::new(),
+            tire_set: None,
+            engine_mode: None,
+        });
+
+        test_support::create_race(&sim_pars, &sim_consts, &tire_config)
+    }
+
+    fn finish_race(race: &mut Race) -> RaceResult {
+        while !race.get_all_finished() {
+            race.simulate_timestep();
+        }
+        race.get_race_result()
+    }
+
+    fn get_no_missed_stop_events(result: &RaceResult) -> usize {
+        result.events.iter().filter(|ev| ev.kind == "MissedStop").count()
+    }
+
+    #[test]
+    fn test_stop_beyond_race_end() {
+        let mut race = create_race();
+        let result = finish_race(&mut race);
+
+        assert_eq!(
+            result.unexecuted_stops,
+            vec![UnexecutedStop {
+                car_no: 44,
+                inlap: 13,
+                compound: "SOFT".to_string(),
+                reason: UnexecutedStopReason::RaceEnded,
+            }]
+        );
+        assert_eq!(get_no_missed_stop_events(&result), 0);
+        assert!(result.pit_stops.iter().any(|x| x.car_no == 33 && x.lap == 4));
+        let events_log = result.format_events(&OutputFormat::default()).unwrap();
+        assert!(events_log.contains("Car 44, inlap 13 (SOFT): RaceEnded"));
+    }
+
+    #[test]
+    fn test_cancelled_stop_is_superseded() {
+        let mut race = create_race();
+        race.apply_strategy_command(&StrategyCommand::CancelPit { car_no: 33, inlap: 4 })
+            .unwrap();
+        let result = finish_race(&mut race);
+
+        let stop_33 = result.unexecuted_stops.iter().find(|x| x.car_no == 33).unwrap();
+        assert_eq!(stop_33.inlap, 4);
+        assert_eq!(stop_33.reason, UnexecutedStopReason::Superseded);
+        assert_eq!(get_no_missed_stop_events(&result), 0);
+    }
+
+    #[test]
+    fn test_missed_stop() {
+        let mut race = create_race();
+        let idx = race.get_car_idx(44).unwrap();
+
+        // plan a stop for the lap that was already completed (the pits lie after the finish
+        // line, i.e. the pit box was already passed)
+        while race.cars_list[idx].sh.get_compl_lap() < 2
+            || race.cars_list[idx].sh.get_s_tracks().1 < 3000.0
+        {
+            race.simulate_timestep();
+        }
+        let inlap = race.cars_list[idx].sh.get_compl_lap();
+        race.cars_list[idx].schedule_weather_strategy(inlap, "MEDIUM");
+
+        let result = finish_race(&mut race);
+
+        assert_eq!(get_no_missed_stop_events(&result), 1);
+        assert!(result.unexecuted_stops.contains(&UnexecutedStop {
+            car_no: 44,
+            inlap,
+            compound: "MEDIUM".to_string(),
+            reason: UnexecutedStopReason::Missed,
+        }));
+    }
+}
+
+#[cfg(test)]
+mod yellow_zone_tests {
+    use crate::core::car::RetirementCause;
+    use crate::core::race::Race;
+    use crate::post::race_result::LAP_FLAG_YELLOW;
+    use crate::test_support;
+
+    /// create_race creates the test race and simulates it until car 44 is on the straight between
+    /// the corners at 2940 m and 3920 m in lap 3. Returns the race and the position of car 44.
+    fn create_race(yellow_zone_length_m: f64) -> (Race, f64) {
+        let (mut sim_pars, mut sim_consts, tire_config) = test_support::load_test_pars();
+        sim_consts.yellow_zone_length_m = yellow_zone_length_m;
+        sim_consts.yellow_zone_no_laps = 2;
+        // no contacts in the duels, such that car 33 stays close to car 44
+        for driver_pars in sim_pars.driver_pars_all.values_mut() {
+            driver_pars.aggression = 0.0;
+        }
+
+        let mut race = test_support::create_race(&sim_pars, &sim_consts, &tire_config);
+        let idx = race.get_car_idx(44).unwrap();
+
+        while race.cars_list[idx].sh.get_compl_lap() < 2
+            || !(3200.0..3600.0).contains(&race.cars_list[idx].sh.get_s_tracks().1)
+        {
+            race.simulate_timestep();
+        }
+        let s_track = race.cars_list[idx].sh.get_s_tracks().1;
+
+        (race, s_track)
+    }
+
+    #[test]
+    fn test_yellow_zone_around_stopped_car() {
+        let (mut race, s_track) = create_race(300.0);
+        let idx = race.get_car_idx(44).unwrap();
+
+        race.retire_cars(&[idx], RetirementCause::Crash);
+        race.simulate_timestep();
+        let lap_start = race.cur_lap_leader;
+
+        let s_range = [s_track - 150.0, s_track + 150.0];
+        let s_ranges: Vec<[f64; 2]> = race.get_yellow_zones().iter().map(|x| x.s_range).collect();
+        assert_eq!(s_ranges, vec![s_range]);
+
+        // the zone is cleared after the configured number of leader laps
+        race.simulate_until_leader_lap(lap_start + 1);
+        assert!(race.get_yellow_zones().is_empty());
+
+        let result = race.get_race_result();
+        let yellow_events: Vec<_> = result
+            .events
+            .iter()
+            .filter(|ev| ev.kind.starts_with("YELLOW"))
+            .map(|ev| (ev.kind.as_str(), ev.lap, ev.cars.to_owned(), ev.s_range))
+            .collect();
+        assert_eq!(
+            yellow_events,
+            vec![
+                ("YELLOW_START", lap_start, vec![44], Some(s_range)),
+                ("YELLOW_END", lap_start + 2, vec![44], Some(s_range)),
+            ]
+        );
+
+        // car 33 passed the zone and its lap is flagged as a yellow flag lap
+        let idx_33 = race.get_car_idx(33).unwrap();
+        assert!((1..=lap_start as usize + 1)
+            .any(|lap| result.get_lap_flags(idx_33, lap) & LAP_FLAG_YELLOW != 0));
+    }
+
+    #[test]
+    fn test_yellow_zone_disabled() {
+        let (mut race, _) = create_race(0.0);
+        let idx = race.get_car_idx(44).unwrap();
+
+        race.retire_cars(&[idx], RetirementCause::Crash);
+        race.simulate_timestep();
+
+        assert!(race.get_yellow_zones().is_empty());
+        assert!(!race.get_race_result().events.iter().any(|ev| ev.kind == "YELLOW_START"));
+    }
+}
+
+#[cfg(test)]
+mod participant_selection_tests {
+    use crate::core::car::CarStatus;
+    use crate::core::race::SimConstants;
+    use crate::core::scripted_event::{ScriptedAction, ScriptedEvent};
+    use crate::core::tireset::TireConfig;
+    use crate::pre::read_sim_pars::SimPars;
+    use crate::pre::sim_opts::Withdrawal;
+    use crate::test_support;
+
+    fn get_sim_pars() -> SimPars {
+        serde_json::from_str(include_str!("../../input/parameters/test_race.json")).unwrap()
+    }
+
+    #[test]
+    fn test_select_participants() {
+        let mut sim_pars = get_sim_pars();
+        sim_pars.race_pars.scripted_events = vec![ScriptedEvent {
+            lap: 10,
+            action: ScriptedAction::Dnf { car_no: 44 },
+            override_stochastic: true,
+        }];
+
+        sim_pars.select_participants(&[], &[44]).unwrap();
+
+        // the grid is re-packed and the scripted events of removed cars are dropped
+        assert_eq!(sim_pars.race_pars.participants, vec![33]);
+        assert_eq!(sim_pars.car_pars_all[&33].p_grid, 1);
+        assert!(sim_pars.race_pars.scripted_events.is_empty());
+        assert!(sim_pars.validate().errors.is_empty());
+
+        let mut sim_pars = get_sim_pars();
+        sim_pars.select_participants(&[33, 44], &[]).unwrap();
+        assert_eq!(sim_pars.race_pars.participants, vec![44, 33]);
+        assert_eq!(sim_pars.car_pars_all[&44].p_grid, 1);
+        assert_eq!(sim_pars.car_pars_all[&33].p_grid, 2);
+    }
+
+    #[test]
+    fn test_select_unknown_or_all_cars() {
+        let mut sim_pars = get_sim_pars();
+        let err = sim_pars.select_participants(&[44, 24], &[]).unwrap_err();
+        assert!(err.to_string().contains("valid car numbers: 44, 33"));
+        assert_eq!(sim_pars.race_pars.participants, vec![44, 33]);
+
+        assert!(sim_pars.select_participants(&[44], &[44]).is_err());
+        assert!(sim_pars.withdraw_car(24, 0).is_err());
+    }
+
+    #[test]
+    fn test_parse_withdrawal() {
+        assert_eq!("24:lap0".parse::<Withdrawal>(), Ok(Withdrawal { car_no: 24, lap: 0 }));
+        assert_eq!("24:12".parse::<Withdrawal>(), Ok(Withdrawal { car_no: 24, lap: 12 }));
+        assert!("24".parse::<Withdrawal>().is_err());
+        assert!("24:lapX".parse::<Withdrawal>().is_err());
+    }
+
+    #[test]
+    fn test_withdraw_car_at_start() {
+        let mut sim_pars = get_sim_pars();
+        let mut sim_consts: SimConstants =
+            serde_json::from_str(include_str!("../../input/parameters/sim_constants.json")).unwrap();
+        let tire_config: TireConfig =
+            serde_json::from_str(include_str!("../../input/parameters/tires.json")).unwrap();
+        sim_consts.failure_rate_per_hour = 0.0;
+        sim_consts.collision_factor = 0.0;
+
+        sim_pars.withdraw_car(33, 0).unwrap();
+        assert!(sim_pars.validate().errors.is_empty());
+
+        let mut race = test_support::create_race(&sim_pars, &sim_consts, &tire_config);
+        race.simulate_timestep();
+
+        // the car is part of the grid, but retires right after the start
+        let idx = race.get_car_idx(33).unwrap();
+        assert_eq!(race.cars_list[idx].status, CarStatus::DNF);
+
+        let result = race.get_race_result();
+        assert!(result
+            .events
+            .iter()
+            .any(|ev| ev.kind == "Retirement" && ev.cars == vec![33] && ev.scripted));
+    }
+}
+
+#[cfg(test)]
+mod sc_decision_tests {
+    use crate::core::car::RetirementCause;
+    use crate::core::race::{FlagState, Race, ScDeploymentPars};
+    use crate::post::race_result::{IncidentLocation, ScDecision, ScOutcome};
+    use crate::test_support;
+
+    /// create_race creates the test race with the given SC probability (equal at every location)
+    /// and simulates it until lap 3, such that car 44 can retire outside the pit lane.
+    fn create_race(sc_probability: f64) -> Race {
+        let (sim_pars, mut sim_consts, tire_config) = test_support::load_test_pars();
+        sim_consts.sc_probability = sc_probability;
+        sim_consts.sc_corner_factor = 1.0;
+        sim_consts.sc_overtaking_zone_factor = 1.0;
+
+        let mut race = test_support::create_race(&sim_pars, &sim_consts, &tire_config);
+        let idx_retire = race.get_car_idx(44).unwrap();
+
+        while race.cur_lap_leader < 3 || race.cars_list[idx_retire].sh.pit_act {
+            race.simulate_timestep();
+        }
+
+        race
+    }
+
+    /// retire_car retires car 44 and returns the SC decisions after a few more timesteps.
+    fn retire_car(race: &mut Race) -> Vec<ScDecision> {
+        let idx_retire = race.get_car_idx(44).unwrap();
+        race.retire_cars(&[idx_retire], RetirementCause::Crash);
+
+        for _ in 0..10 {
+            race.simulate_timestep();
+        }
+
+        race.get_race_result()
+            .events
+            .iter()
+            .filter(|ev| ev.kind == "SC_DECISION")
+            .map(|ev| ev.sc_decision.unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_sc_probability_by_location() {
+        let pars = ScDeploymentPars {
+            probability: 0.8,
+            corner_factor: 1.5,
+            overtaking_zone_factor: 0.5,
+        };
+
+        assert_eq!(pars.get_probability(IncidentLocation::Corner), 1.0);
+        assert!((pars.get_probability(IncidentLocation::OvertakingZone) - 0.4).abs() < 1e-9);
+        assert_eq!(pars.get_probability(IncidentLocation::Other), 0.8);
+    }
+
+    #[test]
+    fn test_sc_deployed() {
+        let mut race = create_race(1.0);
+        let sc_decisions = retire_car(&mut race);
+
+        assert_eq!(sc_decisions.len(), 1);
+        assert_eq!(sc_decisions[0].probability, 1.0);
+        assert_eq!(sc_decisions[0].outcome, ScOutcome::Sc);
+        assert!(matches!(race.flag_state, FlagState::Sc));
+    }
+
+    #[test]
+    fn test_vsc_or_local_yellow_instead_of_sc() {
+        let mut race = create_race(0.0);
+        let sc_decisions = retire_car(&mut race);
+
+        // a single decision, no SC is deployed afterwards
+        assert_eq!(sc_decisions.len(), 1);
+        assert_eq!(sc_decisions[0].probability, 0.0);
+
+        if sc_decisions[0].location == IncidentLocation::Corner {
+            assert_eq!(sc_decisions[0].outcome, ScOutcome::Vsc);
+            assert!(matches!(race.flag_state, FlagState::Vsc));
+        } else {
+            assert_eq!(sc_decisions[0].outcome, ScOutcome::LocalYellow);
+            assert!(matches!(race.flag_state, FlagState::G));
+            assert_eq!(race.get_yellow_zones().len(), 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod sc_length_tests {
+    use crate::core::car::RetirementCause;
+    use crate::core::race::Race;
+    use crate::test_support;
+
+    /// get_sc_length retires car 44 of the seeded test race in lap 3 (SC guaranteed) and returns
+    /// the time between the deployment of the SC and its return to the pit lane.
+    fn get_sc_length(clearance_mean_s: f64, seed: u64) -> f64 {
+        let (mut sim_pars, mut sim_consts, tire_config) = test_support::load_test_pars();
+        sim_pars.race_pars.seed = Some(seed);
+        sim_pars.race_pars.vsc_probability = 0.0;
+        sim_pars.race_pars.red_flag_probability = 0.0;
+        sim_consts.sc_probability = 1.0;
+        sim_consts.sc_corner_factor = 1.0;
+        sim_consts.sc_overtaking_zone_factor = 1.0;
+        sim_consts.clearance_mean_s = clearance_mean_s;
+
+        let mut race =
+            test_support::create_race_with_timestep(&sim_pars, &sim_consts, &tire_config, 0.2);
+        let idx_retire = race.get_car_idx(44).unwrap();
+
+        while race.cur_lap_leader < 3 || race.cars_list[idx_retire].sh.pit_act {
+            race.simulate_timestep();
+        }
+        race.retire_cars(&[idx_retire], RetirementCause::Crash);
+
+        let get_event_time = |race: &Race, kind: &str| {
+            race.get_race_result().events.iter().find(|ev| ev.kind == kind).map(|ev| ev.time_s)
+        };
+
+        while get_event_time(&race, "SC_IN").is_none() && !race.get_all_finished() {
+            race.simulate_timestep();
+        }
+
+        get_event_time(&race, "SC_IN").unwrap() - get_event_time(&race, "SC_DEPLOYED").unwrap()
+    }
+
+    #[test]
+    fn test_sc_length_distribution() {
+        let sc_lengths_short: Vec<f64> = (0..8).map(|seed| get_sc_length(30.0, seed)).collect();
+        let sc_lengths_long: Vec<f64> = (0..8).map(|seed| get_sc_length(240.0, seed)).collect();
+
+        // the drawn clearance times spread the SC lengths over more than one lap
+        let min = sc_lengths_long.iter().cloned().fold(f64::MAX, f64::min);
+        let max = sc_lengths_long.iter().cloned().fold(f64::MIN, f64::max);
+        assert!(max - min > 60.0, "{:?}", sc_lengths_long);
+
+        // every SC lasts at least the bunching lap, a longer clearance never shortens the SC (same
+        // seed: same incident location) and keeps it out longer on average
+        for (sc_length_short, sc_length_long) in sc_lengths_short.iter().zip(sc_lengths_long.iter()) {
+            assert!(*sc_length_short > 60.0);
+            assert!(sc_length_long >= sc_length_short);
+        }
+        let mean = |sc_lengths: &[f64]| sc_lengths.iter().sum::<f64>() / sc_lengths.len() as f64;
+        assert!(mean(&sc_lengths_long) > mean(&sc_lengths_short) + 100.0);
+    }
+}
+
+#[cfg(test)]
+mod pit_closure_tests {
+    use crate::core::car::RetirementCause;
+    use crate::core::race::{FlagState, Race};
+    use crate::test_support;
+
+    /// create_race creates the test race (car 44 plans its stop at the end of lap 13) and
+    /// simulates it until car 44 reaches the given position in lap 13.
+    fn create_race(pit_closed_on_sc_deploy: bool, until: impl Fn(&Race, usize) -> bool) -> Race {
+        let (mut sim_pars, mut sim_consts, tire_config) = test_support::load_test_pars();
+        sim_consts.sc_probability = 1.0;
+        sim_consts.sc_overtaking_zone_factor = 1.0;
+        sim_pars.race_pars.pit_closed_on_sc_deploy = pit_closed_on_sc_deploy;
+        // no contacts in the duels, such that car 44 stays on the lead lap
+        for driver_pars in sim_pars.driver_pars_all.values_mut() {
+            driver_pars.aggression = 0.0;
+        }
+
+        let mut race = test_support::create_race(&sim_pars, &sim_consts, &tire_config);
+        let idx = race.get_car_idx(44).unwrap();
+
+        while race.cars_list[idx].sh.get_compl_lap() < 12 || !until(&race, idx) {
+            race.simulate_timestep();
+        }
+
+        race
+    }
+
+    /// deploy_sc retires car 33 (the SC comes out in the next timestep) and simulates until car 44
+    /// completed the given lap. It returns the leader lap in which the SC came out, the inlaps of
+    /// car 44 and the number of PIT_CLOSED events.
+    fn deploy_sc(race: &mut Race, compl_lap: u32) -> (u32, Vec<u32>, usize) {
+        let idx = race.get_car_idx(44).unwrap();
+        let idx_retire = race.get_car_idx(33).unwrap();
+        race.retire_cars(&[idx_retire], RetirementCause::Crash);
+
+        race.simulate_timestep();
+        assert!(matches!(race.flag_state, FlagState::Sc));
+        let lap_sc = race.cur_lap_leader;
+
+        while race.cars_list[idx].sh.get_compl_lap() < compl_lap {
+            race.simulate_timestep();
+        }
+
+        let inlaps = race
+            .get_pit_stops()
+            .iter()
+            .filter(|pit_stop| pit_stop.car_no == 44)
+            .map(|pit_stop| pit_stop.lap)
+            .collect();
+        let events = race.get_race_result().events;
+        let events_closed: Vec<_> = events.iter().filter(|ev| ev.kind == "PIT_CLOSED").collect();
+        assert!(events_closed.iter().all(|ev| ev.cars == vec![44]));
+        assert!(!events.iter().any(|ev| ev.kind == "MissedStop"));
+
+        (lap_sc, inlaps, events_closed.len())
+    }
+
+    #[test]
+    fn test_pit_stop_postponed_until_reopened() {
+        let before_pit_entry = |race: &Race, idx: usize| {
+            (5300.0..5500.0).contains(&race.cars_list[idx].sh.get_s_tracks().1)
+        };
+
+        // the pit is closed until the end of the first full leader lap behind the SC, the stop is
+        // postponed lap by lap
+        let mut race = create_race(true, before_pit_entry);
+        let (lap_sc, inlaps, no_closed) = deploy_sc(&mut race, 17);
+        assert_eq!(inlaps, vec![lap_sc + 2]);
+        assert_eq!(no_closed as u32, lap_sc + 2 - 13);
+
+        // without the option the car pits immediately
+        let mut race = create_race(false, before_pit_entry);
+        let (_, inlaps, no_closed) = deploy_sc(&mut race, 17);
+        assert_eq!(inlaps, vec![13]);
+        assert_eq!(no_closed, 0);
+    }
+
+    #[test]
+    fn test_sc_deployed_as_car_crosses_pit_entry() {
+        // car 44 crossed the pit entry in the last timestep, the SC comes out before the state
+        // transition is handled -> the car already committed to the stop
+        let mut race = create_race(true, |race: &Race, idx: usize| {
+            let pit_entry = race.track.pit_zone[0];
+            race.cars_list[idx].sh.get_s_track_passed_this_step(pit_entry)
+        });
+        let idx = race.get_car_idx(44).unwrap();
+        assert!(!race.cars_list[idx].sh.pit_act);
+
+        let (_, inlaps, no_closed) = deploy_sc(&mut race, 14);
+        assert_eq!(inlaps, vec![13]);
+        assert_eq!(no_closed, 0);
+    }
+}
+
+#[cfg(test)]
+mod sc_pit_window_tests {
+    use crate::core::car::RetirementCause;
+    use crate::core::race::FlagState;
+    use crate::test_support;
+
+    /// run_race creates the test race (car 44 plans its stop at the end of lap 13), deploys the SC
+    /// in lap 11 of car 44 by retiring car 33 and simulates until car 44 completed lap 14. It
+    /// returns the inlaps of car 44 and the number of PIT_UNDER_SC events.
+    fn run_race(sc_pit_window_laps: Option<u32>) -> (Vec<u32>, usize) {
+        let (mut sim_pars, mut sim_consts, tire_config) = test_support::load_test_pars();
+        sim_consts.sc_probability = 1.0;
+        sim_consts.sc_overtaking_zone_factor = 1.0;
+        sim_pars.race_pars.sc_pit_window_laps = sc_pit_window_laps;
+
+        let mut race = test_support::create_race(&sim_pars, &sim_consts, &tire_config);
+        let idx = race.get_car_idx(44).unwrap();
+
+        while race.cars_list[idx].sh.get_compl_lap() < 10
+            || race.cars_list[idx].sh.get_s_tracks().1 < 1000.0
+        {
+            race.simulate_timestep();
+        }
+
+        let idx_retire = race.get_car_idx(33).unwrap();
+        race.retire_cars(&[idx_retire], RetirementCause::Crash);
+        race.simulate_timestep();
+        assert!(matches!(race.flag_state, FlagState::Sc));
+
+        let laptime_pit = race.track.length / race.track.pit_speedlimit
+            * race.track.real_length_pit_zone
+            / race.track.track_length_pit_zone;
+
+        while race.cars_list[idx].sh.get_compl_lap() < 14 {
+            let sh = &race.cars_list[idx].sh;
+            let pit_transit =
+                matches!(race.flag_state, FlagState::Sc) && sh.pit_act && !sh.pit_standstill_act;
+            race.simulate_timestep();
+
+            // the pit lane transit is not slowed down a second time by the SC
+            if pit_transit {
+                assert!((race.cur_laptimes[idx] - laptime_pit).abs() < 1e-6);
+            }
+        }
+
+        let inlaps = race
+            .get_pit_stops()
+            .iter()
+            .filter(|pit_stop| pit_stop.car_no == 44)
+            .map(|pit_stop| pit_stop.lap)
+            .collect();
+        let events = race.get_race_result().events;
+        let events_pit_sc: Vec<_> = events.iter().filter(|ev| ev.kind == "PIT_UNDER_SC").collect();
+        assert!(events_pit_sc.iter().all(|ev| ev.cars == vec![44]));
+        assert!(!events.iter().any(|ev| ev.kind == "MissedStop"));
+
+        (inlaps, events_pit_sc.len())
+    }
+
+    #[test]
+    fn test_pit_stop_moved_under_sc() {
+        // the stop planned within the window is taken in the SC lap
+        let (inlaps, no_pit_sc) = run_race(Some(3));
+        assert_eq!(inlaps, vec![11]);
+        assert_eq!(no_pit_sc, 1);
+
+        // stops planned later than the window and without the option stay as planned
+        for sc_pit_window_laps in [Some(1), None] {
+            let (inlaps, no_pit_sc) = run_race(sc_pit_window_laps);
+            assert_eq!(inlaps, vec![13]);
+            assert_eq!(no_pit_sc, 0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod live_gap_tests {
+    use crate::core::race::{Race, TIMING_HISTORY_LEN};
+    use crate::test_support;
+
+    fn create_race() -> Race {
+        let (mut sim_pars, sim_consts, tire_config) = test_support::load_test_pars();
+        // no contacts in the duels, such that the gap between the cars stays within the timing
+        // history
+        for driver_pars in sim_pars.driver_pars_all.values_mut() {
+            driver_pars.aggression = 0.0;
+        }
+
+        test_support::create_race(&sim_pars, &sim_consts, &tire_config)
+    }
+
+    #[test]
+    fn test_live_gap_matches_racetimes_at_lap_completion() {
+        let mut race = create_race();
+        let mut no_checks = 0;
+        let mut no_laps_out_of_history = 0;
+
+        while !race.get_all_finished() {
+            race.simulate_timestep();
+
+            for idx_rear in 0..race.cars_list.len() {
+                let compl_lap = race.cars_list[idx_rear].sh.get_compl_lap();
+                if !race.cars_list[idx_rear].sh.get_new_lap() || compl_lap > race.tot_no_laps {
+                    continue;
+                }
+
+                for idx_front in 0..race.cars_list.len() {
+                    // the car ahead must have completed the lap before (or in this timestep)
+                    if idx_front == idx_rear
+                        || race.cars_list[idx_front].sh.get_race_prog()
+                            < race.cars_list[idx_rear].sh.get_race_prog()
+                    {
+                        continue;
+                    }
+
+                    // no live gap to a car that is more laps ahead than the timing history covers
+                    let live_gap = match race.get_live_gap(idx_front, idx_rear) {
+                        Some(x) => x,
+                        None => {
+                            let laps_ahead = race.cars_list[idx_front].sh.get_race_prog()
+                                - race.cars_list[idx_rear].sh.get_race_prog();
+                            assert!(laps_ahead > (TIMING_HISTORY_LEN / 3 - 1) as f64);
+                            no_laps_out_of_history += 1;
+                            continue;
+                        }
+                    };
+                    let racetimes_gap = race.racetimes[idx_rear][compl_lap as usize]
+                        - race.racetimes[idx_front][compl_lap as usize];
+                    // both are interpolated in the timestep in the same way (also on a track with
+                    // varying speed multipliers)
+                    assert!(
+                        (live_gap - racetimes_gap).abs() < 1e-6,
+                        "Live gap {} differs from the racetimes gap {} in lap {}!",
+                        live_gap,
+                        racetimes_gap,
+                        compl_lap
+                    );
+                    no_checks += 1;
+                }
+            }
+        }
+
+        // a check in every lap completed by the car behind (it can be laps down) while the car
+        // ahead is within the timing history
+        let no_laps_rear = race
+            .cars_list
+            .iter()
+            .map(|car| car.sh.get_compl_lap().min(race.tot_no_laps))
+            .min()
+            .unwrap();
+        assert!(no_checks + no_laps_out_of_history >= no_laps_rear as usize);
+        assert!(no_checks > no_laps_out_of_history);
+    }
+
+    #[test]
+    fn test_interval_ahead_between_timing_points() {
+        let mut race = create_race();
+
+        // simulate until both cars are in the middle of lap 5
+        while race.cars_list.iter().any(|car| car.sh.get_race_prog() < 4.5) {
+            race.simulate_timestep();
+        }
+
+        let intervals_ahead = race.get_intervals_ahead();
+        let race_progs: Vec<f64> =
+            race.cars_list.iter().map(|car| car.sh.get_race_prog()).collect();
+        let idx_leader = if race_progs[0] >= race_progs[1] { 0 } else { 1 };
+        let idx_second = 1 - idx_leader;
+
+        assert!(intervals_ahead[idx_leader].is_none());
+        assert!(intervals_ahead[idx_second].is_some());
+        assert_eq!(intervals_ahead[idx_second], race.get_live_gap(idx_leader, idx_second));
+    }
+}
+
+#[cfg(test)]
+mod history_budget_tests {
+    use crate::core::handle_race::record_race;
+    use crate::post::history_config::HistoryConfig;
+    use crate::post::race_recording::read_race_recording;
+    use crate::test_support;
+
+    #[test]
+    fn test_recording_stays_within_memory_budget() {
+        // full race with a fine timestep and a frame in every timestep
+        let (mut sim_pars, sim_consts, tire_config) = test_support::load_test_pars();
+
+        // no contacts in the duels, such that repair stops do not extend the race (and its
+        // histories) beyond the memory budget
+        for driver_pars in sim_pars.driver_pars_all.values_mut() {
+            driver_pars.aggression = 0.0;
+        }
+        let timestep_size = 0.02;
+        let history_config = HistoryConfig {
+            recording_frame_dt: timestep_size,
+            max_memory_bytes: 200 * 1024,
+            recording_spill_path: None,
+        };
+
+        let (result, recording) = record_race(
+            &sim_pars,
+            &sim_consts,
+            &tire_config,
+            timestep_size,
+            &history_config,
+            false,
+        )
+        .unwrap();
+
+        let bytes_frames: usize = recording.frames.iter().map(|x| x.estimate_bytes()).sum();
+        assert!(bytes_frames <= history_config.max_memory_bytes);
+        assert!(recording.frame_dt > timestep_size);
+        assert!(!recording.frames.is_empty());
+
+        // the decimated recording still covers the whole race with a constant frame time step
+        let t_race_end = result.racetimes.iter().map(|x| *x.last().unwrap()).fold(0.0, f64::max);
+        assert!(recording.get_duration() > t_race_end - recording.frame_dt - timestep_size);
+        for frames in recording.frames.windows(2) {
+            assert!((frames[1].t - frames[0].t - recording.frame_dt).abs() < 1.5 * timestep_size);
+        }
+    }
+
+    #[test]
+    fn test_recording_spills_frames_to_file() {
+        let (mut sim_pars, sim_consts, tire_config) = test_support::load_test_pars();
+        sim_pars.race_pars.tot_no_laps = 5;
+        let dir = std::env::temp_dir().join(format!("racesim_spill_{}", std::process::id()));
+        let history_config = HistoryConfig {
+            recording_frame_dt: 0.1,
+            max_memory_bytes: 50 * 1024,
+            recording_spill_path: Some(dir.join("frames.jsonl")),
+        };
+
+        let (_, mut recording) =
+            record_race(&sim_pars, &sim_consts, &tire_config, 0.1, &history_config, false)
+                .unwrap();
+
+        // no decimation, the frames beyond the budget were spilled
+        assert_eq!(recording.frame_dt, 0.1);
+        assert!(recording.no_spilled_frames > 0);
+        let no_frames = recording.no_spilled_frames + recording.frames.len();
+
+        // the written recording contains the spilled frames in front of the frames in memory
+        let path = dir.join("recording.json");
+        assert_eq!(recording.write_to_file(&path).unwrap(), 0);
+        let recording_read = read_race_recording(&path).unwrap();
+        assert_eq!(recording_read.frames.len(), no_frames);
+        assert_eq!(recording_read.cars.len(), recording.cars.len());
+        assert_eq!(recording_read.frame_dt, recording.frame_dt);
+        assert_eq!(recording_read.tot_no_laps, recording.tot_no_laps);
+        assert!((recording_read.frames.last().unwrap().t - recording.get_duration()).abs() < 1e-9);
+
+        // a truncated last line of the spill file (aborted run) drops only that frame
+        let spill_path = dir.join("frames.jsonl");
+        let spilled = std::fs::read_to_string(&spill_path).unwrap();
+        std::fs::write(&spill_path, &spilled[..spilled.len() - 10]).unwrap();
+        let path_truncated = dir.join("recording_truncated.json");
+        assert_eq!(recording.write_to_file(&path_truncated).unwrap(), 1);
+        let recording_truncated = read_race_recording(&path_truncated).unwrap();
+        assert_eq!(recording_truncated.frames.len(), no_frames - 1);
+        std::fs::write(&spill_path, &spilled).unwrap();
+
+        recording.load_spilled_frames().unwrap();
+        assert_eq!(recording.frames.len(), no_frames);
+        for (frame, frame_read) in recording.frames.iter().zip(recording_read.frames.iter()) {
+            assert!((frame.t - frame_read.t).abs() < 1e-9);
+            for (race_prog, race_prog_read) in frame.race_progs.iter().zip(&frame_read.race_progs) {
+                assert!((race_prog - race_prog_read).abs() < 1e-9);
+            }
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_recording_without_positive_frame_dt_rejected() {
+        let (mut sim_pars, sim_consts, tire_config) = test_support::load_test_pars();
+        sim_pars.race_pars.tot_no_laps = 2;
+
+        for &frame_dt in [0.0, -0.5, f64::NAN].iter() {
+            let history_config = HistoryConfig {
+                recording_frame_dt: frame_dt,
+                ..HistoryConfig::default()
+            };
+            assert!(
+                record_race(&sim_pars, &sim_consts, &tire_config, 0.1, &history_config, false)
+                    .is_err()
+            );
+        }
+
+        // a recording file with a frame time step of 0 cannot be read (and thus not rendered)
+        let (_, mut recording) = record_race(
+            &sim_pars,
+            &sim_consts,
+            &tire_config,
+            0.1,
+            &HistoryConfig::default(),
+            false,
+        )
+        .unwrap();
+        assert!(recording.validate().is_ok());
+        recording.frame_dt = 0.0;
+        assert!(recording.validate().is_err());
+
+        let dir = std::env::temp_dir().join(format!("racesim_frame_dt_{}", std::process::id()));
+        let path = dir.join("recording.json");
+        recording.write_to_file(&path).unwrap();
+        let recording_read = read_race_recording(&path);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(recording_read.is_err());
+    }
+}
+
+#[cfg(test)]
+mod debris_yellow_tests {
+    use crate::core::race::{FlagState, Race};
+    use crate::test_support;
+
+    fn create_race(debris_yellow_probability: f64) -> Race {
+        let (sim_pars, mut sim_consts, tire_config) = test_support::load_test_pars();
+        sim_consts.debris_yellow_probability = debris_yellow_probability;
+        sim_consts.debris_yellow_no_laps = [1, 1];
+        // only the random incidents create debris
+        sim_consts.debris_probability = 0.0;
+
+        test_support::create_race(&sim_pars, &sim_consts, &tire_config)
+    }
+
+    fn get_debris_events(race: &Race) -> Vec<(String, u32, Vec<u32>)> {
+        race.get_race_result()
+            .events
+            .iter()
+            .filter(|ev| matches!(ev.kind.as_str(), "DEBRIS_START" | "DEBRIS_END"))
+            .map(|ev| (ev.kind.to_owned(), ev.lap, ev.cars.to_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn test_debris_incident_creates_debris_zone() {
+        let mut race = create_race(1.0);
+
+        race.simulate_until_leader_lap(1);
+        assert_eq!(race.get_debris_zones().len(), 1);
+        let debris_zone = race.get_debris_zones()[0].clone();
+        assert!(debris_zone.cars.is_empty());
+        assert_eq!(debris_zone.lap_cleared, race.cur_lap_leader + 1);
+        assert!(matches!(race.flag_state, FlagState::G));
+
+        // the sectors covered by the debris zone show a yellow flag, the rest stays green
+        let sector_flag_states = race.get_sector_flag_states();
+        for s_track in debris_zone.s_range {
+            assert_eq!(sector_flag_states[race.track.get_sector(s_track)], FlagState::Y);
+        }
+        assert!(sector_flag_states.contains(&FlagState::G));
+        assert_eq!(race.get_most_severe_flag_state(), FlagState::Y);
+
+        // the zone is cleared after one lap and a new incident is drawn on the same lap
+        race.simulate_until_leader_lap(3);
+        let mut debris_events = get_debris_events(&race);
+        assert!(debris_events.iter().all(|(_, _, cars)| cars.is_empty()));
+        debris_events.sort();
+        let kinds_laps: Vec<(&str, u32)> = debris_events
+            .iter()
+            .map(|(kind, lap, _)| (kind.as_str(), *lap))
+            .collect();
+        assert_eq!(
+            kinds_laps,
+            vec![
+                ("DEBRIS_END", 3),
+                ("DEBRIS_END", 4),
+                ("DEBRIS_START", 2),
+                ("DEBRIS_START", 3),
+                ("DEBRIS_START", 4),
+            ]
+        );
+
+        // no escalation to the SC
+        let result = race.get_race_result();
+        assert!(result.events.iter().all(|ev| ev.kind != "SC_DEPLOYED"));
+    }
+
+    #[test]
+    fn test_global_flag_state_covers_all_sectors() {
+        let mut race = create_race(1.0);
+
+        race.simulate_until_leader_lap(1);
+        assert!(race.get_sector_flag_states().contains(&FlagState::G));
+
+        race.flag_state = FlagState::Sc;
+        assert_eq!(race.get_sector_flag_states(), [FlagState::Sc; 3]);
+        assert_eq!(race.get_most_severe_flag_state(), FlagState::Sc);
+    }
+
+    #[test]
+    fn test_debris_yellow_disabled() {
+        let mut race = create_race(0.0);
+
+        race.simulate_until_leader_lap(5);
+        assert!(get_debris_events(&race).is_empty());
+        assert_eq!(race.get_sector_flag_states(), [FlagState::G; 3]);
+    }
+}
+
+#[cfg(test)]
+mod sc_queue_tests {
+    use crate::core::race::{calc_sc_target_laptime, FlagState};
+    use crate::test_support;
+
+    #[test]
+    fn test_sc_target_laptime() {
+        let calc_laptime = |gap| calc_sc_target_laptime(gap, 15.0, 0.5, 50.0, 5000.0, 90.0);
+
+        // in the queue the car holds the SC speed
+        assert!((calc_laptime(15.0) - 100.0).abs() < 1e-9);
+        // far behind the car is limited to the SC delta, too close it slows down
+        assert!((calc_laptime(3000.0) - 90.0).abs() < 1e-9);
+        assert!(calc_laptime(5.0) > 100.0);
+    }
+
+    #[test]
+    fn test_sc_queue_converges_to_target_gap() {
+        let (mut sim_pars, sim_consts, tire_config) = test_support::load_test_pars();
+        // no contacts (and no repair stops) while the cars run close together
+        for driver_pars in sim_pars.driver_pars_all.values_mut() {
+            driver_pars.aggression = 0.0;
+        }
+
+        let mut race = test_support::create_race(&sim_pars, &sim_consts, &tire_config);
+
+        // simulate until both cars are in the middle of lap 5, then put the second car half a lap
+        // behind the leader
+        while race.cars_list.iter().any(|car| car.sh.get_race_prog() < 4.6) {
+            race.simulate_timestep();
+        }
+        let race_progs: Vec<f64> =
+            race.cars_list.iter().map(|car| car.sh.get_race_prog()).collect();
+        let idx_leader = if race_progs[0] >= race_progs[1] { 0 } else { 1 };
+        let idx_rear = 1 - idx_leader;
+        race.cars_list[idx_rear].sh.place_race_prog(race_progs[idx_leader] - 0.5);
+
+        // deploy the SC
+        race.flag_state = FlagState::Sc;
+        race.sc_timer = f64::INFINITY;
+        let t_sc_start = race.cur_racetime;
+
+        let laptime_sc_delta =
+            (race.track.t_q + race.track.t_gap_racepace) * sim_consts.sc_delta_factor;
+        let target_gap = sim_consts.sc_target_gap_m;
+        let tolerance = 5.0;
+        let mut t_lineup = None;
+
+        while matches!(race.flag_state, FlagState::Sc) && race.cur_racetime < 2000.0 {
+            race.simulate_timestep();
+
+            // the rear car closes the gap no faster than the SC delta
+            if !race.cars_list[idx_rear].sh.pit_act {
+                assert!(race.cur_laptimes[idx_rear] >= laptime_sc_delta - 1e-9);
+            }
+
+            let gap = (race.cars_list[idx_leader].sh.get_race_prog()
+                - race.cars_list[idx_rear].sh.get_race_prog())
+                * race.track.length;
+
+            // once lined up, the queue holds station until the SC comes in
+            if t_lineup.is_some() {
+                assert!((gap - target_gap).abs() <= tolerance, "Gap {} m in the queue!", gap);
+            } else if (gap - target_gap).abs() <= tolerance {
+                t_lineup = Some(race.cur_racetime);
+            }
+        }
+
+        // the queue formed gradually (the rear car is at most at the SC delta speed, the leader at
+        // least at 10 m/s) and the SC came in afterwards
+        let t_catchup_min = (0.5 * race.track.length - target_gap - tolerance)
+            / (race.track.length / laptime_sc_delta - 10.0);
+        assert!(t_lineup.unwrap() - t_sc_start > t_catchup_min);
+        assert!(matches!(race.flag_state, FlagState::G));
+    }
+}
+
+#[cfg(test)]
+mod rolling_restart_tests {
+    use crate::core::handle_race::run_race;
+    use crate::core::race::{get_restart_s_track, FlagState, Race, RestartLine};
+    use crate::test_support;
+
+    #[test]
+    fn test_restart_s_track() {
+        let (sim_pars, sim_consts, tire_config) = test_support::load_test_inputs();
+        let race = test_support::create_race(&sim_pars, &sim_consts, &tire_config);
+
+        // the restart line defaults to the finish line, otherwise the last overtaking zone start
+        assert_eq!(sim_consts.restart_line, RestartLine::FinishLine);
+        assert_eq!(get_restart_s_track(&race.track, RestartLine::FinishLine), 0.0);
+        assert_eq!(
+            get_restart_s_track(&race.track, RestartLine::LastOvertakingZone),
+            4000.0
+        );
+    }
+
+    #[test]
+    fn test_rolling_restart_leader_controls_pace() {
+        let (sim_pars, sim_consts, tire_config) = test_support::load_test_pars();
+
+        let mut race = test_support::create_race(&sim_pars, &sim_consts, &tire_config);
+
+        // simulate into lap 5 and deploy the SC
+        while race.cars_list.iter().any(|car| car.sh.get_race_prog() < 4.6) {
+            race.simulate_timestep();
+        }
+        race.flag_state = FlagState::Sc;
+        race.sc_timer = f64::INFINITY;
+
+        while matches!(race.flag_state, FlagState::Sc) && race.cur_racetime < 2000.0 {
+            race.simulate_timestep();
+        }
+        assert!(matches!(race.flag_state, FlagState::G));
+        assert!(race.is_rolling_restart());
+
+        // the restart phase: the leader is not faster than the restart pace and nobody overtakes
+        let laptime_restart = (race.track.t_q + race.track.t_gap_racepace)
+            * sim_consts.sc_delta_factor
+            * (1.0 + sim_consts.restart_pace_margin);
+        let get_order = |race: &Race| {
+            let mut idxs: Vec<usize> = (0..race.cars_list.len()).collect();
+            idxs.sort_by(|&a, &b| {
+                race.cars_list[b]
+                    .sh
+                    .get_race_prog()
+                    .partial_cmp(&race.cars_list[a].sh.get_race_prog())
+                    .unwrap()
+            });
+            idxs
+        };
+        let order_sc_in = get_order(&race);
+        let t_sc_in = race.cur_racetime;
+
+        while race.is_rolling_restart() && race.cur_racetime < t_sc_in + 2.0 * laptime_restart {
+            race.simulate_timestep();
+            assert_eq!(get_order(&race), order_sc_in);
+
+            if race.is_rolling_restart() {
+                assert!(race.cur_laptimes[order_sc_in[0]] >= laptime_restart - 1e-9);
+            }
+        }
+
+        // the race is restarted when the leader crosses the finish line, i.e. within one lap
+        assert!(!race.is_rolling_restart());
+        assert!(race.cur_racetime - t_sc_in <= laptime_restart + 1e-6);
+    }
+    #[test]
+    fn test_restart_lap_slower_for_whole_field() {
+        let (mut sim_pars, sim_consts, tire_config) = test_support::load_test_pars();
+        sim_pars.race_pars.tot_no_laps = 15;
+        sim_pars.race_pars.seed = Some(1);
+        sim_pars.race_pars.scripted_events =
+            serde_json::from_str(r#"[{"lap": 5, "type": "SafetyCar", "no_laps": 2}]"#).unwrap();
+        // no contacts in the duels, such that the green laps are not slowed by damage
+        for driver_pars in sim_pars.driver_pars_all.values_mut() {
+            driver_pars.aggression = 0.0;
+        }
+
+        let result = run_race(&sim_pars, &sim_consts, &tire_config, 0.1).unwrap();
+
+        // the leader backs up the field in the lap in which the SC goes in, the race resumes at
+        // the finish line
+        let sc_in = result.events.iter().find(|ev| ev.kind == "SC_IN").unwrap();
+        let restart_lap = sc_in.lap as usize;
+
+        for laptimes in result.laptimes.iter() {
+            let laptime_green_max = laptimes[restart_lap + 2..=12].iter().cloned().fold(0.0, f64::max);
+            assert!(laptime_green_max > 0.0);
+            assert!(laptimes[restart_lap] > laptime_green_max + 5.0, "{:?}", laptimes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod units_tests {
+    use crate::pre::read_sim_pars::SimPars;
+    use crate::pre::units::{parse_speed, parse_time, SpeedUnit};
+
+    fn get_test_pars_json() -> serde_json::Value {
+        serde_json::from_str(include_str!("../../input/parameters/test_race.json")).unwrap()
+    }
+
+    #[test]
+    fn test_parse_speed() {
+        assert!((parse_speed("80 km/h", SpeedUnit::MPerS).unwrap() - 80.0 / 3.6).abs() < 1e-9);
+        assert!((parse_speed("22.2 m/s", SpeedUnit::MPerS).unwrap() - 22.2).abs() < 1e-9);
+        assert!((parse_speed("22.2", SpeedUnit::MPerS).unwrap() - 22.2).abs() < 1e-9);
+        assert!((parse_speed("80KM/H", SpeedUnit::MPerS).unwrap() - 80.0 / 3.6).abs() < 1e-9);
+        assert!((parse_speed("335 km/h", SpeedUnit::KmPerH).unwrap() - 335.0).abs() < 1e-9);
+        assert!((parse_speed("90 m/s", SpeedUnit::KmPerH).unwrap() - 324.0).abs() < 1e-9);
+        assert!(parse_speed("80 mph", SpeedUnit::MPerS).is_err());
+        assert!(parse_speed("fast", SpeedUnit::MPerS).is_err());
+    }
+
+    #[test]
+    fn test_parse_time() {
+        assert!((parse_time("1:21.500").unwrap() - 81.5).abs() < 1e-9);
+        assert!((parse_time("0:59.9").unwrap() - 59.9).abs() < 1e-9);
+        assert!((parse_time("81.5 s").unwrap() - 81.5).abs() < 1e-9);
+        assert!((parse_time("1.2s").unwrap() - 1.2).abs() < 1e-9);
+        assert!((parse_time("81.5").unwrap() - 81.5).abs() < 1e-9);
+        assert!(parse_time("1:75.0").is_err());
+        assert!(parse_time("1:21:500").is_err());
+        assert!(parse_time("81.5 min").is_err());
+    }
+
+    #[test]
+    fn test_deserialize_unit_suffixes() {
+        let mut pars_json = get_test_pars_json();
+        pars_json["track_pars"]["t_q"] = serde_json::json!("1:21.500");
+        pars_json["track_pars"]["t_gap_racepace"] = serde_json::json!("1.2 s");
+        pars_json["track_pars"]["pit_speedlimit"] = serde_json::json!("80 km/h");
+        for driver_pars in pars_json["driver_pars_all"].as_object_mut().unwrap().values_mut() {
+            driver_pars["vel_max"] = serde_json::json!("90 m/s");
+        }
+
+        let sim_pars: SimPars = serde_json::from_value(pars_json).unwrap();
+        assert!((sim_pars.track_pars.t_q - 81.5).abs() < 1e-9);
+        assert!((sim_pars.track_pars.t_gap_racepace - 1.2).abs() < 1e-9);
+        assert!((sim_pars.track_pars.pit_speedlimit.m_s - 80.0 / 3.6).abs() < 1e-9);
+        assert!(sim_pars
+            .driver_pars_all
+            .values()
+            .all(|driver_pars| (driver_pars.vel_max - 324.0).abs() < 1e-9));
+        assert!(sim_pars.validate().warnings.iter().all(|msg| !msg.contains("pit_speedlimit")));
+
+        // the canonical numeric form is unchanged, invalid strings are rejected
+        let sim_pars: SimPars = serde_json::from_value(get_test_pars_json()).unwrap();
+        assert!((sim_pars.track_pars.t_q - 80.0).abs() < 1e-9);
+        assert!((sim_pars.track_pars.pit_speedlimit.m_s - 22.2).abs() < 1e-9);
+
+        let mut pars_json = get_test_pars_json();
+        pars_json["track_pars"]["pit_speedlimit"] = serde_json::json!("80 mph");
+        assert!(serde_json::from_value::<SimPars>(pars_json).is_err());
+    }
+
+    #[test]
+    fn test_unitless_pit_speedlimit_warning() {
+        let mut pars_json = get_test_pars_json();
+        pars_json["track_pars"]["pit_speedlimit"] = serde_json::json!(80.0);
+        let sim_pars: SimPars = serde_json::from_value(pars_json).unwrap();
+
+        let report = sim_pars.validate();
+        assert!(report.errors.is_empty());
+        assert!(report
+            .warnings
+            .iter()
+            .any(|msg| msg.contains("pit_speedlimit") && msg.contains("80 km/h")));
+
+        // explicit units are taken as intended, also above the plausible limit
+        for pit_speedlimit in ["200 km/h", "45 m/s"].iter() {
+            let mut pars_json = get_test_pars_json();
+            pars_json["track_pars"]["pit_speedlimit"] = serde_json::json!(pit_speedlimit);
+            let sim_pars: SimPars = serde_json::from_value(pars_json).unwrap();
+            assert!(sim_pars.validate().warnings.iter().all(|msg| !msg.contains("pit_speedlimit")));
+        }
+    }
+}
+
+#[cfg(test)]
+mod provenance_tests {
+    use crate::core::handle_race::run_race;
+    use crate::core::race::SimConstants;
+    use crate::core::tireset::TireConfig;
+    use crate::post::output_fmt::OutputFormat;
+    use crate::post::provenance::{calc_config_hash, fmt_utc_timestamp, Provenance};
+    use crate::post::race_result::RaceResult;
+    use crate::post::report::{create_html_report, ReportInput};
+    use crate::pre::read_sim_pars::SimPars;
+    use crate::test_support;
+
+    fn get_test_pars() -> (SimPars, SimConstants, TireConfig) {
+        let (sim_pars, sim_consts, tire_config) = test_support::load_test_pars();
+        (sim_pars, sim_consts, tire_config)
+    }
+
+    /// get_test_provenance returns the provenance of the test race with a fixed time stamp such
+    /// that the outputs can be compared.
+    fn get_test_provenance(sim_pars: &SimPars) -> Provenance {
+        Provenance {
+            timestamp: fmt_utc_timestamp(0),
+            ..Provenance::new("test_race.json", Some(7), 0.1, sim_pars).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_fmt_utc_timestamp() {
+        assert_eq!(fmt_utc_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(fmt_utc_timestamp(951782400), "2000-02-29T00:00:00Z");
+        assert_eq!(fmt_utc_timestamp(1700000000), "2023-11-14T22:13:20Z");
+    }
+
+    #[test]
+    fn test_config_hash() {
+        let (mut sim_pars, _, _) = get_test_pars();
+        let config_hash = calc_config_hash(&sim_pars).unwrap();
+        assert_eq!(config_hash.len(), 16);
+
+        // independent of the order of the hash maps, but sensitive to every parameter
+        let sim_pars_reread: SimPars =
+            serde_json::from_str(&serde_json::to_string(&sim_pars).unwrap()).unwrap();
+        assert_eq!(calc_config_hash(&sim_pars_reread).unwrap(), config_hash);
+
+        // the seed is recorded separately
+        sim_pars.fix_seed(Some(7));
+        assert_eq!(calc_config_hash(&sim_pars).unwrap(), config_hash);
+
+        sim_pars.track_pars.t_q += 0.001;
+        assert_ne!(calc_config_hash(&sim_pars).unwrap(), config_hash);
+    }
+
+    #[test]
+    fn test_fix_seed() {
+        let (mut sim_pars, _, _) = get_test_pars();
+        sim_pars.race_pars.seed = None;
+
+        // a random seed is drawn once and kept, a given seed overrides it
+        let seed = sim_pars.fix_seed(None);
+        assert_eq!(sim_pars.race_pars.seed, Some(seed));
+        assert_eq!(sim_pars.fix_seed(None), seed);
+        assert_eq!(sim_pars.fix_seed(Some(3)), 3);
+        assert_eq!(sim_pars.race_pars.seed, Some(3));
+    }
+
+    #[test]
+    fn test_provenance_in_outputs() {
+        let (sim_pars, sim_consts, tire_config) = get_test_pars();
+        let provenance = get_test_provenance(&sim_pars);
+        let result = run_race(&sim_pars, &sim_consts, &tire_config, 0.1).unwrap();
+        let out_fmt = OutputFormat::default();
+        let out_dir =
+            std::env::temp_dir().join(format!("racesim_provenance_{}", std::process::id()));
+        std::fs::create_dir_all(&out_dir).unwrap();
+
+        // text and CSV outputs: commented header in front of the unchanged content
+        let header = provenance.format_header("#");
+        assert!(header.lines().all(|line| line.starts_with("# ")));
+        assert!(header.contains("# seed: 7"));
+        assert!(header.contains(&format!("# config_hash: {}", provenance.config_hash)));
+
+        let csv_path = out_dir.join("racesim_provenance_test.csv");
+        result.write_lap_times_to_csv(Some(&csv_path), &out_fmt, Some(&provenance)).unwrap();
+        let content = std::fs::read_to_string(&csv_path).unwrap();
+        assert_eq!(content, header + &result.format_lap_times_csv(&out_fmt).unwrap());
+
+        let txt_path = out_dir.join("racesim_provenance_test.txt");
+        result.write_events_to_file(Some(&txt_path), &out_fmt, None).unwrap();
+        let content = std::fs::read_to_string(&txt_path).unwrap();
+        assert_eq!(content, result.format_events(&out_fmt).unwrap());
+
+        // JSON output: structured field that is read back
+        let json_path = out_dir.join("racesim_provenance_test.json");
+        result.write_to_json(Some(&json_path), &[], Some(&provenance)).unwrap();
+        let (_, provenance_read) = RaceResult::read_from_json_with_provenance(&json_path).unwrap();
+        assert_eq!(provenance_read, Some(provenance.to_owned()));
+
+        result.write_to_json(Some(&json_path), &[], None).unwrap();
+        let (_, provenance_read) = RaceResult::read_from_json_with_provenance(&json_path).unwrap();
+        assert!(provenance_read.is_none());
+
+        // HTML report
+        let report_input = ReportInput {
+            result: &result,
+            sim_pars: &sim_pars,
+            sim_consts: &sim_consts,
+            sanity_warnings: &[],
+            plots: &[],
+            provenance: Some(&provenance),
+        };
+        let html = create_html_report(&report_input, &out_fmt).unwrap();
+        assert!(html.contains(&provenance.config_hash));
+        assert!(html.contains("1970-01-01T00:00:00Z"));
+
+        std::fs::remove_dir_all(&out_dir).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod retirement_event_tests {
+    use crate::core::car::{FailureRates, FailureType, RetirementCause};
+    use crate::core::race::{Race, SimConstants};
+    use crate::post::race_result::{IncidentLocation, RaceEvent};
+    use crate::pre::read_sim_pars::SimPars;
+    use crate::test_support;
+
+    fn create_race(failure_rate_per_hour: f64) -> Race {
+        create_race_with(|_, sim_consts| sim_consts.failure_rate_per_hour = failure_rate_per_hour)
+    }
+
+    fn create_race_with(configure: impl FnOnce(&mut SimPars, &mut SimConstants)) -> Race {
+        let (mut sim_pars, mut sim_consts, tire_config) = test_support::load_test_inputs();
+        sim_consts.collision_factor = 0.0;
+        sim_pars.race_pars.vsc_probability = 0.0;
+        sim_pars.race_pars.red_flag_probability = 0.0;
+        sim_consts.sc_probability = 0.0;
+        configure(&mut sim_pars, &mut sim_consts);
+
+        test_support::create_race(&sim_pars, &sim_consts, &tire_config)
+    }
+
+    fn get_events(race: &Race, kind: &str) -> Vec<RaceEvent> {
+        race.get_race_result()
+            .events
+            .into_iter()
+            .filter(|ev| ev.kind == kind)
+            .collect()
+    }
+
+    #[test]
+    fn test_retirement_event_at_race_time_and_position() {
+        let mut race = create_race(0.0);
+        let idx = race.get_car_idx(44).unwrap();
+
+        while race.cur_lap_leader < 3 || race.cars_list[idx].sh.pit_act {
+            race.simulate_timestep();
+        }
+
+        race.retire_cars(&[idx], RetirementCause::Crash);
+        let retirement = race.get_retirements()[idx].clone().unwrap();
+
+        let crashes = get_events(&race, "Crash");
+        assert_eq!(crashes.len(), 1);
+        assert_eq!(crashes[0].cars, vec![44]);
+        assert_eq!(crashes[0].time_s, race.cur_racetime);
+        assert_eq!(crashes[0].time_s, retirement.time_s);
+        assert_eq!(crashes[0].s_range, Some([retirement.s_track, retirement.s_track]));
+
+        // a car can only retire once
+        race.retire_cars(&[idx], RetirementCause::Failure(FailureType::Engine));
+        assert_eq!(get_events(&race, "Crash").len(), 1);
+        assert!(get_events(&race, "ENGINE_FAILURE").is_empty());
+        assert_eq!(race.get_retirements()[idx].as_ref().unwrap().failure, None);
+    }
+
+    #[test]
+    fn test_retirement_decided_once() {
+        let mut race = create_race(0.0);
+        let idx = race.get_car_idx(44).unwrap();
+
+        while race.cur_lap_leader < 3 || race.cars_list[idx].sh.pit_act {
+            race.simulate_timestep();
+        }
+
+        race.retire_cars(&[idx], RetirementCause::Crash);
+
+        // the retirement is decided on in the next step and not considered again afterwards
+        for _ in 0..500 {
+            race.simulate_timestep();
+        }
+
+        let decisions = get_events(&race, "SC_DECISION");
+        assert_eq!(decisions.len(), 1);
+        assert_eq!(decisions[0].cars, vec![44]);
+    }
+
+    #[test]
+    fn test_engine_failure_at_line_crossing() {
+        let mut race = create_race(1.0e9);
+
+        while get_events(&race, "ENGINE_FAILURE").is_empty() {
+            race.simulate_timestep();
+        }
+
+        let failure = get_events(&race, "ENGINE_FAILURE").remove(0);
+        let idx = race.get_car_idx(failure.cars[0]).unwrap();
+        let retirement = race.get_retirements()[idx].clone().unwrap();
+
+        // the car fails when crossing the line, i.e. within the last time step
+        assert!(failure.time_s <= race.cur_racetime);
+        assert!(failure.time_s > race.cur_racetime - 0.1 - 1e-9);
+        assert_eq!(failure.time_s, retirement.time_s);
+        // no time penalties in the test race, i.e. the race time equals the crossing time
+        assert!((failure.time_s - race.racetimes[idx][retirement.lap as usize]).abs() < 1e-6);
+        assert_eq!(failure.s_range, Some([retirement.s_track, retirement.s_track]));
+    }
+
+    #[test]
+    fn test_failure_types_with_separate_rates_and_sc_factors() {
+        for &failure_type in FailureType::ALL.iter() {
+            let failure_rates = match failure_type {
+                FailureType::Engine => FailureRates { engine: 1.0e9, ..Default::default() },
+                FailureType::Gearbox => FailureRates { gearbox: 1.0e9, ..Default::default() },
+                FailureType::Hydraulics => FailureRates { hydraulics: 1.0e9, ..Default::default() },
+                FailureType::Brakes => FailureRates { brakes: 1.0e9, ..Default::default() },
+            };
+            let mut sc_pars = (0.0, 0.0, 0.0);
+            let mut race = create_race_with(|sim_pars, sim_consts| {
+                sim_consts.failure_rates_per_hour = Some(failure_rates);
+                sim_consts.sc_probability = 0.3;
+                sc_pars = (
+                    sim_consts.sc_probability,
+                    sim_consts.sc_corner_factor,
+                    sim_consts.sc_overtaking_zone_factor,
+                );
+                // car 33 never fails, i.e. the decision is about a single car
+                sim_pars.car_pars_all.get_mut(&33).unwrap().failure_rates_per_hour =
+                    Some(FailureRates::default());
+            });
+
+            while get_events(&race, "SC_DECISION").is_empty() {
+                race.simulate_timestep();
+            }
+
+            let failure = get_events(&race, failure_type.get_event_kind()).remove(0);
+            assert_eq!(failure.cars, vec![44]);
+            let idx = race.get_car_idx(44).unwrap();
+            assert_eq!(
+                race.get_race_result().get_failure_type(idx),
+                Some(failure_type)
+            );
+
+            // the SC probability at the location is scaled by the failure type
+            let decision = get_events(&race, "SC_DECISION")[0].sc_decision.unwrap();
+            let location_factor = match decision.location {
+                IncidentLocation::Corner => sc_pars.1,
+                IncidentLocation::OvertakingZone => sc_pars.2,
+                IncidentLocation::Other => 1.0,
+            };
+            let probability_expected = (sc_pars.0 * location_factor).clamp(0.0, 1.0)
+                * failure_type.get_sc_factor();
+            assert!((decision.probability - probability_expected.clamp(0.0, 1.0)).abs() < 1e-9);
+        }
+    }
+}
+
+#[cfg(test)]
+mod finish_under_sc_tests {
+    use crate::core::race::FlagState;
+    use crate::test_support;
+
+    #[test]
+    fn test_finish_under_sc() {
+        let (mut sim_pars, sim_consts, tire_config) = test_support::load_test_pars();
+        // a short race without contacts, such that no car is lapped before the SC comes out
+        sim_pars.race_pars.tot_no_laps = 10;
+        for driver_pars in sim_pars.driver_pars_all.values_mut() {
+            driver_pars.aggression = 0.0;
+        }
+
+        let mut race = test_support::create_race(&sim_pars, &sim_consts, &tire_config);
+        let tot_no_laps = sim_pars.race_pars.tot_no_laps;
+
+        // deploy the SC at the start of the last lap and keep it out until the finish
+        while race.cur_lap_leader < tot_no_laps {
+            race.simulate_timestep();
+        }
+        race.flag_state = FlagState::Sc;
+
+        let laptime_sc_delta =
+            (race.track.t_q + race.track.t_gap_racepace) * sim_consts.sc_delta_factor;
+        let mut finishing_seen = false;
+
+        while !race.get_all_finished() && race.cur_racetime < 20000.0 {
+            if matches!(race.flag_state, FlagState::Sc) {
+                race.sc_timer = f64::INFINITY;
+            }
+            race.simulate_timestep();
+
+            // after the leader took the flag, the SC leads the remaining cars to the line
+            if matches!(race.flag_state, FlagState::C) && !race.get_all_finished() {
+                finishing_seen = true;
+                assert!(race.safety_car.active);
+
+                for (i, car) in race.cars_list.iter().enumerate() {
+                    if !car.sh.pit_act {
+                        assert!(race.cur_laptimes[i] >= laptime_sc_delta - 1e-9);
+                    }
+                }
+            }
+        }
+
+        assert!(finishing_seen);
+        assert!(race.get_all_finished());
+        assert!(!race.safety_car.active);
+
+        let result = race.get_race_result();
+        assert!(!result.sc_active);
+        assert_eq!(result.events.iter().filter(|ev| ev.kind == "FINISH_UNDER_SC").count(), 1);
+        assert!(result.events.iter().all(|ev| ev.kind != "SC_IN"));
+
+        // every car completed the full distance and is classified with its gap to the winner
+        let positions = result.get_final_positions();
+        for (idx, &position) in positions.iter().enumerate() {
+            assert!(result.get_racetime(idx, tot_no_laps as usize) > 0.0);
+
+            if position > 1 {
+                assert!(result.get_gap_to_winner(idx).unwrap() > 0.0);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod partial_wet_start_tests {
+    use crate::core::race::{Race, SimConstants};
+    use crate::core::scripted_event::{ScriptedAction, ScriptedEvent};
+    use crate::core::tireset::TireConfig;
+    use crate::pre::read_sim_pars::SimPars;
+    use crate::test_support;
+
+    /// get_test_pars returns the test race on a track with wetness 0.6 at the start that does not
+    /// get rain. Car 44 gambles on slicks, car 33 starts on intermediates and changes to slicks
+    /// at the end of lap 6.
+    fn get_test_pars() -> (SimPars, SimConstants, TireConfig) {
+        let (mut sim_pars, sim_consts, tire_config) = test_support::load_test_pars();
+
+        sim_pars.race_pars.initial_weather = String::from("Dry");
+        sim_pars.race_pars.initial_wetness = Some(0.6);
+        sim_pars.race_pars.rain_probability = 0.0;
+
+        let strategy_33 = &mut sim_pars.car_pars_all.get_mut(&33).unwrap().strategy;
+        let mut stop = strategy_33[0].to_owned();
+        strategy_33[0].compound = String::from("INTERMEDIATE");
+        stop.inlap = 6;
+        strategy_33.push(stop);
+
+        (sim_pars, sim_consts, tire_config)
+    }
+
+    fn create_race(sim_pars: &SimPars, sim_consts: &SimConstants, tire_cfg: &TireConfig) -> Race {
+        test_support::create_race(sim_pars, sim_consts, tire_cfg)
+    }
+
+    /// get_gap_gambler returns the gap (s) of the slick gambler (car 44) to car 33 after lap 10
+    /// for the given drying time constant (negative if the gamble paid off).
+    fn get_gap_gambler(wetness_time_constant_s: f64) -> f64 {
+        let (mut sim_pars, mut sim_consts, tire_config) = get_test_pars();
+        sim_consts.wetness_time_constant_s = wetness_time_constant_s;
+        // seeded and without contacts in the duels, such that the gap only depends on the tires
+        sim_pars.race_pars.seed = Some(1);
+        for driver_pars in sim_pars.driver_pars_all.values_mut() {
+            driver_pars.aggression = 0.0;
+        }
+        let mut race = create_race(&sim_pars, &sim_consts, &tire_config);
+
+        while race.cars_list.iter().any(|car| car.sh.get_compl_lap() < 10) {
+            race.simulate_timestep();
+        }
+
+        let idx_44 = race.get_car_idx(44).unwrap();
+        let idx_33 = race.get_car_idx(33).unwrap();
+        race.racetimes[idx_44][10] - race.racetimes[idx_33][10]
+    }
+
+    #[test]
+    fn test_weather_penalty_from_first_lap() {
+        let (sim_pars, sim_consts, tire_config) = get_test_pars();
+        let race = create_race(&sim_pars, &sim_consts, &tire_config);
+
+        // the penalty of the first lap depends on the wetness at the start
+        let t_weather_44 = race.get_laptime_breakdowns(race.get_car_idx(44).unwrap())[1].t_weather;
+        let t_weather_33 = race.get_laptime_breakdowns(race.get_car_idx(33).unwrap())[1].t_weather;
+        assert!((t_weather_44 - 0.6 * 42.0).abs() < 1e-9, "{}", t_weather_44);
+        assert!((t_weather_33 - (5.0 + 0.6 * 7.0)).abs() < 1e-9, "{}", t_weather_33);
+    }
+
+    #[test]
+    fn test_slick_gamble_depends_on_drying() {
+        // fast drying: the slick gambler saves the pit stop and the slow laps on intermediates
+        let gap_fast = get_gap_gambler(60.0);
+        assert!(gap_fast < -10.0, "gap {}", gap_fast);
+
+        // the track stays wet: the slick gambler loses far more than a pit stop
+        let gap_slow = get_gap_gambler(1.0e6);
+        assert!(gap_slow > 30.0, "gap {}", gap_slow);
+    }
+
+    #[test]
+    fn test_weather_strategy_respects_hold_laps() {
+        let (mut sim_pars, sim_consts, tire_config) = get_test_pars();
+        sim_pars.race_pars.initial_wetness = Some(0.0);
+        sim_pars.race_pars.scripted_events = vec![ScriptedEvent {
+            lap: 2,
+            action: ScriptedAction::Weather {
+                weather: String::from("Rain"),
+            },
+            override_stochastic: true,
+        }];
+        sim_pars.car_pars_all.get_mut(&44).unwrap().hold_strategy_laps = 3;
+        let mut race = create_race(&sim_pars, &sim_consts, &tire_config);
+        let idx = race.get_car_idx(44).unwrap();
+
+        while race.cur_lap_leader < 2 {
+            race.simulate_timestep();
+        }
+
+        // car 44 keeps its slicks for three more full laps after the lap of the weather change
+        let inlap_expected = race.cars_list[idx].sh.get_compl_lap() + 1 + 3;
+        let strategy = race.cars_list[idx].get_strategy();
+        assert!(strategy
+            .iter()
+            .any(|entry| entry.inlap == inlap_expected && entry.compound == "INTERMEDIATE"));
+        assert!(strategy.iter().all(|entry| entry.inlap == 0 || entry.inlap >= inlap_expected));
+    }
+}
+
+#[cfg(test)]
+mod finish_timeout_tests {
+    use crate::core::race::{Race, SimConstants};
+    use crate::test_support;
+
+    /// run_race simulates the test race until the simulation ends (car 33 makes a pit stop at the
+    /// end of the second to last lap with the given standstill time).
+    fn run_race(t_pit_tirechange: f64) -> (Race, SimConstants) {
+        let (mut sim_pars, sim_consts, tire_config) = test_support::load_test_pars();
+
+        let tot_no_laps = sim_pars.race_pars.tot_no_laps;
+        let car_pars_33 = sim_pars.car_pars_all.get_mut(&33).unwrap();
+        let mut stop = car_pars_33.strategy[0].to_owned();
+        stop.inlap = tot_no_laps - 1;
+        car_pars_33.strategy.push(stop);
+        car_pars_33.t_pit_tirechange = t_pit_tirechange;
+        // no contacts (and no repair stops) in the duels, such that car 44 wins the race
+        for driver_pars in sim_pars.driver_pars_all.values_mut() {
+            driver_pars.aggression = 0.0;
+        }
+        // seeded, such that no random incident of car 44 (e.g. a driver error) delays the flag
+        sim_pars.race_pars.seed = Some(1);
+
+        let mut race = test_support::create_race(&sim_pars, &sim_consts, &tire_config);
+
+        while !race.get_all_finished() && race.cur_racetime < 100000.0 {
+            race.simulate_timestep();
+        }
+
+        (race, sim_consts)
+    }
+
+    #[test]
+    fn test_immobilized_car_force_classified() {
+        let (race, sim_consts) = run_race(1.0e9);
+        assert!(race.get_all_finished());
+
+        let result = race.get_race_result();
+        let tot_no_laps = result.tot_no_laps as usize;
+        let idx_44 = race.get_car_idx(44).unwrap();
+        let idx_33 = race.get_car_idx(33).unwrap();
+
+        // the simulation ends one lap at the slowest pace after the flag
+        let t_chequered = result.get_racetime(idx_44, tot_no_laps);
+        let laptime_max = (race.track.t_q + race.track.t_gap_racepace)
+            * sim_consts.finish_min_pace_factor;
+        assert!(race.cur_racetime > t_chequered + laptime_max);
+        assert!(race.cur_racetime <= t_chequered + laptime_max + 0.25);
+
+        let forced: Vec<_> =
+            result.events.iter().filter(|ev| ev.kind == "ForceClassified").collect();
+        assert_eq!(forced.len(), 1);
+        assert_eq!(forced[0].cars, vec![33]);
+        assert!(forced[0].s_range.is_some());
+
+        // the car is classified with its completed laps
+        assert!(result.get_last_driven_lap(idx_33) < tot_no_laps);
+        assert_eq!(result.get_final_positions()[idx_33], 2);
+        assert!(result.retirements[idx_33].is_none());
+    }
+
+    #[test]
+    fn test_no_force_classification_in_normal_finish() {
+        let (race, _) = run_race(2.5);
+        let result = race.get_race_result();
+
+        assert!(result.events.iter().all(|ev| ev.kind != "ForceClassified"));
+
+        // every car takes the flag, a lapped car one or more laps down
+        let idx_winner = result.get_final_positions().iter().position(|&pos| pos == 1).unwrap();
+        let t_chequered = result.get_racetime(idx_winner, result.tot_no_laps as usize);
+        assert!(t_chequered > 0.0);
+        for idx in 0..result.car_driver_pairs.len() {
+            let last_lap = result.get_last_driven_lap(idx);
+            assert!(result.get_racetime(idx, last_lap) >= t_chequered);
+        }
+    }
+}
+
+#[cfg(test)]
+mod sc_peel_off_tests {
+    use crate::core::race::{FlagState, Race};
+    use crate::interfaces::gui_interface::RaceStateBuilder;
+    use crate::test_support;
+
+    #[test]
+    fn test_sc_peels_off_at_pit_entry_or_finish_line() {
+        let (sim_pars, sim_consts, tire_config) = test_support::load_test_pars();
+
+        let mut race = test_support::create_race(&sim_pars, &sim_consts, &tire_config);
+        race.simulate_until_leader_lap(2);
+
+        // deploy the SC
+        race.flag_state = FlagState::Sc;
+        race.sc_timer = f64::INFINITY;
+        let builder = RaceStateBuilder::new(&race).unwrap();
+        let pit_entry = race.track.pit_zone[0];
+        let mut sc_race_prog_prev: Option<f64> = None;
+        let mut in_this_lap_seen = false;
+
+        while matches!(race.flag_state, FlagState::Sc) && race.cur_racetime < 3000.0 {
+            let sc_s_track_prev = race.safety_car.s_track;
+            let sc_lap_prev = race.safety_car.lap;
+            race.simulate_timestep();
+
+            if matches!(race.flag_state, FlagState::Sc) {
+                // the SC keeps driving at its speed during the final partial lap
+                let race_state = builder.build_state(&race);
+                assert!(race_state.sc_active);
+                if let Some(sc_race_prog_prev) = sc_race_prog_prev {
+                    let delta = race_state.sc_race_prog - sc_race_prog_prev;
+                    let delta_step =
+                        race.safety_car.speed * race.timestep_size / race.track.length;
+                    assert!((delta - delta_step).abs() < 1e-6);
+                }
+                sc_race_prog_prev = Some(race_state.sc_race_prog);
+                in_this_lap_seen |= race_state.sc_in_this_lap;
+            } else {
+                // the SC came in at the pit entry or at the finish line
+                assert!(in_this_lap_seen);
+                assert!(
+                    race.safety_car.lap > sc_lap_prev
+                        || (sc_s_track_prev < pit_entry && pit_entry <= race.safety_car.s_track)
+                );
+            }
+        }
+
+        assert!(matches!(race.flag_state, FlagState::G));
+        assert!(!race.safety_car.active);
+        assert!(!builder.build_state(&race).sc_in_this_lap);
+        assert!(race.get_race_result().events.iter().any(|ev| ev.kind == "SC_IN"));
+    }
+
+    #[test]
+    fn test_sc_spawns_at_pit_exit_ahead_of_leader() {
+        let (sim_pars, sim_consts, tire_config) = test_support::load_test_pars();
+
+        let mut race = test_support::create_race(&sim_pars, &sim_consts, &tire_config);
+        race.simulate_until_leader_lap(2);
+
+        let get_leader = |race: &Race| {
+            (0..race.cars_list.len())
+                .max_by(|&a, &b| {
+                    let race_prog_a = race.cars_list[a].sh.get_race_prog();
+                    race_prog_a.partial_cmp(&race.cars_list[b].sh.get_race_prog()).unwrap()
+                })
+                .unwrap()
+        };
+        let get_sc_dist = |race: &Race| {
+            (race.safety_car.lap - 1) as f64 * race.track.length + race.safety_car.s_track
+        };
+
+        // deploy the SC when the leader just passed the pit exit
+        let pit_exit = race.track.pit_zone[1];
+        while race.cars_list[get_leader(&race)].sh.get_s_tracks().1 <= pit_exit {
+            race.simulate_timestep();
+        }
+        race.flag_state = FlagState::Sc;
+        race.sc_timer = f64::INFINITY;
+        race.simulate_timestep();
+
+        // the SC comes out at the pit exit almost a lap ahead of the leader
+        assert!(race.safety_car.active);
+        let sc_step = race.safety_car.speed * race.timestep_size;
+        assert!(race.safety_car.s_track >= pit_exit - 1e-6);
+        assert!(race.safety_car.s_track <= pit_exit + sc_step + 1e-6);
+
+        let laptime_sc_delta =
+            (race.track.t_q + race.track.t_gap_racepace) * sim_consts.sc_delta_factor;
+
+        while !race.safety_car.leader_caught && race.cur_racetime < 3000.0 {
+            let idx_leader = get_leader(&race);
+            let gap_leader =
+                get_sc_dist(&race) - race.cars_list[idx_leader].sh.get_race_prog() * race.track.length;
+
+            // no car gets ahead of the SC, the leader closes the gap at the SC delta pace instead
+            // of being held back at the SC speed
+            assert!(gap_leader > 0.0);
+            if gap_leader > 100.0 {
+                assert!(race.cur_laptimes[idx_leader] < 1.05 * laptime_sc_delta);
+            }
+            race.simulate_timestep();
+        }
+        assert!(race.safety_car.leader_caught);
+
+        // afterwards the leader stays behind the SC in the queue
+        for _ in 0..200 {
+            race.simulate_timestep();
+            let idx_leader = get_leader(&race);
+            let gap_leader =
+                get_sc_dist(&race) - race.cars_list[idx_leader].sh.get_race_prog() * race.track.length;
+            assert!(gap_leader > 0.0 && gap_leader < 50.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod head_to_head_tests {
+    use crate::post::aggregate::{format_h2h_csv, head_to_head, H2HPair};
+    use crate::post::output_fmt::OutputFormat;
+    use crate::post::race_result::{CarDriverPair, PitStopInfo, RaceResult, RetirementInfo};
+
+    fn get_test_result(
+        racetimes: Vec<Vec<f64>>,
+        pit_stops: Vec<PitStopInfo>,
+        retirements: Vec<Option<RetirementInfo>>,
+    ) -> RaceResult {
+        RaceResult {
+            pit_stops,
+            retirements,
+            ..RaceResult::new(
+                3,
+                vec![
+                    CarDriverPair {
+                        car_no: 1,
+                        driver_initials: String::from("AAA"),
+                    },
+                    CarDriverPair {
+                        car_no: 2,
+                        driver_initials: String::from("BBB"),
+                    },
+                ],
+                racetimes.clone(),
+                racetimes,
+            )
+        }
+    }
+
+    #[test]
+    fn test_pair_parsing() {
+        assert_eq!(
+            "44:63".parse::<H2HPair>(),
+            Ok(H2HPair {
+                car_no_a: 44,
+                car_no_b: 63
+            })
+        );
+        assert!("44".parse::<H2HPair>().is_err());
+        assert!("44:44".parse::<H2HPair>().is_err());
+    }
+
+    #[test]
+    fn test_head_to_head_counts_dnf_runs_separately() {
+        let results = vec![
+            // car 1 wins by 3 s
+            get_test_result(
+                vec![vec![0.0, 90.0, 180.0, 270.0], vec![0.0, 91.0, 182.0, 273.0]],
+                vec![PitStopInfo {
+                    car_no: 1,
+                    lap: 1,
+                    t_standstill: 2.0,
+                }],
+                Vec::new(),
+            ),
+            // car 2 leads from the start and wins by 4 s
+            get_test_result(
+                vec![vec![0.0, 90.0, 180.0, 270.0], vec![0.0, 89.0, 178.0, 266.0]],
+                vec![
+                    PitStopInfo {
+                        car_no: 1,
+                        lap: 2,
+                        t_standstill: 2.4,
+                    },
+                    PitStopInfo {
+                        car_no: 2,
+                        lap: 2,
+                        t_standstill: 3.0,
+                    },
+                ],
+                Vec::new(),
+            ),
+            // car 2 retires in lap 2
+            get_test_result(
+                vec![vec![0.0, 90.0, 180.0, 270.0], vec![0.0, 91.0, 0.0, 0.0]],
+                Vec::new(),
+                vec![
+                    None,
+                    Some(RetirementInfo {
+                        lap: 1,
+                        s_track: 1000.0,
+                        time_s: 120.0,
+                        failure: None,
+                    }),
+                ],
+            ),
+        ];
+
+        let pair = H2HPair {
+            car_no_a: 1,
+            car_no_b: 2,
+        };
+        let reports = head_to_head(&[pair], &results);
+        assert_eq!(reports.len(), 1);
+        let report = &reports[0];
+
+        assert_eq!(report.driver_initials, [String::from("AAA"), String::from("BBB")]);
+        assert_eq!(report.no_runs, 3);
+        assert_eq!(report.no_ahead, [1, 1]);
+        assert_eq!(report.no_dnf, [0, 1]);
+        assert_eq!(report.no_dnf_both, 0);
+
+        // the DNF run is excluded from the gap: (3 s - 4 s) / 2
+        assert!((report.mean_gap.unwrap() + 0.5).abs() < 1e-9);
+
+        assert!((report.mean_laps_led[0] - 2.0).abs() < 1e-9);
+        assert!((report.mean_laps_led[1] - 1.0).abs() < 1e-9);
+        assert!((report.mean_no_pitstops[0] - 2.0 / 3.0).abs() < 1e-9);
+        assert!((report.mean_t_standstill[0].unwrap() - 2.2).abs() < 1e-9);
+        assert!((report.mean_t_standstill[1].unwrap() - 3.0).abs() < 1e-9);
+
+        let csv = format_h2h_csv(&reports, &OutputFormat::default()).unwrap();
+        assert_eq!(csv.lines().count(), 2);
+        assert!(csv.lines().nth(1).unwrap().starts_with("1,2,AAA,BBB,3,1,1,0,1,0,-0.500,"));
+    }
+}
+
+#[cfg(test)]
+mod formation_lap_tests {
+    use crate::core::race::Race;
+    use crate::interfaces::gui_interface::RaceStateBuilder;
+    use crate::test_support;
+
+    fn create_race(formation_lap: bool) -> Race {
+        let (mut sim_pars, mut sim_consts, tire_config) = test_support::load_test_pars();
+        sim_consts.formation_wear_factor = 0.5;
+        sim_pars.race_pars.formation_lap = formation_lap;
+
+        test_support::create_race(&sim_pars, &sim_consts, &tire_config)
+    }
+
+    #[test]
+    fn test_formation_lap_ages_tires_without_counting() {
+        let mut race = create_race(true);
+        let tire_ages: Vec<f64> = race
+            .cars_list
+            .iter()
+            .map(|car| car.get_tire_age_cur_stint())
+            .collect();
+        let fuel_masses: Vec<f64> = race.cars_list.iter().map(|car| car.get_fuel_mass()).collect();
+        let builder = RaceStateBuilder::new(&race).unwrap();
+
+        let mut no_steps = 0;
+        while race.is_formation_lap() {
+            let race_state = builder.build_state(&race);
+            assert!(race_state.formation_lap_act);
+
+            // the cars drive in grid order a lap before the start
+            for car_state in race_state.car_states.iter() {
+                assert!(car_state.race_prog < 0.1);
+            }
+
+            race.simulate_timestep();
+            no_steps += 1;
+        }
+
+        // the formation lap takes its own time, but does not count as race time
+        assert!((no_steps as f64 * 0.1 - race.get_t_formation_lap()).abs() <= 0.1 + 1e-9);
+        assert_eq!(race.cur_racetime, 0.0);
+        assert!(!builder.build_state(&race).formation_lap_act);
+
+        for (i, car) in race.cars_list.iter().enumerate() {
+            assert!((car.get_tire_age_cur_stint() - tire_ages[i] - 0.5).abs() < 1e-9);
+            assert!(car.get_fuel_mass() < fuel_masses[i]);
+        }
+
+        // the race itself is unchanged in length
+        race.simulate_until_leader_lap(1);
+        assert_eq!(race.laptimes[0].len(), race.tot_no_laps as usize + 1);
+        assert!(race.laptimes.iter().any(|laptimes| laptimes[1] > 0.0));
+    }
+
+    #[test]
+    fn test_no_formation_lap_by_default() {
+        let mut race = create_race(false);
+        assert!(!race.is_formation_lap());
+
+        race.simulate_timestep();
+        assert!(race.cur_racetime > 0.0);
+    }
+}
+
+#[cfg(test)]
+mod model_toggles_tests {
+    use crate::core::model_toggles::ModelToggles;
+    use crate::core::race::Race;
+    use crate::test_support;
+
+    fn create_race(model_toggles: ModelToggles) -> Race {
+        let (mut sim_pars, sim_consts, tire_config) = test_support::load_test_pars();
+        sim_pars.race_pars.model_toggles = model_toggles;
+
+        test_support::create_race(&sim_pars, &sim_consts, &tire_config)
+    }
+
+    #[test]
+    fn test_model_toggles_parsing() {
+        let model_toggles: ModelToggles = "tire-degradation, duels".parse().unwrap();
+        assert!(!model_toggles.is_enabled(ModelToggles::TIRE_DEGRADATION));
+        assert!(!model_toggles.is_enabled(ModelToggles::DUELS));
+        assert!(model_toggles.is_enabled(ModelToggles::SC));
+        assert_eq!(model_toggles.get_disabled_names(), vec!["tire-degradation", "duels"]);
+
+        // unknown components list the valid set
+        let err = "tire-degradation,drs".parse::<ModelToggles>().unwrap_err();
+        assert!(err.contains("'drs'"));
+        assert!(err.contains("tire-degradation, dirty-air, sc, weather, mistakes, duels"));
+
+        // the parameter files contain the names of the disabled components
+        let json = serde_json::to_string(&model_toggles).unwrap();
+        assert_eq!(json, r#"["tire-degradation","duels"]"#);
+        assert_eq!(serde_json::from_str::<ModelToggles>(&json).unwrap(), model_toggles);
+        assert!(serde_json::from_str::<ModelToggles>(r#"["drs"]"#).is_err());
+        assert!(!ModelToggles::default().is_any_disabled());
+    }
+
+    #[test]
+    fn test_disabled_tire_degradation_gives_flat_stint_pace() {
+        // car 44 stops after lap 13, the first stint covers laps 1 to 13
+        let get_t_tires = |race: &Race| -> Vec<f64> {
+            let idx = race.cars_list.iter().position(|car| car.car_no == 44).unwrap();
+            race.get_laptime_breakdowns(idx)[1..=13].iter().map(|x| x.t_tire).collect()
+        };
+
+        let mut race = create_race(ModelToggles::default());
+        race.simulate_until_leader_lap(13);
+        let t_tires = get_t_tires(&race);
+        assert!(t_tires[12] > t_tires[0] + 0.1, "t_tire {:?}", t_tires);
+
+        let mut race = create_race("tire-degradation".parse().unwrap());
+        race.simulate_until_leader_lap(13);
+        let t_tires = get_t_tires(&race);
+        assert!(t_tires.iter().all(|&t_tire| t_tire == 0.0), "t_tire {:?}", t_tires);
+        assert!(!race.get_model_toggles().is_enabled(ModelToggles::TIRE_DEGRADATION));
+    }
+}
+
+#[cfg(test)]
+mod first_corner_tests {
+    use crate::core::race::FirstCornerPars;
+    use crate::post::race_result::ScOutcome;
+    use crate::test_support;
+
+    fn get_first_corner_pars() -> FirstCornerPars {
+        FirstCornerPars {
+            probability: 0.15,
+            dnf_probability: 0.4,
+            t_loss_s: [3.0, 10.0],
+        }
+    }
+
+    #[test]
+    fn test_probability_scales_with_no_cars_and_aggression() {
+        let first_corner_pars = get_first_corner_pars();
+
+        let probability = first_corner_pars.calc_probability(20, &[0.5; 14]);
+        assert!((probability - 0.15).abs() < 1e-9);
+        let probability = first_corner_pars.calc_probability(10, &[0.5; 6]);
+        assert!((probability - 0.075).abs() < 1e-9);
+        let probability = first_corner_pars.calc_probability(20, &[1.0; 14]);
+        assert!((probability - 0.3).abs() < 1e-9);
+        assert_eq!(first_corner_pars.calc_probability(2, &[]), 0.0);
+    }
+
+    #[test]
+    fn test_midfield() {
+        let grid_order: Vec<usize> = (0..20).collect();
+        assert_eq!(FirstCornerPars::get_midfield(&grid_order), &grid_order[2..16]);
+        assert!(FirstCornerPars::get_midfield(&[1, 0]).is_empty());
+    }
+
+    #[test]
+    fn test_incident_involves_neighbours_on_the_grid() {
+        let mut first_corner_pars = get_first_corner_pars();
+        // a certain incident with six cars (the probability is clamped to 1.0)
+        first_corner_pars.probability = 10.0;
+        first_corner_pars.dnf_probability = 0.0;
+        let grid_order: Vec<usize> = vec![3, 0, 5, 1, 4, 2];
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..100 {
+            let incident = first_corner_pars
+                .draw_incident(&grid_order, &[1.0; 6], &mut rng)
+                .unwrap();
+            let pos_first = grid_order.iter().position(|&i| i == incident.idxs[0]).unwrap();
+
+            // the incident starts in the midfield and involves the cars directly behind
+            assert!((2..5).contains(&pos_first));
+            assert!(incident.idxs.len() >= 2 && incident.idxs.len() <= 3);
+            assert_eq!(incident.idxs, grid_order[pos_first..pos_first + incident.idxs.len()]);
+            assert!(incident
+                .t_losses
+                .iter()
+                .all(|t_loss| matches!(t_loss, Some(t) if (3.0..=10.0).contains(t))));
+        }
+    }
+
+    #[test]
+    fn test_pileup_brings_out_sc() {
+        let (mut sim_pars, mut sim_consts, tire_config) = test_support::load_test_pars();
+        // a certain pileup with six cars (the probability is clamped to 1.0)
+        sim_consts.first_corner_probability = 10.0;
+        sim_consts.first_corner_dnf_probability = 1.0;
+        // a single retirement would never bring out the SC
+        sim_consts.sc_probability = 0.0;
+        // no car runs into the wrecks of the pileup
+        sim_consts.secondary_collision_probability = 0.0;
+
+        // six cars, the midfield starts from grid positions 3 to 5
+        for (car_no, car_no_orig, p_grid) in [(45, 44, 3), (34, 33, 4), (46, 44, 5), (35, 33, 6)] {
+            let mut car_pars = sim_pars.car_pars_all[&car_no_orig].clone();
+            car_pars.car_no = car_no;
+            car_pars.p_grid = p_grid;
+            sim_pars.car_pars_all.insert(car_no, car_pars);
+            sim_pars.race_pars.participants.push(car_no);
+        }
+
+        let mut race = test_support::create_race(&sim_pars, &sim_consts, &tire_config);
+        let incident = race.get_first_corner_incident().unwrap().clone();
+
+        while race.cur_lap_leader < 2 {
+            race.simulate_timestep();
+        }
+
+        let result = race.get_race_result();
+        let crashes: Vec<_> = result.events.iter().filter(|ev| ev.kind == "Crash").collect();
+        assert_eq!(crashes.len(), 1);
+        assert!(crashes[0].cars.len() >= 2);
+        assert_eq!(crashes[0].lap, 1);
+
+        for &idx in incident.idxs.iter() {
+            assert_eq!(result.get_retirement(idx).unwrap().lap, 0);
+        }
+
+        let sc_decision = result
+            .events
+            .iter()
+            .find_map(|ev| ev.sc_decision.as_ref())
+            .unwrap();
+        assert_eq!(sc_decision.probability, 1.0);
+        assert_eq!(sc_decision.outcome, ScOutcome::Sc);
+        assert!(result.events.iter().any(|ev| ev.kind == "SC_DEPLOYED"));
+    }
+}
+
+#[cfg(test)]
+mod time_limit_tests {
+    use crate::core::handle_race::run_race;
+    use crate::post::output_fmt::OutputFormat;
+    use crate::test_support;
+
+    #[test]
+    fn test_time_limit_ends_race_early() {
+        let (mut sim_pars, sim_consts, tire_config) = test_support::load_test_pars();
+        sim_pars.race_pars.max_race_time_s = Some(300.0);
+
+        let result = run_race(&sim_pars, &sim_consts, &tire_config, 0.1).unwrap();
+        let event = result
+            .events
+            .iter()
+            .find(|ev| ev.kind == "TIME_LIMIT_REACHED")
+            .unwrap();
+        assert!(event.time_s > 300.0);
+
+        // the leader's lap in which the limit was exceeded is the last lap
+        let finish_lap = event.lap;
+        assert!(finish_lap < result.tot_no_laps);
+        assert_eq!(result.get_no_laps_driven(), finish_lap);
+        assert_eq!(result.no_laps_completed.len(), result.car_driver_pairs.len());
+        assert!(result.no_laps_completed.iter().all(|&no_laps| no_laps <= finish_lap));
+        assert!(result.no_laps_completed.contains(&finish_lap));
+
+        // the printer only contains the driven laps
+        let out_fmt = OutputFormat::default();
+        let csv = result.format_lap_times_csv(&out_fmt).unwrap();
+        assert_eq!(csv.lines().count(), finish_lap as usize + 1);
+        let text = result.format_lap_and_race_times(&out_fmt).unwrap();
+        assert!(!text.contains(&format!("\n{:>3}, ", finish_lap + 1)));
+    }
+}
+
+#[cfg(test)]
+mod start_behind_sc_tests {
+    use crate::core::race::{FlagState, Race};
+    use crate::test_support;
+
+    fn create_race(initial_weather: &str) -> Race {
+        let (mut sim_pars, sim_consts, tire_config) = test_support::load_test_pars();
+        sim_pars.race_pars.initial_weather = initial_weather.to_string();
+        sim_pars.race_pars.rain_probability = 0.0;
+        sim_pars.race_pars.start_behind_sc_laps = 2;
+
+        test_support::create_race(&sim_pars, &sim_consts, &tire_config)
+    }
+
+    fn get_order(race: &Race) -> Vec<u32> {
+        let mut cars: Vec<_> = race
+            .cars_list
+            .iter()
+            .map(|car| (car.sh.get_race_prog(), car.car_no))
+            .collect();
+        cars.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+        cars.into_iter().map(|(_, car_no)| car_no).collect()
+    }
+
+    #[test]
+    fn test_rain_start_behind_sc() {
+        let mut race = create_race("Rain");
+        assert!(matches!(race.flag_state, FlagState::Sc));
+        assert!(race.safety_car.active);
+
+        let events = race.get_race_result().events;
+        assert!(events.iter().any(|ev| ev.kind == "START_BEHIND_SC" && ev.lap == 1));
+        assert!(!events
+            .iter()
+            .any(|ev| ev.kind == "StartReaction"));
+
+        // the starting order is kept behind the SC
+        let order_start = get_order(&race);
+        while matches!(race.flag_state, FlagState::Sc) && race.cur_racetime < 1000.0 {
+            race.simulate_timestep();
+            if matches!(race.flag_state, FlagState::Sc) {
+                assert_eq!(get_order(&race), order_start);
+            }
+        }
+
+        // normal racing resumes after the SC laps, the laps behind the SC are driven at SC pace
+        assert!(matches!(race.flag_state, FlagState::G));
+        let lap_in = race
+            .get_race_result()
+            .events
+            .iter()
+            .find(|ev| ev.kind == "SC_IN")
+            .unwrap()
+            .lap;
+        assert!(lap_in <= 3);
+
+        // default sc_delta_factor 1.2
+        let laptime_sc_delta = (race.track.t_q + race.track.t_gap_racepace) * 1.2;
+        for idx in 0..race.cars_list.len() {
+            assert!(race.laptimes[idx][1] > laptime_sc_delta);
+        }
+        assert_eq!(race.get_sc_periods().len(), 1);
+    }
+
+    #[test]
+    fn test_dry_standing_start() {
+        let race = create_race("Dry");
+        assert!(matches!(race.flag_state, FlagState::G));
+        assert!(!race.safety_car.active);
+
+        let events = race.get_race_result().events;
+        assert!(!events.iter().any(|ev| ev.kind == "START_BEHIND_SC"));
+        assert!((0..race.cars_list.len())
+            .all(|idx| race.get_laptime_breakdowns(idx)[1].t_start > 0.0));
+    }
+}
+
+#[cfg(test)]
+mod final_lap_tests {
+    use crate::test_support;
+
+    #[test]
+    fn test_backmarker_completes_final_lap() {
+        let (mut sim_pars, sim_consts, tire_config) = test_support::load_test_pars();
+
+        // car 33 is almost 30 s per lap slower and ends up two laps down
+        sim_pars.race_pars.tot_no_laps = 10;
+        sim_pars.car_pars_all.get_mut(&33).unwrap().t_car += 28.0;
+
+        let mut race = test_support::create_race(&sim_pars, &sim_consts, &tire_config);
+
+        while !race.get_all_finished() && race.cur_racetime < 10000.0 {
+            race.simulate_timestep();
+        }
+        assert!(race.get_all_finished());
+
+        let result = race.get_race_result();
+        let idx_44 = race.get_car_idx(44).unwrap();
+        let idx_33 = race.get_car_idx(33).unwrap();
+
+        // the backmarker finishes the lap it was on when the leader took the flag
+        assert_eq!(result.get_last_driven_lap(idx_44), 10);
+        assert_eq!(result.get_last_driven_lap(idx_33), 8);
+        assert!(result.get_racetime(idx_33, 8) > result.get_racetime(idx_44, 10));
+        assert!(result.get_racetime(idx_33, 8) < result.get_racetime(idx_44, 10) + 150.0);
+        assert_eq!(result.get_final_positions()[idx_44], 1);
+        assert_eq!(result.get_final_positions()[idx_33], 2);
+
+        // every completed lap has its lap time
+        for (idx, no_laps) in [(idx_44, 10), (idx_33, 8)] {
+            assert!((1..=no_laps).all(|lap| result.laptimes[idx][lap] > 0.0));
+            assert!(result.laptimes[idx][no_laps + 1..].iter().all(|&t| t == 0.0));
+        }
+
+        // cars that finished keep driving, but no further laps are recorded
+        let laptimes = race.laptimes.clone();
+        let cur_lap_leader = race.cur_lap_leader;
+        while race.cur_racetime < result.get_racetime(idx_44, 10) + 200.0 {
+            race.simulate_timestep();
+        }
+        assert_eq!(race.laptimes, laptimes);
+        assert_eq!(race.cur_lap_leader, cur_lap_leader);
+    }
+}
+
+#[cfg(test)]
+mod driver_crash_tests {
+    use crate::core::car::CarStatus;
+    use crate::core::model_toggles::ModelToggles;
+    use crate::core::race::{calc_driver_crash_prob, DriverCrashPars, Race, SimConstants};
+    use crate::test_support;
+
+    fn get_default_pars() -> DriverCrashPars {
+        let sim_consts: SimConstants =
+            serde_json::from_str(include_str!("../../input/parameters/sim_constants.json")).unwrap();
+        DriverCrashPars {
+            probability: sim_consts.driver_crash_probability,
+            corner_factor: sim_consts.driver_crash_corner_factor,
+            duel_factor: sim_consts.driver_crash_duel_factor,
+            wet_factor: sim_consts.driver_crash_wet_factor,
+        }
+    }
+
+    #[test]
+    fn test_driver_crash_prob_by_situation() {
+        let pars = get_default_pars();
+        let prob = |corner_act: bool, duel_act: bool, wetness: f64| {
+            // the default incident wetness curve goes up to 2.5 on a fully wet track
+            calc_driver_crash_prob(0.9, corner_act, duel_act, wetness, 1.0 + 1.5 * wetness, &pars)
+        };
+
+        // a solo lap on a dry track is nearly safe, a wet duel in a corner is the most dangerous
+        assert!(prob(false, false, 0.0) < 5.0e-4);
+        assert!(prob(true, false, 0.0) > prob(false, false, 0.0));
+        assert!(prob(false, true, 0.0) > prob(false, false, 0.0));
+        assert!(prob(false, false, 1.0) > prob(false, false, 0.0));
+        for &(corner_act, duel_act, wetness) in [
+            (false, false, 0.0),
+            (true, false, 1.0),
+            (false, true, 1.0),
+            (true, true, 0.0),
+        ]
+        .iter()
+        {
+            assert!(prob(true, true, 1.0) > prob(corner_act, duel_act, wetness));
+        }
+
+        // a perfectly consistent driver does not crash
+        assert_eq!(calc_driver_crash_prob(1.0, true, true, 1.0, 2.5, &pars), 0.0);
+
+        // 20 cars, 50 laps in the wet: about 30 % of the lap in corners and 5 % in duels
+        let prob_lap_wet = 0.95 * (0.7 * prob(false, false, 1.0) + 0.3 * prob(true, false, 1.0))
+            + 0.05 * (0.7 * prob(false, true, 1.0) + 0.3 * prob(true, true, 1.0));
+        let no_crashes_wet = 20.0 * 50.0 * prob_lap_wet;
+        assert!(no_crashes_wet > 2.0 && no_crashes_wet < 4.0);
+    }
+
+    /// count_wet_crashes runs `no_races` test races on a fully wet track (green flag throughout)
+    /// in which both drivers have a consistency of 0.9 and the crash probability is scaled up by
+    /// `prob_scale`. It returns the number of driver crashes and the number of laps driven.
+    fn count_wet_crashes(no_races: u32, prob_scale: f64) -> (u32, u32) {
+        let (mut sim_pars, mut sim_consts, tire_config) = test_support::load_test_pars();
+        sim_consts.driver_crash_probability *= prob_scale;
+        sim_consts.sc_probability = 0.0;
+        sim_consts.secondary_collision_probability = 0.0;
+        sim_pars.race_pars.vsc_probability = 0.0;
+        sim_pars.race_pars.red_flag_probability = 0.0;
+        sim_pars.race_pars.initial_weather = String::from("Rain");
+        sim_pars.race_pars.start_behind_sc_laps = 0;
+        sim_pars.race_pars.rain_probability = 0.0;
+        for driver_pars in sim_pars.driver_pars_all.values_mut() {
+            driver_pars.consistency = 0.9;
+        }
+
+        let mut no_crashes = 0;
+        let mut no_laps = 0;
+
+        for _ in 0..no_races {
+            let mut race =
+                test_support::create_race_with_timestep(&sim_pars, &sim_consts, &tire_config, 0.25);
+            while !race.get_all_finished() {
+                race.simulate_timestep();
+            }
+
+            let result = race.get_race_result();
+            no_crashes += result.events.iter().filter(|ev| ev.kind == "Crash").count() as u32;
+            no_laps += (0..result.car_driver_pairs.len())
+                .map(|idx| result.get_last_driven_lap(idx) as u32)
+                .sum::<u32>();
+        }
+
+        (no_crashes, no_laps)
+    }
+
+    #[test]
+    fn test_wet_race_crash_count() {
+        // the crash probability is scaled up by 5 to see enough crashes in a few races
+        let (no_crashes, no_laps) = count_wet_crashes(20, 5.0);
+        let no_crashes_wet = no_crashes as f64 / 5.0 / no_laps as f64 * 20.0 * 50.0;
+
+        // about 2 - 4 crashes in a 20 car 50 lap wet race (with a margin for the sampling noise)
+        assert!(no_crashes_wet > 1.0 && no_crashes_wet < 5.0, "{} crashes", no_crashes_wet);
+    }
+
+    fn create_race(model_toggles: ModelToggles) -> Race {
+        let (mut sim_pars, mut sim_consts, tire_config) = test_support::load_test_pars();
+        sim_consts.driver_crash_probability = 1.0;
+        sim_pars.race_pars.vsc_probability = 0.0;
+        sim_pars.race_pars.red_flag_probability = 0.0;
+        sim_pars.race_pars.model_toggles = model_toggles;
+
+        // only the driver of car 44 makes mistakes
+        let initials = sim_pars.car_pars_all[&44].strategy[0].driver_initials.to_owned();
+        sim_pars.driver_pars_all.get_mut(&initials).unwrap().consistency = 0.0;
+
+        test_support::create_race(&sim_pars, &sim_consts, &tire_config)
+    }
+
+    #[test]
+    fn test_driver_crash_retires_car() {
+        let mut race = create_race(ModelToggles::default());
+
+        while race.get_retirements().iter().all(|x| x.is_none()) && race.cur_racetime < 300.0 {
+            race.simulate_timestep();
+        }
+
+        let idx = race.get_car_idx(44).unwrap();
+        let retirement = race.get_retirements()[idx].clone().unwrap();
+        assert_eq!(race.cars_list[idx].status, CarStatus::DNF);
+        assert_eq!(retirement.failure, None);
+
+        let result = race.get_race_result();
+        let crash = result.events.iter().find(|ev| ev.kind == "Crash").unwrap();
+        assert_eq!(crash.cars, vec![44]);
+        assert_eq!(crash.s_range, Some([retirement.s_track, retirement.s_track]));
+
+        // the crash is decided on like every other retirement
+        for _ in 0..10 {
+            race.simulate_timestep();
+        }
+        assert!(race
+            .get_race_result()
+            .events
+            .iter()
+            .any(|ev| ev.kind == "SC_DECISION" && ev.cars == vec![44]));
+    }
+
+    #[test]
+    fn test_no_driver_crashes_without_mistakes() {
+        let mut model_toggles = ModelToggles::default();
+        model_toggles.disable(ModelToggles::MISTAKES);
+        let mut race = create_race(model_toggles);
+
+        while race.cur_racetime < 300.0 {
+            race.simulate_timestep();
+        }
+        assert!(race.get_retirements().iter().all(|x| x.is_none()));
+    }
+}
+
+#[cfg(test)]
+mod puncture_tests {
+    use crate::core::car::RetirementCause;
+    use crate::core::handle_race::run_race;
+    use crate::core::race::{calc_puncture_prob, PuncturePars, Race, SimConstants};
+    use crate::test_support;
+
+    #[test]
+    fn test_puncture_prob_grows_over_cliff() {
+        let pars = PuncturePars {
+            probability_over_cliff: 0.002,
+            debris_probability: 0.05,
+            debris_window_s: 120.0,
+            fallback_compound: "HARD".to_string(),
+        };
+
+        assert_eq!(calc_puncture_prob(0.0, &pars), 0.0);
+        assert!((calc_puncture_prob(5.0, &pars) - 0.01).abs() < 1e-12);
+        assert!(calc_puncture_prob(10.0, &pars) > calc_puncture_prob(5.0, &pars));
+        assert_eq!(calc_puncture_prob(1000.0, &pars), 1.0);
+    }
+
+    /// create_race creates the test race without mechanical failures, random punctures and contacts
+    /// in which the hard tires of car 33 reach their cliff after 5 laps.
+    fn create_race(configure: impl FnOnce(&mut SimConstants)) -> Race {
+        let (mut sim_pars, mut sim_consts, mut tire_config) = test_support::load_test_pars();
+        sim_consts.driver_crash_probability = 0.0;
+        sim_consts.secondary_collision_probability = 0.0;
+        sim_consts.sc_probability = 0.0;
+        sim_pars.race_pars.vsc_probability = 0.0;
+        sim_pars.race_pars.red_flag_probability = 0.0;
+        tire_config.hard.default_cliff_age = 5.0;
+        for driver_pars in sim_pars.driver_pars_all.values_mut() {
+            driver_pars.degr_pars_all.get_mut("HARD").unwrap().cliff_age = Some(5.0);
+            driver_pars.aggression = 0.0;
+        }
+        configure(&mut sim_consts);
+
+        test_support::create_race(&sim_pars, &sim_consts, &tire_config)
+    }
+
+    #[test]
+    fn test_puncture_forces_unscheduled_stop() {
+        let mut race = create_race(|sim_consts| sim_consts.puncture_probability_over_cliff = 1.0);
+        let idx = race.get_car_idx(33).unwrap();
+        let laptime_puncture = race.track.length / race.track.pit_speedlimit;
+
+        // car 33 has no planned stop, it limps to the pits after the puncture (the lap time of a
+        // timestep is calculated before a puncture is drawn in it)
+        while race.get_pit_stops().iter().all(|pit_stop| pit_stop.car_no != 33)
+            || race.cars_list[idx].sh.pit_act
+        {
+            let punctured = race.cars_list[idx].is_punctured();
+            race.simulate_timestep();
+            if punctured && !race.cars_list[idx].sh.pit_act {
+                assert!(race.cur_laptimes[idx] >= laptime_puncture - 1e-9);
+            }
+            assert!(race.cars_list[idx].sh.get_compl_lap() < 20);
+        }
+
+        let result = race.get_race_result();
+        let puncture = result
+            .events
+            .iter()
+            .find(|ev| ev.kind == "PUNCTURE" && ev.cars == vec![33])
+            .unwrap();
+        assert!(puncture.s_range.is_some());
+
+        let pit_stop = result.pit_stops.iter().find(|x| x.car_no == 33).unwrap();
+        assert!(pit_stop.t_standstill > 0.0);
+        assert!(pit_stop.lap >= puncture.lap.saturating_sub(1) && pit_stop.lap <= puncture.lap + 2);
+
+        // a new set of the same compound is fitted
+        let car = &race.cars_list[idx];
+        assert!(!car.is_punctured());
+        assert_eq!(car.get_current_compound().to_uppercase(), "HARD");
+        assert!(car.get_tire_age_cur_stint() < 1.0);
+    }
+
+    #[test]
+    fn test_puncture_on_crash_debris() {
+        let mut race = create_race(|sim_consts| {
+            sim_consts.puncture_debris_probability = 1.0;
+            sim_consts.puncture_debris_window_s = 300.0;
+        });
+        let idx_crash = race.get_car_idx(33).unwrap();
+        let idx = race.get_car_idx(44).unwrap();
+
+        while race.cur_lap_leader < 3 {
+            race.simulate_timestep();
+        }
+        race.retire_cars(&[idx_crash], RetirementCause::Crash);
+        let t_crash = race.cur_racetime;
+
+        // car 44 runs over the debris within the next lap
+        while !race.cars_list[idx].is_punctured() && race.cur_racetime < t_crash + 300.0 {
+            race.simulate_timestep();
+        }
+
+        assert!(race.cars_list[idx].is_punctured());
+        assert!(race
+            .get_race_result()
+            .events
+            .iter()
+            .any(|ev| ev.kind == "PUNCTURE" && ev.cars == vec![44]));
+    }
+
+    #[test]
+    fn test_unknown_fallback_compound_rejected() {
+        let (sim_pars, mut sim_consts, tire_config) = test_support::load_test_pars();
+        assert!(sim_pars.validate_sim_consts(&sim_consts).errors.is_empty());
+
+        sim_consts.puncture_fallback_compound = String::from("hyper");
+        let errors = sim_pars.validate_sim_consts(&sim_consts).errors;
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().all(|msg| msg.contains("puncture_fallback_compound HYPER")));
+
+        // the race is not started
+        assert!(run_race(&sim_pars, &sim_consts, &tire_config, 0.5).is_err());
+    }
+}
+
+#[cfg(test)]
+mod fuel_tests {
+    use crate::core::car::CarStatus;
+    use crate::core::race::{Race, SimConstants};
+    use crate::post::race_result::{
+        ClassificationStatus, RaceEvent, DSQ_FUEL_EVENT_KIND, DSQ_TECHNICAL_EVENT_KIND,
+        LAP_FLAG_FUEL_SAVE,
+    };
+    use crate::pre::read_sim_pars::SimPars;
+    use crate::test_support;
+
+    fn create_race(configure: impl FnOnce(&mut SimPars, &mut SimConstants)) -> Race {
+        let (mut sim_pars, mut sim_consts, tire_config) = test_support::load_test_pars();
+        // no contacts (and no repair stops) in the duels, such that car 33 leads the whole race
+        for driver_pars in sim_pars.driver_pars_all.values_mut() {
+            driver_pars.aggression = 0.0;
+        }
+        configure(&mut sim_pars, &mut sim_consts);
+
+        test_support::create_race(&sim_pars, &sim_consts, &tire_config)
+    }
+
+    #[test]
+    fn test_fuel_burn_gains_pace() {
+        let mut race = create_race(|_, _| {});
+        let idx = race.get_car_idx(33).unwrap();
+
+        // the car is fuelled for the race distance plus the margin
+        let m_fuel_start = race.cars_list[idx].get_fuel_mass();
+        assert!(m_fuel_start > race.cars_list[idx].fuel_needed_for_laps(race.tot_no_laps));
+
+        while !race.get_all_finished() {
+            race.simulate_timestep();
+        }
+
+        // 39 laps at 1.5 kg per lap and 0.035 s/kg between lap 1 and lap 40 make about 2.0 s
+        let breakdowns = race.get_laptime_breakdowns(idx);
+        let t_gain = breakdowns[1].t_fuel - breakdowns[40].t_fuel;
+        assert!(t_gain > 1.5 && t_gain < 2.5, "fuel gain {}", t_gain);
+        assert!(race.cars_list[idx].get_fuel_mass() > 0.0);
+        assert_eq!(race.cars_list[idx].status, CarStatus::Running);
+
+        let result = race.get_race_result();
+        assert!(!result.events.iter().any(|ev| ev.kind == "FUEL_WARNING"));
+    }
+
+    #[test]
+    fn test_car_short_on_fuel_runs_dry() {
+        // without fuel saving (it would only delay the stop)
+        let mut race = create_race(|sim_pars, sim_consts| {
+            sim_pars.car_pars_all.get_mut(&44).unwrap().m_fuel_fixed = Some(30.0);
+            sim_consts.fuel_save_reduction = 0.0;
+        });
+        let idx = race.get_car_idx(44).unwrap();
+
+        // the explicit fuel mass is used as given and the car is warned on the grid
+        assert_eq!(race.cars_list[idx].get_fuel_mass(), 30.0);
+        let result = race.get_race_result();
+        let warning = result.events.iter().find(|ev| ev.kind == "FUEL_WARNING").unwrap();
+        assert_eq!(warning.cars, vec![44]);
+        assert_eq!(warning.lap, 0);
+
+        while race.cars_list[idx].status != CarStatus::DNF && !race.get_all_finished() {
+            race.simulate_timestep();
+        }
+
+        // the car stops at the end of the first lap it could not complete (20 laps at 1.5 kg)
+        let no_laps_fuel = (30.0 / race.cars_list[idx].fuel_needed_for_laps(1)).floor() as u32;
+        let retirement = race.get_retirements()[idx].clone().unwrap();
+        assert_eq!(retirement.lap, no_laps_fuel + 1);
+        assert!(race
+            .get_race_result()
+            .events
+            .iter()
+            .any(|ev| ev.kind == "OUT_OF_FUEL" && ev.cars == vec![44]));
+    }
+
+    #[test]
+    fn test_fuel_save_reaches_finish() {
+        let mut race_ref = create_race(|_, _| {});
+        let idx = race_ref.get_car_idx(44).unwrap();
+        let tot_no_laps = race_ref.tot_no_laps;
+
+        // the car starts with fuel for 3 laps less than the race distance
+        let m_fuel = race_ref.cars_list[idx].fuel_needed_for_laps(tot_no_laps - 3);
+        let mut race = create_race(|sim_pars, _| {
+            sim_pars.car_pars_all.get_mut(&44).unwrap().m_fuel_fixed = Some(m_fuel)
+        });
+
+        while !race_ref.get_all_finished() {
+            race_ref.simulate_timestep();
+        }
+        while !race.get_all_finished() {
+            race.simulate_timestep();
+        }
+
+        // fuel saving brings the car to the finish
+        assert_eq!(race.cars_list[idx].status, CarStatus::Running);
+        assert!(race.cars_list[idx].get_fuel_mass() >= 0.0);
+        assert!(!race.cars_list[idx].is_fuel_save());
+        let result = race.get_race_result();
+        assert!(!result.events.iter().any(|ev| ev.kind == "OUT_OF_FUEL"));
+
+        // the car saves fuel after the first lap until the projection is healthy again
+        let laps_fuel_save: Vec<usize> = (1..=tot_no_laps as usize)
+            .filter(|&lap| result.get_lap_flags(idx, lap) & LAP_FLAG_FUEL_SAVE != 0)
+            .collect();
+        assert!(!laps_fuel_save.is_empty());
+        assert!(!laps_fuel_save.contains(&1));
+        assert!(!laps_fuel_save.contains(&(tot_no_laps as usize)));
+
+        // the middle stint is slower than without fuel saving despite the lighter car
+        let calc_mean_t_fuel = |race: &Race| {
+            let breakdowns = race.get_laptime_breakdowns(idx);
+            (20..=30).map(|lap| breakdowns[lap].t_fuel).sum::<f64>() / 11.0
+        };
+        let t_loss = calc_mean_t_fuel(&race) - calc_mean_t_fuel(&race_ref);
+        assert!(t_loss > 0.3, "fuel save loss {}", t_loss);
+        assert!((20..=30).all(|lap| laps_fuel_save.contains(&lap)));
+    }
+
+    #[test]
+    fn test_car_below_fuel_reserve_is_disqualified() {
+        let race_ref = create_race(|_, _| {});
+        let idx = race_ref.get_car_idx(33).unwrap();
+        let tot_no_laps = race_ref.tot_no_laps;
+
+        // the leading car reaches the finish with 1 kg of fuel, but 2 kg are required
+        let m_fuel = race_ref.cars_list[idx].fuel_needed_for_laps(tot_no_laps) + 1.0;
+        let mut race = create_race(|sim_pars, sim_consts| {
+            sim_pars.car_pars_all.get_mut(&33).unwrap().m_fuel_fixed = Some(m_fuel);
+            sim_consts.fuel_save_reduction = 0.0;
+            sim_consts.fuel_reserve_min_kg = 2.0;
+        });
+
+        while !race.get_all_finished() {
+            race.simulate_timestep();
+        }
+
+        assert_eq!(race.cars_list[idx].status, CarStatus::Running);
+        let result = race.get_race_result();
+
+        // the disqualified car keeps its lap times but is classified last
+        assert_eq!(result.get_status(idx), ClassificationStatus::Dsq);
+        assert_eq!(result.get_last_driven_lap(idx), tot_no_laps as usize);
+        assert_eq!(
+            result.get_final_positions()[idx],
+            result.car_driver_pairs.len() as u32
+        );
+        assert!((0..result.car_driver_pairs.len())
+            .filter(|&i| i != idx)
+            .all(|i| result.get_status(i) == ClassificationStatus::Finished));
+
+        let dsq = result.events.iter().find(|ev| ev.kind == DSQ_FUEL_EVENT_KIND).unwrap();
+        assert_eq!(dsq.cars, vec![33]);
+        assert_eq!(dsq.lap, tot_no_laps);
+        assert!(!result.events.iter().any(|ev| ev.kind == DSQ_TECHNICAL_EVENT_KIND));
+    }
+
+    #[test]
+    fn test_technical_infringement_disqualifies_finished_cars() {
+        // every car fails the technical check, car 44 retires before the finish
+        let mut race = create_race(|sim_pars, sim_consts| {
+            sim_consts.dsq_technical_probability = 1.0;
+            sim_pars.race_pars.scripted_events =
+                serde_json::from_str(r#"[{"lap": 20, "type": "Dnf", "car_no": 44}]"#).unwrap();
+        });
+        let idx_dnf = race.get_car_idx(44).unwrap();
+        let idx_dsq = race.get_car_idx(33).unwrap();
+
+        while !race.get_all_finished() {
+            race.simulate_timestep();
+        }
+
+        // only the car that finished is checked
+        let result = race.get_race_result();
+        assert_eq!(result.get_status(idx_dsq), ClassificationStatus::Dsq);
+        assert_eq!(result.get_status(idx_dnf), ClassificationStatus::Dnf);
+        assert_eq!(result.format_status_suffix(idx_dsq), " (DSQ)");
+
+        let dsqs: Vec<&RaceEvent> = result
+            .events
+            .iter()
+            .filter(|ev| ev.kind == DSQ_TECHNICAL_EVENT_KIND || ev.kind == DSQ_FUEL_EVENT_KIND)
+            .collect();
+        assert_eq!(dsqs.len(), 1);
+        assert_eq!(dsqs[0].kind, DSQ_TECHNICAL_EVENT_KIND);
+        assert_eq!(dsqs[0].cars, vec![33]);
+    }
+}
+
+#[cfg(test)]
+mod ers_tests {
+    use crate::core::car::ErsPars;
+    use crate::core::race::calc_ers_duel;
+
+    const OVERTAKE_THRESHOLD: f64 = 0.2;
+    const TIMESTEP_SIZE: f64 = 0.1;
+
+    fn get_ers_pars() -> ErsPars {
+        ErsPars {
+            capacity: 4.0,
+            t_gain: 0.5,
+            deploy_rate: 0.12,
+            harvest_per_lap: 2.0,
+            t_harvest_loss: 0.1,
+        }
+    }
+
+    /// simulate_duel lets the cars fight with the given battery energies (MJ) and returns the
+    /// number of steps until the car behind passes (None if it is held up) and the remaining
+    /// energies.
+    fn simulate_duel(
+        pace_diff: f64,
+        energy_front: f64,
+        energy_rear: f64,
+    ) -> (Option<u32>, f64, f64) {
+        let ers_pars = get_ers_pars();
+        let mut energies = [energy_front, energy_rear];
+
+        for step in 0..1000 {
+            let (deploy_front, deploy_rear, pace_diff_ers) = calc_ers_duel(
+                pace_diff,
+                OVERTAKE_THRESHOLD,
+                energies[0],
+                energies[1],
+                &ers_pars,
+            );
+            if pace_diff_ers > OVERTAKE_THRESHOLD {
+                return (Some(step), energies[0], energies[1]);
+            }
+
+            for (energy, deploy) in energies.iter_mut().zip([deploy_front, deploy_rear].iter()) {
+                if *deploy {
+                    *energy = (*energy - ers_pars.deploy_rate * TIMESTEP_SIZE).max(0.0);
+                }
+            }
+        }
+
+        (None, energies[0], energies[1])
+    }
+
+    #[test]
+    fn test_ers_duel_until_depleted() {
+        // the slower car behind attacks, the defender answers until both batteries are empty,
+        // then the faster car in front keeps the position
+        let (step_pass, energy_front, energy_rear) = simulate_duel(-0.1, 4.0, 4.0);
+        assert!(step_pass.is_none());
+        assert_eq!(energy_front, 0.0);
+        assert_eq!(energy_rear, 0.0);
+
+        // the faster car behind passes despite the defense
+        let (step_pass, _, _) = simulate_duel(0.3, 4.0, 4.0);
+        assert_eq!(step_pass, Some(0));
+
+        // empty batteries: no deployment, the base pace decides
+        let (deploy_front, deploy_rear, pace_diff_ers) =
+            calc_ers_duel(0.3, OVERTAKE_THRESHOLD, 0.0, 0.0, &get_ers_pars());
+        assert!(!deploy_front && !deploy_rear);
+        assert_eq!(pace_diff_ers, 0.3);
+    }
+
+    #[test]
+    fn test_ers_defender_runs_dry_first() {
+        // the defender has half a battery, the attacker passes once it is empty (2 MJ at
+        // 0.012 MJ per step)
+        let (step_pass, energy_front, energy_rear) = simulate_duel(-0.1, 2.0, 4.0);
+        let step_pass = step_pass.unwrap();
+        assert!((166..=168).contains(&step_pass), "pass in step {}", step_pass);
+        assert_eq!(energy_front, 0.0);
+        assert!(energy_rear > 1.9);
+
+        // a car without ERS advantage does not attack a car it cannot pass
+        let (deploy_front, deploy_rear, _) =
+            calc_ers_duel(-0.4, OVERTAKE_THRESHOLD, 4.0, 4.0, &get_ers_pars());
+        assert!(!deploy_front && !deploy_rear);
+    }
+}
+
+#[cfg(test)]
+mod engine_mode_tests {
+    use crate::core::car::EngineMode;
+    use crate::core::race::{select_engine_mode, EngineModeSelectPars};
+    use crate::test_support;
+
+    #[test]
+    fn test_select_engine_mode() {
+        let pars = EngineModeSelectPars {
+            auto: true,
+            push_gap_s: 3.0,
+            conserve_gap_s: 10.0,
+            conserve_laps: 10,
+        };
+
+        // within undercut range of the car ahead (also late in the race)
+        assert_eq!(select_engine_mode(Some(2.0), Some(1.0), 30, &pars), EngineMode::Push);
+        assert_eq!(select_engine_mode(Some(2.0), Some(15.0), 5, &pars), EngineMode::Push);
+
+        // large gap behind only counts late in the race
+        assert_eq!(select_engine_mode(Some(5.0), Some(15.0), 30, &pars), EngineMode::Standard);
+        assert_eq!(select_engine_mode(Some(5.0), Some(15.0), 5, &pars), EngineMode::Conserve);
+        assert_eq!(select_engine_mode(Some(5.0), Some(4.0), 5, &pars), EngineMode::Standard);
+
+        // leader without a car behind
+        assert_eq!(select_engine_mode(None, None, 3, &pars), EngineMode::Conserve);
+    }
+
+    #[test]
+    fn test_engine_mode_from_strategy() {
+        let (mut sim_pars, sim_consts, tire_config) = test_support::load_test_pars();
+
+        // car 44 pushes in the first stint (then automatic choice), car 33 conserves all race
+        sim_pars.car_pars_all.get_mut(&44).unwrap().strategy[0].engine_mode = Some(EngineMode::Push);
+        sim_pars.car_pars_all.get_mut(&33).unwrap().strategy[0].engine_mode =
+            Some(EngineMode::Conserve);
+
+        let mut race = test_support::create_race(&sim_pars, &sim_consts, &tire_config);
+        let idx_ham = race.get_car_idx(44).unwrap();
+        let idx_ver = race.get_car_idx(33).unwrap();
+        let m_fuel_start = race.cars_list[idx_ver].get_fuel_mass();
+
+        while !race.get_all_finished() {
+            race.simulate_timestep();
+        }
+
+        // the mode of every lap is recorded in the result
+        let result = race.get_race_result();
+        assert!((1..=13).all(|lap| result.get_engine_mode(idx_ham, lap) == EngineMode::Push));
+        let no_laps_ver = result.no_laps_completed[idx_ver];
+        assert!((1..=no_laps_ver as usize)
+            .all(|lap| result.get_engine_mode(idx_ver, lap) == EngineMode::Conserve));
+
+        // conserve mode costs lap time and saves fuel
+        let t_loss = race.get_laptime_breakdowns(idx_ver)[1].t_car
+            - sim_pars.car_pars_all[&33].t_car;
+        assert!((t_loss - sim_consts.engine_conserve_t_loss_s).abs() < 1e-9);
+        let m_fuel_burnt = m_fuel_start - race.cars_list[idx_ver].get_fuel_mass();
+        let m_fuel_burnt_exp = race.cars_list[idx_ver].fuel_needed_for_laps(no_laps_ver)
+            * sim_consts.engine_conserve_fuel_factor;
+        assert!((m_fuel_burnt - m_fuel_burnt_exp).abs() < 1e-6);
+    }
+}
+
+#[cfg(test)]
+mod grid_penalty_tests {
+    use crate::core::race::{apply_grid_penalties, Race};
+    use crate::test_support;
+
+    #[test]
+    fn test_apply_grid_penalties() {
+        let p_grids = [1, 2, 3, 4, 5, 6];
+
+        // without penalties the grid stays as it is
+        assert_eq!(apply_grid_penalties(&p_grids, &[0; 6]), vec![1, 2, 3, 4, 5, 6]);
+
+        // P2 gets 3 places, P3 to P5 move up
+        assert_eq!(
+            apply_grid_penalties(&p_grids, &[0, 3, 0, 0, 0, 0]),
+            vec![1, 5, 2, 3, 4, 6]
+        );
+
+        // P1 (+3) and P2 (+2) collide on P4, the better qualified car gets the slot
+        assert_eq!(
+            apply_grid_penalties(&p_grids, &[3, 2, 0, 0, 0, 0]),
+            vec![4, 5, 1, 2, 3, 6]
+        );
+
+        // penalties beyond the end of the field end on the last slots (larger target further back)
+        assert_eq!(
+            apply_grid_penalties(&p_grids, &[0, 0, 0, 0, 5, 2]),
+            vec![1, 2, 3, 4, 6, 5]
+        );
+        assert_eq!(
+            apply_grid_penalties(&p_grids, &[10, 0, 0, 0, 0, 0]),
+            vec![6, 1, 2, 3, 4, 5]
+        );
+    }
+
+    fn create_race(grid_penalties: &[(u32, u32)]) -> Race {
+        let (mut sim_pars, sim_consts, tire_config) = test_support::load_test_inputs();
+        for &(car_no, places) in grid_penalties.iter() {
+            sim_pars.race_pars.grid_penalties.insert(car_no, places);
+        }
+
+        test_support::create_race(&sim_pars, &sim_consts, &tire_config)
+    }
+
+    #[test]
+    fn test_grid_penalty_in_race() {
+        // car 44 qualified on pole and drops one place behind car 33
+        let race = create_race(&[(44, 1)]);
+        let idx_ham = race.get_car_idx(44).unwrap();
+        let idx_ver = race.get_car_idx(33).unwrap();
+        assert_eq!(race.cars_list[idx_ham].p_grid, 2);
+        assert_eq!(race.cars_list[idx_ver].p_grid, 1);
+
+        let result = race.get_race_result();
+        let grid: Vec<(u32, u32, u32)> = result
+            .starting_grid
+            .iter()
+            .map(|slot| (slot.car_no, slot.p_grid_nominal, slot.penalty_places))
+            .collect();
+        assert_eq!(grid, vec![(33, 2, 0), (44, 1, 1)]);
+
+        // back-of-grid penalty
+        let race = create_race(&[(44, 99)]);
+        let result = race.get_race_result();
+        assert_eq!(result.starting_grid[1].car_no, 44);
+        assert!(result.starting_grid[1].back_of_grid);
+        assert_eq!(result.starting_grid[1].penalty_places, 0);
+    }
+}
+
+#[cfg(test)]
+mod setup_tests {
+    use crate::core::car::SetupPars;
+    use crate::core::race::{calc_setup_overtake_factor, SimConstants};
+    use crate::core::track::Track;
+    use crate::pre::read_sim_pars::SimPars;
+
+    fn get_setup_pars() -> SetupPars {
+        let sim_consts: SimConstants =
+            serde_json::from_str(include_str!("../../input/parameters/sim_constants.json")).unwrap();
+        SetupPars {
+            corner_t_loss: sim_consts.setup_corner_t_loss_s,
+            drag_t_loss: sim_consts.setup_drag_t_loss_s,
+            wear_sensitivity: sim_consts.setup_wear_sensitivity,
+            overtake_sensitivity: sim_consts.setup_overtake_sensitivity,
+        }
+    }
+
+    /// find_optimal_downforce sweeps the downforce level in steps of 0.01.
+    fn find_optimal_downforce(track: &Track, setup_pars: &SetupPars) -> f64 {
+        (0..=100)
+            .map(|x| x as f64 / 100.0)
+            .min_by(|a, b| {
+                setup_pars
+                    .calc_t_delta(*a, track)
+                    .partial_cmp(&setup_pars.calc_t_delta(*b, track))
+                    .unwrap()
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_optimal_downforce_depends_on_track() {
+        let sim_pars: SimPars =
+            serde_json::from_str(include_str!("../../input/parameters/test_race.json")).unwrap();
+        let setup_pars = get_setup_pars();
+
+        // the neutral setup costs nothing, the optimum lies strictly inside the range
+        let mut track = Track::new(&sim_pars.track_pars);
+        assert!(setup_pars.calc_t_delta(0.5, &track).abs() < 1e-9);
+        let downforce_opt = find_optimal_downforce(&track, &setup_pars);
+        assert!(downforce_opt > 0.1 && downforce_opt < 0.9);
+        assert!(setup_pars.calc_t_delta(downforce_opt, &track) <= 1e-9);
+        assert!(setup_pars.calc_t_delta(0.0, &track) > 0.0);
+        assert!(setup_pars.calc_t_delta(1.0, &track) > 0.0);
+
+        // longer straights favour less downforce, more corners favour more downforce
+        track.overtaking_zones_lap_frac *= 2.0;
+        assert!(find_optimal_downforce(&track, &setup_pars) < downforce_opt - 0.1);
+        track.overtaking_zones_lap_frac /= 2.0;
+        track.corners_lap_frac *= 2.0;
+        assert!(find_optimal_downforce(&track, &setup_pars) > downforce_opt + 0.1);
+    }
+
+    #[test]
+    fn test_downforce_wear_and_overtaking() {
+        let setup_pars = get_setup_pars();
+
+        assert!((setup_pars.get_wear_factor(0.5) - 1.0).abs() < 1e-9);
+        assert!(setup_pars.get_wear_factor(1.0) < 1.0);
+        assert!(setup_pars.get_wear_factor(0.0) > 1.0);
+
+        // neutral setups do not change the overtaking threshold
+        assert!((calc_setup_overtake_factor(0.5, 0.5, true, &setup_pars) - 1.0).abs() < 1e-9);
+
+        // high downforce of the attacker makes overtaking harder
+        assert!(calc_setup_overtake_factor(0.5, 1.0, false, &setup_pars) > 1.0);
+
+        // high downforce of the defender only matters against an attacker with DRS
+        assert!((calc_setup_overtake_factor(1.0, 0.5, false, &setup_pars) - 1.0).abs() < 1e-9);
+        assert!(calc_setup_overtake_factor(1.0, 0.5, true, &setup_pars) < 1.0);
+    }
+}
+
+#[cfg(test)]
+mod overtake_contact_tests {
+    use crate::core::car::CarStatus;
+    use crate::core::race::{OvertakeContactOutcome, OvertakeContactPars, Race};
+    use crate::test_support;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn get_overtake_contact_pars() -> OvertakeContactPars {
+        OvertakeContactPars {
+            probability: 0.01,
+            t_loss_s: [1.5, 5.0],
+            spin_probability: 0.3,
+            spin_t_loss_s: [8.0, 15.0],
+            dnf_probability: 0.15,
+            double_dnf_probability: 0.3,
+            attacker_share: 0.7,
+        }
+    }
+
+    #[test]
+    fn test_probability_scales_with_aggression_and_consistency() {
+        let mut pars = get_overtake_contact_pars();
+        let calc_rate = |pars: &OvertakeContactPars, aggressions, consistencies| {
+            -(1.0 - pars.calc_probability(aggressions, consistencies, 1.0, 0.1)).ln() / 0.1
+        };
+
+        assert!((calc_rate(&pars, [0.5, 0.5], [1.0, 1.0]) - 0.01).abs() < 1e-9);
+        assert!((calc_rate(&pars, [1.0, 1.0], [1.0, 1.0]) - 0.02).abs() < 1e-9);
+        assert!((calc_rate(&pars, [0.5, 0.5], [0.0, 0.0]) - 0.02).abs() < 1e-9);
+        assert!((calc_rate(&pars, [0.5, 0.5], [0.5, 1.0]) - 0.0125).abs() < 1e-9);
+
+        // wet track
+        let probability_wet = pars.calc_probability([0.5, 0.5], [1.0, 1.0], 2.0, 0.1);
+        assert!(probability_wet > pars.calc_probability([0.5, 0.5], [1.0, 1.0], 1.0, 0.1));
+
+        pars.probability = 0.0;
+        assert_eq!(pars.calc_probability([1.0, 1.0], [0.0, 0.0], 2.0, 0.1), 0.0);
+    }
+
+    #[test]
+    fn test_outcome_shares() {
+        let pars = get_overtake_contact_pars();
+        let mut rng = StdRng::seed_from_u64(290);
+        let no_draws = 10000;
+        let (mut no_dnf, mut no_double_dnf, mut no_spin, mut no_rear) = (0, 0, 0, 0);
+
+        for _ in 0..no_draws {
+            match pars.draw_outcome(&mut rng) {
+                OvertakeContactOutcome::TimeLoss { t_loss_front, t_loss_rear } => {
+                    assert!((1.5..=5.0).contains(&t_loss_front));
+                    assert!((1.5..=5.0).contains(&t_loss_rear));
+                }
+                OvertakeContactOutcome::Spin { rear, t_loss } => {
+                    assert!((8.0..=15.0).contains(&t_loss));
+                    no_spin += 1;
+                    no_rear += rear as u32;
+                }
+                OvertakeContactOutcome::Dnf { rear, both } => {
+                    no_dnf += 1;
+                    no_double_dnf += both as u32;
+                    no_rear += rear as u32;
+                }
+            }
+        }
+
+        let share = |no: u32, no_tot: u32| no as f64 / no_tot as f64;
+        assert!((share(no_dnf, no_draws) - 0.15).abs() < 0.02);
+        assert!((share(no_spin, no_draws) - 0.3).abs() < 0.02);
+        assert!((share(no_double_dnf, no_dnf) - 0.3).abs() < 0.05);
+
+        // the attacker spins or retires more often
+        assert!((share(no_rear, no_dnf + no_spin) - 0.7).abs() < 0.05);
+    }
+
+    /// create_race creates a race in which the faster VER (33) starts directly behind HAM (44),
+    /// both on hard tires without a stop, and every failed attack in a corner ends with the
+    /// retirement of the attacker.
+    fn create_race() -> Race {
+        let (mut sim_pars, mut sim_consts, tire_config) = test_support::load_test_pars();
+        sim_consts.overtake_contact_spin_probability = 0.0;
+        sim_consts.overtake_contact_dnf_probability = 1.0;
+        sim_consts.overtake_contact_double_dnf_probability = 0.0;
+        sim_consts.overtake_contact_attacker_share = 1.0;
+        sim_pars.race_pars.overtake_contact_probability = 1.0e3;
+        sim_pars.race_pars.use_drs = false;
+        // a seed with a normal start (an unusual start of HAM or a minor contact in the first lap
+        // lets VER pass without an attack in a corner)
+        sim_pars.race_pars.seed = Some(1);
+        // the grid positions of the test track are counted towards the finish line
+        for (car_no, p_grid, t_car) in [(44, 2, 0.0), (33, 1, -1.0)] {
+            let car_pars = sim_pars.car_pars_all.get_mut(&car_no).unwrap();
+            car_pars.p_grid = p_grid;
+            car_pars.t_car = t_car;
+            car_pars.strategy.truncate(1);
+            car_pars.strategy[0].compound = "HARD".to_string();
+        }
+        test_support::create_race(&sim_pars, &sim_consts, &tire_config)
+    }
+
+    #[test]
+    fn test_contact_retirement_in_race() {
+        let mut race = create_race();
+        while race.get_retirements().iter().all(|x| x.is_none()) && race.cur_lap_leader < 10 {
+            race.simulate_timestep();
+        }
+
+        // the attacker retires, the event contains both cars (attacker last)
+        let idx_ver = race.get_car_idx(33).unwrap();
+        assert_eq!(race.cars_list[idx_ver].status, CarStatus::DNF);
+        let result = race.get_race_result();
+        let crash = result.events.iter().find(|ev| ev.kind == "Crash").unwrap();
+        assert_eq!(crash.cars, vec![44, 33]);
+
+        // the retirement is decided on like every other retirement
+        for _ in 0..10 {
+            race.simulate_timestep();
+        }
+        assert!(race
+            .get_race_result()
+            .events
+            .iter()
+            .any(|ev| ev.kind == "SC_DECISION" && ev.cars == vec![33]));
+    }
+}
+
+#[cfg(test)]
+mod overheat_race_tests {
+    use crate::core::race::Race;
+    use crate::test_support;
+
+    /// create_race creates a seeded race in which the faster VER (33) starts directly behind HAM
+    /// (44), both on hard tires without a stop, without DRS and without contacts in the close
+    /// fight.
+    fn create_race(overheat_heat_rate: f64) -> Race {
+        let (mut sim_pars, mut sim_consts, tire_config) = test_support::load_test_pars();
+        sim_consts.overheat_heat_rate = overheat_heat_rate;
+        sim_pars.race_pars.use_drs = false;
+        // the grid positions of the test track are counted towards the finish line
+        for (car_no, p_grid, t_car) in [(44, 2, 0.0), (33, 1, -0.4)] {
+            let car_pars = sim_pars.car_pars_all.get_mut(&car_no).unwrap();
+            car_pars.p_grid = p_grid;
+            car_pars.t_car = t_car;
+            car_pars.strategy.truncate(1);
+            car_pars.strategy[0].compound = "HARD".to_string();
+        }
+        for driver_pars in sim_pars.driver_pars_all.values_mut() {
+            driver_pars.aggression = 0.0;
+        }
+        sim_pars.race_pars.seed = Some(1);
+        test_support::create_race(&sim_pars, &sim_consts, &tire_config)
+    }
+
+    /// get_gaps returns the gaps of VER behind HAM at the end of the first ten laps.
+    fn get_gaps(overheat_heat_rate: f64) -> Vec<f64> {
+        let mut race = create_race(overheat_heat_rate);
+        race.simulate_until_leader_lap(11);
+        let result = race.get_race_result();
+        let idx_ham = race.get_car_idx(44).unwrap();
+        let idx_ver = race.get_car_idx(33).unwrap();
+        (1..=10)
+            .map(|lap| result.racetimes[idx_ver][lap] - result.racetimes[idx_ham][lap])
+            .collect()
+    }
+
+    #[test]
+    fn test_following_car_drops_back_when_overheating() {
+        // the faster car catches up, overheats in the dirty air and drops back until it cools down
+        let gaps = get_gaps(0.02);
+        assert!(gaps[1..].iter().all(|gap| (0.95..=1.3).contains(gap)), "gaps {:?}", gaps);
+
+        // without overheating it stays right behind the slower car (or passes it)
+        let gaps = get_gaps(0.0);
+        assert!(gaps[1..].iter().all(|gap| *gap < 0.95), "gaps {:?}", gaps);
+    }
+}
@@ -0,0 +1,313 @@
+use crate::post::output_fmt::OutputFormat;
+use crate::post::provenance::{prepend_header, Provenance};
+use crate::post::race_result::{write_output_file, RaceResult};
+use std::fmt::Write;
+use std::str::FromStr;
+
+/// H2HPair is a pair of cars that is compared in the head-to-head report, given as
+/// `<car_no_a>:<car_no_b>` (e.g. 44:63). There are no teams in the simulation, therefore the
+/// pairs have to be stated explicitly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct H2HPair {
+    pub car_no_a: u32,
+    pub car_no_b: u32,
+}
+
+impl FromStr for H2HPair {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("Invalid head-to-head pair '{}' (expected e.g. 44:63)!", s);
+        let (car_no_a, car_no_b) = s.split_once(':').ok_or_else(err)?;
+        let car_no_a: u32 = car_no_a.trim().parse().map_err(|_| err())?;
+        let car_no_b: u32 = car_no_b.trim().parse().map_err(|_| err())?;
+
+        if car_no_a == car_no_b {
+            return Err(format!(
+                "Head-to-head pair '{}' compares a car with itself!",
+                s
+            ));
+        }
+
+        Ok(H2HPair { car_no_a, car_no_b })
+    }
+}
+
+/// H2HReport contains the head-to-head comparison of two cars over a batch of runs. All arrays
+/// are ordered as [car a, car b]. The results do not contain a qualifying session, therefore
+/// only the race is compared.
+/// * `no_runs` - Number of runs in which both cars took part
+/// * `no_ahead` - Number of runs in which the car finished ahead of the other (both classified)
+/// * `no_dnf` - Number of runs in which only this car did not finish
+/// * `no_dnf_both` - Number of runs in which both cars did not finish
+/// * `mean_gap` - (s) Mean race time gap of car b to car a at the finish (positive: car a ahead),
+///   only runs in which both cars finished on the same lap, None if there was no such run
+/// * `mean_laps_led` - Mean number of laps led per run
+/// * `mean_no_pitstops` - Mean number of pit stops per run
+/// * `mean_t_standstill` - (s) Mean standstill time of the pit stops, None without pit stops
+#[derive(Debug, Clone, PartialEq)]
+pub struct H2HReport {
+    pub car_nos: [u32; 2],
+    pub driver_initials: [String; 2],
+    pub no_runs: u32,
+    pub no_ahead: [u32; 2],
+    pub no_dnf: [u32; 2],
+    pub no_dnf_both: u32,
+    pub mean_gap: Option<f64>,
+    pub mean_laps_led: [f64; 2],
+    pub mean_no_pitstops: [f64; 2],
+    pub mean_t_standstill: [Option<f64>; 2],
+}
+
+/// head_to_head creates the head-to-head report of every given pair over the results of a
+/// Monte Carlo batch. Runs in which one of the cars did not take part are ignored, runs in which
+/// one or both of the cars did not finish are counted separately and excluded from the gaps.
+pub fn head_to_head(pairs: &[H2HPair], results: &[RaceResult]) -> Vec<H2HReport> {
+    pairs
+        .iter()
+        .map(|pair| calc_h2h_report(pair, results))
+        .collect()
+}
+
+fn calc_h2h_report(pair: &H2HPair, results: &[RaceResult]) -> H2HReport {
+    let car_nos = [pair.car_no_a, pair.car_no_b];
+    let mut driver_initials = [String::from("-"), String::from("-")];
+    let mut no_runs = 0;
+    let mut no_ahead = [0; 2];
+    let mut no_dnf = [0; 2];
+    let mut no_dnf_both = 0;
+    let mut gap_sum = 0.0;
+    let mut no_gaps = 0;
+    let mut laps_led = [0; 2];
+    let mut no_pitstops = [0; 2];
+    let mut t_standstill_sum = [0.0; 2];
+
+    for result in results.iter() {
+        let find_idx = |car_no: u32| {
+            result
+                .car_driver_pairs
+                .iter()
+                .position(|pair| pair.car_no == car_no)
+        };
+
+        let idxs = match (find_idx(car_nos[0]), find_idx(car_nos[1])) {
+            (Some(idx_a), Some(idx_b)) => [idx_a, idx_b],
+            _ => continue,
+        };
+
+        no_runs += 1;
+
+        for (i, &idx) in idxs.iter().enumerate() {
+            driver_initials[i] = result.car_driver_pairs[idx].driver_initials.clone();
+        }
+
+        // classification
+        let dnf = [
+            result.get_retirement(idxs[0]).is_some(),
+            result.get_retirement(idxs[1]).is_some(),
+        ];
+
+        match dnf {
+            [true, true] => no_dnf_both += 1,
+            [true, false] => no_dnf[0] += 1,
+            [false, true] => no_dnf[1] += 1,
+            [false, false] => {
+                let positions = result.get_final_positions();
+
+                if positions[idxs[0]] < positions[idxs[1]] {
+                    no_ahead[0] += 1;
+                } else {
+                    no_ahead[1] += 1;
+                }
+
+                // lapped cars have no comparable race time gap
+                let last_laps = [
+                    result.get_last_driven_lap(idxs[0]),
+                    result.get_last_driven_lap(idxs[1]),
+                ];
+
+                if last_laps[0] == last_laps[1] {
+                    gap_sum += result.get_racetime(idxs[1], last_laps[1])
+                        - result.get_racetime(idxs[0], last_laps[0]);
+                    no_gaps += 1;
+                }
+            }
+        }
+
+        // laps led
+        for lap in 1..=result.tot_no_laps {
+            let leader = match result.get_snapshot(lap) {
+                Some(snapshot) => snapshot.car_nos.first().copied(),
+                None => None,
+            };
+
+            for i in 0..2 {
+                if leader == Some(car_nos[i]) {
+                    laps_led[i] += 1;
+                }
+            }
+        }
+
+        // pit stop execution
+        for pit_stop in result.pit_stops.iter() {
+            for i in 0..2 {
+                if pit_stop.car_no == car_nos[i] {
+                    no_pitstops[i] += 1;
+                    t_standstill_sum[i] += pit_stop.t_standstill;
+                }
+            }
+        }
+    }
+
+    let calc_mean = |sum: f64, no: u32| {
+        if no > 0 {
+            Some(sum / no as f64)
+        } else {
+            None
+        }
+    };
+
+    H2HReport {
+        car_nos,
+        driver_initials,
+        no_runs,
+        no_ahead,
+        no_dnf,
+        no_dnf_both,
+        mean_gap: calc_mean(gap_sum, no_gaps),
+        mean_laps_led: [0, 1].map(|i| calc_mean(laps_led[i] as f64, no_runs).unwrap_or(0.0)),
+        mean_no_pitstops: [0, 1].map(|i| calc_mean(no_pitstops[i] as f64, no_runs).unwrap_or(0.0)),
+        mean_t_standstill: [0, 1].map(|i| calc_mean(t_standstill_sum[i], no_pitstops[i])),
+    }
+}
+
+/// format_h2h_table creates the head-to-head table as it is printed to the console, one block
+/// per pair.
+pub fn format_h2h_table(
+    reports: &[H2HReport],
+    out_fmt: &OutputFormat,
+) -> Result<String, std::fmt::Error> {
+    let mut tmp_string = String::new();
+    let fmt_opt = |val: Option<f64>, prec: usize| match val {
+        Some(val) => out_fmt.fmt_num(val, 8, prec),
+        None => format!("{:>8}", "-"),
+    };
+
+    for report in reports.iter() {
+        writeln!(
+            tmp_string,
+            "#{} {} vs. #{} {} ({} runs)",
+            report.car_nos[0],
+            report.driver_initials[0],
+            report.car_nos[1],
+            report.driver_initials[1],
+            report.no_runs
+        )?;
+        writeln!(
+            tmp_string,
+            "  {:<22} {:>8} {:>8}",
+            "", report.driver_initials[0], report.driver_initials[1]
+        )?;
+        writeln!(
+            tmp_string,
+            "  {:<22} {:>8} {:>8}",
+            "Finished ahead", report.no_ahead[0], report.no_ahead[1]
+        )?;
+        writeln!(
+            tmp_string,
+            "  {:<22} {:>8} {:>8}",
+            "DNF (alone)", report.no_dnf[0], report.no_dnf[1]
+        )?;
+        writeln!(
+            tmp_string,
+            "  {:<22} {} {}",
+            "Laps led (mean)",
+            out_fmt.fmt_num(report.mean_laps_led[0], 8, 1),
+            out_fmt.fmt_num(report.mean_laps_led[1], 8, 1)
+        )?;
+        writeln!(
+            tmp_string,
+            "  {:<22} {} {}",
+            "Pit stops (mean)",
+            out_fmt.fmt_num(report.mean_no_pitstops[0], 8, 2),
+            out_fmt.fmt_num(report.mean_no_pitstops[1], 8, 2)
+        )?;
+        writeln!(
+            tmp_string,
+            "  {:<22} {} {}",
+            "Standstill (mean, s)",
+            fmt_opt(report.mean_t_standstill[0], 2),
+            fmt_opt(report.mean_t_standstill[1], 2)
+        )?;
+        writeln!(tmp_string, "  DNF (both): {}", report.no_dnf_both)?;
+        writeln!(
+            tmp_string,
+            "  Race gap {} -> {} (mean, s): {}",
+            report.driver_initials[0],
+            report.driver_initials[1],
+            fmt_opt(report.mean_gap, 3).trim_start()
+        )?;
+    }
+
+    Ok(tmp_string)
+}
+
+/// format_h2h_csv creates the CSV representation of the head-to-head reports, one row per pair.
+pub fn format_h2h_csv(
+    reports: &[H2HReport],
+    out_fmt: &OutputFormat,
+) -> Result<String, std::fmt::Error> {
+    let sep = out_fmt.csv_separator();
+    let fmt_opt = |val: Option<f64>, prec: usize| match val {
+        Some(val) => out_fmt.fmt_num(val, 0, prec),
+        None => String::new(),
+    };
+    let mut tmp_string = String::new();
+
+    writeln!(
+        tmp_string,
+        "car_no_a{s}car_no_b{s}driver_a{s}driver_b{s}no_runs{s}no_ahead_a{s}no_ahead_b{s}\
+         no_dnf_a{s}no_dnf_b{s}no_dnf_both{s}mean_gap{s}mean_laps_led_a{s}mean_laps_led_b{s}\
+         mean_no_pitstops_a{s}mean_no_pitstops_b{s}mean_t_standstill_a{s}mean_t_standstill_b",
+        s = sep
+    )?;
+
+    for report in reports.iter() {
+        writeln!(
+            tmp_string,
+            "{}{s}{}{s}{}{s}{}{s}{}{s}{}{s}{}{s}{}{s}{}{s}{}{s}{}{s}{}{s}{}{s}{}{s}{}{s}{}{s}{}",
+            report.car_nos[0],
+            report.car_nos[1],
+            report.driver_initials[0],
+            report.driver_initials[1],
+            report.no_runs,
+            report.no_ahead[0],
+            report.no_ahead[1],
+            report.no_dnf[0],
+            report.no_dnf[1],
+            report.no_dnf_both,
+            fmt_opt(report.mean_gap, 3),
+            out_fmt.fmt_num(report.mean_laps_led[0], 0, 2),
+            out_fmt.fmt_num(report.mean_laps_led[1], 0, 2),
+            out_fmt.fmt_num(report.mean_no_pitstops[0], 0, 2),
+            out_fmt.fmt_num(report.mean_no_pitstops[1], 0, 2),
+            fmt_opt(report.mean_t_standstill[0], 3),
+            fmt_opt(report.mean_t_standstill[1], 3),
+            s = sep
+        )?;
+    }
+
+    Ok(tmp_string)
+}
+
+/// write_h2h_to_csv writes the head-to-head reports to a CSV file (default:
+/// output/head_to_head.csv). Returns the path to the written file.
+pub fn write_h2h_to_csv(
+    reports: &[H2HReport],
+    path: Option<&std::path::Path>,
+    out_fmt: &OutputFormat,
+    provenance: Option<&Provenance>,
+) -> anyhow::Result<String> {
+    let content = prepend_header(format_h2h_csv(reports, out_fmt)?, provenance);
+    write_output_file(path, "head_to_head.csv", &content)
+}
@@ -0,0 +1,55 @@
+use crate::core::race::EventObserver;
+use crate::post::output_fmt::OutputFormat;
+use crate::post::race_result::RaceEvent;
+use anyhow::Context;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+/// events_log_observer returns an observer that appends every race event to a text log at the
+/// given path as soon as it happens. The lines match the events log written after the race, but
+/// without the standings context that is only known at the end.
+pub fn events_log_observer(path: &Path, out_fmt: OutputFormat) -> anyhow::Result<EventObserver> {
+    file_observer(path, move |event| Ok(event.format(&out_fmt)?))
+}
+
+/// jsonl_observer returns an observer that appends every race event as a single JSON line to the
+/// file at the given path as soon as it happens.
+pub fn jsonl_observer(path: &Path) -> anyhow::Result<EventObserver> {
+    file_observer(path, |event| Ok(format!("{}\n", serde_json::to_string(event)?)))
+}
+
+/// file_observer creates (or truncates) the file and returns an observer that writes every
+/// formatted event to it. The first write error is reported and all further events are skipped
+/// such that a broken stream does not affect the simulation.
+fn file_observer<F>(path: &Path, format_event: F) -> anyhow::Result<EventObserver>
+where
+    F: Fn(&RaceEvent) -> anyhow::Result<String> + 'static,
+{
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .context(format!("Failed to create directory {}!", dir.display()))?;
+    }
+
+    let mut file = File::create(path)
+        .context(format!("Failed to create event stream {}!", path.display()))?;
+    let path = path.to_path_buf();
+    let mut failed = false;
+
+    Ok(Box::new(move |event: &RaceEvent| {
+        if failed {
+            return;
+        }
+
+        let result = format_event(event).and_then(|line| {
+            file.write_all(line.as_bytes())?;
+            file.flush()?;
+            Ok(())
+        });
+
+        if let Err(e) = result {
+            eprintln!("WARNING: Could not write event stream {}: {}", path.display(), e);
+            failed = true;
+        }
+    }))
+}
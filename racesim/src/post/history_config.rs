@@ -0,0 +1,76 @@
+use crate::post::race_recording::RECORDING_FRAME_DT;
+use std::path::PathBuf;
+
+/// Default memory budget of the per-step histories in bytes.
+pub const HISTORY_MAX_MEMORY_BYTES: usize = 256 * 1024 * 1024;
+
+/// HistoryConfig contains the settings of every feature that accumulates data during the race
+/// (e.g. the recording of the race states). It is consulted by every accumulation site such that
+/// long races with small timesteps do not run out of memory.
+/// * `recording_frame_dt` - (s) Race time between two recorded frames (decimation of the steps)
+/// * `max_memory_bytes` - Memory budget of the histories. If it would be exceeded, the recording
+///   is spilled to disk (if a spill file is set) or switches to a coarser decimation.
+/// * `recording_spill_path` - Path of a file the recorded frames are moved to when the budget is
+///   reached (OPTIONAL)
+#[derive(Debug, Clone)]
+pub struct HistoryConfig {
+    pub recording_frame_dt: f64,
+    pub max_memory_bytes: usize,
+    pub recording_spill_path: Option<PathBuf>,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        HistoryConfig {
+            recording_frame_dt: RECORDING_FRAME_DT,
+            max_memory_bytes: HISTORY_MAX_MEMORY_BYTES,
+            recording_spill_path: None,
+        }
+    }
+}
+
+/// HistoryBudget keeps a running estimate of the memory used by the histories.
+/// * `max_bytes` - Memory budget
+/// * `bytes_used` - Estimated memory currently used
+/// * `peak_bytes` - Highest estimate so far
+#[derive(Debug, Clone, Default)]
+pub struct HistoryBudget {
+    pub max_bytes: usize,
+    pub bytes_used: usize,
+    pub peak_bytes: usize,
+}
+
+impl HistoryBudget {
+    pub fn new(max_bytes: usize) -> HistoryBudget {
+        HistoryBudget {
+            max_bytes,
+            bytes_used: 0,
+            peak_bytes: 0,
+        }
+    }
+
+    /// fits returns true if the given number of bytes can be added without exceeding the budget.
+    pub fn fits(&self, bytes: usize) -> bool {
+        self.bytes_used + bytes <= self.max_bytes
+    }
+
+    /// add adds the given number of bytes to the estimate.
+    pub fn add(&mut self, bytes: usize) {
+        self.bytes_used += bytes;
+        self.peak_bytes = self.peak_bytes.max(self.bytes_used);
+    }
+
+    /// release removes the given number of bytes from the estimate.
+    pub fn release(&mut self, bytes: usize) {
+        self.bytes_used = self.bytes_used.saturating_sub(bytes);
+    }
+}
+
+/// fmt_bytes formats a number of bytes in kB or MB for the console output.
+pub fn fmt_bytes(bytes: usize) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    } else {
+        format!("{:.1} kB", bytes as f64 / 1024.0)
+    }
+}
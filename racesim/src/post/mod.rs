@@ -1 +1,10 @@
+pub mod aggregate;
+pub mod event_stream;
+pub mod history_config;
+pub mod output_fmt;
+pub mod provenance;
+pub mod race_recording;
 pub mod race_result;
+pub mod report;
+pub mod result_diff;
+pub mod velocity_trace;
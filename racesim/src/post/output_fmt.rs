@@ -0,0 +1,121 @@
+use std::str::FromStr;
+
+/// Lang selects the language of the captions and axis labels used in the output artifacts.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Lang {
+    #[default]
+    Pl,
+    En,
+}
+
+impl FromStr for Lang {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "pl" => Ok(Lang::Pl),
+            "en" => Ok(Lang::En),
+            _ => Err(format!("Unknown output language '{}' (expected pl or en)!", s)),
+        }
+    }
+}
+
+/// OutputStrings is the strings table used for plot captions and axis labels.
+#[derive(Debug)]
+pub struct OutputStrings {
+    pub caption_laptime: &'static str,
+    pub caption_speed: &'static str,
+    pub caption_race_trace: &'static str,
+    pub caption_positions: &'static str,
+    pub caption_strategy: &'static str,
+    pub caption_velocity_trace: &'static str,
+    pub averaged_suffix: &'static str,
+    pub axis_lap: &'static str,
+    pub axis_position: &'static str,
+    pub axis_gap: &'static str,
+    pub axis_distance: &'static str,
+    pub axis_velocity: &'static str,
+    pub legend_lap_flags: &'static str,
+}
+
+static STRINGS_PL: OutputStrings = OutputStrings {
+    caption_laptime: "Czas okrążenia",
+    caption_speed: "Średnia prędkość na okrążeniach",
+    caption_race_trace: "Strata do lidera",
+    caption_positions: "Pozycje",
+    caption_strategy: "Strategie opon",
+    caption_velocity_trace: "Prędkość na okrążeniu",
+    averaged_suffix: "uśrednione z {} prób",
+    axis_lap: "Okrążenie",
+    axis_position: "Pozycja",
+    axis_gap: "Strata (s)",
+    axis_distance: "Dystans (m)",
+    axis_velocity: "Prędkość (km/h)",
+    legend_lap_flags: "T - ruch za wolniejszym autem, SC - SC/VSC/żółta flaga, P - wjazd/wyjazd z alei, M - błąd kierowcy, D - uszkodzenia, F - oszczędzanie paliwa",
+};
+
+static STRINGS_EN: OutputStrings = OutputStrings {
+    caption_laptime: "Lap time",
+    caption_speed: "Average speed per lap",
+    caption_race_trace: "Gap to the leader",
+    caption_positions: "Positions",
+    caption_strategy: "Tire strategies",
+    caption_velocity_trace: "Speed over the lap",
+    averaged_suffix: "averaged over {} runs",
+    axis_lap: "Lap",
+    axis_position: "Position",
+    axis_gap: "Gap (s)",
+    axis_distance: "Distance (m)",
+    axis_velocity: "Speed (km/h)",
+    legend_lap_flags: "T - traffic behind a slower car, SC - SC/VSC/yellow flag, P - pit in/out lap, M - driver mistake, D - damage, F - fuel saving",
+};
+
+/// OutputFormat bundles the locale settings (language and decimal separator) that are applied
+/// when writing results to the console, text files, CSV files, and plots.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OutputFormat {
+    pub lang: Lang,
+    pub decimal_comma: bool,
+}
+
+impl OutputFormat {
+    /// fmt_num formats a number with the given width and precision and replaces the decimal point
+    /// by a comma if indicated.
+    pub fn fmt_num(&self, val: f64, width: usize, prec: usize) -> String {
+        let tmp = format!("{:width$.prec$}", val, width = width, prec = prec);
+        if self.decimal_comma {
+            tmp.replace('.', ",")
+        } else {
+            tmp
+        }
+    }
+
+    /// csv_separator returns the field separator for CSV files. A semicolon is used in
+    /// comma-decimal mode such that the files remain machine-parseable.
+    pub fn csv_separator(&self) -> char {
+        if self.decimal_comma {
+            ';'
+        } else {
+            ','
+        }
+    }
+
+    /// strings returns the strings table for the selected language.
+    pub fn strings(&self) -> &'static OutputStrings {
+        match self.lang {
+            Lang::Pl => &STRINGS_PL,
+            Lang::En => &STRINGS_EN,
+        }
+    }
+
+    /// caption_averaged appends the "averaged over n runs" hint to a plot caption.
+    pub fn caption_averaged(&self, caption: &str, no_runs: u32) -> String {
+        format!(
+            "{} ({})",
+            caption,
+            self.strings()
+                .averaged_suffix
+                .replace("{}", &no_runs.to_string())
+        )
+    }
+}
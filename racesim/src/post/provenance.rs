@@ -0,0 +1,163 @@
+use crate::pre::read_sim_pars::SimPars;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// Provenance documents how the output artifacts of a run were produced. It is assembled once per
+/// run and embedded into every exported file (plot footer, file header, JSON field, HTML report).
+/// * `scenario` - Parameter file(s) of the scenario
+/// * `seed` - Random seed of the run (run i of a Monte Carlo batch uses seed + i, None: unknown)
+/// * `timestep_size` - (s) Time step size of the simulation
+/// * `version` - Version of the race simulator
+/// * `timestamp` - Start of the run (UTC, ISO 8601)
+/// * `config_hash` - Fingerprint of the resolved simulation parameters (see `calc_config_hash`)
+/// * `disabled_components` - Model components switched off for the run (ablation)
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Provenance {
+    pub scenario: String,
+    pub seed: Option<u64>,
+    pub timestep_size: f64,
+    pub version: String,
+    pub timestamp: String,
+    pub config_hash: String,
+    #[serde(default)]
+    pub disabled_components: Vec<String>,
+}
+
+impl Provenance {
+    /// new assembles the provenance of a run started now.
+    pub fn new(
+        scenario: &str,
+        seed: Option<u64>,
+        timestep_size: f64,
+        sim_pars: &SimPars,
+    ) -> anyhow::Result<Provenance> {
+        let t_now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .context("Failed to determine the current time!")?
+            .as_secs();
+
+        Ok(Provenance {
+            scenario: scenario.to_owned(),
+            seed,
+            timestep_size,
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            timestamp: fmt_utc_timestamp(t_now),
+            config_hash: calc_config_hash(sim_pars)?,
+            disabled_components: sim_pars.race_pars.model_toggles.into(),
+        })
+    }
+
+    /// fmt_seed returns the seed as string ("none" if the run was not seeded).
+    fn fmt_seed(&self) -> String {
+        self.seed.map_or_else(|| String::from("none"), |seed| seed.to_string())
+    }
+
+    /// get_footer returns the single line drawn below the plots.
+    pub fn get_footer(&self) -> String {
+        let mut footer = format!(
+            "{} | seed {} | dt {:.3}s | racesim {} | {}",
+            self.scenario,
+            self.fmt_seed(),
+            self.timestep_size,
+            self.version,
+            self.timestamp
+        );
+
+        if !self.disabled_components.is_empty() {
+            write!(footer, " | disabled: {}", self.disabled_components.join(",")).unwrap();
+        }
+
+        footer
+    }
+
+    /// get_fields returns the fields as (key, value) pairs, e.g. for the HTML report.
+    pub fn get_fields(&self) -> Vec<(&'static str, String)> {
+        let mut fields = vec![
+            ("scenario", self.scenario.to_owned()),
+            ("seed", self.fmt_seed()),
+            ("timestep_size", format!("{:.3}s", self.timestep_size)),
+            ("version", format!("racesim {}", self.version)),
+            ("timestamp", self.timestamp.to_owned()),
+            ("config_hash", self.config_hash.to_owned()),
+        ];
+
+        if !self.disabled_components.is_empty() {
+            fields.push(("disabled_components", self.disabled_components.join(",")));
+        }
+
+        fields
+    }
+
+    /// format_header returns the fields as comment lines (one field per line) that are placed in
+    /// front of the content of text and CSV outputs.
+    pub fn format_header(&self, comment_prefix: &str) -> String {
+        let mut header = String::new();
+
+        for (key, value) in self.get_fields() {
+            writeln!(header, "{} {}: {}", comment_prefix, key, value).unwrap();
+        }
+
+        header
+    }
+}
+
+/// prepend_header returns the content with the provenance header in front (if available).
+pub fn prepend_header(content: String, provenance: Option<&Provenance>) -> String {
+    match provenance {
+        Some(provenance) => provenance.format_header("#") + &content,
+        None => content,
+    }
+}
+
+/// calc_config_hash calculates a fingerprint (FNV-1a, 64 bit, hex) of the resolved simulation
+/// parameters. The parameters are converted into a JSON value first such that the keys are
+/// sorted and the hash does not depend on the order of the hash maps.
+pub fn calc_config_hash(sim_pars: &SimPars) -> anyhow::Result<String> {
+    let mut sim_pars_json =
+        serde_json::to_value(sim_pars).context("Failed to serialize simulation parameters!")?;
+
+    // the seed is recorded on its own, runs of a Monte Carlo batch share the configuration
+    if let Some(race_pars) = sim_pars_json["race_pars"].as_object_mut() {
+        race_pars.remove("seed");
+    }
+    let sim_pars_json = sim_pars_json.to_string();
+
+    Ok(format!("{:016x}", fnv1a_64(sim_pars_json.as_bytes())))
+}
+
+/// fnv1a_64 calculates the 64 bit FNV-1a hash of the data (stable across platforms and
+/// compiler versions, contrary to the hasher of the standard library).
+fn fnv1a_64(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// fmt_utc_timestamp formats seconds since the UNIX epoch as UTC time stamp in ISO 8601 format,
+/// e.g. 2023-11-14T22:13:20Z.
+pub fn fmt_utc_timestamp(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let secs_of_day = secs % 86400;
+
+    // conversion of the days since the epoch into a civil date (proleptic Gregorian calendar)
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    )
+}
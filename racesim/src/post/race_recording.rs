@@ -0,0 +1,276 @@
+use crate::core::track::TrackPars;
+use anyhow::Context;
+use serde::ser::{Error as SerError, SerializeSeq, Serializer};
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Default race time step between two recorded frames in seconds.
+pub const RECORDING_FRAME_DT: f64 = 0.5;
+
+/// * `car_no` - Car number
+/// * `driver_initials` - Initials of the start driver
+/// * `color` - RGB color of the car
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RecordedCar {
+    pub car_no: u32,
+    pub driver_initials: String,
+    pub color: [u8; 3],
+}
+
+/// * `t` - (s) Race time of the frame
+/// * `lap_leader` - Current lap of the leader
+/// * `race_progs` - Race progress of every car (same order as `RaceRecording::cars`)
+/// * `sc_race_prog` - Race progress of the safety car (only if it is on track)
+/// * `flag_state` - Flag state as shown in the GUI banner
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RecordedFrame {
+    pub t: f64,
+    pub lap_leader: u32,
+    pub race_progs: Vec<f64>,
+    pub sc_race_prog: Option<f64>,
+    pub flag_state: String,
+}
+
+impl RecordedFrame {
+    /// estimate_bytes returns the estimated memory used by the frame.
+    pub fn estimate_bytes(&self) -> usize {
+        std::mem::size_of::<RecordedFrame>()
+            + self.race_progs.capacity() * std::mem::size_of::<f64>()
+            + self.flag_state.capacity()
+    }
+}
+
+/// RaceRecording contains the race states sampled with a fixed frame time step such that the race
+/// can be rendered offline (e.g. to an animated GIF) without running the simulation again. Frames
+/// moved to the spill file (see `spill_frames`) precede the frames kept in memory.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RaceRecording {
+    pub track_pars: TrackPars,
+    pub tot_no_laps: u32,
+    pub frame_dt: f64,
+    pub cars: Vec<RecordedCar>,
+    pub frames: Vec<RecordedFrame>,
+    #[serde(skip)]
+    pub spill_path: Option<PathBuf>,
+    #[serde(skip)]
+    pub no_spilled_frames: usize,
+}
+
+impl RaceRecording {
+    /// validate checks that the recording can be replayed, i.e. that its frame time step is a
+    /// positive number (frames are looked up by dividing the race time by it).
+    pub fn validate(&self) -> anyhow::Result<()> {
+        check_frame_dt(self.frame_dt)
+    }
+
+    /// get_duration returns the recorded race time in seconds.
+    pub fn get_duration(&self) -> f64 {
+        self.frames.last().map_or(0.0, |frame| frame.t)
+    }
+
+    /// decimate keeps every second frame in memory and doubles the frame time step accordingly.
+    /// It returns the memory released (estimated).
+    pub fn decimate(&mut self) -> usize {
+        let bytes_before: usize = self.frames.iter().map(|frame| frame.estimate_bytes()).sum();
+
+        let mut idx = 0;
+        self.frames.retain(|_| {
+            idx += 1;
+            idx % 2 == 1
+        });
+        self.frame_dt *= 2.0;
+
+        let bytes_after: usize = self.frames.iter().map(|frame| frame.estimate_bytes()).sum();
+        bytes_before - bytes_after
+    }
+
+    /// spill_frames appends the frames in memory to the spill file (one JSON frame per line) and
+    /// removes them from memory. It returns the memory released (estimated).
+    pub fn spill_frames(&mut self) -> anyhow::Result<usize> {
+        let spill_path = self
+            .spill_path
+            .to_owned()
+            .ok_or_else(|| anyhow::anyhow!("No spill file set for the recording!"))?;
+
+        if let Some(parent) = spill_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let fh = OpenOptions::new()
+            .create(true)
+            .append(self.no_spilled_frames > 0)
+            .write(true)
+            .truncate(self.no_spilled_frames == 0)
+            .open(&spill_path)
+            .context(format!("Failed to open spill file {}!", spill_path.display()))?;
+        let mut writer = BufWriter::new(fh);
+
+        let mut bytes = 0;
+        for frame in self.frames.iter() {
+            serde_json::to_writer(&mut writer, frame)
+                .context(format!("Failed to write spill file {}!", spill_path.display()))?;
+            writeln!(writer)?;
+            bytes += frame.estimate_bytes();
+        }
+        writer.flush()?;
+
+        self.no_spilled_frames += self.frames.len();
+        self.frames = Vec::new();
+        Ok(bytes)
+    }
+
+    /// load_spilled_frames moves the frames of the spill file back into memory (e.g. for
+    /// rendering).
+    pub fn load_spilled_frames(&mut self) -> anyhow::Result<()> {
+        if self.no_spilled_frames == 0 {
+            return Ok(());
+        }
+
+        let mut frames = self.read_spilled_frames()?;
+        frames.append(&mut self.frames);
+        self.frames = frames;
+        self.no_spilled_frames = 0;
+        Ok(())
+    }
+
+    /// read_spilled_frames reads the frames of the spill file.
+    fn read_spilled_frames(&self) -> anyhow::Result<Vec<RecordedFrame>> {
+        let spill_path = match &self.spill_path {
+            Some(spill_path) if self.no_spilled_frames > 0 => spill_path,
+            _ => return Ok(Vec::new()),
+        };
+
+        let fh = OpenOptions::new()
+            .read(true)
+            .open(spill_path)
+            .context(format!("Failed to open spill file {}!", spill_path.display()))?;
+
+        let mut frames = Vec::with_capacity(self.no_spilled_frames);
+        for line in BufReader::new(fh).lines() {
+            let frame = serde_json::from_str(&line?)
+                .context(format!("Failed to parse spill file {}!", spill_path.display()))?;
+            frames.push(frame);
+        }
+
+        Ok(frames)
+    }
+
+    /// write_to_file writes the recording as JSON to the given path. Spilled frames are streamed
+    /// from the spill file, i.e. they are not loaded into memory. Spilled frames that cannot be
+    /// read back (e.g. a truncated last line after an aborted run) are left out, their number is
+    /// returned.
+    pub fn write_to_file(&self, path: &Path) -> anyhow::Result<usize> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let fh = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+            .context(format!("Failed to open recording file {}!", path.display()))?;
+
+        if self.no_spilled_frames == 0 {
+            serde_json::to_writer(&fh, self)
+                .context(format!("Failed to write recording file {}!", path.display()))?;
+            return Ok(0);
+        }
+
+        let spill_path = self
+            .spill_path
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No spill file set for the recording!"))?;
+        let recording = SpilledRecording {
+            track_pars: &self.track_pars,
+            tot_no_laps: self.tot_no_laps,
+            frame_dt: self.frame_dt,
+            cars: &self.cars,
+            frames: SpilledFrames {
+                spill_path,
+                frames: &self.frames,
+                no_spilled_read: Cell::new(0),
+            },
+        };
+
+        let mut writer = BufWriter::new(fh);
+        serde_json::to_writer(&mut writer, &recording)
+            .context(format!("Failed to write recording file {}!", path.display()))?;
+        writer.flush()?;
+
+        Ok(self
+            .no_spilled_frames
+            .saturating_sub(recording.frames.no_spilled_read.get()))
+    }
+}
+
+/// SpilledRecording serializes a recording with spilled frames in the format of `RaceRecording`.
+#[derive(Serialize)]
+struct SpilledRecording<'a> {
+    track_pars: &'a TrackPars,
+    tot_no_laps: u32,
+    frame_dt: f64,
+    cars: &'a [RecordedCar],
+    frames: SpilledFrames<'a>,
+}
+
+/// SpilledFrames serializes the frames of the spill file followed by the frames in memory as a
+/// single sequence. Lines of the spill file that cannot be parsed are skipped, `no_spilled_read`
+/// counts the frames read from the spill file.
+struct SpilledFrames<'a> {
+    spill_path: &'a Path,
+    frames: &'a [RecordedFrame],
+    no_spilled_read: Cell<usize>,
+}
+
+impl Serialize for SpilledFrames<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let fh_spill = OpenOptions::new().read(true).open(self.spill_path).map_err(|e| {
+            S::Error::custom(format!(
+                "Failed to open spill file {}: {}",
+                self.spill_path.display(),
+                e
+            ))
+        })?;
+
+        let mut seq = serializer.serialize_seq(None)?;
+        for line in BufReader::new(fh_spill).lines() {
+            let line = line.map_err(S::Error::custom)?;
+            if let Ok(frame) = serde_json::from_str::<RecordedFrame>(&line) {
+                seq.serialize_element(&frame)?;
+                self.no_spilled_read.set(self.no_spilled_read.get() + 1);
+            }
+        }
+        for frame in self.frames.iter() {
+            seq.serialize_element(frame)?;
+        }
+        seq.end()
+    }
+}
+
+/// read_race_recording reads a recording written by `RaceRecording::write_to_file`.
+pub fn read_race_recording(path: &Path) -> anyhow::Result<RaceRecording> {
+    let fh = OpenOptions::new()
+        .read(true)
+        .open(path)
+        .context(format!("Failed to open recording file {}!", path.display()))?;
+
+    let recording: RaceRecording = serde_json::from_reader(&fh)
+        .context(format!("Failed to parse recording file {}!", path.display()))?;
+    recording
+        .validate()
+        .context(format!("Invalid recording file {}!", path.display()))?;
+
+    Ok(recording)
+}
+
+/// check_frame_dt returns an error if the frame time step of a recording is not a positive number.
+pub fn check_frame_dt(frame_dt: f64) -> anyhow::Result<()> {
+    if !(frame_dt.is_finite() && frame_dt > 0.0) {
+        anyhow::bail!("Frame time step of the recording must be positive (got {})!", frame_dt)
+    }
+    Ok(())
+}
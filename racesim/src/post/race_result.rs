@@ -1,8 +1,97 @@
 use std::fmt::Write;
 use std::io::Write as IoWrite;
 
+use crate::core::car::{EngineMode, FailureType};
+use crate::core::livery::get_fallback_color;
+use crate::core::race::StartReaction;
+use crate::core::tire_inventory::TireInventory;
+use crate::post::output_fmt::OutputFormat;
+use crate::post::provenance::{prepend_header, Provenance};
+use anyhow::Context;
 use serde::{Serialize, Deserialize};
 
+/// Lap context flags stored per car and lap in `RaceResult::lap_flags`.
+pub const LAP_FLAG_TRAFFIC: u8 = 1 << 0;
+pub const LAP_FLAG_SC: u8 = 1 << 1;
+pub const LAP_FLAG_PIT: u8 = 1 << 2;
+pub const LAP_FLAG_MISTAKE: u8 = 1 << 3;
+pub const LAP_FLAG_DAMAGE: u8 = 1 << 4;
+pub const LAP_FLAG_FUEL_SAVE: u8 = 1 << 5;
+pub const LAP_FLAG_YELLOW: u8 = 1 << 6;
+
+/// LAP_FLAG_CODES contains the flags with their codes and descriptions as used in the outputs.
+pub const LAP_FLAG_CODES: [(u8, &str, &str); 7] = [
+    (LAP_FLAG_TRAFFIC, "T", "traffic behind a slower car"),
+    (LAP_FLAG_SC, "SC", "SC or VSC in part of the lap"),
+    (LAP_FLAG_YELLOW, "Y", "local yellow flag in part of the lap"),
+    (LAP_FLAG_PIT, "P", "pit in or out lap"),
+    (LAP_FLAG_MISTAKE, "M", "driver mistake"),
+    (LAP_FLAG_DAMAGE, "D", "damage present"),
+    (LAP_FLAG_FUEL_SAVE, "F", "fuel saving (lift and coast)"),
+];
+
+/// (s) Finishing gaps below this value are marked as photo finish in the classification.
+pub const PHOTO_FINISH_THRESHOLD: f64 = 0.05;
+
+/// Cars that completed fewer than this fraction of the winner's laps (rounded down) are not
+/// classified.
+pub const CLASSIFICATION_MIN_LAPS_FRAC: f64 = 0.9;
+
+/// Lap and race times of long races are written in blocks of this many laps, every block is
+/// preceded by the car and driver header line.
+pub const LAPS_PER_TEXT_BLOCK: usize = 100;
+
+/// Possible distances (in laps) between two labels of the lap axis of the plots.
+const LAP_AXIS_STEPS: [u32; 10] = [1, 2, 5, 10, 20, 50, 100, 200, 500, 1000];
+
+/// Maximum number of labels on the lap axis of the plots.
+const LAP_AXIS_MAX_LABELS: u32 = 20;
+
+/// LapAxis contains the lap axis of a plot over the laps of a race.
+/// * `range` - Range of the axis (never empty)
+/// * `step` - Distance between two axis labels in laps
+/// * `no_labels` - Number of axis labels
+#[derive(Debug, Clone, PartialEq)]
+pub struct LapAxis {
+    pub range: std::ops::Range<u32>,
+    pub step: u32,
+    pub no_labels: usize,
+}
+
+/// get_lap_axis returns the lap axis of a plot over the given number of laps. A single-lap race
+/// gets the range [0, 2] around lap 1, since the range [1, 1] would be empty. For long races, the
+/// label step is chosen such that at most `LAP_AXIS_MAX_LABELS` labels are drawn.
+pub fn get_lap_axis(tot_no_laps: u32) -> LapAxis {
+    if tot_no_laps <= 1 {
+        return LapAxis {
+            range: 0..2,
+            step: 1,
+            no_labels: 3,
+        };
+    }
+
+    let step = LAP_AXIS_STEPS
+        .iter()
+        .copied()
+        .find(|&step| (tot_no_laps - 1) / step < LAP_AXIS_MAX_LABELS)
+        .unwrap_or_else(|| (tot_no_laps - 1) / (LAP_AXIS_MAX_LABELS - 1) + 1);
+
+    LapAxis {
+        range: 1..tot_no_laps,
+        step,
+        no_labels: ((tot_no_laps - 1) / step + 1) as usize,
+    }
+}
+
+/// format_lap_flags returns the codes of the set flags, e.g. "[T][SC]" (empty if no flag is set).
+pub fn format_lap_flags(flags: u8) -> String {
+    LAP_FLAG_CODES
+        .iter()
+        .filter(|(flag, _, _)| flags & flag != 0)
+        .map(|(_, code, _)| format!("[{}]", code))
+        .collect()
+}
+
 /// CarDriverPair is used to store car number and driver initials for post-processing the results.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CarDriverPair {
@@ -10,9 +99,47 @@ pub struct CarDriverPair {
     pub driver_initials: String,
 }
 
+/// GridSlot contains the effective starting position of a car after the grid penalties were
+/// applied, together with the nominal position and the penalty to make the grid auditable.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct GridSlot {
+    pub car_no: u32,
+    pub p_grid_nominal: u32,
+    pub p_grid: u32,
+    pub penalty_places: u32, // places given as grid penalty (0 for back-of-grid penalties)
+    pub back_of_grid: bool,
+}
+
+/// ClassificationStatus is the status of a car in the final classification.
+/// * `Finished` - Car took the chequered flag and is classified
+/// * `Dnf` - Car retired during the race
+/// * `Dsq` - Car was disqualified by the post-race checks (lap times are kept, classified last)
+/// * `NotClassified` - Car took the chequered flag but completed fewer than
+///   `CLASSIFICATION_MIN_LAPS_FRAC` of the winner's laps
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum ClassificationStatus {
+    Finished,
+    Dnf,
+    Dsq,
+    NotClassified,
+}
+
+impl ClassificationStatus {
+    /// get_code returns the code of the status as used in the classification outputs (None for
+    /// classified cars).
+    pub fn get_code(&self) -> Option<&'static str> {
+        match self {
+            ClassificationStatus::Finished => None,
+            ClassificationStatus::Dnf => Some("DNF"),
+            ClassificationStatus::Dsq => Some("DSQ"),
+            ClassificationStatus::NotClassified => Some("NC"),
+        }
+    }
+}
+
 /// RaceResult contains all race information that is required for post-processing the results.
 /// 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub struct RaceResult {
     pub tot_no_laps: u32,
     pub car_driver_pairs: Vec<CarDriverPair>,
@@ -22,39 +149,102 @@ pub struct RaceResult {
     pub sc_position: f64, //gdzie jest SC
     pub weather_history: Vec<String>,
     pub events: Vec<RaceEvent>,
+    #[serde(default)]
+    pub pit_stops: Vec<PitStopInfo>,
+    #[serde(default)]
+    pub no_pitstops: Vec<u32>, // liczba postojów każdego auta (bez przejazdów przez aleję)
+    #[serde(default)]
+    pub t_pitlane: Vec<f64>, // (s) łączny czas w alei serwisowej każdego auta
+    #[serde(default)]
+    pub tire_inventories: Vec<TireInventory>, // przydział opon aut (tylko w formacie weekendowym)
+    #[serde(default)]
+    pub lap_flags: Vec<Vec<u8>>, // flagi kontekstu (LAP_FLAG_*) każdego okrążenia każdego auta
+    #[serde(default)]
+    pub car_colors: Vec<[u8; 3]>, // kolory aut (RGB) po zastąpieniu brakujących kolorów zastępczymi
+    #[serde(default)]
+    pub track_length: f64, // (m) długość toru (0 w starszych wynikach)
+    #[serde(default)]
+    pub retirements: Vec<Option<RetirementInfo>>, // miejsce i czas wycofania każdego auta (None bez DNF)
+    #[serde(default)]
+    pub time_losses: Vec<TimeLossBreakdown>, // strata każdego auta względem czystego przejazdu
+    #[serde(default)]
+    pub unexecuted_stops: Vec<UnexecutedStop>, // zaplanowane pit stopy, które nie zostały wykonane
+    #[serde(default)]
+    pub no_laps_completed: Vec<u32>, // liczba ukończonych okrążeń każdego auta (np. po limicie czasu)
+    #[serde(default)]
+    pub sc_periods: Vec<ScPeriod>, // okresy SC i VSC w kolejności wystąpienia
+    #[serde(default)]
+    pub engine_modes: Vec<Vec<EngineMode>>, // tryb silnika każdego okrążenia każdego auta
+    #[serde(default)]
+    pub compounds: Vec<Vec<String>>, // mieszanka opon na starcie każdego okrążenia każdego auta
+    #[serde(default)]
+    pub starting_grid: Vec<GridSlot>, // pola startowe po zastosowaniu kar (w kolejności startu)
+    #[serde(default)]
+    pub t_cars: Vec<f64>, // (s) zastosowane t_car każdego auta (np. po rozwoju bolidu w sezonie)
+    #[serde(default)]
+    pub statuses: Vec<ClassificationStatus>, // status każdego auta w klasyfikacji (puste w starszych wynikach)
 }
 
 impl RaceResult {
-    /// write_lap_and_race_times_to_file writes lap and race times to a text file in output/.
-    /// Returns the path to the written file.
-    pub fn write_lap_and_race_times_to_file(
-        &self,
-        path: Option<&std::path::Path>,
-    ) -> anyhow::Result<String> {
-        // Prepare content strings identical to console format
-        let mut tmp_string_laptime = String::new();
-        let mut tmp_string_racetime = String::new();
+    /// new creates a result from the lap and race times of the given cars. All further data is
+    /// empty, as in the results of older versions.
+    pub fn new(
+        tot_no_laps: u32,
+        car_driver_pairs: Vec<CarDriverPair>,
+        laptimes: Vec<Vec<f64>>,
+        racetimes: Vec<Vec<f64>>,
+    ) -> RaceResult {
+        RaceResult {
+            tot_no_laps,
+            car_driver_pairs,
+            laptimes,
+            racetimes,
+            ..Default::default()
+        }
+    }
 
-        for lap in 1..self.tot_no_laps as usize + 1 {
-            write!(&mut tmp_string_laptime, "{:3}, ", lap)?;
-            write!(&mut tmp_string_racetime, "{:3}, ", lap)?;
+    /// format_lap_and_race_times creates the text block containing lap and race times as it is
+    /// printed to the console and written to the text file.
+    pub fn format_lap_and_race_times(&self, out_fmt: &OutputFormat) -> Result<String, std::fmt::Error> {
+        // width of the lap column, such that it stays aligned for races with more than 999 laps
+        let lap_width = self.tot_no_laps.to_string().len().max(3);
+
+        // laps that were not driven (race ended early) are not printed
+        let no_laps_driven = self.get_no_laps_driven();
+
+        // create lines for lap times and race times
+        let mut lines_laptime = Vec::with_capacity(no_laps_driven as usize);
+        let mut lines_racetime = Vec::with_capacity(no_laps_driven as usize);
+
+        for lap in 1..no_laps_driven as usize + 1 {
+            let mut tmp_string_laptime = format!("{:w$}, ", lap, w = lap_width);
+            let mut tmp_string_racetime = format!("{:w$}, ", lap, w = lap_width);
 
             for i in 0..self.car_driver_pairs.len() {
+                let mut laptime = format!("{}s", out_fmt.fmt_num(self.get_laptime(i, lap), 8, 3));
+                let racetime = out_fmt.fmt_num(self.get_racetime(i, lap), 8, 3);
+
+                let flags = self.get_lap_flags(i, lap);
+                if flags != 0 {
+                    write!(&mut laptime, " {}", format_lap_flags(flags))?;
+                }
+
                 if i < self.car_driver_pairs.len() - 1 {
-                    write!(&mut tmp_string_laptime, "{:8.3}s, ", self.laptimes[i][lap])?;
-                    write!(
-                        &mut tmp_string_racetime,
-                        "{:8.3}s, ",
-                        self.racetimes[i][lap]
-                    )?;
+                    write!(&mut tmp_string_laptime, "{}, ", laptime)?;
+                    write!(&mut tmp_string_racetime, "{}s, ", racetime)?;
                 } else {
-                    writeln!(&mut tmp_string_laptime, "{:8.3}s", self.laptimes[i][lap])?;
-                    writeln!(&mut tmp_string_racetime, "{:8.3}s", self.racetimes[i][lap])?;
+                    write!(&mut tmp_string_laptime, "{}", laptime)?;
+                    write!(&mut tmp_string_racetime, "{}s", racetime)?;
                 }
             }
+
+            lines_laptime.push(tmp_string_laptime);
+            lines_racetime.push(tmp_string_racetime);
         }
 
-        let mut tmp_string_car_driver_info = String::from("lap, ");
+        // create string with car and driver info
+        let mut tmp_string_car_driver_info = format!("{:>w$}, ", "lap", w = lap_width);
+
         for (i, car_driver_pair) in self.car_driver_pairs.iter().enumerate() {
             if i < self.car_driver_pairs.len() - 1 {
                 write!(
@@ -73,93 +263,1669 @@ impl RaceResult {
 
         let mut content = String::new();
         writeln!(&mut content, "RESULT: Lap times")?;
-        writeln!(&mut content, "{}", tmp_string_car_driver_info)?;
-        writeln!(&mut content, "{}", tmp_string_laptime)?;
+        write_lap_blocks(&mut content, &tmp_string_car_driver_info, &lines_laptime)?;
+        if self.lap_flags.iter().flatten().any(|&flags| flags != 0) {
+            writeln!(&mut content, "{}", format_lap_flags_legend())?;
+        }
         writeln!(&mut content, "RESULT: Race times")?;
-        writeln!(&mut content, "{}", tmp_string_car_driver_info)?;
-        writeln!(&mut content, "{}", tmp_string_racetime)?;
+        write_lap_blocks(&mut content, &tmp_string_car_driver_info, &lines_racetime)?;
 
-        // Ensure output directory exists
-        let out_dir = std::path::Path::new("output");
-        std::fs::create_dir_all(out_dir)?;
+        Ok(content)
+    }
 
-        // Resolve file path (default to output/last_run.txt)
-        let out_path = if let Some(p) = path { p.to_path_buf() } else { out_dir.join("last_run.txt") };
+    /// format_lap_times_csv creates the CSV content containing one row per lap and one column per
+    /// car. In comma-decimal mode the fields are separated by semicolons.
+    pub fn format_lap_times_csv(&self, out_fmt: &OutputFormat) -> Result<String, std::fmt::Error> {
+        let sep = out_fmt.csv_separator();
+        let mut content = String::from("lap");
+
+        for car_driver_pair in self.car_driver_pairs.iter() {
+            write!(
+                &mut content,
+                "{}{} ({})",
+                sep, car_driver_pair.car_no, car_driver_pair.driver_initials
+            )?;
+        }
+
+        // flags columns (one per car) if the lap context flags are available
+        let with_flags = !self.lap_flags.is_empty();
+        if with_flags {
+            for car_driver_pair in self.car_driver_pairs.iter() {
+                write!(&mut content, "{}{} flags", sep, car_driver_pair.car_no)?;
+            }
+        }
+        writeln!(&mut content)?;
 
-        // Write file
-        let mut file = std::fs::OpenOptions::new()
-            .create(true)
-            .truncate(true)
-            .write(true)
-            .open(&out_path)?;
-        file.write_all(content.as_bytes())?;
-        file.flush()?;
+        for lap in 1..self.get_no_laps_driven() as usize + 1 {
+            write!(&mut content, "{}", lap)?;
+            for i in 0..self.car_driver_pairs.len() {
+                write!(&mut content, "{}{}", sep, out_fmt.fmt_num(self.get_laptime(i, lap), 0, 3))?;
+            }
+            if with_flags {
+                for i in 0..self.car_driver_pairs.len() {
+                    write!(&mut content, "{}{}", sep, format_lap_flags(self.get_lap_flags(i, lap)))?;
+                }
+            }
+            writeln!(&mut content)?;
+        }
 
-        Ok(out_path.to_string_lossy().into_owned())
+        Ok(content)
     }
 
-    /// print_lap_and_race_times prints the resulting lap and race times to the console output.
-    pub fn print_lap_and_race_times(&self) {
-        // create string for lap times and race times
-        let mut tmp_string_laptime = String::new();
-        let mut tmp_string_racetime = String::new();
+    /// get_laptime returns the lap time of a lap of a car (0 if not available).
+    pub fn get_laptime(&self, idx: usize, lap: usize) -> f64 {
+        self.laptimes
+            .get(idx)
+            .and_then(|laptimes| laptimes.get(lap))
+            .copied()
+            .unwrap_or(0.0)
+    }
 
-        for lap in 1..self.tot_no_laps as usize + 1 {
-            write!(&mut tmp_string_laptime, "{:3}, ", lap).unwrap();
-            write!(&mut tmp_string_racetime, "{:3}, ", lap).unwrap();
+    /// get_racetime returns the race time of a car at the end of a lap (0 if not available).
+    pub fn get_racetime(&self, idx: usize, lap: usize) -> f64 {
+        self.racetimes
+            .get(idx)
+            .and_then(|racetimes| racetimes.get(lap))
+            .copied()
+            .unwrap_or(0.0)
+    }
 
-            for i in 0..self.car_driver_pairs.len() {
-                if i < self.car_driver_pairs.len() - 1 {
-                    write!(&mut tmp_string_laptime, "{:8.3}s, ", self.laptimes[i][lap]).unwrap();
-                    write!(
-                        &mut tmp_string_racetime,
-                        "{:8.3}s, ",
-                        self.racetimes[i][lap]
-                    )
-                    .unwrap();
+    /// get_car_color returns the color of a car. If the result does not contain the color (e.g.
+    /// older results or anonymized results), the fallback livery of the car number is used.
+    pub fn get_car_color(&self, idx: usize) -> [u8; 3] {
+        self.car_colors
+            .get(idx)
+            .copied()
+            .unwrap_or_else(|| get_fallback_color(self.car_driver_pairs[idx].car_no))
+    }
+
+    /// get_lap_flags returns the context flags of a lap of a car (0 if not available).
+    pub fn get_lap_flags(&self, idx: usize, lap: usize) -> u8 {
+        self.lap_flags
+            .get(idx)
+            .and_then(|flags| flags.get(lap))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// get_engine_mode returns the engine mode of a lap of a car (standard if not available).
+    pub fn get_engine_mode(&self, idx: usize, lap: usize) -> EngineMode {
+        self.engine_modes
+            .get(idx)
+            .and_then(|engine_modes| engine_modes.get(lap))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// get_stints returns the stints of a car as (first lap, last lap, compound). A stint ends with
+    /// a pit stop or a change of the compound the car started a lap on (empty if not available).
+    pub fn get_stints(&self, idx: usize) -> Vec<(u32, u32, String)> {
+        let mut stints: Vec<(u32, u32, String)> = vec![];
+        let compounds = match self.compounds.get(idx) {
+            Some(compounds) => compounds,
+            None => return stints,
+        };
+        let car_no = self.car_driver_pairs[idx].car_no;
+
+        for lap in 1..=self.get_last_driven_lap(idx).min(compounds.len().saturating_sub(1)) {
+            let lap = lap as u32;
+            let compound = &compounds[lap as usize];
+            let after_stop = self
+                .pit_stops
+                .iter()
+                .any(|pit_stop| pit_stop.car_no == car_no && pit_stop.lap + 1 == lap);
+
+            match stints.last_mut() {
+                Some(stint) if stint.2 == *compound && !after_stop => stint.1 = lap,
+                _ => stints.push((lap, lap, compound.to_owned())),
+            }
+        }
+
+        stints
+    }
+
+    /// write_lap_and_race_times_to_file writes lap and race times to a text file in output/. The
+    /// provenance of the run (if given) is written as commented header. Returns the path to the
+    /// written file.
+    pub fn write_lap_and_race_times_to_file(
+        &self,
+        path: Option<&std::path::Path>,
+        out_fmt: &OutputFormat,
+        provenance: Option<&Provenance>,
+    ) -> anyhow::Result<String> {
+        let content = prepend_header(self.format_lap_and_race_times(out_fmt)?, provenance);
+        write_output_file(path, "last_run.txt", &content)
+    }
+
+    /// write_lap_times_to_csv writes the lap times to a CSV file in output/. The provenance of the
+    /// run (if given) is written as commented header. Returns the path to the written file.
+    pub fn write_lap_times_to_csv(
+        &self,
+        path: Option<&std::path::Path>,
+        out_fmt: &OutputFormat,
+        provenance: Option<&Provenance>,
+    ) -> anyhow::Result<String> {
+        let content = prepend_header(self.format_lap_times_csv(out_fmt)?, provenance);
+        write_output_file(path, "last_run.csv", &content)
+    }
+
+    /// get_distance returns the distance (m) covered by the car with the given index: the completed
+    /// laps and, for a retired car, the part of the lap driven until the retirement. Returns None if
+    /// the track length is not available.
+    pub fn get_distance(&self, idx: usize) -> Option<f64> {
+        if self.track_length <= 0.0 {
+            return None;
+        }
+
+        Some(match self.get_retirement(idx) {
+            Some(retirement) => {
+                retirement.lap as f64 * self.track_length
+                    + retirement.s_track.clamp(0.0, self.track_length)
+            }
+            None => self.get_last_driven_lap(idx) as f64 * self.track_length,
+        })
+    }
+
+    /// get_avg_speed returns the average speed (km/h) of the car with the given index over its
+    /// distance, including neutralizations (SC, VSC) and pit stops. Returns None if the distance or
+    /// the driven time is not available.
+    pub fn get_avg_speed(&self, idx: usize) -> Option<f64> {
+        let distance = self.get_distance(idx)?;
+        let t_driven = match self.get_retirement(idx) {
+            Some(retirement) => retirement.time_s,
+            None => self.get_racetime(idx, self.get_last_driven_lap(idx)),
+        };
+
+        if t_driven > 0.0 {
+            Some(distance / t_driven * 3.6)
+        } else {
+            None
+        }
+    }
+
+    /// get_race_avg_speed returns the average speed (km/h) of the race, i.e. the average speed of
+    /// the winner over the race distance including neutralizations, as quoted by commentators.
+    pub fn get_race_avg_speed(&self) -> Option<f64> {
+        let idx_winner = self.get_final_positions().iter().position(|&pos| pos == 1)?;
+        self.get_avg_speed(idx_winner)
+    }
+
+    /// get_distance_stats returns the distance and average speed of every car and of the race.
+    pub fn get_distance_stats(&self) -> DistanceStats {
+        let idx_winner = self.get_final_positions().iter().position(|&pos| pos == 1);
+
+        DistanceStats {
+            cars: (0..self.car_driver_pairs.len())
+                .map(|idx| CarDistanceStats {
+                    car_no: self.car_driver_pairs[idx].car_no,
+                    distance_km: self.get_distance(idx).map(|distance| distance / 1000.0),
+                    avg_speed_kmh: self.get_avg_speed(idx),
+                    retired: self.get_retirement(idx).is_some(),
+                })
+                .collect(),
+            race_distance_km: idx_winner
+                .and_then(|idx| self.get_distance(idx))
+                .map(|distance| distance / 1000.0),
+            race_avg_speed_kmh: self.get_race_avg_speed(),
+        }
+    }
+
+    /// get_retirement returns the retirement of the car with the given index (None if the car did
+    /// not retire).
+    pub fn get_retirement(&self, idx: usize) -> Option<&RetirementInfo> {
+        self.retirements.get(idx).and_then(|retirement| retirement.as_ref())
+    }
+
+    /// get_status returns the classification status of the car with the given index. Older results
+    /// without statuses only distinguish finished and retired cars.
+    pub fn get_status(&self, idx: usize) -> ClassificationStatus {
+        match self.statuses.get(idx) {
+            Some(status) => *status,
+            None if self.get_retirement(idx).is_some() => ClassificationStatus::Dnf,
+            None => ClassificationStatus::Finished,
+        }
+    }
+
+    /// calc_statuses determines the classification status of every car: the given cars were
+    /// disqualified by the post-race checks, retired cars did not finish and the remaining cars are
+    /// not classified if they completed fewer than `CLASSIFICATION_MIN_LAPS_FRAC` of the laps of
+    /// the winner.
+    pub fn calc_statuses(&mut self, dsq_idxs: &[usize]) {
+        let no_laps_winner = (0..self.car_driver_pairs.len())
+            .filter(|idx| !dsq_idxs.contains(idx))
+            .map(|idx| self.get_last_driven_lap(idx))
+            .max()
+            .unwrap_or(0);
+        let no_laps_min = (CLASSIFICATION_MIN_LAPS_FRAC * no_laps_winner as f64).floor() as usize;
+
+        self.statuses = (0..self.car_driver_pairs.len())
+            .map(|idx| {
+                if dsq_idxs.contains(&idx) {
+                    ClassificationStatus::Dsq
+                } else if self.get_retirement(idx).is_some() {
+                    ClassificationStatus::Dnf
+                } else if self.get_last_driven_lap(idx) < no_laps_min {
+                    ClassificationStatus::NotClassified
                 } else {
-                    writeln!(&mut tmp_string_laptime, "{:8.3}s", self.laptimes[i][lap]).unwrap();
-                    writeln!(&mut tmp_string_racetime, "{:8.3}s", self.racetimes[i][lap]).unwrap();
+                    ClassificationStatus::Finished
                 }
-            }
+            })
+            .collect();
+    }
+
+    /// format_status_suffix returns the status of a car that retired or was not classified, e.g.
+    /// " (DNF)" (empty for classified cars).
+    pub fn format_status_suffix(&self, idx: usize) -> String {
+        self.get_status(idx)
+            .get_code()
+            .map(|code| format!(" ({})", code))
+            .unwrap_or_default()
+    }
+
+    /// get_failure_type returns the type of the mechanical failure that retired the car with the
+    /// given index, None if the car finished or retired for another reason.
+    pub fn get_failure_type(&self, idx: usize) -> Option<FailureType> {
+        self.get_retirement(idx).and_then(|retirement| retirement.failure)
+    }
+
+    /// get_fastest_lap returns the index of the car that drove the fastest lap of the race and
+    /// the lap time. Returns None if no lap was completed.
+    pub fn get_fastest_lap(&self) -> Option<(usize, f64)> {
+        (0..self.car_driver_pairs.len())
+            .flat_map(|idx| {
+                (1..self.tot_no_laps as usize + 1).map(move |lap| (idx, self.get_laptime(idx, lap)))
+            })
+            .filter(|(_, t)| t.is_finite() && *t > 0.0)
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+    }
+
+    /// get_fastest_pit_stop returns the pit stop with the shortest standstill time.
+    pub fn get_fastest_pit_stop(&self) -> Option<&PitStopInfo> {
+        self.pit_stops
+            .iter()
+            .min_by(|a, b| a.t_standstill.partial_cmp(&b.t_standstill).unwrap())
+    }
+
+    /// get_last_driven_lap returns the last lap that was completed by the car with the given index
+    /// (0 if the car did not complete any lap).
+    pub fn get_last_driven_lap(&self, idx: usize) -> usize {
+        (1..self.tot_no_laps as usize + 1)
+            .rev()
+            .find(|&lap| self.get_racetime(idx, lap) > 0.0)
+            .unwrap_or(0)
+    }
+
+    /// get_no_laps_driven returns the number of laps driven by the leading car. It is smaller than
+    /// `tot_no_laps` if the race was ended early (e.g. by the race time limit), the remaining
+    /// entries of the lap time rows are zero then.
+    pub fn get_no_laps_driven(&self) -> u32 {
+        (0..self.car_driver_pairs.len())
+            .map(|idx| self.get_last_driven_lap(idx) as u32)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// get_sc_periods_summary returns the number and the total duration (s) of the periods of the
+    /// given kind.
+    pub fn get_sc_periods_summary(&self, kind: ScPeriodKind) -> (u32, f64) {
+        self.sc_periods
+            .iter()
+            .filter(|sc_period| sc_period.kind == kind)
+            .fold((0, 0.0), |(no_periods, t_tot), sc_period| {
+                (no_periods + 1, t_tot + sc_period.get_duration())
+            })
+    }
+
+    /// get_final_positions returns the final position of every car (same order as
+    /// `car_driver_pairs`). Cars are classified by the number of completed laps and then by race
+    /// time, disqualified cars are moved to the bottom of the classification.
+    pub fn get_final_positions(&self) -> Vec<u32> {
+        let last_laps: Vec<usize> = (0..self.car_driver_pairs.len())
+            .map(|idx| self.get_last_driven_lap(idx))
+            .collect();
+        let dsqs: Vec<bool> = (0..self.car_driver_pairs.len())
+            .map(|idx| self.get_status(idx) == ClassificationStatus::Dsq)
+            .collect();
+
+        let mut idxs_sorted: Vec<usize> = (0..self.car_driver_pairs.len()).collect();
+        idxs_sorted.sort_by(|&a, &b| {
+            dsqs[a].cmp(&dsqs[b]).then_with(|| last_laps[b].cmp(&last_laps[a])).then_with(|| {
+                self.get_racetime(a, last_laps[a])
+                    .partial_cmp(&self.get_racetime(b, last_laps[b]))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+        });
+
+        let mut positions = vec![0; idxs_sorted.len()];
+        for (pos, idx) in idxs_sorted.iter().enumerate() {
+            positions[*idx] = pos as u32 + 1;
         }
 
-        // create string with car and driver info
-        let mut tmp_string_car_driver_info = String::from("lap, ");
+        positions
+    }
 
-        for (i, car_driver_pair) in self.car_driver_pairs.iter().enumerate() {
-            if i < self.car_driver_pairs.len() - 1 {
+    /// get_gap_to_winner returns the gap (s) of the car with the given index to the winner at the
+    /// finish. The finish times are interpolated within the timestep such that the gap is not
+    /// quantized to the timestep size. Returns None if the car completed fewer laps than the winner
+    /// or no lap at all.
+    pub fn get_gap_to_winner(&self, idx: usize) -> Option<f64> {
+        let positions = self.get_final_positions();
+        let idx_winner = positions.iter().position(|&pos| pos == 1)?;
+        let last_lap = self.get_last_driven_lap(idx);
+
+        if last_lap == 0 || last_lap != self.get_last_driven_lap(idx_winner) {
+            return None;
+        }
+
+        Some(self.get_racetime(idx, last_lap) - self.get_racetime(idx_winner, last_lap))
+    }
+
+    /// format_pit_summary creates the pit summary of the car with the given index, e.g.
+    /// "2 stops, 51.3 s". Returns None if the result does not contain pit information.
+    pub fn format_pit_summary(&self, idx: usize, out_fmt: &OutputFormat) -> Option<String> {
+        let no_pitstops = *self.no_pitstops.get(idx)?;
+        let t_pitlane = *self.t_pitlane.get(idx)?;
+
+        Some(format!(
+            "{} {}, {} s",
+            no_pitstops,
+            if no_pitstops == 1 { "stop" } else { "stops" },
+            out_fmt.fmt_num(t_pitlane, 0, 1)
+        ))
+    }
+
+    /// format_classification creates the final classification including the pit summary of every
+    /// car and the gap to the winner. Photo finishes (gap to the car ahead below
+    /// `PHOTO_FINISH_THRESHOLD`) are listed below the classification.
+    pub fn format_classification(&self, out_fmt: &OutputFormat) -> Result<String, std::fmt::Error> {
+        let positions = self.get_final_positions();
+        let mut idxs_sorted: Vec<usize> = (0..positions.len()).collect();
+        idxs_sorted.sort_by_key(|&idx| positions[idx]);
+
+        let mut tmp_string = String::new();
+        let mut photo_finishes = vec![];
+
+        for (pos_idx, &idx) in idxs_sorted.iter().enumerate() {
+            let pair = &self.car_driver_pairs[idx];
+            let dsq = self.get_status(idx) == ClassificationStatus::Dsq;
+            let pos = if dsq {
+                String::from("DSQ")
+            } else {
+                format!("P{}", positions[idx])
+            };
+            write!(&mut tmp_string, "{:<4} #{:<3} {:3}", pos, pair.car_no, pair.driver_initials)?;
+
+            if let Some(pit_summary) = self.format_pit_summary(idx, out_fmt) {
+                write!(&mut tmp_string, "  {}", pit_summary)?;
+            }
+
+            if let (Some(distance), Some(avg_speed)) =
+                (self.get_distance(idx), self.get_avg_speed(idx))
+            {
                 write!(
-                    &mut tmp_string_car_driver_info,
-                    "{:3} ({}), ",
-                    car_driver_pair.car_no, car_driver_pair.driver_initials
-                )
-                .unwrap()
+                    &mut tmp_string,
+                    "  {} km, {} km/h{}",
+                    out_fmt.fmt_num(distance / 1000.0, 0, 1),
+                    out_fmt.fmt_num(avg_speed, 0, 1),
+                    if dsq { String::new() } else { self.format_status_suffix(idx) }
+                )?;
+            }
+
+            if pos_idx > 0 && !dsq {
+                match self.get_gap_to_winner(idx) {
+                    Some(gap) => {
+                        write!(&mut tmp_string, "  +{} s", out_fmt.fmt_num(gap, 0, 3))?;
+
+                        let idx_ahead = idxs_sorted[pos_idx - 1];
+                        if let Some(gap_ahead) = self.get_gap_to_winner(idx_ahead) {
+                            if gap - gap_ahead < PHOTO_FINISH_THRESHOLD {
+                                photo_finishes.push((idx_ahead, idx, gap - gap_ahead));
+                            }
+                        }
+                    }
+                    None => {
+                        let laps_behind = self.get_last_driven_lap(idxs_sorted[0])
+                            - self.get_last_driven_lap(idx);
+                        if laps_behind > 0 {
+                            write!(
+                                &mut tmp_string,
+                                "  +{} {}",
+                                laps_behind,
+                                if laps_behind == 1 { "lap" } else { "laps" }
+                            )?;
+                        }
+                    }
+                }
+            }
+
+            writeln!(&mut tmp_string)?;
+        }
+
+        if let (Some(race_distance), Some(race_avg_speed)) = (
+            idxs_sorted.first().and_then(|&idx| self.get_distance(idx)),
+            self.get_race_avg_speed(),
+        ) {
+            writeln!(
+                &mut tmp_string,
+                "Race distance {} km, average race speed {} km/h (including neutralizations)",
+                out_fmt.fmt_num(race_distance / 1000.0, 0, 1),
+                out_fmt.fmt_num(race_avg_speed, 0, 1)
+            )?;
+        }
+
+        for &(idx_ahead, idx, gap) in photo_finishes.iter() {
+            writeln!(
+                &mut tmp_string,
+                "Photo finish #{} - #{}: {} s",
+                self.car_driver_pairs[idx_ahead].car_no,
+                self.car_driver_pairs[idx].car_no,
+                out_fmt.fmt_num(gap, 0, 4)
+            )?;
+        }
+
+        if !photo_finishes.is_empty() {
+            writeln!(
+                &mut tmp_string,
+                "(finish times are interpolated linearly within a timestep, i.e. assuming constant \
+                 speed - differences of the driven lines below the timestep are not modeled)"
+            )?;
+        }
+
+        Ok(tmp_string)
+    }
+
+    /// anonymized returns a copy of the result in which the car numbers and driver initials are
+    /// replaced by aliases ("Car A", "Car B", ...) in all places, together with the mapping to
+    /// the real identities. The aliases are assigned in the order of the real car numbers such
+    /// that the same field always gets the same aliases.
+    pub fn anonymized(&self) -> (RaceResult, IdentityMap) {
+        let mut idxs_sorted: Vec<usize> = (0..self.car_driver_pairs.len()).collect();
+        idxs_sorted.sort_by_key(|&idx| self.car_driver_pairs[idx].car_no);
+
+        let mut identity_map = IdentityMap { entries: vec![] };
+
+        for (i, &idx) in idxs_sorted.iter().enumerate() {
+            let pair = &self.car_driver_pairs[idx];
+            identity_map.entries.push(IdentityMapEntry {
+                car_no: pair.car_no,
+                driver_initials: pair.driver_initials.to_owned(),
+                alias_car_no: i as u32 + 1,
+                alias: format!("Car {}", get_alias_letters(i)),
+            });
+        }
+
+        let mut result = self.to_owned();
+
+        for pair in result.car_driver_pairs.iter_mut() {
+            let entry = identity_map.get_entry(pair.car_no).unwrap();
+            pair.car_no = entry.alias_car_no;
+            pair.driver_initials = entry.alias.to_owned();
+        }
+
+        for ev in result.events.iter_mut() {
+            for car_no in ev.cars.iter_mut() {
+                *car_no = identity_map.get_alias_car_no(*car_no);
+            }
+
+            if let Some(context) = ev.context.as_mut() {
+                for snapshot in context.before.iter_mut().chain(context.after.iter_mut()) {
+                    for car_no in snapshot.car_nos.iter_mut() {
+                        *car_no = identity_map.get_alias_car_no(*car_no);
+                    }
+                }
+            }
+        }
+
+        for pit_stop in result.pit_stops.iter_mut() {
+            pit_stop.car_no = identity_map.get_alias_car_no(pit_stop.car_no);
+        }
+
+        for inventory in result.tire_inventories.iter_mut() {
+            inventory.car_no = identity_map.get_alias_car_no(inventory.car_no);
+        }
+
+        for stop in result.unexecuted_stops.iter_mut() {
+            stop.car_no = identity_map.get_alias_car_no(stop.car_no);
+        }
+
+        for sc_period in result.sc_periods.iter_mut() {
+            if let Some(car_no) = sc_period.cause_car_no.as_mut() {
+                *car_no = identity_map.get_alias_car_no(*car_no);
+            }
+        }
+
+        // the liveries would reveal the teams, the fallback liveries of the aliases are used
+        result.car_colors.clear();
+
+        (result, identity_map)
+    }
+
+    /// get_snapshot returns the running order and gaps at the end of the given lap (lap 0 is the
+    /// grid). Returns None if the lap lies outside the race or no car completed it.
+    pub fn get_snapshot(&self, lap: u32) -> Option<EventSnapshot> {
+        if lap > self.tot_no_laps {
+            return None;
+        }
+
+        // lap 0 contains the start offsets of the grid, later laps must have been driven
+        let mut entries: Vec<(u32, f64)> = self
+            .car_driver_pairs
+            .iter()
+            .zip(self.racetimes.iter())
+            .filter_map(|(pair, racetimes)| {
+                let racetime = *racetimes.get(lap as usize)?;
+                if racetime.is_finite() && (lap == 0 || racetime > 0.0) {
+                    Some((pair.car_no, racetime))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        if entries.is_empty() {
+            return None;
+        }
+
+        entries.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+        let t_leader = entries[0].1;
+
+        Some(EventSnapshot {
+            lap,
+            car_nos: entries.iter().map(|entry| entry.0).collect(),
+            gaps: entries.iter().map(|entry| entry.1 - t_leader).collect(),
+        })
+    }
+
+    /// classification_at_lap returns the classification at the moment the leader completed the
+    /// given lap, i.e. the order in which the cars crossed the line after that moment (as for the
+    /// chequered flag). A lapped car is therefore classified with the laps it had completed when
+    /// it crossed the line the next time. Cars that had retired until that moment are shown as
+    /// DNF behind the running cars. Returns None if the lap lies outside the race or no car
+    /// completed it.
+    pub fn classification_at_lap(&self, lap: u32) -> Option<LapClassification> {
+        if lap == 0 || lap > self.tot_no_laps {
+            return None;
+        }
+
+        // the classification is frozen when the first car completed the lap
+        let t_freeze = (0..self.car_driver_pairs.len())
+            .map(|idx| self.get_racetime(idx, lap as usize))
+            .filter(|&racetime| racetime.is_finite() && racetime > 0.0)
+            .fold(f64::INFINITY, f64::min);
+
+        if !t_freeze.is_finite() {
+            return None;
+        }
+
+        // determine the progress (completed laps, race time) of every car at the freeze
+        let mut progress: Vec<(usize, u32, f64, bool)> = vec![];
+
+        for idx in 0..self.car_driver_pairs.len() {
+            if let Some(retirement) = self.get_retirement(idx) {
+                if retirement.time_s <= t_freeze {
+                    progress.push((idx, retirement.lap, retirement.time_s, true));
+                    continue;
+                }
+            }
+
+            // first crossing of the line at or after the freeze, or the last crossing before it
+            // if the car did not cross the line again (e.g. retired afterwards)
+            let lap_next = (1..lap as usize + 1).find(|&lap_tmp| {
+                let racetime = self.get_racetime(idx, lap_tmp);
+                racetime.is_finite() && racetime >= t_freeze
+            });
+            let lap_compl = match lap_next {
+                Some(x) => x,
+                None => (1..lap as usize + 1)
+                    .rev()
+                    .find(|&lap_tmp| self.get_racetime(idx, lap_tmp) > 0.0)
+                    .unwrap_or(0),
+            };
+
+            // older results do not contain the retirements, a car that did not complete the lap
+            // and never crossed the line again is then regarded as retired
+            let retired = lap_next.is_none()
+                && self.get_retirement(idx).is_none()
+                && lap_compl < lap as usize;
+
+            progress.push((idx, lap_compl as u32, self.get_racetime(idx, lap_compl), retired));
+        }
+
+        // running cars by progress, retired cars behind them by the laps driven until the DNF
+        progress.sort_by(|a, b| {
+            a.3.cmp(&b.3).then_with(|| b.1.cmp(&a.1)).then_with(|| {
+                let ord = a.2.partial_cmp(&b.2).unwrap_or(std::cmp::Ordering::Equal);
+                if a.3 { ord.reverse() } else { ord }
+            })
+        });
+
+        let t_leader = progress[0].2;
+
+        Some(LapClassification {
+            lap,
+            entries: progress
+                .iter()
+                .map(|&(idx, laps, racetime, retired)| {
+                    let car_no = self.car_driver_pairs[idx].car_no;
+                    LapClassificationEntry {
+                        car_no,
+                        driver_initials: self.car_driver_pairs[idx].driver_initials.to_owned(),
+                        laps,
+                        gap: if !retired && laps == lap { Some(racetime - t_leader) } else { None },
+                        no_pitstops: self
+                            .pit_stops
+                            .iter()
+                            .filter(|pit_stop| pit_stop.car_no == car_no && pit_stop.lap <= laps)
+                            .count() as u32,
+                        retired,
+                    }
+                })
+                .collect(),
+        })
+    }
+
+    /// format_classification_at_lap creates the intermediate classification at the end of the
+    /// given lap (see `classification_at_lap`). Returns None if the lap lies outside the race or no
+    /// car completed it.
+    pub fn format_classification_at_lap(
+        &self,
+        lap: u32,
+        out_fmt: &OutputFormat,
+    ) -> Result<Option<String>, std::fmt::Error> {
+        let classification = match self.classification_at_lap(lap) {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+
+        let mut tmp_string = String::new();
+
+        for (pos_idx, entry) in classification.entries.iter().enumerate() {
+            write!(
+                &mut tmp_string,
+                "P{:<3} #{:<3} {:3}  {} {}",
+                pos_idx + 1,
+                entry.car_no,
+                entry.driver_initials,
+                entry.no_pitstops,
+                if entry.no_pitstops == 1 { "stop" } else { "stops" }
+            )?;
+
+            if entry.retired {
+                write!(&mut tmp_string, "  DNF (lap {})", entry.laps)?;
+            } else if let Some(gap) = entry.gap {
+                if pos_idx > 0 {
+                    write!(&mut tmp_string, "  +{} s", out_fmt.fmt_num(gap, 0, 3))?;
+                }
             } else {
+                let laps_behind = classification.lap - entry.laps;
                 write!(
-                    &mut tmp_string_car_driver_info,
-                    "{:3} ({})",
-                    car_driver_pair.car_no, car_driver_pair.driver_initials
-                )
-                .unwrap()
+                    &mut tmp_string,
+                    "  +{} {}",
+                    laps_behind,
+                    if laps_behind == 1 { "lap" } else { "laps" }
+                )?;
             }
+
+            writeln!(&mut tmp_string)?;
         }
 
-        // print everything to the console
-        println!("RESULT: Lap times");
-        println!("{}", tmp_string_car_driver_info);
-        println!("{}", tmp_string_laptime);
+        Ok(Some(tmp_string))
+    }
 
-        println!("RESULT: Race times");
-        println!("{}", tmp_string_car_driver_info);
-        println!("{}", tmp_string_racetime);
+    /// format_time_losses creates the table of the time every car lost versus an ideal clean run
+    /// (see `TimeLossBreakdown`), sorted by the final classification. Returns None if the result
+    /// does not contain the time losses (older results).
+    pub fn format_time_losses(
+        &self,
+        out_fmt: &OutputFormat,
+    ) -> Result<Option<String>, std::fmt::Error> {
+        if self.time_losses.len() != self.car_driver_pairs.len() {
+            return Ok(None);
+        }
+
+        let positions = self.get_final_positions();
+        let mut idxs_sorted: Vec<usize> = (0..positions.len()).collect();
+        idxs_sorted.sort_by_key(|&idx| positions[idx]);
+
+        let mut tmp_string = String::new();
+        write!(&mut tmp_string, "Car  Drv")?;
+        for (_, label) in TIME_LOSS_CAUSES.iter() {
+            write!(&mut tmp_string, " {:>9}", label)?;
+        }
+        writeln!(&mut tmp_string, " {:>9}", "Total")?;
+
+        for &idx in idxs_sorted.iter() {
+            let pair = &self.car_driver_pairs[idx];
+            let time_losses = &self.time_losses[idx];
+            write!(&mut tmp_string, "#{:<3} {:3}", pair.car_no, pair.driver_initials)?;
+
+            for (cause, _) in TIME_LOSS_CAUSES.iter() {
+                write!(&mut tmp_string, " {}", out_fmt.fmt_num(time_losses.get(*cause), 9, 1))?;
+            }
+            write!(&mut tmp_string, " {}", out_fmt.fmt_num(time_losses.get_total(), 9, 1))?;
+
+            if let Some(code) = self.get_status(idx).get_code() {
+                write!(&mut tmp_string, "  ({})", code)?;
+            }
+            writeln!(&mut tmp_string)?;
+        }
+
+        Ok(Some(tmp_string))
+    }
+
+    /// add_event_contexts enriches every significant event with the running order one lap before
+    /// and one lap after the event lap.
+    pub fn add_event_contexts(&mut self) {
+        for i in 0..self.events.len() {
+            if !self.events[i].is_significant() {
+                continue;
+            }
+
+            let lap = self.events[i].lap;
+            let context = EventContext {
+                before: lap.checked_sub(1).and_then(|x| self.get_snapshot(x)),
+                after: self.get_snapshot(lap + 1),
+            };
+            self.events[i].context = Some(context);
+        }
+    }
+
+    /// format_events creates the text block of the event log including the before/after tables of
+    /// the significant events and the planned pit stops that were never executed.
+    pub fn format_events(&self, out_fmt: &OutputFormat) -> Result<String, std::fmt::Error> {
+        let mut tmp_string = String::new();
+
+        for ev in self.events.iter() {
+            tmp_string.push_str(&ev.format(out_fmt)?);
+
+            if let Some(context) = &ev.context {
+                for &(label, snapshot) in &[("before", &context.before), ("after", &context.after)] {
+                    if let Some(snapshot) = snapshot {
+                        write!(&mut tmp_string, "    {:6} (lap {:3}): ", label, snapshot.lap)?;
+                        for (pos, (car_no, gap)) in
+                            snapshot.car_nos.iter().zip(snapshot.gaps.iter()).enumerate()
+                        {
+                            write!(
+                                &mut tmp_string,
+                                "P{} #{} +{}s  ",
+                                pos + 1,
+                                car_no,
+                                out_fmt.fmt_num(*gap, 0, 3)
+                            )?;
+                        }
+                        writeln!(&mut tmp_string)?;
+                    }
+                }
+            }
+        }
+
+        if !self.unexecuted_stops.is_empty() {
+            writeln!(&mut tmp_string, "Planned pit stops that were never executed:")?;
+            for stop in self.unexecuted_stops.iter() {
+                writeln!(
+                    &mut tmp_string,
+                    "    Car {}, inlap {} ({}): {:?}",
+                    stop.car_no, stop.inlap, stop.compound, stop.reason
+                )?;
+            }
+        }
+
+        Ok(tmp_string)
+    }
+
+    /// write_events_to_file writes the event log to a text file in output/. The provenance of the
+    /// run (if given) is written as commented header. Returns the path to the written file.
+    pub fn write_events_to_file(
+        &self,
+        path: Option<&std::path::Path>,
+        out_fmt: &OutputFormat,
+        provenance: Option<&Provenance>,
+    ) -> anyhow::Result<String> {
+        let content = prepend_header(self.format_events(out_fmt)?, provenance);
+        write_output_file(path, "last_run_events.txt", &content)
+    }
+
+    /// sanity_check checks the results for non-physical values. `t_q` is the qualifying lap time
+    /// of the track, `t_pit_loss` an upper bound of the time lost by a pit stop. Laps that were not
+    /// driven (e.g. after a DNF) are skipped.
+    pub fn sanity_check(&self, t_q: f64, t_pit_loss: f64) -> Vec<SanityWarning> {
+        let mut warnings = Vec::new();
+        let no_cars = self.car_driver_pairs.len();
+
+        // possible gap change in one lap: pace delta (SC, damage, rain) plus a pit stop
+        let max_gap_change = 0.5 * t_q + t_pit_loss;
+
+        for i in 0..no_cars {
+            let car_no = self.car_driver_pairs[i].car_no;
+            let last_lap = self.get_last_driven_lap(i);
+
+            for lap in 1..self.tot_no_laps as usize + 1 {
+                let laptime = self.get_laptime(i, lap);
+
+                if laptime < 0.0 {
+                    warnings.push(SanityWarning::new(
+                        SanityRule::NegativeLapTime,
+                        car_no,
+                        lap,
+                        format!("negative lap time {:.3}s", laptime),
+                    ));
+                } else if laptime > 0.0 && laptime < 0.95 * t_q {
+                    warnings.push(SanityWarning::new(
+                        SanityRule::FastLap,
+                        car_no,
+                        lap,
+                        format!("lap time {:.3}s is faster than 95% of t_q ({:.3}s)", laptime, t_q),
+                    ));
+                }
+
+                if lap > 1
+                    && lap <= last_lap
+                    && self.get_racetime(i, lap) < self.get_racetime(i, lap - 1)
+                {
+                    warnings.push(SanityWarning::new(
+                        SanityRule::DecreasingRaceTime,
+                        car_no,
+                        lap,
+                        format!(
+                            "race time decreased from {:.3}s to {:.3}s",
+                            self.get_racetime(i, lap - 1),
+                            self.get_racetime(i, lap)
+                        ),
+                    ));
+                }
+            }
+
+            // race time must equal the sum of the lap times
+            if last_lap > 0 {
+                let sum_laptimes: f64 =
+                    (1..last_lap + 1).map(|lap| self.get_laptime(i, lap)).sum();
+                let racetime = self.get_racetime(i, last_lap);
+
+                if (racetime - sum_laptimes).abs() > 1e-3 {
+                    warnings.push(SanityWarning::new(
+                        SanityRule::RaceTimeMismatch,
+                        car_no,
+                        last_lap,
+                        format!(
+                            "race time {:.3}s differs from the sum of lap times {:.3}s",
+                            racetime, sum_laptimes
+                        ),
+                    ));
+                }
+            }
+        }
+
+        // gaps to the leader must not change by more than physically possible in one lap
+        for lap in 2..self.tot_no_laps as usize + 1 {
+            let driven: Vec<usize> = (0..no_cars)
+                .filter(|&i| {
+                    self.get_racetime(i, lap) > 0.0 && self.get_racetime(i, lap - 1) > 0.0
+                })
+                .collect();
+
+            let leader_prev = driven
+                .iter()
+                .map(|&i| self.get_racetime(i, lap - 1))
+                .fold(f64::INFINITY, f64::min);
+            let leader_cur = driven
+                .iter()
+                .map(|&i| self.get_racetime(i, lap))
+                .fold(f64::INFINITY, f64::min);
+
+            for &i in driven.iter() {
+                let gap_prev = self.get_racetime(i, lap - 1) - leader_prev;
+                let gap_cur = self.get_racetime(i, lap) - leader_cur;
+
+                if (gap_cur - gap_prev).abs() > max_gap_change {
+                    warnings.push(SanityWarning::new(
+                        SanityRule::GapJump,
+                        self.car_driver_pairs[i].car_no,
+                        lap,
+                        format!(
+                            "gap to the leader changed from {:.3}s to {:.3}s",
+                            gap_prev, gap_cur
+                        ),
+                    ));
+                }
+            }
+        }
+
+        warnings
+    }
+
+    /// write_to_json writes the results together with the sanity check findings and the provenance
+    /// of the run (if given) to a JSON file in output/. Returns the path to the written file.
+    pub fn write_to_json(
+        &self,
+        path: Option<&std::path::Path>,
+        sanity_warnings: &[SanityWarning],
+        provenance: Option<&Provenance>,
+    ) -> anyhow::Result<String> {
+        let content = serde_json::to_string_pretty(&ResultExport {
+            provenance,
+            result: self,
+            sanity_warnings,
+            distance_stats: self.get_distance_stats(),
+        })?;
+        write_output_file(path, "last_run.json", &content)
+    }
+
+    /// read_from_json reads the results from a JSON export written by `write_to_json`.
+    pub fn read_from_json(path: &std::path::Path) -> anyhow::Result<RaceResult> {
+        Ok(RaceResult::read_from_json_with_provenance(path)?.0)
+    }
+
+    /// read_from_json_with_provenance reads the results and the provenance of the run (None for
+    /// exports without provenance) from a JSON export written by `write_to_json`.
+    pub fn read_from_json_with_provenance(
+        path: &std::path::Path,
+    ) -> anyhow::Result<(RaceResult, Option<Provenance>)> {
+        let content = std::fs::read_to_string(path)
+            .context(format!("Failed to read results {}!", path.to_string_lossy()))?;
+        let import: ResultImport = serde_json::from_str(&content)
+            .context(format!("Failed to parse results {}!", path.to_string_lossy()))?;
+        Ok((import.result, import.provenance))
+    }
+
+    /// print_lap_and_race_times prints the resulting lap and race times to the console output.
+    pub fn print_lap_and_race_times(&self, out_fmt: &OutputFormat) {
+        print!("{}", self.format_lap_and_race_times(out_fmt).unwrap());
+    }
+
+    /// is_wet_lap returns true if it was raining at the start of the (leader) lap.
+    fn is_wet_lap(&self, lap: u32) -> bool {
+        lap >= 1
+            && self
+                .weather_history
+                .get(lap as usize - 1)
+                .map(|weather| weather == "Rain")
+                .unwrap_or(false)
+    }
+
+    /// get_incident_rates counts the incidents (see `INCIDENT_EVENT_KINDS`) and the driven leader
+    /// laps split by the weather at the start of the lap.
+    pub fn get_incident_rates(&self) -> IncidentRates {
+        let mut incident_rates = IncidentRates::default();
+
+        for lap in 1..=(self.weather_history.len() as u32).min(self.tot_no_laps) {
+            if self.is_wet_lap(lap) {
+                incident_rates.no_laps_wet += 1;
+            } else {
+                incident_rates.no_laps_dry += 1;
+            }
+        }
+
+        for ev in self
+            .events
+            .iter()
+            .filter(|ev| INCIDENT_EVENT_KINDS.contains(&ev.kind.as_str()))
+        {
+            if self.is_wet_lap(ev.lap) {
+                incident_rates.no_incidents_wet += 1;
+            } else {
+                incident_rates.no_incidents_dry += 1;
+            }
+        }
+
+        incident_rates
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+/// Event kinds that are counted as incidents in the incident rates ("EngineFailure" is the
+/// failure event of results written before the failure types were distinguished).
+pub const INCIDENT_EVENT_KINDS: [&str; 7] = [
+    "Contact",
+    "Crash",
+    "ENGINE_FAILURE",
+    "GEARBOX_FAILURE",
+    "HYDRAULICS_FAILURE",
+    "BRAKE_FAILURE",
+    "EngineFailure",
+];
+
+/// Event kind of a car disqualified in the post-race checks for finishing with less fuel than the
+/// required reserve.
+pub const DSQ_FUEL_EVENT_KIND: &str = "DsqFuel";
+
+/// Event kind of a car disqualified in the post-race checks for a technical infringement.
+pub const DSQ_TECHNICAL_EVENT_KIND: &str = "DsqTechnical";
+
+/// is_failure_event_kind returns true for the event kinds of the mechanical failures (including
+/// the "EngineFailure" event of older results).
+pub fn is_failure_event_kind(kind: &str) -> bool {
+    kind == "EngineFailure"
+        || FailureType::ALL
+            .iter()
+            .any(|failure_type| failure_type.get_event_kind() == kind)
+}
+
+/// is_retirement_event_kind returns true for the event kinds that retire a car (crash, mechanical
+/// failure, running out of fuel or scripted retirement).
+pub fn is_retirement_event_kind(kind: &str) -> bool {
+    kind == "Crash" || kind == "OUT_OF_FUEL" || kind == "Retirement" || is_failure_event_kind(kind)
+}
+
+/// IncidentRates contains the number of incidents and driven (leader) laps split by the weather
+/// condition, e.g. to calibrate the wetness dependency of the incident probabilities.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IncidentRates {
+    pub no_laps_dry: u32,
+    pub no_laps_wet: u32,
+    pub no_incidents_dry: u32,
+    pub no_incidents_wet: u32,
+}
+
+impl IncidentRates {
+    /// from_results sums up the incident rates of several runs.
+    pub fn from_results(results: &[RaceResult]) -> IncidentRates {
+        let mut incident_rates = IncidentRates::default();
+
+        for result in results.iter() {
+            let tmp = result.get_incident_rates();
+            incident_rates.no_laps_dry += tmp.no_laps_dry;
+            incident_rates.no_laps_wet += tmp.no_laps_wet;
+            incident_rates.no_incidents_dry += tmp.no_incidents_dry;
+            incident_rates.no_incidents_wet += tmp.no_incidents_wet;
+        }
+
+        incident_rates
+    }
+
+    /// get_rate_dry returns the incidents per 100 dry laps (None without dry laps).
+    pub fn get_rate_dry(&self) -> Option<f64> {
+        calc_rate_per_100_laps(self.no_incidents_dry, self.no_laps_dry)
+    }
+
+    /// get_rate_wet returns the incidents per 100 wet laps (None without wet laps).
+    pub fn get_rate_wet(&self) -> Option<f64> {
+        calc_rate_per_100_laps(self.no_incidents_wet, self.no_laps_wet)
+    }
+
+    /// format creates the summary of the incident rates, one line per weather condition.
+    pub fn format(&self, out_fmt: &OutputFormat) -> String {
+        let mut tmp_string = String::new();
+
+        for &(label, no_incidents, no_laps, rate) in &[
+            ("Dry", self.no_incidents_dry, self.no_laps_dry, self.get_rate_dry()),
+            ("Wet", self.no_incidents_wet, self.no_laps_wet, self.get_rate_wet()),
+        ] {
+            let rate_str = match rate {
+                Some(rate) => format!("{} per 100 laps", out_fmt.fmt_num(rate, 0, 2)),
+                None => String::from("-"),
+            };
+            tmp_string.push_str(&format!(
+                "{}: {} incidents in {} laps ({})\n",
+                label, no_incidents, no_laps, rate_str
+            ));
+        }
+
+        tmp_string
+    }
+}
+
+fn calc_rate_per_100_laps(no_incidents: u32, no_laps: u32) -> Option<f64> {
+    if no_laps == 0 {
+        None
+    } else {
+        Some(no_incidents as f64 / no_laps as f64 * 100.0)
+    }
+}
+
+/// write_output_file writes the content to the given path or to output/{default_filename} if no
+/// path is given. Returns the path to the written file.
+pub(crate) fn write_output_file(
+    path: Option<&std::path::Path>,
+    default_filename: &str,
+    content: &str,
+) -> anyhow::Result<String> {
+    // Resolve file path (the output directory is only created if it is used)
+    let out_path = if let Some(p) = path {
+        p.to_path_buf()
+    } else {
+        let out_dir = std::path::Path::new("output");
+        std::fs::create_dir_all(out_dir)?;
+        out_dir.join(default_filename)
+    };
+
+    // Write file
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .truncate(true)
+        .write(true)
+        .open(&out_path)?;
+    file.write_all(content.as_bytes())?;
+    file.flush()?;
+
+    Ok(out_path.to_string_lossy().into_owned())
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub struct RaceEvent {
-    pub kind: String,        // "Crash", "WeatherRainStart", "WeatherDryStart", "SC_DEPLOYED", "SC_IN"
+    pub kind: String,        // "Crash", "WeatherRainStart", "WeatherDryStart", "SC_DEPLOYED", "SC_IN", "LeadChange"
     pub lap: u32,            // numer okrążenia w momencie zdarzenia (1-based)
     pub time_s: f64,         // czas wyścigu w sekundach
     pub cars: Vec<u32>,      // dotknięte auta (np. przy kraksie)
+    #[serde(default)]
+    pub context: Option<EventContext>, // kolejność przed i po zdarzeniu (tylko istotne zdarzenia)
+    #[serde(default)]
+    pub s_range: Option<[f64; 2]>, // (m) odcinek toru zdarzenia (np. gruz), dla punktu początek = koniec
+    #[serde(default)]
+    pub scripted: bool,      // zdarzenie wymuszone przez scenariusz (scripted_events)
+    #[serde(default)]
+    pub sc_decision: Option<ScDecision>, // losowanie SC po wycofaniu auta (tylko SC_DECISION)
+    #[serde(default)]
+    pub start: Option<StartReaction>, // przebieg nietypowego startu auta (tylko StartReaction)
+    #[serde(default)]
+    pub pit_stop: Option<PitStopInfo>, // wylosowany czas postoju (tylko PIT_STOP)
+}
+
+impl RaceEvent {
+    /// new creates an event of the given kind without a track section, context, SC decision, start
+    /// or pit stop.
+    pub fn new(kind: &str, lap: u32, time_s: f64, cars: Vec<u32>) -> RaceEvent {
+        RaceEvent {
+            kind: kind.to_string(),
+            lap,
+            time_s,
+            cars,
+            ..Default::default()
+        }
+    }
+
+    /// is_significant returns true for the events that get a before/after context in the results.
+    pub fn is_significant(&self) -> bool {
+        is_retirement_event_kind(&self.kind)
+            || matches!(
+                self.kind.as_str(),
+                "SC_DEPLOYED"
+                    | "VSC_DEPLOYED"
+                    | "RED_FLAG"
+                    | "PUNCTURE"
+                    | "Spin"
+                    | "LeadChange"
+                    | "WeatherRainStart"
+                    | "WeatherDryStart"
+            )
+    }
+
+    /// format returns the events log line of the event (with the affected track section if there
+    /// is one, but without the standings context).
+    pub fn format(&self, out_fmt: &OutputFormat) -> Result<String, std::fmt::Error> {
+        let mut tmp_string = String::new();
+
+        writeln!(
+            &mut tmp_string,
+            "Lap {:3}, t = {} s: {} (cars: {:?}){}",
+            self.lap,
+            out_fmt.fmt_num(self.time_s, 0, 3),
+            self.kind,
+            self.cars,
+            if self.scripted { " [scripted]" } else { "" }
+        )?;
+
+        if let Some(s_range) = self.s_range {
+            if s_range[0] == s_range[1] {
+                writeln!(&mut tmp_string, "    s = {} m", out_fmt.fmt_num(s_range[0], 0, 0))?;
+            } else {
+                writeln!(
+                    &mut tmp_string,
+                    "    s = {} - {} m",
+                    out_fmt.fmt_num(s_range[0], 0, 0),
+                    out_fmt.fmt_num(s_range[1], 0, 0)
+                )?;
+            }
+        }
+
+        if let Some(sc_decision) = &self.sc_decision {
+            writeln!(
+                &mut tmp_string,
+                "    {:?}: p(SC) = {} -> {:?}",
+                sc_decision.location,
+                out_fmt.fmt_num(sc_decision.probability, 0, 2),
+                sc_decision.outcome
+            )?;
+        }
+
+        if let Some(start) = &self.start {
+            writeln!(
+                &mut tmp_string,
+                "    reaction = {} s, anti-stall = {} s, launch delta = {} s{}",
+                out_fmt.fmt_num(start.t_reaction, 0, 3),
+                out_fmt.fmt_num(start.t_anti_stall, 0, 3),
+                out_fmt.fmt_num(start.launch_delta, 0, 3),
+                if start.jump_start { ", jump start" } else { "" }
+            )?;
+        }
+
+        if let Some(pit_stop) = &self.pit_stop {
+            writeln!(
+                &mut tmp_string,
+                "    standstill = {} s",
+                out_fmt.fmt_num(pit_stop.t_standstill, 0, 2)
+            )?;
+        }
+
+        Ok(tmp_string)
+    }
+}
+
+/// IncidentLocation names the part of the track where a car stopped.
+/// * `Corner` - Inside a corner of the track
+/// * `OvertakingZone` - Inside an overtaking zone (straight with run-off area)
+/// * `Other` - Anywhere else
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum IncidentLocation {
+    Corner,
+    OvertakingZone,
+    Other,
+}
+
+/// ScOutcome names the neutralization chosen after a retirement.
+/// * `Sc` - Safety car
+/// * `Vsc` - Virtual safety car (the SC roll failed in a corner)
+/// * `LocalYellow` - Local yellow flag around the car only (the SC roll failed elsewhere)
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum ScOutcome {
+    Sc,
+    Vsc,
+    LocalYellow,
+}
+
+/// ScDecision contains the roll for a safety car after a retirement.
+/// * `location` - Part of the track where the (first) retired car stopped
+/// * `probability` - Probability of the safety car at this location
+/// * `outcome` - Chosen neutralization
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub struct ScDecision {
+    pub location: IncidentLocation,
+    pub probability: f64,
+    pub outcome: ScOutcome,
+}
+
+/// ScPeriodKind is the neutralization of an SC period.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum ScPeriodKind {
+    Sc,
+    Vsc,
+}
+
+/// ScPeriod contains a period in which the race was neutralized by the safety car or the virtual
+/// safety car. An SC period lasts from the deployment of the safety car until it enters the pit
+/// lane (or until the last car finished behind it).
+/// * `kind` - SC or VSC
+/// * `start_lap` - Leader's lap in which the period started
+/// * `end_lap` - Leader's lap in which the period ended
+/// * `t_start` - (s) Race time at the start of the period
+/// * `t_end` - (s) Race time at the end of the period
+/// * `cause_car_no` - Car whose retirement caused the period (None for scripted safety cars and
+///   the restart after a red flag)
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct ScPeriod {
+    pub kind: ScPeriodKind,
+    pub start_lap: u32,
+    pub end_lap: u32,
+    pub t_start: f64,
+    pub t_end: f64,
+    pub cause_car_no: Option<u32>,
+}
+
+impl ScPeriod {
+    /// get_duration returns the duration of the period in seconds.
+    pub fn get_duration(&self) -> f64 {
+        self.t_end - self.t_start
+    }
+}
+
+/// EventSnapshot contains the running order at the end of a lap.
+/// * `lap` - Lap after which the snapshot was taken
+/// * `car_nos` - Car numbers in running order (only cars that completed the lap)
+/// * `gaps` - (s) Gaps to the leader in the same order as `car_nos`
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct EventSnapshot {
+    pub lap: u32,
+    pub car_nos: Vec<u32>,
+    pub gaps: Vec<f64>,
+}
+
+/// LapClassificationEntry contains the classification of a car at the end of a lap.
+/// * `laps` - Number of laps completed at the end of the lap of the leader (laps until the DNF for
+///   retired cars)
+/// * `gap` - (s) Gap to the leader, None for lapped and retired cars
+/// * `no_pitstops` - Number of pit stops with an in-lap within the completed laps
+/// * `retired` - True if the car had retired before the end of the lap of the leader
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct LapClassificationEntry {
+    pub car_no: u32,
+    pub driver_initials: String,
+    pub laps: u32,
+    pub gap: Option<f64>,
+    pub no_pitstops: u32,
+    pub retired: bool,
+}
+
+/// LapClassification contains the intermediate classification at the end of a lap.
+/// * `lap` - Lap after which the classification was frozen
+/// * `entries` - Entries in classification order (running cars first, retired cars last)
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct LapClassification {
+    pub lap: u32,
+    pub entries: Vec<LapClassificationEntry>,
+}
+
+/// EventContext contains the running order one lap before and one lap after an event such that the
+/// effect of the event can be shown without simulating the race again. A snapshot is None if the
+/// lap lies outside the race.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct EventContext {
+    pub before: Option<EventSnapshot>,
+    pub after: Option<EventSnapshot>,
+}
+
+/// RetirementInfo contains the position and the time of a car at the moment of its retirement.
+/// * `lap` - Number of laps completed before the retirement
+/// * `s_track` - (m) Position on the track in the lap of the retirement
+/// * `time_s` - (s) Race time of the retirement
+/// * `failure` - Type of the mechanical failure, None if the car did not retire due to a failure
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct RetirementInfo {
+    pub lap: u32,
+    pub s_track: f64,
+    pub time_s: f64,
+    #[serde(default)]
+    pub failure: Option<FailureType>,
+}
+
+/// TimeLossCause is the cause a lap time adjustment is accounted to in the time loss breakdown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeLossCause {
+    Tire,
+    Fuel,
+    Traffic,
+    Duels,
+    PitStops,
+    Neutralization,
+    Weather,
+    Damage,
+    Mistakes,
+    Start,
+    Penalties,
+    Other,
+}
+
+/// TIME_LOSS_CAUSES contains the causes with their column labels as used in the outputs.
+pub const TIME_LOSS_CAUSES: [(TimeLossCause, &str); 12] = [
+    (TimeLossCause::Tire, "Tires"),
+    (TimeLossCause::Fuel, "Fuel"),
+    (TimeLossCause::Traffic, "Traffic"),
+    (TimeLossCause::Duels, "Duels"),
+    (TimeLossCause::PitStops, "Pit"),
+    (TimeLossCause::Neutralization, "SC/VSC"),
+    (TimeLossCause::Weather, "Weather"),
+    (TimeLossCause::Damage, "Damage"),
+    (TimeLossCause::Mistakes, "Mistakes"),
+    (TimeLossCause::Start, "Start"),
+    (TimeLossCause::Penalties, "Penalties"),
+    (TimeLossCause::Other, "Other"),
+];
+
+/// TimeLossBreakdown contains the time a car lost versus an ideal clean run, i.e. driving every
+/// lap in `t_q + t_gap_racepace + t_car + t_driver`. The sum of all causes equals the difference
+/// between the race time of the car and the race time of the clean run (negative values are
+/// gains, e.g. by DRS or the position on the grid).
+/// * `t_tire` - (s) Compound and tire degradation
+/// * `t_fuel` - (s) Fuel mass
+/// * `t_traffic` - (s) Dirty air, following and being blocked by slower cars
+/// * `t_duels` - (s) Duels and overtakes (including DRS and the ERS deployment)
+/// * `t_pit` - (s) Pit lane (driving through and standstill)
+/// * `t_neutralization` - (s) SC, VSC, yellow flags and slowing down for a fresh crash
+/// * `t_weather` - (s) Wet track and wrong tires for the weather
+/// * `t_damage` - (s) Damage from contacts
+/// * `t_mistakes` - (s) Driver mistakes under pressure
+/// * `t_start` - (s) Reaction at the start, anti-stall and the position on the grid
+/// * `t_penalties` - (s) Time penalties
+/// * `t_other` - (s) Everything else (lap time scatter, track shape, corners, engine mode, setup,
+///   speed limit, ERS harvesting)
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub struct TimeLossBreakdown {
+    pub t_tire: f64,
+    pub t_fuel: f64,
+    pub t_traffic: f64,
+    pub t_duels: f64,
+    pub t_pit: f64,
+    pub t_neutralization: f64,
+    pub t_weather: f64,
+    pub t_damage: f64,
+    pub t_mistakes: f64,
+    pub t_start: f64,
+    pub t_penalties: f64,
+    pub t_other: f64,
+}
+
+impl TimeLossBreakdown {
+    /// get_mut returns a mutable reference to the time lost to the given cause.
+    fn get_mut(&mut self, cause: TimeLossCause) -> &mut f64 {
+        match cause {
+            TimeLossCause::Tire => &mut self.t_tire,
+            TimeLossCause::Fuel => &mut self.t_fuel,
+            TimeLossCause::Traffic => &mut self.t_traffic,
+            TimeLossCause::Duels => &mut self.t_duels,
+            TimeLossCause::PitStops => &mut self.t_pit,
+            TimeLossCause::Neutralization => &mut self.t_neutralization,
+            TimeLossCause::Weather => &mut self.t_weather,
+            TimeLossCause::Damage => &mut self.t_damage,
+            TimeLossCause::Mistakes => &mut self.t_mistakes,
+            TimeLossCause::Start => &mut self.t_start,
+            TimeLossCause::Penalties => &mut self.t_penalties,
+            TimeLossCause::Other => &mut self.t_other,
+        }
+    }
+
+    /// get returns the time (s) lost to the given cause.
+    pub fn get(&self, cause: TimeLossCause) -> f64 {
+        match cause {
+            TimeLossCause::Tire => self.t_tire,
+            TimeLossCause::Fuel => self.t_fuel,
+            TimeLossCause::Traffic => self.t_traffic,
+            TimeLossCause::Duels => self.t_duels,
+            TimeLossCause::PitStops => self.t_pit,
+            TimeLossCause::Neutralization => self.t_neutralization,
+            TimeLossCause::Weather => self.t_weather,
+            TimeLossCause::Damage => self.t_damage,
+            TimeLossCause::Mistakes => self.t_mistakes,
+            TimeLossCause::Start => self.t_start,
+            TimeLossCause::Penalties => self.t_penalties,
+            TimeLossCause::Other => self.t_other,
+        }
+    }
+
+    /// add adds the given time (s) to the given cause.
+    pub fn add(&mut self, cause: TimeLossCause, t: f64) {
+        *self.get_mut(cause) += t;
+    }
+
+    /// add_scaled adds all causes of the other breakdown multiplied by the given factor.
+    pub fn add_scaled(&mut self, other: &TimeLossBreakdown, factor: f64) {
+        for (cause, _) in TIME_LOSS_CAUSES.iter() {
+            self.add(*cause, other.get(*cause) * factor);
+        }
+    }
+
+    /// get_total returns the sum of all causes.
+    pub fn get_total(&self) -> f64 {
+        TIME_LOSS_CAUSES.iter().map(|(cause, _)| self.get(*cause)).sum()
+    }
+}
+
+/// PitStopInfo contains the drawn standstill time of a single pit stop.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PitStopInfo {
+    pub car_no: u32,
+    pub lap: u32,
+    pub t_standstill: f64,
+}
+
+/// UnexecutedStopReason names the reason why a planned pit stop was never executed.
+/// * `RaceEnded` - The race ended before the in-lap (e.g. shortened race or lapped car)
+/// * `Retired` - The car retired before the pit stop
+/// * `Superseded` - The pit stop was removed by a strategy change during the race
+/// * `Missed` - The car passed the in-lap without pitting (see the "MissedStop" event)
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum UnexecutedStopReason {
+    RaceEnded,
+    Retired,
+    Superseded,
+    Missed,
+}
+
+/// UnexecutedStop contains a planned pit stop of the strategy that was never executed.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct UnexecutedStop {
+    pub car_no: u32,
+    pub inlap: u32,
+    pub compound: String,
+    pub reason: UnexecutedStopReason,
+}
+
+/// SanityRule names the rule of the sanity check that was violated.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum SanityRule {
+    FastLap,
+    NegativeLapTime,
+    DecreasingRaceTime,
+    GapJump,
+    RaceTimeMismatch,
+}
+
+/// SanityWarning describes a non-physical value found in the results.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SanityWarning {
+    pub rule: SanityRule,
+    pub car_no: u32,
+    pub lap: u32,
+    pub msg: String,
+}
+
+impl SanityWarning {
+    fn new(rule: SanityRule, car_no: u32, lap: usize, msg: String) -> SanityWarning {
+        SanityWarning {
+            rule,
+            car_no,
+            lap: lap as u32,
+            msg,
+        }
+    }
+}
+
+/// write_lap_blocks writes the given lap lines in blocks of `LAPS_PER_TEXT_BLOCK` lines, each
+/// preceded by the header line. The blocks are separated by an empty line, which also terminates
+/// the last block.
+fn write_lap_blocks(content: &mut String, header: &str, lines: &[String]) -> std::fmt::Result {
+    for block in lines.chunks(LAPS_PER_TEXT_BLOCK) {
+        writeln!(content, "{}", header)?;
+        for line in block.iter() {
+            writeln!(content, "{}", line)?;
+        }
+        writeln!(content)?;
+    }
+
+    // a race without laps keeps its (empty) block
+    if lines.is_empty() {
+        writeln!(content, "{}", header)?;
+        writeln!(content)?;
+    }
+
+    Ok(())
+}
+
+/// format_lap_flags_legend returns the explanation of the lap flag codes in a single line.
+pub fn format_lap_flags_legend() -> String {
+    let codes: Vec<String> = LAP_FLAG_CODES
+        .iter()
+        .map(|(_, code, description)| format!("[{}] {}", code, description))
+        .collect();
+    format!("Lap flags: {}", codes.join(", "))
+}
+
+/// get_alias_letters returns the letters of the i-th alias (A, B, ..., Z, AA, AB, ...).
+fn get_alias_letters(i: usize) -> String {
+    let letter = (b'A' + (i % 26) as u8) as char;
+
+    if i < 26 {
+        letter.to_string()
+    } else {
+        format!("{}{}", get_alias_letters(i / 26 - 1), letter)
+    }
+}
+
+/// IdentityMapEntry links a real car and start driver to the alias used in anonymized outputs.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct IdentityMapEntry {
+    pub car_no: u32,
+    pub driver_initials: String,
+    pub alias_car_no: u32,
+    pub alias: String,
+}
+
+/// IdentityMap contains the mapping created by `RaceResult::anonymized`. It should be kept
+/// private, it allows to de-anonymize the published outputs.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct IdentityMap {
+    pub entries: Vec<IdentityMapEntry>,
+}
+
+impl IdentityMap {
+    /// get_entry returns the entry of the real car number.
+    pub fn get_entry(&self, car_no: u32) -> Option<&IdentityMapEntry> {
+        self.entries.iter().find(|entry| entry.car_no == car_no)
+    }
+
+    /// get_alias_car_no returns the alias car number of the real car number (unknown car numbers
+    /// are mapped to 0 such that they are not published).
+    pub fn get_alias_car_no(&self, car_no: u32) -> u32 {
+        self.get_entry(car_no).map_or(0, |entry| entry.alias_car_no)
+    }
+
+    /// write_to_file writes the mapping to a JSON file in output/ (mapping.json if no path is
+    /// given). Returns the path to the written file.
+    pub fn write_to_file(&self, path: Option<&std::path::Path>) -> anyhow::Result<String> {
+        let content = serde_json::to_string_pretty(self)?;
+        write_output_file(path, "mapping.json", &content)
+    }
+}
+
+/// ResultExport is the structure written to the JSON export.
+#[derive(Debug, Serialize)]
+struct ResultExport<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    provenance: Option<&'a Provenance>,
+    result: &'a RaceResult,
+    sanity_warnings: &'a [SanityWarning],
+    distance_stats: DistanceStats,
+}
+
+/// ResultImport is the part of the JSON export that is read back.
+#[derive(Debug, Deserialize)]
+struct ResultImport {
+    #[serde(default)]
+    provenance: Option<Provenance>,
+    result: RaceResult,
+}
+
+/// CarDistanceStats contains the distance statistics of a car.
+/// * `distance_km` - (km) Covered distance (including the partial lap of a retired car)
+/// * `avg_speed_kmh` - (km/h) Average speed including neutralizations and pit stops
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct CarDistanceStats {
+    pub car_no: u32,
+    pub distance_km: Option<f64>,
+    pub avg_speed_kmh: Option<f64>,
+    pub retired: bool,
+}
+
+/// DistanceStats contains the distance statistics of all cars and of the race (winner).
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct DistanceStats {
+    pub cars: Vec<CarDistanceStats>,
+    pub race_distance_km: Option<f64>,
+    pub race_avg_speed_kmh: Option<f64>,
 }
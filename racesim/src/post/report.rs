@@ -0,0 +1,386 @@
+use crate::core::livery::CarColor;
+use crate::core::race::SimConstants;
+use crate::post::output_fmt::OutputFormat;
+use crate::post::provenance::Provenance;
+use crate::post::race_result::{
+    write_output_file, ClassificationStatus, RaceResult, SanityWarning, TIME_LOSS_CAUSES,
+};
+use crate::pre::read_sim_pars::SimPars;
+use std::fmt::Write;
+
+/// ReportPlot is a plot embedded into the HTML report.
+/// * `title` - Title shown above the plot
+/// * `png` - Content of the PNG file
+#[derive(Debug, Clone)]
+pub struct ReportPlot {
+    pub title: String,
+    pub png: Vec<u8>,
+}
+
+/// ReportInput contains everything that documents a simulation run in the HTML report.
+/// * `result` - Race result (classification, lap times, events)
+/// * `sim_pars` - Resolved simulation parameters
+/// * `sim_consts` - Resolved simulation constants
+/// * `sanity_warnings` - Findings of the sanity check of the results
+/// * `plots` - Plots as created by the plot exporters
+/// * `provenance` - Provenance of the run, shown below the title (OPTIONAL)
+#[derive(Debug)]
+pub struct ReportInput<'a> {
+    pub result: &'a RaceResult,
+    pub sim_pars: &'a SimPars,
+    pub sim_consts: &'a SimConstants,
+    pub sanity_warnings: &'a [SanityWarning],
+    pub plots: &'a [ReportPlot],
+    pub provenance: Option<&'a Provenance>,
+}
+
+const REPORT_STYLE: &str = "body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { border: 1px solid #ccc; padding: 0.2em 0.6em; text-align: right; }
+th { background: #eee; }
+img { max-width: 100%; }
+pre { background: #f6f6f6; padding: 1em; }";
+
+const BASE64_CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// encode_base64 encodes the data as base64 (standard alphabet with padding) such that it can be
+/// embedded as data URI.
+pub fn encode_base64(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64_CHARS[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+/// escape_html escapes the characters with a special meaning in HTML.
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// write_table writes an HTML table with the given header and rows (cells are escaped).
+fn write_table(html: &mut String, header: &[&str], rows: &[Vec<String>]) -> std::fmt::Result {
+    writeln!(html, "<table>")?;
+    write!(html, "<tr>")?;
+    for cell in header.iter() {
+        write!(html, "<th>{}</th>", escape_html(cell))?;
+    }
+    writeln!(html, "</tr>")?;
+
+    for row in rows.iter() {
+        write!(html, "<tr>")?;
+        for cell in row.iter() {
+            write!(html, "<td>{}</td>", escape_html(cell))?;
+        }
+        writeln!(html, "</tr>")?;
+    }
+
+    writeln!(html, "</table>")
+}
+
+/// get_classification_rows creates the rows of the classification table.
+fn get_classification_rows(result: &RaceResult, out_fmt: &OutputFormat) -> Vec<Vec<String>> {
+    let positions = result.get_final_positions();
+    let mut idxs_sorted: Vec<usize> = (0..positions.len()).collect();
+    idxs_sorted.sort_by_key(|&idx| positions[idx]);
+
+    idxs_sorted
+        .iter()
+        .map(|&idx| {
+            let pair = &result.car_driver_pairs[idx];
+            let gap = if positions[idx] == 1 {
+                String::new()
+            } else if result.get_status(idx) == ClassificationStatus::Dsq {
+                String::from("DSQ")
+            } else {
+                match result.get_gap_to_winner(idx) {
+                    Some(gap) => format!("+{} s", out_fmt.fmt_num(gap, 0, 3)),
+                    None => String::from("lapped"),
+                }
+            };
+
+            vec![
+                positions[idx].to_string(),
+                pair.car_no.to_string(),
+                pair.driver_initials.to_owned(),
+                gap,
+                result.format_pit_summary(idx, out_fmt).unwrap_or_default(),
+                result
+                    .get_distance(idx)
+                    .map(|distance| {
+                        format!(
+                            "{} km{}",
+                            out_fmt.fmt_num(distance / 1000.0, 0, 1),
+                            result.format_status_suffix(idx)
+                        )
+                    })
+                    .unwrap_or_default(),
+                result
+                    .get_avg_speed(idx)
+                    .map(|avg_speed| format!("{} km/h", out_fmt.fmt_num(avg_speed, 0, 1)))
+                    .unwrap_or_default(),
+            ]
+        })
+        .collect()
+}
+
+/// get_driver_stats_rows creates the rows of the driver statistics table (completed laps, best
+/// lap, mean lap time).
+fn get_driver_stats_rows(result: &RaceResult, out_fmt: &OutputFormat) -> Vec<Vec<String>> {
+    result
+        .car_driver_pairs
+        .iter()
+        .enumerate()
+        .map(|(idx, pair)| {
+            let laptimes: Vec<(usize, f64)> = (1..result.tot_no_laps as usize + 1)
+                .map(|lap| (lap, result.get_laptime(idx, lap)))
+                .filter(|(_, t)| t.is_finite() && *t > 0.0)
+                .collect();
+
+            let best_lap = laptimes
+                .iter()
+                .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+                .map(|(lap, t)| format!("{} s (lap {})", out_fmt.fmt_num(*t, 0, 3), lap))
+                .unwrap_or_default();
+            let mean_laptime = if laptimes.is_empty() {
+                String::new()
+            } else {
+                let mean = laptimes.iter().map(|(_, t)| t).sum::<f64>() / laptimes.len() as f64;
+                format!("{} s", out_fmt.fmt_num(mean, 0, 3))
+            };
+
+            vec![
+                pair.car_no.to_string(),
+                pair.driver_initials.to_owned(),
+                laptimes.len().to_string(),
+                best_lap,
+                mean_laptime,
+            ]
+        })
+        .collect()
+}
+
+/// get_time_loss_rows creates the rows of the time loss table (sorted by the final positions).
+fn get_time_loss_rows(result: &RaceResult, out_fmt: &OutputFormat) -> Vec<Vec<String>> {
+    let positions = result.get_final_positions();
+    let mut idxs_sorted: Vec<usize> = (0..result.time_losses.len()).collect();
+    idxs_sorted.sort_by_key(|&idx| positions[idx]);
+
+    idxs_sorted
+        .iter()
+        .map(|&idx| {
+            let pair = &result.car_driver_pairs[idx];
+            let time_losses = &result.time_losses[idx];
+
+            let mut row = vec![pair.car_no.to_string(), pair.driver_initials.to_owned()];
+            row.extend(
+                TIME_LOSS_CAUSES
+                    .iter()
+                    .map(|(cause, _)| out_fmt.fmt_num(time_losses.get(*cause), 0, 1)),
+            );
+            row.push(out_fmt.fmt_num(time_losses.get_total(), 0, 1));
+            row
+        })
+        .collect()
+}
+
+/// get_car_color_rows creates the rows of the car colors table. Fallback liveries (no color in
+/// the parameter file) are marked such that the user knows that the color was not configured.
+fn get_car_color_rows(result: &RaceResult, sim_pars: &SimPars) -> Vec<Vec<String>> {
+    result
+        .car_driver_pairs
+        .iter()
+        .enumerate()
+        .map(|(idx, pair)| {
+            let color = CarColor {
+                rgb: result.get_car_color(idx),
+                fallback: result.car_colors.get(idx).is_none()
+                    || sim_pars
+                        .car_pars_all
+                        .get(&pair.car_no)
+                        .is_none_or(|car_pars| car_pars.color.trim().is_empty()),
+            };
+
+            vec![
+                pair.car_no.to_string(),
+                pair.driver_initials.to_owned(),
+                color.to_hex(),
+                if color.fallback {
+                    String::from("fallback (no color in the parameter file)")
+                } else {
+                    String::from("parameter file")
+                },
+            ]
+        })
+        .collect()
+}
+
+/// create_html_report creates a self-contained HTML document (no external assets) documenting a
+/// simulation run: provenance, plots, classification, driver statistics, time losses, events,
+/// sanity check warnings and the resolved configuration.
+pub fn create_html_report(
+    report_input: &ReportInput,
+    out_fmt: &OutputFormat,
+) -> anyhow::Result<String> {
+    let result = report_input.result;
+    let title = format!(
+        "{} {} - race report",
+        report_input.sim_pars.track_pars.name, report_input.sim_pars.race_pars.season
+    );
+
+    let mut html = String::new();
+    writeln!(html, "<!DOCTYPE html>")?;
+    writeln!(html, "<html>\n<head>\n<meta charset=\"utf-8\">")?;
+    writeln!(html, "<title>{}</title>", escape_html(&title))?;
+    writeln!(html, "<style>\n{}\n</style>\n</head>\n<body>", REPORT_STYLE)?;
+    writeln!(html, "<h1>{}</h1>", escape_html(&title))?;
+
+    // provenance of the run
+    if let Some(provenance) = report_input.provenance {
+        let provenance_rows: Vec<Vec<String>> = provenance
+            .get_fields()
+            .into_iter()
+            .map(|(key, value)| vec![key.to_owned(), value])
+            .collect();
+        write_table(&mut html, &["Provenance", ""], &provenance_rows)?;
+    }
+
+    // classification and driver statistics
+    writeln!(html, "<h2>Classification</h2>")?;
+    write_table(
+        &mut html,
+        &["Pos", "Car", "Driver", "Gap", "Pit stops", "Distance", "Avg. speed"],
+        &get_classification_rows(result, out_fmt),
+    )?;
+    if let Some(race_avg_speed) = result.get_race_avg_speed() {
+        writeln!(
+            html,
+            "<p>Average race speed (including neutralizations): {} km/h</p>",
+            out_fmt.fmt_num(race_avg_speed, 0, 1)
+        )?;
+    }
+
+    writeln!(html, "<h2>Driver statistics</h2>")?;
+    write_table(
+        &mut html,
+        &["Car", "Driver", "Laps", "Best lap", "Mean lap time"],
+        &get_driver_stats_rows(result, out_fmt),
+    )?;
+
+    if result.time_losses.len() == result.car_driver_pairs.len() {
+        writeln!(html, "<h2>Time lost versus a clean run (s)</h2>")?;
+        let mut header = vec!["Car", "Driver"];
+        header.extend(TIME_LOSS_CAUSES.iter().map(|(_, label)| *label));
+        header.push("Total");
+        write_table(&mut html, &header, &get_time_loss_rows(result, out_fmt))?;
+    }
+
+    // plots
+    for plot in report_input.plots.iter() {
+        writeln!(html, "<h2>{}</h2>", escape_html(&plot.title))?;
+        writeln!(
+            html,
+            "<img alt=\"{}\" src=\"data:image/png;base64,{}\">",
+            escape_html(&plot.title),
+            encode_base64(&plot.png)
+        )?;
+    }
+
+    // events timeline
+    writeln!(html, "<h2>Events</h2>")?;
+    let event_rows: Vec<Vec<String>> = result
+        .events
+        .iter()
+        .map(|ev| {
+            vec![
+                ev.lap.to_string(),
+                out_fmt.fmt_num(ev.time_s, 0, 1),
+                if ev.scripted {
+                    format!("{} (scripted)", ev.kind)
+                } else {
+                    ev.kind.to_owned()
+                },
+                ev.cars
+                    .iter()
+                    .map(|car_no| car_no.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", "),
+            ]
+        })
+        .collect();
+    write_table(&mut html, &["Lap", "Time (s)", "Event", "Cars"], &event_rows)?;
+
+    // sanity check
+    writeln!(html, "<h2>Sanity check</h2>")?;
+    if report_input.sanity_warnings.is_empty() {
+        writeln!(html, "<p>No warnings.</p>")?;
+    } else {
+        let warning_rows: Vec<Vec<String>> = report_input
+            .sanity_warnings
+            .iter()
+            .map(|warning| {
+                vec![
+                    format!("{:?}", warning.rule),
+                    warning.car_no.to_string(),
+                    warning.lap.to_string(),
+                    warning.msg.to_owned(),
+                ]
+            })
+            .collect();
+        write_table(&mut html, &["Rule", "Car", "Lap", "Message"], &warning_rows)?;
+    }
+
+    // resolved configuration
+    writeln!(html, "<h2>Configuration</h2>")?;
+    write_table(
+        &mut html,
+        &["Car", "Driver", "Color", "Source"],
+        &get_car_color_rows(result, report_input.sim_pars),
+    )?;
+    for (label, config) in [
+        ("Simulation parameters", serde_json::to_string_pretty(report_input.sim_pars)?),
+        ("Simulation constants", serde_json::to_string_pretty(report_input.sim_consts)?),
+    ]
+    .iter()
+    {
+        writeln!(
+            html,
+            "<details>\n<summary>{}</summary>\n<pre>{}</pre>\n</details>",
+            label,
+            escape_html(config)
+        )?;
+    }
+
+    writeln!(html, "</body>\n</html>")?;
+
+    Ok(html)
+}
+
+/// write_html_report writes the HTML report to the given path or to output/report.html if no path
+/// is given. Returns the path to the written file.
+pub fn write_html_report(
+    path: Option<&std::path::Path>,
+    report_input: &ReportInput,
+    out_fmt: &OutputFormat,
+) -> anyhow::Result<String> {
+    let html = create_html_report(report_input, out_fmt)?;
+    write_output_file(path, "report.html", &html)
+}
@@ -0,0 +1,271 @@
+use crate::post::output_fmt::OutputFormat;
+use crate::post::race_result::RaceResult;
+use std::fmt::Write;
+
+/// CarDiff contains the differences of a car between two results of the same scenario.
+/// * `car_no` - Car number
+/// * `driver_initials` - Driver initials (of the current result)
+/// * `position_prev` - Final position in the previous result (None if the car was not part of it)
+/// * `position_cur` - Final position in the current result (None if the car was not part of it)
+/// * `racetime_prev` - Race time at the last completed lap in the previous result
+/// * `racetime_cur` - Race time at the last completed lap in the current result
+/// * `laps_prev` - Number of completed laps in the previous result
+/// * `laps_cur` - Number of completed laps in the current result
+#[derive(Debug, Clone, PartialEq)]
+pub struct CarDiff {
+    pub car_no: u32,
+    pub driver_initials: String,
+    pub position_prev: Option<u32>,
+    pub position_cur: Option<u32>,
+    pub racetime_prev: f64,
+    pub racetime_cur: f64,
+    pub laps_prev: usize,
+    pub laps_cur: usize,
+}
+
+impl CarDiff {
+    /// get_delta_position returns the change of the final position (positive if the car lost
+    /// positions in the current result).
+    pub fn get_delta_position(&self) -> Option<i32> {
+        Some(self.position_cur? as i32 - self.position_prev? as i32)
+    }
+
+    /// get_delta_racetime returns the change of the race time (positive if the car was slower in
+    /// the current result). The race times are only comparable if the car completed the same
+    /// number of laps in both results, otherwise None is returned.
+    pub fn get_delta_racetime(&self) -> Option<f64> {
+        if self.position_prev.is_none()
+            || self.position_cur.is_none()
+            || self.laps_prev == 0
+            || self.laps_prev != self.laps_cur
+        {
+            return None;
+        }
+
+        Some(self.racetime_cur - self.racetime_prev)
+    }
+}
+
+/// ResultDiff contains the differences between two results of the same scenario, e.g. of two runs
+/// in the GUI.
+/// * `car_diffs` - Differences of the cars, sorted by the current final position (cars that are
+///   part of the previous result only are appended)
+/// * `sc_laps_prev` - Laps under safety car in the previous result
+/// * `sc_laps_cur` - Laps under safety car in the current result
+/// * `rain_laps_prev` - Laps (of the leader) started in rain in the previous result
+/// * `rain_laps_cur` - Laps (of the leader) started in rain in the current result
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultDiff {
+    pub car_diffs: Vec<CarDiff>,
+    pub sc_laps_prev: Vec<u32>,
+    pub sc_laps_cur: Vec<u32>,
+    pub rain_laps_prev: Vec<u32>,
+    pub rain_laps_cur: Vec<u32>,
+}
+
+/// get_sc_laps returns the laps of the leader under safety car, i.e. from the lap of every
+/// deployment (or the start behind the SC) until the lap in which the SC came in (or the end of the race).
+pub fn get_sc_laps(result: &RaceResult) -> Vec<u32> {
+    let mut sc_laps = vec![];
+    let mut lap_deployed: Option<u32> = None;
+
+    for event in result.events.iter() {
+        match event.kind.as_str() {
+            "SC_DEPLOYED" | "START_BEHIND_SC" => lap_deployed = Some(event.lap),
+            "SC_IN" => {
+                if let Some(lap_start) = lap_deployed.take() {
+                    sc_laps.extend(lap_start..event.lap + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(lap_start) = lap_deployed {
+        sc_laps.extend(lap_start..result.tot_no_laps + 1);
+    }
+
+    sc_laps.dedup();
+    sc_laps
+}
+
+/// get_rain_laps returns the laps (of the leader) that were started in rain.
+pub fn get_rain_laps(result: &RaceResult) -> Vec<u32> {
+    result
+        .weather_history
+        .iter()
+        .enumerate()
+        .filter(|(_, weather)| weather.as_str() == "Rain")
+        .map(|(i, _)| i as u32 + 1)
+        .collect()
+}
+
+/// format_laps formats a list of laps as ranges, e.g. "3-5, 12" ("none" if empty).
+fn format_laps(laps: &[u32]) -> String {
+    if laps.is_empty() {
+        return String::from("none");
+    }
+
+    let mut ranges: Vec<(u32, u32)> = vec![];
+
+    for &lap in laps.iter() {
+        match ranges.last_mut() {
+            Some(range) if range.1 + 1 == lap => range.1 = lap,
+            _ => ranges.push((lap, lap)),
+        }
+    }
+
+    ranges
+        .iter()
+        .map(|&(first, last)| {
+            if first == last {
+                first.to_string()
+            } else {
+                format!("{}-{}", first, last)
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+impl ResultDiff {
+    /// new compares the current result with the previous result. Cars are matched by their car
+    /// numbers.
+    pub fn new(result_prev: &RaceResult, result_cur: &RaceResult) -> ResultDiff {
+        let positions_prev = result_prev.get_final_positions();
+        let positions_cur = result_cur.get_final_positions();
+
+        let get_car_info = |result: &RaceResult, positions: &[u32], car_no: u32| {
+            let idx = result
+                .car_driver_pairs
+                .iter()
+                .position(|pair| pair.car_no == car_no)?;
+            let laps = result.get_last_driven_lap(idx);
+
+            Some((positions[idx], result.get_racetime(idx, laps), laps))
+        };
+
+        let mut car_diffs: Vec<CarDiff> = vec![];
+        let pairs_all = result_cur
+            .car_driver_pairs
+            .iter()
+            .chain(result_prev.car_driver_pairs.iter());
+
+        for pair in pairs_all {
+            if car_diffs.iter().any(|x| x.car_no == pair.car_no) {
+                continue;
+            }
+
+            let info_prev = get_car_info(result_prev, &positions_prev, pair.car_no);
+            let info_cur = get_car_info(result_cur, &positions_cur, pair.car_no);
+
+            car_diffs.push(CarDiff {
+                car_no: pair.car_no,
+                driver_initials: pair.driver_initials.to_owned(),
+                position_prev: info_prev.map(|x| x.0),
+                position_cur: info_cur.map(|x| x.0),
+                racetime_prev: info_prev.map_or(0.0, |x| x.1),
+                racetime_cur: info_cur.map_or(0.0, |x| x.1),
+                laps_prev: info_prev.map_or(0, |x| x.2),
+                laps_cur: info_cur.map_or(0, |x| x.2),
+            });
+        }
+
+        car_diffs.sort_by_key(|x| x.position_cur.unwrap_or(u32::MAX));
+
+        ResultDiff {
+            car_diffs,
+            sc_laps_prev: get_sc_laps(result_prev),
+            sc_laps_cur: get_sc_laps(result_cur),
+            rain_laps_prev: get_rain_laps(result_prev),
+            rain_laps_cur: get_rain_laps(result_cur),
+        }
+    }
+
+    /// get_laptime_pairs returns the lap times of a car in both results as (lap, previous lap
+    /// time, current lap time). Laps that were not driven in a result contain NaN.
+    pub fn get_laptime_pairs(
+        result_prev: &RaceResult,
+        result_cur: &RaceResult,
+        car_no: u32,
+    ) -> Vec<(u32, f64, f64)> {
+        let get_laptime = |result: &RaceResult, lap: u32| {
+            let laptime = result
+                .car_driver_pairs
+                .iter()
+                .position(|pair| pair.car_no == car_no)
+                .map_or(0.0, |idx| result.get_laptime(idx, lap as usize));
+
+            if laptime > 0.0 {
+                laptime
+            } else {
+                f64::NAN
+            }
+        };
+
+        (1..result_prev.tot_no_laps.max(result_cur.tot_no_laps) + 1)
+            .map(|lap| (lap, get_laptime(result_prev, lap), get_laptime(result_cur, lap)))
+            .collect()
+    }
+
+    /// get_differing_events returns a note for every stochastic influence that differed between
+    /// the two results (safety car and weather), e.g. "SC laps: 12-15 -> none". The list is empty
+    /// if the results do not differ in these respects.
+    pub fn get_differing_events(&self) -> Vec<String> {
+        let mut notes = vec![];
+
+        if self.sc_laps_prev != self.sc_laps_cur {
+            notes.push(format!(
+                "SC laps: {} -> {}",
+                format_laps(&self.sc_laps_prev),
+                format_laps(&self.sc_laps_cur)
+            ));
+        }
+
+        if self.rain_laps_prev != self.rain_laps_cur {
+            notes.push(format!(
+                "Rain laps: {} -> {}",
+                format_laps(&self.rain_laps_prev),
+                format_laps(&self.rain_laps_cur)
+            ));
+        }
+
+        notes
+    }
+
+    /// format creates a table of the position and race time deltas of all cars followed by the
+    /// differing events.
+    pub fn format(&self, out_fmt: &OutputFormat) -> Result<String, std::fmt::Error> {
+        let mut content = String::new();
+        let fmt_pos = |pos: Option<u32>| pos.map_or(String::from("-"), |x| format!("P{}", x));
+
+        writeln!(
+            content,
+            "{:>4} {:>4} {:>5} {:>5} {:>6} {:>10}",
+            "Car", "Drv", "Prev", "Cur", "dPos", "dTime (s)"
+        )?;
+
+        for car_diff in self.car_diffs.iter() {
+            writeln!(
+                content,
+                "{:>4} {:>4} {:>5} {:>5} {:>6} {:>10}",
+                car_diff.car_no,
+                car_diff.driver_initials,
+                fmt_pos(car_diff.position_prev),
+                fmt_pos(car_diff.position_cur),
+                car_diff
+                    .get_delta_position()
+                    .map_or(String::from("-"), |x| format!("{:+}", x)),
+                car_diff.get_delta_racetime().map_or(String::from("-"), |x| {
+                    format!("{}{}", if x >= 0.0 { "+" } else { "" }, out_fmt.fmt_num(x, 0, 3))
+                }),
+            )?;
+        }
+
+        for note in self.get_differing_events().iter() {
+            writeln!(content, "{}", note)?;
+        }
+
+        Ok(content)
+    }
+}
@@ -0,0 +1,156 @@
+use crate::core::track::Track;
+use crate::post::race_result::RaceResult;
+
+/// VelocityTrace contains the modeled speed of a car over the track distance in a single lap. The
+/// speed on track follows the speed multipliers of the track, `v(s) = v_base * multiplier(s)`,
+/// where `v_base` is chosen such that the lap integrates to the recorded lap time. The pit lane is
+/// driven at the speed limit (scaled to track coordinates) and the standstill time is removed
+/// before the normalization.
+/// * `car_no` - Car number
+/// * `lap` - Lap of the trace
+/// * `laptime` - (s) Recorded lap time
+/// * `dists` - (m) Track distance of every sample (center of the segment)
+/// * `velocities` - (m/s) Modeled speed of every sample
+/// * `in_pitlane` - Flags that indicate if the car was in the pit lane at the sample
+/// * `t_standstill` - (s) Standstill time of a pit stop in the lap (0 if none)
+#[derive(Debug, Clone, PartialEq)]
+pub struct VelocityTrace {
+    pub car_no: u32,
+    pub lap: u32,
+    pub laptime: f64,
+    pub dists: Vec<f64>,
+    pub velocities: Vec<f64>,
+    pub in_pitlane: Vec<bool>,
+    pub t_standstill: f64,
+}
+
+impl VelocityTrace {
+    /// get_modeled_laptime integrates the trace over the track distance (including the standstill
+    /// time), i.e. it should match the recorded lap time.
+    pub fn get_modeled_laptime(&self) -> f64 {
+        let ds = self.get_segment_length();
+        self.velocities.iter().map(|v| ds / v).sum::<f64>() + self.t_standstill
+    }
+
+    /// get_segment_length returns the track distance covered by a single sample.
+    fn get_segment_length(&self) -> f64 {
+        match self.dists.first() {
+            Some(dist) => 2.0 * dist,
+            None => 0.0,
+        }
+    }
+}
+
+/// is_in_interval checks if the track position is within the interval [start, end] that can wrap
+/// around the finish line.
+fn is_in_interval(s_track: f64, start: f64, end: f64) -> bool {
+    if start <= end {
+        s_track >= start && s_track <= end
+    } else {
+        s_track >= start || s_track <= end
+    }
+}
+
+/// get_velocity_trace creates the velocity trace of the given car in the given lap. The number of
+/// samples equals the number of track multipliers. An error is returned if the car is not part of
+/// the result or did not complete the lap.
+pub fn get_velocity_trace(
+    result: &RaceResult,
+    track: &Track,
+    car_no: u32,
+    lap: u32,
+) -> anyhow::Result<VelocityTrace> {
+    let idx = result
+        .car_driver_pairs
+        .iter()
+        .position(|pair| pair.car_no == car_no)
+        .ok_or_else(|| anyhow::anyhow!("Car {} is not part of the result!", car_no))?;
+    let laptime = result.get_laptime(idx, lap as usize);
+
+    if lap == 0 || laptime.is_nan() || laptime <= 0.0 {
+        anyhow::bail!("Car {} did not complete lap {}!", car_no, lap);
+    }
+
+    // sample positions (one per multiplier)
+    let no_samples = track.multipliers.len().max(1);
+    let ds = track.length / no_samples as f64;
+    let dists: Vec<f64> = (0..no_samples).map(|i| (i as f64 + 0.5) * ds).collect();
+
+    // determine the part of the pit lane driven in this lap: a pit zone across the finish line is
+    // split between in- and outlap, otherwise it belongs to the outlap if the pits are located
+    // after the finish line and to the inlap else
+    let is_inlap = result
+        .pit_stops
+        .iter()
+        .any(|pit_stop| pit_stop.car_no == car_no && pit_stop.lap == lap);
+    let is_outlap = result
+        .pit_stops
+        .iter()
+        .any(|pit_stop| pit_stop.car_no == car_no && pit_stop.lap + 1 == lap);
+    let [pit_start, pit_end] = track.pit_zone;
+    let pit_wraps = pit_start > pit_end;
+
+    let in_pitlane: Vec<bool> = dists
+        .iter()
+        .map(|&s| {
+            if !is_in_interval(s, pit_start, pit_end) {
+                false
+            } else if pit_wraps {
+                (is_inlap && s >= pit_start) || (is_outlap && s <= pit_end)
+            } else if track.pits_aft_finishline {
+                is_outlap
+            } else {
+                is_inlap
+            }
+        })
+        .collect();
+
+    // standstill time of the pit stops whose pit box is passed in this lap
+    let t_standstill: f64 = result
+        .pit_stops
+        .iter()
+        .filter(|pit_stop| {
+            pit_stop.car_no == car_no
+                && if track.pits_aft_finishline {
+                    pit_stop.lap + 1 == lap
+                } else {
+                    pit_stop.lap == lap
+                }
+        })
+        .map(|pit_stop| pit_stop.t_standstill)
+        .sum();
+
+    // pit lane speed in track coordinates (the real pit lane can be longer or shorter)
+    let v_pit = if track.real_length_pit_zone > 0.0 {
+        track.pit_speedlimit * track.track_length_pit_zone / track.real_length_pit_zone
+    } else {
+        track.pit_speedlimit
+    };
+    let t_pit_drive = in_pitlane.iter().filter(|&&x| x).count() as f64 * ds / v_pit;
+
+    // normalize the speed on track such that the lap integrates to the recorded lap time
+    let t_track = (laptime - t_pit_drive - t_standstill).max(1e-3);
+    let t_track_unscaled: f64 = dists
+        .iter()
+        .zip(in_pitlane.iter())
+        .filter(|(_, &pit)| !pit)
+        .map(|(&s, _)| ds / track.get_multiplier(s))
+        .sum();
+    let v_base = t_track_unscaled / t_track;
+
+    let velocities = dists
+        .iter()
+        .zip(in_pitlane.iter())
+        .map(|(&s, &pit)| if pit { v_pit } else { v_base * track.get_multiplier(s) })
+        .collect();
+
+    Ok(VelocityTrace {
+        car_no,
+        lap,
+        laptime,
+        dists,
+        velocities,
+        in_pitlane,
+        t_standstill,
+    })
+}
@@ -1,2 +1,4 @@
 pub mod read_sim_pars;
+pub mod scenario_randomizer;
 pub mod sim_opts;
+pub mod units;
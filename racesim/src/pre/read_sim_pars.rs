@@ -1,16 +1,19 @@
-use crate::core::car::CarPars;
+use crate::core::car::{CarPars, StrategyEntry};
 use crate::core::driver::DriverPars;
+use crate::core::livery::resolve_car_color;
 use crate::core::race::{RacePars, SimConstants};
+use crate::core::scripted_event::{validate_scripted_events, ScriptedAction, ScriptedEvent};
 use crate::core::track::TrackPars;
+use crate::pre::units::PIT_SPEEDLIMIT_PLAUSIBLE_MAX;
 use anyhow::Context;
-use serde::Deserialize;
-use crate::core::tireset::TireConfig;
+use serde::{Deserialize, Serialize};
+use crate::core::tireset::{TireConfig, COMPOUNDS};
 use std::collections::HashMap;
 use std::fs::OpenOptions;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// SimPars is used to store all other parameter structs.
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct SimPars {
     pub race_pars: RacePars,
     pub track_pars: TrackPars,
@@ -18,24 +21,422 @@ pub struct SimPars {
     pub car_pars_all: HashMap<u32, CarPars>,
 }
 
+/// ValidationReport contains the errors (the race cannot be simulated) and warnings (the race can
+/// be simulated but probably not as intended) found when validating the simulation parameters.
+#[derive(Debug, Default, Clone)]
+pub struct ValidationReport {
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+impl SimPars {
+    /// validate checks the strategies of all participants, the scripted events and the
+    /// plausibility of the track parameter units.
+    pub fn validate(&self) -> ValidationReport {
+        let mut report = ValidationReport::default();
+
+        // a speed given with a unit suffix is taken as intended
+        let pit_speedlimit = self.track_pars.pit_speedlimit;
+        if pit_speedlimit.unitless && pit_speedlimit.m_s > PIT_SPEEDLIMIT_PLAUSIBLE_MAX {
+            report.warnings.push(format!(
+                "Track: pit_speedlimit of {:.1} m/s is implausibly high, probably given in km/h \
+                (use e.g. \"{:.0} km/h\")",
+                pit_speedlimit.m_s, pit_speedlimit.m_s
+            ));
+        }
+
+        if let Some(wetness) = self.race_pars.initial_wetness {
+            if !(0.0..=1.0).contains(&wetness) {
+                report.errors.push(format!(
+                    "Race: initial_wetness of {} is outside of the range [0, 1]",
+                    wetness
+                ));
+            }
+        }
+
+        for car_no in self.race_pars.participants.iter() {
+            match self.car_pars_all.get(car_no) {
+                Some(car_pars) => {
+                    let car_report =
+                        validate_strategy(&car_pars.strategy, self.race_pars.tot_no_laps);
+                    for msg in car_report.errors {
+                        report.errors.push(format!("Car {}: {}", car_no, msg));
+                    }
+                    for msg in car_report.warnings {
+                        report.warnings.push(format!("Car {}: {}", car_no, msg));
+                    }
+
+                    if let Some(rate) = car_pars.failure_rate_per_hour {
+                        if !(rate.is_finite() && rate >= 0.0) {
+                            report.errors.push(format!(
+                                "Car {}: failure_rate_per_hour of {} must be a non-negative rate",
+                                car_no, rate
+                            ));
+                        }
+                    }
+
+                    if let Some(m_fuel) = car_pars.m_fuel_fixed {
+                        if !(m_fuel.is_finite() && m_fuel >= 0.0) {
+                            report.errors.push(format!(
+                                "Car {}: m_fuel_fixed of {} kg must be a non-negative fuel mass",
+                                car_no, m_fuel
+                            ));
+                        }
+                    }
+
+                    if !(0.0..=1.0).contains(&car_pars.downforce_level) {
+                        report.errors.push(format!(
+                            "Car {}: downforce_level of {} is outside of the range [0, 1]",
+                            car_no, car_pars.downforce_level
+                        ));
+                    }
+
+                    if let Some(rates) = car_pars.failure_rates_per_hour {
+                        if !rates.is_valid() {
+                            report.errors.push(format!(
+                                "Car {}: failure_rates_per_hour {:?} must be non-negative rates",
+                                car_no, rates
+                            ));
+                        }
+                    }
+
+                    match resolve_car_color(&car_pars.color, *car_no) {
+                        Ok(color) if color.fallback => report.warnings.push(format!(
+                            "Car {}: no color given, fallback livery {} is used",
+                            car_no,
+                            color.to_hex()
+                        )),
+                        Ok(_) => {}
+                        Err(_) => report
+                            .errors
+                            .push(format!("Car {}: invalid color '{}'", car_no, car_pars.color)),
+                    }
+                }
+                None => report
+                    .errors
+                    .push(format!("Car {}: missing car parameters", car_no)),
+            }
+        }
+
+        for msg in validate_scripted_events(
+            &self.race_pars.scripted_events,
+            self.race_pars.tot_no_laps,
+            &self.race_pars.participants,
+            &self.race_pars.initial_weather,
+        ) {
+            report.errors.push(format!("Scripted events: {}", msg));
+        }
+
+        let mut penalized_cars: Vec<&u32> = self.race_pars.grid_penalties.keys().collect();
+        penalized_cars.sort();
+        for car_no in penalized_cars {
+            if !self.race_pars.participants.contains(car_no) {
+                report.warnings.push(format!(
+                    "Race: grid penalty for car {} is ignored, the car is not a participant",
+                    car_no
+                ));
+            }
+        }
+
+        report
+    }
+
+    /// validate_sim_consts checks the simulation constants that refer to the simulation
+    /// parameters: the drivers of all participants need degradation parameters for the puncture
+    /// fallback compound.
+    pub fn validate_sim_consts(&self, sim_consts: &SimConstants) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        let fallback_compound = sim_consts.puncture_fallback_compound.to_uppercase();
+
+        let mut driver_initials: Vec<&str> = self
+            .race_pars
+            .participants
+            .iter()
+            .filter_map(|car_no| self.car_pars_all.get(car_no))
+            .flat_map(|car_pars| car_pars.strategy.iter())
+            .map(|entry| entry.driver_initials.as_str())
+            .collect();
+        driver_initials.sort_unstable();
+        driver_initials.dedup();
+
+        for initials in driver_initials {
+            match self.driver_pars_all.get(initials) {
+                Some(driver_pars) if !driver_pars.degr_pars_all.contains_key(&fallback_compound) => {
+                    report.errors.push(format!(
+                        "Driver {}: no degradation parameters for the puncture_fallback_compound {}",
+                        initials, fallback_compound
+                    ))
+                }
+                _ => {}
+            }
+        }
+
+        report
+    }
+
+    /// check_sim_consts returns an error containing all errors found by `validate_sim_consts`.
+    pub fn check_sim_consts(&self, sim_consts: &SimConstants) -> anyhow::Result<()> {
+        let report = self.validate_sim_consts(sim_consts);
+
+        if !report.errors.is_empty() {
+            anyhow::bail!("Simulation constants are invalid:\n{}", report.errors.join("\n"));
+        }
+
+        Ok(())
+    }
+
+    /// select_participants restricts the participants to the given car numbers (all if empty)
+    /// without the excluded ones, keeping their order. The grid positions of the remaining cars are
+    /// re-packed without gaps (preserving their relative order) and scripted events and grid
+    /// penalties of removed cars are dropped. Unknown car numbers are rejected.
+    pub fn select_participants(
+        &mut self,
+        only_cars: &[u32],
+        exclude_cars: &[u32],
+    ) -> anyhow::Result<()> {
+        for car_no in only_cars.iter().chain(exclude_cars.iter()) {
+            self.check_participant(*car_no)?;
+        }
+
+        self.race_pars.participants.retain(|car_no| {
+            (only_cars.is_empty() || only_cars.contains(car_no)) && !exclude_cars.contains(car_no)
+        });
+
+        if self.race_pars.participants.is_empty() {
+            anyhow::bail!("No participants left after selecting the cars!");
+        }
+
+        let participants = self.race_pars.participants.to_owned();
+        self.race_pars.scripted_events.retain(|scripted_event| {
+            scripted_event
+                .get_car_no()
+                .is_none_or(|car_no| participants.contains(&car_no))
+        });
+        self.race_pars
+            .grid_penalties
+            .retain(|car_no, _| participants.contains(car_no));
+
+        // re-pack grid positions
+        let mut grid: Vec<(u32, u32)> = participants
+            .iter()
+            .filter_map(|car_no| self.car_pars_all.get(car_no).map(|x| (x.p_grid, *car_no)))
+            .collect();
+        grid.sort();
+
+        for (i, (_, car_no)) in grid.iter().enumerate() {
+            if let Some(car_pars) = self.car_pars_all.get_mut(car_no) {
+                car_pars.p_grid = i as u32 + 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// withdraw_car adds a scripted retirement of the given car at the start of the given leader
+    /// lap (lap 0: right after the start), the car is still part of the grid. The stochastic
+    /// models are not affected.
+    pub fn withdraw_car(&mut self, car_no: u32, lap: u32) -> anyhow::Result<()> {
+        self.check_participant(car_no)?;
+
+        self.race_pars.scripted_events.push(ScriptedEvent {
+            lap,
+            action: ScriptedAction::Dnf { car_no },
+            override_stochastic: false,
+        });
+
+        Ok(())
+    }
+
+    /// fix_seed sets the seed of the race draws (given seed, else the seed of the parameter file,
+    /// else a random one) and returns it. A run with a fixed seed can be reproduced.
+    pub fn fix_seed(&mut self, seed: Option<u64>) -> u64 {
+        let seed = seed
+            .or(self.race_pars.seed)
+            .unwrap_or_else(rand::random);
+        self.race_pars.seed = Some(seed);
+        seed
+    }
+
+    /// check_participant returns an error listing the valid car numbers if the given car is not a
+    /// participant.
+    fn check_participant(&self, car_no: u32) -> anyhow::Result<()> {
+        if !self.race_pars.participants.contains(&car_no) {
+            let valid: Vec<String> =
+                self.race_pars.participants.iter().map(|x| x.to_string()).collect();
+            anyhow::bail!(
+                "Car {} is not a participant (valid car numbers: {})!",
+                car_no,
+                valid.join(", ")
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// validate_strategy checks a single strategy: the start entry must exist, inlaps must be within
+/// the race length and unique, and compounds must be known. A warning is added if less than two
+/// different slick compounds are used in a dry strategy (two-compound rule).
+pub fn validate_strategy(strategy: &[StrategyEntry], tot_no_laps: u32) -> ValidationReport {
+    let mut report = ValidationReport::default();
+
+    if strategy.is_empty() {
+        report.errors.push(String::from("strategy has no start entry"));
+        return report;
+    }
+
+    let mut inlaps: Vec<u32> = Vec::with_capacity(strategy.len());
+
+    for (i, entry) in strategy.iter().enumerate() {
+        if i > 0 && (entry.inlap == 0 || entry.inlap >= tot_no_laps) {
+            report.errors.push(format!(
+                "inlap {} is not within the race length [1, {}]",
+                entry.inlap,
+                tot_no_laps.saturating_sub(1)
+            ));
+        }
+        if i > 0 && inlaps.contains(&entry.inlap) {
+            report
+                .errors
+                .push(format!("inlap {} is used more than once", entry.inlap));
+        }
+        inlaps.push(entry.inlap);
+
+        if !COMPOUNDS.contains(&entry.compound.to_uppercase().as_str()) {
+            report
+                .errors
+                .push(format!("unknown compound '{}'", entry.compound));
+        }
+    }
+
+    let mut slicks: Vec<String> = Vec::new();
+    let mut wet_used = false;
+
+    for entry in strategy.iter() {
+        let compound = entry.compound.to_uppercase();
+        match compound.as_str() {
+            "SOFT" | "MEDIUM" | "HARD" => {
+                if !slicks.contains(&compound) {
+                    slicks.push(compound)
+                }
+            }
+            _ => wet_used = true,
+        }
+    }
+
+    if !wet_used && slicks.len() < 2 {
+        report.warnings.push(String::from(
+            "less than two different slick compounds are used (two-compound rule)",
+        ));
+    }
+
+    report
+}
+
 /// read_sim_pars reads the JSON file and decodes the JSON string into the simulation parameters
 /// struct.
 pub fn read_sim_pars(filepath: &Path) -> anyhow::Result<SimPars> {
     // open file
+    let content = std::fs::read_to_string(filepath).context(format!(
+        "Failed to open parameter file {}!",
+        filepath.to_str().unwrap()
+    ))?;
+
+    // parse parameter file content
+    let pars = parse_par_file(filepath, &content).context(format!(
+        "Failed to parse parameter file {}!",
+        filepath.to_str().unwrap()
+    ))?;
+    Ok(pars)
+}
+
+/// is_toml_file returns true if the file has the extension `.toml` (any case).
+fn is_toml_file(filepath: &Path) -> bool {
+    filepath
+        .extension()
+        .map(|ext| ext.eq_ignore_ascii_case("toml"))
+        .unwrap_or(false)
+}
+
+/// parse_par_file decodes the content of a parameter or scenario file, which is JSON or TOML
+/// (extension `.toml`). TOML is converted to JSON first, such that both formats are decoded the
+/// same way (e.g. the car numbers used as keys of `car_pars_all`).
+fn parse_par_file<T: serde::de::DeserializeOwned>(
+    filepath: &Path,
+    content: &str,
+) -> anyhow::Result<T> {
+    if is_toml_file(filepath) {
+        let value: toml::Value = toml::from_str(content)?;
+        Ok(serde_json::from_value(serde_json::to_value(value)?)?)
+    } else {
+        Ok(serde_json::from_str(content)?)
+    }
+}
+
+/// write_sim_pars writes the simulation parameters to a JSON file such that an edited scenario can
+/// be reproduced.
+pub fn write_sim_pars(sim_pars: &SimPars, filepath: &Path) -> anyhow::Result<()> {
+    let fh = OpenOptions::new()
+        .create(true)
+        .truncate(true)
+        .write(true)
+        .open(filepath)
+        .context(format!(
+            "Failed to open parameter file {} for writing!",
+            filepath.to_string_lossy()
+        ))?;
+
+    serde_json::to_writer_pretty(&fh, sim_pars).context(format!(
+        "Failed to write parameter file {}!",
+        filepath.to_string_lossy()
+    ))?;
+    Ok(())
+}
+
+/// RunConfig is the resolved configuration of a run that is written next to the results, such that
+/// the run can be post-processed later (e.g. plots that require the track geometry).
+/// * `sim_pars` - Simulation parameters of the run
+/// * `trackfile_path` - Path to the track file used for the track geometry
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RunConfig {
+    pub sim_pars: SimPars,
+    pub trackfile_path: PathBuf,
+}
+
+/// write_run_config writes the run configuration to a JSON file.
+pub fn write_run_config(run_config: &RunConfig, filepath: &Path) -> anyhow::Result<()> {
+    let fh = OpenOptions::new()
+        .create(true)
+        .truncate(true)
+        .write(true)
+        .open(filepath)
+        .context(format!(
+            "Failed to open run configuration file {} for writing!",
+            filepath.to_string_lossy()
+        ))?;
+
+    serde_json::to_writer_pretty(&fh, run_config).context(format!(
+        "Failed to write run configuration file {}!",
+        filepath.to_string_lossy()
+    ))?;
+    Ok(())
+}
+
+/// read_run_config reads the run configuration from a JSON file.
+pub fn read_run_config(filepath: &Path) -> anyhow::Result<RunConfig> {
     let fh = OpenOptions::new()
         .read(true)
         .open(filepath)
         .context(format!(
-            "Failed to open parameter file {}!",
-            filepath.to_str().unwrap()
+            "Failed to open run configuration file {}!",
+            filepath.to_string_lossy()
         ))?;
 
-    // read and parse parameter file content
-    let pars = serde_json::from_reader(&fh).context(format!(
-        "Failed to parse parameter file {}!",
-        filepath.to_str().unwrap()
+    let run_config = serde_json::from_reader(&fh).context(format!(
+        "Failed to parse run configuration file {}!",
+        filepath.to_string_lossy()
     ))?;
-    Ok(pars)
+    Ok(run_config)
 }
 
 /// Read simulation constants (physics/engine parameters) from a JSON file.
@@ -63,14 +464,11 @@ pub struct RaceScenarioFile {
 }
 
 pub fn read_race_scenario(filepath: &Path) -> anyhow::Result<RaceScenarioFile> {
-    let fh = OpenOptions::new()
-        .read(true)
-        .open(filepath)
-        .context(format!(
-            "Failed to open race scenario file {}!",
-            filepath.to_str().unwrap()
-        ))?;
-    let pars = serde_json::from_reader(&fh).context(format!(
+    let content = std::fs::read_to_string(filepath).context(format!(
+        "Failed to open race scenario file {}!",
+        filepath.to_str().unwrap()
+    ))?;
+    let pars = parse_par_file(filepath, &content).context(format!(
         "Failed to parse race scenario file {}!",
         filepath.to_str().unwrap()
     ))?;
@@ -107,6 +505,25 @@ pub fn read_tire_config(filepath: &Path) -> anyhow::Result<TireConfig> {
     Ok(pars)
 }
 
+/// read_track_pars_by_name loads the track parameters of the given track from
+/// `input/parameters/tracks/{track_name}.json`.
+fn read_track_pars_by_name(track_name: &Option<String>) -> anyhow::Result<TrackPars> {
+    let track_name = track_name
+        .clone()
+        .ok_or_else(|| {
+            anyhow::anyhow!("Scenario missing track_name; required when track_pars is not present")
+        })?;
+    let track_path: std::path::PathBuf = [
+        "input",
+        "parameters",
+        "tracks",
+        &format!("{}.json", track_name),
+    ]
+    .iter()
+    .collect();
+    read_track_pars(&track_path)
+}
+
 /// Flexible reader: tries full SimPars first; if it fails, reads a scenario-only file
 /// (without `track_pars`) and loads track from `input/parameters/tracks/{track_name}.json`.
 pub fn read_sim_pars_flexible(filepath: &Path) -> anyhow::Result<SimPars> {
@@ -114,20 +531,7 @@ pub fn read_sim_pars_flexible(filepath: &Path) -> anyhow::Result<SimPars> {
         Ok(p) => Ok(p),
         Err(_) => {
             let scen = read_race_scenario(filepath)?;
-            let track_name = scen
-                .race_pars
-                .track_name
-                .clone()
-                .ok_or_else(|| anyhow::anyhow!("Scenario missing track_name; required when track_pars is not present"))?;
-            let track_path: std::path::PathBuf = [
-                "input",
-                "parameters",
-                "tracks",
-                &format!("{}.json", track_name),
-            ]
-            .iter()
-            .collect();
-            let track_pars = read_track_pars(&track_path)?;
+            let track_pars = read_track_pars_by_name(&scen.race_pars.track_name)?;
             Ok(SimPars {
                 race_pars: scen.race_pars,
                 track_pars,
@@ -137,3 +541,174 @@ pub fn read_sim_pars_flexible(filepath: &Path) -> anyhow::Result<SimPars> {
         }
     }
 }
+
+/// PartialSimPars is used to read a parameter file that contains only a part of the simulation
+/// parameters, e.g. the cars and drivers of a single team. Several partial files are combined into
+/// the simulation parameters by `merge_sim_pars`.
+/// * `race_pars` - Race parameters (must be contained in exactly one file)
+/// * `track_pars` - Track parameters (must be contained in at most one file, otherwise the track
+///   is loaded by the `track_name` of the race parameters)
+/// * `driver_pars_all` - Driver parameters (complete entries, replacing those of earlier files)
+/// * `car_pars_all` - Car parameters (complete entries, replacing those of earlier files)
+/// * `participants` - Additional participants, appended to those of the race parameters
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct PartialSimPars {
+    pub race_pars: Option<RacePars>,
+    pub track_pars: Option<TrackPars>,
+    #[serde(default)]
+    pub driver_pars_all: HashMap<String, DriverPars>,
+    #[serde(default)]
+    pub car_pars_all: HashMap<u32, CarPars>,
+    #[serde(default)]
+    pub participants: Vec<u32>,
+}
+
+/// merge_sim_pars combines partial parameter files (given as pairs of a source name used in the
+/// error messages and the file content) into the simulation parameters. The car and driver maps
+/// are unioned, an entry of a later file replaces the whole entry of an earlier file with the same
+/// key (the entries are not merged field by field). The participants of all files are concatenated and de-duplicated (first occurrence
+/// wins). The race and track parameters must come from exactly one file each, an error is returned
+/// otherwise.
+pub fn merge_sim_pars(parts: &[(String, PartialSimPars)]) -> anyhow::Result<SimPars> {
+    // race and track parameters must be unique
+    let race_pars_sources: Vec<&(String, PartialSimPars)> =
+        parts.iter().filter(|(_, part)| part.race_pars.is_some()).collect();
+    let track_pars_sources: Vec<&(String, PartialSimPars)> =
+        parts.iter().filter(|(_, part)| part.track_pars.is_some()).collect();
+
+    if race_pars_sources.len() != 1 {
+        anyhow::bail!(
+            "race_pars must be defined in exactly one parameter file, found in {} ({})!",
+            race_pars_sources.len(),
+            race_pars_sources
+                .iter()
+                .map(|(source, _)| source.as_str())
+                .collect::<Vec<&str>>()
+                .join(", ")
+        );
+    }
+
+    if track_pars_sources.len() > 1 {
+        anyhow::bail!(
+            "track_pars must be defined in at most one parameter file, found in {} ({})!",
+            track_pars_sources.len(),
+            track_pars_sources
+                .iter()
+                .map(|(source, _)| source.as_str())
+                .collect::<Vec<&str>>()
+                .join(", ")
+        );
+    }
+
+    let mut race_pars = race_pars_sources[0].1.race_pars.clone().unwrap();
+    let track_pars = match track_pars_sources.first() {
+        Some((_, part)) => part.track_pars.clone().unwrap(),
+        None => read_track_pars_by_name(&race_pars.track_name)?,
+    };
+
+    // union the car and driver parameters, later files replace the entries of earlier ones
+    let mut driver_pars_all = HashMap::new();
+    let mut car_pars_all = HashMap::new();
+
+    for (_, part) in parts.iter() {
+        driver_pars_all.extend(part.driver_pars_all.clone());
+        car_pars_all.extend(part.car_pars_all.clone());
+    }
+
+    // concatenate the participants (the race parameters of the race_pars file come first)
+    let mut participants = race_pars.participants.clone();
+
+    for (_, part) in parts.iter() {
+        participants.extend(part.participants.iter());
+    }
+
+    let mut participants_unique: Vec<u32> = Vec::with_capacity(participants.len());
+
+    for car_no in participants {
+        if !participants_unique.contains(&car_no) {
+            participants_unique.push(car_no);
+        }
+    }
+    race_pars.participants = participants_unique;
+
+    Ok(SimPars {
+        race_pars,
+        track_pars,
+        driver_pars_all,
+        car_pars_all,
+    })
+}
+
+/// read_sim_pars_merged reads one or several parameter files. A single file is read by
+/// `read_sim_pars_flexible`, several files are read as partial parameter files and merged by
+/// `merge_sim_pars` (in the given order).
+pub fn read_sim_pars_merged(filepaths: &[PathBuf]) -> anyhow::Result<SimPars> {
+    if filepaths.len() == 1 {
+        return read_sim_pars_flexible(&filepaths[0]);
+    }
+
+    let mut parts = Vec::with_capacity(filepaths.len());
+
+    for filepath in filepaths.iter() {
+        let content = std::fs::read_to_string(filepath).context(format!(
+            "Failed to open parameter file {}!",
+            filepath.to_string_lossy()
+        ))?;
+        let part: PartialSimPars = parse_par_file(filepath, &content).context(format!(
+            "Failed to parse parameter file {}!",
+            filepath.to_string_lossy()
+        ))?;
+        parts.push((filepath.to_string_lossy().to_string(), part));
+    }
+
+    merge_sim_pars(&parts)
+}
+
+/// is_probably_scenario checks cheaply if the content of a JSON or TOML file is a scenario or a
+/// full parameter file, i.e. if it contains the top-level key `race_pars`. The content is not
+/// decoded into the parameter structs.
+pub fn is_probably_scenario(content: &str) -> bool {
+    if !content.contains("race_pars") {
+        return false;
+    }
+
+    match serde_json::from_str::<serde_json::Value>(content) {
+        Ok(value) => value.get("race_pars").is_some(),
+        Err(_) => toml::from_str::<toml::Value>(content)
+            .map(|value| value.get("race_pars").is_some())
+            .unwrap_or(false),
+    }
+}
+
+/// discover_scenarios returns the JSON and TOML files in the given directories (not recursive) that
+/// are probably scenarios, sorted by path. YAML files are not offered since they cannot be read by
+/// `read_sim_pars_flexible`. Directories that cannot be read are skipped.
+pub fn discover_scenarios(dirs: &[&Path]) -> Vec<std::path::PathBuf> {
+    let mut scenario_paths = vec![];
+
+    for dir in dirs.iter() {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(x) => x,
+            Err(_) => continue,
+        };
+
+        for path in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+            let is_par_file = path
+                .extension()
+                .map(|ext| ext.eq_ignore_ascii_case("json") || ext.eq_ignore_ascii_case("toml"))
+                .unwrap_or(false);
+
+            if is_par_file
+                && std::fs::read_to_string(&path)
+                    .map(|content| is_probably_scenario(&content))
+                    .unwrap_or(false)
+            {
+                scenario_paths.push(path);
+            }
+        }
+    }
+
+    scenario_paths.sort();
+    scenario_paths.dedup();
+    scenario_paths
+}
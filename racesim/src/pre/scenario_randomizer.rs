@@ -0,0 +1,133 @@
+use crate::pre::read_sim_pars::{write_sim_pars, SimPars};
+use anyhow::Context;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Deserialize;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+/// RandomizerSpec defines which parameters are jittered and by how much. All values are the
+/// half-widths of uniform distributions around the base values, a value of zero disables the jitter.
+/// * `t_car` - (s) Jitter of the car time loss
+/// * `t_driver` - (s) Jitter of the driver time loss
+/// * `consistency` - (-) Jitter of the driver consistency (result is limited to [0.0, 1.0])
+/// * `inlap_shift` - (laps) Maximum shift of the planned pit stops
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct RandomizerSpec {
+    #[serde(default)]
+    pub t_car: f64,
+    #[serde(default)]
+    pub t_driver: f64,
+    #[serde(default)]
+    pub consistency: f64,
+    #[serde(default)]
+    pub inlap_shift: u32,
+}
+
+/// read_randomizer_spec reads the randomizer specification from a TOML file.
+pub fn read_randomizer_spec(filepath: &Path) -> anyhow::Result<RandomizerSpec> {
+    let content = read_to_string(filepath).context(format!(
+        "Failed to open randomizer spec file {}!",
+        filepath.display()
+    ))?;
+
+    toml::from_str(&content).context(format!(
+        "Failed to parse randomizer spec file {}!",
+        filepath.display()
+    ))
+}
+
+/// draw_jitter draws a value from the uniform distribution [-half_width, half_width].
+fn draw_jitter(rng: &mut StdRng, half_width: f64) -> f64 {
+    if half_width > 0.0 {
+        rng.gen_range(-half_width..=half_width)
+    } else {
+        0.0
+    }
+}
+
+/// randomize_scenario creates `no_variants` variants of the base scenario by jittering the
+/// parameters selected in the spec. All draws are made from a generator seeded by `seed` such
+/// that the set of variants is reproducible.
+///
+/// The planned pit stops are shifted such that they remain in ascending order and within the
+/// valid lap range [1, tot_no_laps - 1].
+pub fn randomize_scenario(
+    base: &SimPars,
+    spec: &RandomizerSpec,
+    no_variants: u32,
+    seed: u64,
+) -> Vec<SimPars> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let tot_no_laps = base.race_pars.tot_no_laps;
+
+    // sort keys such that the order of the draws does not depend on the hash map order
+    let mut car_nos: Vec<u32> = base.car_pars_all.keys().copied().collect();
+    car_nos.sort_unstable();
+    let mut initials: Vec<String> = base.driver_pars_all.keys().cloned().collect();
+    initials.sort();
+
+    let mut variants = Vec::with_capacity(no_variants as usize);
+
+    for _ in 0..no_variants {
+        let mut variant = base.to_owned();
+
+        for car_no in car_nos.iter() {
+            let car_pars = variant.car_pars_all.get_mut(car_no).unwrap();
+            car_pars.t_car += draw_jitter(&mut rng, spec.t_car);
+
+            // shift pit stops (entry 0 contains the start compound)
+            if car_pars.strategy.is_empty() {
+                continue;
+            }
+
+            car_pars.strategy[1..].sort_by_key(|entry| entry.inlap);
+            let no_stops = car_pars.strategy.len() - 1;
+            let mut inlap_prev = 0;
+
+            for (i, entry) in car_pars.strategy.iter_mut().skip(1).enumerate() {
+                let shift = if spec.inlap_shift > 0 {
+                    rng.gen_range(-(spec.inlap_shift as i64)..=spec.inlap_shift as i64)
+                } else {
+                    0
+                };
+
+                // keep the stops unique and leave room for the following ones
+                let inlap_min = inlap_prev as i64 + 1;
+                let inlap_max = tot_no_laps as i64 - 1 - (no_stops - 1 - i) as i64;
+
+                entry.inlap = (entry.inlap as i64 + shift).min(inlap_max).max(inlap_min) as u32;
+                inlap_prev = entry.inlap;
+            }
+        }
+
+        for initials in initials.iter() {
+            let driver_pars = variant.driver_pars_all.get_mut(initials).unwrap();
+            driver_pars.t_driver += draw_jitter(&mut rng, spec.t_driver);
+            driver_pars.consistency = (driver_pars.consistency
+                + draw_jitter(&mut rng, spec.consistency))
+            .clamp(0.0, 1.0);
+        }
+
+        variants.push(variant);
+    }
+
+    variants
+}
+
+/// write_scenario_variants writes the variants as variant_000.json, variant_001.json, ... into
+/// the output directory and returns the written paths.
+pub fn write_scenario_variants(variants: &[SimPars], out_dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(out_dir)
+        .context(format!("Failed to create directory {}!", out_dir.display()))?;
+
+    let mut paths = Vec::with_capacity(variants.len());
+
+    for (i, variant) in variants.iter().enumerate() {
+        let path = out_dir.join(format!("variant_{:03}.json", i));
+        write_sim_pars(variant, &path)?;
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
@@ -1,5 +1,9 @@
-use clap::Parser;
+use crate::core::model_toggles::ModelToggles;
+use crate::post::aggregate::H2HPair;
+use crate::post::output_fmt::{Lang, OutputFormat};
+use clap::{Parser, Subcommand};
 use std::path::PathBuf;
+use std::str::FromStr;
 
 #[derive(Debug, Parser, Clone)]
 #[clap(
@@ -9,6 +13,10 @@ use std::path::PathBuf;
     about = "A time-discrete race simulator written in Rust"
 )]
 pub struct SimOpts {
+    // SUBCOMMANDS ---------------------------------------------------------------------------------
+    #[clap(subcommand)]
+    pub command: Option<SimCommand>,
+
     // FLAGS ---------------------------------------------------------------------------------------
     /// Activate debug printing (only for non-GUI mode)
     #[clap(short, long)]
@@ -18,20 +26,303 @@ pub struct SimOpts {
     #[clap(short, long)]
     pub gui: bool,
 
+    /// Use a comma as decimal separator in the outputs (CSV files then use ';' as separator)
+    #[clap(long)]
+    pub decimal_comma: bool,
+
+    /// Additionally write the lap times to a CSV file (only for non-GUI mode)
+    #[clap(long)]
+    pub csv: bool,
+
+    /// Additionally write the results and sanity check findings to a JSON file (only for non-GUI
+    /// mode)
+    #[clap(long)]
+    pub json: bool,
+
+    /// Exit with an error if the sanity check of the results finds non-physical values
+    #[clap(long)]
+    pub strict: bool,
+
+    /// Replace car numbers and driver initials by aliases (Car A, Car B, ...) in all outputs, the
+    /// mapping to the real identities is written to output/mapping.json (only for non-GUI mode)
+    #[clap(long)]
+    pub anonymize: bool,
+
+    /// Write a self-contained HTML report (plots, tables, events, configuration) to
+    /// output/report.html (only for a single non-GUI run)
+    #[clap(long)]
+    pub report: bool,
+
+    /// Never prompt for input, e.g. for the scenario if no parameter file is given (prompts are only
+    /// shown if stdin is a terminal)
+    #[clap(long)]
+    pub non_interactive: bool,
+
     // OPTIONS -------------------------------------------------------------------------------------
     /// Set number of simulation runs (only for non-GUI mode, ignored in GUI mode)
     #[clap(short, long, default_value = "1")]
     pub no_sim_runs: u32,
 
-    /// Set path to the simulation parameter file (OPTIONAL: if not set, uses hardcoded 2-car race)
+    /// Set path to the simulation parameter file, can be given multiple times to merge partial
+    /// files into a combined grid (later files override earlier ones)
     #[clap(short, long)]
-    pub parfile_path: Option<PathBuf>, 
+    pub parfile_path: Vec<PathBuf>,
+
+    /// Print the intermediate classification at the end of the given lap and mark the lap in the
+    /// plot (only for a single non-GUI run)
+    #[clap(long)]
+    pub classification_at_lap: Option<u32>,
+
+    /// Simulate only the given cars (comma-separated car numbers), the grid positions are re-packed
+    #[clap(long, use_value_delimiter = true)]
+    pub only_cars: Vec<u32>,
+
+    /// Remove the given cars from the participants (comma-separated car numbers)
+    #[clap(long, use_value_delimiter = true)]
+    pub exclude_cars: Vec<u32>,
+
+    /// Keep a car in the grid but retire it at the start of a leader lap, e.g. 24:lap0 retires car
+    /// 24 right after the start (can be given multiple times)
+    #[clap(long)]
+    pub withdraw: Vec<Withdrawal>,
+
+    /// Compare two cars head-to-head over the runs of a Monte Carlo batch, e.g. 44:63 (only
+    /// relevant with --runs > 1, can be given multiple times)
+    #[clap(long)]
+    pub h2h: Vec<H2HPair>,
+
+    /// Set random seed of the run (default: seed of the parameter file or random), run i of a
+    /// Monte Carlo batch uses seed + i
+    #[clap(long)]
+    pub seed: Option<u64>,
+
+    /// Switch off model components for the whole run (ablation), e.g. tire-degradation,dirty-air
+    /// (valid: tire-degradation, dirty-air, sc, weather, mistakes, duels)
+    #[clap(long)]
+    pub disable: Option<ModelToggles>,
 
     /// Set real-time factor (only relevant in GUI mode)
     #[clap(short, long, default_value = "1.0")]
     pub realtime_factor: f64,
 
+    /// Set update frequency (Hz) of the GUI (only relevant in GUI mode)
+    #[clap(long, default_value = "20.0")]
+    pub gui_fps: f64,
+
     /// Set simulation timestep size in seconds, should be in the range [0.001, 1.0]
     #[clap(short, long, default_value = "0.1")]
     pub timestep_size: f64,
+
+    /// Set language of plot captions and labels (pl or en)
+    #[clap(long, default_value = "pl")]
+    pub lang: Lang,
+
+    /// Stream the race events live to output/last_run_events_live.txt and
+    /// output/last_run_events.jsonl (only for non-GUI single runs without recording)
+    #[clap(long)]
+    pub stream_events: bool,
+
+    /// Record the race states to a file for offline rendering (only for non-GUI single runs)
+    #[clap(long)]
+    pub record: Option<PathBuf>,
+
+    /// Set memory budget in MB of the per-step histories (e.g. the recording). If it would be
+    /// exceeded, the recording is spilled to --record-spill or its frame time step is increased
+    #[clap(long, default_value = "256")]
+    pub history_budget_mb: usize,
+
+    /// Set path of a file to which recorded frames are moved when the memory budget is reached
+    #[clap(long)]
+    pub record_spill: Option<PathBuf>,
+
+    /// Render the race to an animated GIF after a non-GUI single run (recorded races are rendered
+    /// with the render subcommand)
+    #[clap(long)]
+    pub render: Option<PathBuf>,
+
+    /// Set frame rate of the rendered animation
+    #[clap(long, default_value = "30.0")]
+    pub render_fps: f64,
+
+    /// Set number of race seconds shown per second of the rendered animation
+    #[clap(long, default_value = "60.0")]
+    pub render_speedup: f64,
+}
+
+/// SimCommand contains the tools that can be run instead of a race simulation.
+#[derive(Debug, Subcommand, Clone)]
+pub enum SimCommand {
+    /// Generate jittered variants of a base scenario for robustness testing
+    RandomizeScenario {
+        /// Set path to the base scenario
+        #[clap(short, long)]
+        base: PathBuf,
+
+        /// Set path to the TOML file specifying which parameters are jittered
+        #[clap(short, long)]
+        spec: PathBuf,
+
+        /// Set output directory of the variants
+        #[clap(short, long, default_value = "output/variants")]
+        out_dir: PathBuf,
+
+        /// Set number of variants
+        #[clap(short = 'k', long, default_value = "10")]
+        no_variants: u32,
+
+        /// Set master seed of the random draws
+        #[clap(long, default_value = "0")]
+        seed: u64,
+    },
+
+    /// Simulate a race until the leader completed the given lap and write a checkpoint from which
+    /// the race can be resumed (e.g. by whatif)
+    Checkpoint {
+        /// Set path to the simulation parameter file
+        #[clap(short, long)]
+        parfile_path: PathBuf,
+
+        /// Set leader lap after which the race is interrupted
+        #[clap(long)]
+        lap: u32,
+
+        /// Set seed of the simulated history (default: seed of the parameter file or random)
+        #[clap(long)]
+        seed: Option<u64>,
+
+        /// Set path of the written checkpoint
+        #[clap(short, long)]
+        out: PathBuf,
+    },
+
+    /// Compare the outcomes of a race resumed from a checkpoint with and without one modified
+    /// strategy decision
+    Whatif {
+        /// Set path to the checkpoint of the race (written by the checkpoint subcommand)
+        #[clap(long)]
+        resume_from: PathBuf,
+
+        /// Set modified decision, e.g. car_pars_all.44.strategy.2.inlap=34
+        #[clap(long)]
+        set: String,
+
+        /// Set number of resumed runs per variant
+        #[clap(long, default_value = "50")]
+        runs: u32,
+    },
+
+    /// Render a recorded race (written with --record) to an animated GIF without simulating
+    Render {
+        /// Set path to the recorded race
+        #[clap(short, long)]
+        input: PathBuf,
+
+        /// Set path of the animation (default: input path with extension gif)
+        #[clap(short, long)]
+        out: Option<PathBuf>,
+
+        /// Set frame rate of the animation
+        #[clap(long, default_value = "30.0")]
+        fps: f64,
+
+        /// Set number of race seconds shown per second of the animation
+        #[clap(long, default_value = "60.0")]
+        speedup: f64,
+    },
+
+    /// Let a single car drive alone (free practice) and show the lap time breakdown of every lap,
+    /// the breakdown is also written to output/practice_breakdown.csv
+    Practice {
+        /// Set path to the simulation parameter file
+        #[clap(short, long)]
+        parfile_path: PathBuf,
+
+        /// Set car number of the practicing car
+        #[clap(long)]
+        car: u32,
+
+        /// Set number of laps
+        #[clap(long, default_value = "30")]
+        laps: u32,
+    },
+
+    /// Simulate the rounds of a season one after another and show the championship standings,
+    /// the penalty points and the bans served, the season state is written to
+    /// output/season_state.json
+    Season {
+        /// Set path to the season file
+        #[clap(short, long)]
+        season_path: PathBuf,
+    },
+
+    /// Plot a previous run from the results and the run configuration in the run directory
+    /// (written with --json), the plot is written to the run directory
+    Plot {
+        /// Set kind of the plot (velocity: modeled speed over the track distance in a single lap)
+        #[clap(long, default_value = "velocity")]
+        kind: PlotKind,
+
+        /// Set car number of the plotted car
+        #[clap(long)]
+        car: u32,
+
+        /// Set plotted lap
+        #[clap(long)]
+        lap: u32,
+
+        /// Set directory of the run (must contain last_run.json and run_config.json)
+        #[clap(long, default_value = "output")]
+        run_dir: PathBuf,
+    },
+}
+
+/// PlotKind selects the plot created by the plot subcommand.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlotKind {
+    Velocity,
+}
+
+impl FromStr for PlotKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "velocity" => Ok(PlotKind::Velocity),
+            _ => Err(format!("Unknown plot kind '{}' (expected velocity)!", s)),
+        }
+    }
+}
+
+/// Withdrawal is a car that retires at the start of the given leader lap (0: right after the
+/// start), given as `<car_no>:lap<lap>` or `<car_no>:<lap>`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Withdrawal {
+    pub car_no: u32,
+    pub lap: u32,
+}
+
+impl FromStr for Withdrawal {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("Invalid withdrawal '{}' (expected e.g. 24:lap0)!", s);
+        let (car_no, lap) = s.split_once(':').ok_or_else(err)?;
+        let lap = lap.trim();
+        let lap = lap.strip_prefix("lap").unwrap_or(lap);
+
+        Ok(Withdrawal {
+            car_no: car_no.trim().parse().map_err(|_| err())?,
+            lap: lap.parse().map_err(|_| err())?,
+        })
+    }
+}
+
+impl SimOpts {
+    /// get_output_format returns the output locale settings selected on the command line.
+    pub fn get_output_format(&self) -> OutputFormat {
+        OutputFormat {
+            lang: self.lang,
+            decimal_comma: self.decimal_comma,
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,129 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Above this unitless pit speed limit (m/s) the value was most likely entered in km/h.
+pub const PIT_SPEEDLIMIT_PLAUSIBLE_MAX: f64 = 40.0;
+
+/// NumberOrString is the raw form of a unit-aware parameter: either the canonical number or a
+/// string with a unit suffix.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NumberOrString {
+    Number(f64),
+    String(String),
+}
+
+/// SpeedUnit is the canonical unit a parsed speed is converted to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpeedUnit {
+    MPerS,
+    KmPerH,
+}
+
+/// parse_speed parses a speed given as a number (in the canonical unit) with an optional unit
+/// suffix "m/s" or "km/h", e.g. "80 km/h", and converts it to the canonical unit.
+pub fn parse_speed(s: &str, unit: SpeedUnit) -> Result<f64, String> {
+    let s_trimmed = s.trim();
+    let s_lower = s_trimmed.to_lowercase();
+
+    let (number, factor) = if let Some(number) = s_lower.strip_suffix("km/h") {
+        (number, if unit == SpeedUnit::MPerS { 1.0 / 3.6 } else { 1.0 })
+    } else if let Some(number) = s_lower.strip_suffix("m/s") {
+        (number, if unit == SpeedUnit::KmPerH { 3.6 } else { 1.0 })
+    } else {
+        (s_lower.as_str(), 1.0)
+    };
+
+    number
+        .trim()
+        .parse::<f64>()
+        .map(|x| x * factor)
+        .map_err(|_| format!("invalid speed '{}' (expected e.g. 80 km/h or 22.2 m/s)", s_trimmed))
+}
+
+/// parse_time parses a time in seconds given as a number with an optional suffix "s" (e.g.
+/// "81.5 s") or as minutes and seconds from a timing sheet (e.g. "1:21.500").
+pub fn parse_time(s: &str) -> Result<f64, String> {
+    let s_trimmed = s.trim();
+    let err = || format!("invalid time '{}' (expected e.g. 1:21.500 or 81.5 s)", s_trimmed);
+
+    if let Some((minutes, seconds)) = s_trimmed.split_once(':') {
+        let minutes = minutes.trim().parse::<u32>().map_err(|_| err())?;
+        let seconds = seconds.trim().parse::<f64>().map_err(|_| err())?;
+
+        if !(0.0..60.0).contains(&seconds) {
+            return Err(err());
+        }
+
+        Ok(minutes as f64 * 60.0 + seconds)
+    } else {
+        s_trimmed
+            .strip_suffix('s')
+            .unwrap_or(s_trimmed)
+            .trim()
+            .parse::<f64>()
+            .map_err(|_| err())
+    }
+}
+
+/// SpeedMPerS is a speed parameter in m/s that can be given as number or as string with a unit.
+/// `unitless` is true if the value was given without a unit suffix, such that an implausible value
+/// can be reported as a probable unit mix-up. It is written as plain number in m/s.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpeedMPerS {
+    pub m_s: f64,
+    pub unitless: bool,
+}
+
+impl Serialize for SpeedMPerS {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_f64(self.m_s)
+    }
+}
+
+impl<'de> Deserialize<'de> for SpeedMPerS {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match NumberOrString::deserialize(deserializer)? {
+            NumberOrString::Number(x) => Ok(SpeedMPerS {
+                m_s: x,
+                unitless: true,
+            }),
+            NumberOrString::String(s) => {
+                let s_lower = s.trim().to_lowercase();
+                Ok(SpeedMPerS {
+                    m_s: parse_speed(&s, SpeedUnit::MPerS).map_err(serde::de::Error::custom)?,
+                    unitless: !s_lower.ends_with("km/h") && !s_lower.ends_with("m/s"),
+                })
+            }
+        }
+    }
+}
+
+/// deserialize_speed_km_h deserializes a speed in km/h given as number or as string with a unit.
+pub fn deserialize_speed_km_h<'de, D>(deserializer: D) -> Result<f64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match NumberOrString::deserialize(deserializer)? {
+        NumberOrString::Number(x) => Ok(x),
+        NumberOrString::String(s) => {
+            parse_speed(&s, SpeedUnit::KmPerH).map_err(serde::de::Error::custom)
+        }
+    }
+}
+
+/// deserialize_time_s deserializes a time in s given as number or as string (see `parse_time`).
+pub fn deserialize_time_s<'de, D>(deserializer: D) -> Result<f64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match NumberOrString::deserialize(deserializer)? {
+        NumberOrString::Number(x) => Ok(x),
+        NumberOrString::String(s) => parse_time(&s).map_err(serde::de::Error::custom),
+    }
+}